@@ -0,0 +1,5 @@
+-- Archived projects are read-only and left out of default listings, but stay
+-- available for reporting.
+ALTER TABLE projects ADD COLUMN archived_at TIMESTAMPTZ;
+
+CREATE INDEX idx_projects_archived_at ON projects(archived_at) WHERE archived_at IS NOT NULL;
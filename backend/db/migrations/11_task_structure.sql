@@ -0,0 +1,24 @@
+-- Plan structure imported from scheduling tools: a WBS hierarchy, milestones,
+-- and the type and lag of each dependency. `tasks.dependencies` still lists a
+-- task's predecessors; a predecessor without a task_links row is a plain
+-- finish-to-start link with no lag.
+ALTER TABLE tasks ADD COLUMN parent_id UUID REFERENCES tasks(id) ON DELETE SET NULL;
+ALTER TABLE tasks ADD COLUMN wbs VARCHAR(64);
+ALTER TABLE tasks ADD COLUMN milestone BOOLEAN NOT NULL DEFAULT FALSE;
+
+CREATE INDEX idx_tasks_parent_id ON tasks(parent_id) WHERE parent_id IS NOT NULL;
+
+CREATE TYPE dependency_type AS ENUM (
+    'finish_to_start',
+    'start_to_start',
+    'finish_to_finish',
+    'start_to_finish'
+);
+
+CREATE TABLE task_links (
+    task_id UUID NOT NULL REFERENCES tasks(id) ON DELETE CASCADE,
+    predecessor_id UUID NOT NULL REFERENCES tasks(id) ON DELETE CASCADE,
+    link_type dependency_type NOT NULL DEFAULT 'finish_to_start',
+    lag_minutes INTEGER NOT NULL DEFAULT 0,
+    PRIMARY KEY (task_id, predecessor_id)
+);
@@ -0,0 +1,31 @@
+-- Gate reviews scheduled ahead of a phase transition
+CREATE TABLE gate_reviews (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    project_id UUID NOT NULL REFERENCES projects(id) ON DELETE CASCADE,
+    -- The phase the review decides whether to enter
+    phase lifecycle_phase NOT NULL,
+    scheduled_at TIMESTAMPTZ NOT NULL,
+    duration_minutes INTEGER NOT NULL DEFAULT 60 CHECK (duration_minutes > 0),
+    location VARCHAR(255),
+    notes TEXT,
+    created_by UUID REFERENCES users(id),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    version INTEGER NOT NULL DEFAULT 1
+);
+
+CREATE INDEX idx_gate_reviews_project ON gate_reviews(project_id, scheduled_at);
+
+-- iCalendar subscriptions. Calendar clients cannot send credentials, so the
+-- feed URL carries a random token; only its hash is stored.
+CREATE TABLE calendar_feeds (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    -- NULL for the user's own assignments across projects
+    project_id UUID REFERENCES projects(id) ON DELETE CASCADE,
+    token_hash TEXT NOT NULL UNIQUE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    revoked_at TIMESTAMPTZ
+);
+
+CREATE INDEX idx_calendar_feeds_user ON calendar_feeds(user_id) WHERE revoked_at IS NULL;
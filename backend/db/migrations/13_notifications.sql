@@ -0,0 +1,42 @@
+CREATE TYPE notification_event AS ENUM (
+    'task_assigned',
+    'task_overdue',
+    'predecessor_slipped',
+    'gate_approval_requested',
+    'phase_transitioned',
+    'budget_threshold_crossed'
+);
+
+CREATE TABLE notifications (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    event notification_event NOT NULL,
+    title VARCHAR(255) NOT NULL,
+    body TEXT NOT NULL,
+    project_id UUID REFERENCES projects(id) ON DELETE CASCADE,
+    task_id UUID REFERENCES tasks(id) ON DELETE CASCADE,
+    -- Conditions that stay true (an overdue task, a crossed budget threshold)
+    -- are raised on every check; the key makes them notify once
+    dedupe_key TEXT,
+    -- Channels the recipient wanted when the notification was raised
+    in_app BOOLEAN NOT NULL,
+    email BOOLEAN NOT NULL,
+    read_at TIMESTAMPTZ,
+    emailed_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE (user_id, dedupe_key)
+);
+
+CREATE INDEX idx_notifications_inbox ON notifications(user_id, created_at DESC) WHERE in_app;
+CREATE INDEX idx_notifications_unread ON notifications(user_id) WHERE in_app AND read_at IS NULL;
+CREATE INDEX idx_notifications_email_pending ON notifications(created_at)
+    WHERE email AND emailed_at IS NULL;
+
+-- Missing rows mean the defaults: in-app on, email off
+CREATE TABLE notification_preferences (
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    event notification_event NOT NULL,
+    in_app BOOLEAN NOT NULL,
+    email BOOLEAN NOT NULL,
+    PRIMARY KEY (user_id, event)
+);
@@ -0,0 +1,66 @@
+CREATE TYPE job_kind AS ENUM (
+    'overdue_tasks',
+    'notification_emails',
+    'daily_digest',
+    'trash_purge',
+    'schedule_metrics'
+);
+
+CREATE TYPE job_status AS ENUM (
+    'pending',
+    'running',
+    'succeeded',
+    'failed'
+);
+
+-- Work for the in-process runner. Replicas claim due rows with
+-- FOR UPDATE SKIP LOCKED, so each job runs on one of them.
+CREATE TABLE jobs (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    kind job_kind NOT NULL,
+    payload JSONB NOT NULL DEFAULT '{}',
+    status job_status NOT NULL DEFAULT 'pending',
+    -- Schedule that enqueued the job; NULL when enqueued by hand
+    schedule VARCHAR(64),
+    attempts INTEGER NOT NULL DEFAULT 0,
+    max_attempts INTEGER NOT NULL DEFAULT 5 CHECK (max_attempts > 0),
+    -- When the job is due; pushed back after a failed attempt
+    run_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    last_error TEXT,
+    locked_by VARCHAR(64),
+    started_at TIMESTAMPTZ,
+    finished_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX idx_jobs_due ON jobs(run_at) WHERE status = 'pending';
+CREATE INDEX idx_jobs_created_at ON jobs(created_at);
+
+-- Cron schedules; whichever replica locks a due row first enqueues its job
+-- and moves next_run_at on
+CREATE TABLE job_schedules (
+    name VARCHAR(64) PRIMARY KEY,
+    kind job_kind NOT NULL,
+    cron VARCHAR(100) NOT NULL,
+    enabled BOOLEAN NOT NULL DEFAULT TRUE,
+    next_run_at TIMESTAMPTZ NOT NULL,
+    last_enqueued_at TIMESTAMPTZ,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+-- Progress against plan, recomputed by the schedule_metrics job
+CREATE TABLE project_schedule_metrics (
+    project_id UUID PRIMARY KEY REFERENCES projects(id) ON DELETE CASCADE,
+    task_count INTEGER NOT NULL,
+    completed_count INTEGER NOT NULL,
+    overdue_count INTEGER NOT NULL,
+    -- Task progress weighted by task duration
+    percent_complete NUMERIC(5, 2) NOT NULL,
+    -- Share of task duration that has elapsed, i.e. where progress should be
+    percent_planned NUMERIC(5, 2) NOT NULL,
+    computed_at TIMESTAMPTZ NOT NULL
+);
+
+-- Unread notifications already summarized in a digest email
+ALTER TABLE notifications ADD COLUMN digested_at TIMESTAMPTZ;
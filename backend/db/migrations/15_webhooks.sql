@@ -0,0 +1,60 @@
+CREATE TYPE webhook_event AS ENUM (
+    'project.created',
+    'project.updated',
+    'project.deleted',
+    'project.restored',
+    'project.phase_changed',
+    'task.created',
+    'task.updated',
+    'task.deleted',
+    'task.restored',
+    'task.overdue'
+);
+
+CREATE TYPE webhook_delivery_status AS ENUM (
+    'pending',
+    'succeeded',
+    'failed'
+);
+
+CREATE TABLE webhooks (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    url TEXT NOT NULL,
+    -- Kept in the clear: every delivery is signed with it
+    secret TEXT NOT NULL,
+    events webhook_event[] NOT NULL CHECK (cardinality(events) > 0),
+    description TEXT,
+    active BOOLEAN NOT NULL DEFAULT TRUE,
+    created_by UUID REFERENCES users(id) ON DELETE SET NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+-- Outbox and delivery log in one: rows are written in the transaction that
+-- raised the event and sent by the webhook_deliveries job.
+CREATE TABLE webhook_deliveries (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    webhook_id UUID NOT NULL REFERENCES webhooks(id) ON DELETE CASCADE,
+    event webhook_event NOT NULL,
+    payload JSONB NOT NULL,
+    status webhook_delivery_status NOT NULL DEFAULT 'pending',
+    attempts INTEGER NOT NULL DEFAULT 0,
+    -- When the next attempt is due; pushed back after a failed one
+    next_attempt_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    response_status INTEGER,
+    response_body TEXT,
+    last_error TEXT,
+    delivered_at TIMESTAMPTZ,
+    -- Delivery this one repeats, when sent again by hand
+    redelivery_of UUID REFERENCES webhook_deliveries(id) ON DELETE SET NULL,
+    -- Conditions raised on every check (an overdue task) are sent once
+    dedupe_key TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE (webhook_id, dedupe_key)
+);
+
+CREATE INDEX idx_webhook_deliveries_due ON webhook_deliveries(next_attempt_at)
+    WHERE status = 'pending';
+CREATE INDEX idx_webhook_deliveries_webhook ON webhook_deliveries(webhook_id, created_at DESC);
+
+ALTER TYPE job_kind ADD VALUE 'webhook_deliveries';
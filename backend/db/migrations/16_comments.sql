@@ -0,0 +1,43 @@
+CREATE TYPE comment_target AS ENUM (
+    'project',
+    'task',
+    'phase_transition',
+    'gate_review'
+);
+
+ALTER TYPE notification_event ADD VALUE 'mentioned';
+
+-- Markdown discussion attached to a project or one of its records
+CREATE TABLE comments (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    target_type comment_target NOT NULL,
+    target_id UUID NOT NULL,
+    -- Project the target belongs to, for visibility checks and cleanup
+    project_id UUID NOT NULL REFERENCES projects(id) ON DELETE CASCADE,
+    -- Comment this one replies to, on the same target
+    parent_id UUID REFERENCES comments(id) ON DELETE CASCADE,
+    author_id UUID NOT NULL REFERENCES users(id),
+    body TEXT NOT NULL,
+    -- Users resolved from @mentions in the current body
+    mentions UUID[] NOT NULL DEFAULT '{}',
+    edited_at TIMESTAMPTZ,
+    deleted_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    version INTEGER NOT NULL DEFAULT 1
+);
+
+CREATE INDEX idx_comments_target ON comments(target_type, target_id, created_at);
+CREATE INDEX idx_comments_parent ON comments(parent_id);
+CREATE INDEX idx_comments_project ON comments(project_id);
+
+-- Bodies replaced by edits, one row per edit
+CREATE TABLE comment_revisions (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    comment_id UUID NOT NULL REFERENCES comments(id) ON DELETE CASCADE,
+    body TEXT NOT NULL,
+    edited_by UUID REFERENCES users(id),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX idx_comment_revisions_comment ON comment_revisions(comment_id, created_at);
@@ -0,0 +1,74 @@
+-- Records of a project a document can be attached to; the project itself
+-- is implied by the document
+CREATE TYPE document_target AS ENUM (
+    'task',
+    'deliverable',
+    'phase_transition',
+    'gate_review'
+);
+
+ALTER TYPE job_kind ADD VALUE 'document_cleanup';
+
+-- A file kept with a project. The content lives in document storage, one
+-- object per version.
+CREATE TABLE documents (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    project_id UUID NOT NULL REFERENCES projects(id) ON DELETE CASCADE,
+    title VARCHAR(255) NOT NULL,
+    description TEXT,
+    -- Number of the latest entry in document_versions
+    current_version INTEGER NOT NULL DEFAULT 1,
+    created_by UUID REFERENCES users(id),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    version INTEGER NOT NULL DEFAULT 1
+);
+
+CREATE INDEX idx_documents_project ON documents(project_id, title);
+
+CREATE TABLE document_versions (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    document_id UUID NOT NULL REFERENCES documents(id) ON DELETE CASCADE,
+    number INTEGER NOT NULL CHECK (number > 0),
+    filename VARCHAR(255) NOT NULL,
+    content_type VARCHAR(255) NOT NULL,
+    size_bytes BIGINT NOT NULL CHECK (size_bytes > 0),
+    -- Hex SHA-256 of the content, checked again on download
+    checksum CHAR(64) NOT NULL,
+    storage_key VARCHAR(512) NOT NULL UNIQUE,
+    uploaded_by UUID REFERENCES users(id),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE (document_id, number)
+);
+
+CREATE TABLE document_links (
+    document_id UUID NOT NULL REFERENCES documents(id) ON DELETE CASCADE,
+    target_type document_target NOT NULL,
+    target_id UUID NOT NULL,
+    created_by UUID REFERENCES users(id),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (document_id, target_type, target_id)
+);
+
+CREATE INDEX idx_document_links_target ON document_links(target_type, target_id);
+
+-- Stored objects whose version rows are gone, however they went (document
+-- deleted, project purged). The document_cleanup job removes them from
+-- storage.
+CREATE TABLE document_blob_deletions (
+    storage_key VARCHAR(512) PRIMARY KEY,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE OR REPLACE FUNCTION queue_document_blob_deletion() RETURNS TRIGGER AS $$
+BEGIN
+    INSERT INTO document_blob_deletions (storage_key)
+    VALUES (OLD.storage_key)
+    ON CONFLICT DO NOTHING;
+    RETURN OLD;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER document_versions_queue_blob_deletion
+AFTER DELETE ON document_versions
+FOR EACH ROW EXECUTE FUNCTION queue_document_blob_deletion();
@@ -0,0 +1,45 @@
+-- Documents the application can produce from a project's records
+CREATE TYPE document_kind AS ENUM (
+    'requirements_specification',
+    'phase_report',
+    'closure_report'
+);
+
+CREATE TYPE template_format AS ENUM (
+    'markdown',
+    'html'
+);
+
+CREATE TYPE output_format AS ENUM (
+    'markdown',
+    'html',
+    'pdf'
+);
+
+ALTER TYPE job_kind ADD VALUE 'document_generation';
+
+-- The organisation's own templates. A kind without an active template is
+-- rendered from the built-in one.
+CREATE TABLE document_templates (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    kind document_kind NOT NULL,
+    name VARCHAR(255) NOT NULL,
+    format template_format NOT NULL,
+    body TEXT NOT NULL,
+    active BOOLEAN NOT NULL DEFAULT TRUE,
+    created_by UUID REFERENCES users(id),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    version INTEGER NOT NULL DEFAULT 1
+);
+
+CREATE UNIQUE INDEX idx_document_templates_active ON document_templates(kind) WHERE active;
+
+-- The document each generation of a kind and format adds a version to
+CREATE TABLE generated_documents (
+    project_id UUID NOT NULL REFERENCES projects(id) ON DELETE CASCADE,
+    kind document_kind NOT NULL,
+    format output_format NOT NULL,
+    document_id UUID NOT NULL UNIQUE REFERENCES documents(id) ON DELETE CASCADE,
+    PRIMARY KEY (project_id, kind, format)
+);
@@ -0,0 +1,95 @@
+CREATE TYPE requirement_type AS ENUM (
+    'functional',
+    'non_functional'
+);
+
+CREATE TYPE requirement_priority AS ENUM (
+    'low',
+    'medium',
+    'high',
+    'critical'
+);
+
+CREATE TYPE requirement_status AS ENUM (
+    'draft',
+    'approved',
+    'implemented',
+    'verified',
+    'rejected'
+);
+
+-- Numbers of deleted requirements are not given out again
+ALTER TABLE projects ADD COLUMN last_requirement_number INTEGER NOT NULL DEFAULT 0;
+
+-- What a project has to deliver, numbered per project as REQ-001, REQ-002…
+CREATE TABLE requirements (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    project_id UUID NOT NULL REFERENCES projects(id) ON DELETE CASCADE,
+    number INTEGER NOT NULL CHECK (number > 0),
+    code VARCHAR(16) GENERATED ALWAYS AS (
+        'REQ-' || CASE WHEN number < 1000 THEN lpad(number::text, 3, '0') ELSE number::text END
+    ) STORED,
+    title VARCHAR(255) NOT NULL,
+    description TEXT,
+    type requirement_type NOT NULL,
+    priority requirement_priority NOT NULL DEFAULT 'medium',
+    status requirement_status NOT NULL DEFAULT 'draft',
+    acceptance_criteria TEXT,
+    created_by UUID REFERENCES users(id),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    version INTEGER NOT NULL DEFAULT 1,
+    UNIQUE (project_id, number)
+);
+
+-- Every version of a requirement, the current one included
+CREATE TABLE requirement_revisions (
+    requirement_id UUID NOT NULL REFERENCES requirements(id) ON DELETE CASCADE,
+    version INTEGER NOT NULL,
+    title VARCHAR(255) NOT NULL,
+    description TEXT,
+    type requirement_type NOT NULL,
+    priority requirement_priority NOT NULL,
+    status requirement_status NOT NULL,
+    acceptance_criteria TEXT,
+    changed_by UUID REFERENCES users(id),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (requirement_id, version)
+);
+
+-- Part of the design that realises one or more requirements
+CREATE TABLE design_items (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    project_id UUID NOT NULL REFERENCES projects(id) ON DELETE CASCADE,
+    title VARCHAR(255) NOT NULL,
+    description TEXT,
+    created_by UUID REFERENCES users(id),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    version INTEGER NOT NULL DEFAULT 1
+);
+
+CREATE INDEX idx_design_items_project ON design_items(project_id, title);
+
+CREATE TYPE trace_entity AS ENUM (
+    'requirement',
+    'design_item',
+    'task'
+);
+
+-- Traceability from a requirement down to the work that implements it:
+-- requirement → design item → task, or requirement → task directly
+CREATE TABLE trace_links (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    project_id UUID NOT NULL REFERENCES projects(id) ON DELETE CASCADE,
+    source_type trace_entity NOT NULL,
+    source_id UUID NOT NULL,
+    target_type trace_entity NOT NULL,
+    target_id UUID NOT NULL,
+    created_by UUID REFERENCES users(id),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE (source_type, source_id, target_type, target_id)
+);
+
+CREATE INDEX idx_trace_links_project ON trace_links(project_id);
+CREATE INDEX idx_trace_links_target ON trace_links(target_type, target_id);
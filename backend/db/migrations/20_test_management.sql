@@ -0,0 +1,88 @@
+CREATE TYPE test_level AS ENUM (
+    'unit',
+    'integration',
+    'system',
+    'acceptance'
+);
+
+CREATE TYPE test_outcome AS ENUM (
+    'passed',
+    'failed',
+    'blocked'
+);
+
+ALTER TYPE trace_entity ADD VALUE 'test_case';
+
+-- Lowest pass rate of the project's test cases, in percent, with which the
+-- review lets the project enter its phase
+
+ALTER TABLE gate_reviews ADD COLUMN min_pass_rate INTEGER
+    CHECK (min_pass_rate BETWEEN 0 AND 100);
+
+CREATE TABLE test_suites (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    project_id UUID NOT NULL REFERENCES projects(id) ON DELETE CASCADE,
+    name VARCHAR(255) NOT NULL,
+    description TEXT,
+    level test_level NOT NULL,
+    created_by UUID REFERENCES users(id),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    version INTEGER NOT NULL DEFAULT 1
+);
+
+CREATE INDEX idx_test_suites_project ON test_suites(project_id, name);
+
+CREATE TABLE test_cases (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    suite_id UUID NOT NULL REFERENCES test_suites(id) ON DELETE CASCADE,
+    -- The suite's project, for visibility checks and reports
+    project_id UUID NOT NULL REFERENCES projects(id) ON DELETE CASCADE,
+    title VARCHAR(255) NOT NULL,
+    description TEXT,
+    preconditions TEXT,
+    -- Outcome of the case as a whole; each step has its own
+    expected_result TEXT,
+    created_by UUID REFERENCES users(id),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    version INTEGER NOT NULL DEFAULT 1
+);
+
+CREATE INDEX idx_test_cases_suite ON test_cases(suite_id, title);
+CREATE INDEX idx_test_cases_project ON test_cases(project_id);
+
+CREATE TABLE test_steps (
+    test_case_id UUID NOT NULL REFERENCES test_cases(id) ON DELETE CASCADE,
+    position INTEGER NOT NULL CHECK (position > 0),
+    action TEXT NOT NULL,
+    expected_result TEXT NOT NULL,
+    PRIMARY KEY (test_case_id, position)
+);
+
+-- One execution of test cases against a build, e.g. "2.3.0-rc1", within a
+-- test cycle
+CREATE TABLE test_runs (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    project_id UUID NOT NULL REFERENCES projects(id) ON DELETE CASCADE,
+    build VARCHAR(255) NOT NULL,
+    cycle VARCHAR(255),
+    notes TEXT,
+    created_by UUID REFERENCES users(id),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX idx_test_runs_project ON test_runs(project_id, created_at);
+
+-- Outcome of a test case in a run. Recording it again replaces it.
+CREATE TABLE test_results (
+    run_id UUID NOT NULL REFERENCES test_runs(id) ON DELETE CASCADE,
+    test_case_id UUID NOT NULL REFERENCES test_cases(id) ON DELETE CASCADE,
+    outcome test_outcome NOT NULL,
+    notes TEXT,
+    tester_id UUID REFERENCES users(id),
+    executed_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (run_id, test_case_id)
+);
+
+CREATE INDEX idx_test_results_case ON test_results(test_case_id, executed_at);
@@ -0,0 +1,59 @@
+CREATE TYPE defect_severity AS ENUM (
+    'low',
+    'medium',
+    'high',
+    'critical'
+);
+
+CREATE TYPE defect_priority AS ENUM (
+    'low',
+    'medium',
+    'high',
+    'urgent'
+);
+
+CREATE TYPE defect_status AS ENUM (
+    'new',
+    'triaged',
+    'fixing',
+    'verified',
+    'closed'
+);
+
+-- Numbers of deleted defects are not given out again
+ALTER TABLE projects ADD COLUMN last_defect_number INTEGER NOT NULL DEFAULT 0;
+
+-- Whether the review lets the project enter its phase only while no
+-- critical defect is open
+ALTER TABLE gate_reviews ADD COLUMN no_open_critical_defects BOOLEAN NOT NULL DEFAULT FALSE;
+
+-- A fault found in the product, numbered per project as DEF-001, DEF-002…
+CREATE TABLE defects (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    project_id UUID NOT NULL REFERENCES projects(id) ON DELETE CASCADE,
+    number INTEGER NOT NULL CHECK (number > 0),
+    code VARCHAR(16) GENERATED ALWAYS AS (
+        'DEF-' || CASE WHEN number < 1000 THEN lpad(number::text, 3, '0') ELSE number::text END
+    ) STORED,
+    title VARCHAR(255) NOT NULL,
+    description TEXT,
+    severity defect_severity NOT NULL,
+    priority defect_priority NOT NULL DEFAULT 'medium',
+    status defect_status NOT NULL DEFAULT 'new',
+    assignee_id UUID REFERENCES users(id),
+    -- The failing test case, and the run it failed in
+    test_case_id UUID REFERENCES test_cases(id) ON DELETE SET NULL,
+    test_run_id UUID REFERENCES test_runs(id) ON DELETE SET NULL,
+    requirement_id UUID REFERENCES requirements(id) ON DELETE SET NULL,
+    -- The task that fixes the defect
+    fix_task_id UUID REFERENCES tasks(id) ON DELETE SET NULL,
+    reported_by UUID REFERENCES users(id),
+    closed_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    version INTEGER NOT NULL DEFAULT 1,
+    UNIQUE (project_id, number)
+);
+
+CREATE INDEX idx_defects_project_status ON defects(project_id, status, severity);
+CREATE INDEX idx_defects_assignee ON defects(assignee_id) WHERE assignee_id IS NOT NULL;
@@ -0,0 +1,7 @@
+-- Search snippets are HTML: text users wrote is escaped before matches
+-- are wrapped in <mark> tags
+CREATE FUNCTION html_escape(input TEXT) RETURNS TEXT
+LANGUAGE SQL IMMUTABLE STRICT PARALLEL SAFE AS $$
+    SELECT replace(replace(replace(replace(replace(input,
+        '&', '&amp;'), '<', '&lt;'), '>', '&gt;'), '"', '&quot;'), '''', '&#39;')
+$$;
@@ -0,0 +1,22 @@
+-- Tasks are assigned to resources. A resource is a user's when they share an
+-- email, which is how assignments reach users for visibility, calendar feeds
+-- and notifications.
+CREATE VIEW resource_users AS
+    SELECT r.id AS resource_id, u.id AS user_id
+    FROM resources r
+    JOIN users u ON lower(u.email) = lower(r.email);
+
+-- The one place the project visibility rule lives: admins and project
+-- managers (sees_all) see every project, others the projects they own or
+-- have tasks assigned in. Callers still exclude projects in the trash.
+CREATE FUNCTION project_visible_to(for_project UUID, for_user UUID, sees_all BOOLEAN)
+RETURNS BOOLEAN
+LANGUAGE SQL STABLE PARALLEL SAFE AS $$
+    SELECT sees_all
+        OR EXISTS (SELECT 1 FROM projects p WHERE p.id = for_project AND p.owner_id = for_user)
+        OR EXISTS (
+            SELECT 1 FROM tasks t
+            JOIN resource_users ru ON ru.resource_id = ANY(t.assigned_to)
+            WHERE t.project_id = for_project AND t.deleted_at IS NULL
+              AND ru.user_id = for_user)
+$$;
@@ -0,0 +1,2 @@
+-- Invitation emails are sent by the job runner, so a failed send is retried
+ALTER TYPE job_kind ADD VALUE 'invitation_email';
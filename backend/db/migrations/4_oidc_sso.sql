@@ -0,0 +1,28 @@
+-- Users provisioned through single sign-on have no local password
+ALTER TABLE users
+ALTER COLUMN password_hash DROP NOT NULL;
+
+ALTER TABLE users
+ADD COLUMN oidc_issuer TEXT,
+ADD COLUMN oidc_subject TEXT;
+
+CREATE UNIQUE INDEX idx_users_oidc_identity ON users(oidc_issuer, oidc_subject)
+WHERE oidc_issuer IS NOT NULL;
+
+-- Pending authorization-code requests (state -> PKCE verifier and nonce)
+CREATE TABLE oidc_login_states (
+    state TEXT PRIMARY KEY,
+    code_verifier TEXT NOT NULL,
+    nonce TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+-- Single-row table holding instance-wide authentication settings
+CREATE TABLE auth_settings (
+    id BOOLEAN PRIMARY KEY DEFAULT TRUE CHECK (id),
+    password_login_enabled BOOLEAN NOT NULL DEFAULT TRUE,
+    updated_by UUID REFERENCES users(id),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+INSERT INTO auth_settings (id) VALUES (TRUE);
@@ -0,0 +1,20 @@
+-- Deactivated users keep their history but can no longer sign in
+ALTER TABLE users
+ADD COLUMN deactivated_at TIMESTAMPTZ;
+
+CREATE TABLE user_invitations (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    email VARCHAR(255) NOT NULL,
+    full_name VARCHAR(255) NOT NULL,
+    role user_role NOT NULL,
+    token_hash TEXT NOT NULL UNIQUE,
+    invited_by UUID NOT NULL REFERENCES users(id),
+    expires_at TIMESTAMPTZ NOT NULL,
+    accepted_at TIMESTAMPTZ,
+    revoked_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+-- At most one open invitation per address
+CREATE UNIQUE INDEX idx_user_invitations_pending_email ON user_invitations(email)
+WHERE accepted_at IS NULL AND revoked_at IS NULL;
@@ -0,0 +1,24 @@
+-- Weighted search documents: names rank above descriptions
+ALTER TABLE projects
+ADD COLUMN search_vector tsvector GENERATED ALWAYS AS (
+    setweight(to_tsvector('english', coalesce(name, '')), 'A') ||
+    setweight(to_tsvector('english', coalesce(description, '')), 'B')
+) STORED;
+
+ALTER TABLE tasks
+ADD COLUMN search_vector tsvector GENERATED ALWAYS AS (
+    setweight(to_tsvector('english', coalesce(name, '')), 'A') ||
+    setweight(to_tsvector('english', coalesce(description, '')), 'B')
+) STORED;
+
+ALTER TABLE phase_transitions
+ADD COLUMN search_vector tsvector GENERATED ALWAYS AS (
+    to_tsvector('english', coalesce(description, ''))
+) STORED;
+
+CREATE INDEX idx_projects_search ON projects USING GIN (search_vector);
+CREATE INDEX idx_tasks_search ON tasks USING GIN (search_vector);
+CREATE INDEX idx_phase_transitions_search ON phase_transitions USING GIN (search_vector);
+
+-- Supports the assignee visibility check
+CREATE INDEX idx_tasks_assigned_to ON tasks USING GIN (assigned_to);
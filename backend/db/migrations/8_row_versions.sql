@@ -0,0 +1,5 @@
+-- Optimistic concurrency control: every update bumps the row's version,
+-- which the API exposes as its ETag and checks against If-Match.
+ALTER TABLE projects ADD COLUMN version INTEGER NOT NULL DEFAULT 1;
+ALTER TABLE tasks ADD COLUMN version INTEGER NOT NULL DEFAULT 1;
+ALTER TABLE users ADD COLUMN version INTEGER NOT NULL DEFAULT 1;
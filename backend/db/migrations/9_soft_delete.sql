@@ -0,0 +1,11 @@
+-- Soft delete: rows stay in place with a deleted_at timestamp until the
+-- purge job removes them. Tasks trashed along with their project share the
+-- project's deleted_at, which is how a restore finds them again.
+ALTER TABLE projects ADD COLUMN deleted_at TIMESTAMPTZ;
+ALTER TABLE tasks ADD COLUMN deleted_at TIMESTAMPTZ;
+
+CREATE INDEX idx_projects_deleted_at ON projects(deleted_at) WHERE deleted_at IS NOT NULL;
+CREATE INDEX idx_tasks_deleted_at ON tasks(deleted_at) WHERE deleted_at IS NOT NULL;
+
+ALTER TYPE audit_action ADD VALUE 'restore';
+ALTER TYPE audit_action ADD VALUE 'purge';
@@ -1,36 +1,294 @@
-use crate::models::{project::*, user::*};
+use crate::errors::FieldError;
+use crate::models::{
+    audit::*, auth::*, calendar::*, comment::*, defect::*, document::*, export::*, import::*,
+    job::*, lifecycle::LifecyclePhase, notification::*, pagination::PageLinks, project::*,
+    realtime::*, requirement::*, resource::*, search::*, task::*, template::*, testing::*,
+    traceability::*, user::*, webhook::*,
+};
 use utoipa::OpenApi;
 use utoipa_swagger_ui::SwaggerUi;
 
 #[derive(OpenApi)]
 #[openapi(
     paths(
+        crate::routes::audit::list_audit_entries,
+        crate::routes::audit::get_entity_history,
+        crate::routes::auth::accept_invitation,
+        crate::routes::auth::oidc_login,
+        crate::routes::auth::oidc_callback,
+        crate::routes::auth::get_settings,
+        crate::routes::auth::update_settings,
+        crate::routes::auth::jwks,
+        crate::routes::calendar::create_feed,
+        crate::routes::calendar::list_feeds,
+        crate::routes::calendar::revoke_feed,
+        crate::routes::calendar::get_feed,
+        crate::routes::comments::create_comment,
+        crate::routes::comments::list_comments,
+        crate::routes::comments::get_comment,
+        crate::routes::comments::update_comment,
+        crate::routes::comments::delete_comment,
+        crate::routes::comments::list_revisions,
+        crate::routes::comments::get_activity,
+        crate::routes::defects::create_defect,
+        crate::routes::defects::list_defects,
+        crate::routes::defects::get_defect,
+        crate::routes::defects::update_defect,
+        crate::routes::defects::delete_defect,
+        crate::routes::documents::upload_document,
+        crate::routes::documents::generate_document,
+        crate::routes::documents::list_documents,
+        crate::routes::documents::get_document,
+        crate::routes::documents::update_document,
+        crate::routes::documents::delete_document,
+        crate::routes::documents::upload_version,
+        crate::routes::documents::list_versions,
+        crate::routes::documents::download_document,
+        crate::routes::documents::list_links,
+        crate::routes::documents::link_document,
+        crate::routes::documents::unlink_document,
+        crate::routes::events::stream_events,
+        crate::routes::jobs::list_jobs,
+        crate::routes::jobs::get_job,
+        crate::routes::jobs::retry_job,
+        crate::routes::jobs::list_schedules,
+        crate::routes::jobs::update_schedule,
+        crate::routes::jobs::run_schedule,
+        crate::routes::notifications::list_notifications,
+        crate::routes::notifications::get_unread_count,
+        crate::routes::notifications::mark_all_read,
+        crate::routes::notifications::mark_read,
+        crate::routes::notifications::get_preferences,
+        crate::routes::notifications::update_preferences,
         crate::routes::projects::create_project,
         crate::routes::projects::get_project,
         crate::routes::projects::update_project,
         crate::routes::projects::delete_project,
         crate::routes::projects::get_projects,
+        crate::routes::projects::get_project_trash,
+        crate::routes::projects::import_project,
+        crate::routes::projects::export_project,
+        crate::routes::projects::get_schedule_metrics,
+        crate::routes::projects::get_traceability,
+        crate::routes::projects::get_test_report,
+        crate::routes::projects::get_open_defects,
+        crate::routes::projects::restore_project,
+        crate::routes::projects::archive_project,
+        crate::routes::projects::unarchive_project,
+        crate::routes::requirements::create_requirement,
+        crate::routes::requirements::list_requirements,
+        crate::routes::requirements::get_requirement,
+        crate::routes::requirements::update_requirement,
+        crate::routes::requirements::delete_requirement,
+        crate::routes::requirements::list_revisions,
+        crate::routes::resources::get_resources,
+        crate::routes::resources::get_resource,
+        crate::routes::resources::create_resource,
+        crate::routes::resources::update_resource,
+        crate::routes::resources::delete_resource,
+        crate::routes::search::search,
+        crate::routes::tasks::get_tasks,
+        crate::routes::tasks::get_task_trash,
+        crate::routes::tasks::bulk_tasks,
+        crate::routes::tasks::get_project_task_links,
+        crate::routes::tasks::restore_task,
+        crate::routes::templates::create_template,
+        crate::routes::templates::list_templates,
+        crate::routes::templates::get_built_in_template,
+        crate::routes::templates::get_template,
+        crate::routes::templates::update_template,
+        crate::routes::templates::delete_template,
+        crate::routes::testing::create_suite,
+        crate::routes::testing::list_suites,
+        crate::routes::testing::get_suite,
+        crate::routes::testing::update_suite,
+        crate::routes::testing::delete_suite,
+        crate::routes::testing::create_case,
+        crate::routes::testing::list_cases,
+        crate::routes::testing::get_case,
+        crate::routes::testing::update_case,
+        crate::routes::testing::delete_case,
+        crate::routes::testing::create_run,
+        crate::routes::testing::list_runs,
+        crate::routes::testing::get_run,
+        crate::routes::testing::record_result,
+        crate::routes::traceability::create_design_item,
+        crate::routes::traceability::list_design_items,
+        crate::routes::traceability::get_design_item,
+        crate::routes::traceability::update_design_item,
+        crate::routes::traceability::delete_design_item,
+        crate::routes::traceability::create_trace_link,
+        crate::routes::traceability::list_trace_links,
+        crate::routes::traceability::delete_trace_link,
         crate::routes::users::create_user,
         crate::routes::users::get_user,
         crate::routes::users::update_user,
-        crate::routes::users::delete_user,
+        crate::routes::users::deactivate_user,
+        crate::routes::users::reactivate_user,
         crate::routes::users::list_users,
+        crate::routes::users::invite_user,
+        crate::routes::users::list_invitations,
+        crate::routes::users::revoke_invitation,
+        crate::routes::users::get_me,
+        crate::routes::users::update_me,
+        crate::routes::webhooks::create_webhook,
+        crate::routes::webhooks::list_webhooks,
+        crate::routes::webhooks::get_webhook,
+        crate::routes::webhooks::update_webhook,
+        crate::routes::webhooks::delete_webhook,
+        crate::routes::webhooks::list_deliveries,
+        crate::routes::webhooks::redeliver,
     ),
     components(
         schemas(
+            ActivityItem,
+            AuditAction,
+            AuditEntity,
+            AuditEntry,
+            AuthResponse,
+            AuthSettings,
+            AuthSettingsUpdate,
+            BuiltInTemplate,
+            BulkTaskOperation,
+            BulkTaskRequest,
+            BulkTaskResponse,
+            BulkTaskResult,
+            CalendarFeed,
+            CalendarFeedCreate,
+            CalendarFeedCreated,
+            ChangeEvent,
+            ChangeKind,
+            Comment,
+            CommentCreate,
+            CommentRevision,
+            CommentTarget,
+            CommentUpdate,
+            DeliveryStatus,
+            DependencyType,
+            DesignItem,
+            DesignItemCreate,
+            DesignItemUpdate,
+            Defect,
+            DefectCreate,
+            DefectPriority,
+            DefectSeverity,
+            DefectStatus,
+            DefectUpdate,
+            Document,
+            DocumentGenerate,
+            DocumentKind,
+            DocumentLink,
+            DocumentLinkCreate,
+            DocumentTarget,
+            DocumentTemplate,
+            DocumentTemplateCreate,
+            DocumentTemplateUpdate,
+            DocumentUpdate,
+            DocumentVersion,
+            ExportFormat,
+            FieldError,
+            ImportReport,
+            ImportWarning,
+            ImportedLink,
+            ImportedProject,
+            ImportedResource,
+            ImportedTask,
+            Invitation,
+            InvitationAccept,
+            InvitationCreate,
+            Job,
+            JobKind,
+            JobSchedule,
+            JobScheduleUpdate,
+            JobStatus,
+            LifecyclePhase,
+            Notification,
+            OpenDefectCounts,
+            NotificationEvent,
+            NotificationPreference,
+            OutputFormat,
+            PageLinks,
             Project,
             ProjectCreate,
             ProjectUpdate,
             ProjectStatus,
+            ProfileUpdate,
+            Requirement,
+            RequirementCreate,
+            RequirementPriority,
+            RequirementRevision,
+            RequirementStatus,
+            RequirementType,
+            RequirementUpdate,
+            Resource,
+            ResourceAction,
+            ResourceCreate,
+            ResourceUpdate,
+            ScheduleMetrics,
+            SearchHit,
+            SearchResults,
+            Task,
+            TaskCreate,
+            TaskLink,
+            TaskStatus,
+            TaskUpdate,
+            TemplateFormat,
+            RequirementCoverage,
+            TestCase,
+            TestCaseCreate,
+            TestCaseUpdate,
+            TestLevel,
+            TestOutcome,
+            TestReport,
+            TestResult,
+            TestResultRecord,
+            TestRun,
+            TestRunCreate,
+            TestRunDetails,
+            TestStep,
+            TestSuite,
+            TestSuiteCreate,
+            TestSuiteUpdate,
+            TestTally,
+            TraceEntity,
+            TraceLink,
+            TraceLinkCreate,
+            TraceRef,
+            TraceabilityMatrix,
+            TraceabilityRow,
+            UnreadCount,
             User,
             UserCreate,
             UserUpdate,
             UserRole,
+            Webhook,
+            WebhookCreate,
+            WebhookCreated,
+            WebhookDelivery,
+            WebhookEvent,
+            WebhookUpdate,
         )
     ),
     tags(
+        (name = "audit", description = "Audit log"),
+        (name = "auth", description = "Authentication endpoints"),
+        (name = "calendar", description = "iCalendar subscriptions"),
+        (name = "comments", description = "Threaded comments and activity timelines"),
+        (name = "defects", description = "Defects raised from testing and their workflow"),
+        (name = "documents", description = "Project documents and their versions"),
+        (name = "events", description = "Real-time change stream"),
+        (name = "jobs", description = "Background jobs and schedules"),
+        (name = "notifications", description = "In-app notifications and delivery preferences"),
         (name = "projects", description = "Project management endpoints"),
-        (name = "users", description = "User management endpoints")
+        (name = "requirements", description = "Requirements and their versions"),
+        (name = "resources", description = "Resource management endpoints"),
+        (name = "search", description = "Full-text search"),
+        (name = "tasks", description = "Task management endpoints"),
+        (name = "templates", description = "Templates for generated documents"),
+        (name = "testing", description = "Test suites, cases, runs and their results"),
+        (name = "traceability", description = "Design items and trace links from requirements to tasks and test cases"),
+        (name = "users", description = "User management endpoints"),
+        (name = "webhooks", description = "Outbound webhooks and their delivery log")
     )
 )]
 pub struct ApiDoc;
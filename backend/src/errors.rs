@@ -1,10 +1,15 @@
+use actix_web::http::StatusCode;
 use actix_web::{HttpResponse, ResponseError};
 use bcrypt::BcryptError;
-use jsonwebtoken::errors::Error as JwtError;
+use jsonwebtoken::errors::{Error as JwtError, ErrorKind as JwtErrorKind};
 use serde::Serialize;
 use sqlx::Error as SqlxError;
 use thiserror::Error;
-use validator::ValidationErrors;
+use utoipa::ToSchema;
+use validator::{ValidationErrors, ValidationErrorsKind};
+
+use crate::extractors::precondition::etag;
+use crate::middleware::request_id;
 
 #[derive(Error, Debug)]
 pub enum ServiceError {
@@ -29,65 +34,369 @@ pub enum ServiceError {
     #[error("Bad request: {0}")]
     BadRequest(String),
 
-    #[error("Validation error: {0}")]
-    ValidationError(String),
+    /// One or more request fields failed validation.
+    #[error("Validation error: {}", describe_fields(.0))]
+    ValidationError(Vec<FieldError>),
+
+    #[error("Unauthorized: {0}")]
+    Unauthorized(String),
+
+    #[error("Token expired")]
+    TokenExpired,
+
+    /// The request clashes with existing state, e.g. a duplicate email.
+    #[error("Conflict: {0}")]
+    Conflict(String),
+
+    /// The project, or the project a record belongs to, is archived.
+    #[error("Project is archived and cannot be modified")]
+    ProjectArchived,
+
+    /// The request is well-formed but breaks a business rule.
+    #[error("Unprocessable: {0}")]
+    UnprocessableEntity(String),
+
+    #[error("Identity provider error: {0}")]
+    IdentityProviderError(String),
+
+    /// Document content could not be written to or read from storage.
+    #[error("Document storage error: {0}")]
+    StorageError(String),
+
+    /// One operation of a bulk request failed and the whole batch was rolled
+    /// back. Field errors are reported under `prefix`, e.g. `operations[3].task`.
+    #[error("Operation {index} failed: {error}")]
+    BulkOperationFailed {
+        index: usize,
+        prefix: String,
+        error: Box<ServiceError>,
+    },
+
+    /// The record changed since the caller read it; `current` is its state now.
+    #[error("Precondition failed: the record has been modified (now at version {version})")]
+    PreconditionFailed {
+        version: i32,
+        current: serde_json::Value,
+    },
+}
+
+/// A validation failure tied to one request field, so clients can
+/// highlight it.
+#[derive(Debug, Clone, Serialize, ToSchema, PartialEq)]
+pub struct FieldError {
+    #[schema(example = "email")]
+    pub field: String,
+    /// Stable machine-readable reason, e.g. `length`, `email`, `required`
+    #[schema(example = "email")]
+    pub code: String,
+    #[schema(example = "must be a valid email address")]
+    pub message: String,
+}
+
+impl FieldError {
+    /// The same error for a field nested under `prefix`.
+    pub fn prefixed(mut self, prefix: &str) -> Self {
+        self.field = format!("{}.{}", prefix, self.field);
+        self
+    }
+}
+
+impl ServiceError {
+    /// A validation error for a single field.
+    pub fn invalid_field(field: &str, code: &str, message: impl Into<String>) -> Self {
+        ServiceError::ValidationError(vec![FieldError {
+            field: field.to_string(),
+            code: code.to_string(),
+            message: message.into(),
+        }])
+    }
+
+    /// Stable identifier clients can branch on; also the last segment of
+    /// the problem `type`.
+    pub fn code(&self) -> &'static str {
+        match self {
+            ServiceError::InternalServerError | ServiceError::PasswordHashError(_) => {
+                "internal_error"
+            }
+            ServiceError::Forbidden => "forbidden",
+            ServiceError::DatabaseError(err) => match database_violation(err) {
+                Some(Violation::Unique) => "conflict",
+                Some(Violation::ForeignKey) => "invalid_reference",
+                Some(Violation::Check) => "constraint_violation",
+                None => "database_error",
+            },
+            ServiceError::NotFound(_) => "not_found",
+            ServiceError::InvalidCredentials => "invalid_credentials",
+            ServiceError::BadRequest(_) => "bad_request",
+            ServiceError::ValidationError(_) => "validation_failed",
+            ServiceError::Unauthorized(_) => "unauthorized",
+            ServiceError::TokenExpired => "token_expired",
+            ServiceError::Conflict(_) => "conflict",
+            ServiceError::ProjectArchived => "project_archived",
+            ServiceError::UnprocessableEntity(_) => "unprocessable_entity",
+            ServiceError::IdentityProviderError(_) => "identity_provider_error",
+            ServiceError::StorageError(_) => "storage_error",
+            ServiceError::PreconditionFailed { .. } => "precondition_failed",
+            ServiceError::BulkOperationFailed { error, .. } => error.code(),
+        }
+    }
+
+    /// Human-readable explanation. Internal details (database and hashing
+    /// errors) are never exposed.
+    fn detail(&self) -> String {
+        match self {
+            ServiceError::DatabaseError(err) => match database_violation(err) {
+                Some(Violation::Unique) => "A record with these values already exists".into(),
+                Some(Violation::ForeignKey) => "A referenced record does not exist".into(),
+                Some(Violation::Check) => "The values violate a data constraint".into(),
+                None => "Database error occurred".into(),
+            },
+            ServiceError::PasswordHashError(_) => "Password processing error occurred".into(),
+            ServiceError::IdentityProviderError(_) => "Identity provider error occurred".into(),
+            ServiceError::StorageError(_) => "Document storage error occurred".into(),
+            ServiceError::NotFound(message)
+            | ServiceError::BadRequest(message)
+            | ServiceError::Unauthorized(message)
+            | ServiceError::Conflict(message)
+            | ServiceError::UnprocessableEntity(message) => message.clone(),
+            ServiceError::ValidationError(fields) => describe_fields(fields),
+            ServiceError::BulkOperationFailed { index, error, .. } => {
+                format!("Operation {}: {}", index, error.detail())
+            }
+            _ => self.to_string(),
+        }
+    }
+
+    pub(crate) fn field_errors(&self) -> Vec<FieldError> {
+        match self {
+            ServiceError::ValidationError(fields) => fields.clone(),
+            ServiceError::BulkOperationFailed { prefix, error, .. } => {
+                let fields = error.field_errors();
+                if fields.is_empty() {
+                    // Still point at the operation that failed
+                    return vec![FieldError {
+                        field: prefix.clone(),
+                        code: error.code().to_string(),
+                        message: error.detail(),
+                    }];
+                }
+                fields
+                    .into_iter()
+                    .map(|field| field.prefixed(prefix))
+                    .collect()
+            }
+            // Point the client at the duplicated column when Postgres names it
+            ServiceError::DatabaseError(SqlxError::Database(err)) if err.is_unique_violation() => {
+                unique_field(err.table(), err.constraint())
+                    .map(|field| {
+                        vec![FieldError {
+                            field,
+                            code: "unique".into(),
+                            message: "is already taken".into(),
+                        }]
+                    })
+                    .unwrap_or_default()
+            }
+            _ => vec![],
+        }
+    }
+}
+
+enum Violation {
+    Unique,
+    ForeignKey,
+    Check,
+}
+
+fn database_violation(err: &SqlxError) -> Option<Violation> {
+    match err {
+        SqlxError::Database(err) if err.is_unique_violation() => Some(Violation::Unique),
+        SqlxError::Database(err) if err.is_foreign_key_violation() => Some(Violation::ForeignKey),
+        SqlxError::Database(err) if err.is_check_violation() => Some(Violation::Check),
+        _ => None,
+    }
+}
+
+/// Column behind a unique constraint named by Postgres' default
+/// `<table>_<column>_key` convention.
+fn unique_field(table: Option<&str>, constraint: Option<&str>) -> Option<String> {
+    let column = constraint?
+        .strip_prefix(table?)?
+        .strip_prefix('_')?
+        .strip_suffix("_key")?;
+    Some(column.to_string())
+}
+
+fn describe_fields(fields: &[FieldError]) -> String {
+    fields
+        .iter()
+        .map(|error| format!("{}: {}", error.field, error.message))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn default_message(error: &validator::ValidationError) -> String {
+    let param = |name: &str| error.params.get(name).map(|value| value.to_string());
+    match error.code.as_ref() {
+        "email" => "must be a valid email address".into(),
+        "url" => "must be a valid URL".into(),
+        "length" => match (param("min"), param("max")) {
+            (Some(min), Some(max)) => format!("must be between {} and {} characters", min, max),
+            (Some(min), None) => format!("must be at least {} characters", min),
+            (None, Some(max)) => format!("must be at most {} characters", max),
+            (None, None) => "has an invalid length".into(),
+        },
+        "range" => match (param("min"), param("max")) {
+            (Some(min), Some(max)) => format!("must be between {} and {}", min, max),
+            (Some(min), None) => format!("must be at least {}", min),
+            (None, Some(max)) => format!("must be at most {}", max),
+            (None, None) => "is out of range".into(),
+        },
+        code => format!("is invalid ({})", code),
+    }
 }
 
 impl From<ValidationErrors> for ServiceError {
     fn from(err: ValidationErrors) -> Self {
-        ServiceError::ValidationError(err.to_string())
+        let mut fields = Vec::new();
+        collect_field_errors(&err, "", &mut fields);
+        fields.sort_by(|a, b| a.field.cmp(&b.field));
+        ServiceError::ValidationError(fields)
+    }
+}
+
+fn collect_field_errors(errors: &ValidationErrors, prefix: &str, out: &mut Vec<FieldError>) {
+    for (field, kind) in errors.errors() {
+        let field = if prefix.is_empty() {
+            field.to_string()
+        } else {
+            format!("{}.{}", prefix, field)
+        };
+        match kind {
+            ValidationErrorsKind::Field(errors) => out.extend(errors.iter().map(|error| {
+                FieldError {
+                    field: field.clone(),
+                    code: error.code.to_string(),
+                    message: error
+                        .message
+                        .as_ref()
+                        .map(|message| message.to_string())
+                        .unwrap_or_else(|| default_message(error)),
+                }
+            })),
+            ValidationErrorsKind::Struct(nested) => collect_field_errors(nested, &field, out),
+            ValidationErrorsKind::List(items) => {
+                for (index, nested) in items {
+                    collect_field_errors(nested, &format!("{}[{}]", field, index), out);
+                }
+            }
+        }
     }
 }
 
 impl From<JwtError> for ServiceError {
-    fn from(_: JwtError) -> Self {
-        ServiceError::InternalServerError
+    fn from(err: JwtError) -> Self {
+        match err.kind() {
+            JwtErrorKind::ExpiredSignature => ServiceError::TokenExpired,
+            JwtErrorKind::InvalidToken
+            | JwtErrorKind::InvalidSignature
+            | JwtErrorKind::InvalidAlgorithm
+            | JwtErrorKind::InvalidIssuer
+            | JwtErrorKind::InvalidAudience
+            | JwtErrorKind::InvalidSubject
+            | JwtErrorKind::ImmatureSignature
+            | JwtErrorKind::MissingRequiredClaim(_)
+            | JwtErrorKind::Base64(_)
+            | JwtErrorKind::Json(_)
+            | JwtErrorKind::Utf8(_) => ServiceError::Unauthorized("Invalid token".into()),
+            _ => {
+                log::error!("Token signing error: {:?}", err);
+                ServiceError::InternalServerError
+            }
+        }
     }
 }
 
+/// RFC 7807 problem details, extended with a stable `code`, the request ID
+/// and per-field validation errors.
 #[derive(Serialize)]
-struct ErrorResponse {
-    error: String,
+struct Problem<'a> {
+    #[serde(rename = "type")]
+    problem_type: String,
+    title: &'static str,
+    status: u16,
+    detail: String,
+    code: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    request_id: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    errors: Vec<FieldError>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    current: Option<&'a serde_json::Value>,
 }
 
+pub const PROBLEM_CONTENT_TYPE: &str = "application/problem+json";
+
 impl ResponseError for ServiceError {
-    fn error_response(&self) -> HttpResponse {
+    fn status_code(&self) -> StatusCode {
         match self {
-            ServiceError::InternalServerError => {
-                HttpResponse::InternalServerError().json(ErrorResponse {
-                    error: self.to_string(),
-                })
-            }
-
-            ServiceError::InvalidCredentials => HttpResponse::Unauthorized().json(ErrorResponse {
-                error: "Invalid credentials".to_string(),
-            }),
-            ServiceError::Forbidden => HttpResponse::Forbidden().json(ErrorResponse {
-                error: self.to_string(),
-            }),
-            ServiceError::DatabaseError(_) => {
-                HttpResponse::InternalServerError().json(ErrorResponse {
-                    error: "Database error occurred".to_string(),
-                })
-            }
-            ServiceError::PasswordHashError(_) => {
-                HttpResponse::InternalServerError().json(ErrorResponse {
-                    error: "Password processing error occurred".to_string(),
-                })
+            ServiceError::InternalServerError | ServiceError::PasswordHashError(_) => {
+                StatusCode::INTERNAL_SERVER_ERROR
             }
-            ServiceError::NotFound(ref message) => HttpResponse::NotFound().json(ErrorResponse {
-                error: message.clone(),
-            }),
-            ServiceError::BadRequest(ref message) => {
-                HttpResponse::BadRequest().json(ErrorResponse {
-                    error: message.clone(),
-                })
-            }
-            ServiceError::ValidationError(ref message) => {
-                HttpResponse::BadRequest().json(ErrorResponse {
-                    error: message.clone(),
-                })
+            ServiceError::Forbidden => StatusCode::FORBIDDEN,
+            ServiceError::DatabaseError(err) => match database_violation(err) {
+                Some(Violation::Unique) => StatusCode::CONFLICT,
+                Some(Violation::ForeignKey) | Some(Violation::Check) => {
+                    StatusCode::UNPROCESSABLE_ENTITY
+                }
+                None => StatusCode::INTERNAL_SERVER_ERROR,
+            },
+            ServiceError::NotFound(_) => StatusCode::NOT_FOUND,
+            ServiceError::InvalidCredentials
+            | ServiceError::Unauthorized(_)
+            | ServiceError::TokenExpired => StatusCode::UNAUTHORIZED,
+            ServiceError::BadRequest(_) => StatusCode::BAD_REQUEST,
+            ServiceError::ValidationError(_) | ServiceError::UnprocessableEntity(_) => {
+                StatusCode::UNPROCESSABLE_ENTITY
             }
+            ServiceError::Conflict(_) | ServiceError::ProjectArchived => StatusCode::CONFLICT,
+            ServiceError::IdentityProviderError(_) => StatusCode::BAD_GATEWAY,
+            ServiceError::StorageError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            ServiceError::PreconditionFailed { .. } => StatusCode::PRECONDITION_FAILED,
+            ServiceError::BulkOperationFailed { error, .. } => error.status_code(),
+        }
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        let status = self.status_code();
+        if status.is_server_error() {
+            log::error!("{}", self);
+        }
+
+        let current = match self {
+            ServiceError::PreconditionFailed { current, .. } => Some(current),
+            ServiceError::BulkOperationFailed { error, .. } => match error.as_ref() {
+                ServiceError::PreconditionFailed { current, .. } => Some(current),
+                _ => None,
+            },
+            _ => None,
+        };
+        let problem = Problem {
+            problem_type: format!("/problems/{}", self.code().replace('_', "-")),
+            title: status.canonical_reason().unwrap_or("Error"),
+            status: status.as_u16(),
+            detail: self.detail(),
+            code: self.code(),
+            request_id: request_id::current_id(),
+            errors: self.field_errors(),
+            current,
+        };
+
+        let mut response = HttpResponse::build(status);
+        response.content_type(PROBLEM_CONTENT_TYPE);
+        if let ServiceError::PreconditionFailed { version, .. } = self {
+            response.insert_header(etag(*version));
         }
+        response.json(problem)
     }
 }
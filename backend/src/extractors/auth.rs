@@ -1,52 +1,112 @@
-use actix_web::{dev::Payload, error::ErrorUnauthorized, http::header, FromRequest, HttpRequest};
-use futures::future::{ready, Ready};
-use jsonwebtoken::{decode, DecodingKey, Validation};
-use std::env;
+use actix_web::{dev::Payload, http::header, web, FromRequest, HttpRequest};
+use chrono::{DateTime, Utc};
+use futures::future::{ready, LocalBoxFuture};
+use sqlx::PgPool;
 
-use crate::models::{auth::Claims, user::UserRole};
+use crate::errors::ServiceError;
+use crate::middleware::request_id;
+use crate::models::audit::AuditContext;
+use crate::models::user::UserRole;
+use crate::services::token_service::TokenService;
 
 pub struct AuthenticatedUser {
     pub user_id: uuid::Uuid,
     pub role: UserRole,
+    pub request_id: Option<String>,
+    /// When the caller's token expires
+    pub expires_at: DateTime<Utc>,
+}
+
+impl AuthenticatedUser {
+    pub fn require_admin(&self) -> Result<(), ServiceError> {
+        match self.role {
+            UserRole::Admin => Ok(()),
+            _ => Err(ServiceError::Forbidden),
+        }
+    }
+
+    /// Attributes changes made in this request to the caller.
+    pub fn audit(&self) -> AuditContext {
+        AuditContext {
+            actor_id: Some(self.user_id),
+            request_id: self.request_id.clone(),
+        }
+    }
 }
 
 impl FromRequest for AuthenticatedUser {
-    type Error = actix_web::Error;
-    type Future = Ready<Result<Self, Self::Error>>;
+    type Error = ServiceError;
+    type Future = LocalBoxFuture<'static, Result<Self, Self::Error>>;
 
     fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
         // Extract authorization header
         let auth_header = match req.headers().get(header::AUTHORIZATION) {
             Some(header) => header.to_str().unwrap_or(""),
-            None => return ready(Err(ErrorUnauthorized("No authorization header"))),
+            None => {
+                return Box::pin(ready(Err(ServiceError::Unauthorized(
+                    "No authorization header".into(),
+                ))))
+            }
         };
 
         if !auth_header.starts_with("Bearer ") {
-            return ready(Err(ErrorUnauthorized("Invalid authorization header")));
+            return Box::pin(ready(Err(ServiceError::Unauthorized(
+                "Invalid authorization header".into(),
+            ))));
         }
 
         let token = &auth_header["Bearer ".len()..];
-        let jwt_secret = match env::var("JWT_SECRET") {
-            Ok(secret) => secret,
-            Err(_) => return ready(Err(ErrorUnauthorized("JWT_SECRET not configured"))),
+        let tokens = match req.app_data::<web::Data<TokenService>>() {
+            Some(tokens) => tokens,
+            None => {
+                log::error!("Token service not configured");
+                return Box::pin(ready(Err(ServiceError::InternalServerError)));
+            }
         };
 
         // Decode and validate JWT
-        let token_data = match decode::<Claims>(
-            token,
-            &DecodingKey::from_secret(jwt_secret.as_bytes()),
-            &Validation::default(),
-        ) {
-            Ok(data) => data,
-            Err(_) => return ready(Err(ErrorUnauthorized("Invalid token"))),
+        let claims = match tokens.verify(token) {
+            Ok(claims) => claims,
+            Err(e) => return Box::pin(ready(Err(e))),
         };
 
-        let claims = token_data.claims;
-        let role: UserRole = serde_json::from_str(&claims.role).unwrap_or(UserRole::Developer);
+        let user_id = match uuid::Uuid::parse_str(&claims.sub) {
+            Ok(user_id) => user_id,
+            Err(_) => {
+                return Box::pin(ready(Err(ServiceError::Unauthorized(
+                    "Invalid token".into(),
+                ))))
+            }
+        };
+
+        let expires_at = DateTime::from_timestamp(claims.exp as i64, 0).unwrap_or_default();
+        let pool = req.app_data::<web::Data<PgPool>>().cloned();
+        let request_id = request_id::current(req);
+        Box::pin(async move {
+            let pool = pool.ok_or_else(|| {
+                log::error!("Database not configured");
+                ServiceError::InternalServerError
+            })?;
+
+            // The account is looked up on every request so deactivation and
+            // role changes take effect without waiting for tokens to expire.
+            let account = sqlx::query!(
+                r#"SELECT role as "role: UserRole", deactivated_at FROM users WHERE id = $1"#,
+                user_id
+            )
+            .fetch_optional(pool.get_ref())
+            .await?;
 
-        ready(Ok(AuthenticatedUser {
-            user_id: uuid::Uuid::parse_str(&claims.sub).unwrap(),
-            role,
-        }))
+            match account {
+                Some(account) if account.deactivated_at.is_none() => Ok(AuthenticatedUser {
+                    user_id,
+                    role: account.role,
+                    request_id,
+                    expires_at,
+                }),
+                Some(_) => Err(ServiceError::Unauthorized("Account is deactivated".into())),
+                None => Err(ServiceError::Unauthorized("Invalid token".into())),
+            }
+        })
     }
 }
@@ -0,0 +1,53 @@
+use actix_web::{
+    dev::Payload,
+    http::header::{self, EntityTag, Header},
+    FromRequest, HttpRequest,
+};
+use futures::future::{ready, Ready};
+
+use crate::errors::ServiceError;
+
+/// The `ETag` for a record at the given version.
+pub fn etag(version: i32) -> header::ETag {
+    header::ETag(EntityTag::new_strong(version.to_string()))
+}
+
+/// The version named by the request's `If-Match` header. `None` when the
+/// header is absent or `*`, in which case the write is unconditional.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct IfMatch(pub Option<i32>);
+
+impl IfMatch {
+    fn parse(req: &HttpRequest) -> Result<Self, ServiceError> {
+        if !req.headers().contains_key(header::IF_MATCH) {
+            return Ok(IfMatch(None));
+        }
+
+        let malformed = || ServiceError::BadRequest("Malformed If-Match header".into());
+        match header::IfMatch::parse(req).map_err(|_| malformed())? {
+            header::IfMatch::Any => Ok(IfMatch(None)),
+            header::IfMatch::Items(tags) => match tags.as_slice() {
+                [tag] if !tag.weak => tag
+                    .tag()
+                    .parse()
+                    .map(|v| IfMatch(Some(v)))
+                    .map_err(|_| malformed()),
+                [_] => Err(ServiceError::BadRequest(
+                    "If-Match requires a strong entity tag".into(),
+                )),
+                _ => Err(ServiceError::BadRequest(
+                    "If-Match must name exactly one entity tag".into(),
+                )),
+            },
+        }
+    }
+}
+
+impl FromRequest for IfMatch {
+    type Error = ServiceError;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        ready(Self::parse(req))
+    }
+}
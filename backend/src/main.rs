@@ -1,16 +1,28 @@
 use actix_cors::Cors;
-use actix_web::{middleware, web, App, HttpServer};
+use actix_web::{middleware::from_fn, middleware::Logger, web, App, HttpServer};
 use dotenv::dotenv;
 use sqlx::postgres::PgPoolOptions;
 use std::env;
 
+use middleware::request_id;
+use services::email_service::EmailService;
+use services::job_service::{JobContext, JobService};
+use services::oidc_service::{OidcConfig, OidcService};
+use services::realtime_service::Broadcaster;
+use services::storage_service;
+use services::token_service::TokenService;
+use services::trash_service::PurgePolicy;
+use services::webhook_service::WebhookService;
+
 mod api_docs;
 mod db;
 mod errors;
 mod extractors;
+mod middleware;
 mod models;
 mod routes;
 mod services;
+#[cfg(test)]
 mod tests;
 
 #[actix_web::main]
@@ -31,6 +43,86 @@ async fn main() -> std::io::Result<()> {
         .await
         .expect("Failed to run migrations");
 
+    let tokens = match TokenService::from_env() {
+        Ok(tokens) => web::Data::new(tokens),
+        Err(e) => {
+            log::error!("Invalid JWT signing key configuration: {}", e);
+            return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, e));
+        }
+    };
+    log::info!("Signing tokens with key '{}'", tokens.active_kid());
+
+    let email = match EmailService::from_env() {
+        Ok(email) => web::Data::new(email),
+        Err(e) => {
+            log::error!("Invalid SMTP configuration: {}", e);
+            return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, e));
+        }
+    };
+
+    let oidc = match OidcConfig::from_env() {
+        Ok(Some(config)) => {
+            log::info!("OIDC single sign-on enabled");
+            Some(web::Data::new(OidcService::new(config)))
+        }
+        Ok(None) => None,
+        Err(e) => {
+            log::error!("Invalid OIDC configuration: {}", e);
+            return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, e));
+        }
+    };
+
+    let purge = match PurgePolicy::from_env() {
+        Ok(Some(policy)) => {
+            log::info!(
+                "Purging trash older than {} days",
+                policy.retention.num_days()
+            );
+            Some(policy)
+        }
+        Ok(None) => {
+            log::info!("Trash purging disabled");
+            None
+        }
+        Err(e) => {
+            log::error!("Invalid trash purge configuration: {}", e);
+            return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, e));
+        }
+    };
+
+    let storage = match storage_service::from_env() {
+        Ok(storage) => storage,
+        Err(e) => {
+            log::error!("Invalid document storage configuration: {}", e);
+            return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, e));
+        }
+    };
+
+    match JobService::poll_interval_from_env() {
+        Ok(interval) => {
+            let context = JobContext {
+                pool: db_pool.clone(),
+                email: email.clone().into_inner(),
+                purge,
+                http: WebhookService::http_client(),
+                storage: storage.clone(),
+            };
+            JobService::spawn_runner(context, interval);
+        }
+        Err(e) => {
+            log::error!("Invalid job runner configuration: {}", e);
+            return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, e));
+        }
+    }
+
+    let broadcaster = Broadcaster::new();
+    if let Err(e) = broadcaster.listen(&db_pool).await {
+        log::error!("Failed to listen for change notifications: {}", e);
+        return Err(std::io::Error::other(e.to_string()));
+    }
+    let broadcaster = web::Data::new(broadcaster);
+    let storage = web::Data::from(storage);
+
     log::info!("Starting server at http://127.0.0.1:3001");
     log::info!("Swagger UI available at http://127.0.0.1:3001/swagger-ui/");
 
@@ -40,11 +132,20 @@ async fn main() -> std::io::Result<()> {
             .allow_any_method()
             .allow_any_header();
 
-        App::new()
-            .wrap(middleware::Logger::default())
+        let mut app = App::new()
+            .wrap(Logger::default())
+            .wrap(from_fn(request_id::request_id))
             .wrap(cors)
             .app_data(web::Data::new(db_pool.clone()))
-            .configure(routes::config)
+            .app_data(tokens.clone())
+            .app_data(email.clone())
+            .app_data(broadcaster.clone())
+            .app_data(storage.clone());
+        if let Some(oidc) = &oidc {
+            app = app.app_data(oidc.clone());
+        }
+
+        app.configure(routes::config)
             .configure(api_docs::configure_swagger)
     })
     .bind("127.0.0.1:3001")?
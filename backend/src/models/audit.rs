@@ -0,0 +1,130 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+
+#[derive(Debug, Serialize, Deserialize, sqlx::Type, ToSchema, PartialEq, Clone, Copy)]
+#[sqlx(type_name = "audit_action", rename_all = "snake_case")]
+pub enum AuditAction {
+    Create,
+    Update,
+    /// Moved to the trash, or removed outright for records without one
+    Delete,
+    /// Taken back out of the trash
+    Restore,
+    /// Removed from the trash for good
+    Purge,
+}
+
+/// Kinds of records covered by the audit log.
+#[derive(Debug, Serialize, Deserialize, ToSchema, PartialEq, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditEntity {
+    Project,
+    Task,
+    User,
+    Invitation,
+    Resource,
+    PhaseTransition,
+    AuthSettings,
+    GateReview,
+    CalendarFeed,
+    Webhook,
+    Comment,
+    Document,
+    /// A document attached to a record; `entity_id` is the document's
+    DocumentLink,
+    DocumentTemplate,
+    Requirement,
+    DesignItem,
+    TraceLink,
+    TestSuite,
+    TestCase,
+    TestRun,
+    /// The outcome of a test case in a run; `entity_id` is the run's
+    TestResult,
+    Defect,
+}
+
+impl AuditEntity {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AuditEntity::Project => "project",
+            AuditEntity::Task => "task",
+            AuditEntity::User => "user",
+            AuditEntity::Invitation => "invitation",
+            AuditEntity::Resource => "resource",
+            AuditEntity::PhaseTransition => "phase_transition",
+            AuditEntity::AuthSettings => "auth_settings",
+            AuditEntity::GateReview => "gate_review",
+            AuditEntity::CalendarFeed => "calendar_feed",
+            AuditEntity::Webhook => "webhook",
+            AuditEntity::Comment => "comment",
+            AuditEntity::Document => "document",
+            AuditEntity::DocumentLink => "document_link",
+            AuditEntity::DocumentTemplate => "document_template",
+            AuditEntity::Requirement => "requirement",
+            AuditEntity::DesignItem => "design_item",
+            AuditEntity::TraceLink => "trace_link",
+            AuditEntity::TestSuite => "test_suite",
+            AuditEntity::TestCase => "test_case",
+            AuditEntity::TestRun => "test_run",
+            AuditEntity::TestResult => "test_result",
+            AuditEntity::Defect => "defect",
+        }
+    }
+}
+
+/// Who is making a change, threaded from the route into every mutating
+/// service call so the audit entry is written in the same transaction.
+#[derive(Debug, Clone, Default)]
+pub struct AuditContext {
+    /// `None` for unauthenticated flows such as self-registration.
+    pub actor_id: Option<Uuid>,
+    pub request_id: Option<String>,
+}
+
+impl AuditContext {
+    pub fn anonymous(request_id: Option<String>) -> Self {
+        AuditContext {
+            actor_id: None,
+            request_id,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, FromRow, ToSchema)]
+pub struct AuditEntry {
+    pub id: Uuid,
+    pub actor_id: Option<Uuid>,
+    pub entity_type: String,
+    pub entity_id: Uuid,
+    pub action: AuditAction,
+    #[schema(value_type = Option<Object>)]
+    pub before: Option<serde_json::Value>,
+    #[schema(value_type = Option<Object>)]
+    pub after: Option<serde_json::Value>,
+    /// Changed fields as `{"field": {"before": .., "after": ..}}`
+    #[schema(value_type = Object)]
+    pub changes: serde_json::Value,
+    pub request_id: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Filters accepted by `GET /api/audit`.
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct AuditFilter {
+    pub entity_type: Option<AuditEntity>,
+    pub entity_id: Option<Uuid>,
+    pub actor_id: Option<Uuid>,
+    pub action: Option<AuditAction>,
+    pub request_id: Option<String>,
+    /// Only entries recorded at or after this time
+    pub from: Option<DateTime<Utc>>,
+    /// Only entries recorded before this time
+    pub to: Option<DateTime<Utc>>,
+}
+
+pub const AUDIT_SORT_FIELDS: &[&str] = &["created_at", "entity_type", "action"];
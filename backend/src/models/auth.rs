@@ -1,3 +1,4 @@
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use utoipa::ToSchema;
 use validator::Validate;
@@ -24,3 +25,23 @@ pub struct Claims {
     pub role: String,
     pub exp: usize, // expiration time
 }
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct AuthSettings {
+    pub password_login_enabled: bool,
+    pub updated_by: Option<uuid::Uuid>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct AuthSettingsUpdate {
+    pub password_login_enabled: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct OidcCallbackParams {
+    pub code: Option<String>,
+    pub state: String,
+    pub error: Option<String>,
+    pub error_description: Option<String>,
+}
@@ -0,0 +1,95 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use utoipa::ToSchema;
+use uuid::Uuid;
+use validator::{Validate, ValidationError};
+
+use crate::models::lifecycle::LifecyclePhase;
+use crate::models::version::Versioned;
+
+/// A review meeting that decides whether a project may enter `phase`.
+/// The exit criteria of the latest review for a phase are the ones enforced.
+#[derive(Debug, Serialize, Deserialize, FromRow, ToSchema)]
+pub struct GateReview {
+    pub id: Uuid,
+    pub project_id: Uuid,
+    pub phase: LifecyclePhase,
+    pub scheduled_at: DateTime<Utc>,
+    pub duration_minutes: i32,
+    pub location: Option<String>,
+    pub notes: Option<String>,
+    /// Exit criterion: the project may only enter `phase` once this
+    /// percentage of its executed test cases pass
+    pub min_pass_rate: Option<i32>,
+    /// Exit criterion: the project may only enter `phase` while no critical
+    /// defect is open
+    pub no_open_critical_defects: bool,
+    pub created_by: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub version: i32,
+}
+
+impl Versioned for GateReview {
+    fn version(&self) -> i32 {
+        self.version
+    }
+}
+
+fn validate_duration(value: i32) -> Result<(), ValidationError> {
+    if !(1..=24 * 60).contains(&value) {
+        return Err(ValidationError::new("range")
+            .with_message("duration must be between 1 minute and 24 hours".into()));
+    }
+    Ok(())
+}
+
+fn default_duration() -> i32 {
+    60
+}
+
+#[derive(Debug, Serialize, Deserialize, Validate, ToSchema)]
+pub struct GateReviewCreate {
+    pub phase: LifecyclePhase,
+    pub scheduled_at: DateTime<Utc>,
+    #[serde(default = "default_duration")]
+    #[validate(custom(function = "validate_duration"))]
+    #[schema(example = 60)]
+    pub duration_minutes: i32,
+    #[validate(length(max = 255))]
+    pub location: Option<String>,
+    pub notes: Option<String>,
+    #[validate(range(min = 0, max = 100))]
+    #[schema(example = 95)]
+    pub min_pass_rate: Option<i32>,
+    #[serde(default)]
+    pub no_open_critical_defects: bool,
+}
+
+/// An iCalendar subscription. The token itself is only shown when the feed
+/// is created.
+#[derive(Debug, Serialize, Deserialize, FromRow, ToSchema)]
+pub struct CalendarFeed {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    /// `None` for the user's own assignments across projects
+    pub project_id: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, ToSchema)]
+pub struct CalendarFeedCreate {
+    /// Subscribe to one project's schedule instead of your own assignments
+    pub project_id: Option<Uuid>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct CalendarFeedCreated {
+    #[serde(flatten)]
+    pub feed: CalendarFeed,
+    /// Subscription URL for calendar clients; anyone holding it can read the
+    /// feed until it is revoked
+    #[schema(example = "/api/calendar/XyZ...-token.ics")]
+    pub url: String,
+}
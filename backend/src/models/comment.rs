@@ -0,0 +1,137 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::models::audit::{AuditEntity, AuditEntry};
+use crate::models::version::Versioned;
+
+/// Kinds of records that can be discussed.
+#[derive(Debug, Serialize, Deserialize, sqlx::Type, ToSchema, PartialEq, Eq, Clone, Copy)]
+#[sqlx(type_name = "comment_target", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum CommentTarget {
+    Project,
+    Task,
+    PhaseTransition,
+    GateReview,
+}
+
+impl CommentTarget {
+    /// How the record appears in the audit log.
+    pub fn audit_entity(&self) -> AuditEntity {
+        match self {
+            CommentTarget::Project => AuditEntity::Project,
+            CommentTarget::Task => AuditEntity::Task,
+            CommentTarget::PhaseTransition => AuditEntity::PhaseTransition,
+            CommentTarget::GateReview => AuditEntity::GateReview,
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            CommentTarget::Project => "Project",
+            CommentTarget::Task => "Task",
+            CommentTarget::PhaseTransition => "Phase transition",
+            CommentTarget::GateReview => "Gate review",
+        }
+    }
+}
+
+/// A comment on a project, task, phase transition or gate review. Deleted
+/// comments stay in their thread with an empty body so replies keep their
+/// place.
+#[derive(Debug, Serialize, Deserialize, FromRow, ToSchema, Clone)]
+pub struct Comment {
+    pub id: Uuid,
+    pub target_type: CommentTarget,
+    pub target_id: Uuid,
+    pub project_id: Uuid,
+    /// Comment this one replies to
+    pub parent_id: Option<Uuid>,
+    pub author_id: Uuid,
+    /// Markdown
+    #[schema(example = "Looks good. @jane.doe@example.com can you confirm the test plan?")]
+    pub body: String,
+    /// Users mentioned in the body
+    pub mentions: Vec<Uuid>,
+    /// When the body was last changed
+    pub edited_at: Option<DateTime<Utc>>,
+    pub deleted_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub version: i32,
+}
+
+impl Versioned for Comment {
+    fn version(&self) -> i32 {
+        self.version
+    }
+}
+
+impl Comment {
+    /// What readers see: deleted comments lose their content.
+    pub fn redacted(mut self) -> Self {
+        if self.deleted_at.is_some() {
+            self.body.clear();
+            self.mentions.clear();
+        }
+        self
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Validate, ToSchema)]
+pub struct CommentCreate {
+    pub target_type: CommentTarget,
+    pub target_id: Uuid,
+    /// Reply to this comment, which must be on the same record
+    pub parent_id: Option<Uuid>,
+    /// Markdown. Mention users as `@` followed by their email address.
+    #[validate(length(min = 1, max = 10000))]
+    pub body: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Validate, ToSchema)]
+pub struct CommentUpdate {
+    #[validate(length(min = 1, max = 10000))]
+    pub body: String,
+}
+
+/// A body a comment had before it was edited.
+#[derive(Debug, Serialize, Deserialize, FromRow, ToSchema)]
+pub struct CommentRevision {
+    pub id: Uuid,
+    pub comment_id: Uuid,
+    pub body: String,
+    pub edited_by: Option<Uuid>,
+    /// When this body was replaced
+    pub created_at: DateTime<Utc>,
+}
+
+/// The record whose comments or activity are requested.
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct CommentFilter {
+    pub target_type: CommentTarget,
+    pub target_id: Uuid,
+}
+
+/// One entry of a record's activity timeline.
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ActivityItem {
+    /// A recorded change to the record
+    Change(AuditEntry),
+    Comment(Comment),
+}
+
+impl ActivityItem {
+    pub fn occurred_at(&self) -> DateTime<Utc> {
+        match self {
+            ActivityItem::Change(entry) => entry.created_at,
+            ActivityItem::Comment(comment) => comment.created_at,
+        }
+    }
+}
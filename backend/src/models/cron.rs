@@ -0,0 +1,158 @@
+use chrono::{DateTime, Datelike, Duration, DurationRound, TimeZone, Timelike, Utc};
+use std::fmt;
+use std::str::FromStr;
+
+/// How far ahead `next_after` looks before giving up on an expression that
+/// can never fire, such as `0 0 31 2 *`.
+const SEARCH_YEARS: i32 = 5;
+
+/// A five-field cron expression (`minute hour day-of-month month
+/// day-of-week`), evaluated in UTC.
+///
+/// Fields accept `*`, single values, ranges (`1-5`), steps (`*/15`, `8-18/2`)
+/// and comma-separated lists of those. Day of week runs from 0 (Sunday) to 7
+/// (Sunday again). As in classic cron, when both day fields are restricted a
+/// day matches if either does.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CronSchedule {
+    source: String,
+    minutes: u64,
+    hours: u64,
+    days: u64,
+    months: u64,
+    weekdays: u64,
+    any_day: bool,
+    any_weekday: bool,
+}
+
+impl CronSchedule {
+    /// The first time strictly after `after` that the schedule fires.
+    pub fn next_after(&self, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let mut t = after.duration_trunc(Duration::minutes(1)).ok()? + Duration::minutes(1);
+        let limit = after.year() + SEARCH_YEARS;
+
+        while t.year() <= limit {
+            if !has(self.months, t.month()) {
+                let (year, month) = if t.month() == 12 {
+                    (t.year() + 1, 1)
+                } else {
+                    (t.year(), t.month() + 1)
+                };
+                t = Utc.with_ymd_and_hms(year, month, 1, 0, 0, 0).single()?;
+                continue;
+            }
+            if !self.matches_day(t) {
+                t = t.duration_trunc(Duration::days(1)).ok()? + Duration::days(1);
+                continue;
+            }
+            if !has(self.hours, t.hour()) {
+                t = t.duration_trunc(Duration::hours(1)).ok()? + Duration::hours(1);
+                continue;
+            }
+            if !has(self.minutes, t.minute()) {
+                t += Duration::minutes(1);
+                continue;
+            }
+            return Some(t);
+        }
+
+        None
+    }
+
+    fn matches_day(&self, t: DateTime<Utc>) -> bool {
+        let day = has(self.days, t.day());
+        let weekday = has(self.weekdays, t.weekday().num_days_from_sunday());
+        match (self.any_day, self.any_weekday) {
+            (true, true) => true,
+            (true, false) => weekday,
+            (false, true) => day,
+            (false, false) => day || weekday,
+        }
+    }
+}
+
+fn has(set: u64, value: u32) -> bool {
+    set & (1 << value) != 0
+}
+
+/// Parses one field into a bit set of the values it allows.
+fn parse_field(field: &str, name: &str, min: u32, max: u32) -> Result<u64, String> {
+    let invalid = || format!("invalid {} field '{}'", name, field);
+    let mut set = 0u64;
+
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => {
+                let step: u32 = step.parse().map_err(|_| invalid())?;
+                if step == 0 {
+                    return Err(invalid());
+                }
+                (range, step)
+            }
+            None => (part, 1),
+        };
+        let (start, end) = match range {
+            "*" => (min, max),
+            _ => match range.split_once('-') {
+                Some((start, end)) => (
+                    start.parse().map_err(|_| invalid())?,
+                    end.parse().map_err(|_| invalid())?,
+                ),
+                // `5/10` means every 10 starting at 5
+                None if step > 1 => (range.parse().map_err(|_| invalid())?, max),
+                None => {
+                    let value = range.parse().map_err(|_| invalid())?;
+                    (value, value)
+                }
+            },
+        };
+        if start < min || end > max || start > end {
+            return Err(format!(
+                "{} field '{}' must stay within {}-{}",
+                name, field, min, max
+            ));
+        }
+        for value in (start..=end).step_by(step as usize) {
+            set |= 1 << value;
+        }
+    }
+
+    Ok(set)
+}
+
+impl FromStr for CronSchedule {
+    type Err = String;
+
+    fn from_str(source: &str) -> Result<Self, Self::Err> {
+        let fields: Vec<&str> = source.split_whitespace().collect();
+        let [minute, hour, day, month, weekday] = fields[..] else {
+            return Err(format!(
+                "expected 5 fields (minute hour day month weekday), got {}",
+                fields.len()
+            ));
+        };
+
+        let mut weekdays = parse_field(weekday, "weekday", 0, 7)?;
+        // 7 is another name for Sunday
+        if has(weekdays, 7) {
+            weekdays |= 1;
+        }
+
+        Ok(CronSchedule {
+            source: fields.join(" "),
+            minutes: parse_field(minute, "minute", 0, 59)?,
+            hours: parse_field(hour, "hour", 0, 23)?,
+            days: parse_field(day, "day", 1, 31)?,
+            months: parse_field(month, "month", 1, 12)?,
+            weekdays,
+            any_day: day == "*",
+            any_weekday: weekday == "*",
+        })
+    }
+}
+
+impl fmt::Display for CronSchedule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.source)
+    }
+}
@@ -0,0 +1,161 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::models::patch::Patch;
+use crate::models::version::Versioned;
+
+/// How badly a defect affects the product.
+#[derive(Debug, Serialize, Deserialize, sqlx::Type, ToSchema, PartialEq, Eq, Clone, Copy)]
+#[sqlx(type_name = "defect_severity", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum DefectSeverity {
+    Low,
+    Medium,
+    High,
+    /// Blocks the release, e.g. data loss or a crash in a main flow
+    Critical,
+}
+
+/// How soon a defect should be fixed.
+#[derive(Debug, Serialize, Deserialize, sqlx::Type, ToSchema, PartialEq, Eq, Clone, Copy)]
+#[sqlx(type_name = "defect_priority", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum DefectPriority {
+    Low,
+    Medium,
+    High,
+    Urgent,
+}
+
+#[derive(Debug, Serialize, Deserialize, sqlx::Type, ToSchema, PartialEq, Eq, Clone, Copy)]
+#[sqlx(type_name = "defect_status", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum DefectStatus {
+    New,
+    /// Confirmed, with severity and priority agreed
+    Triaged,
+    Fixing,
+    /// The fix was retested and holds
+    Verified,
+    Closed,
+}
+
+impl DefectStatus {
+    /// Whether a defect may move from `self` to `next`: one step along
+    /// new → triaged → fixing → verified → closed, back to fixing when the
+    /// fix does not hold, straight to closed when it is not a defect, and
+    /// back to triaged when a closed one turns up again.
+    pub fn may_move_to(&self, next: DefectStatus) -> bool {
+        matches!(
+            (self, next),
+            (DefectStatus::New, DefectStatus::Triaged)
+                | (DefectStatus::Triaged, DefectStatus::Fixing)
+                | (DefectStatus::Fixing, DefectStatus::Verified)
+                | (DefectStatus::Verified, DefectStatus::Closed)
+                | (DefectStatus::Verified, DefectStatus::Fixing)
+                | (DefectStatus::New, DefectStatus::Closed)
+                | (DefectStatus::Triaged, DefectStatus::Closed)
+                | (DefectStatus::Closed, DefectStatus::Triaged)
+        )
+    }
+}
+
+/// A defect of a project, numbered within it. It may point at the test
+/// case and run it failed in, the requirement it breaks and the task that
+/// fixes it.
+#[derive(Debug, Serialize, Deserialize, FromRow, ToSchema, Clone)]
+pub struct Defect {
+    pub id: Uuid,
+    pub project_id: Uuid,
+    #[schema(example = "DEF-001")]
+    pub code: String,
+    #[schema(example = "Invoice totals are off by one cent")]
+    pub title: String,
+    pub description: Option<String>,
+    pub severity: DefectSeverity,
+    pub priority: DefectPriority,
+    pub status: DefectStatus,
+    pub assignee_id: Option<Uuid>,
+    pub test_case_id: Option<Uuid>,
+    pub test_run_id: Option<Uuid>,
+    pub requirement_id: Option<Uuid>,
+    pub fix_task_id: Option<Uuid>,
+    pub reported_by: Option<Uuid>,
+    /// When the defect was last closed
+    pub closed_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub version: i32,
+}
+
+impl Versioned for Defect {
+    fn version(&self) -> i32 {
+        self.version
+    }
+}
+
+/// Raises a defect. Given a test run, the test case must have failed in it.
+#[derive(Debug, Serialize, Deserialize, Validate, ToSchema)]
+pub struct DefectCreate {
+    pub project_id: Uuid,
+    #[validate(length(min = 1, max = 255))]
+    pub title: String,
+    pub description: Option<String>,
+    pub severity: DefectSeverity,
+    /// Defaults to `medium`
+    pub priority: Option<DefectPriority>,
+    pub assignee_id: Option<Uuid>,
+    pub test_case_id: Option<Uuid>,
+    pub test_run_id: Option<Uuid>,
+    pub requirement_id: Option<Uuid>,
+    pub fix_task_id: Option<Uuid>,
+}
+
+/// JSON Merge Patch for a defect. `status` must follow the workflow.
+#[derive(Debug, Serialize, Deserialize, Validate, ToSchema, Default)]
+#[serde(default)]
+pub struct DefectUpdate {
+    #[validate(length(min = 1, max = 255))]
+    #[schema(value_type = Option<String>)]
+    pub title: Patch<String>,
+    #[schema(value_type = Option<String>)]
+    pub description: Patch<String>,
+    #[schema(value_type = Option<DefectSeverity>)]
+    pub severity: Patch<DefectSeverity>,
+    #[schema(value_type = Option<DefectPriority>)]
+    pub priority: Patch<DefectPriority>,
+    #[schema(value_type = Option<DefectStatus>)]
+    pub status: Patch<DefectStatus>,
+    #[schema(value_type = Option<Uuid>)]
+    pub assignee_id: Patch<Uuid>,
+    #[schema(value_type = Option<Uuid>)]
+    pub requirement_id: Patch<Uuid>,
+    #[schema(value_type = Option<Uuid>)]
+    pub fix_task_id: Patch<Uuid>,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct DefectFilter {
+    pub project_id: Uuid,
+    pub status: Option<DefectStatus>,
+    pub severity: Option<DefectSeverity>,
+    pub assignee_id: Option<Uuid>,
+    /// Only new, triaged and fixing defects
+    pub open: Option<bool>,
+}
+
+/// Open defects of a project by severity.
+#[derive(Debug, Serialize, Deserialize, ToSchema, PartialEq, Default)]
+pub struct OpenDefectCounts {
+    pub project_id: Uuid,
+    pub low: i64,
+    pub medium: i64,
+    pub high: i64,
+    pub critical: i64,
+    pub total: i64,
+}
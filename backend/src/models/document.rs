@@ -0,0 +1,149 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::models::patch::Patch;
+use crate::models::version::Versioned;
+
+/// Records of a project a document can be linked to.
+#[derive(Debug, Serialize, Deserialize, sqlx::Type, ToSchema, PartialEq, Eq, Clone, Copy)]
+#[sqlx(type_name = "document_target", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum DocumentTarget {
+    /// Supporting material for a task
+    Task,
+    /// The work product of a milestone
+    Deliverable,
+    PhaseTransition,
+    GateReview,
+}
+
+impl DocumentTarget {
+    pub fn label(&self) -> &'static str {
+        match self {
+            DocumentTarget::Task => "Task",
+            DocumentTarget::Deliverable => "Deliverable",
+            DocumentTarget::PhaseTransition => "Phase transition",
+            DocumentTarget::GateReview => "Gate review",
+        }
+    }
+}
+
+/// A file kept with a project, described by its latest version. `version`
+/// is the record's version for `If-Match`; `current_version` numbers the
+/// uploaded content.
+#[derive(Debug, Serialize, Deserialize, FromRow, ToSchema, Clone)]
+pub struct Document {
+    pub id: Uuid,
+    pub project_id: Uuid,
+    #[schema(example = "System requirements specification")]
+    pub title: String,
+    pub description: Option<String>,
+    pub current_version: i32,
+    #[schema(example = "srs.pdf")]
+    pub filename: String,
+    #[schema(example = "application/pdf")]
+    pub content_type: String,
+    pub size_bytes: i64,
+    /// Hex SHA-256 of the current content
+    pub checksum: String,
+    pub created_by: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub version: i32,
+}
+
+impl Versioned for Document {
+    fn version(&self) -> i32 {
+        self.version
+    }
+}
+
+/// One uploaded revision of a document's content.
+#[derive(Debug, Serialize, Deserialize, FromRow, ToSchema, Clone)]
+pub struct DocumentVersion {
+    pub id: Uuid,
+    pub document_id: Uuid,
+    pub number: i32,
+    pub filename: String,
+    pub content_type: String,
+    pub size_bytes: i64,
+    /// Hex SHA-256 of the content
+    pub checksum: String,
+    #[serde(skip)]
+    pub storage_key: String,
+    pub uploaded_by: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Metadata for a new document; the content is the request body and its
+/// `Content-Type` header.
+#[derive(Debug, Deserialize, Validate, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct DocumentUpload {
+    pub project_id: Uuid,
+    /// Name the file is downloaded under
+    #[validate(length(min = 1, max = 255))]
+    pub filename: String,
+    /// Defaults to the filename
+    #[validate(length(min = 1, max = 255))]
+    pub title: Option<String>,
+    pub description: Option<String>,
+    /// Link the document to this record of the project as well
+    pub target_type: Option<DocumentTarget>,
+    pub target_id: Option<Uuid>,
+}
+
+/// A new version's filename; the content is the request body.
+#[derive(Debug, Deserialize, Validate, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct VersionUpload {
+    /// Defaults to the current version's filename
+    #[validate(length(min = 1, max = 255))]
+    pub filename: Option<String>,
+}
+
+/// JSON Merge Patch for a document's metadata.
+#[derive(Debug, Serialize, Deserialize, Validate, ToSchema, Default)]
+#[serde(default)]
+pub struct DocumentUpdate {
+    #[validate(length(min = 1, max = 255))]
+    #[schema(value_type = Option<String>)]
+    pub title: Patch<String>,
+    #[schema(value_type = Option<String>)]
+    pub description: Patch<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, FromRow, ToSchema, Clone)]
+pub struct DocumentLink {
+    pub document_id: Uuid,
+    pub target_type: DocumentTarget,
+    pub target_id: Uuid,
+    pub created_by: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct DocumentLinkCreate {
+    pub target_type: DocumentTarget,
+    pub target_id: Uuid,
+}
+
+/// Documents of a project, or those linked to one of its records.
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct DocumentFilter {
+    pub project_id: Option<Uuid>,
+    pub target_type: Option<DocumentTarget>,
+    pub target_id: Option<Uuid>,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct DownloadParams {
+    /// Version number; the current version when omitted
+    pub version: Option<i32>,
+}
@@ -0,0 +1,28 @@
+use serde::Deserialize;
+use utoipa::{IntoParams, ToSchema};
+
+#[derive(Debug, Default, Deserialize, ToSchema, PartialEq, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    /// Microsoft Project XML, readable by `POST /api/projects/import`
+    #[default]
+    Mspdi,
+    /// One row per task, for spreadsheets
+    Csv,
+}
+
+/// Query options for `GET /api/projects/{id}/export`.
+#[derive(Debug, Default, Deserialize, IntoParams)]
+pub struct ExportParams {
+    #[serde(default)]
+    #[param(inline)]
+    pub format: ExportFormat,
+}
+
+/// A serialized plan, ready to be sent as a download.
+#[derive(Debug)]
+pub struct PlanExport {
+    pub content_type: &'static str,
+    pub filename: String,
+    pub body: String,
+}
@@ -0,0 +1,98 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::types::BigDecimal;
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+
+use crate::models::task::DependencyType;
+
+/// Query options for `POST /api/projects/import`.
+#[derive(Debug, Default, Deserialize, IntoParams)]
+pub struct ImportParams {
+    /// Map the plan and report warnings without creating anything
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+/// How an imported plan maps onto projects, tasks and resources. Returned
+/// for dry runs and, with the created ids filled in, once the plan is saved.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ImportReport {
+    pub dry_run: bool,
+    /// Set once the plan has been saved
+    pub project_id: Option<Uuid>,
+    pub project: ImportedProject,
+    pub tasks: Vec<ImportedTask>,
+    pub resources: Vec<ImportedResource>,
+    pub warnings: Vec<ImportWarning>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ImportedProject {
+    pub name: String,
+    pub description: Option<String>,
+    pub start_date: DateTime<Utc>,
+    pub end_date: DateTime<Utc>,
+}
+
+/// A task of the plan, identified by its UID in the source file.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ImportedTask {
+    pub uid: i64,
+    pub name: String,
+    pub description: Option<String>,
+    pub wbs: Option<String>,
+    pub parent_uid: Option<i64>,
+    pub milestone: bool,
+    pub start_date: DateTime<Utc>,
+    pub end_date: DateTime<Utc>,
+    #[schema(value_type = String, example = "50")]
+    pub progress: BigDecimal,
+    pub predecessors: Vec<ImportedLink>,
+    /// UID of the resource the task is assigned to
+    pub resource_uid: Option<i64>,
+    /// Set once the plan has been saved
+    pub task_id: Option<Uuid>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ImportedLink {
+    pub predecessor_uid: i64,
+    pub link_type: DependencyType,
+    pub lag_minutes: i32,
+}
+
+/// What happens to a resource of the plan.
+#[derive(Debug, Serialize, ToSchema, PartialEq, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum ResourceAction {
+    /// Created as a new resource
+    Create,
+    /// Matched to an existing resource by email, or by name without one
+    Match,
+    /// Left out; its assignments are dropped
+    Skip,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ImportedResource {
+    pub uid: i64,
+    pub name: String,
+    pub email: Option<String>,
+    pub role: String,
+    #[schema(value_type = String, example = "100")]
+    pub availability: BigDecimal,
+    #[schema(value_type = String, example = "95.00")]
+    pub hourly_rate: BigDecimal,
+    pub action: ResourceAction,
+    /// The matched resource, or the created one once the plan has been saved
+    pub resource_id: Option<Uuid>,
+}
+
+/// Something in the file that was adjusted or left out.
+#[derive(Debug, Serialize, ToSchema, PartialEq)]
+pub struct ImportWarning {
+    /// The element concerned, e.g. `Task 12` or `Assignment 3`
+    pub element: String,
+    pub message: String,
+}
@@ -0,0 +1,115 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::types::BigDecimal;
+use sqlx::FromRow;
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+
+use crate::models::patch::Patch;
+
+/// Work the background runner knows how to do.
+#[derive(Debug, Serialize, Deserialize, sqlx::Type, ToSchema, PartialEq, Eq, Clone, Copy)]
+#[sqlx(type_name = "job_kind", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum JobKind {
+    /// Notify about tasks past their end date
+    OverdueTasks,
+    /// Send notifications waiting for the email channel
+    NotificationEmails,
+    /// Email each user a summary of their unread notifications
+    DailyDigest,
+    /// Remove projects and tasks that have been in the trash too long
+    TrashPurge,
+    /// Recompute progress against plan for every live project
+    ScheduleMetrics,
+    /// Send webhook deliveries that are due, including retries
+    WebhookDeliveries,
+    /// Remove stored content of deleted document versions
+    DocumentCleanup,
+    /// Generate a document from a project's records
+    DocumentGeneration,
+    /// Email a pending invitation its link
+    InvitationEmail,
+}
+
+#[derive(Debug, Serialize, Deserialize, sqlx::Type, ToSchema, PartialEq, Eq, Clone, Copy)]
+#[sqlx(type_name = "job_status", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum JobStatus {
+    /// Waiting for `run_at`, including failed attempts that will be retried
+    Pending,
+    Running,
+    Succeeded,
+    /// Gave up after `max_attempts`
+    Failed,
+}
+
+#[derive(Debug, Serialize, Deserialize, FromRow, ToSchema)]
+pub struct Job {
+    pub id: Uuid,
+    pub kind: JobKind,
+    #[schema(value_type = Object)]
+    pub payload: serde_json::Value,
+    pub status: JobStatus,
+    /// Schedule that enqueued the job, if any
+    pub schedule: Option<String>,
+    pub attempts: i32,
+    pub max_attempts: i32,
+    pub run_at: DateTime<Utc>,
+    pub last_error: Option<String>,
+    /// Runner that claimed the job last
+    pub locked_by: Option<String>,
+    pub started_at: Option<DateTime<Utc>>,
+    pub finished_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Filters accepted by `GET /api/jobs`.
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct JobFilter {
+    pub kind: Option<JobKind>,
+    pub status: Option<JobStatus>,
+}
+
+pub const JOB_SORT_FIELDS: &[&str] = &["created_at", "run_at", "kind", "status"];
+
+#[derive(Debug, Serialize, Deserialize, FromRow, ToSchema)]
+pub struct JobSchedule {
+    pub name: String,
+    pub kind: JobKind,
+    /// Five-field cron expression in UTC
+    #[schema(example = "0 2 * * *")]
+    pub cron: String,
+    pub enabled: bool,
+    pub next_run_at: DateTime<Utc>,
+    pub last_enqueued_at: Option<DateTime<Utc>>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// JSON Merge Patch for a schedule.
+#[derive(Debug, Serialize, Deserialize, ToSchema, Default)]
+#[serde(default)]
+pub struct JobScheduleUpdate {
+    #[schema(value_type = Option<String>, example = "30 1 * * *")]
+    pub cron: Patch<String>,
+    #[schema(value_type = Option<bool>)]
+    pub enabled: Patch<bool>,
+}
+
+/// Progress against plan for one project, as of `computed_at`.
+#[derive(Debug, Serialize, Deserialize, FromRow, ToSchema)]
+pub struct ScheduleMetrics {
+    pub project_id: Uuid,
+    pub task_count: i32,
+    pub completed_count: i32,
+    pub overdue_count: i32,
+    /// Task progress weighted by task duration
+    #[schema(value_type = String, example = "42.50")]
+    pub percent_complete: BigDecimal,
+    /// Share of task duration already elapsed, where progress should be
+    #[schema(value_type = String, example = "50.00")]
+    pub percent_planned: BigDecimal,
+    pub computed_at: DateTime<Utc>,
+}
@@ -1,9 +1,10 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 use uuid::Uuid;
 use validator::Validate;
 
-#[derive(Debug, Serialize, Deserialize, sqlx::Type, PartialEq, Clone, Copy)]
+#[derive(Debug, Serialize, Deserialize, sqlx::Type, ToSchema, PartialEq, Clone, Copy)]
 #[sqlx(type_name = "lifecycle_phase", rename_all = "snake_case")]
 pub enum LifecyclePhase {
     Proposal,
@@ -22,7 +23,12 @@ pub struct PhaseTransition {
     pub phase: LifecyclePhase,
     #[validate(length(min = 1, message = "Description is required"))]
     pub description: String,
+    /// Free-text references; prefer `documents`
     pub attachments: Option<Vec<String>>,
+    /// Documents of the project to link to the transition, e.g. the signed
+    /// gate approval
+    #[serde(default)]
+    pub documents: Vec<Uuid>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
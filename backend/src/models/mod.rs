@@ -1,6 +1,27 @@
+pub mod audit;
 pub mod auth;
+pub mod calendar;
+pub mod comment;
+pub mod cron;
+pub mod defect;
+pub mod document;
+pub mod export;
+pub mod import;
+pub mod job;
 pub mod lifecycle;
+pub mod mustache;
+pub mod notification;
+pub mod pagination;
+pub mod patch;
 pub mod project;
+pub mod realtime;
+pub mod requirement;
 pub mod resource;
+pub mod search;
 pub mod task;
+pub mod template;
+pub mod testing;
+pub mod traceability;
 pub mod user;
+pub mod version;
+pub mod webhook;
@@ -0,0 +1,260 @@
+use serde_json::Value;
+use std::str::FromStr;
+
+/// A logic-less template in the Mustache style, rendered against JSON.
+///
+/// Supported tags are `{{name}}` (escaped), `{{{name}}}` and `{{& name}}`
+/// (verbatim), sections `{{#name}}…{{/name}}`, inverted sections
+/// `{{^name}}…{{/name}}` and comments `{{! … }}`. Names are dotted paths
+/// looked up from the innermost section outwards; `{{.}}` is the current
+/// item. A section repeats for each element of a list and renders once for
+/// any other value except `null`, `false`, `""` and `[]`. Section and
+/// comment tags alone on a line take the line with them, so templates can
+/// lay out Markdown tables and lists naturally.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Template {
+    nodes: Vec<Node>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Node {
+    Text(String),
+    Variable {
+        path: Vec<String>,
+        escaped: bool,
+    },
+    Section {
+        path: Vec<String>,
+        inverted: bool,
+        children: Vec<Node>,
+    },
+}
+
+impl Template {
+    /// Renders the template, passing substituted text through `escape`
+    /// unless the tag asks for it verbatim.
+    pub fn render(&self, context: &Value, escape: fn(&str) -> String) -> String {
+        let mut out = String::new();
+        let mut stack = vec![context];
+        render_nodes(&self.nodes, &mut stack, escape, &mut out);
+        out
+    }
+}
+
+impl FromStr for Template {
+    type Err = String;
+
+    fn from_str(source: &str) -> Result<Self, Self::Err> {
+        // Open sections: name, line opened on, and the nodes collected so far
+        let mut open: Vec<(String, usize, Vec<Node>)> = Vec::new();
+        let mut nodes = Vec::new();
+        let mut text = String::new();
+        let mut rest = source;
+
+        while let Some(start) = rest.find("{{") {
+            text.push_str(&rest[..start]);
+            let line = line_of(source, source.len() - rest.len() + start);
+            let after = &rest[start + 2..];
+            let (tag, close) = match after.strip_prefix('{') {
+                Some(tag) => (tag, "}}}"),
+                None => (after, "}}"),
+            };
+            let end = tag
+                .find(close)
+                .ok_or_else(|| format!("line {}: unclosed tag", line))?;
+            let content = &tag[..end];
+            rest = &tag[end + close.len()..];
+
+            let (sigil, name) = match content.trim_start().chars().next() {
+                _ if close == "}}}" => ('&', content.trim()),
+                Some(c @ ('#' | '^' | '/' | '!' | '&')) => (c, content.trim_start()[1..].trim()),
+                _ => (' ', content.trim()),
+            };
+
+            // Section and comment tags alone on their line leave no trace
+            if matches!(sigil, '#' | '^' | '/' | '!') {
+                let tag_start = source.len() - after.len() - 2;
+                let line_start = source[..tag_start].rfind('\n').map_or(0, |i| i + 1);
+                let before_blank = source[line_start..tag_start]
+                    .chars()
+                    .all(|c| c == ' ' || c == '\t');
+                let line_end = rest.find('\n');
+                let after_blank = rest[..line_end.unwrap_or(rest.len())]
+                    .trim_end_matches('\r')
+                    .chars()
+                    .all(|c| c == ' ' || c == '\t');
+                if before_blank && after_blank {
+                    text.truncate(text.rfind('\n').map_or(0, |i| i + 1));
+                    rest = match line_end {
+                        Some(i) => &rest[i + 1..],
+                        None => "",
+                    };
+                }
+            }
+
+            if sigil == '!' {
+                continue;
+            }
+            if name.is_empty() {
+                return Err(format!("line {}: tag without a name", line));
+            }
+            if !text.is_empty() {
+                nodes.push(Node::Text(std::mem::take(&mut text)));
+            }
+            match sigil {
+                '#' | '^' => {
+                    let parent = std::mem::take(&mut nodes);
+                    open.push((format!("{}{}", sigil, name), line, parent));
+                }
+                '/' => {
+                    let Some((opened, _, parent)) = open.pop() else {
+                        return Err(format!("line {}: {{{{/{}}}}} closes nothing", line, name));
+                    };
+                    if opened[1..] != *name {
+                        return Err(format!(
+                            "line {}: {{{{/{}}}}} closes {{{{{}}}}}",
+                            line, name, opened
+                        ));
+                    }
+                    let children = std::mem::replace(&mut nodes, parent);
+                    nodes.push(Node::Section {
+                        path: path(name),
+                        inverted: opened.starts_with('^'),
+                        children,
+                    });
+                }
+                _ => nodes.push(Node::Variable {
+                    path: path(name),
+                    escaped: sigil == ' ',
+                }),
+            }
+        }
+        text.push_str(rest);
+
+        if let Some((name, line, _)) = open.pop() {
+            return Err(format!("line {}: {{{{{}}}}} is never closed", line, name));
+        }
+        if !text.is_empty() {
+            nodes.push(Node::Text(text));
+        }
+
+        Ok(Template { nodes })
+    }
+}
+
+fn line_of(source: &str, offset: usize) -> usize {
+    source[..offset].matches('\n').count() + 1
+}
+
+fn path(name: &str) -> Vec<String> {
+    if name == "." {
+        Vec::new()
+    } else {
+        name.split('.').map(str::to_string).collect()
+    }
+}
+
+static NULL: Value = Value::Null;
+
+fn render_nodes<'a>(
+    nodes: &[Node],
+    stack: &mut Vec<&'a Value>,
+    escape: fn(&str) -> String,
+    out: &mut String,
+) {
+    for node in nodes {
+        match node {
+            Node::Text(text) => out.push_str(text),
+            Node::Variable { path, escaped } => {
+                let text = match lookup(stack, path) {
+                    Some(Value::String(s)) => s.clone(),
+                    Some(Value::Number(n)) => n.to_string(),
+                    Some(Value::Bool(b)) => b.to_string(),
+                    _ => String::new(),
+                };
+                if *escaped {
+                    out.push_str(&escape(&text));
+                } else {
+                    out.push_str(&text);
+                }
+            }
+            Node::Section {
+                path,
+                inverted,
+                children,
+            } => {
+                let value = lookup(stack, path).unwrap_or(&NULL);
+                if *inverted {
+                    if !truthy(value) {
+                        render_nodes(children, stack, escape, out);
+                    }
+                    continue;
+                }
+                let items = match value {
+                    Value::Array(items) => items.iter().collect(),
+                    _ if truthy(value) => vec![value],
+                    _ => Vec::new(),
+                };
+                for item in items {
+                    stack.push(item);
+                    render_nodes(children, stack, escape, out);
+                    stack.pop();
+                }
+            }
+        }
+    }
+}
+
+fn lookup<'a>(stack: &[&'a Value], path: &[String]) -> Option<&'a Value> {
+    let Some((first, rest)) = path.split_first() else {
+        return stack.last().copied();
+    };
+    let mut value = stack.iter().rev().find_map(|scope| scope.get(first))?;
+    for key in rest {
+        value = value.get(key)?;
+    }
+    Some(value)
+}
+
+fn truthy(value: &Value) -> bool {
+    match value {
+        Value::Null | Value::Bool(false) => false,
+        Value::String(s) => !s.is_empty(),
+        Value::Array(items) => !items.is_empty(),
+        _ => true,
+    }
+}
+
+/// Escapes text for HTML element content and attribute values.
+pub fn escape_html(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+/// Escapes text so Markdown shows it literally, on one line. Keeps
+/// substituted values from starting emphasis or breaking table cells.
+pub fn escape_markdown(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' | '`' | '*' | '_' | '[' | ']' | '<' | '>' | '|' | '#' => {
+                out.push('\\');
+                out.push(c);
+            }
+            '\r' => {}
+            '\n' => out.push(' '),
+            _ => out.push(c),
+        }
+    }
+    out
+}
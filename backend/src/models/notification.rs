@@ -0,0 +1,98 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+
+/// Things users can be notified about.
+#[derive(Debug, Serialize, Deserialize, sqlx::Type, ToSchema, PartialEq, Eq, Clone, Copy)]
+#[sqlx(type_name = "notification_event", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum NotificationEvent {
+    /// A task was assigned to you
+    TaskAssigned,
+    /// A task assigned to you, or in a project you own, passed its end date
+    TaskOverdue,
+    /// A predecessor of one of your tasks now finishes later
+    PredecessorSlipped,
+    /// A gate review was scheduled and needs an approver
+    GateApprovalRequested,
+    /// A project you own or work on entered a new phase
+    PhaseTransitioned,
+    /// The planned cost of a project you own reached a share of its budget
+    BudgetThresholdCrossed,
+    /// Someone mentioned you in a comment
+    Mentioned,
+}
+
+impl NotificationEvent {
+    pub const ALL: [NotificationEvent; 7] = [
+        NotificationEvent::TaskAssigned,
+        NotificationEvent::TaskOverdue,
+        NotificationEvent::PredecessorSlipped,
+        NotificationEvent::GateApprovalRequested,
+        NotificationEvent::PhaseTransitioned,
+        NotificationEvent::BudgetThresholdCrossed,
+        NotificationEvent::Mentioned,
+    ];
+}
+
+#[derive(Debug, Serialize, Deserialize, FromRow, ToSchema)]
+pub struct Notification {
+    pub id: Uuid,
+    pub event: NotificationEvent,
+    pub title: String,
+    pub body: String,
+    pub project_id: Option<Uuid>,
+    pub task_id: Option<Uuid>,
+    pub read_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Filters accepted by `GET /api/notifications`.
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct NotificationFilter {
+    /// Only notifications that have not been read
+    #[serde(default)]
+    pub unread: bool,
+}
+
+pub const NOTIFICATION_SORT_FIELDS: &[&str] = &["created_at", "event"];
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct UnreadCount {
+    pub unread: i64,
+}
+
+/// Which channels deliver one kind of event to the user. Events without a
+/// stored preference are delivered in-app only.
+#[derive(Debug, Serialize, Deserialize, FromRow, ToSchema, PartialEq, Clone)]
+pub struct NotificationPreference {
+    pub event: NotificationEvent,
+    pub in_app: bool,
+    pub email: bool,
+}
+
+impl NotificationPreference {
+    pub fn default_for(event: NotificationEvent) -> Self {
+        NotificationPreference {
+            event,
+            in_app: true,
+            email: false,
+        }
+    }
+}
+
+/// What a raised notification says and what it points at.
+#[derive(Debug, Clone)]
+pub struct NotificationContent {
+    pub event: NotificationEvent,
+    pub title: String,
+    pub body: String,
+    pub project_id: Option<Uuid>,
+    pub task_id: Option<Uuid>,
+    /// Set for conditions that are re-checked, so each recipient is only
+    /// told once
+    pub dedupe_key: Option<String>,
+}
@@ -0,0 +1,116 @@
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+use crate::errors::ServiceError;
+
+pub const DEFAULT_PER_PAGE: i64 = 50;
+pub const MAX_PER_PAGE: i64 = 200;
+
+/// Paging and sorting parameters shared by every list endpoint.
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct PageParams {
+    /// Page number, starting at 1
+    #[param(minimum = 1, example = 1)]
+    pub page: Option<i64>,
+    /// Items per page (default 50, at most 200)
+    #[param(minimum = 1, maximum = 200, example = 50)]
+    pub per_page: Option<i64>,
+    /// Field to sort by; prefix with `-` for descending order
+    #[param(example = "-created_at")]
+    pub sort: Option<String>,
+}
+
+impl PageParams {
+    pub fn page(&self) -> i64 {
+        self.page.unwrap_or(1).max(1)
+    }
+
+    pub fn per_page(&self) -> i64 {
+        self.per_page
+            .unwrap_or(DEFAULT_PER_PAGE)
+            .clamp(1, MAX_PER_PAGE)
+    }
+
+    /// Rows to skip for the requested page; pages too far out to address
+    /// are rejected rather than overflowing.
+    pub fn offset(&self) -> Result<i64, ServiceError> {
+        (self.page() - 1)
+            .checked_mul(self.per_page())
+            .ok_or_else(|| ServiceError::invalid_field("page", "range", "Page is out of range"))
+    }
+
+    /// Resolves `sort` against the fields a list allows sorting by and
+    /// returns an `ORDER BY` clause. `id` is appended as a tie-breaker so
+    /// pages are stable when sort values repeat.
+    pub fn order_by(&self, allowed: &[&str], default: &str) -> Result<String, ServiceError> {
+        let sort = self.sort.as_deref().unwrap_or(default);
+        let (field, direction) = match sort.strip_prefix('-') {
+            Some(field) => (field, "DESC"),
+            None => (sort, "ASC"),
+        };
+
+        if !allowed.contains(&field) {
+            return Err(ServiceError::BadRequest(format!(
+                "Cannot sort by '{}'; expected one of: {}",
+                field,
+                allowed.join(", ")
+            )));
+        }
+
+        Ok(format!(
+            " ORDER BY {} {}, id {}",
+            field, direction, direction
+        ))
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, ToSchema)]
+pub struct PageLinks {
+    pub next: Option<String>,
+    pub prev: Option<String>,
+}
+
+/// One page of a list together with the total number of matching items.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    pub total: i64,
+    pub page: i64,
+    pub per_page: i64,
+    pub links: PageLinks,
+}
+
+impl<T> Page<T> {
+    pub fn new(items: Vec<T>, total: i64, params: &PageParams) -> Self {
+        Page {
+            items,
+            total,
+            page: params.page(),
+            per_page: params.per_page(),
+            links: PageLinks::default(),
+        }
+    }
+
+    /// Fills in links to the neighbouring pages, keeping every other query
+    /// parameter of the current request.
+    pub fn with_links(mut self, path: &str, query: &str) -> Self {
+        let link = |page: i64| {
+            let mut pairs: Vec<&str> = query
+                .split('&')
+                .filter(|pair| !pair.is_empty() && !pair.starts_with("page="))
+                .collect();
+            let page = format!("page={}", page);
+            pairs.push(&page);
+            format!("{}?{}", path, pairs.join("&"))
+        };
+
+        if self.page.saturating_mul(self.per_page) < self.total {
+            self.links.next = Some(link(self.page + 1));
+        }
+        if self.page > 1 {
+            self.links.prev = Some(link(self.page - 1));
+        }
+        self
+    }
+}
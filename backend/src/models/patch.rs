@@ -0,0 +1,82 @@
+use std::borrow::Cow;
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use validator::{ValidateEmail, ValidateLength};
+
+use crate::errors::ServiceError;
+
+/// One member of a JSON Merge Patch (RFC 7396) body: left out, set to
+/// `null`, or set to a value. Fields must be marked `#[serde(default)]` so a
+/// missing member deserializes as `Absent`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum Patch<T> {
+    /// Member not present: keep the current value.
+    #[default]
+    Absent,
+    /// Member is `null`: clear the current value.
+    Null,
+    Value(T),
+}
+
+impl<T> Patch<T> {
+    pub fn as_value(&self) -> Option<&T> {
+        match self {
+            Patch::Value(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// The new value of a nullable field.
+    pub fn apply(self, current: Option<T>) -> Option<T> {
+        match self {
+            Patch::Absent => current,
+            Patch::Null => None,
+            Patch::Value(value) => Some(value),
+        }
+    }
+
+    /// The new value of a field that cannot be cleared; `null` is rejected.
+    pub fn apply_required(self, field: &str, current: T) -> Result<T, ServiceError> {
+        match self {
+            Patch::Absent => Ok(current),
+            Patch::Null => Err(ServiceError::invalid_field(
+                field,
+                "required",
+                format!("{} cannot be null", field),
+            )),
+            Patch::Value(value) => Ok(value),
+        }
+    }
+}
+
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for Patch<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Ok(match Option::<T>::deserialize(deserializer)? {
+            Some(value) => Patch::Value(value),
+            None => Patch::Null,
+        })
+    }
+}
+
+impl<T: Serialize> Serialize for Patch<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Patch::Value(value) => value.serialize(serializer),
+            _ => serializer.serialize_none(),
+        }
+    }
+}
+
+// Validation rules only apply to values actually being written
+
+impl<T: ValidateLength<u64>> ValidateLength<u64> for Patch<T> {
+    fn length(&self) -> Option<u64> {
+        self.as_value().and_then(ValidateLength::length)
+    }
+}
+
+impl<T: ValidateEmail> ValidateEmail for Patch<T> {
+    fn as_email_string(&self) -> Option<Cow<'_, str>> {
+        self.as_value().and_then(ValidateEmail::as_email_string)
+    }
+}
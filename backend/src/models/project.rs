@@ -2,15 +2,22 @@ use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use sqlx::types::BigDecimal;
 use sqlx::FromRow;
-use utoipa::ToSchema;
+use utoipa::{IntoParams, ToSchema};
 use uuid::Uuid;
 use validator::{Validate, ValidationError};
 
+use crate::errors::ServiceError;
+use crate::models::lifecycle::LifecyclePhase;
+use crate::models::patch::Patch;
+use crate::models::version::Versioned;
+
 fn validate_budget_min(value: &BigDecimal) -> Result<(), ValidationError> {
     use bigdecimal::FromPrimitive;
     let min = BigDecimal::from_f64(0.0).unwrap();
     if value < &min {
-        return Err(ValidationError::new("budget must be non-negative"));
+        return Err(
+            ValidationError::new("range").with_message("budget must be non-negative".into())
+        );
     }
     Ok(())
 }
@@ -31,9 +38,31 @@ pub struct Project {
     pub client_id: Option<Uuid>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
+    /// Bumped on every change; sent as the `ETag` and checked against `If-Match`
+    pub version: i32,
+    /// Set while the project is in the trash
+    pub deleted_at: Option<DateTime<Utc>>,
+    /// Set once a closed project is archived; it is then read-only
+    pub archived_at: Option<DateTime<Utc>>,
 }
 
-#[derive(Debug, Serialize, Deserialize, sqlx::Type, ToSchema, PartialEq)]
+impl Versioned for Project {
+    fn version(&self) -> i32 {
+        self.version
+    }
+}
+
+impl Project {
+    /// Rejects changes to an archived project or anything that belongs to it.
+    pub fn ensure_writable(&self) -> Result<(), ServiceError> {
+        match self.archived_at {
+            Some(_) => Err(ServiceError::ProjectArchived),
+            None => Ok(()),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, sqlx::Type, ToSchema, PartialEq, Clone, Copy)]
 #[sqlx(type_name = "project_status", rename_all = "snake_case")]
 pub enum ProjectStatus {
     Planning,
@@ -57,16 +86,55 @@ pub struct ProjectCreate {
     pub client_id: Option<Uuid>,
 }
 
-#[derive(Debug, Serialize, Deserialize, Validate, ToSchema)]
+fn validate_budget_patch(value: &Patch<BigDecimal>) -> Result<(), ValidationError> {
+    value.as_value().map_or(Ok(()), validate_budget_min)
+}
+
+/// JSON Merge Patch for a project: absent fields are left alone and `null`
+/// clears `description` and `client_id`.
+#[derive(Debug, Default, Serialize, Deserialize, Validate, ToSchema)]
+#[serde(default)]
 pub struct ProjectUpdate {
     #[validate(length(min = 1, max = 255))]
-    pub name: Option<String>,
-    pub description: Option<String>,
-    pub start_date: Option<DateTime<Utc>>,
-    pub end_date: Option<DateTime<Utc>>,
+    #[schema(value_type = Option<String>)]
+    pub name: Patch<String>,
+    #[schema(value_type = Option<String>)]
+    pub description: Patch<String>,
+    #[schema(value_type = Option<DateTime<Utc>>)]
+    pub start_date: Patch<DateTime<Utc>>,
+    #[schema(value_type = Option<DateTime<Utc>>)]
+    pub end_date: Patch<DateTime<Utc>>,
+    #[schema(value_type = Option<ProjectStatus>)]
+    pub status: Patch<ProjectStatus>,
+    #[schema(value_type = Option<String>, example = "150000.00")]
+    #[validate(custom(function = "validate_budget_patch"))]
+    pub budget: Patch<BigDecimal>,
+    #[schema(value_type = Option<Uuid>)]
+    pub client_id: Patch<Uuid>,
+}
+
+/// Filters accepted by `GET /api/projects`.
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ProjectFilter {
     pub status: Option<ProjectStatus>,
-    #[schema(value_type = String, example = "150000.00")]
-    #[validate(custom(function = "validate_budget_min"))]
-    pub budget: Option<BigDecimal>,
-    pub client_id: Option<Uuid>,
+    pub phase: Option<LifecyclePhase>,
+    /// Only projects starting on or after this date
+    pub starts_after: Option<DateTime<Utc>>,
+    /// Only projects ending on or before this date
+    pub ends_before: Option<DateTime<Utc>>,
+    /// Archived projects are left out unless `true`, which lists only them
+    pub archived: Option<bool>,
 }
+
+pub const PROJECT_SORT_FIELDS: &[&str] = &[
+    "name",
+    "start_date",
+    "end_date",
+    "status",
+    "budget",
+    "created_at",
+    "updated_at",
+    "deleted_at",
+    "archived_at",
+];
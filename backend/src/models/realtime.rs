@@ -0,0 +1,68 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+
+/// Kinds of change streamed to clients.
+#[derive(Debug, Serialize, Deserialize, ToSchema, PartialEq, Eq, Clone, Copy)]
+pub enum ChangeKind {
+    #[serde(rename = "task.created")]
+    TaskCreated,
+    #[serde(rename = "task.updated")]
+    TaskUpdated,
+    /// The task was moved to the trash, on its own or with its project
+    #[serde(rename = "task.deleted")]
+    TaskDeleted,
+    /// The task came back out of the trash, on its own or with its project
+    #[serde(rename = "task.restored")]
+    TaskRestored,
+    /// The project came back out of the trash
+    #[serde(rename = "project.restored")]
+    ProjectRestored,
+    #[serde(rename = "project.phase_changed")]
+    PhaseTransitioned,
+    #[serde(rename = "comment.added")]
+    CommentAdded,
+    /// A new document, or a new version of one
+    #[serde(rename = "document.uploaded")]
+    DocumentUploaded,
+}
+
+impl ChangeKind {
+    /// Name used as the SSE `event` field.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ChangeKind::TaskCreated => "task.created",
+            ChangeKind::TaskUpdated => "task.updated",
+            ChangeKind::TaskDeleted => "task.deleted",
+            ChangeKind::TaskRestored => "task.restored",
+            ChangeKind::ProjectRestored => "project.restored",
+            ChangeKind::PhaseTransitioned => "project.phase_changed",
+            ChangeKind::CommentAdded => "comment.added",
+            ChangeKind::DocumentUploaded => "document.uploaded",
+        }
+    }
+}
+
+/// A change to a project, small enough for a Postgres notification. Clients
+/// fetch the entity itself if they need more than its id and version.
+#[derive(Debug, Serialize, Deserialize, ToSchema, PartialEq, Clone)]
+pub struct ChangeEvent {
+    pub id: Uuid,
+    pub kind: ChangeKind,
+    pub project_id: Uuid,
+    /// The task, project, phase transition, comment or document that changed
+    pub entity_id: Uuid,
+    /// The entity's version after the change, where it has one
+    pub version: Option<i32>,
+    pub actor_id: Option<Uuid>,
+    pub occurred_at: DateTime<Utc>,
+}
+
+/// Query accepted by `GET /api/events`.
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct StreamParams {
+    /// Only stream this project's changes
+    pub project_id: Option<Uuid>,
+}
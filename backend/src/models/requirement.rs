@@ -0,0 +1,134 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::models::patch::Patch;
+use crate::models::version::Versioned;
+
+#[derive(Debug, Serialize, Deserialize, sqlx::Type, ToSchema, PartialEq, Eq, Clone, Copy)]
+#[sqlx(type_name = "requirement_type", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum RequirementType {
+    /// Something the system does
+    Functional,
+    /// A quality the system has: performance, security, usability…
+    NonFunctional,
+}
+
+#[derive(Debug, Serialize, Deserialize, sqlx::Type, ToSchema, PartialEq, Eq, Clone, Copy)]
+#[sqlx(type_name = "requirement_priority", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum RequirementPriority {
+    Low,
+    Medium,
+    High,
+    Critical,
+}
+
+#[derive(Debug, Serialize, Deserialize, sqlx::Type, ToSchema, PartialEq, Eq, Clone, Copy)]
+#[sqlx(type_name = "requirement_status", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum RequirementStatus {
+    Draft,
+    /// Agreed with the stakeholders; work may be planned against it
+    Approved,
+    Implemented,
+    /// Shown to be met by testing
+    Verified,
+    Rejected,
+}
+
+/// A requirement of a project. `code` numbers it within the project and
+/// never changes; every change to its content makes a new version, kept in
+/// its revisions.
+#[derive(Debug, Serialize, Deserialize, FromRow, ToSchema, Clone)]
+pub struct Requirement {
+    pub id: Uuid,
+    pub project_id: Uuid,
+    #[schema(example = "REQ-001")]
+    pub code: String,
+    #[schema(example = "Users can reset a forgotten password")]
+    pub title: String,
+    pub description: Option<String>,
+    #[serde(rename = "type")]
+    pub requirement_type: RequirementType,
+    pub priority: RequirementPriority,
+    pub status: RequirementStatus,
+    /// Conditions the delivered work must satisfy, usually one per line
+    pub acceptance_criteria: Option<String>,
+    pub created_by: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub version: i32,
+}
+
+impl Versioned for Requirement {
+    fn version(&self) -> i32 {
+        self.version
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Validate, ToSchema)]
+pub struct RequirementCreate {
+    pub project_id: Uuid,
+    #[validate(length(min = 1, max = 255))]
+    pub title: String,
+    pub description: Option<String>,
+    #[serde(rename = "type")]
+    pub requirement_type: RequirementType,
+    /// Defaults to medium
+    pub priority: Option<RequirementPriority>,
+    /// Defaults to draft
+    pub status: Option<RequirementStatus>,
+    pub acceptance_criteria: Option<String>,
+}
+
+/// JSON Merge Patch for a requirement.
+#[derive(Debug, Serialize, Deserialize, Validate, ToSchema, Default)]
+#[serde(default)]
+pub struct RequirementUpdate {
+    #[validate(length(min = 1, max = 255))]
+    #[schema(value_type = Option<String>)]
+    pub title: Patch<String>,
+    #[schema(value_type = Option<String>)]
+    pub description: Patch<String>,
+    #[serde(rename = "type")]
+    #[schema(value_type = Option<RequirementType>)]
+    pub requirement_type: Patch<RequirementType>,
+    #[schema(value_type = Option<RequirementPriority>)]
+    pub priority: Patch<RequirementPriority>,
+    #[schema(value_type = Option<RequirementStatus>)]
+    pub status: Patch<RequirementStatus>,
+    #[schema(value_type = Option<String>)]
+    pub acceptance_criteria: Patch<String>,
+}
+
+/// A requirement's content as of one of its versions.
+#[derive(Debug, Serialize, Deserialize, FromRow, ToSchema)]
+pub struct RequirementRevision {
+    pub requirement_id: Uuid,
+    pub version: i32,
+    pub title: String,
+    pub description: Option<String>,
+    #[serde(rename = "type")]
+    pub requirement_type: RequirementType,
+    pub priority: RequirementPriority,
+    pub status: RequirementStatus,
+    pub acceptance_criteria: Option<String>,
+    pub changed_by: Option<Uuid>,
+    /// When this version was made
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct RequirementFilter {
+    pub project_id: Uuid,
+    #[serde(rename = "type")]
+    pub requirement_type: Option<RequirementType>,
+    pub priority: Option<RequirementPriority>,
+    pub status: Option<RequirementStatus>,
+}
@@ -1,17 +1,103 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use sqlx::types::BigDecimal;
 use sqlx::FromRow;
+use utoipa::ToSchema;
 use uuid::Uuid;
+use validator::{Validate, ValidationError};
 
-#[derive(Debug, Serialize, Deserialize, FromRow)]
+use crate::models::patch::Patch;
+
+fn validate_availability(value: &BigDecimal) -> Result<(), ValidationError> {
+    if value < &BigDecimal::from(0) || value > &BigDecimal::from(100) {
+        return Err(ValidationError::new("range")
+            .with_message("availability must be between 0 and 100".into()));
+    }
+    Ok(())
+}
+
+fn validate_rate_min(value: &BigDecimal) -> Result<(), ValidationError> {
+    if value < &BigDecimal::from(0) {
+        return Err(
+            ValidationError::new("range").with_message("hourly rate must be non-negative".into())
+        );
+    }
+    Ok(())
+}
+
+#[derive(Debug, Serialize, Deserialize, FromRow, ToSchema)]
 pub struct Resource {
     pub id: Uuid,
+    #[schema(example = "Jane Doe")]
     pub name: String,
+    #[schema(example = "jane.doe@example.com")]
     pub email: String,
+    #[schema(example = "Backend Developer")]
     pub role: String,
     pub skills: Vec<String>,
-    pub availability: f32, // percentage
-    pub hourly_rate: f64,
+    #[schema(value_type = String, example = "80.00")]
+    pub availability: BigDecimal, // percentage
+    #[schema(value_type = String, example = "95.00")]
+    pub hourly_rate: BigDecimal,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
+
+#[derive(Debug, Serialize, Deserialize, Validate, ToSchema)]
+pub struct ResourceCreate {
+    #[validate(length(min = 1, max = 255))]
+    pub name: String,
+    #[validate(email)]
+    pub email: String,
+    #[validate(length(min = 1, max = 100))]
+    pub role: String,
+    #[serde(default)]
+    pub skills: Vec<String>,
+    #[schema(value_type = String, example = "100.00")]
+    #[validate(custom(function = "validate_availability"))]
+    pub availability: BigDecimal,
+    #[schema(value_type = String, example = "95.00")]
+    #[validate(custom(function = "validate_rate_min"))]
+    pub hourly_rate: BigDecimal,
+}
+
+fn validate_availability_patch(value: &Patch<BigDecimal>) -> Result<(), ValidationError> {
+    value.as_value().map_or(Ok(()), validate_availability)
+}
+
+fn validate_rate_patch(value: &Patch<BigDecimal>) -> Result<(), ValidationError> {
+    value.as_value().map_or(Ok(()), validate_rate_min)
+}
+
+/// JSON Merge Patch for a resource: absent fields are left alone and `null`
+/// clears `skills`; the other fields cannot be cleared.
+#[derive(Debug, Default, Serialize, Deserialize, Validate, ToSchema)]
+#[serde(default)]
+pub struct ResourceUpdate {
+    #[validate(length(min = 1, max = 255))]
+    #[schema(value_type = Option<String>)]
+    pub name: Patch<String>,
+    #[validate(email)]
+    #[schema(value_type = Option<String>)]
+    pub email: Patch<String>,
+    #[validate(length(min = 1, max = 100))]
+    #[schema(value_type = Option<String>)]
+    pub role: Patch<String>,
+    #[schema(value_type = Option<Vec<String>>)]
+    pub skills: Patch<Vec<String>>,
+    #[schema(value_type = Option<String>, example = "100.00")]
+    #[validate(custom(function = "validate_availability_patch"))]
+    pub availability: Patch<BigDecimal>,
+    #[schema(value_type = Option<String>, example = "95.00")]
+    #[validate(custom(function = "validate_rate_patch"))]
+    pub hourly_rate: Patch<BigDecimal>,
+}
+
+pub const RESOURCE_SORT_FIELDS: &[&str] = &[
+    "name",
+    "email",
+    "role",
+    "availability",
+    "hourly_rate",
+    "created_at",
+];
@@ -0,0 +1,33 @@
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct SearchParams {
+    /// Search terms; supports quoted phrases, `or` and `-excluded` words
+    #[param(example = "ERP migration")]
+    pub q: String,
+    /// Maximum results per entity type (default 10, at most 50)
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct SearchHit {
+    pub id: Uuid,
+    pub project_id: Uuid,
+    pub project_name: String,
+    pub title: String,
+    /// HTML: the matching text, escaped, with terms wrapped in `<mark>` tags
+    pub snippet: String,
+    pub rank: f32,
+}
+
+/// Matches grouped by entity type, each group ordered by relevance.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct SearchResults {
+    pub query: String,
+    pub projects: Vec<SearchHit>,
+    pub tasks: Vec<SearchHit>,
+    pub phase_transitions: Vec<SearchHit>,
+}
@@ -2,9 +2,12 @@ use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use sqlx::types::BigDecimal;
 use sqlx::FromRow;
-use utoipa::ToSchema;
+use utoipa::{IntoParams, ToSchema};
 use uuid::Uuid;
-use validator::Validate;
+use validator::{Validate, ValidationError};
+
+use crate::models::patch::Patch;
+use crate::models::version::Versioned;
 
 #[derive(Debug, Serialize, Deserialize, FromRow, ToSchema)]
 pub struct Task {
@@ -19,11 +22,26 @@ pub struct Task {
     pub dependencies: Vec<Uuid>,
     #[schema(value_type = String, example = "150000.00")]
     pub progress: BigDecimal, // percentage
+    /// Summary task this task sits under in the WBS
+    pub parent_id: Option<Uuid>,
+    #[schema(example = "1.2.3")]
+    pub wbs: Option<String>,
+    pub milestone: bool,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
+    /// Bumped on every change; sent as the `ETag` and checked against `If-Match`
+    pub version: i32,
+    /// Set while the task is in the trash
+    pub deleted_at: Option<DateTime<Utc>>,
+}
+
+impl Versioned for Task {
+    fn version(&self) -> i32 {
+        self.version
+    }
 }
 
-#[derive(Debug, Serialize, Deserialize, sqlx::Type, ToSchema, PartialEq)]
+#[derive(Debug, Serialize, Deserialize, sqlx::Type, ToSchema, PartialEq, Clone, Copy)]
 #[sqlx(type_name = "task_status", rename_all = "snake_case")]
 pub enum TaskStatus {
     Pending,
@@ -41,19 +59,173 @@ pub struct TaskCreate {
     pub start_date: DateTime<Utc>,
     pub end_date: DateTime<Utc>,
     pub dependencies: Vec<Uuid>,
+    #[serde(default)]
+    pub parent_id: Option<Uuid>,
+    #[serde(default)]
+    #[validate(length(max = 64))]
+    pub wbs: Option<String>,
+    #[serde(default)]
+    pub milestone: bool,
 }
 
+fn validate_progress_patch(value: &Patch<BigDecimal>) -> Result<(), ValidationError> {
+    match value.as_value() {
+        Some(progress) if progress < &BigDecimal::from(0) || progress > &BigDecimal::from(100) => {
+            Err(ValidationError::new("range")
+                .with_message("progress must be between 0 and 100".into()))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// JSON Merge Patch for a task: absent fields are left alone, `null` clears
+/// `description`, `parent_id` or `wbs`, unassigns the task, or removes all
+/// dependencies.
 #[derive(Debug, Serialize, Deserialize, Validate, ToSchema, Default)]
+#[serde(default)]
 pub struct TaskUpdate {
     #[validate(length(min = 1, max = 255))]
-    pub name: Option<String>,
-    pub description: Option<String>,
-    pub assigned_to: Option<Uuid>,
-    #[schema(value_type = TaskStatus)]
+    #[schema(value_type = Option<String>)]
+    pub name: Patch<String>,
+    #[schema(value_type = Option<String>)]
+    pub description: Patch<String>,
+    #[schema(value_type = Option<Uuid>)]
+    pub assigned_to: Patch<Uuid>,
+    #[schema(value_type = Option<TaskStatus>)]
+    pub status: Patch<TaskStatus>,
+    #[validate(custom(function = "validate_progress_patch"))]
+    #[schema(value_type = Option<String>, example = "50.00")]
+    pub progress: Patch<BigDecimal>,
+    #[schema(value_type = Option<DateTime<Utc>>)]
+    pub start_date: Patch<DateTime<Utc>>,
+    #[schema(value_type = Option<DateTime<Utc>>)]
+    pub end_date: Patch<DateTime<Utc>>,
+    #[schema(value_type = Option<Vec<Uuid>>)]
+    pub dependencies: Patch<Vec<Uuid>>,
+    #[schema(value_type = Option<Uuid>)]
+    pub parent_id: Patch<Uuid>,
+    #[validate(length(max = 64))]
+    #[schema(value_type = Option<String>)]
+    pub wbs: Patch<String>,
+    #[schema(value_type = Option<bool>)]
+    pub milestone: Patch<bool>,
+}
+
+/// Filters accepted by `GET /api/tasks`.
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct TaskFilter {
+    pub project_id: Option<Uuid>,
     pub status: Option<TaskStatus>,
-    #[schema(value_type = String, example = "150000.00")]
-    pub progress: Option<BigDecimal>,
-    pub start_date: Option<DateTime<Utc>>,
-    pub end_date: Option<DateTime<Utc>>,
-    pub dependencies: Option<Vec<Uuid>>,
+    /// Only tasks assigned to this resource
+    pub assignee: Option<Uuid>,
+    /// Only tasks past their end date that are not completed (or, when
+    /// false, only tasks that are not overdue)
+    pub overdue: Option<bool>,
+}
+
+pub const TASK_SORT_FIELDS: &[&str] = &[
+    "name",
+    "start_date",
+    "end_date",
+    "status",
+    "progress",
+    "created_at",
+    "updated_at",
+    "deleted_at",
+];
+
+/// How a task's dates depend on a predecessor's.
+#[derive(Debug, Serialize, Deserialize, sqlx::Type, ToSchema, PartialEq, Eq, Clone, Copy)]
+#[sqlx(type_name = "dependency_type", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum DependencyType {
+    FinishToStart,
+    StartToStart,
+    FinishToFinish,
+    StartToFinish,
+}
+
+impl DependencyType {
+    /// The link `Type` code used in MS Project XML.
+    pub fn mspdi_code(self) -> u8 {
+        match self {
+            DependencyType::FinishToFinish => 0,
+            DependencyType::FinishToStart => 1,
+            DependencyType::StartToFinish => 2,
+            DependencyType::StartToStart => 3,
+        }
+    }
+
+    pub fn from_mspdi(code: u8) -> Option<Self> {
+        match code {
+            0 => Some(DependencyType::FinishToFinish),
+            1 => Some(DependencyType::FinishToStart),
+            2 => Some(DependencyType::StartToFinish),
+            3 => Some(DependencyType::StartToStart),
+            _ => None,
+        }
+    }
+
+    /// Short form used by scheduling tools, e.g. `FS`.
+    pub fn abbreviation(self) -> &'static str {
+        match self {
+            DependencyType::FinishToStart => "FS",
+            DependencyType::StartToStart => "SS",
+            DependencyType::FinishToFinish => "FF",
+            DependencyType::StartToFinish => "SF",
+        }
+    }
+}
+
+/// Type and lag of one entry in a task's `dependencies`.
+#[derive(Debug, Serialize, Deserialize, FromRow, ToSchema, PartialEq, Clone)]
+pub struct TaskLink {
+    pub task_id: Uuid,
+    pub predecessor_id: Uuid,
+    pub link_type: DependencyType,
+    /// Working minutes between the linked dates; negative for lead time
+    pub lag_minutes: i32,
+}
+
+/// Most operations accepted by one `POST /api/tasks/bulk` request.
+pub const MAX_BULK_OPERATIONS: usize = 100;
+
+/// One step of a bulk request. `version`, when given, is checked like
+/// `If-Match` on the single-task endpoints.
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum BulkTaskOperation {
+    Create {
+        task: TaskCreate,
+    },
+    Update {
+        id: Uuid,
+        version: Option<i32>,
+        changes: TaskUpdate,
+    },
+    Delete {
+        id: Uuid,
+        version: Option<i32>,
+    },
+}
+
+/// Operations applied in order, all in one transaction.
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct BulkTaskRequest {
+    pub operations: Vec<BulkTaskOperation>,
+}
+
+/// Outcome of one operation, in request order.
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum BulkTaskResult {
+    Created { index: usize, task: Task },
+    Updated { index: usize, task: Task },
+    Deleted { index: usize, id: Uuid },
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct BulkTaskResponse {
+    pub results: Vec<BulkTaskResult>,
 }
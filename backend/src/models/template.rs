@@ -0,0 +1,178 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::models::lifecycle::LifecyclePhase;
+use crate::models::patch::Patch;
+use crate::models::version::Versioned;
+
+/// Documents generated from a project's records.
+#[derive(Debug, Serialize, Deserialize, sqlx::Type, ToSchema, PartialEq, Eq, Clone, Copy)]
+#[sqlx(type_name = "document_kind", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum DocumentKind {
+    /// Requirements Specification Document, reviewed at the requirements gate
+    RequirementsSpecification,
+    /// Summary of the phase the project is in, for its gate review
+    PhaseReport,
+    /// Final report, kept with the project once it is archived
+    ClosureReport,
+}
+
+impl DocumentKind {
+    pub fn label(&self) -> &'static str {
+        match self {
+            DocumentKind::RequirementsSpecification => "Requirements Specification",
+            DocumentKind::PhaseReport => "Phase Report",
+            DocumentKind::ClosureReport => "Closure Report",
+        }
+    }
+
+    /// Phase whose gate the document belongs to; `None` for the phase the
+    /// project is in.
+    pub fn phase(&self) -> Option<LifecyclePhase> {
+        match self {
+            DocumentKind::RequirementsSpecification => Some(LifecyclePhase::Requirements),
+            DocumentKind::PhaseReport => None,
+            DocumentKind::ClosureReport => Some(LifecyclePhase::Closed),
+        }
+    }
+
+    pub fn slug(&self) -> &'static str {
+        match self {
+            DocumentKind::RequirementsSpecification => "requirements-specification",
+            DocumentKind::PhaseReport => "phase-report",
+            DocumentKind::ClosureReport => "closure-report",
+        }
+    }
+}
+
+/// Language a template is written in.
+#[derive(Debug, Serialize, Deserialize, sqlx::Type, ToSchema, PartialEq, Eq, Clone, Copy)]
+#[sqlx(type_name = "template_format", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum TemplateFormat {
+    /// Renders to Markdown, HTML or PDF
+    Markdown,
+    /// A complete HTML page; renders to HTML only
+    Html,
+}
+
+#[derive(Debug, Serialize, Deserialize, sqlx::Type, ToSchema, PartialEq, Eq, Clone, Copy)]
+#[sqlx(type_name = "output_format", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum OutputFormat {
+    Markdown,
+    Html,
+    Pdf,
+}
+
+impl OutputFormat {
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            OutputFormat::Markdown => "text/markdown",
+            OutputFormat::Html => "text/html",
+            OutputFormat::Pdf => "application/pdf",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            OutputFormat::Markdown => "md",
+            OutputFormat::Html => "html",
+            OutputFormat::Pdf => "pdf",
+        }
+    }
+}
+
+/// A template the organisation uses in place of the built-in one for its
+/// kind while it is active.
+///
+/// Templates are Mustache-style: `{{project.name}}`, `{{#tasks}}…{{/tasks}}`.
+/// The data available is described by `GET
+/// /api/document-templates/built-in/{kind}`, whose templates use all of it.
+#[derive(Debug, Serialize, Deserialize, FromRow, ToSchema, Clone)]
+pub struct DocumentTemplate {
+    pub id: Uuid,
+    pub kind: DocumentKind,
+    #[schema(example = "House style RSD")]
+    pub name: String,
+    pub format: TemplateFormat,
+    pub body: String,
+    /// Whether generation uses this template; at most one per kind
+    pub active: bool,
+    pub created_by: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub version: i32,
+}
+
+impl Versioned for DocumentTemplate {
+    fn version(&self) -> i32 {
+        self.version
+    }
+}
+
+/// The template a kind falls back to without an active one of the
+/// organisation's.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct BuiltInTemplate {
+    pub kind: DocumentKind,
+    pub format: TemplateFormat,
+    pub body: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Validate, ToSchema)]
+pub struct DocumentTemplateCreate {
+    pub kind: DocumentKind,
+    #[validate(length(min = 1, max = 255))]
+    pub name: String,
+    pub format: TemplateFormat,
+    #[validate(length(min = 1, max = 1048576))]
+    pub body: String,
+    /// Defaults to true, replacing the kind's active template
+    #[serde(default = "default_active")]
+    pub active: bool,
+}
+
+fn default_active() -> bool {
+    true
+}
+
+/// JSON Merge Patch for a template. Activating it deactivates the kind's
+/// other templates.
+#[derive(Debug, Serialize, Deserialize, Validate, ToSchema, Default)]
+#[serde(default)]
+pub struct DocumentTemplateUpdate {
+    #[validate(length(min = 1, max = 255))]
+    #[schema(value_type = Option<String>)]
+    pub name: Patch<String>,
+    #[schema(value_type = Option<TemplateFormat>)]
+    pub format: Patch<TemplateFormat>,
+    #[validate(length(min = 1, max = 1048576))]
+    #[schema(value_type = Option<String>)]
+    pub body: Patch<String>,
+    #[schema(value_type = Option<bool>)]
+    pub active: Patch<bool>,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct TemplateFilter {
+    pub kind: Option<DocumentKind>,
+}
+
+/// Renders a document for a project and stores it as the next version of
+/// the project's document of that kind and format.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct DocumentGenerate {
+    pub project_id: Uuid,
+    pub kind: DocumentKind,
+    pub format: OutputFormat,
+    /// Gate review to attach the document to, besides the transition into
+    /// the kind's phase
+    pub gate_review_id: Option<Uuid>,
+}
@@ -0,0 +1,270 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::models::patch::Patch;
+use crate::models::version::Versioned;
+
+/// Test levels of the Integration & Testing phase.
+#[derive(Debug, Serialize, Deserialize, sqlx::Type, ToSchema, PartialEq, Eq, Clone, Copy)]
+#[sqlx(type_name = "test_level", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum TestLevel {
+    Unit,
+    Integration,
+    System,
+    /// User acceptance testing
+    Acceptance,
+}
+
+#[derive(Debug, Serialize, Deserialize, sqlx::Type, ToSchema, PartialEq, Eq, Clone, Copy)]
+#[sqlx(type_name = "test_outcome", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum TestOutcome {
+    Passed,
+    Failed,
+    /// Could not be executed, e.g. because of an environment problem
+    Blocked,
+}
+
+#[derive(Debug, Serialize, Deserialize, FromRow, ToSchema, Clone)]
+pub struct TestSuite {
+    pub id: Uuid,
+    pub project_id: Uuid,
+    #[schema(example = "Invoicing system tests")]
+    pub name: String,
+    pub description: Option<String>,
+    pub level: TestLevel,
+    pub created_by: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub version: i32,
+}
+
+impl Versioned for TestSuite {
+    fn version(&self) -> i32 {
+        self.version
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Validate, ToSchema)]
+pub struct TestSuiteCreate {
+    pub project_id: Uuid,
+    #[validate(length(min = 1, max = 255))]
+    pub name: String,
+    pub description: Option<String>,
+    pub level: TestLevel,
+}
+
+/// JSON Merge Patch for a test suite.
+#[derive(Debug, Serialize, Deserialize, Validate, ToSchema, Default)]
+#[serde(default)]
+pub struct TestSuiteUpdate {
+    #[validate(length(min = 1, max = 255))]
+    #[schema(value_type = Option<String>)]
+    pub name: Patch<String>,
+    #[schema(value_type = Option<String>)]
+    pub description: Patch<String>,
+    #[schema(value_type = Option<TestLevel>)]
+    pub level: Patch<TestLevel>,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct TestSuiteFilter {
+    pub project_id: Uuid,
+}
+
+#[derive(Debug, Serialize, Deserialize, Validate, ToSchema, PartialEq, Clone)]
+pub struct TestStep {
+    #[validate(length(min = 1, max = 10000))]
+    #[schema(example = "Submit the invoice form with a zero amount")]
+    pub action: String,
+    #[validate(length(min = 1, max = 10000))]
+    #[schema(example = "The form shows \"Amount must be positive\"")]
+    pub expected_result: String,
+}
+
+/// A test case and its steps, in order.
+#[derive(Debug, Serialize, Deserialize, ToSchema, Clone)]
+pub struct TestCase {
+    pub id: Uuid,
+    pub suite_id: Uuid,
+    pub project_id: Uuid,
+    #[schema(example = "Reject invoices without an amount")]
+    pub title: String,
+    pub description: Option<String>,
+    pub preconditions: Option<String>,
+    pub steps: Vec<TestStep>,
+    /// Outcome of the case as a whole
+    pub expected_result: Option<String>,
+    pub created_by: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub version: i32,
+}
+
+impl Versioned for TestCase {
+    fn version(&self) -> i32 {
+        self.version
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Validate, ToSchema)]
+pub struct TestCaseCreate {
+    pub suite_id: Uuid,
+    #[validate(length(min = 1, max = 255))]
+    pub title: String,
+    pub description: Option<String>,
+    pub preconditions: Option<String>,
+    #[serde(default)]
+    #[validate(nested)]
+    pub steps: Vec<TestStep>,
+    pub expected_result: Option<String>,
+}
+
+/// JSON Merge Patch for a test case. `steps` replaces all the steps.
+#[derive(Debug, Serialize, Deserialize, Validate, ToSchema, Default)]
+#[serde(default)]
+pub struct TestCaseUpdate {
+    #[validate(length(min = 1, max = 255))]
+    #[schema(value_type = Option<String>)]
+    pub title: Patch<String>,
+    #[schema(value_type = Option<String>)]
+    pub description: Patch<String>,
+    #[schema(value_type = Option<String>)]
+    pub preconditions: Patch<String>,
+    #[schema(value_type = Option<Vec<TestStep>>)]
+    pub steps: Patch<Vec<TestStep>>,
+    #[schema(value_type = Option<String>)]
+    pub expected_result: Patch<String>,
+}
+
+/// Test cases of a suite, or of a whole project.
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct TestCaseFilter {
+    pub project_id: Option<Uuid>,
+    pub suite_id: Option<Uuid>,
+}
+
+/// One execution of test cases against a build.
+#[derive(Debug, Serialize, Deserialize, FromRow, ToSchema, Clone)]
+pub struct TestRun {
+    pub id: Uuid,
+    pub project_id: Uuid,
+    #[schema(example = "2.3.0-rc1")]
+    pub build: String,
+    #[schema(example = "System test cycle 2")]
+    pub cycle: Option<String>,
+    pub notes: Option<String>,
+    pub created_by: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Validate, ToSchema)]
+pub struct TestRunCreate {
+    pub project_id: Uuid,
+    #[validate(length(min = 1, max = 255))]
+    pub build: String,
+    #[validate(length(min = 1, max = 255))]
+    pub cycle: Option<String>,
+    pub notes: Option<String>,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct TestRunFilter {
+    pub project_id: Uuid,
+    pub cycle: Option<String>,
+}
+
+/// The outcome of a test case in a run.
+#[derive(Debug, Serialize, Deserialize, FromRow, ToSchema, Clone)]
+pub struct TestResult {
+    pub run_id: Uuid,
+    pub test_case_id: Uuid,
+    pub outcome: TestOutcome,
+    pub notes: Option<String>,
+    /// Who executed the case
+    pub tester_id: Option<Uuid>,
+    pub executed_at: DateTime<Utc>,
+}
+
+/// Records the caller as the tester. Recording a case again in the same
+/// run replaces its result.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct TestResultRecord {
+    pub test_case_id: Uuid,
+    pub outcome: TestOutcome,
+    pub notes: Option<String>,
+}
+
+/// Outcomes of a set of test cases, counting each case once.
+#[derive(Debug, Serialize, Deserialize, ToSchema, PartialEq, Default, Clone)]
+pub struct TestTally {
+    pub test_cases: usize,
+    pub passed: usize,
+    pub failed: usize,
+    pub blocked: usize,
+    pub not_run: usize,
+    /// Percentage of executed cases that passed; `None` before any ran
+    pub pass_rate: Option<f64>,
+}
+
+impl TestTally {
+    pub fn count(outcomes: impl IntoIterator<Item = Option<TestOutcome>>) -> Self {
+        let mut tally = TestTally::default();
+        for outcome in outcomes {
+            tally.test_cases += 1;
+            match outcome {
+                Some(TestOutcome::Passed) => tally.passed += 1,
+                Some(TestOutcome::Failed) => tally.failed += 1,
+                Some(TestOutcome::Blocked) => tally.blocked += 1,
+                None => tally.not_run += 1,
+            }
+        }
+        let executed = tally.passed + tally.failed + tally.blocked;
+        if executed > 0 {
+            let rate = tally.passed as f64 * 100.0 / executed as f64;
+            tally.pass_rate = Some((rate * 10.0).round() / 10.0);
+        }
+        tally
+    }
+}
+
+/// A run with its results.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct TestRunDetails {
+    #[serde(flatten)]
+    pub run: TestRun,
+    /// Over the cases executed in this run
+    pub summary: TestTally,
+    pub results: Vec<TestResult>,
+}
+
+/// How well one requirement is tested.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct RequirementCoverage {
+    pub requirement_id: Uuid,
+    #[schema(example = "REQ-001")]
+    pub code: String,
+    pub title: String,
+    /// No test case traces to the requirement
+    pub untested: bool,
+    /// Over the test cases traced to the requirement
+    #[serde(flatten)]
+    pub tally: TestTally,
+}
+
+/// Pass rate of a project's test cases, each by its latest result, overall
+/// and per requirement.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct TestReport {
+    pub project_id: Uuid,
+    pub overall: TestTally,
+    pub requirements: Vec<RequirementCoverage>,
+}
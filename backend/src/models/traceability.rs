@@ -0,0 +1,165 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::models::patch::Patch;
+use crate::models::requirement::{RequirementPriority, RequirementStatus};
+use crate::models::version::Versioned;
+
+/// Part of a project's design, traced from the requirements it realises to
+/// the tasks that build it.
+#[derive(Debug, Serialize, Deserialize, FromRow, ToSchema, Clone)]
+pub struct DesignItem {
+    pub id: Uuid,
+    pub project_id: Uuid,
+    #[schema(example = "Password reset token service")]
+    pub title: String,
+    pub description: Option<String>,
+    pub created_by: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub version: i32,
+}
+
+impl Versioned for DesignItem {
+    fn version(&self) -> i32 {
+        self.version
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Validate, ToSchema)]
+pub struct DesignItemCreate {
+    pub project_id: Uuid,
+    #[validate(length(min = 1, max = 255))]
+    pub title: String,
+    pub description: Option<String>,
+}
+
+/// JSON Merge Patch for a design item.
+#[derive(Debug, Serialize, Deserialize, Validate, ToSchema, Default)]
+#[serde(default)]
+pub struct DesignItemUpdate {
+    #[validate(length(min = 1, max = 255))]
+    #[schema(value_type = Option<String>)]
+    pub title: Patch<String>,
+    #[schema(value_type = Option<String>)]
+    pub description: Patch<String>,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct DesignItemFilter {
+    pub project_id: Uuid,
+}
+
+/// Records that take part in traceability.
+#[derive(Debug, Serialize, Deserialize, sqlx::Type, ToSchema, PartialEq, Eq, Hash, Clone, Copy)]
+#[sqlx(type_name = "trace_entity", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum TraceEntity {
+    Requirement,
+    DesignItem,
+    Task,
+    TestCase,
+}
+
+impl TraceEntity {
+    pub fn label(&self) -> &'static str {
+        match self {
+            TraceEntity::Requirement => "Requirement",
+            TraceEntity::DesignItem => "Design item",
+            TraceEntity::Task => "Task",
+            TraceEntity::TestCase => "Test case",
+        }
+    }
+
+    /// Whether a link may lead from `self` to `target`: a requirement to
+    /// the design items and tasks that realise it, a design item to the
+    /// tasks that build it, and a requirement or task to the test cases
+    /// that verify it.
+    pub fn may_trace_to(&self, target: TraceEntity) -> bool {
+        matches!(
+            (self, target),
+            (TraceEntity::Requirement, TraceEntity::DesignItem)
+                | (TraceEntity::Requirement, TraceEntity::Task)
+                | (TraceEntity::DesignItem, TraceEntity::Task)
+                | (TraceEntity::Requirement, TraceEntity::TestCase)
+                | (TraceEntity::Task, TraceEntity::TestCase)
+        )
+    }
+}
+
+/// A link from a record to one that realises it, one step down the chain
+/// requirement → design item → task → test case.
+#[derive(Debug, Serialize, Deserialize, FromRow, ToSchema, Clone)]
+pub struct TraceLink {
+    pub id: Uuid,
+    pub project_id: Uuid,
+    pub source_type: TraceEntity,
+    pub source_id: Uuid,
+    pub target_type: TraceEntity,
+    pub target_id: Uuid,
+    pub created_by: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct TraceLinkCreate {
+    pub source_type: TraceEntity,
+    pub source_id: Uuid,
+    pub target_type: TraceEntity,
+    pub target_id: Uuid,
+}
+
+/// Links of a project, or those from or to one of its records.
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct TraceLinkFilter {
+    pub project_id: Option<Uuid>,
+    pub entity_type: Option<TraceEntity>,
+    pub entity_id: Option<Uuid>,
+}
+
+/// A record a requirement traces to.
+#[derive(Debug, Serialize, Deserialize, ToSchema, PartialEq, Clone)]
+pub struct TraceRef {
+    pub id: Uuid,
+    /// The task's WBS code, blank for other records
+    pub wbs: Option<String>,
+    pub title: String,
+}
+
+/// One requirement's row of the traceability matrix.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct TraceabilityRow {
+    pub requirement_id: Uuid,
+    #[schema(example = "REQ-001")]
+    pub code: String,
+    pub title: String,
+    pub priority: RequirementPriority,
+    pub status: RequirementStatus,
+    pub design_items: Vec<TraceRef>,
+    /// Tasks linked to the requirement directly or through a design item
+    pub tasks: Vec<TraceRef>,
+    /// Test cases linked to the requirement directly or through its tasks
+    pub test_cases: Vec<TraceRef>,
+    /// No task implements the requirement
+    pub untasked: bool,
+    /// No test case covers the requirement
+    pub untested: bool,
+}
+
+/// Every requirement of a project, except rejected ones, with what
+/// realises it.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct TraceabilityMatrix {
+    pub project_id: Uuid,
+    pub rows: Vec<TraceabilityRow>,
+    /// Number of rows flagged `untasked`
+    pub untasked: usize,
+    /// Number of rows flagged `untested`
+    pub untested: usize,
+}
@@ -1,22 +1,35 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
-use utoipa::ToSchema;
+use utoipa::{IntoParams, ToSchema};
 use uuid::Uuid;
 use validator::Validate;
 
+use crate::models::patch::Patch;
+use crate::models::version::Versioned;
+
 #[derive(Debug, Serialize, Deserialize, FromRow, ToSchema, PartialEq)]
 pub struct User {
     #[schema(example = "550e8400-e29b-41d4-a716-446655440000")]
     pub id: Uuid,
     #[schema(example = "john.doe@example.com")]
     pub email: String,
-    pub password_hash: String,
+    #[serde(skip_serializing)]
+    pub password_hash: Option<String>,
     #[schema(example = "John Doe")]
     pub full_name: String,
     pub role: UserRole,
+    pub deactivated_at: Option<DateTime<Utc>>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
+    /// Bumped on every change; sent as the `ETag` and checked against `If-Match`
+    pub version: i32,
+}
+
+impl Versioned for User {
+    fn version(&self) -> i32 {
+        self.version
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, sqlx::Type, ToSchema, PartialEq, Clone)]
@@ -28,6 +41,14 @@ pub enum UserRole {
     QaEngineer,
 }
 
+impl UserRole {
+    /// Admins and project managers see every project; other roles only see
+    /// projects they own or have tasks assigned in.
+    pub fn sees_all_projects(&self) -> bool {
+        matches!(self, UserRole::Admin | UserRole::ProjectManager)
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, Validate, ToSchema, Clone)]
 pub struct UserCreate {
     #[validate(email)]
@@ -41,15 +62,75 @@ pub struct UserCreate {
     pub role: UserRole,
 }
 
-#[derive(Debug, Serialize, Deserialize, Validate, ToSchema)]
+/// JSON Merge Patch for a user. Every field is required, so `null` is
+/// rejected like any other invalid value.
+#[derive(Debug, Default, Serialize, Deserialize, Validate, ToSchema)]
+#[serde(default)]
 pub struct UserUpdate {
     #[validate(email)]
-    #[schema(example = "john.doe@example.com")]
-    pub email: Option<String>,
+    #[schema(value_type = Option<String>, example = "john.doe@example.com")]
+    pub email: Patch<String>,
     #[validate(length(min = 8))]
-    pub password: Option<String>,
+    #[schema(value_type = Option<String>)]
+    pub password: Patch<String>,
+    #[validate(length(min = 1, max = 255))]
+    #[schema(value_type = Option<String>, example = "John Doe")]
+    pub full_name: Patch<String>,
+    #[schema(value_type = Option<UserRole>)]
+    pub role: Patch<UserRole>,
+}
+
+/// Filters accepted by `GET /api/users`.
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct UserFilter {
+    pub role: Option<UserRole>,
+    /// Only active (true) or deactivated (false) users
+    pub active: Option<bool>,
+}
+
+pub const USER_SORT_FIELDS: &[&str] = &["email", "full_name", "role", "created_at", "updated_at"];
+
+#[derive(Debug, Serialize, Deserialize, FromRow, ToSchema)]
+pub struct Invitation {
+    pub id: Uuid,
+    #[schema(example = "jane.doe@example.com")]
+    pub email: String,
+    pub full_name: String,
+    pub role: UserRole,
+    pub invited_by: Uuid,
+    pub expires_at: DateTime<Utc>,
+    pub accepted_at: Option<DateTime<Utc>>,
+    pub revoked_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Validate, ToSchema)]
+pub struct InvitationCreate {
+    #[validate(email)]
+    #[schema(example = "jane.doe@example.com")]
+    pub email: String,
+    #[validate(length(min = 1, max = 255))]
+    #[schema(example = "Jane Doe")]
+    pub full_name: String,
+    pub role: UserRole,
+}
+
+#[derive(Debug, Serialize, Deserialize, Validate, ToSchema)]
+pub struct InvitationAccept {
+    pub token: String,
+    #[validate(length(min = 8))]
+    pub password: String,
+}
+
+/// Self-service changes to the caller's own account. Changing the password
+/// requires the current one.
+#[derive(Debug, Serialize, Deserialize, Validate, ToSchema)]
+pub struct ProfileUpdate {
     #[validate(length(min = 1, max = 255))]
     #[schema(example = "John Doe")]
     pub full_name: Option<String>,
-    pub role: Option<UserRole>,
+    pub current_password: Option<String>,
+    #[validate(length(min = 8))]
+    pub new_password: Option<String>,
 }
@@ -0,0 +1,28 @@
+use serde::Serialize;
+
+use crate::errors::ServiceError;
+
+/// Records guarded by optimistic concurrency control. `version` starts at 1
+/// and is bumped by every update, so it doubles as the record's ETag.
+pub trait Versioned: Serialize {
+    fn version(&self) -> i32;
+
+    /// Fails with `PreconditionFailed`, carrying the current state, when the
+    /// caller expected a different version. `None` means the write is
+    /// unconditional.
+    fn check_version(&self, expected: Option<i32>) -> Result<(), ServiceError> {
+        match expected {
+            Some(expected) if expected != self.version() => {
+                let current = serde_json::to_value(self).map_err(|e| {
+                    log::error!("Failed to serialize current state: {:?}", e);
+                    ServiceError::InternalServerError
+                })?;
+                Err(ServiceError::PreconditionFailed {
+                    version: self.version(),
+                    current,
+                })
+            }
+            _ => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,173 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::models::patch::Patch;
+
+/// Events a webhook can subscribe to.
+#[derive(Debug, Serialize, Deserialize, sqlx::Type, ToSchema, PartialEq, Eq, Clone, Copy)]
+#[sqlx(type_name = "webhook_event")]
+pub enum WebhookEvent {
+    #[sqlx(rename = "project.created")]
+    #[serde(rename = "project.created")]
+    ProjectCreated,
+    #[sqlx(rename = "project.updated")]
+    #[serde(rename = "project.updated")]
+    ProjectUpdated,
+    /// The project was moved to the trash
+    #[sqlx(rename = "project.deleted")]
+    #[serde(rename = "project.deleted")]
+    ProjectDeleted,
+    /// The project came back out of the trash
+    #[sqlx(rename = "project.restored")]
+    #[serde(rename = "project.restored")]
+    ProjectRestored,
+    #[sqlx(rename = "project.phase_changed")]
+    #[serde(rename = "project.phase_changed")]
+    ProjectPhaseChanged,
+    #[sqlx(rename = "task.created")]
+    #[serde(rename = "task.created")]
+    TaskCreated,
+    #[sqlx(rename = "task.updated")]
+    #[serde(rename = "task.updated")]
+    TaskUpdated,
+    /// The task was moved to the trash, on its own or with its project
+    #[sqlx(rename = "task.deleted")]
+    #[serde(rename = "task.deleted")]
+    TaskDeleted,
+    /// The task came back out of the trash, on its own or with its project
+    #[sqlx(rename = "task.restored")]
+    #[serde(rename = "task.restored")]
+    TaskRestored,
+    /// The task passed its end date without being completed; sent once per
+    /// end date by the nightly overdue check
+    #[sqlx(rename = "task.overdue")]
+    #[serde(rename = "task.overdue")]
+    TaskOverdue,
+}
+
+impl WebhookEvent {
+    /// Name used in payloads and the `X-Webhook-Event` header.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WebhookEvent::ProjectCreated => "project.created",
+            WebhookEvent::ProjectUpdated => "project.updated",
+            WebhookEvent::ProjectDeleted => "project.deleted",
+            WebhookEvent::ProjectRestored => "project.restored",
+            WebhookEvent::ProjectPhaseChanged => "project.phase_changed",
+            WebhookEvent::TaskCreated => "task.created",
+            WebhookEvent::TaskUpdated => "task.updated",
+            WebhookEvent::TaskDeleted => "task.deleted",
+            WebhookEvent::TaskRestored => "task.restored",
+            WebhookEvent::TaskOverdue => "task.overdue",
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, sqlx::Type, ToSchema, PartialEq, Eq, Clone, Copy)]
+#[sqlx(type_name = "webhook_delivery_status", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum DeliveryStatus {
+    /// Not sent yet, including failed attempts that will be retried
+    Pending,
+    Succeeded,
+    /// Gave up after the last retry
+    Failed,
+}
+
+/// A webhook subscription. The signing secret is only shown when the
+/// webhook is created.
+#[derive(Debug, Serialize, Deserialize, FromRow, ToSchema)]
+pub struct Webhook {
+    pub id: Uuid,
+    #[schema(example = "https://erp.example.com/hooks/waterfall")]
+    pub url: String,
+    pub events: Vec<WebhookEvent>,
+    pub description: Option<String>,
+    /// Inactive webhooks receive no new deliveries
+    pub active: bool,
+    pub created_by: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Validate, ToSchema)]
+pub struct WebhookCreate {
+    /// `http` or `https` URL that receives a POST per event
+    #[schema(example = "https://erp.example.com/hooks/waterfall")]
+    pub url: String,
+    /// Key for the HMAC-SHA256 signature; generated when omitted
+    #[validate(length(min = 16, max = 255))]
+    pub secret: Option<String>,
+    #[validate(length(min = 1, message = "subscribe to at least one event"))]
+    pub events: Vec<WebhookEvent>,
+    #[validate(length(max = 255))]
+    pub description: Option<String>,
+    #[serde(default = "default_active")]
+    pub active: bool,
+}
+
+fn default_active() -> bool {
+    true
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct WebhookCreated {
+    #[serde(flatten)]
+    pub webhook: Webhook,
+    /// Verify deliveries against this; it cannot be read back later
+    pub secret: String,
+}
+
+/// JSON Merge Patch for a webhook. A new `secret` takes effect for the next
+/// attempt, including retries of earlier deliveries.
+#[derive(Debug, Serialize, Deserialize, ToSchema, Default)]
+#[serde(default)]
+pub struct WebhookUpdate {
+    #[schema(value_type = Option<String>)]
+    pub url: Patch<String>,
+    #[schema(value_type = Option<String>)]
+    pub secret: Patch<String>,
+    #[schema(value_type = Option<Vec<WebhookEvent>>)]
+    pub events: Patch<Vec<WebhookEvent>>,
+    #[schema(value_type = Option<String>)]
+    pub description: Patch<String>,
+    #[schema(value_type = Option<bool>)]
+    pub active: Patch<bool>,
+}
+
+/// One attempt series to send an event to a webhook.
+#[derive(Debug, Serialize, Deserialize, FromRow, ToSchema)]
+pub struct WebhookDelivery {
+    pub id: Uuid,
+    pub webhook_id: Uuid,
+    pub event: WebhookEvent,
+    /// The request body, exactly as signed
+    #[schema(value_type = Object)]
+    pub payload: serde_json::Value,
+    pub status: DeliveryStatus,
+    pub attempts: i32,
+    pub next_attempt_at: DateTime<Utc>,
+    /// HTTP status of the last attempt, if the receiver answered
+    pub response_status: Option<i32>,
+    /// Start of the receiver's last response body
+    pub response_body: Option<String>,
+    pub last_error: Option<String>,
+    pub delivered_at: Option<DateTime<Utc>>,
+    /// Delivery this one repeats, when sent again by hand
+    pub redelivery_of: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Filters accepted by `GET /api/webhooks/{id}/deliveries`.
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct DeliveryFilter {
+    pub status: Option<DeliveryStatus>,
+    pub event: Option<WebhookEvent>,
+}
+
+pub const DELIVERY_SORT_FIELDS: &[&str] = &["created_at", "next_attempt_at", "status", "event"];
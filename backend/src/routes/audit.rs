@@ -0,0 +1,66 @@
+use crate::errors::ServiceError;
+use crate::extractors::auth::AuthenticatedUser;
+use crate::models::audit::{AuditEntity, AuditEntry, AuditFilter};
+use crate::models::pagination::{Page, PageParams};
+use crate::services::audit_service::AuditService;
+use actix_web::{get, web, HttpRequest, HttpResponse};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+pub fn config(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/audit")
+            .service(list_audit_entries)
+            .service(get_entity_history),
+    );
+}
+
+/// Search the audit log
+#[utoipa::path(
+    get,
+    path = "/api/audit",
+    params(PageParams, AuditFilter),
+    responses(
+        (status = 200, description = "Page of audit entries", body = Page<AuditEntry>),
+        (status = 403, description = "Admin role required")
+    )
+)]
+#[get("")]
+pub async fn list_audit_entries(
+    req: HttpRequest,
+    auth_user: AuthenticatedUser,
+    params: web::Query<PageParams>,
+    filter: web::Query<AuditFilter>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, ServiceError> {
+    auth_user.require_admin()?;
+    let entries = AuditService::list(&params, &filter, &pool)
+        .await?
+        .with_links(req.path(), req.query_string());
+    Ok(HttpResponse::Ok().json(entries))
+}
+
+/// Change history of a single record, oldest first
+#[utoipa::path(
+    get,
+    path = "/api/audit/{entity_type}/{entity_id}",
+    params(
+        ("entity_type" = AuditEntity, Path, description = "Kind of record"),
+        ("entity_id" = Uuid, Path, description = "Record ID")
+    ),
+    responses(
+        (status = 200, description = "Audit entries for the record", body = Vec<AuditEntry>),
+        (status = 403, description = "Admin role required")
+    )
+)]
+#[get("/{entity_type}/{entity_id}")]
+pub async fn get_entity_history(
+    auth_user: AuthenticatedUser,
+    path: web::Path<(AuditEntity, Uuid)>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, ServiceError> {
+    auth_user.require_admin()?;
+    let (entity, entity_id) = path.into_inner();
+    let history = AuditService::history(entity, entity_id, &pool).await?;
+    Ok(HttpResponse::Ok().json(history))
+}
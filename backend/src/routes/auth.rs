@@ -1,8 +1,18 @@
-use crate::models::auth::{AuthResponse, LoginCredentials};
-use crate::models::user::UserCreate;
+use crate::errors::ServiceError;
+use crate::extractors::auth::AuthenticatedUser;
+use crate::middleware::request_id;
+use crate::models::audit::AuditContext;
+use crate::models::auth::{
+    AuthResponse, AuthSettings, AuthSettingsUpdate, LoginCredentials, OidcCallbackParams,
+};
+use crate::models::user::{InvitationAccept, UserCreate, UserRole};
 use crate::services::auth_service::AuthService;
-use actix_web::{post, web, HttpResponse, ResponseError};
+use crate::services::oidc_service::OidcService;
+use crate::services::token_service::TokenService;
+use crate::services::user_service::UserService;
+use actix_web::{get, http::header, post, put, web, HttpRequest, HttpResponse, ResponseError};
 use sqlx::PgPool;
+use validator::Validate;
 
 #[utoipa::path(
     post,
@@ -10,13 +20,27 @@ use sqlx::PgPool;
     request_body = UserCreate,
     responses(
         (status = 200, description = "User registered successfully", body = AuthResponse),
-        (status = 400, description = "Invalid input"),
+        (status = 422, description = "Validation failed"),
+        (status = 403, description = "Role cannot be self-assigned"),
         (status = 409, description = "User already exists")
     )
 )]
 #[post("/register")]
-pub async fn register(user_create: web::Json<UserCreate>, pool: web::Data<PgPool>) -> HttpResponse {
-    match AuthService::register(user_create.into_inner(), &pool).await {
+pub async fn register(
+    req: HttpRequest,
+    user_create: web::Json<UserCreate>,
+    tokens: web::Data<TokenService>,
+    pool: web::Data<PgPool>,
+) -> HttpResponse {
+    // Privileged roles are only granted through admin invitations
+    if !matches!(user_create.role, UserRole::Developer | UserRole::QaEngineer) {
+        return ServiceError::Forbidden.error_response();
+    }
+    if let Err(e) = user_create.validate() {
+        return ServiceError::from(e).error_response();
+    }
+    let audit = AuditContext::anonymous(request_id::current(&req));
+    match AuthService::register(user_create.into_inner(), &tokens, &audit, &pool).await {
         Ok(response) => HttpResponse::Ok().json(response),
         Err(e) => e.error_response(),
     }
@@ -34,14 +58,176 @@ pub async fn register(user_create: web::Json<UserCreate>, pool: web::Data<PgPool
 #[post("/login")]
 pub async fn login(
     credentials: web::Json<LoginCredentials>,
+    tokens: web::Data<TokenService>,
     pool: web::Data<PgPool>,
 ) -> HttpResponse {
-    match AuthService::login(credentials.into_inner(), &pool).await {
+    match AuthService::login(credentials.into_inner(), &tokens, &pool).await {
         Ok(response) => HttpResponse::Ok().json(response),
         Err(e) => e.error_response(),
     }
 }
 
+#[utoipa::path(
+    post,
+    path = "/api/auth/invitations/accept",
+    request_body = InvitationAccept,
+    responses(
+        (status = 200, description = "Account created", body = AuthResponse),
+        (status = 400, description = "Invalid or expired invitation")
+    )
+)]
+#[post("/invitations/accept")]
+pub async fn accept_invitation(
+    req: HttpRequest,
+    accept: web::Json<InvitationAccept>,
+    tokens: web::Data<TokenService>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, ServiceError> {
+    accept.validate().map_err(ServiceError::from)?;
+    let audit = AuditContext::anonymous(request_id::current(&req));
+    let user = UserService::accept_invitation(accept.into_inner(), &audit, &pool).await?;
+    Ok(HttpResponse::Ok().json(tokens.issue(&user)?))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/auth/oidc/login",
+    responses(
+        (status = 302, description = "Redirect to the identity provider"),
+        (status = 404, description = "Single sign-on is not configured")
+    )
+)]
+#[get("/oidc/login")]
+pub async fn oidc_login(
+    oidc: Option<web::Data<OidcService>>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, ServiceError> {
+    let oidc =
+        oidc.ok_or_else(|| ServiceError::NotFound("Single sign-on is not configured".into()))?;
+    let url = oidc.authorization_url(&pool).await?;
+    Ok(HttpResponse::Found()
+        .insert_header((header::LOCATION, url))
+        .finish())
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/auth/oidc/callback",
+    params(
+        ("code" = Option<String>, Query, description = "Authorization code"),
+        ("state" = String, Query, description = "Login state issued by /oidc/login")
+    ),
+    responses(
+        (status = 200, description = "Login successful", body = AuthResponse),
+        (status = 401, description = "Login rejected"),
+        (status = 502, description = "Identity provider error")
+    )
+)]
+#[get("/oidc/callback")]
+pub async fn oidc_callback(
+    req: HttpRequest,
+    params: web::Query<OidcCallbackParams>,
+    oidc: Option<web::Data<OidcService>>,
+    tokens: web::Data<TokenService>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, ServiceError> {
+    let oidc =
+        oidc.ok_or_else(|| ServiceError::NotFound("Single sign-on is not configured".into()))?;
+    let params = params.into_inner();
+
+    if let Some(error) = params.error {
+        let description = params.error_description.unwrap_or_default();
+        return Err(ServiceError::Unauthorized(format!(
+            "Identity provider returned {}: {}",
+            error, description
+        )));
+    }
+    let code = params
+        .code
+        .ok_or_else(|| ServiceError::BadRequest("Missing authorization code".into()))?;
+
+    let response = oidc
+        .complete_login(
+            &code,
+            &params.state,
+            &tokens,
+            &AuditContext::anonymous(request_id::current(&req)),
+            &pool,
+        )
+        .await?;
+    Ok(HttpResponse::Ok().json(response))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/auth/settings",
+    responses(
+        (status = 200, description = "Authentication settings", body = AuthSettings),
+        (status = 403, description = "Admin role required")
+    )
+)]
+#[get("/settings")]
+pub async fn get_settings(
+    auth_user: AuthenticatedUser,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, ServiceError> {
+    auth_user.require_admin()?;
+    let settings = AuthService::get_settings(&pool).await?;
+    Ok(HttpResponse::Ok().json(settings))
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/auth/settings",
+    request_body = AuthSettingsUpdate,
+    responses(
+        (status = 200, description = "Authentication settings updated", body = AuthSettings),
+        (status = 403, description = "Admin role required"),
+        (status = 422, description = "Password login cannot be disabled without single sign-on")
+    )
+)]
+#[put("/settings")]
+pub async fn update_settings(
+    auth_user: AuthenticatedUser,
+    update: web::Json<AuthSettingsUpdate>,
+    oidc: Option<web::Data<OidcService>>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, ServiceError> {
+    auth_user.require_admin()?;
+    let settings = AuthService::update_settings(
+        update.into_inner(),
+        oidc.is_some(),
+        &auth_user.audit(),
+        &pool,
+    )
+    .await?;
+    Ok(HttpResponse::Ok().json(settings))
+}
+
+/// Public keys for verifying tokens issued by this server
+#[utoipa::path(
+    get,
+    path = "/.well-known/jwks.json",
+    responses(
+        (status = 200, description = "JSON Web Key Set")
+    )
+)]
+#[get("/.well-known/jwks.json")]
+pub async fn jwks(tokens: web::Data<TokenService>) -> HttpResponse {
+    HttpResponse::Ok()
+        .insert_header((header::CACHE_CONTROL, "public, max-age=300"))
+        .json(tokens.jwks())
+}
+
 pub fn config(cfg: &mut web::ServiceConfig) {
-    cfg.service(web::scope("/auth").service(register).service(login));
+    cfg.service(
+        web::scope("/auth")
+            .service(register)
+            .service(login)
+            .service(accept_invitation)
+            .service(oidc_login)
+            .service(oidc_callback)
+            .service(get_settings)
+            .service(update_settings),
+    );
 }
@@ -0,0 +1,120 @@
+use crate::errors::ServiceError;
+use crate::extractors::auth::AuthenticatedUser;
+use crate::models::calendar::{CalendarFeed, CalendarFeedCreate, CalendarFeedCreated};
+use crate::services::calendar_service::CalendarService;
+use actix_web::http::header;
+use actix_web::{delete, get, post, web, HttpResponse};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+pub fn config(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/calendar")
+            .service(create_feed)
+            .service(list_feeds)
+            .service(revoke_feed)
+            .service(get_feed),
+    );
+}
+
+/// Create an iCalendar subscription
+///
+/// Without a `project_id` the feed lists the caller's assigned tasks and the
+/// gate reviews of their projects; with one, the whole project's schedule.
+/// The returned URL is only shown once.
+#[utoipa::path(
+    post,
+    path = "/api/calendar/feeds",
+    request_body(content = Option<CalendarFeedCreate>, content_type = "application/json"),
+    responses(
+        (status = 201, description = "Feed created", body = CalendarFeedCreated),
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "Project not found")
+    )
+)]
+#[post("/feeds")]
+pub async fn create_feed(
+    auth_user: AuthenticatedUser,
+    feed: Option<web::Json<CalendarFeedCreate>>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, ServiceError> {
+    let feed = CalendarService::create_feed(
+        feed.map(web::Json::into_inner).unwrap_or_default(),
+        auth_user.user_id,
+        &auth_user.role,
+        &auth_user.audit(),
+        &pool,
+    )
+    .await?;
+    Ok(HttpResponse::Created().json(feed))
+}
+
+/// List the caller's active calendar feeds
+#[utoipa::path(
+    get,
+    path = "/api/calendar/feeds",
+    responses(
+        (status = 200, description = "Active feeds", body = [CalendarFeed]),
+        (status = 401, description = "Unauthorized")
+    )
+)]
+#[get("/feeds")]
+pub async fn list_feeds(
+    auth_user: AuthenticatedUser,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, ServiceError> {
+    let feeds = CalendarService::list_feeds(auth_user.user_id, &pool).await?;
+    Ok(HttpResponse::Ok().json(feeds))
+}
+
+/// Revoke one of the caller's calendar feeds
+#[utoipa::path(
+    delete,
+    path = "/api/calendar/feeds/{id}",
+    params(("id" = Uuid, Path, description = "Feed ID")),
+    responses(
+        (status = 204, description = "Feed revoked"),
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "Feed not found")
+    )
+)]
+#[delete("/feeds/{id}")]
+pub async fn revoke_feed(
+    auth_user: AuthenticatedUser,
+    id: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, ServiceError> {
+    CalendarService::revoke_feed(
+        id.into_inner(),
+        auth_user.user_id,
+        &auth_user.audit(),
+        &pool,
+    )
+    .await?;
+    Ok(HttpResponse::NoContent().finish())
+}
+
+/// Fetch a calendar feed
+///
+/// Authenticated by the token in the URL, since calendar clients cannot send
+/// bearer tokens.
+#[utoipa::path(
+    get,
+    path = "/api/calendar/{token}.ics",
+    params(("token" = String, Path, description = "Feed token")),
+    responses(
+        (status = 200, description = "The feed", content_type = "text/calendar", body = String),
+        (status = 404, description = "Unknown or revoked feed")
+    )
+)]
+#[get("/{token}.ics")]
+pub async fn get_feed(
+    token: web::Path<String>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, ServiceError> {
+    let body = CalendarService::render_feed(&token, &pool).await?;
+    Ok(HttpResponse::Ok()
+        .content_type("text/calendar; charset=utf-8")
+        .insert_header((header::CACHE_CONTROL, "private, max-age=300"))
+        .body(body))
+}
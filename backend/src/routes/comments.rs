@@ -0,0 +1,228 @@
+use crate::errors::ServiceError;
+use crate::extractors::auth::AuthenticatedUser;
+use crate::extractors::precondition::{etag, IfMatch};
+use crate::models::comment::{
+    ActivityItem, Comment, CommentCreate, CommentFilter, CommentRevision, CommentTarget,
+    CommentUpdate,
+};
+use crate::services::comment_service::CommentService;
+use actix_web::{delete, get, post, route, web, HttpResponse};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+pub fn config(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/comments")
+            .service(create_comment)
+            .service(list_comments)
+            .service(get_comment)
+            .service(update_comment)
+            .service(delete_comment)
+            .service(list_revisions),
+    )
+    .service(get_activity);
+}
+
+/// Comment on a project, task, phase transition or gate review
+///
+/// Bodies are Markdown. `@` followed by a user's email address mentions
+/// them, and mentioned users who can see the project are notified.
+#[utoipa::path(
+    post,
+    path = "/api/comments",
+    request_body = CommentCreate,
+    responses(
+        (status = 201, description = "Comment added", body = Comment),
+        (status = 404, description = "Record not found"),
+        (status = 409, description = "The project is archived"),
+        (status = 422, description = "Empty body, or the parent is not on the same record")
+    )
+)]
+#[post("")]
+pub async fn create_comment(
+    auth_user: AuthenticatedUser,
+    comment: web::Json<CommentCreate>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, ServiceError> {
+    let comment = CommentService::create(
+        comment.into_inner(),
+        auth_user.user_id,
+        &auth_user.role,
+        &auth_user.audit(),
+        &pool,
+    )
+    .await?;
+    Ok(HttpResponse::Created()
+        .insert_header(etag(comment.version))
+        .json(comment))
+}
+
+/// List the comments on a record, oldest first
+#[utoipa::path(
+    get,
+    path = "/api/comments",
+    params(CommentFilter),
+    responses(
+        (status = 200, description = "Comments, with deleted ones left empty", body = [Comment]),
+        (status = 404, description = "Record not found")
+    )
+)]
+#[get("")]
+pub async fn list_comments(
+    auth_user: AuthenticatedUser,
+    filter: web::Query<CommentFilter>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, ServiceError> {
+    let comments = CommentService::list(
+        filter.target_type,
+        filter.target_id,
+        auth_user.user_id,
+        &auth_user.role,
+        &pool,
+    )
+    .await?;
+    Ok(HttpResponse::Ok().json(comments))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/comments/{id}",
+    params(("id" = Uuid, Path, description = "Comment ID")),
+    responses(
+        (status = 200, description = "The comment", body = Comment),
+        (status = 404, description = "Comment not found")
+    )
+)]
+#[get("/{id}")]
+pub async fn get_comment(
+    auth_user: AuthenticatedUser,
+    id: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, ServiceError> {
+    let comment =
+        CommentService::get(id.into_inner(), auth_user.user_id, &auth_user.role, &pool).await?;
+    Ok(HttpResponse::Ok()
+        .insert_header(etag(comment.version))
+        .json(comment))
+}
+
+/// Edit your own comment. The previous body is kept as a revision.
+#[utoipa::path(
+    method(put, patch),
+    path = "/api/comments/{id}",
+    params(("id" = Uuid, Path, description = "Comment ID")),
+    request_body = CommentUpdate,
+    responses(
+        (status = 200, description = "Updated comment", body = Comment),
+        (status = 403, description = "Only the author can edit a comment"),
+        (status = 404, description = "Comment not found"),
+        (status = 412, description = "The comment changed since it was read")
+    )
+)]
+#[route("/{id}", method = "PUT", method = "PATCH")]
+pub async fn update_comment(
+    auth_user: AuthenticatedUser,
+    id: web::Path<Uuid>,
+    if_match: IfMatch,
+    update: web::Json<CommentUpdate>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, ServiceError> {
+    let comment = CommentService::update(
+        id.into_inner(),
+        update.into_inner(),
+        if_match.0,
+        auth_user.user_id,
+        &auth_user.role,
+        &auth_user.audit(),
+        &pool,
+    )
+    .await?;
+    Ok(HttpResponse::Ok()
+        .insert_header(etag(comment.version))
+        .json(comment))
+}
+
+/// Delete a comment. Its replies stay in the thread.
+#[utoipa::path(
+    delete,
+    path = "/api/comments/{id}",
+    params(("id" = Uuid, Path, description = "Comment ID")),
+    responses(
+        (status = 204, description = "Comment deleted"),
+        (status = 403, description = "Only the author or an admin can delete a comment"),
+        (status = 404, description = "Comment not found"),
+        (status = 412, description = "The comment changed since it was read")
+    )
+)]
+#[delete("/{id}")]
+pub async fn delete_comment(
+    auth_user: AuthenticatedUser,
+    id: web::Path<Uuid>,
+    if_match: IfMatch,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, ServiceError> {
+    CommentService::delete(
+        id.into_inner(),
+        if_match.0,
+        auth_user.user_id,
+        &auth_user.role,
+        &auth_user.audit(),
+        &pool,
+    )
+    .await?;
+    Ok(HttpResponse::NoContent().finish())
+}
+
+/// Edit history of a comment, oldest body first
+#[utoipa::path(
+    get,
+    path = "/api/comments/{id}/revisions",
+    params(("id" = Uuid, Path, description = "Comment ID")),
+    responses(
+        (status = 200, description = "Replaced bodies", body = [CommentRevision]),
+        (status = 404, description = "Comment not found")
+    )
+)]
+#[get("/{id}/revisions")]
+pub async fn list_revisions(
+    auth_user: AuthenticatedUser,
+    id: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, ServiceError> {
+    let revisions =
+        CommentService::revisions(id.into_inner(), auth_user.user_id, &auth_user.role, &pool)
+            .await?;
+    Ok(HttpResponse::Ok().json(revisions))
+}
+
+/// Activity timeline of a record: its recorded changes and its comments,
+/// oldest first
+#[utoipa::path(
+    get,
+    path = "/api/activity/{target_type}/{target_id}",
+    params(
+        ("target_type" = CommentTarget, Path, description = "Kind of record"),
+        ("target_id" = Uuid, Path, description = "Record ID")
+    ),
+    responses(
+        (status = 200, description = "Changes and comments", body = [ActivityItem]),
+        (status = 404, description = "Record not found")
+    )
+)]
+#[get("/activity/{target_type}/{target_id}")]
+pub async fn get_activity(
+    auth_user: AuthenticatedUser,
+    path: web::Path<(CommentTarget, Uuid)>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, ServiceError> {
+    let (target_type, target_id) = path.into_inner();
+    let activity = CommentService::activity(
+        target_type,
+        target_id,
+        auth_user.user_id,
+        &auth_user.role,
+        &pool,
+    )
+    .await?;
+    Ok(HttpResponse::Ok().json(activity))
+}
@@ -0,0 +1,167 @@
+use crate::errors::ServiceError;
+use crate::extractors::auth::AuthenticatedUser;
+use crate::extractors::precondition::{etag, IfMatch};
+use crate::models::defect::{Defect, DefectCreate, DefectFilter, DefectUpdate};
+use crate::services::defect_service::DefectService;
+use actix_web::{delete, get, post, route, web, HttpResponse};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+pub fn config(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/defects")
+            .service(create_defect)
+            .service(list_defects)
+            .service(get_defect)
+            .service(update_defect)
+            .service(delete_defect),
+    );
+}
+
+/// Raise a defect
+///
+/// Given a test run, the test case must have failed in it.
+#[utoipa::path(
+    post,
+    path = "/api/defects",
+    request_body = DefectCreate,
+    responses(
+        (status = 201, description = "Defect raised", body = Defect),
+        (status = 404, description = "Project not found"),
+        (status = 409, description = "The project is archived"),
+        (status = 422, description = "A linked record is not in the project, or the test did not fail")
+    )
+)]
+#[post("")]
+pub async fn create_defect(
+    auth_user: AuthenticatedUser,
+    defect: web::Json<DefectCreate>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, ServiceError> {
+    let defect = DefectService::create(
+        defect.into_inner(),
+        auth_user.user_id,
+        &auth_user.role,
+        &auth_user.audit(),
+        &pool,
+    )
+    .await?;
+    Ok(HttpResponse::Created()
+        .insert_header(etag(defect.version))
+        .json(defect))
+}
+
+/// List a project's defects, most severe first
+#[utoipa::path(
+    get,
+    path = "/api/defects",
+    params(DefectFilter),
+    responses(
+        (status = 200, description = "Defects", body = [Defect]),
+        (status = 404, description = "Project not found")
+    )
+)]
+#[get("")]
+pub async fn list_defects(
+    auth_user: AuthenticatedUser,
+    filter: web::Query<DefectFilter>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, ServiceError> {
+    let defects = DefectService::list(&filter, auth_user.user_id, &auth_user.role, &pool).await?;
+    Ok(HttpResponse::Ok().json(defects))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/defects/{id}",
+    params(("id" = Uuid, Path, description = "Defect ID")),
+    responses(
+        (status = 200, description = "The defect", body = Defect),
+        (status = 404, description = "Defect not found")
+    )
+)]
+#[get("/{id}")]
+pub async fn get_defect(
+    auth_user: AuthenticatedUser,
+    id: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, ServiceError> {
+    let defect =
+        DefectService::get(id.into_inner(), auth_user.user_id, &auth_user.role, &pool).await?;
+    Ok(HttpResponse::Ok()
+        .insert_header(etag(defect.version))
+        .json(defect))
+}
+
+/// Update a defect
+///
+/// Status moves along new → triaged → fixing → verified → closed. A fix
+/// that does not hold goes from verified back to fixing; a new or triaged
+/// report that is not a defect may be closed; a closed defect is reopened
+/// as triaged.
+#[utoipa::path(
+    method(put, patch),
+    path = "/api/defects/{id}",
+    params(("id" = Uuid, Path, description = "Defect ID")),
+    request_body = DefectUpdate,
+    responses(
+        (status = 200, description = "Updated defect", body = Defect),
+        (status = 404, description = "Defect not found"),
+        (status = 409, description = "The project is archived"),
+        (status = 412, description = "The defect changed since it was read"),
+        (status = 422, description = "Status change outside the workflow")
+    )
+)]
+#[route("/{id}", method = "PUT", method = "PATCH")]
+pub async fn update_defect(
+    auth_user: AuthenticatedUser,
+    id: web::Path<Uuid>,
+    if_match: IfMatch,
+    update: web::Json<DefectUpdate>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, ServiceError> {
+    let defect = DefectService::update(
+        id.into_inner(),
+        update.into_inner(),
+        if_match.0,
+        auth_user.user_id,
+        &auth_user.role,
+        &auth_user.audit(),
+        &pool,
+    )
+    .await?;
+    Ok(HttpResponse::Ok()
+        .insert_header(etag(defect.version))
+        .json(defect))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/defects/{id}",
+    params(("id" = Uuid, Path, description = "Defect ID")),
+    responses(
+        (status = 204, description = "Defect deleted"),
+        (status = 403, description = "Only the reporter, an admin or a project manager can delete a defect"),
+        (status = 404, description = "Defect not found"),
+        (status = 409, description = "The project is archived"),
+        (status = 412, description = "The defect changed since it was read")
+    )
+)]
+#[delete("/{id}")]
+pub async fn delete_defect(
+    auth_user: AuthenticatedUser,
+    id: web::Path<Uuid>,
+    if_match: IfMatch,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, ServiceError> {
+    DefectService::delete(
+        id.into_inner(),
+        if_match.0,
+        auth_user.user_id,
+        &auth_user.role,
+        &auth_user.audit(),
+        &pool,
+    )
+    .await?;
+    Ok(HttpResponse::NoContent().finish())
+}
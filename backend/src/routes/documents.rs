@@ -0,0 +1,431 @@
+use crate::errors::ServiceError;
+use crate::extractors::auth::AuthenticatedUser;
+use crate::extractors::precondition::{etag, IfMatch};
+use crate::models::document::{
+    Document, DocumentFilter, DocumentLink, DocumentLinkCreate, DocumentTarget, DocumentUpdate,
+    DocumentUpload, DocumentVersion, DownloadParams, VersionUpload,
+};
+use crate::models::template::DocumentGenerate;
+use crate::models::user::UserRole;
+use crate::services::document_service::{DocumentService, MAX_DOCUMENT_BYTES};
+use crate::services::storage_service::DocumentStorage;
+use crate::services::template_service::TemplateService;
+use actix_web::http::header;
+use actix_web::{delete, get, post, route, web, HttpRequest, HttpResponse};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+pub fn config(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/documents")
+            .app_data(web::PayloadConfig::new(MAX_DOCUMENT_BYTES))
+            .service(upload_document)
+            .service(generate_document)
+            .service(list_documents)
+            .service(get_document)
+            .service(update_document)
+            .service(delete_document)
+            .service(upload_version)
+            .service(list_versions)
+            .service(download_document)
+            .service(list_links)
+            .service(link_document)
+            .service(unlink_document),
+    );
+}
+
+fn content_type(req: &HttpRequest) -> Option<&str> {
+    req.headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+}
+
+/// Upload a document to a project
+///
+/// The request body is the file itself and `Content-Type` its type. Files
+/// may be up to 25 MiB; common office, PDF, image and text formats are
+/// accepted.
+#[utoipa::path(
+    post,
+    path = "/api/documents",
+    params(DocumentUpload),
+    request_body(content = Vec<u8>, content_type = "application/octet-stream", description = "File content, sent with its own content type"),
+    responses(
+        (status = 201, description = "Document stored as version 1", body = Document),
+        (status = 404, description = "Project not found"),
+        (status = 409, description = "The project is archived"),
+        (status = 413, description = "File too large"),
+        (status = 422, description = "Empty file, type not accepted, or invalid link")
+    )
+)]
+#[post("")]
+pub async fn upload_document(
+    req: HttpRequest,
+    auth_user: AuthenticatedUser,
+    params: web::Query<DocumentUpload>,
+    body: web::Bytes,
+    storage: web::Data<dyn DocumentStorage>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, ServiceError> {
+    let document = DocumentService::upload(
+        params.into_inner(),
+        content_type(&req),
+        body,
+        auth_user.user_id,
+        &auth_user.role,
+        &auth_user.audit(),
+        storage.get_ref(),
+        &pool,
+    )
+    .await?;
+    Ok(HttpResponse::Created()
+        .insert_header(etag(document.version))
+        .json(document))
+}
+
+/// Generate a document from a project's records
+///
+/// Renders the organisation's active template for the kind, or the built-in
+/// one, and stores the result as the next version of the project's
+/// generated document of that kind and format. The document is linked to
+/// the project's transition into the kind's phase and to the gate review,
+/// if given.
+#[utoipa::path(
+    post,
+    path = "/api/documents/generate",
+    request_body = DocumentGenerate,
+    responses(
+        (status = 201, description = "Generated document with its new version", body = Document),
+        (status = 403, description = "Admin or project manager role required"),
+        (status = 404, description = "Project not found"),
+        (status = 409, description = "The project is archived"),
+        (status = 422, description = "Unknown gate review, or a format the template cannot render to")
+    )
+)]
+#[post("/generate")]
+pub async fn generate_document(
+    auth_user: AuthenticatedUser,
+    request: web::Json<DocumentGenerate>,
+    storage: web::Data<dyn DocumentStorage>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, ServiceError> {
+    if !matches!(auth_user.role, UserRole::Admin | UserRole::ProjectManager) {
+        return Err(ServiceError::Forbidden);
+    }
+    let document = TemplateService::generate(
+        request.into_inner(),
+        auth_user.user_id,
+        &auth_user.role,
+        &auth_user.audit(),
+        storage.get_ref(),
+        &pool,
+    )
+    .await?;
+    Ok(HttpResponse::Created()
+        .insert_header(etag(document.version))
+        .json(document))
+}
+
+/// List the documents of a project, or those linked to one of its records
+#[utoipa::path(
+    get,
+    path = "/api/documents",
+    params(DocumentFilter),
+    responses(
+        (status = 200, description = "Documents by title", body = [Document]),
+        (status = 404, description = "Project or record not found"),
+        (status = 422, description = "Neither a project nor a record given")
+    )
+)]
+#[get("")]
+pub async fn list_documents(
+    auth_user: AuthenticatedUser,
+    filter: web::Query<DocumentFilter>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, ServiceError> {
+    let documents =
+        DocumentService::list(&filter, auth_user.user_id, &auth_user.role, &pool).await?;
+    Ok(HttpResponse::Ok().json(documents))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/documents/{id}",
+    params(("id" = Uuid, Path, description = "Document ID")),
+    responses(
+        (status = 200, description = "The document and its current version", body = Document),
+        (status = 404, description = "Document not found")
+    )
+)]
+#[get("/{id}")]
+pub async fn get_document(
+    auth_user: AuthenticatedUser,
+    id: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, ServiceError> {
+    let document =
+        DocumentService::get(id.into_inner(), auth_user.user_id, &auth_user.role, &pool).await?;
+    Ok(HttpResponse::Ok()
+        .insert_header(etag(document.version))
+        .json(document))
+}
+
+/// Change a document's title or description
+#[utoipa::path(
+    method(put, patch),
+    path = "/api/documents/{id}",
+    params(("id" = Uuid, Path, description = "Document ID")),
+    request_body = DocumentUpdate,
+    responses(
+        (status = 200, description = "Updated document", body = Document),
+        (status = 404, description = "Document not found"),
+        (status = 412, description = "The document changed since it was read")
+    )
+)]
+#[route("/{id}", method = "PUT", method = "PATCH")]
+pub async fn update_document(
+    auth_user: AuthenticatedUser,
+    id: web::Path<Uuid>,
+    if_match: IfMatch,
+    update: web::Json<DocumentUpdate>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, ServiceError> {
+    let document = DocumentService::update(
+        id.into_inner(),
+        update.into_inner(),
+        if_match.0,
+        auth_user.user_id,
+        &auth_user.role,
+        &auth_user.audit(),
+        &pool,
+    )
+    .await?;
+    Ok(HttpResponse::Ok()
+        .insert_header(etag(document.version))
+        .json(document))
+}
+
+/// Delete a document with all its versions
+#[utoipa::path(
+    delete,
+    path = "/api/documents/{id}",
+    params(("id" = Uuid, Path, description = "Document ID")),
+    responses(
+        (status = 204, description = "Document deleted"),
+        (status = 403, description = "Only the uploader, an admin or a project manager can delete a document"),
+        (status = 404, description = "Document not found"),
+        (status = 412, description = "The document changed since it was read")
+    )
+)]
+#[delete("/{id}")]
+pub async fn delete_document(
+    auth_user: AuthenticatedUser,
+    id: web::Path<Uuid>,
+    if_match: IfMatch,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, ServiceError> {
+    DocumentService::delete(
+        id.into_inner(),
+        if_match.0,
+        auth_user.user_id,
+        &auth_user.role,
+        &auth_user.audit(),
+        &pool,
+    )
+    .await?;
+    Ok(HttpResponse::NoContent().finish())
+}
+
+/// Upload a new version of a document
+///
+/// The body and `Content-Type` are handled as for a new document.
+#[utoipa::path(
+    post,
+    path = "/api/documents/{id}/versions",
+    params(("id" = Uuid, Path, description = "Document ID"), VersionUpload),
+    request_body(content = Vec<u8>, content_type = "application/octet-stream", description = "File content, sent with its own content type"),
+    responses(
+        (status = 201, description = "Document with its new current version", body = Document),
+        (status = 404, description = "Document not found"),
+        (status = 409, description = "The project is archived"),
+        (status = 412, description = "The document changed since it was read"),
+        (status = 413, description = "File too large"),
+        (status = 422, description = "Empty file or type not accepted")
+    )
+)]
+#[post("/{id}/versions")]
+#[allow(clippy::too_many_arguments)]
+pub async fn upload_version(
+    req: HttpRequest,
+    auth_user: AuthenticatedUser,
+    id: web::Path<Uuid>,
+    if_match: IfMatch,
+    params: web::Query<VersionUpload>,
+    body: web::Bytes,
+    storage: web::Data<dyn DocumentStorage>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, ServiceError> {
+    let document = DocumentService::add_version(
+        id.into_inner(),
+        params.into_inner(),
+        content_type(&req),
+        body,
+        if_match.0,
+        auth_user.user_id,
+        &auth_user.role,
+        &auth_user.audit(),
+        storage.get_ref(),
+        &pool,
+    )
+    .await?;
+    Ok(HttpResponse::Created()
+        .insert_header(etag(document.version))
+        .json(document))
+}
+
+/// Every version of a document, oldest first
+#[utoipa::path(
+    get,
+    path = "/api/documents/{id}/versions",
+    params(("id" = Uuid, Path, description = "Document ID")),
+    responses(
+        (status = 200, description = "Versions", body = [DocumentVersion]),
+        (status = 404, description = "Document not found")
+    )
+)]
+#[get("/{id}/versions")]
+pub async fn list_versions(
+    auth_user: AuthenticatedUser,
+    id: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, ServiceError> {
+    let versions =
+        DocumentService::versions(id.into_inner(), auth_user.user_id, &auth_user.role, &pool)
+            .await?;
+    Ok(HttpResponse::Ok().json(versions))
+}
+
+/// Download a document's content
+#[utoipa::path(
+    get,
+    path = "/api/documents/{id}/content",
+    params(("id" = Uuid, Path, description = "Document ID"), DownloadParams),
+    responses(
+        (status = 200, description = "The file as an attachment", body = Vec<u8>, content_type = "application/octet-stream"),
+        (status = 404, description = "Document or version not found")
+    )
+)]
+#[get("/{id}/content")]
+pub async fn download_document(
+    auth_user: AuthenticatedUser,
+    id: web::Path<Uuid>,
+    params: web::Query<DownloadParams>,
+    storage: web::Data<dyn DocumentStorage>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, ServiceError> {
+    let (version, content) = DocumentService::download(
+        id.into_inner(),
+        params.version,
+        auth_user.user_id,
+        &auth_user.role,
+        storage.get_ref(),
+        &pool,
+    )
+    .await?;
+    Ok(HttpResponse::Ok()
+        .content_type(version.content_type)
+        .insert_header((
+            header::CONTENT_DISPOSITION,
+            format!("attachment; filename=\"{}\"", version.filename),
+        ))
+        .insert_header((header::X_CONTENT_TYPE_OPTIONS, "nosniff"))
+        .body(content))
+}
+
+/// Records of the project the document is linked to
+#[utoipa::path(
+    get,
+    path = "/api/documents/{id}/links",
+    params(("id" = Uuid, Path, description = "Document ID")),
+    responses(
+        (status = 200, description = "Links", body = [DocumentLink]),
+        (status = 404, description = "Document not found")
+    )
+)]
+#[get("/{id}/links")]
+pub async fn list_links(
+    auth_user: AuthenticatedUser,
+    id: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, ServiceError> {
+    let links =
+        DocumentService::links(id.into_inner(), auth_user.user_id, &auth_user.role, &pool).await?;
+    Ok(HttpResponse::Ok().json(links))
+}
+
+/// Link a document to a task, deliverable, phase transition or gate review
+/// of its project. Deliverables are milestone tasks.
+#[utoipa::path(
+    post,
+    path = "/api/documents/{id}/links",
+    params(("id" = Uuid, Path, description = "Document ID")),
+    request_body = DocumentLinkCreate,
+    responses(
+        (status = 201, description = "Document linked", body = DocumentLink),
+        (status = 404, description = "Document not found"),
+        (status = 409, description = "The project is archived"),
+        (status = 422, description = "The record does not exist in the document's project")
+    )
+)]
+#[post("/{id}/links")]
+pub async fn link_document(
+    auth_user: AuthenticatedUser,
+    id: web::Path<Uuid>,
+    link: web::Json<DocumentLinkCreate>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, ServiceError> {
+    let link = DocumentService::link(
+        id.into_inner(),
+        link.into_inner(),
+        auth_user.user_id,
+        &auth_user.role,
+        &auth_user.audit(),
+        &pool,
+    )
+    .await?;
+    Ok(HttpResponse::Created().json(link))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/documents/{id}/links/{target_type}/{target_id}",
+    params(
+        ("id" = Uuid, Path, description = "Document ID"),
+        ("target_type" = DocumentTarget, Path, description = "Kind of record"),
+        ("target_id" = Uuid, Path, description = "Record ID")
+    ),
+    responses(
+        (status = 204, description = "Link removed"),
+        (status = 404, description = "Document or link not found"),
+        (status = 409, description = "The project is archived")
+    )
+)]
+#[delete("/{id}/links/{target_type}/{target_id}")]
+pub async fn unlink_document(
+    auth_user: AuthenticatedUser,
+    path: web::Path<(Uuid, DocumentTarget, Uuid)>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, ServiceError> {
+    let (id, target_type, target_id) = path.into_inner();
+    DocumentService::unlink(
+        id,
+        target_type,
+        target_id,
+        auth_user.user_id,
+        &auth_user.role,
+        &auth_user.audit(),
+        &pool,
+    )
+    .await?;
+    Ok(HttpResponse::NoContent().finish())
+}
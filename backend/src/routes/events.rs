@@ -0,0 +1,61 @@
+use crate::errors::ServiceError;
+use crate::extractors::auth::AuthenticatedUser;
+use crate::models::realtime::{ChangeEvent, StreamParams};
+use crate::services::realtime_service::{Broadcaster, RealtimeService, StreamSession};
+use actix_web::http::header;
+use actix_web::{get, web, HttpResponse};
+use sqlx::PgPool;
+
+pub fn config(cfg: &mut web::ServiceConfig) {
+    cfg.service(web::scope("/events").service(stream_events));
+}
+
+/// Stream changes to visible projects as Server-Sent Events
+///
+/// Each change arrives as an event named after its kind (`task.created`,
+/// `task.updated`, `task.deleted`, `task.restored`, `project.restored`,
+/// `project.phase_changed`, `comment.added`, `document.uploaded`) with a
+/// `ChangeEvent` as data. A `resync` event means changes were missed and the
+/// client should reload. The stream ends with an `expired` event when the
+/// token expires or the account is deactivated; reconnect with a fresh
+/// token. Send the token in the `Authorization` header, which needs a
+/// fetch-based EventSource client in browsers.
+#[utoipa::path(
+    get,
+    path = "/api/events",
+    params(StreamParams),
+    responses(
+        (status = 200, description = "Event stream", content_type = "text/event-stream", body = ChangeEvent),
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "Project not found")
+    )
+)]
+#[get("")]
+pub async fn stream_events(
+    auth_user: AuthenticatedUser,
+    params: web::Query<StreamParams>,
+    broadcaster: web::Data<Broadcaster>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, ServiceError> {
+    // Subscribe first so nothing committed while visibility is worked out
+    // is missed
+    let events = broadcaster.subscribe();
+    let visibility =
+        RealtimeService::visibility(auth_user.user_id, &auth_user.role, params.project_id, &pool)
+            .await?;
+
+    Ok(HttpResponse::Ok()
+        .content_type("text/event-stream")
+        .insert_header((header::CACHE_CONTROL, "no-cache"))
+        // Stops nginx from buffering the stream
+        .insert_header(("X-Accel-Buffering", "no"))
+        .streaming(RealtimeService::stream(
+            events,
+            visibility,
+            StreamSession {
+                user_id: auth_user.user_id,
+                expires_at: auth_user.expires_at,
+                pool: pool.get_ref().clone(),
+            },
+        )))
+}
@@ -0,0 +1,159 @@
+use crate::errors::ServiceError;
+use crate::extractors::auth::AuthenticatedUser;
+use crate::models::job::{Job, JobFilter, JobSchedule, JobScheduleUpdate};
+use crate::models::pagination::{Page, PageParams};
+use crate::services::job_service::JobService;
+use actix_web::{get, post, route, web, HttpRequest, HttpResponse};
+use chrono::Utc;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+pub fn config(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/jobs")
+            .service(list_jobs)
+            .service(list_schedules)
+            .service(update_schedule)
+            .service(run_schedule)
+            .service(get_job)
+            .service(retry_job),
+    );
+}
+
+/// List background jobs, newest first by default
+#[utoipa::path(
+    get,
+    path = "/api/jobs",
+    params(PageParams, JobFilter),
+    responses(
+        (status = 200, description = "Page of jobs", body = Page<Job>),
+        (status = 403, description = "Admin role required")
+    )
+)]
+#[get("")]
+pub async fn list_jobs(
+    req: HttpRequest,
+    auth_user: AuthenticatedUser,
+    params: web::Query<PageParams>,
+    filter: web::Query<JobFilter>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, ServiceError> {
+    auth_user.require_admin()?;
+    let jobs = JobService::list(&params, &filter, &pool)
+        .await?
+        .with_links(req.path(), req.query_string());
+    Ok(HttpResponse::Ok().json(jobs))
+}
+
+/// Get a background job, including its last error
+#[utoipa::path(
+    get,
+    path = "/api/jobs/{id}",
+    params(("id" = Uuid, Path, description = "Job ID")),
+    responses(
+        (status = 200, description = "The job", body = Job),
+        (status = 403, description = "Admin role required"),
+        (status = 404, description = "Job not found")
+    )
+)]
+#[get("/{id}")]
+pub async fn get_job(
+    auth_user: AuthenticatedUser,
+    id: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, ServiceError> {
+    auth_user.require_admin()?;
+    let job = JobService::get(id.into_inner(), &pool).await?;
+    Ok(HttpResponse::Ok().json(job))
+}
+
+/// Queue a failed job again with a fresh set of attempts
+#[utoipa::path(
+    post,
+    path = "/api/jobs/{id}/retry",
+    params(("id" = Uuid, Path, description = "Job ID")),
+    responses(
+        (status = 200, description = "The job, pending again", body = Job),
+        (status = 403, description = "Admin role required"),
+        (status = 404, description = "Job not found"),
+        (status = 409, description = "Job has not failed")
+    )
+)]
+#[post("/{id}/retry")]
+pub async fn retry_job(
+    auth_user: AuthenticatedUser,
+    id: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, ServiceError> {
+    auth_user.require_admin()?;
+    let job = JobService::retry(id.into_inner(), Utc::now(), &pool).await?;
+    Ok(HttpResponse::Ok().json(job))
+}
+
+/// List the cron schedules that enqueue jobs
+#[utoipa::path(
+    get,
+    path = "/api/jobs/schedules",
+    responses(
+        (status = 200, description = "Every schedule", body = [JobSchedule]),
+        (status = 403, description = "Admin role required")
+    )
+)]
+#[get("/schedules")]
+pub async fn list_schedules(
+    auth_user: AuthenticatedUser,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, ServiceError> {
+    auth_user.require_admin()?;
+    let schedules = JobService::list_schedules(&pool).await?;
+    Ok(HttpResponse::Ok().json(schedules))
+}
+
+/// Change a schedule's cron expression or enable or disable it. The body is
+/// a JSON Merge Patch for both PUT and PATCH.
+#[utoipa::path(
+    method(put, patch),
+    path = "/api/jobs/schedules/{name}",
+    params(("name" = String, Path, description = "Schedule name")),
+    request_body = JobScheduleUpdate,
+    responses(
+        (status = 200, description = "Updated schedule", body = JobSchedule),
+        (status = 403, description = "Admin role required"),
+        (status = 404, description = "Schedule not found"),
+        (status = 422, description = "Invalid cron expression")
+    )
+)]
+#[route("/schedules/{name}", method = "PUT", method = "PATCH")]
+pub async fn update_schedule(
+    auth_user: AuthenticatedUser,
+    name: web::Path<String>,
+    update: web::Json<JobScheduleUpdate>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, ServiceError> {
+    auth_user.require_admin()?;
+    let schedule =
+        JobService::update_schedule(&name, update.into_inner(), Utc::now(), &pool).await?;
+    Ok(HttpResponse::Ok().json(schedule))
+}
+
+/// Enqueue a schedule's job right away
+#[utoipa::path(
+    post,
+    path = "/api/jobs/schedules/{name}/run",
+    params(("name" = String, Path, description = "Schedule name")),
+    responses(
+        (status = 202, description = "Job enqueued", body = Job),
+        (status = 403, description = "Admin role required"),
+        (status = 404, description = "Schedule not found")
+    )
+)]
+#[post("/schedules/{name}/run")]
+pub async fn run_schedule(
+    auth_user: AuthenticatedUser,
+    name: web::Path<String>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, ServiceError> {
+    auth_user.require_admin()?;
+    let job = JobService::run_schedule_now(&name, Utc::now(), &pool).await?;
+    Ok(HttpResponse::Accepted().json(job))
+}
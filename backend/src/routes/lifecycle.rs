@@ -1,9 +1,11 @@
 use crate::errors::ServiceError;
 use crate::extractors::auth::AuthenticatedUser;
+use crate::extractors::precondition::IfMatch;
+use crate::models::calendar::GateReviewCreate;
 use crate::models::lifecycle::PhaseTransition;
 use crate::models::user::UserRole;
 use crate::services::lifecycle_service::LifecycleService;
-use actix_web::{get, post, web, HttpResponse};
+use actix_web::{delete, get, post, web, HttpResponse};
 use sqlx::PgPool;
 use uuid::Uuid;
 use validator::Validate;
@@ -13,14 +15,19 @@ pub fn config(cfg: &mut web::ServiceConfig) {
         web::scope("/lifecycle")
             .service(transition_phase)
             .service(get_phase_details)
-            .service(get_project_lifecycle),
+            .service(get_project_lifecycle)
+            .service(schedule_gate_review)
+            .service(get_gate_reviews)
+            .service(cancel_gate_review),
     );
 }
 
-/// Transition project to a new phase
+/// Transition project to a new phase. `If-Match` is checked against the
+/// project's version.
 #[post("/transition")]
 async fn transition_phase(
     auth_user: AuthenticatedUser,
+    if_match: IfMatch,
     transition: web::Json<PhaseTransition>,
     pool: web::Data<PgPool>,
 ) -> Result<HttpResponse, ServiceError> {
@@ -32,7 +39,8 @@ async fn transition_phase(
         UserRole::Admin | UserRole::ProjectManager => {
             let result = LifecycleService::transition_phase(
                 transition.into_inner(),
-                auth_user.user_id,
+                if_match.0,
+                &auth_user.audit(),
                 &pool,
             )
             .await?;
@@ -45,7 +53,7 @@ async fn transition_phase(
 /// Get details for a specific phase
 #[get("/phase/{phase_id}")]
 async fn get_phase_details(
-    auth_user: AuthenticatedUser,
+    _auth_user: AuthenticatedUser,
     phase_id: web::Path<Uuid>,
     pool: web::Data<PgPool>,
 ) -> Result<HttpResponse, ServiceError> {
@@ -56,10 +64,63 @@ async fn get_phase_details(
 /// Get complete lifecycle history for a project
 #[get("/project/{project_id}")]
 async fn get_project_lifecycle(
-    auth_user: AuthenticatedUser,
+    _auth_user: AuthenticatedUser,
     project_id: web::Path<Uuid>,
     pool: web::Data<PgPool>,
 ) -> Result<HttpResponse, ServiceError> {
     let history = LifecycleService::get_project_lifecycle(*project_id, &pool).await?;
     Ok(HttpResponse::Ok().json(history))
 }
+
+/// Schedule a gate review for a project
+#[post("/project/{project_id}/gate-reviews")]
+async fn schedule_gate_review(
+    auth_user: AuthenticatedUser,
+    project_id: web::Path<Uuid>,
+    review: web::Json<GateReviewCreate>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, ServiceError> {
+    match auth_user.role {
+        UserRole::Admin | UserRole::ProjectManager => {
+            let review = LifecycleService::schedule_gate_review(
+                *project_id,
+                review.into_inner(),
+                &auth_user.audit(),
+                &pool,
+            )
+            .await?;
+            Ok(HttpResponse::Created().json(review))
+        }
+        _ => Err(ServiceError::Forbidden),
+    }
+}
+
+/// List the gate reviews scheduled for a project
+#[get("/project/{project_id}/gate-reviews")]
+async fn get_gate_reviews(
+    _auth_user: AuthenticatedUser,
+    project_id: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, ServiceError> {
+    let reviews = LifecycleService::get_gate_reviews(*project_id, &pool).await?;
+    Ok(HttpResponse::Ok().json(reviews))
+}
+
+/// Cancel a scheduled gate review. `If-Match` is checked against the
+/// review's version.
+#[delete("/gate-reviews/{id}")]
+async fn cancel_gate_review(
+    auth_user: AuthenticatedUser,
+    if_match: IfMatch,
+    id: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, ServiceError> {
+    match auth_user.role {
+        UserRole::Admin | UserRole::ProjectManager => {
+            LifecycleService::cancel_gate_review(*id, if_match.0, &auth_user.audit(), &pool)
+                .await?;
+            Ok(HttpResponse::NoContent().finish())
+        }
+        _ => Err(ServiceError::Forbidden),
+    }
+}
@@ -1,20 +1,64 @@
 use actix_web::web;
 
+use crate::errors::ServiceError;
+
+pub mod audit;
 pub mod auth;
+pub mod calendar;
+pub mod comments;
+pub mod defects;
+pub mod documents;
+pub mod events;
+pub mod jobs;
 pub mod lifecycle;
+pub mod notifications;
 pub mod projects;
+pub mod requirements;
 pub mod resources;
+pub mod search;
 pub mod tasks;
+pub mod templates;
+pub mod testing;
+pub mod traceability;
 pub mod users;
+pub mod webhooks;
 
 pub fn config(cfg: &mut web::ServiceConfig) {
-    cfg.service(
+    // Malformed bodies, query strings and paths get the same problem+json
+    // shape as every other error
+    cfg.app_data(web::JsonConfig::default().error_handler(|err, _| {
+        ServiceError::BadRequest(format!("Invalid JSON body: {}", err)).into()
+    }))
+    .app_data(web::QueryConfig::default().error_handler(|err, _| {
+        ServiceError::BadRequest(format!("Invalid query string: {}", err)).into()
+    }))
+    .app_data(
+        web::PathConfig::default().error_handler(|err, _| {
+            ServiceError::BadRequest(format!("Invalid path: {}", err)).into()
+        }),
+    );
+
+    cfg.service(auth::jwks).service(
         web::scope("/api")
             .configure(auth::config)
+            .configure(audit::config)
+            .configure(calendar::config)
+            .configure(comments::config)
+            .configure(defects::config)
+            .configure(documents::config)
+            .configure(events::config)
             .configure(projects::config)
+            .configure(requirements::config)
             .configure(resources::config)
+            .configure(search::config)
             .configure(tasks::config)
+            .configure(templates::config)
+            .configure(testing::config)
+            .configure(traceability::config)
+            .configure(jobs::config)
             .configure(lifecycle::config)
-            .configure(users::config),
+            .configure(notifications::config)
+            .configure(users::config)
+            .configure(webhooks::config),
     );
 }
@@ -0,0 +1,145 @@
+use crate::errors::ServiceError;
+use crate::extractors::auth::AuthenticatedUser;
+use crate::models::notification::{
+    Notification, NotificationFilter, NotificationPreference, UnreadCount,
+};
+use crate::models::pagination::{Page, PageParams};
+use crate::services::notification_service::NotificationService;
+use actix_web::{get, post, put, web, HttpRequest, HttpResponse};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+pub fn config(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/notifications")
+            .service(list_notifications)
+            .service(get_unread_count)
+            .service(mark_all_read)
+            .service(mark_read)
+            .service(get_preferences)
+            .service(update_preferences),
+    );
+}
+
+/// The caller's in-app notifications
+#[utoipa::path(
+    get,
+    path = "/api/notifications",
+    params(PageParams, NotificationFilter),
+    responses(
+        (status = 200, description = "Page of notifications", body = Page<Notification>),
+        (status = 401, description = "Unauthorized")
+    )
+)]
+#[get("")]
+pub async fn list_notifications(
+    req: HttpRequest,
+    auth_user: AuthenticatedUser,
+    params: web::Query<PageParams>,
+    filter: web::Query<NotificationFilter>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, ServiceError> {
+    let notifications = NotificationService::list(auth_user.user_id, &params, &filter, &pool)
+        .await?
+        .with_links(req.path(), req.query_string());
+    Ok(HttpResponse::Ok().json(notifications))
+}
+
+/// Number of unread in-app notifications
+#[utoipa::path(
+    get,
+    path = "/api/notifications/unread-count",
+    responses(
+        (status = 200, description = "Unread count", body = UnreadCount),
+        (status = 401, description = "Unauthorized")
+    )
+)]
+#[get("/unread-count")]
+pub async fn get_unread_count(
+    auth_user: AuthenticatedUser,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, ServiceError> {
+    let unread = NotificationService::unread_count(auth_user.user_id, &pool).await?;
+    Ok(HttpResponse::Ok().json(UnreadCount { unread }))
+}
+
+/// Mark every notification read
+#[utoipa::path(
+    post,
+    path = "/api/notifications/read-all",
+    responses(
+        (status = 204, description = "All notifications marked read"),
+        (status = 401, description = "Unauthorized")
+    )
+)]
+#[post("/read-all")]
+pub async fn mark_all_read(
+    auth_user: AuthenticatedUser,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, ServiceError> {
+    NotificationService::mark_all_read(auth_user.user_id, &pool).await?;
+    Ok(HttpResponse::NoContent().finish())
+}
+
+/// Mark one notification read
+#[utoipa::path(
+    post,
+    path = "/api/notifications/{id}/read",
+    params(("id" = Uuid, Path, description = "Notification ID")),
+    responses(
+        (status = 200, description = "Notification marked read", body = Notification),
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "Notification not found")
+    )
+)]
+#[post("/{id}/read")]
+pub async fn mark_read(
+    auth_user: AuthenticatedUser,
+    id: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, ServiceError> {
+    let notification =
+        NotificationService::mark_read(id.into_inner(), auth_user.user_id, &pool).await?;
+    Ok(HttpResponse::Ok().json(notification))
+}
+
+/// The caller's delivery channels for each event
+#[utoipa::path(
+    get,
+    path = "/api/notifications/preferences",
+    responses(
+        (status = 200, description = "Preference for every event", body = [NotificationPreference]),
+        (status = 401, description = "Unauthorized")
+    )
+)]
+#[get("/preferences")]
+pub async fn get_preferences(
+    auth_user: AuthenticatedUser,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, ServiceError> {
+    let preferences = NotificationService::get_preferences(auth_user.user_id, &pool).await?;
+    Ok(HttpResponse::Ok().json(preferences))
+}
+
+/// Change delivery channels for the events listed
+#[utoipa::path(
+    put,
+    path = "/api/notifications/preferences",
+    request_body = [NotificationPreference],
+    responses(
+        (status = 200, description = "Preference for every event", body = [NotificationPreference]),
+        (status = 400, description = "Unknown event"),
+        (status = 401, description = "Unauthorized")
+    )
+)]
+#[put("/preferences")]
+pub async fn update_preferences(
+    auth_user: AuthenticatedUser,
+    preferences: web::Json<Vec<NotificationPreference>>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, ServiceError> {
+    let preferences =
+        NotificationService::update_preferences(auth_user.user_id, preferences.into_inner(), &pool)
+            .await?;
+    Ok(HttpResponse::Ok().json(preferences))
+}
@@ -1,9 +1,23 @@
 use crate::errors::ServiceError;
 use crate::extractors::auth::AuthenticatedUser;
-use crate::models::project::{Project, ProjectCreate, ProjectUpdate};
+use crate::extractors::precondition::{etag, IfMatch};
+use crate::models::defect::OpenDefectCounts;
+use crate::models::export::ExportParams;
+use crate::models::import::{ImportParams, ImportReport};
+use crate::models::job::ScheduleMetrics;
+use crate::models::pagination::{Page, PageParams};
+use crate::models::project::{Project, ProjectCreate, ProjectFilter, ProjectUpdate};
+use crate::models::testing::TestReport;
+use crate::models::traceability::TraceabilityMatrix;
 use crate::models::user::UserRole;
+use crate::services::defect_service::DefectService;
+use crate::services::export_service::ExportService;
+use crate::services::import_service::{ImportService, MAX_IMPORT_BYTES};
 use crate::services::project_service::ProjectService;
-use actix_web::{delete, get, post, put, web, HttpResponse};
+use crate::services::testing_service::TestingService;
+use crate::services::traceability_service::TraceabilityService;
+use actix_web::http::header;
+use actix_web::{delete, get, post, route, web, HttpRequest, HttpResponse};
 use sqlx::PgPool;
 use uuid::Uuid;
 use validator::Validate;
@@ -11,11 +25,22 @@ use validator::Validate;
 pub fn config(cfg: &mut web::ServiceConfig) {
     cfg.service(
         web::scope("/projects")
+            .app_data(web::PayloadConfig::new(MAX_IMPORT_BYTES))
             .service(get_projects)
+            .service(get_project_trash)
+            .service(import_project)
+            .service(export_project)
+            .service(get_schedule_metrics)
+            .service(get_traceability)
+            .service(get_test_report)
+            .service(get_open_defects)
             .service(get_project)
             .service(create_project)
             .service(update_project)
-            .service(delete_project),
+            .service(delete_project)
+            .service(restore_project)
+            .service(archive_project)
+            .service(unarchive_project),
     );
 }
 
@@ -23,20 +48,58 @@ pub fn config(cfg: &mut web::ServiceConfig) {
 #[utoipa::path(
     get,
     path = "/api/projects",
+    params(PageParams, ProjectFilter),
     responses(
-        (status = 200, description = "List of projects", body = Vec<Project>),
+        (status = 200, description = "Page of projects", body = Page<Project>),
+        (status = 400, description = "Invalid filter or sort field"),
         (status = 500, description = "Internal server error")
     )
 )]
 #[get("")]
 async fn get_projects(
+    req: HttpRequest,
     auth_user: AuthenticatedUser,
+    params: web::Query<PageParams>,
+    filter: web::Query<ProjectFilter>,
     pool: web::Data<PgPool>,
 ) -> Result<HttpResponse, ServiceError> {
     // Only allow certain roles to access this endpoint
     match auth_user.role {
         UserRole::Admin | UserRole::ProjectManager | UserRole::Developer => {
-            let projects = ProjectService::get_all(&pool).await?;
+            let projects = ProjectService::get_all(&params, &filter, &pool)
+                .await?
+                .with_links(req.path(), req.query_string());
+            Ok(HttpResponse::Ok().json(projects))
+        }
+        _ => Err(ServiceError::Forbidden),
+    }
+}
+
+/// List projects in the trash
+#[utoipa::path(
+    get,
+    path = "/api/projects/trash",
+    params(PageParams, ProjectFilter),
+    responses(
+        (status = 200, description = "Page of trashed projects", body = Page<Project>),
+        (status = 400, description = "Invalid filter or sort field"),
+        (status = 403, description = "Admin or project manager role required"),
+        (status = 500, description = "Internal server error")
+    )
+)]
+#[get("/trash")]
+async fn get_project_trash(
+    req: HttpRequest,
+    auth_user: AuthenticatedUser,
+    params: web::Query<PageParams>,
+    filter: web::Query<ProjectFilter>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, ServiceError> {
+    match auth_user.role {
+        UserRole::Admin | UserRole::ProjectManager => {
+            let projects = ProjectService::get_trash(&params, &filter, &pool)
+                .await?
+                .with_links(req.path(), req.query_string());
             Ok(HttpResponse::Ok().json(projects))
         }
         _ => Err(ServiceError::Forbidden),
@@ -66,7 +129,9 @@ async fn get_project(
         .map_err(|_| ServiceError::BadRequest("Invalid UUID format".to_string()))?;
 
     let project = ProjectService::get_by_id(project_id, &db).await?;
-    Ok(HttpResponse::Ok().json(project))
+    Ok(HttpResponse::Ok()
+        .insert_header(etag(project.version))
+        .json(project))
 }
 
 /// Create a new project
@@ -76,7 +141,7 @@ async fn get_project(
     request_body = ProjectCreate,
     responses(
         (status = 201, description = "Project created successfully", body = Project),
-        (status = 400, description = "Validation error"),
+        (status = 422, description = "Validation failed"),
         (status = 500, description = "Internal server error")
     )
 )]
@@ -90,31 +155,228 @@ async fn create_project(
     println!("User role: {:?}", auth_user.role);
     match auth_user.role {
         UserRole::Admin | UserRole::ProjectManager => {
-            let project = ProjectService::create(project.into_inner(), &pool).await?;
+            let project =
+                ProjectService::create(project.into_inner(), &auth_user.audit(), &pool).await?;
             Ok(HttpResponse::Created().json(project))
         }
         _ => Err(ServiceError::Forbidden),
     }
 }
 
-/// Update an existing project
+/// Import a plan from a Microsoft Project XML (MSPDI) file
+///
+/// Creates the project with its tasks, WBS hierarchy, dependencies, milestones,
+/// resources and assignments in one transaction. With `dry_run=true` nothing is
+/// saved and the report shows the mapping and any warnings.
+#[utoipa::path(
+    post,
+    path = "/api/projects/import",
+    params(ImportParams),
+    request_body(content = String, content_type = "application/xml", description = "MSPDI document"),
+    responses(
+        (status = 200, description = "Dry run report", body = ImportReport),
+        (status = 201, description = "Plan imported", body = ImportReport),
+        (status = 400, description = "Not a readable MSPDI document"),
+        (status = 403, description = "Admin or project manager role required"),
+        (status = 409, description = "A resource of the plan conflicts with an existing one"),
+        (status = 422, description = "The plan is missing required data"),
+        (status = 500, description = "Internal server error")
+    )
+)]
+#[post("/import")]
+async fn import_project(
+    auth_user: AuthenticatedUser,
+    params: web::Query<ImportParams>,
+    body: web::Bytes,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, ServiceError> {
+    match auth_user.role {
+        UserRole::Admin | UserRole::ProjectManager => {
+            let xml = std::str::from_utf8(&body)
+                .map_err(|_| ServiceError::BadRequest("The plan must be UTF-8 encoded".into()))?;
+            let report =
+                ImportService::import_mspdi(xml, params.dry_run, &auth_user.audit(), &pool).await?;
+            if report.dry_run {
+                Ok(HttpResponse::Ok().json(report))
+            } else {
+                Ok(HttpResponse::Created().json(report))
+            }
+        }
+        _ => Err(ServiceError::Forbidden),
+    }
+}
+
+/// Export a project plan as Microsoft Project XML or CSV
+///
+/// The MSPDI export carries tasks, WBS hierarchy, dependencies, milestones,
+/// progress, resources and assignments, and can be imported again.
+#[utoipa::path(
+    get,
+    path = "/api/projects/{id}/export",
+    params(("id" = Uuid, Path, description = "Project UUID"), ExportParams),
+    responses(
+        (status = 200, description = "The plan as an attachment", content(
+            (String = "application/xml"),
+            (String = "text/csv")
+        )),
+        (status = 400, description = "Unknown format"),
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "Project not found"),
+        (status = 500, description = "Internal server error")
+    )
+)]
+#[get("/{id}/export")]
+async fn export_project(
+    auth_user: AuthenticatedUser,
+    id: web::Path<Uuid>,
+    params: web::Query<ExportParams>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, ServiceError> {
+    let export = ExportService::export(
+        id.into_inner(),
+        params.format,
+        auth_user.user_id,
+        &auth_user.role,
+        &pool,
+    )
+    .await?;
+    Ok(HttpResponse::Ok()
+        .content_type(format!("{}; charset=utf-8", export.content_type))
+        .insert_header((
+            header::CONTENT_DISPOSITION,
+            format!("attachment; filename=\"{}\"", export.filename),
+        ))
+        .body(export.body))
+}
+
+/// Progress against plan, as last computed by the `schedule_metrics` job
+#[utoipa::path(
+    get,
+    path = "/api/projects/{id}/schedule-metrics",
+    params(("id" = Uuid, Path, description = "Project UUID")),
+    responses(
+        (status = 200, description = "Schedule metrics", body = ScheduleMetrics),
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "Project not found or metrics not computed yet")
+    )
+)]
+#[get("/{id}/schedule-metrics")]
+async fn get_schedule_metrics(
+    auth_user: AuthenticatedUser,
+    id: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, ServiceError> {
+    let metrics = ProjectService::get_schedule_metrics(
+        id.into_inner(),
+        auth_user.user_id,
+        &auth_user.role,
+        &pool,
+    )
+    .await?;
+    Ok(HttpResponse::Ok().json(metrics))
+}
+
+/// Requirements traceability matrix
+///
+/// Each requirement, except rejected ones, with the design items, tasks
+/// and test cases that realise it. Requirements no task implements are
+/// flagged `untasked`, those no test case covers `untested`.
+#[utoipa::path(
+    get,
+    path = "/api/projects/{id}/traceability",
+    params(("id" = Uuid, Path, description = "Project UUID")),
+    responses(
+        (status = 200, description = "Traceability matrix", body = TraceabilityMatrix),
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "Project not found")
+    )
+)]
+#[get("/{id}/traceability")]
+async fn get_traceability(
+    auth_user: AuthenticatedUser,
+    id: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, ServiceError> {
+    let matrix =
+        TraceabilityService::matrix(id.into_inner(), auth_user.user_id, &auth_user.role, &pool)
+            .await?;
+    Ok(HttpResponse::Ok().json(matrix))
+}
+
+/// Test execution report
+///
+/// Pass rate of the project's test cases, each counted by its latest
+/// result, overall and per requirement.
+#[utoipa::path(
+    get,
+    path = "/api/projects/{id}/test-report",
+    params(("id" = Uuid, Path, description = "Project UUID")),
+    responses(
+        (status = 200, description = "Test report", body = TestReport),
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "Project not found")
+    )
+)]
+#[get("/{id}/test-report")]
+async fn get_test_report(
+    auth_user: AuthenticatedUser,
+    id: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, ServiceError> {
+    let report =
+        TestingService::report(id.into_inner(), auth_user.user_id, &auth_user.role, &pool).await?;
+    Ok(HttpResponse::Ok().json(report))
+}
+
+/// Open defects by severity
+///
+/// Counts the project's new, triaged and fixing defects.
 #[utoipa::path(
-    put,
+    get,
+    path = "/api/projects/{id}/open-defects",
+    params(("id" = Uuid, Path, description = "Project UUID")),
+    responses(
+        (status = 200, description = "Open defect counts", body = OpenDefectCounts),
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "Project not found")
+    )
+)]
+#[get("/{id}/open-defects")]
+async fn get_open_defects(
+    auth_user: AuthenticatedUser,
+    id: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, ServiceError> {
+    let counts =
+        DefectService::open_counts(id.into_inner(), auth_user.user_id, &auth_user.role, &pool)
+            .await?;
+    Ok(HttpResponse::Ok().json(counts))
+}
+
+/// Update an existing project. The body is a JSON Merge Patch (RFC 7396)
+/// for both PUT and PATCH.
+#[utoipa::path(
+    method(put, patch),
     path = "/api/projects/{id}",
     params(
-        ("id" = String, Path, description = "Project UUID")
+        ("id" = String, Path, description = "Project UUID"),
+        ("If-Match" = Option<String>, Header, description = "Version the change applies to, as returned in ETag")
     ),
     request_body = ProjectUpdate,
     responses(
         (status = 200, description = "Project updated successfully", body = Project),
-        (status = 400, description = "Validation error"),
+        (status = 422, description = "Validation failed"),
         (status = 404, description = "Project not found"),
+        (status = 409, description = "Project is archived"),
+        (status = 412, description = "Project was modified since it was read"),
         (status = 500, description = "Internal server error")
     )
 )]
-#[put("/{id}")]
+#[route("/{id}", method = "PUT", method = "PATCH")]
 async fn update_project(
+    auth_user: AuthenticatedUser,
     path: web::Path<String>,
+    if_match: IfMatch,
     project: web::Json<ProjectUpdate>,
     db: web::Data<PgPool>,
 ) -> Result<HttpResponse, ServiceError> {
@@ -123,36 +385,168 @@ async fn update_project(
         .map_err(|_| ServiceError::BadRequest("Invalid UUID format".to_string()))?;
 
     // Validate the request body
-    project
-        .validate()
-        .map_err(|e| ServiceError::ValidationError(e.to_string()))?;
+    project.validate().map_err(ServiceError::from)?;
 
-    let updated_project = ProjectService::update(project_id, project.into_inner(), &db).await?;
-    Ok(HttpResponse::Ok().json(updated_project))
+    let updated_project = ProjectService::update(
+        project_id,
+        project.into_inner(),
+        if_match.0,
+        &auth_user.audit(),
+        &db,
+    )
+    .await?;
+    Ok(HttpResponse::Ok()
+        .insert_header(etag(updated_project.version))
+        .json(updated_project))
 }
 
-/// Delete a project
+/// Move a project and its tasks to the trash
 #[utoipa::path(
     delete,
     path = "/api/projects/{id}",
     params(
-        ("id" = String, Path, description = "Project UUID")
+        ("id" = String, Path, description = "Project UUID"),
+        ("If-Match" = Option<String>, Header, description = "Version the change applies to, as returned in ETag")
     ),
     responses(
-        (status = 204, description = "Project deleted successfully"),
+        (status = 204, description = "Project moved to the trash"),
+        (status = 403, description = "Admin or project manager role required"),
         (status = 404, description = "Project not found"),
+        (status = 409, description = "Project is archived"),
+        (status = 412, description = "Project was modified since it was read"),
         (status = 500, description = "Internal server error")
     )
 )]
 #[delete("/{id}")]
 async fn delete_project(
+    auth_user: AuthenticatedUser,
     path: web::Path<String>,
+    if_match: IfMatch,
     db: web::Data<PgPool>,
 ) -> Result<HttpResponse, ServiceError> {
     let id = path.into_inner();
     let project_id = Uuid::parse_str(&id)
         .map_err(|_| ServiceError::BadRequest("Invalid UUID format".to_string()))?;
 
-    ProjectService::delete(project_id, &db).await?;
-    Ok(HttpResponse::NoContent().finish())
+    match auth_user.role {
+        UserRole::Admin | UserRole::ProjectManager => {
+            ProjectService::delete(
+                project_id,
+                if_match.0,
+                auth_user.user_id,
+                &auth_user.role,
+                &auth_user.audit(),
+                &db,
+            )
+            .await?;
+            Ok(HttpResponse::NoContent().finish())
+        }
+        _ => Err(ServiceError::Forbidden),
+    }
+}
+
+/// Restore a project from the trash, along with the tasks trashed with it
+#[utoipa::path(
+    post,
+    path = "/api/projects/{id}/restore",
+    params(
+        ("id" = String, Path, description = "Project UUID"),
+        ("If-Match" = Option<String>, Header, description = "Version the change applies to, as returned in ETag")
+    ),
+    responses(
+        (status = 200, description = "Project restored", body = Project),
+        (status = 403, description = "Admin or project manager role required"),
+        (status = 404, description = "Project not found in trash"),
+        (status = 412, description = "Project was modified since it was read"),
+        (status = 500, description = "Internal server error")
+    )
+)]
+#[post("/{id}/restore")]
+async fn restore_project(
+    auth_user: AuthenticatedUser,
+    path: web::Path<Uuid>,
+    if_match: IfMatch,
+    db: web::Data<PgPool>,
+) -> Result<HttpResponse, ServiceError> {
+    match auth_user.role {
+        UserRole::Admin | UserRole::ProjectManager => {
+            let project =
+                ProjectService::restore(path.into_inner(), if_match.0, &auth_user.audit(), &db)
+                    .await?;
+            Ok(HttpResponse::Ok()
+                .insert_header(etag(project.version))
+                .json(project))
+        }
+        _ => Err(ServiceError::Forbidden),
+    }
+}
+
+/// Archive a closed project, making it and its tasks read-only
+#[utoipa::path(
+    post,
+    path = "/api/projects/{id}/archive",
+    params(
+        ("id" = Uuid, Path, description = "Project UUID"),
+        ("If-Match" = Option<String>, Header, description = "Version the change applies to, as returned in ETag")
+    ),
+    responses(
+        (status = 200, description = "Project archived", body = Project),
+        (status = 403, description = "Admin or project manager role required"),
+        (status = 404, description = "Project not found"),
+        (status = 409, description = "Project is already archived"),
+        (status = 412, description = "Project was modified since it was read"),
+        (status = 422, description = "Project is not in the closed phase"),
+        (status = 500, description = "Internal server error")
+    )
+)]
+#[post("/{id}/archive")]
+async fn archive_project(
+    auth_user: AuthenticatedUser,
+    path: web::Path<Uuid>,
+    if_match: IfMatch,
+    db: web::Data<PgPool>,
+) -> Result<HttpResponse, ServiceError> {
+    match auth_user.role {
+        UserRole::Admin | UserRole::ProjectManager => {
+            let project =
+                ProjectService::archive(path.into_inner(), if_match.0, &auth_user.audit(), &db)
+                    .await?;
+            Ok(HttpResponse::Ok()
+                .insert_header(etag(project.version))
+                .json(project))
+        }
+        _ => Err(ServiceError::Forbidden),
+    }
+}
+
+/// Unarchive a project so it can be changed again
+#[utoipa::path(
+    post,
+    path = "/api/projects/{id}/unarchive",
+    params(
+        ("id" = Uuid, Path, description = "Project UUID"),
+        ("If-Match" = Option<String>, Header, description = "Version the change applies to, as returned in ETag")
+    ),
+    responses(
+        (status = 200, description = "Project unarchived", body = Project),
+        (status = 403, description = "Admin role required"),
+        (status = 404, description = "Project not found"),
+        (status = 412, description = "Project was modified since it was read"),
+        (status = 422, description = "Project is not archived"),
+        (status = 500, description = "Internal server error")
+    )
+)]
+#[post("/{id}/unarchive")]
+async fn unarchive_project(
+    auth_user: AuthenticatedUser,
+    path: web::Path<Uuid>,
+    if_match: IfMatch,
+    db: web::Data<PgPool>,
+) -> Result<HttpResponse, ServiceError> {
+    auth_user.require_admin()?;
+    let project =
+        ProjectService::unarchive(path.into_inner(), if_match.0, &auth_user.audit(), &db).await?;
+    Ok(HttpResponse::Ok()
+        .insert_header(etag(project.version))
+        .json(project))
 }
@@ -0,0 +1,201 @@
+use crate::errors::ServiceError;
+use crate::extractors::auth::AuthenticatedUser;
+use crate::extractors::precondition::{etag, IfMatch};
+use crate::models::requirement::{
+    Requirement, RequirementCreate, RequirementFilter, RequirementRevision, RequirementUpdate,
+};
+use crate::models::user::UserRole;
+use crate::services::requirement_service::RequirementService;
+use actix_web::{delete, get, post, route, web, HttpResponse};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+pub fn config(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/requirements")
+            .service(create_requirement)
+            .service(list_requirements)
+            .service(get_requirement)
+            .service(update_requirement)
+            .service(delete_requirement)
+            .service(list_revisions),
+    );
+}
+
+/// Add a requirement to a project
+///
+/// Requirements are numbered per project: REQ-001, REQ-002 and so on.
+#[utoipa::path(
+    post,
+    path = "/api/requirements",
+    request_body = RequirementCreate,
+    responses(
+        (status = 201, description = "Requirement created", body = Requirement),
+        (status = 403, description = "Admin or project manager role required"),
+        (status = 404, description = "Project not found"),
+        (status = 409, description = "The project is archived"),
+        (status = 422, description = "Validation failed")
+    )
+)]
+#[post("")]
+pub async fn create_requirement(
+    auth_user: AuthenticatedUser,
+    requirement: web::Json<RequirementCreate>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, ServiceError> {
+    if !matches!(auth_user.role, UserRole::Admin | UserRole::ProjectManager) {
+        return Err(ServiceError::Forbidden);
+    }
+    let requirement = RequirementService::create(
+        requirement.into_inner(),
+        auth_user.user_id,
+        &auth_user.role,
+        &auth_user.audit(),
+        &pool,
+    )
+    .await?;
+    Ok(HttpResponse::Created()
+        .insert_header(etag(requirement.version))
+        .json(requirement))
+}
+
+/// List a project's requirements in number order
+#[utoipa::path(
+    get,
+    path = "/api/requirements",
+    params(RequirementFilter),
+    responses(
+        (status = 200, description = "Requirements", body = [Requirement]),
+        (status = 404, description = "Project not found")
+    )
+)]
+#[get("")]
+pub async fn list_requirements(
+    auth_user: AuthenticatedUser,
+    filter: web::Query<RequirementFilter>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, ServiceError> {
+    let requirements =
+        RequirementService::list(&filter, auth_user.user_id, &auth_user.role, &pool).await?;
+    Ok(HttpResponse::Ok().json(requirements))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/requirements/{id}",
+    params(("id" = Uuid, Path, description = "Requirement ID")),
+    responses(
+        (status = 200, description = "The requirement", body = Requirement),
+        (status = 404, description = "Requirement not found")
+    )
+)]
+#[get("/{id}")]
+pub async fn get_requirement(
+    auth_user: AuthenticatedUser,
+    id: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, ServiceError> {
+    let requirement =
+        RequirementService::get(id.into_inner(), auth_user.user_id, &auth_user.role, &pool).await?;
+    Ok(HttpResponse::Ok()
+        .insert_header(etag(requirement.version))
+        .json(requirement))
+}
+
+/// Change a requirement. Each change is kept as a new version.
+#[utoipa::path(
+    method(put, patch),
+    path = "/api/requirements/{id}",
+    params(("id" = Uuid, Path, description = "Requirement ID")),
+    request_body = RequirementUpdate,
+    responses(
+        (status = 200, description = "Updated requirement", body = Requirement),
+        (status = 403, description = "Admin or project manager role required"),
+        (status = 404, description = "Requirement not found"),
+        (status = 409, description = "The project is archived"),
+        (status = 412, description = "The requirement changed since it was read"),
+        (status = 422, description = "Validation failed")
+    )
+)]
+#[route("/{id}", method = "PUT", method = "PATCH")]
+pub async fn update_requirement(
+    auth_user: AuthenticatedUser,
+    id: web::Path<Uuid>,
+    if_match: IfMatch,
+    update: web::Json<RequirementUpdate>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, ServiceError> {
+    if !matches!(auth_user.role, UserRole::Admin | UserRole::ProjectManager) {
+        return Err(ServiceError::Forbidden);
+    }
+    let requirement = RequirementService::update(
+        id.into_inner(),
+        update.into_inner(),
+        if_match.0,
+        auth_user.user_id,
+        &auth_user.role,
+        &auth_user.audit(),
+        &pool,
+    )
+    .await?;
+    Ok(HttpResponse::Ok()
+        .insert_header(etag(requirement.version))
+        .json(requirement))
+}
+
+/// Delete a requirement with its history and trace links
+#[utoipa::path(
+    delete,
+    path = "/api/requirements/{id}",
+    params(("id" = Uuid, Path, description = "Requirement ID")),
+    responses(
+        (status = 204, description = "Requirement deleted"),
+        (status = 403, description = "Admin or project manager role required"),
+        (status = 404, description = "Requirement not found"),
+        (status = 409, description = "The project is archived"),
+        (status = 412, description = "The requirement changed since it was read")
+    )
+)]
+#[delete("/{id}")]
+pub async fn delete_requirement(
+    auth_user: AuthenticatedUser,
+    id: web::Path<Uuid>,
+    if_match: IfMatch,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, ServiceError> {
+    if !matches!(auth_user.role, UserRole::Admin | UserRole::ProjectManager) {
+        return Err(ServiceError::Forbidden);
+    }
+    RequirementService::delete(
+        id.into_inner(),
+        if_match.0,
+        auth_user.user_id,
+        &auth_user.role,
+        &auth_user.audit(),
+        &pool,
+    )
+    .await?;
+    Ok(HttpResponse::NoContent().finish())
+}
+
+/// Every version of a requirement, oldest first
+#[utoipa::path(
+    get,
+    path = "/api/requirements/{id}/revisions",
+    params(("id" = Uuid, Path, description = "Requirement ID")),
+    responses(
+        (status = 200, description = "Versions, the last being current", body = [RequirementRevision]),
+        (status = 404, description = "Requirement not found")
+    )
+)]
+#[get("/{id}/revisions")]
+pub async fn list_revisions(
+    auth_user: AuthenticatedUser,
+    id: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, ServiceError> {
+    let revisions =
+        RequirementService::revisions(id.into_inner(), auth_user.user_id, &auth_user.role, &pool)
+            .await?;
+    Ok(HttpResponse::Ok().json(revisions))
+}
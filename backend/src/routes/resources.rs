@@ -1,5 +1,13 @@
-use actix_web::{web, HttpResponse, get, post};
+use crate::errors::ServiceError;
+use crate::extractors::auth::AuthenticatedUser;
+use crate::models::pagination::{Page, PageParams};
+use crate::models::resource::{Resource, ResourceCreate, ResourceUpdate};
+use crate::models::user::UserRole;
+use crate::services::resource_service::ResourceService;
+use actix_web::{delete, get, post, route, web, HttpRequest, HttpResponse};
 use sqlx::PgPool;
+use uuid::Uuid;
+use validator::Validate;
 
 pub fn config(cfg: &mut web::ServiceConfig) {
     cfg.service(
@@ -7,27 +15,141 @@ pub fn config(cfg: &mut web::ServiceConfig) {
             .service(get_resources)
             .service(get_resource)
             .service(create_resource)
+            .service(update_resource)
+            .service(delete_resource),
     );
 }
 
+fn require_resource_manager(auth_user: &AuthenticatedUser) -> Result<(), ServiceError> {
+    match auth_user.role {
+        UserRole::Admin | UserRole::ProjectManager => Ok(()),
+        _ => Err(ServiceError::Forbidden),
+    }
+}
+
+/// List resources
+#[utoipa::path(
+    get,
+    path = "/api/resources",
+    params(PageParams),
+    responses(
+        (status = 200, description = "Page of resources", body = Page<Resource>),
+        (status = 400, description = "Invalid sort field")
+    )
+)]
 #[get("")]
-async fn get_resources(_db: web::Data<PgPool>) -> HttpResponse {
-    HttpResponse::Ok().json(serde_json::json!({
-        "message": "Get all resources"
-    }))
+pub async fn get_resources(
+    req: HttpRequest,
+    _auth_user: AuthenticatedUser,
+    params: web::Query<PageParams>,
+    db: web::Data<PgPool>,
+) -> Result<HttpResponse, ServiceError> {
+    let resources = ResourceService::get_all(&params, &db)
+        .await?
+        .with_links(req.path(), req.query_string());
+    Ok(HttpResponse::Ok().json(resources))
 }
 
+/// Get resource by ID
+#[utoipa::path(
+    get,
+    path = "/api/resources/{id}",
+    params(
+        ("id" = Uuid, Path, description = "Resource ID")
+    ),
+    responses(
+        (status = 200, description = "Resource found", body = Resource),
+        (status = 404, description = "Resource not found")
+    )
+)]
 #[get("/{id}")]
-async fn get_resource(path: web::Path<String>, _db: web::Data<PgPool>) -> HttpResponse {
-    let id = path.into_inner();
-    HttpResponse::Ok().json(serde_json::json!({
-        "message": format!("Get resource {}", id)
-    }))
+pub async fn get_resource(
+    _auth_user: AuthenticatedUser,
+    id: web::Path<Uuid>,
+    db: web::Data<PgPool>,
+) -> Result<HttpResponse, ServiceError> {
+    let resource = ResourceService::get_by_id(id.into_inner(), &db).await?;
+    Ok(HttpResponse::Ok().json(resource))
 }
 
+/// Create a resource
+#[utoipa::path(
+    post,
+    path = "/api/resources",
+    request_body = ResourceCreate,
+    responses(
+        (status = 201, description = "Resource created", body = Resource),
+        (status = 422, description = "Validation failed"),
+        (status = 403, description = "Admin or project manager role required")
+    )
+)]
 #[post("")]
-async fn create_resource(_db: web::Data<PgPool>) -> HttpResponse {
-    HttpResponse::Created().json(serde_json::json!({
-        "message": "Create new resource"
-    }))
+pub async fn create_resource(
+    auth_user: AuthenticatedUser,
+    resource: web::Json<ResourceCreate>,
+    db: web::Data<PgPool>,
+) -> Result<HttpResponse, ServiceError> {
+    require_resource_manager(&auth_user)?;
+
+    let resource = ResourceService::create(resource.into_inner(), &auth_user.audit(), &db).await?;
+    Ok(HttpResponse::Created().json(resource))
+}
+
+/// Update a resource with a JSON Merge Patch (RFC 7396)
+#[utoipa::path(
+    method(put, patch),
+    path = "/api/resources/{id}",
+    params(
+        ("id" = Uuid, Path, description = "Resource ID")
+    ),
+    request_body = ResourceUpdate,
+    responses(
+        (status = 200, description = "Resource updated", body = Resource),
+        (status = 422, description = "Validation failed"),
+        (status = 403, description = "Admin or project manager role required"),
+        (status = 404, description = "Resource not found")
+    )
+)]
+#[route("/{id}", method = "PUT", method = "PATCH")]
+pub async fn update_resource(
+    auth_user: AuthenticatedUser,
+    id: web::Path<Uuid>,
+    resource: web::Json<ResourceUpdate>,
+    db: web::Data<PgPool>,
+) -> Result<HttpResponse, ServiceError> {
+    require_resource_manager(&auth_user)?;
+    resource.validate()?;
+
+    let resource = ResourceService::update(
+        id.into_inner(),
+        resource.into_inner(),
+        &auth_user.audit(),
+        &db,
+    )
+    .await?;
+    Ok(HttpResponse::Ok().json(resource))
+}
+
+/// Delete a resource
+#[utoipa::path(
+    delete,
+    path = "/api/resources/{id}",
+    params(
+        ("id" = Uuid, Path, description = "Resource ID")
+    ),
+    responses(
+        (status = 204, description = "Resource deleted"),
+        (status = 403, description = "Admin or project manager role required"),
+        (status = 404, description = "Resource not found")
+    )
+)]
+#[delete("/{id}")]
+pub async fn delete_resource(
+    auth_user: AuthenticatedUser,
+    id: web::Path<Uuid>,
+    db: web::Data<PgPool>,
+) -> Result<HttpResponse, ServiceError> {
+    require_resource_manager(&auth_user)?;
+    ResourceService::delete(id.into_inner(), &auth_user.audit(), &db).await?;
+    Ok(HttpResponse::NoContent().finish())
 }
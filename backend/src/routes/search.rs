@@ -0,0 +1,39 @@
+use crate::errors::ServiceError;
+use crate::extractors::auth::AuthenticatedUser;
+use crate::models::search::{SearchParams, SearchResults};
+use crate::services::search_service::SearchService;
+use actix_web::{get, web, HttpResponse};
+use sqlx::PgPool;
+
+pub fn config(cfg: &mut web::ServiceConfig) {
+    cfg.service(search);
+}
+
+/// Search projects, tasks and phase transitions
+#[utoipa::path(
+    get,
+    path = "/api/search",
+    params(SearchParams),
+    responses(
+        (status = 200, description = "Matches grouped by entity type", body = SearchResults),
+        (status = 422, description = "Empty search query"),
+        (status = 401, description = "Not authenticated")
+    )
+)]
+#[get("/search")]
+pub async fn search(
+    auth_user: AuthenticatedUser,
+    params: web::Query<SearchParams>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, ServiceError> {
+    let params = params.into_inner();
+    let results = SearchService::search(
+        &params.q,
+        params.limit,
+        auth_user.user_id,
+        &auth_user.role,
+        &pool,
+    )
+    .await?;
+    Ok(HttpResponse::Ok().json(results))
+}
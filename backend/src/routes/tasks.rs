@@ -1,29 +1,81 @@
 use crate::errors::ServiceError;
-use crate::models::task::{TaskCreate, TaskUpdate};
+use crate::extractors::auth::AuthenticatedUser;
+use crate::extractors::precondition::{etag, IfMatch};
+use crate::models::pagination::{Page, PageParams};
+use crate::models::patch::Patch;
+use crate::models::task::{
+    BulkTaskRequest, BulkTaskResponse, Task, TaskCreate, TaskFilter, TaskLink, TaskUpdate,
+};
 use crate::services::task_service::TaskService;
-use actix_web::{delete, get, post, put, web, HttpResponse};
+use actix_web::{delete, get, post, put, route, web, HttpRequest, HttpResponse};
 use bigdecimal::BigDecimal;
 use sqlx::PgPool;
 use uuid::Uuid;
-use validator::Validate;
 
 pub fn config(cfg: &mut web::ServiceConfig) {
     cfg.service(
         web::scope("/tasks")
             .service(get_tasks)
+            .service(get_task_trash)
             .service(get_task)
             .service(create_task)
+            .service(bulk_tasks)
             .service(update_task)
             .service(delete_task)
+            .service(restore_task)
             .service(get_project_tasks)
+            .service(get_project_task_links)
             .service(get_resource_tasks)
             .service(update_task_progress),
     );
 }
 
+/// List tasks
+#[utoipa::path(
+    get,
+    path = "/api/tasks",
+    params(PageParams, TaskFilter),
+    responses(
+        (status = 200, description = "Page of tasks", body = Page<Task>),
+        (status = 400, description = "Invalid filter or sort field"),
+        (status = 500, description = "Internal server error")
+    )
+)]
 #[get("")]
-async fn get_tasks(db: web::Data<PgPool>) -> Result<HttpResponse, ServiceError> {
-    let tasks = TaskService::get_all(&db).await?;
+pub async fn get_tasks(
+    req: HttpRequest,
+    params: web::Query<PageParams>,
+    filter: web::Query<TaskFilter>,
+    db: web::Data<PgPool>,
+) -> Result<HttpResponse, ServiceError> {
+    let tasks = TaskService::get_all(&params, &filter, &db)
+        .await?
+        .with_links(req.path(), req.query_string());
+    Ok(HttpResponse::Ok().json(tasks))
+}
+
+/// List tasks in the trash
+#[utoipa::path(
+    get,
+    path = "/api/tasks/trash",
+    params(PageParams, TaskFilter),
+    responses(
+        (status = 200, description = "Page of trashed tasks", body = Page<Task>),
+        (status = 400, description = "Invalid filter or sort field"),
+        (status = 500, description = "Internal server error")
+    )
+)]
+#[get("/trash")]
+pub async fn get_task_trash(
+    req: HttpRequest,
+    _auth_user: AuthenticatedUser,
+    params: web::Query<PageParams>,
+    filter: web::Query<TaskFilter>,
+    db: web::Data<PgPool>,
+) -> Result<HttpResponse, ServiceError> {
+    let tasks = TaskService::get_trash(&params, &filter, &db)
+        .await?
+        .with_links(req.path(), req.query_string());
     Ok(HttpResponse::Ok().json(tasks))
 }
 
@@ -33,45 +85,109 @@ async fn get_task(
     db: web::Data<PgPool>,
 ) -> Result<HttpResponse, ServiceError> {
     let task = TaskService::get_by_id(id.into_inner(), &db).await?;
-    Ok(HttpResponse::Ok().json(task))
+    Ok(HttpResponse::Ok()
+        .insert_header(etag(task.version))
+        .json(task))
 }
 
 #[post("")]
 async fn create_task(
+    auth_user: AuthenticatedUser,
     task: web::Json<TaskCreate>,
     db: web::Data<PgPool>,
 ) -> Result<HttpResponse, ServiceError> {
-    task.validate()
-        .map_err(|e| ServiceError::ValidationError(e.to_string()))?;
-
-    let task = TaskService::create(task.into_inner(), &db).await?;
+    let task = TaskService::create(task.into_inner(), &auth_user.audit(), &db).await?;
     Ok(HttpResponse::Created().json(task))
 }
 
-#[put("/{id}")]
+/// Create, update and delete tasks in one transaction
+#[utoipa::path(
+    post,
+    path = "/api/tasks/bulk",
+    request_body = BulkTaskRequest,
+    responses(
+        (status = 200, description = "Every operation applied, results in request order", body = BulkTaskResponse),
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "An operation's task was not found; nothing was applied"),
+        (status = 409, description = "An operation targets an archived project; nothing was applied"),
+        (status = 412, description = "An operation's version is stale; nothing was applied"),
+        (status = 422, description = "Invalid operations, reported per index as operations[i]"),
+        (status = 500, description = "Internal server error")
+    )
+)]
+#[post("/bulk")]
+pub async fn bulk_tasks(
+    auth_user: AuthenticatedUser,
+    request: web::Json<BulkTaskRequest>,
+    db: web::Data<PgPool>,
+) -> Result<HttpResponse, ServiceError> {
+    let response = TaskService::bulk(request.into_inner(), &auth_user.audit(), &db).await?;
+    Ok(HttpResponse::Ok().json(response))
+}
+
+/// Update a task with a JSON Merge Patch (RFC 7396)
+#[route("/{id}", method = "PUT", method = "PATCH")]
 async fn update_task(
+    auth_user: AuthenticatedUser,
     id: web::Path<Uuid>,
+    if_match: IfMatch,
     task: web::Json<TaskUpdate>,
     db: web::Data<PgPool>,
 ) -> Result<HttpResponse, ServiceError> {
-    task.validate()
-        .map_err(|e| ServiceError::ValidationError(e.to_string()))?;
-
-    let task = TaskService::update(id.into_inner(), task.into_inner(), &db).await?;
-    Ok(HttpResponse::Ok().json(task))
+    let task = TaskService::update(
+        id.into_inner(),
+        task.into_inner(),
+        if_match.0,
+        &auth_user.audit(),
+        &db,
+    )
+    .await?;
+    Ok(HttpResponse::Ok()
+        .insert_header(etag(task.version))
+        .json(task))
 }
 
 #[delete("/{id}")]
 async fn delete_task(
+    auth_user: AuthenticatedUser,
     id: web::Path<Uuid>,
+    if_match: IfMatch,
     db: web::Data<PgPool>,
 ) -> Result<HttpResponse, ServiceError> {
-    sqlx::query!("DELETE FROM tasks WHERE id = $1", id.into_inner())
-        .execute(&**db)
-        .await?;
+    TaskService::delete(id.into_inner(), if_match.0, &auth_user.audit(), &db).await?;
     Ok(HttpResponse::NoContent().finish())
 }
 
+/// Restore a task from the trash
+#[utoipa::path(
+    post,
+    path = "/api/tasks/{id}/restore",
+    params(
+        ("id" = Uuid, Path, description = "Task UUID"),
+        ("If-Match" = Option<String>, Header, description = "Version the change applies to, as returned in ETag")
+    ),
+    responses(
+        (status = 200, description = "Task restored", body = Task),
+        (status = 404, description = "Task not found in trash"),
+        (status = 409, description = "The task's project is archived"),
+        (status = 412, description = "Task was modified since it was read"),
+        (status = 422, description = "The task's project is in the trash"),
+        (status = 500, description = "Internal server error")
+    )
+)]
+#[post("/{id}/restore")]
+async fn restore_task(
+    auth_user: AuthenticatedUser,
+    id: web::Path<Uuid>,
+    if_match: IfMatch,
+    db: web::Data<PgPool>,
+) -> Result<HttpResponse, ServiceError> {
+    let task = TaskService::restore(id.into_inner(), if_match.0, &auth_user.audit(), &db).await?;
+    Ok(HttpResponse::Ok()
+        .insert_header(etag(task.version))
+        .json(task))
+}
+
 #[get("/project/{project_id}")]
 async fn get_project_tasks(
     project_id: web::Path<Uuid>,
@@ -81,6 +197,26 @@ async fn get_project_tasks(
     Ok(HttpResponse::Ok().json(tasks))
 }
 
+/// Type and lag of the project's task dependencies. Dependencies without an
+/// entry are finish-to-start with no lag.
+#[utoipa::path(
+    get,
+    path = "/api/tasks/project/{project_id}/links",
+    params(("project_id" = Uuid, Path, description = "Project UUID")),
+    responses(
+        (status = 200, description = "Dependency details", body = Vec<TaskLink>),
+        (status = 500, description = "Internal server error")
+    )
+)]
+#[get("/project/{project_id}/links")]
+pub async fn get_project_task_links(
+    project_id: web::Path<Uuid>,
+    db: web::Data<PgPool>,
+) -> Result<HttpResponse, ServiceError> {
+    let links = TaskService::get_links_by_project(project_id.into_inner(), &db).await?;
+    Ok(HttpResponse::Ok().json(links))
+}
+
 #[get("/resource/{resource_id}")]
 async fn get_resource_tasks(
     resource_id: web::Path<Uuid>,
@@ -92,26 +228,34 @@ async fn get_resource_tasks(
 
 #[put("/{id}/progress")]
 async fn update_task_progress(
+    auth_user: AuthenticatedUser,
     id: web::Path<Uuid>,
+    if_match: IfMatch,
     progress: web::Json<i32>,
     db: web::Data<PgPool>,
 ) -> Result<HttpResponse, ServiceError> {
     let progress = progress.into_inner();
     if !(0..=100).contains(&progress) {
-        return Err(ServiceError::ValidationError(
-            "Progress must be between 0 and 100".into(),
+        return Err(ServiceError::invalid_field(
+            "progress",
+            "range",
+            "Progress must be between 0 and 100",
         ));
     }
 
     let task = TaskService::update(
         id.into_inner(),
         TaskUpdate {
-            progress: Some(BigDecimal::from(progress)),
+            progress: Patch::Value(BigDecimal::from(progress)),
             ..Default::default()
         },
+        if_match.0,
+        &auth_user.audit(),
         &db,
     )
     .await?;
 
-    Ok(HttpResponse::Ok().json(task))
+    Ok(HttpResponse::Ok()
+        .insert_header(etag(task.version))
+        .json(task))
 }
@@ -0,0 +1,170 @@
+use crate::errors::ServiceError;
+use crate::extractors::auth::AuthenticatedUser;
+use crate::extractors::precondition::{etag, IfMatch};
+use crate::models::template::{
+    BuiltInTemplate, DocumentKind, DocumentTemplate, DocumentTemplateCreate,
+    DocumentTemplateUpdate, TemplateFilter,
+};
+use crate::services::template_service::TemplateService;
+use actix_web::{delete, get, post, route, web, HttpResponse};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+pub fn config(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/document-templates")
+            .service(create_template)
+            .service(list_templates)
+            .service(get_built_in_template)
+            .service(get_template)
+            .service(update_template)
+            .service(delete_template),
+    );
+}
+
+/// Add a template for generated documents
+///
+/// Templates are Markdown, which renders to Markdown, HTML and PDF, or a
+/// complete HTML page, which renders to HTML only. Substitutions use
+/// Mustache tags such as `{{project.name}}` and `{{#tasks}}…{{/tasks}}`;
+/// the built-in templates show the data available.
+#[utoipa::path(
+    post,
+    path = "/api/document-templates",
+    request_body = DocumentTemplateCreate,
+    responses(
+        (status = 201, description = "Template created", body = DocumentTemplate),
+        (status = 403, description = "Admin role required"),
+        (status = 422, description = "Invalid name or template syntax")
+    )
+)]
+#[post("")]
+pub async fn create_template(
+    auth_user: AuthenticatedUser,
+    template: web::Json<DocumentTemplateCreate>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, ServiceError> {
+    auth_user.require_admin()?;
+    let template =
+        TemplateService::create(template.into_inner(), &auth_user.audit(), &pool).await?;
+    Ok(HttpResponse::Created()
+        .insert_header(etag(template.version))
+        .json(template))
+}
+
+/// List the organisation's templates
+#[utoipa::path(
+    get,
+    path = "/api/document-templates",
+    params(TemplateFilter),
+    responses(
+        (status = 200, description = "Templates by kind, active first", body = [DocumentTemplate])
+    )
+)]
+#[get("")]
+pub async fn list_templates(
+    _auth_user: AuthenticatedUser,
+    filter: web::Query<TemplateFilter>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, ServiceError> {
+    let templates = TemplateService::list(&filter, &pool).await?;
+    Ok(HttpResponse::Ok().json(templates))
+}
+
+/// The template used for a kind while the organisation has no active one
+#[utoipa::path(
+    get,
+    path = "/api/document-templates/built-in/{kind}",
+    params(("kind" = DocumentKind, Path, description = "Kind of document")),
+    responses(
+        (status = 200, description = "The built-in template", body = BuiltInTemplate)
+    )
+)]
+#[get("/built-in/{kind}")]
+pub async fn get_built_in_template(
+    _auth_user: AuthenticatedUser,
+    kind: web::Path<DocumentKind>,
+) -> Result<HttpResponse, ServiceError> {
+    Ok(HttpResponse::Ok().json(TemplateService::built_in(kind.into_inner())))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/document-templates/{id}",
+    params(("id" = Uuid, Path, description = "Template ID")),
+    responses(
+        (status = 200, description = "The template", body = DocumentTemplate),
+        (status = 404, description = "Template not found")
+    )
+)]
+#[get("/{id}")]
+pub async fn get_template(
+    _auth_user: AuthenticatedUser,
+    id: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, ServiceError> {
+    let template = TemplateService::get(id.into_inner(), &pool).await?;
+    Ok(HttpResponse::Ok()
+        .insert_header(etag(template.version))
+        .json(template))
+}
+
+/// Change a template, or make it the one its kind is generated from
+#[utoipa::path(
+    method(put, patch),
+    path = "/api/document-templates/{id}",
+    params(("id" = Uuid, Path, description = "Template ID")),
+    request_body = DocumentTemplateUpdate,
+    responses(
+        (status = 200, description = "Updated template", body = DocumentTemplate),
+        (status = 403, description = "Admin role required"),
+        (status = 404, description = "Template not found"),
+        (status = 412, description = "The template changed since it was read"),
+        (status = 422, description = "Invalid name or template syntax")
+    )
+)]
+#[route("/{id}", method = "PUT", method = "PATCH")]
+pub async fn update_template(
+    auth_user: AuthenticatedUser,
+    id: web::Path<Uuid>,
+    if_match: IfMatch,
+    update: web::Json<DocumentTemplateUpdate>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, ServiceError> {
+    auth_user.require_admin()?;
+    let template = TemplateService::update(
+        id.into_inner(),
+        update.into_inner(),
+        if_match.0,
+        &auth_user.audit(),
+        &pool,
+    )
+    .await?;
+    Ok(HttpResponse::Ok()
+        .insert_header(etag(template.version))
+        .json(template))
+}
+
+/// Delete a template; its kind falls back to the built-in template
+#[utoipa::path(
+    delete,
+    path = "/api/document-templates/{id}",
+    params(("id" = Uuid, Path, description = "Template ID")),
+    responses(
+        (status = 204, description = "Template deleted"),
+        (status = 403, description = "Admin role required"),
+        (status = 404, description = "Template not found"),
+        (status = 412, description = "The template changed since it was read")
+    )
+)]
+#[delete("/{id}")]
+pub async fn delete_template(
+    auth_user: AuthenticatedUser,
+    id: web::Path<Uuid>,
+    if_match: IfMatch,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, ServiceError> {
+    auth_user.require_admin()?;
+    TemplateService::delete(id.into_inner(), if_match.0, &auth_user.audit(), &pool).await?;
+    Ok(HttpResponse::NoContent().finish())
+}
@@ -0,0 +1,447 @@
+use crate::errors::ServiceError;
+use crate::extractors::auth::AuthenticatedUser;
+use crate::extractors::precondition::{etag, IfMatch};
+use crate::models::testing::{
+    TestCase, TestCaseCreate, TestCaseFilter, TestCaseUpdate, TestResult, TestResultRecord,
+    TestRun, TestRunCreate, TestRunDetails, TestRunFilter, TestSuite, TestSuiteCreate,
+    TestSuiteFilter, TestSuiteUpdate,
+};
+use crate::models::user::UserRole;
+use crate::services::testing_service::TestingService;
+use actix_web::{delete, get, post, route, web, HttpResponse};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+pub fn config(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/test-suites")
+            .service(create_suite)
+            .service(list_suites)
+            .service(get_suite)
+            .service(update_suite)
+            .service(delete_suite),
+    )
+    .service(
+        web::scope("/test-cases")
+            .service(create_case)
+            .service(list_cases)
+            .service(get_case)
+            .service(update_case)
+            .service(delete_case),
+    )
+    .service(
+        web::scope("/test-runs")
+            .service(create_run)
+            .service(list_runs)
+            .service(get_run)
+            .service(record_result),
+    );
+}
+
+fn require_tester(auth_user: &AuthenticatedUser) -> Result<(), ServiceError> {
+    match auth_user.role {
+        UserRole::Admin | UserRole::ProjectManager | UserRole::QaEngineer => Ok(()),
+        _ => Err(ServiceError::Forbidden),
+    }
+}
+
+/// Add a test suite to a project
+#[utoipa::path(
+    post,
+    path = "/api/test-suites",
+    request_body = TestSuiteCreate,
+    responses(
+        (status = 201, description = "Test suite created", body = TestSuite),
+        (status = 403, description = "Admin, project manager or QA engineer role required"),
+        (status = 404, description = "Project not found"),
+        (status = 409, description = "The project is archived")
+    )
+)]
+#[post("")]
+pub async fn create_suite(
+    auth_user: AuthenticatedUser,
+    suite: web::Json<TestSuiteCreate>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, ServiceError> {
+    require_tester(&auth_user)?;
+    let suite = TestingService::create_suite(
+        suite.into_inner(),
+        auth_user.user_id,
+        &auth_user.role,
+        &auth_user.audit(),
+        &pool,
+    )
+    .await?;
+    Ok(HttpResponse::Created()
+        .insert_header(etag(suite.version))
+        .json(suite))
+}
+
+/// List a project's test suites by name
+#[utoipa::path(
+    get,
+    path = "/api/test-suites",
+    params(TestSuiteFilter),
+    responses(
+        (status = 200, description = "Test suites", body = [TestSuite]),
+        (status = 404, description = "Project not found")
+    )
+)]
+#[get("")]
+pub async fn list_suites(
+    auth_user: AuthenticatedUser,
+    filter: web::Query<TestSuiteFilter>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, ServiceError> {
+    let suites =
+        TestingService::list_suites(filter.project_id, auth_user.user_id, &auth_user.role, &pool)
+            .await?;
+    Ok(HttpResponse::Ok().json(suites))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/test-suites/{id}",
+    params(("id" = Uuid, Path, description = "Test suite ID")),
+    responses(
+        (status = 200, description = "The test suite", body = TestSuite),
+        (status = 404, description = "Test suite not found")
+    )
+)]
+#[get("/{id}")]
+pub async fn get_suite(
+    auth_user: AuthenticatedUser,
+    id: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, ServiceError> {
+    let suite =
+        TestingService::get_suite(id.into_inner(), auth_user.user_id, &auth_user.role, &pool)
+            .await?;
+    Ok(HttpResponse::Ok()
+        .insert_header(etag(suite.version))
+        .json(suite))
+}
+
+#[utoipa::path(
+    method(put, patch),
+    path = "/api/test-suites/{id}",
+    params(("id" = Uuid, Path, description = "Test suite ID")),
+    request_body = TestSuiteUpdate,
+    responses(
+        (status = 200, description = "Updated test suite", body = TestSuite),
+        (status = 403, description = "Admin, project manager or QA engineer role required"),
+        (status = 404, description = "Test suite not found"),
+        (status = 409, description = "The project is archived"),
+        (status = 412, description = "The test suite changed since it was read")
+    )
+)]
+#[route("/{id}", method = "PUT", method = "PATCH")]
+pub async fn update_suite(
+    auth_user: AuthenticatedUser,
+    id: web::Path<Uuid>,
+    if_match: IfMatch,
+    update: web::Json<TestSuiteUpdate>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, ServiceError> {
+    require_tester(&auth_user)?;
+    let suite = TestingService::update_suite(
+        id.into_inner(),
+        update.into_inner(),
+        if_match.0,
+        auth_user.user_id,
+        &auth_user.role,
+        &auth_user.audit(),
+        &pool,
+    )
+    .await?;
+    Ok(HttpResponse::Ok()
+        .insert_header(etag(suite.version))
+        .json(suite))
+}
+
+/// Delete a test suite with its test cases and their results
+#[utoipa::path(
+    delete,
+    path = "/api/test-suites/{id}",
+    params(("id" = Uuid, Path, description = "Test suite ID")),
+    responses(
+        (status = 204, description = "Test suite deleted"),
+        (status = 403, description = "Admin, project manager or QA engineer role required"),
+        (status = 404, description = "Test suite not found"),
+        (status = 409, description = "The project is archived"),
+        (status = 412, description = "The test suite changed since it was read")
+    )
+)]
+#[delete("/{id}")]
+pub async fn delete_suite(
+    auth_user: AuthenticatedUser,
+    id: web::Path<Uuid>,
+    if_match: IfMatch,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, ServiceError> {
+    require_tester(&auth_user)?;
+    TestingService::delete_suite(
+        id.into_inner(),
+        if_match.0,
+        auth_user.user_id,
+        &auth_user.role,
+        &auth_user.audit(),
+        &pool,
+    )
+    .await?;
+    Ok(HttpResponse::NoContent().finish())
+}
+
+/// Add a test case, with its steps in order, to a suite
+#[utoipa::path(
+    post,
+    path = "/api/test-cases",
+    request_body = TestCaseCreate,
+    responses(
+        (status = 201, description = "Test case created", body = TestCase),
+        (status = 403, description = "Admin, project manager or QA engineer role required"),
+        (status = 404, description = "Test suite not found"),
+        (status = 409, description = "The project is archived")
+    )
+)]
+#[post("")]
+pub async fn create_case(
+    auth_user: AuthenticatedUser,
+    case: web::Json<TestCaseCreate>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, ServiceError> {
+    require_tester(&auth_user)?;
+    let case = TestingService::create_case(
+        case.into_inner(),
+        auth_user.user_id,
+        &auth_user.role,
+        &auth_user.audit(),
+        &pool,
+    )
+    .await?;
+    Ok(HttpResponse::Created()
+        .insert_header(etag(case.version))
+        .json(case))
+}
+
+/// List the test cases of a suite or project by title
+#[utoipa::path(
+    get,
+    path = "/api/test-cases",
+    params(TestCaseFilter),
+    responses(
+        (status = 200, description = "Test cases", body = [TestCase]),
+        (status = 404, description = "Project or test suite not found"),
+        (status = 422, description = "Neither a project nor a suite given")
+    )
+)]
+#[get("")]
+pub async fn list_cases(
+    auth_user: AuthenticatedUser,
+    filter: web::Query<TestCaseFilter>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, ServiceError> {
+    let cases =
+        TestingService::list_cases(&filter, auth_user.user_id, &auth_user.role, &pool).await?;
+    Ok(HttpResponse::Ok().json(cases))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/test-cases/{id}",
+    params(("id" = Uuid, Path, description = "Test case ID")),
+    responses(
+        (status = 200, description = "The test case", body = TestCase),
+        (status = 404, description = "Test case not found")
+    )
+)]
+#[get("/{id}")]
+pub async fn get_case(
+    auth_user: AuthenticatedUser,
+    id: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, ServiceError> {
+    let case = TestingService::get_case(id.into_inner(), auth_user.user_id, &auth_user.role, &pool)
+        .await?;
+    Ok(HttpResponse::Ok()
+        .insert_header(etag(case.version))
+        .json(case))
+}
+
+/// Update a test case; steps given replace all of its steps
+#[utoipa::path(
+    method(put, patch),
+    path = "/api/test-cases/{id}",
+    params(("id" = Uuid, Path, description = "Test case ID")),
+    request_body = TestCaseUpdate,
+    responses(
+        (status = 200, description = "Updated test case", body = TestCase),
+        (status = 403, description = "Admin, project manager or QA engineer role required"),
+        (status = 404, description = "Test case not found"),
+        (status = 409, description = "The project is archived"),
+        (status = 412, description = "The test case changed since it was read")
+    )
+)]
+#[route("/{id}", method = "PUT", method = "PATCH")]
+pub async fn update_case(
+    auth_user: AuthenticatedUser,
+    id: web::Path<Uuid>,
+    if_match: IfMatch,
+    update: web::Json<TestCaseUpdate>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, ServiceError> {
+    require_tester(&auth_user)?;
+    let case = TestingService::update_case(
+        id.into_inner(),
+        update.into_inner(),
+        if_match.0,
+        auth_user.user_id,
+        &auth_user.role,
+        &auth_user.audit(),
+        &pool,
+    )
+    .await?;
+    Ok(HttpResponse::Ok()
+        .insert_header(etag(case.version))
+        .json(case))
+}
+
+/// Delete a test case with its results and trace links
+#[utoipa::path(
+    delete,
+    path = "/api/test-cases/{id}",
+    params(("id" = Uuid, Path, description = "Test case ID")),
+    responses(
+        (status = 204, description = "Test case deleted"),
+        (status = 403, description = "Admin, project manager or QA engineer role required"),
+        (status = 404, description = "Test case not found"),
+        (status = 409, description = "The project is archived"),
+        (status = 412, description = "The test case changed since it was read")
+    )
+)]
+#[delete("/{id}")]
+pub async fn delete_case(
+    auth_user: AuthenticatedUser,
+    id: web::Path<Uuid>,
+    if_match: IfMatch,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, ServiceError> {
+    require_tester(&auth_user)?;
+    TestingService::delete_case(
+        id.into_inner(),
+        if_match.0,
+        auth_user.user_id,
+        &auth_user.role,
+        &auth_user.audit(),
+        &pool,
+    )
+    .await?;
+    Ok(HttpResponse::NoContent().finish())
+}
+
+/// Start a test run against a build
+#[utoipa::path(
+    post,
+    path = "/api/test-runs",
+    request_body = TestRunCreate,
+    responses(
+        (status = 201, description = "Test run created", body = TestRun),
+        (status = 403, description = "Admin, project manager or QA engineer role required"),
+        (status = 404, description = "Project not found"),
+        (status = 409, description = "The project is archived")
+    )
+)]
+#[post("")]
+pub async fn create_run(
+    auth_user: AuthenticatedUser,
+    run: web::Json<TestRunCreate>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, ServiceError> {
+    require_tester(&auth_user)?;
+    let run = TestingService::create_run(
+        run.into_inner(),
+        auth_user.user_id,
+        &auth_user.role,
+        &auth_user.audit(),
+        &pool,
+    )
+    .await?;
+    Ok(HttpResponse::Created().json(run))
+}
+
+/// List a project's test runs, newest first
+#[utoipa::path(
+    get,
+    path = "/api/test-runs",
+    params(TestRunFilter),
+    responses(
+        (status = 200, description = "Test runs", body = [TestRun]),
+        (status = 404, description = "Project not found")
+    )
+)]
+#[get("")]
+pub async fn list_runs(
+    auth_user: AuthenticatedUser,
+    filter: web::Query<TestRunFilter>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, ServiceError> {
+    let runs =
+        TestingService::list_runs(&filter, auth_user.user_id, &auth_user.role, &pool).await?;
+    Ok(HttpResponse::Ok().json(runs))
+}
+
+/// Get a test run with its results
+#[utoipa::path(
+    get,
+    path = "/api/test-runs/{id}",
+    params(("id" = Uuid, Path, description = "Test run ID")),
+    responses(
+        (status = 200, description = "The run and its results", body = TestRunDetails),
+        (status = 404, description = "Test run not found")
+    )
+)]
+#[get("/{id}")]
+pub async fn get_run(
+    auth_user: AuthenticatedUser,
+    id: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, ServiceError> {
+    let run =
+        TestingService::get_run(id.into_inner(), auth_user.user_id, &auth_user.role, &pool).await?;
+    Ok(HttpResponse::Ok().json(run))
+}
+
+/// Record the outcome of a test case in a run
+///
+/// The caller is recorded as the tester. Recording a case again in the
+/// same run replaces its result.
+#[utoipa::path(
+    post,
+    path = "/api/test-runs/{id}/results",
+    params(("id" = Uuid, Path, description = "Test run ID")),
+    request_body = TestResultRecord,
+    responses(
+        (status = 200, description = "The recorded result", body = TestResult),
+        (status = 404, description = "Test run not found"),
+        (status = 409, description = "The project is archived"),
+        (status = 422, description = "The test case is not in the run's project")
+    )
+)]
+#[post("/{id}/results")]
+pub async fn record_result(
+    auth_user: AuthenticatedUser,
+    id: web::Path<Uuid>,
+    record: web::Json<TestResultRecord>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, ServiceError> {
+    let result = TestingService::record_result(
+        id.into_inner(),
+        record.into_inner(),
+        auth_user.user_id,
+        &auth_user.role,
+        &auth_user.audit(),
+        &pool,
+    )
+    .await?;
+    Ok(HttpResponse::Ok().json(result))
+}
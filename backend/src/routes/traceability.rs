@@ -0,0 +1,273 @@
+use crate::errors::ServiceError;
+use crate::extractors::auth::AuthenticatedUser;
+use crate::extractors::precondition::{etag, IfMatch};
+use crate::models::traceability::{
+    DesignItem, DesignItemCreate, DesignItemFilter, DesignItemUpdate, TraceLink, TraceLinkCreate,
+    TraceLinkFilter,
+};
+use crate::models::user::UserRole;
+use crate::services::traceability_service::TraceabilityService;
+use actix_web::{delete, get, post, route, web, HttpResponse};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+pub fn config(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/design-items")
+            .service(create_design_item)
+            .service(list_design_items)
+            .service(get_design_item)
+            .service(update_design_item)
+            .service(delete_design_item),
+    )
+    .service(
+        web::scope("/trace-links")
+            .service(create_trace_link)
+            .service(list_trace_links)
+            .service(delete_trace_link),
+    );
+}
+
+fn require_designer(auth_user: &AuthenticatedUser) -> Result<(), ServiceError> {
+    match auth_user.role {
+        UserRole::Admin | UserRole::ProjectManager | UserRole::Developer => Ok(()),
+        _ => Err(ServiceError::Forbidden),
+    }
+}
+
+/// Add a design item to a project
+#[utoipa::path(
+    post,
+    path = "/api/design-items",
+    request_body = DesignItemCreate,
+    responses(
+        (status = 201, description = "Design item created", body = DesignItem),
+        (status = 403, description = "Admin, project manager or developer role required"),
+        (status = 404, description = "Project not found"),
+        (status = 409, description = "The project is archived")
+    )
+)]
+#[post("")]
+pub async fn create_design_item(
+    auth_user: AuthenticatedUser,
+    item: web::Json<DesignItemCreate>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, ServiceError> {
+    require_designer(&auth_user)?;
+    let item = TraceabilityService::create_design_item(
+        item.into_inner(),
+        auth_user.user_id,
+        &auth_user.role,
+        &auth_user.audit(),
+        &pool,
+    )
+    .await?;
+    Ok(HttpResponse::Created()
+        .insert_header(etag(item.version))
+        .json(item))
+}
+
+/// List a project's design items by title
+#[utoipa::path(
+    get,
+    path = "/api/design-items",
+    params(DesignItemFilter),
+    responses(
+        (status = 200, description = "Design items", body = [DesignItem]),
+        (status = 404, description = "Project not found")
+    )
+)]
+#[get("")]
+pub async fn list_design_items(
+    auth_user: AuthenticatedUser,
+    filter: web::Query<DesignItemFilter>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, ServiceError> {
+    let items = TraceabilityService::list_design_items(
+        filter.project_id,
+        auth_user.user_id,
+        &auth_user.role,
+        &pool,
+    )
+    .await?;
+    Ok(HttpResponse::Ok().json(items))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/design-items/{id}",
+    params(("id" = Uuid, Path, description = "Design item ID")),
+    responses(
+        (status = 200, description = "The design item", body = DesignItem),
+        (status = 404, description = "Design item not found")
+    )
+)]
+#[get("/{id}")]
+pub async fn get_design_item(
+    auth_user: AuthenticatedUser,
+    id: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, ServiceError> {
+    let item = TraceabilityService::get_design_item(
+        id.into_inner(),
+        auth_user.user_id,
+        &auth_user.role,
+        &pool,
+    )
+    .await?;
+    Ok(HttpResponse::Ok()
+        .insert_header(etag(item.version))
+        .json(item))
+}
+
+#[utoipa::path(
+    method(put, patch),
+    path = "/api/design-items/{id}",
+    params(("id" = Uuid, Path, description = "Design item ID")),
+    request_body = DesignItemUpdate,
+    responses(
+        (status = 200, description = "Updated design item", body = DesignItem),
+        (status = 403, description = "Admin, project manager or developer role required"),
+        (status = 404, description = "Design item not found"),
+        (status = 409, description = "The project is archived"),
+        (status = 412, description = "The design item changed since it was read")
+    )
+)]
+#[route("/{id}", method = "PUT", method = "PATCH")]
+pub async fn update_design_item(
+    auth_user: AuthenticatedUser,
+    id: web::Path<Uuid>,
+    if_match: IfMatch,
+    update: web::Json<DesignItemUpdate>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, ServiceError> {
+    require_designer(&auth_user)?;
+    let item = TraceabilityService::update_design_item(
+        id.into_inner(),
+        update.into_inner(),
+        if_match.0,
+        auth_user.user_id,
+        &auth_user.role,
+        &auth_user.audit(),
+        &pool,
+    )
+    .await?;
+    Ok(HttpResponse::Ok()
+        .insert_header(etag(item.version))
+        .json(item))
+}
+
+/// Delete a design item and its trace links
+#[utoipa::path(
+    delete,
+    path = "/api/design-items/{id}",
+    params(("id" = Uuid, Path, description = "Design item ID")),
+    responses(
+        (status = 204, description = "Design item deleted"),
+        (status = 403, description = "Admin, project manager or developer role required"),
+        (status = 404, description = "Design item not found"),
+        (status = 409, description = "The project is archived"),
+        (status = 412, description = "The design item changed since it was read")
+    )
+)]
+#[delete("/{id}")]
+pub async fn delete_design_item(
+    auth_user: AuthenticatedUser,
+    id: web::Path<Uuid>,
+    if_match: IfMatch,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, ServiceError> {
+    require_designer(&auth_user)?;
+    TraceabilityService::delete_design_item(
+        id.into_inner(),
+        if_match.0,
+        auth_user.user_id,
+        &auth_user.role,
+        &auth_user.audit(),
+        &pool,
+    )
+    .await?;
+    Ok(HttpResponse::NoContent().finish())
+}
+
+/// Trace a requirement or design item to what realises it
+///
+/// Links lead from a requirement to a design item, task or test case, from
+/// a design item to a task, or from a task to a test case, within one
+/// project.
+#[utoipa::path(
+    post,
+    path = "/api/trace-links",
+    request_body = TraceLinkCreate,
+    responses(
+        (status = 201, description = "Link created, or the existing one", body = TraceLink),
+        (status = 404, description = "Source record not found"),
+        (status = 409, description = "The project is archived"),
+        (status = 422, description = "The records cannot be linked")
+    )
+)]
+#[post("")]
+pub async fn create_trace_link(
+    auth_user: AuthenticatedUser,
+    link: web::Json<TraceLinkCreate>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, ServiceError> {
+    let link = TraceabilityService::link(
+        link.into_inner(),
+        auth_user.user_id,
+        &auth_user.role,
+        &auth_user.audit(),
+        &pool,
+    )
+    .await?;
+    Ok(HttpResponse::Created().json(link))
+}
+
+/// List the trace links of a project, or those from or to one record
+#[utoipa::path(
+    get,
+    path = "/api/trace-links",
+    params(TraceLinkFilter),
+    responses(
+        (status = 200, description = "Links, oldest first", body = [TraceLink]),
+        (status = 404, description = "Project or record not found"),
+        (status = 422, description = "Neither a project nor a record given")
+    )
+)]
+#[get("")]
+pub async fn list_trace_links(
+    auth_user: AuthenticatedUser,
+    filter: web::Query<TraceLinkFilter>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, ServiceError> {
+    let links =
+        TraceabilityService::links(&filter, auth_user.user_id, &auth_user.role, &pool).await?;
+    Ok(HttpResponse::Ok().json(links))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/trace-links/{id}",
+    params(("id" = Uuid, Path, description = "Trace link ID")),
+    responses(
+        (status = 204, description = "Link removed"),
+        (status = 404, description = "Trace link not found"),
+        (status = 409, description = "The project is archived")
+    )
+)]
+#[delete("/{id}")]
+pub async fn delete_trace_link(
+    auth_user: AuthenticatedUser,
+    id: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, ServiceError> {
+    TraceabilityService::unlink(
+        id.into_inner(),
+        auth_user.user_id,
+        &auth_user.role,
+        &auth_user.audit(),
+        &pool,
+    )
+    .await?;
+    Ok(HttpResponse::NoContent().finish())
+}
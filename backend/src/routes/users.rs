@@ -1,7 +1,12 @@
 use crate::errors::ServiceError;
-use crate::models::user::{User, UserCreate, UserUpdate};
+use crate::extractors::auth::AuthenticatedUser;
+use crate::extractors::precondition::{etag, IfMatch};
+use crate::models::pagination::{Page, PageParams};
+use crate::models::user::{
+    Invitation, InvitationCreate, ProfileUpdate, User, UserCreate, UserFilter, UserUpdate,
+};
 use crate::services::user_service::UserService;
-use actix_web::{delete, get, post, put, web, HttpResponse};
+use actix_web::{delete, get, post, put, route, web, HttpRequest, HttpResponse};
 use sqlx::PgPool;
 use uuid::Uuid;
 use validator::Validate;
@@ -12,18 +17,20 @@ use validator::Validate;
     request_body = UserCreate,
     responses(
         (status = 201, description = "User created successfully", body = User),
-        (status = 400, description = "Invalid input"),
+        (status = 422, description = "Validation failed"),
+        (status = 403, description = "Admin role required"),
         (status = 500, description = "Internal server error")
     )
 )]
-#[post("/api/users")]
+#[post("")]
 pub async fn create_user(
+    auth_user: AuthenticatedUser,
     pool: web::Data<PgPool>,
     user: web::Json<UserCreate>,
 ) -> Result<HttpResponse, actix_web::Error> {
-    user.validate()
-        .map_err(|e| ServiceError::ValidationError(e.to_string()))?;
-    let user = UserService::create(user.into_inner(), &pool).await?;
+    auth_user.require_admin()?;
+    user.validate().map_err(ServiceError::from)?;
+    let user = UserService::create(user.into_inner(), &auth_user.audit(), &pool).await?;
     Ok(HttpResponse::Created().json(user))
 }
 
@@ -39,80 +46,278 @@ pub async fn create_user(
         ("id" = Uuid, Path, description = "User ID")
     )
 )]
-#[get("/api/users/{id}")]
+#[get("/{id}")]
 pub async fn get_user(
+    _auth_user: AuthenticatedUser,
     pool: web::Data<PgPool>,
     id: web::Path<Uuid>,
 ) -> Result<HttpResponse, actix_web::Error> {
     let user = UserService::get_by_id(id.into_inner(), &pool).await?;
-    Ok(HttpResponse::Ok().json(user))
+    Ok(HttpResponse::Ok()
+        .insert_header(etag(user.version))
+        .json(user))
 }
 
 #[utoipa::path(
-    put,
+    method(put, patch),
     path = "/api/users/{id}",
     request_body = UserUpdate,
     responses(
         (status = 200, description = "User updated successfully", body = User),
-        (status = 400, description = "Invalid input"),
+        (status = 422, description = "Validation failed"),
+        (status = 403, description = "Admin role required"),
         (status = 404, description = "User not found"),
+        (status = 412, description = "User was modified since it was read"),
         (status = 500, description = "Internal server error")
     ),
     params(
-        ("id" = Uuid, Path, description = "User ID")
+        ("id" = Uuid, Path, description = "User ID"),
+        ("If-Match" = Option<String>, Header, description = "Version the change applies to, as returned in ETag")
     )
 )]
-#[put("/api/users/{id}")]
+#[route("/{id}", method = "PUT", method = "PATCH")]
 pub async fn update_user(
+    auth_user: AuthenticatedUser,
     pool: web::Data<PgPool>,
     id: web::Path<Uuid>,
+    if_match: IfMatch,
     user: web::Json<UserUpdate>,
 ) -> Result<HttpResponse, actix_web::Error> {
-    user.validate()
-        .map_err(|e| ServiceError::ValidationError(e.to_string()))?;
-    let user = UserService::update(id.into_inner(), user.into_inner(), &pool).await?;
-    Ok(HttpResponse::Ok().json(user))
+    auth_user.require_admin()?;
+    user.validate().map_err(ServiceError::from)?;
+    let user = UserService::update(
+        id.into_inner(),
+        user.into_inner(),
+        if_match.0,
+        &auth_user.audit(),
+        &pool,
+    )
+    .await?;
+    Ok(HttpResponse::Ok()
+        .insert_header(etag(user.version))
+        .json(user))
 }
 
 #[utoipa::path(
-    delete,
-    path = "/api/users/{id}",
+    post,
+    path = "/api/users/{id}/deactivate",
     responses(
-        (status = 204, description = "User deleted successfully"),
+        (status = 200, description = "User deactivated", body = User),
+        (status = 422, description = "Admins cannot deactivate themselves"),
+        (status = 403, description = "Admin role required"),
         (status = 404, description = "User not found"),
-        (status = 500, description = "Internal server error")
+        (status = 412, description = "User was modified since it was read")
     ),
     params(
-        ("id" = Uuid, Path, description = "User ID")
+        ("id" = Uuid, Path, description = "User ID"),
+        ("If-Match" = Option<String>, Header, description = "Version the change applies to, as returned in ETag")
     )
 )]
-#[delete("/api/users/{id}")]
-pub async fn delete_user(
+#[post("/{id}/deactivate")]
+pub async fn deactivate_user(
+    auth_user: AuthenticatedUser,
     pool: web::Data<PgPool>,
     id: web::Path<Uuid>,
+    if_match: IfMatch,
 ) -> Result<HttpResponse, actix_web::Error> {
-    UserService::delete(id.into_inner(), &pool).await?;
-    Ok(HttpResponse::NoContent().finish())
+    auth_user.require_admin()?;
+    let id = id.into_inner();
+    if id == auth_user.user_id {
+        return Err(ServiceError::UnprocessableEntity(
+            "You cannot deactivate your own account".into(),
+        )
+        .into());
+    }
+    let user = UserService::deactivate(id, if_match.0, &auth_user.audit(), &pool).await?;
+    Ok(HttpResponse::Ok()
+        .insert_header(etag(user.version))
+        .json(user))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/users/{id}/reactivate",
+    responses(
+        (status = 200, description = "User reactivated", body = User),
+        (status = 403, description = "Admin role required"),
+        (status = 404, description = "User not found"),
+        (status = 412, description = "User was modified since it was read")
+    ),
+    params(
+        ("id" = Uuid, Path, description = "User ID"),
+        ("If-Match" = Option<String>, Header, description = "Version the change applies to, as returned in ETag")
+    )
+)]
+#[post("/{id}/reactivate")]
+pub async fn reactivate_user(
+    auth_user: AuthenticatedUser,
+    pool: web::Data<PgPool>,
+    id: web::Path<Uuid>,
+    if_match: IfMatch,
+) -> Result<HttpResponse, actix_web::Error> {
+    auth_user.require_admin()?;
+    let user =
+        UserService::reactivate(id.into_inner(), if_match.0, &auth_user.audit(), &pool).await?;
+    Ok(HttpResponse::Ok()
+        .insert_header(etag(user.version))
+        .json(user))
 }
 
 #[utoipa::path(
     get,
     path = "/api/users",
+    params(PageParams, UserFilter),
     responses(
-        (status = 200, description = "List of users", body = Vec<User>),
+        (status = 200, description = "Page of users", body = Page<User>),
+        (status = 400, description = "Invalid filter or sort field"),
         (status = 500, description = "Internal server error")
     )
 )]
-#[get("/api/users")]
-pub async fn list_users(pool: web::Data<PgPool>) -> Result<HttpResponse, actix_web::Error> {
-    let users = UserService::list(&pool).await?;
+#[get("")]
+pub async fn list_users(
+    req: HttpRequest,
+    _auth_user: AuthenticatedUser,
+    params: web::Query<PageParams>,
+    filter: web::Query<UserFilter>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let users = UserService::list(&params, &filter, &pool)
+        .await?
+        .with_links(req.path(), req.query_string());
     Ok(HttpResponse::Ok().json(users))
 }
 
+#[utoipa::path(
+    post,
+    path = "/api/users/invitations",
+    request_body = InvitationCreate,
+    responses(
+        (status = 201, description = "Invitation created; its email is sent in the background", body = Invitation),
+        (status = 409, description = "User already exists or has a pending invitation"),
+        (status = 422, description = "Validation failed"),
+        (status = 403, description = "Admin role required")
+    )
+)]
+#[post("/invitations")]
+pub async fn invite_user(
+    auth_user: AuthenticatedUser,
+    pool: web::Data<PgPool>,
+    invitation: web::Json<InvitationCreate>,
+) -> Result<HttpResponse, actix_web::Error> {
+    auth_user.require_admin()?;
+    invitation.validate().map_err(ServiceError::from)?;
+    let invitation =
+        UserService::invite(invitation.into_inner(), &auth_user.audit(), &pool).await?;
+    Ok(HttpResponse::Created().json(invitation))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/users/invitations",
+    responses(
+        (status = 200, description = "Pending invitations", body = Vec<Invitation>),
+        (status = 403, description = "Admin role required")
+    )
+)]
+#[get("/invitations")]
+pub async fn list_invitations(
+    auth_user: AuthenticatedUser,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    auth_user.require_admin()?;
+    let invitations = UserService::list_invitations(&pool).await?;
+    Ok(HttpResponse::Ok().json(invitations))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/users/invitations/{id}",
+    responses(
+        (status = 204, description = "Invitation revoked"),
+        (status = 403, description = "Admin role required"),
+        (status = 404, description = "Invitation not found")
+    ),
+    params(
+        ("id" = Uuid, Path, description = "Invitation ID")
+    )
+)]
+#[delete("/invitations/{id}")]
+pub async fn revoke_invitation(
+    auth_user: AuthenticatedUser,
+    pool: web::Data<PgPool>,
+    id: web::Path<Uuid>,
+) -> Result<HttpResponse, actix_web::Error> {
+    auth_user.require_admin()?;
+    UserService::revoke_invitation(id.into_inner(), &auth_user.audit(), &pool).await?;
+    Ok(HttpResponse::NoContent().finish())
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/me",
+    responses(
+        (status = 200, description = "The caller's profile", body = User),
+        (status = 401, description = "Not authenticated")
+    )
+)]
+#[get("")]
+pub async fn get_me(
+    auth_user: AuthenticatedUser,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let user = UserService::get_by_id(auth_user.user_id, &pool).await?;
+    Ok(HttpResponse::Ok()
+        .insert_header(etag(user.version))
+        .json(user))
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/me",
+    params(
+        ("If-Match" = Option<String>, Header, description = "Version the change applies to, as returned in ETag")
+    ),
+    request_body = ProfileUpdate,
+    responses(
+        (status = 200, description = "Profile updated", body = User),
+        (status = 422, description = "Validation failed"),
+        (status = 401, description = "Current password is wrong"),
+        (status = 412, description = "Profile was modified since it was read")
+    )
+)]
+#[put("")]
+pub async fn update_me(
+    auth_user: AuthenticatedUser,
+    pool: web::Data<PgPool>,
+    if_match: IfMatch,
+    update: web::Json<ProfileUpdate>,
+) -> Result<HttpResponse, actix_web::Error> {
+    update.validate().map_err(ServiceError::from)?;
+    let user = UserService::update_profile(
+        auth_user.user_id,
+        update.into_inner(),
+        if_match.0,
+        &auth_user.audit(),
+        &pool,
+    )
+    .await?;
+    Ok(HttpResponse::Ok()
+        .insert_header(etag(user.version))
+        .json(user))
+}
+
 pub fn config(cfg: &mut web::ServiceConfig) {
-    cfg.service(create_user)
-        .service(get_user)
-        .service(update_user)
-        .service(delete_user)
-        .service(list_users);
+    cfg.service(
+        web::scope("/users")
+            .service(create_user)
+            .service(list_users)
+            .service(invite_user)
+            .service(list_invitations)
+            .service(revoke_invitation)
+            .service(get_user)
+            .service(update_user)
+            .service(deactivate_user)
+            .service(reactivate_user),
+    )
+    .service(web::scope("/me").service(get_me).service(update_me));
 }
@@ -0,0 +1,201 @@
+use crate::errors::ServiceError;
+use crate::extractors::auth::AuthenticatedUser;
+use crate::models::pagination::{Page, PageParams};
+use crate::models::webhook::{
+    DeliveryFilter, Webhook, WebhookCreate, WebhookCreated, WebhookDelivery, WebhookUpdate,
+};
+use crate::services::webhook_service::WebhookService;
+use actix_web::{delete, get, post, route, web, HttpRequest, HttpResponse};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+pub fn config(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/webhooks")
+            .service(create_webhook)
+            .service(list_webhooks)
+            .service(get_webhook)
+            .service(update_webhook)
+            .service(delete_webhook)
+            .service(list_deliveries)
+            .service(redeliver),
+    );
+}
+
+/// Subscribe a URL to events
+///
+/// Each event is sent as a JSON POST carrying `X-Webhook-Event`,
+/// `X-Webhook-Delivery`, `X-Webhook-Timestamp` and `X-Webhook-Signature`
+/// headers. The signature is `sha256=` followed by the hex HMAC-SHA256 of
+/// `{timestamp}.{body}` under the secret. Failed deliveries are retried with
+/// exponential backoff. The secret is only returned here.
+#[utoipa::path(
+    post,
+    path = "/api/webhooks",
+    request_body = WebhookCreate,
+    responses(
+        (status = 201, description = "Webhook created", body = WebhookCreated),
+        (status = 403, description = "Admin role required"),
+        (status = 422, description = "Invalid URL, secret or events")
+    )
+)]
+#[post("")]
+pub async fn create_webhook(
+    auth_user: AuthenticatedUser,
+    webhook: web::Json<WebhookCreate>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, ServiceError> {
+    auth_user.require_admin()?;
+    let created = WebhookService::create(webhook.into_inner(), &auth_user.audit(), &pool).await?;
+    Ok(HttpResponse::Created().json(created))
+}
+
+/// List webhook subscriptions
+#[utoipa::path(
+    get,
+    path = "/api/webhooks",
+    responses(
+        (status = 200, description = "Every webhook", body = [Webhook]),
+        (status = 403, description = "Admin role required")
+    )
+)]
+#[get("")]
+pub async fn list_webhooks(
+    auth_user: AuthenticatedUser,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, ServiceError> {
+    auth_user.require_admin()?;
+    let webhooks = WebhookService::list(&pool).await?;
+    Ok(HttpResponse::Ok().json(webhooks))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/webhooks/{id}",
+    params(("id" = Uuid, Path, description = "Webhook ID")),
+    responses(
+        (status = 200, description = "The webhook", body = Webhook),
+        (status = 403, description = "Admin role required"),
+        (status = 404, description = "Webhook not found")
+    )
+)]
+#[get("/{id}")]
+pub async fn get_webhook(
+    auth_user: AuthenticatedUser,
+    id: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, ServiceError> {
+    auth_user.require_admin()?;
+    let webhook = WebhookService::get(id.into_inner(), &pool).await?;
+    Ok(HttpResponse::Ok().json(webhook))
+}
+
+/// Update a webhook. The body is a JSON Merge Patch for both PUT and PATCH;
+/// set `active` to false to pause deliveries without losing the log.
+#[utoipa::path(
+    method(put, patch),
+    path = "/api/webhooks/{id}",
+    params(("id" = Uuid, Path, description = "Webhook ID")),
+    request_body = WebhookUpdate,
+    responses(
+        (status = 200, description = "Updated webhook", body = Webhook),
+        (status = 403, description = "Admin role required"),
+        (status = 404, description = "Webhook not found"),
+        (status = 422, description = "Invalid URL, secret or events")
+    )
+)]
+#[route("/{id}", method = "PUT", method = "PATCH")]
+pub async fn update_webhook(
+    auth_user: AuthenticatedUser,
+    id: web::Path<Uuid>,
+    update: web::Json<WebhookUpdate>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, ServiceError> {
+    auth_user.require_admin()?;
+    let webhook = WebhookService::update(
+        id.into_inner(),
+        update.into_inner(),
+        &auth_user.audit(),
+        &pool,
+    )
+    .await?;
+    Ok(HttpResponse::Ok().json(webhook))
+}
+
+/// Delete a webhook and its delivery log
+#[utoipa::path(
+    delete,
+    path = "/api/webhooks/{id}",
+    params(("id" = Uuid, Path, description = "Webhook ID")),
+    responses(
+        (status = 204, description = "Webhook deleted"),
+        (status = 403, description = "Admin role required"),
+        (status = 404, description = "Webhook not found")
+    )
+)]
+#[delete("/{id}")]
+pub async fn delete_webhook(
+    auth_user: AuthenticatedUser,
+    id: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, ServiceError> {
+    auth_user.require_admin()?;
+    WebhookService::delete(id.into_inner(), &auth_user.audit(), &pool).await?;
+    Ok(HttpResponse::NoContent().finish())
+}
+
+/// List a webhook's deliveries, newest first by default
+#[utoipa::path(
+    get,
+    path = "/api/webhooks/{id}/deliveries",
+    params(("id" = Uuid, Path, description = "Webhook ID"), PageParams, DeliveryFilter),
+    responses(
+        (status = 200, description = "Page of deliveries", body = Page<WebhookDelivery>),
+        (status = 403, description = "Admin role required"),
+        (status = 404, description = "Webhook not found")
+    )
+)]
+#[get("/{id}/deliveries")]
+pub async fn list_deliveries(
+    req: HttpRequest,
+    auth_user: AuthenticatedUser,
+    id: web::Path<Uuid>,
+    params: web::Query<PageParams>,
+    filter: web::Query<DeliveryFilter>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, ServiceError> {
+    auth_user.require_admin()?;
+    let deliveries = WebhookService::list_deliveries(id.into_inner(), &params, &filter, &pool)
+        .await?
+        .with_links(req.path(), req.query_string());
+    Ok(HttpResponse::Ok().json(deliveries))
+}
+
+/// Send a logged delivery again
+///
+/// Queues a new delivery with the same payload; it goes out on the next run
+/// of the `webhook-deliveries` schedule.
+#[utoipa::path(
+    post,
+    path = "/api/webhooks/{id}/deliveries/{delivery_id}/redeliver",
+    params(
+        ("id" = Uuid, Path, description = "Webhook ID"),
+        ("delivery_id" = Uuid, Path, description = "Delivery to repeat")
+    ),
+    responses(
+        (status = 202, description = "Redelivery queued", body = WebhookDelivery),
+        (status = 403, description = "Admin role required"),
+        (status = 404, description = "Delivery not found")
+    )
+)]
+#[post("/{id}/deliveries/{delivery_id}/redeliver")]
+pub async fn redeliver(
+    auth_user: AuthenticatedUser,
+    path: web::Path<(Uuid, Uuid)>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, ServiceError> {
+    auth_user.require_admin()?;
+    let (id, delivery_id) = path.into_inner();
+    let delivery = WebhookService::redeliver(id, delivery_id, &pool).await?;
+    Ok(HttpResponse::Accepted().json(delivery))
+}
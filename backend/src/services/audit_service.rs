@@ -0,0 +1,258 @@
+use crate::errors::ServiceError;
+use crate::models::audit::{
+    AuditAction, AuditContext, AuditEntity, AuditEntry, AuditFilter, AUDIT_SORT_FIELDS,
+};
+use crate::models::pagination::{Page, PageParams};
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+use sqlx::{PgConnection, PgPool, Postgres, QueryBuilder};
+use uuid::Uuid;
+
+pub struct AuditService;
+
+impl AuditService {
+    pub async fn record_create<T: Serialize>(
+        conn: &mut PgConnection,
+        audit: &AuditContext,
+        entity: AuditEntity,
+        entity_id: Uuid,
+        after: &T,
+    ) -> Result<(), ServiceError> {
+        let after = snapshot(after)?;
+        Self::record(
+            conn,
+            audit,
+            entity,
+            entity_id,
+            AuditAction::Create,
+            None,
+            Some(after),
+        )
+        .await
+    }
+
+    pub async fn record_update<T: Serialize>(
+        conn: &mut PgConnection,
+        audit: &AuditContext,
+        entity: AuditEntity,
+        entity_id: Uuid,
+        before: &T,
+        after: &T,
+    ) -> Result<(), ServiceError> {
+        let before = snapshot(before)?;
+        let after = snapshot(after)?;
+        Self::record(
+            conn,
+            audit,
+            entity,
+            entity_id,
+            AuditAction::Update,
+            Some(before),
+            Some(after),
+        )
+        .await
+    }
+
+    pub async fn record_delete<T: Serialize>(
+        conn: &mut PgConnection,
+        audit: &AuditContext,
+        entity: AuditEntity,
+        entity_id: Uuid,
+        before: &T,
+    ) -> Result<(), ServiceError> {
+        let before = snapshot(before)?;
+        Self::record(
+            conn,
+            audit,
+            entity,
+            entity_id,
+            AuditAction::Delete,
+            Some(before),
+            None,
+        )
+        .await
+    }
+
+    pub async fn record_restore<T: Serialize>(
+        conn: &mut PgConnection,
+        audit: &AuditContext,
+        entity: AuditEntity,
+        entity_id: Uuid,
+        before: &T,
+        after: &T,
+    ) -> Result<(), ServiceError> {
+        let before = snapshot(before)?;
+        let after = snapshot(after)?;
+        Self::record(
+            conn,
+            audit,
+            entity,
+            entity_id,
+            AuditAction::Restore,
+            Some(before),
+            Some(after),
+        )
+        .await
+    }
+
+    pub async fn record_purge<T: Serialize>(
+        conn: &mut PgConnection,
+        audit: &AuditContext,
+        entity: AuditEntity,
+        entity_id: Uuid,
+        before: &T,
+    ) -> Result<(), ServiceError> {
+        let before = snapshot(before)?;
+        Self::record(
+            conn,
+            audit,
+            entity,
+            entity_id,
+            AuditAction::Purge,
+            Some(before),
+            None,
+        )
+        .await
+    }
+
+    async fn record(
+        conn: &mut PgConnection,
+        audit: &AuditContext,
+        entity: AuditEntity,
+        entity_id: Uuid,
+        action: AuditAction,
+        before: Option<Value>,
+        after: Option<Value>,
+    ) -> Result<(), ServiceError> {
+        let changes = diff(before.as_ref(), after.as_ref());
+
+        sqlx::query!(
+            r#"
+            INSERT INTO audit_log (actor_id, entity_type, entity_id, action, before, after, changes, request_id)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            "#,
+            audit.actor_id,
+            entity.as_str(),
+            entity_id,
+            action as AuditAction,
+            before,
+            after,
+            changes,
+            audit.request_id
+        )
+        .execute(conn)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn list(
+        params: &PageParams,
+        filter: &AuditFilter,
+        pool: &PgPool,
+    ) -> Result<Page<AuditEntry>, ServiceError> {
+        let order_by = params.order_by(AUDIT_SORT_FIELDS, "-created_at")?;
+
+        let mut count = QueryBuilder::new("SELECT COUNT(*) FROM audit_log WHERE TRUE");
+        Self::push_filters(&mut count, filter);
+        let total: i64 = count.build_query_scalar().fetch_one(pool).await?;
+
+        let mut query = QueryBuilder::new(
+            r#"
+            SELECT id, actor_id, entity_type, entity_id, action, before, after, changes,
+                   request_id, created_at
+            FROM audit_log
+            WHERE TRUE"#,
+        );
+        Self::push_filters(&mut query, filter);
+        query
+            .push(order_by)
+            .push(" LIMIT ")
+            .push_bind(params.per_page())
+            .push(" OFFSET ")
+            .push_bind(params.offset()?);
+
+        let entries = query.build_query_as::<AuditEntry>().fetch_all(pool).await?;
+
+        Ok(Page::new(entries, total, params))
+    }
+
+    fn push_filters<'a>(query: &mut QueryBuilder<'a, Postgres>, filter: &'a AuditFilter) {
+        if let Some(entity_type) = filter.entity_type {
+            query
+                .push(" AND entity_type = ")
+                .push_bind(entity_type.as_str());
+        }
+        if let Some(entity_id) = filter.entity_id {
+            query.push(" AND entity_id = ").push_bind(entity_id);
+        }
+        if let Some(actor_id) = filter.actor_id {
+            query.push(" AND actor_id = ").push_bind(actor_id);
+        }
+        if let Some(action) = filter.action {
+            query.push(" AND action = ").push_bind(action);
+        }
+        if let Some(request_id) = &filter.request_id {
+            query.push(" AND request_id = ").push_bind(request_id);
+        }
+        if let Some(from) = filter.from {
+            query.push(" AND created_at >= ").push_bind(from);
+        }
+        if let Some(to) = filter.to {
+            query.push(" AND created_at < ").push_bind(to);
+        }
+    }
+
+    /// Every recorded change to one record, oldest first.
+    pub async fn history(
+        entity: AuditEntity,
+        entity_id: Uuid,
+        pool: &PgPool,
+    ) -> Result<Vec<AuditEntry>, ServiceError> {
+        let entries = sqlx::query_as!(
+            AuditEntry,
+            r#"
+            SELECT id, actor_id, entity_type, entity_id, action as "action: AuditAction",
+                   before, after, changes, request_id, created_at
+            FROM audit_log
+            WHERE entity_type = $1 AND entity_id = $2
+            ORDER BY created_at ASC, id ASC
+            "#,
+            entity.as_str(),
+            entity_id
+        )
+        .fetch_all(pool)
+        .await?;
+
+        Ok(entries)
+    }
+}
+
+fn snapshot<T: Serialize>(value: &T) -> Result<Value, ServiceError> {
+    serde_json::to_value(value).map_err(|e| {
+        log::error!("Failed to serialize audit snapshot: {:?}", e);
+        ServiceError::InternalServerError
+    })
+}
+
+/// Top-level fields that differ between two snapshots, as
+/// `{"field": {"before": .., "after": ..}}`. A missing snapshot (create or
+/// delete) counts as every field being null.
+pub fn diff(before: Option<&Value>, after: Option<&Value>) -> Value {
+    let empty = Map::new();
+    let before = before.and_then(Value::as_object).unwrap_or(&empty);
+    let after = after.and_then(Value::as_object).unwrap_or(&empty);
+
+    let mut changes = Map::new();
+    for key in before.keys().chain(after.keys()) {
+        if changes.contains_key(key) {
+            continue;
+        }
+        let old = before.get(key).unwrap_or(&Value::Null);
+        let new = after.get(key).unwrap_or(&Value::Null);
+        if old != new {
+            changes.insert(key.clone(), json!({ "before": old, "after": new }));
+        }
+    }
+    Value::Object(changes)
+}
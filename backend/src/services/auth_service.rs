@@ -1,32 +1,39 @@
 use crate::errors::ServiceError;
-use crate::models::auth::{AuthResponse, Claims, LoginCredentials};
+use crate::models::audit::{AuditContext, AuditEntity};
+use crate::models::auth::{AuthResponse, AuthSettings, AuthSettingsUpdate, LoginCredentials};
 use crate::models::user::{User, UserCreate};
+use crate::services::audit_service::AuditService;
+use crate::services::token_service::TokenService;
 use crate::services::user_service::UserService;
 use bcrypt::verify;
-use chrono::{Duration, Utc};
-use jsonwebtoken::{encode, EncodingKey, Header};
 use sqlx::PgPool;
-use std::env;
+use uuid::Uuid;
 
 pub struct AuthService;
 
 impl AuthService {
     pub async fn register(
         user_create: UserCreate,
+        tokens: &TokenService,
+        audit: &AuditContext,
         pool: &PgPool,
     ) -> Result<AuthResponse, ServiceError> {
-        let user = UserService::create(user_create, pool).await?;
-        Self::generate_token(&user)
+        Self::ensure_password_login_enabled(pool).await?;
+        let user = UserService::create(user_create, audit, pool).await?;
+        tokens.issue(&user)
     }
 
     pub async fn login(
         credentials: LoginCredentials,
+        tokens: &TokenService,
         pool: &PgPool,
     ) -> Result<AuthResponse, ServiceError> {
+        Self::ensure_password_login_enabled(pool).await?;
+
         let user = sqlx::query_as!(
             User,
             r#"
-            SELECT id, email, password_hash, full_name, role as "role: _", created_at, updated_at
+            SELECT id, email, password_hash, full_name, role as "role: _", deactivated_at, created_at, updated_at, version
             FROM users WHERE email = $1
             "#,
             credentials.email
@@ -35,38 +42,89 @@ impl AuthService {
         .await?
         .ok_or(ServiceError::InvalidCredentials)?;
 
-        if !verify(credentials.password.as_bytes(), &user.password_hash)? {
+        // Accounts provisioned through single sign-on have no password
+        let password_hash = user
+            .password_hash
+            .as_deref()
+            .ok_or(ServiceError::InvalidCredentials)?;
+
+        if !verify(credentials.password.as_bytes(), password_hash)? {
             return Err(ServiceError::InvalidCredentials);
         }
 
-        Self::generate_token(&user)
+        if user.deactivated_at.is_some() {
+            return Err(ServiceError::Unauthorized("Account is deactivated".into()));
+        }
+
+        tokens.issue(&user)
     }
 
-    fn generate_token(user: &User) -> Result<AuthResponse, ServiceError> {
-        let jwt_secret = env::var("JWT_SECRET").expect("JWT_SECRET must be set");
-        let expiration = Utc::now()
-            .checked_add_signed(Duration::hours(24))
-            .expect("valid timestamp")
-            .timestamp() as usize;
+    pub async fn get_settings(pool: &PgPool) -> Result<AuthSettings, ServiceError> {
+        let settings = sqlx::query_as!(
+            AuthSettings,
+            "SELECT password_login_enabled, updated_by, updated_at FROM auth_settings"
+        )
+        .fetch_one(pool)
+        .await?;
+
+        Ok(settings)
+    }
+
+    /// Password login may only be turned off while single sign-on is
+    /// configured; otherwise nobody, the admin included, could sign in.
+    pub async fn update_settings(
+        update: AuthSettingsUpdate,
+        sso_configured: bool,
+        audit: &AuditContext,
+        pool: &PgPool,
+    ) -> Result<AuthSettings, ServiceError> {
+        if !update.password_login_enabled && !sso_configured {
+            return Err(ServiceError::invalid_field(
+                "password_login_enabled",
+                "sso_required",
+                "Password login cannot be disabled while single sign-on is not configured",
+            ));
+        }
+        let mut tx = pool.begin().await?;
+        let before = sqlx::query_as!(
+            AuthSettings,
+            "SELECT password_login_enabled, updated_by, updated_at FROM auth_settings FOR UPDATE"
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+
+        let settings = sqlx::query_as!(
+            AuthSettings,
+            r#"
+            UPDATE auth_settings
+            SET password_login_enabled = $1, updated_by = $2, updated_at = CURRENT_TIMESTAMP
+            RETURNING password_login_enabled, updated_by, updated_at
+            "#,
+            update.password_login_enabled,
+            audit.actor_id
+        )
+        .fetch_one(&mut *tx)
+        .await?;
 
-        let claims = Claims {
-            sub: user.id.to_string(),
-            email: user.email.clone(),
-            role: serde_json::to_string(&user.role)
-                .map_err(|_e| ServiceError::InternalServerError)?,
-            exp: expiration,
-        };
+        // The settings row is a singleton, recorded under the nil ID
+        AuditService::record_update(
+            &mut tx,
+            audit,
+            AuditEntity::AuthSettings,
+            Uuid::nil(),
+            &before,
+            &settings,
+        )
+        .await?;
+        tx.commit().await?;
 
-        let token = encode(
-            &Header::default(),
-            &claims,
-            &EncodingKey::from_secret(jwt_secret.as_bytes()),
-        )?;
+        Ok(settings)
+    }
 
-        Ok(AuthResponse {
-            token,
-            token_type: "Bearer".to_string(),
-            user_id: user.id,
-        })
+    async fn ensure_password_login_enabled(pool: &PgPool) -> Result<(), ServiceError> {
+        if !Self::get_settings(pool).await?.password_login_enabled {
+            return Err(ServiceError::Forbidden);
+        }
+        Ok(())
     }
 }
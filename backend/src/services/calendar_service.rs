@@ -0,0 +1,403 @@
+use chrono::{DateTime, Duration, Utc};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::errors::ServiceError;
+use crate::models::audit::{AuditContext, AuditEntity};
+use crate::models::calendar::{CalendarFeed, CalendarFeedCreate, CalendarFeedCreated};
+use crate::models::lifecycle::LifecyclePhase;
+use crate::models::user::UserRole;
+use crate::services::audit_service::AuditService;
+use crate::services::project_service::ProjectService;
+use crate::services::token_service::{hash_token, random_token};
+
+/// Domain part of event UIDs. UIDs derive from row ids, so an event keeps its
+/// UID across refreshes and calendar clients update it in place.
+const UID_DOMAIN: &str = "waterfall-manager";
+/// RFC 5545 limits content lines to 75 octets, excluding the line break.
+const MAX_LINE_OCTETS: usize = 75;
+
+/// One VEVENT of a feed.
+struct CalendarEvent {
+    uid: String,
+    summary: String,
+    description: Option<String>,
+    location: Option<String>,
+    category: &'static str,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+    sequence: i32,
+    modified: DateTime<Utc>,
+}
+
+struct FeedTask {
+    id: Uuid,
+    name: String,
+    description: Option<String>,
+    project_name: String,
+    start_date: DateTime<Utc>,
+    end_date: DateTime<Utc>,
+    milestone: bool,
+    version: i32,
+    updated_at: DateTime<Utc>,
+}
+
+struct FeedGateReview {
+    id: Uuid,
+    project_name: String,
+    phase: LifecyclePhase,
+    scheduled_at: DateTime<Utc>,
+    duration_minutes: i32,
+    location: Option<String>,
+    notes: Option<String>,
+    version: i32,
+    updated_at: DateTime<Utc>,
+}
+
+/// A live feed looked up by its token.
+struct FeedOwner {
+    user_id: Uuid,
+    role: UserRole,
+    project_id: Option<Uuid>,
+}
+
+pub struct CalendarService;
+
+impl CalendarService {
+    /// Creates a subscription for the caller. A project feed requires that
+    /// the caller can see the project.
+    pub async fn create_feed(
+        feed: CalendarFeedCreate,
+        user_id: Uuid,
+        role: &UserRole,
+        audit: &AuditContext,
+        pool: &PgPool,
+    ) -> Result<CalendarFeedCreated, ServiceError> {
+        if let Some(project_id) = feed.project_id {
+            let mut conn = pool.acquire().await?;
+            if !ProjectService::is_visible_to(project_id, user_id, role, &mut conn).await? {
+                return Err(ServiceError::NotFound("Project not found".into()));
+            }
+        }
+
+        let token = random_token();
+        let mut tx = pool.begin().await?;
+        let created = sqlx::query_as!(
+            CalendarFeed,
+            r#"
+            INSERT INTO calendar_feeds (user_id, project_id, token_hash)
+            VALUES ($1, $2, $3)
+            RETURNING id, user_id, project_id, created_at
+            "#,
+            user_id,
+            feed.project_id,
+            hash_token(&token)
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+
+        AuditService::record_create(
+            &mut tx,
+            audit,
+            AuditEntity::CalendarFeed,
+            created.id,
+            &created,
+        )
+        .await?;
+        tx.commit().await?;
+
+        Ok(CalendarFeedCreated {
+            feed: created,
+            url: format!("/api/calendar/{}.ics", token),
+        })
+    }
+
+    pub async fn list_feeds(
+        user_id: Uuid,
+        pool: &PgPool,
+    ) -> Result<Vec<CalendarFeed>, ServiceError> {
+        let feeds = sqlx::query_as!(
+            CalendarFeed,
+            r#"
+            SELECT id, user_id, project_id, created_at
+            FROM calendar_feeds
+            WHERE user_id = $1 AND revoked_at IS NULL
+            ORDER BY created_at
+            "#,
+            user_id
+        )
+        .fetch_all(pool)
+        .await?;
+
+        Ok(feeds)
+    }
+
+    /// Revokes one of the caller's feeds; its URL stops working at once.
+    pub async fn revoke_feed(
+        id: Uuid,
+        user_id: Uuid,
+        audit: &AuditContext,
+        pool: &PgPool,
+    ) -> Result<(), ServiceError> {
+        let mut tx = pool.begin().await?;
+        let feed = sqlx::query_as!(
+            CalendarFeed,
+            r#"
+            UPDATE calendar_feeds SET revoked_at = NOW()
+            WHERE id = $1 AND user_id = $2 AND revoked_at IS NULL
+            RETURNING id, user_id, project_id, created_at
+            "#,
+            id,
+            user_id
+        )
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or(ServiceError::NotFound("Calendar feed not found".into()))?;
+
+        AuditService::record_delete(&mut tx, audit, AuditEntity::CalendarFeed, id, &feed).await?;
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    /// Renders the feed behind `token` as an iCalendar document. Revoked
+    /// tokens, deactivated owners and projects the owner can no longer see
+    /// all read as not found.
+    pub async fn render_feed(token: &str, pool: &PgPool) -> Result<String, ServiceError> {
+        let not_found = || ServiceError::NotFound("Calendar feed not found".into());
+        let owner = sqlx::query_as!(
+            FeedOwner,
+            r#"
+            SELECT f.user_id, u.role as "role: UserRole", f.project_id
+            FROM calendar_feeds f
+            JOIN users u ON u.id = f.user_id
+            WHERE f.token_hash = $1 AND f.revoked_at IS NULL AND u.deactivated_at IS NULL
+            "#,
+            hash_token(token)
+        )
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(not_found)?;
+
+        let (name, tasks, reviews) = match owner.project_id {
+            Some(project_id) => {
+                let mut conn = pool.acquire().await?;
+                if !ProjectService::is_visible_to(project_id, owner.user_id, &owner.role, &mut conn)
+                    .await?
+                {
+                    return Err(not_found());
+                }
+                let name = sqlx::query_scalar!(
+                    "SELECT name FROM projects WHERE id = $1 AND deleted_at IS NULL",
+                    project_id
+                )
+                .fetch_optional(pool)
+                .await?
+                .ok_or_else(not_found)?;
+                (
+                    name,
+                    Self::project_tasks(project_id, pool).await?,
+                    Self::project_gate_reviews(project_id, pool).await?,
+                )
+            }
+            None => (
+                "My assignments".to_string(),
+                Self::assigned_tasks(owner.user_id, pool).await?,
+                Self::involved_gate_reviews(owner.user_id, pool).await?,
+            ),
+        };
+
+        let mut events: Vec<CalendarEvent> = tasks.into_iter().map(task_event).collect();
+        events.extend(reviews.into_iter().map(gate_review_event));
+        Ok(render_calendar(&name, &events))
+    }
+
+    async fn project_tasks(project_id: Uuid, pool: &PgPool) -> Result<Vec<FeedTask>, ServiceError> {
+        let tasks = sqlx::query_as!(
+            FeedTask,
+            r#"
+            SELECT t.id, t.name, t.description, p.name as project_name,
+                   t.start_date, t.end_date, t.milestone, t.version, t.updated_at
+            FROM tasks t
+            JOIN projects p ON p.id = t.project_id
+            WHERE t.project_id = $1 AND t.deleted_at IS NULL
+            ORDER BY t.start_date
+            "#,
+            project_id
+        )
+        .fetch_all(pool)
+        .await?;
+
+        Ok(tasks)
+    }
+
+    async fn assigned_tasks(user_id: Uuid, pool: &PgPool) -> Result<Vec<FeedTask>, ServiceError> {
+        let tasks = sqlx::query_as!(
+            FeedTask,
+            r#"
+            SELECT t.id, t.name, t.description, p.name as project_name,
+                   t.start_date, t.end_date, t.milestone, t.version, t.updated_at
+            FROM tasks t
+            JOIN projects p ON p.id = t.project_id
+            WHERE t.deleted_at IS NULL AND p.deleted_at IS NULL
+              AND EXISTS (
+                  SELECT 1 FROM resource_users ru
+                  WHERE ru.user_id = $1 AND ru.resource_id = ANY(t.assigned_to)
+              )
+            ORDER BY t.start_date
+            "#,
+            user_id
+        )
+        .fetch_all(pool)
+        .await?;
+
+        Ok(tasks)
+    }
+
+    async fn project_gate_reviews(
+        project_id: Uuid,
+        pool: &PgPool,
+    ) -> Result<Vec<FeedGateReview>, ServiceError> {
+        let reviews = sqlx::query_as!(
+            FeedGateReview,
+            r#"
+            SELECT g.id, p.name as project_name, g.phase as "phase: LifecyclePhase",
+                   g.scheduled_at, g.duration_minutes, g.location, g.notes,
+                   g.version, g.updated_at
+            FROM gate_reviews g
+            JOIN projects p ON p.id = g.project_id
+            WHERE g.project_id = $1
+            ORDER BY g.scheduled_at
+            "#,
+            project_id
+        )
+        .fetch_all(pool)
+        .await?;
+
+        Ok(reviews)
+    }
+
+    /// Gate reviews of projects the user owns or has tasks in.
+    async fn involved_gate_reviews(
+        user_id: Uuid,
+        pool: &PgPool,
+    ) -> Result<Vec<FeedGateReview>, ServiceError> {
+        let reviews = sqlx::query_as!(
+            FeedGateReview,
+            r#"
+            SELECT g.id, p.name as project_name, g.phase as "phase: LifecyclePhase",
+                   g.scheduled_at, g.duration_minutes, g.location, g.notes,
+                   g.version, g.updated_at
+            FROM gate_reviews g
+            JOIN projects p ON p.id = g.project_id
+            WHERE p.deleted_at IS NULL
+              AND project_visible_to(p.id, $1, FALSE)
+            ORDER BY g.scheduled_at
+            "#,
+            user_id
+        )
+        .fetch_all(pool)
+        .await?;
+
+        Ok(reviews)
+    }
+}
+
+fn task_event(task: FeedTask) -> CalendarEvent {
+    let (summary, category) = if task.milestone {
+        (format!("Milestone: {}", task.name), "Milestone")
+    } else {
+        (task.name, "Task")
+    };
+    CalendarEvent {
+        uid: format!("task-{}@{}", task.id, UID_DOMAIN),
+        summary,
+        description: Some(match task.description {
+            Some(description) => format!("{}\n\n{}", task.project_name, description),
+            None => task.project_name,
+        }),
+        location: None,
+        category,
+        start: task.start_date,
+        end: task.end_date.max(task.start_date),
+        sequence: task.version,
+        modified: task.updated_at,
+    }
+}
+
+fn gate_review_event(review: FeedGateReview) -> CalendarEvent {
+    CalendarEvent {
+        uid: format!("gate-review-{}@{}", review.id, UID_DOMAIN),
+        summary: format!("{}: {:?} gate review", review.project_name, review.phase),
+        description: review.notes,
+        location: review.location,
+        category: "Gate review",
+        start: review.scheduled_at,
+        end: review.scheduled_at + Duration::minutes(i64::from(review.duration_minutes)),
+        sequence: review.version,
+        modified: review.updated_at,
+    }
+}
+
+fn render_calendar(name: &str, events: &[CalendarEvent]) -> String {
+    let mut out = String::new();
+    let mut line = |content: String| fold_line(&mut out, &content);
+
+    line("BEGIN:VCALENDAR".into());
+    line("VERSION:2.0".into());
+    line("PRODID:-//Waterfall Manager//Calendar Feed//EN".into());
+    line("CALSCALE:GREGORIAN".into());
+    line("METHOD:PUBLISH".into());
+    line(format!("X-WR-CALNAME:{}", escape_text(name)));
+    for event in events {
+        line("BEGIN:VEVENT".into());
+        line(format!("UID:{}", event.uid));
+        // The last change rather than the render time, so an unchanged event
+        // renders identically on every refresh
+        line(format!("DTSTAMP:{}", ics_date(event.modified)));
+        line(format!("LAST-MODIFIED:{}", ics_date(event.modified)));
+        line(format!("SEQUENCE:{}", event.sequence));
+        line(format!("DTSTART:{}", ics_date(event.start)));
+        line(format!("DTEND:{}", ics_date(event.end)));
+        line(format!("SUMMARY:{}", escape_text(&event.summary)));
+        if let Some(description) = &event.description {
+            line(format!("DESCRIPTION:{}", escape_text(description)));
+        }
+        if let Some(location) = &event.location {
+            line(format!("LOCATION:{}", escape_text(location)));
+        }
+        line(format!("CATEGORIES:{}", event.category));
+        line("END:VEVENT".into());
+    }
+    line("END:VCALENDAR".into());
+    out
+}
+
+fn ics_date(date: DateTime<Utc>) -> String {
+    date.format("%Y%m%dT%H%M%SZ").to_string()
+}
+
+fn escape_text(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace("\r\n", "\\n")
+        .replace('\n', "\\n")
+}
+
+/// Writes one content line, folding it onto continuation lines (CRLF and a
+/// space) without splitting a UTF-8 character.
+fn fold_line(out: &mut String, content: &str) {
+    let mut width = 0;
+    for c in content.chars() {
+        if width + c.len_utf8() > MAX_LINE_OCTETS {
+            out.push_str("\r\n ");
+            // The leading space counts towards the continuation line
+            width = 1;
+        }
+        out.push(c);
+        width += c.len_utf8();
+    }
+    out.push_str("\r\n");
+}
@@ -0,0 +1,491 @@
+use sqlx::{PgConnection, PgPool};
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::errors::ServiceError;
+use crate::models::audit::{AuditContext, AuditEntity};
+use crate::models::comment::{
+    ActivityItem, Comment, CommentCreate, CommentRevision, CommentTarget, CommentUpdate,
+};
+use crate::models::realtime::ChangeKind;
+use crate::models::user::UserRole;
+use crate::models::version::Versioned;
+use crate::services::audit_service::AuditService;
+use crate::services::notification_service::NotificationService;
+use crate::services::project_service::ProjectService;
+use crate::services::realtime_service::RealtimeService;
+
+pub struct CommentService;
+
+impl CommentService {
+    /// Adds a comment, or a reply when `parent_id` is set, and notifies the
+    /// users it mentions.
+    pub async fn create(
+        new_comment: CommentCreate,
+        author_id: Uuid,
+        role: &UserRole,
+        audit: &AuditContext,
+        pool: &PgPool,
+    ) -> Result<Comment, ServiceError> {
+        new_comment.validate()?;
+
+        let mut tx = pool.begin().await?;
+        let project_id = Self::visible_target(
+            new_comment.target_type,
+            new_comment.target_id,
+            author_id,
+            role,
+            &mut tx,
+        )
+        .await?;
+        ProjectService::lock(project_id, &mut tx)
+            .await?
+            .ensure_writable()?;
+
+        if let Some(parent_id) = new_comment.parent_id {
+            let in_thread = sqlx::query_scalar!(
+                r#"
+                SELECT EXISTS (
+                    SELECT 1 FROM comments
+                    WHERE id = $1 AND target_type = $2 AND target_id = $3
+                      AND deleted_at IS NULL
+                ) as "exists!"
+                "#,
+                parent_id,
+                new_comment.target_type as CommentTarget,
+                new_comment.target_id
+            )
+            .fetch_one(&mut *tx)
+            .await?;
+            if !in_thread {
+                return Err(ServiceError::invalid_field(
+                    "parent_id",
+                    "thread",
+                    "replies must answer a comment on the same record",
+                ));
+            }
+        }
+
+        let mentions = Self::resolve_mentions(&new_comment.body, project_id, &mut tx).await?;
+        let comment = sqlx::query_as!(
+            Comment,
+            r#"
+            INSERT INTO comments (target_type, target_id, project_id, parent_id, author_id, body, mentions)
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            RETURNING id, target_type as "target_type: CommentTarget", target_id, project_id,
+                      parent_id, author_id, body, mentions, edited_at, deleted_at,
+                      created_at, updated_at, version
+            "#,
+            new_comment.target_type as CommentTarget,
+            new_comment.target_id,
+            project_id,
+            new_comment.parent_id,
+            author_id,
+            new_comment.body,
+            &mentions
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+
+        AuditService::record_create(&mut tx, audit, AuditEntity::Comment, comment.id, &comment)
+            .await?;
+        NotificationService::mentioned_in(&comment, &comment.mentions, &mut tx).await?;
+        RealtimeService::publish_in(
+            ChangeKind::CommentAdded,
+            project_id,
+            comment.id,
+            Some(comment.version),
+            audit,
+            &mut tx,
+        )
+        .await?;
+        tx.commit().await?;
+
+        Ok(comment)
+    }
+
+    /// Every comment on a record, oldest first. Replies carry the id of the
+    /// comment they answer, so clients can rebuild the threads.
+    pub async fn list(
+        target_type: CommentTarget,
+        target_id: Uuid,
+        user_id: Uuid,
+        role: &UserRole,
+        pool: &PgPool,
+    ) -> Result<Vec<Comment>, ServiceError> {
+        let mut conn = pool.acquire().await?;
+        Self::visible_target(target_type, target_id, user_id, role, &mut conn).await?;
+
+        let comments = sqlx::query_as!(
+            Comment,
+            r#"
+            SELECT id, target_type as "target_type: CommentTarget", target_id, project_id,
+                   parent_id, author_id, body, mentions, edited_at, deleted_at,
+                   created_at, updated_at, version
+            FROM comments
+            WHERE target_type = $1 AND target_id = $2
+            ORDER BY created_at ASC, id ASC
+            "#,
+            target_type as CommentTarget,
+            target_id
+        )
+        .fetch_all(&mut *conn)
+        .await?;
+
+        Ok(comments.into_iter().map(Comment::redacted).collect())
+    }
+
+    pub async fn get(
+        id: Uuid,
+        user_id: Uuid,
+        role: &UserRole,
+        pool: &PgPool,
+    ) -> Result<Comment, ServiceError> {
+        let mut conn = pool.acquire().await?;
+        let comment = Self::fetch(id, false, &mut conn).await?;
+        Self::ensure_visible(&comment, user_id, role, &mut conn).await?;
+
+        Ok(comment.redacted())
+    }
+
+    /// Replaces the body of the caller's own comment, keeping the old one as
+    /// a revision. Only users newly mentioned by the edit are notified.
+    pub async fn update(
+        id: Uuid,
+        update: CommentUpdate,
+        expected_version: Option<i32>,
+        user_id: Uuid,
+        role: &UserRole,
+        audit: &AuditContext,
+        pool: &PgPool,
+    ) -> Result<Comment, ServiceError> {
+        update.validate()?;
+
+        let mut tx = pool.begin().await?;
+        let existing = Self::fetch(id, true, &mut tx).await?;
+        Self::ensure_visible(&existing, user_id, role, &mut tx).await?;
+        if existing.deleted_at.is_some() {
+            return Err(ServiceError::NotFound("Comment not found".into()));
+        }
+        if existing.author_id != user_id {
+            return Err(ServiceError::Forbidden);
+        }
+        existing.check_version(expected_version)?;
+        ProjectService::lock(existing.project_id, &mut tx)
+            .await?
+            .ensure_writable()?;
+        if update.body == existing.body {
+            return Ok(existing);
+        }
+
+        sqlx::query!(
+            "INSERT INTO comment_revisions (comment_id, body, edited_by) VALUES ($1, $2, $3)",
+            id,
+            existing.body,
+            user_id
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        let mentions = Self::resolve_mentions(&update.body, existing.project_id, &mut tx).await?;
+        let updated = sqlx::query_as!(
+            Comment,
+            r#"
+            UPDATE comments
+            SET body = $2, mentions = $3, edited_at = NOW(), updated_at = NOW(),
+                version = version + 1
+            WHERE id = $1
+            RETURNING id, target_type as "target_type: CommentTarget", target_id, project_id,
+                      parent_id, author_id, body, mentions, edited_at, deleted_at,
+                      created_at, updated_at, version
+            "#,
+            id,
+            update.body,
+            &mentions
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+
+        AuditService::record_update(
+            &mut tx,
+            audit,
+            AuditEntity::Comment,
+            id,
+            &existing,
+            &updated,
+        )
+        .await?;
+        let added: Vec<Uuid> = mentions
+            .into_iter()
+            .filter(|user| !existing.mentions.contains(user))
+            .collect();
+        NotificationService::mentioned_in(&updated, &added, &mut tx).await?;
+        tx.commit().await?;
+
+        Ok(updated)
+    }
+
+    /// Soft-deletes a comment. Authors can delete their own comments and
+    /// admins any comment; replies stay in the thread.
+    pub async fn delete(
+        id: Uuid,
+        expected_version: Option<i32>,
+        user_id: Uuid,
+        role: &UserRole,
+        audit: &AuditContext,
+        pool: &PgPool,
+    ) -> Result<(), ServiceError> {
+        let mut tx = pool.begin().await?;
+        let existing = Self::fetch(id, true, &mut tx).await?;
+        Self::ensure_visible(&existing, user_id, role, &mut tx).await?;
+        if existing.deleted_at.is_some() {
+            return Err(ServiceError::NotFound("Comment not found".into()));
+        }
+        if existing.author_id != user_id && *role != UserRole::Admin {
+            return Err(ServiceError::Forbidden);
+        }
+        existing.check_version(expected_version)?;
+        ProjectService::lock(existing.project_id, &mut tx)
+            .await?
+            .ensure_writable()?;
+
+        sqlx::query!(
+            r#"
+            UPDATE comments
+            SET deleted_at = NOW(), updated_at = NOW(), version = version + 1
+            WHERE id = $1
+            "#,
+            id
+        )
+        .execute(&mut *tx)
+        .await?;
+        AuditService::record_delete(&mut tx, audit, AuditEntity::Comment, id, &existing).await?;
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    /// Earlier bodies of a comment, oldest first.
+    pub async fn revisions(
+        id: Uuid,
+        user_id: Uuid,
+        role: &UserRole,
+        pool: &PgPool,
+    ) -> Result<Vec<CommentRevision>, ServiceError> {
+        let mut conn = pool.acquire().await?;
+        let comment = Self::fetch(id, false, &mut conn).await?;
+        Self::ensure_visible(&comment, user_id, role, &mut conn).await?;
+        if comment.deleted_at.is_some() {
+            return Err(ServiceError::NotFound("Comment not found".into()));
+        }
+
+        let revisions = sqlx::query_as!(
+            CommentRevision,
+            r#"
+            SELECT id, comment_id, body, edited_by, created_at
+            FROM comment_revisions
+            WHERE comment_id = $1
+            ORDER BY created_at ASC, id ASC
+            "#,
+            id
+        )
+        .fetch_all(&mut *conn)
+        .await?;
+
+        Ok(revisions)
+    }
+
+    /// A record's changes from the audit log and its comments, oldest first.
+    pub async fn activity(
+        target_type: CommentTarget,
+        target_id: Uuid,
+        user_id: Uuid,
+        role: &UserRole,
+        pool: &PgPool,
+    ) -> Result<Vec<ActivityItem>, ServiceError> {
+        let comments = Self::list(target_type, target_id, user_id, role, pool).await?;
+        let changes = AuditService::history(target_type.audit_entity(), target_id, pool).await?;
+
+        let mut activity: Vec<ActivityItem> = changes
+            .into_iter()
+            .map(ActivityItem::Change)
+            .chain(comments.into_iter().map(ActivityItem::Comment))
+            .collect();
+        activity.sort_by_key(ActivityItem::occurred_at);
+
+        Ok(activity)
+    }
+
+    /// Addresses mentioned in a body, lowercased, in order of appearance. A
+    /// mention is `@` followed by an email address, so `@` inside a word,
+    /// as in a plain address, does not count.
+    pub fn mentioned_emails(body: &str) -> Vec<String> {
+        let is_address_char = |c: char| c.is_ascii_alphanumeric() || "._%+-@".contains(c);
+
+        let mut emails = Vec::new();
+        for (at, _) in body.match_indices('@') {
+            if body[..at].chars().next_back().is_some_and(is_address_char) {
+                continue;
+            }
+            let rest = &body[at + 1..];
+            let end = rest.find(|c| !is_address_char(c)).unwrap_or(rest.len());
+            // Trailing dots end the sentence, not the address
+            let candidate = rest[..end].trim_end_matches('.');
+            let Some((local, domain)) = candidate.split_once('@') else {
+                continue;
+            };
+            if local.is_empty() || domain.contains('@') || !domain.contains('.') {
+                continue;
+            }
+            let email = candidate.to_lowercase();
+            if !emails.contains(&email) {
+                emails.push(email);
+            }
+        }
+        emails
+    }
+
+    /// Active users mentioned in `body` who can see the project. Mentions of
+    /// anyone else stay plain text.
+    async fn resolve_mentions(
+        body: &str,
+        project_id: Uuid,
+        conn: &mut PgConnection,
+    ) -> Result<Vec<Uuid>, ServiceError> {
+        let emails = Self::mentioned_emails(body);
+        if emails.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let users = sqlx::query!(
+            r#"
+            SELECT u.id, u.role as "role: UserRole"
+            FROM users u
+            WHERE lower(u.email) = ANY($1) AND u.deactivated_at IS NULL
+            ORDER BY array_position($1, lower(u.email))
+            "#,
+            &emails
+        )
+        .fetch_all(&mut *conn)
+        .await?;
+
+        let mut mentioned = Vec::new();
+        for user in users {
+            if ProjectService::is_visible_to(project_id, user.id, &user.role, conn).await? {
+                mentioned.push(user.id);
+            }
+        }
+        Ok(mentioned)
+    }
+
+    /// Loads a comment, including deleted ones, optionally locking it.
+    async fn fetch(
+        id: Uuid,
+        for_update: bool,
+        conn: &mut PgConnection,
+    ) -> Result<Comment, ServiceError> {
+        let comment = if for_update {
+            sqlx::query_as!(
+                Comment,
+                r#"
+                SELECT id, target_type as "target_type: CommentTarget", target_id, project_id,
+                       parent_id, author_id, body, mentions, edited_at, deleted_at,
+                       created_at, updated_at, version
+                FROM comments
+                WHERE id = $1
+                FOR UPDATE
+                "#,
+                id
+            )
+            .fetch_optional(conn)
+            .await?
+        } else {
+            sqlx::query_as!(
+                Comment,
+                r#"
+                SELECT id, target_type as "target_type: CommentTarget", target_id, project_id,
+                       parent_id, author_id, body, mentions, edited_at, deleted_at,
+                       created_at, updated_at, version
+                FROM comments
+                WHERE id = $1
+                "#,
+                id
+            )
+            .fetch_optional(conn)
+            .await?
+        };
+
+        comment.ok_or(ServiceError::NotFound("Comment not found".into()))
+    }
+
+    /// Comments are hidden along with their project: from users who cannot
+    /// see it, and while it is in the trash.
+    async fn ensure_visible(
+        comment: &Comment,
+        user_id: Uuid,
+        role: &UserRole,
+        conn: &mut PgConnection,
+    ) -> Result<(), ServiceError> {
+        if ProjectService::is_visible_to(comment.project_id, user_id, role, conn).await? {
+            Ok(())
+        } else {
+            Err(ServiceError::NotFound("Comment not found".into()))
+        }
+    }
+
+    /// The project a record belongs to, provided the user can see it.
+    async fn visible_target(
+        target_type: CommentTarget,
+        target_id: Uuid,
+        user_id: Uuid,
+        role: &UserRole,
+        conn: &mut PgConnection,
+    ) -> Result<Uuid, ServiceError> {
+        let project_id = match target_type {
+            CommentTarget::Project => {
+                sqlx::query_scalar!(
+                    "SELECT id FROM projects WHERE id = $1 AND deleted_at IS NULL",
+                    target_id
+                )
+                .fetch_optional(&mut *conn)
+                .await?
+            }
+            CommentTarget::Task => {
+                sqlx::query_scalar!(
+                    "SELECT project_id FROM tasks WHERE id = $1 AND deleted_at IS NULL",
+                    target_id
+                )
+                .fetch_optional(&mut *conn)
+                .await?
+            }
+            CommentTarget::PhaseTransition => {
+                sqlx::query_scalar!(
+                    "SELECT project_id FROM phase_transitions WHERE id = $1",
+                    target_id
+                )
+                .fetch_optional(&mut *conn)
+                .await?
+            }
+            CommentTarget::GateReview => {
+                sqlx::query_scalar!(
+                    "SELECT project_id FROM gate_reviews WHERE id = $1",
+                    target_id
+                )
+                .fetch_optional(&mut *conn)
+                .await?
+            }
+        };
+
+        match project_id {
+            Some(project_id)
+                if ProjectService::is_visible_to(project_id, user_id, role, conn).await? =>
+            {
+                Ok(project_id)
+            }
+            _ => Err(ServiceError::NotFound(format!(
+                "{} not found",
+                target_type.label()
+            ))),
+        }
+    }
+}
@@ -0,0 +1,482 @@
+use sqlx::{PgConnection, PgPool};
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::errors::ServiceError;
+use crate::models::audit::{AuditContext, AuditEntity};
+use crate::models::defect::{
+    Defect, DefectCreate, DefectFilter, DefectPriority, DefectSeverity, DefectStatus, DefectUpdate,
+    OpenDefectCounts,
+};
+use crate::models::testing::TestOutcome;
+use crate::models::user::UserRole;
+use crate::models::version::Versioned;
+use crate::services::audit_service::AuditService;
+use crate::services::project_service::ProjectService;
+
+pub struct DefectService;
+
+/// Records a defect points at, checked to belong to its project. `None`
+/// skips the check.
+#[derive(Default)]
+struct DefectRefs {
+    assignee_id: Option<Uuid>,
+    test_case_id: Option<Uuid>,
+    test_run_id: Option<Uuid>,
+    requirement_id: Option<Uuid>,
+    fix_task_id: Option<Uuid>,
+}
+
+impl DefectService {
+    /// Raises a defect under the project's next number.
+    pub async fn create(
+        new_defect: DefectCreate,
+        user_id: Uuid,
+        role: &UserRole,
+        audit: &AuditContext,
+        pool: &PgPool,
+    ) -> Result<Defect, ServiceError> {
+        new_defect.validate()?;
+
+        let mut tx = pool.begin().await?;
+        if !ProjectService::is_visible_to(new_defect.project_id, user_id, role, &mut tx).await? {
+            return Err(ServiceError::NotFound("Project not found".into()));
+        }
+        ProjectService::lock(new_defect.project_id, &mut tx)
+            .await?
+            .ensure_writable()?;
+        Self::check_refs(
+            new_defect.project_id,
+            &DefectRefs {
+                assignee_id: new_defect.assignee_id,
+                test_case_id: new_defect.test_case_id,
+                test_run_id: new_defect.test_run_id,
+                requirement_id: new_defect.requirement_id,
+                fix_task_id: new_defect.fix_task_id,
+            },
+            &mut tx,
+        )
+        .await?;
+
+        let number = sqlx::query_scalar!(
+            r#"
+            UPDATE projects SET last_defect_number = last_defect_number + 1
+            WHERE id = $1
+            RETURNING last_defect_number
+            "#,
+            new_defect.project_id
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+        let id = sqlx::query_scalar!(
+            r#"
+            INSERT INTO defects (
+                project_id, number, title, description, severity, priority, assignee_id,
+                test_case_id, test_run_id, requirement_id, fix_task_id, reported_by
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12)
+            RETURNING id
+            "#,
+            new_defect.project_id,
+            number,
+            new_defect.title,
+            new_defect.description,
+            new_defect.severity as DefectSeverity,
+            new_defect.priority.unwrap_or(DefectPriority::Medium) as DefectPriority,
+            new_defect.assignee_id,
+            new_defect.test_case_id,
+            new_defect.test_run_id,
+            new_defect.requirement_id,
+            new_defect.fix_task_id,
+            user_id
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+        let defect = Self::fetch(id, false, &mut tx).await?;
+
+        AuditService::record_create(&mut tx, audit, AuditEntity::Defect, id, &defect).await?;
+        tx.commit().await?;
+
+        Ok(defect)
+    }
+
+    /// A project's defects, most severe first, then by number.
+    pub async fn list(
+        filter: &DefectFilter,
+        user_id: Uuid,
+        role: &UserRole,
+        pool: &PgPool,
+    ) -> Result<Vec<Defect>, ServiceError> {
+        let mut conn = pool.acquire().await?;
+        if !ProjectService::is_visible_to(filter.project_id, user_id, role, &mut conn).await? {
+            return Err(ServiceError::NotFound("Project not found".into()));
+        }
+
+        let defects = sqlx::query_as!(
+            Defect,
+            r#"
+            SELECT id, project_id, code as "code!", title, description,
+                   severity as "severity: DefectSeverity",
+                   priority as "priority: DefectPriority",
+                   status as "status: DefectStatus",
+                   assignee_id, test_case_id, test_run_id, requirement_id, fix_task_id,
+                   reported_by, closed_at, created_at, updated_at, version
+            FROM defects
+            WHERE project_id = $1
+              AND ($2::defect_status IS NULL OR status = $2)
+              AND ($3::defect_severity IS NULL OR severity = $3)
+              AND ($4::uuid IS NULL OR assignee_id = $4)
+              AND (NOT $5 OR status IN ('new', 'triaged', 'fixing'))
+            ORDER BY severity DESC, number
+            "#,
+            filter.project_id,
+            filter.status as Option<DefectStatus>,
+            filter.severity as Option<DefectSeverity>,
+            filter.assignee_id,
+            filter.open.unwrap_or(false)
+        )
+        .fetch_all(&mut *conn)
+        .await?;
+
+        Ok(defects)
+    }
+
+    pub async fn get(
+        id: Uuid,
+        user_id: Uuid,
+        role: &UserRole,
+        pool: &PgPool,
+    ) -> Result<Defect, ServiceError> {
+        let mut conn = pool.acquire().await?;
+        let defect = Self::fetch(id, false, &mut conn).await?;
+        Self::ensure_visible(&defect, user_id, role, &mut conn).await?;
+
+        Ok(defect)
+    }
+
+    /// Changes a defect. A new status must follow the workflow; closing
+    /// stamps `closed_at` and reopening clears it.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn update(
+        id: Uuid,
+        update: DefectUpdate,
+        expected_version: Option<i32>,
+        user_id: Uuid,
+        role: &UserRole,
+        audit: &AuditContext,
+        pool: &PgPool,
+    ) -> Result<Defect, ServiceError> {
+        update.validate()?;
+
+        let mut tx = pool.begin().await?;
+        let existing = Self::fetch(id, true, &mut tx).await?;
+        Self::ensure_visible(&existing, user_id, role, &mut tx).await?;
+        existing.check_version(expected_version)?;
+        ProjectService::lock(existing.project_id, &mut tx)
+            .await?
+            .ensure_writable()?;
+
+        let title = update
+            .title
+            .apply_required("title", existing.title.clone())?;
+        let description = update.description.apply(existing.description.clone());
+        let severity = update
+            .severity
+            .apply_required("severity", existing.severity)?;
+        let priority = update
+            .priority
+            .apply_required("priority", existing.priority)?;
+        let status = update.status.apply_required("status", existing.status)?;
+        if status != existing.status && !existing.status.may_move_to(status) {
+            return Err(ServiceError::invalid_field(
+                "status",
+                "transition",
+                format!("a {:?} defect cannot move to {:?}", existing.status, status)
+                    .to_lowercase(),
+            ));
+        }
+        let assignee_id = update.assignee_id.apply(existing.assignee_id);
+        let requirement_id = update.requirement_id.apply(existing.requirement_id);
+        let fix_task_id = update.fix_task_id.apply(existing.fix_task_id);
+        // Only references being changed are checked; a fix task may have
+        // gone to the trash since it was linked
+        let changed = |new: Option<Uuid>, old: Option<Uuid>| new.filter(|_| new != old);
+        Self::check_refs(
+            existing.project_id,
+            &DefectRefs {
+                assignee_id: changed(assignee_id, existing.assignee_id),
+                requirement_id: changed(requirement_id, existing.requirement_id),
+                fix_task_id: changed(fix_task_id, existing.fix_task_id),
+                ..Default::default()
+            },
+            &mut tx,
+        )
+        .await?;
+
+        sqlx::query!(
+            r#"
+            UPDATE defects
+            SET title = $2, description = $3, severity = $4, priority = $5, status = $6,
+                assignee_id = $7, requirement_id = $8, fix_task_id = $9,
+                closed_at = CASE
+                    WHEN $6::defect_status <> 'closed' THEN NULL
+                    WHEN status = 'closed' THEN closed_at
+                    ELSE NOW()
+                END,
+                updated_at = NOW(), version = version + 1
+            WHERE id = $1
+            "#,
+            id,
+            title,
+            description,
+            severity as DefectSeverity,
+            priority as DefectPriority,
+            status as DefectStatus,
+            assignee_id,
+            requirement_id,
+            fix_task_id
+        )
+        .execute(&mut *tx)
+        .await?;
+        let updated = Self::fetch(id, false, &mut tx).await?;
+
+        AuditService::record_update(&mut tx, audit, AuditEntity::Defect, id, &existing, &updated)
+            .await?;
+        tx.commit().await?;
+
+        Ok(updated)
+    }
+
+    /// Removes a defect. Only its reporter, an admin or a project manager
+    /// may, so an open defect cannot quietly disappear. Its number is not
+    /// reused.
+    pub async fn delete(
+        id: Uuid,
+        expected_version: Option<i32>,
+        user_id: Uuid,
+        role: &UserRole,
+        audit: &AuditContext,
+        pool: &PgPool,
+    ) -> Result<(), ServiceError> {
+        let mut tx = pool.begin().await?;
+        let existing = Self::fetch(id, true, &mut tx).await?;
+        Self::ensure_visible(&existing, user_id, role, &mut tx).await?;
+        if existing.reported_by != Some(user_id) && !role.sees_all_projects() {
+            return Err(ServiceError::Forbidden);
+        }
+        existing.check_version(expected_version)?;
+        ProjectService::lock(existing.project_id, &mut tx)
+            .await?
+            .ensure_writable()?;
+
+        sqlx::query!("DELETE FROM defects WHERE id = $1", id)
+            .execute(&mut *tx)
+            .await?;
+        AuditService::record_delete(&mut tx, audit, AuditEntity::Defect, id, &existing).await?;
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    /// Open defects of a project by severity.
+    pub async fn open_counts(
+        project_id: Uuid,
+        user_id: Uuid,
+        role: &UserRole,
+        pool: &PgPool,
+    ) -> Result<OpenDefectCounts, ServiceError> {
+        let mut conn = pool.acquire().await?;
+        if !ProjectService::is_visible_to(project_id, user_id, role, &mut conn).await? {
+            return Err(ServiceError::NotFound("Project not found".into()));
+        }
+
+        Self::open_counts_in(project_id, &mut conn).await
+    }
+
+    pub(crate) async fn open_counts_in(
+        project_id: Uuid,
+        conn: &mut PgConnection,
+    ) -> Result<OpenDefectCounts, ServiceError> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT severity as "severity: DefectSeverity", COUNT(*) as "count!"
+            FROM defects
+            WHERE project_id = $1 AND status IN ('new', 'triaged', 'fixing')
+            GROUP BY severity
+            "#,
+            project_id
+        )
+        .fetch_all(conn)
+        .await?;
+
+        let mut counts = OpenDefectCounts {
+            project_id,
+            ..Default::default()
+        };
+        for row in rows {
+            match row.severity {
+                DefectSeverity::Low => counts.low = row.count,
+                DefectSeverity::Medium => counts.medium = row.count,
+                DefectSeverity::High => counts.high = row.count,
+                DefectSeverity::Critical => counts.critical = row.count,
+            }
+            counts.total += row.count;
+        }
+
+        Ok(counts)
+    }
+
+    /// Fails on the first reference that does not exist in the project. A
+    /// test run needs the test case, which must have failed in it.
+    async fn check_refs(
+        project_id: Uuid,
+        refs: &DefectRefs,
+        conn: &mut PgConnection,
+    ) -> Result<(), ServiceError> {
+        let not_found = |field: &str, label: &str| {
+            ServiceError::invalid_field(
+                field,
+                "not_found",
+                format!("{} not found in the project", label),
+            )
+        };
+
+        if let Some(assignee_id) = refs.assignee_id {
+            let exists = sqlx::query_scalar!(
+                r#"SELECT EXISTS(SELECT 1 FROM users WHERE id = $1) as "exists!""#,
+                assignee_id
+            )
+            .fetch_one(&mut *conn)
+            .await?;
+            if !exists {
+                return Err(ServiceError::invalid_field(
+                    "assignee_id",
+                    "not_found",
+                    "assignee not found",
+                ));
+            }
+        }
+        if let Some(test_case_id) = refs.test_case_id {
+            let found = sqlx::query_scalar!(
+                "SELECT project_id FROM test_cases WHERE id = $1",
+                test_case_id
+            )
+            .fetch_optional(&mut *conn)
+            .await?;
+            if found != Some(project_id) {
+                return Err(not_found("test_case_id", "test case"));
+            }
+        }
+        if let Some(test_run_id) = refs.test_run_id {
+            let Some(test_case_id) = refs.test_case_id else {
+                return Err(ServiceError::invalid_field(
+                    "test_case_id",
+                    "required",
+                    "a test run needs the test case that failed in it",
+                ));
+            };
+            let outcome = sqlx::query_scalar!(
+                r#"
+                SELECT outcome as "outcome: TestOutcome"
+                FROM test_results
+                WHERE run_id = $1 AND test_case_id = $2
+                "#,
+                test_run_id,
+                test_case_id
+            )
+            .fetch_optional(&mut *conn)
+            .await?;
+            if outcome != Some(TestOutcome::Failed) {
+                return Err(ServiceError::invalid_field(
+                    "test_run_id",
+                    "not_failed",
+                    "the test case did not fail in this run",
+                ));
+            }
+        }
+        if let Some(requirement_id) = refs.requirement_id {
+            let found = sqlx::query_scalar!(
+                "SELECT project_id FROM requirements WHERE id = $1",
+                requirement_id
+            )
+            .fetch_optional(&mut *conn)
+            .await?;
+            if found != Some(project_id) {
+                return Err(not_found("requirement_id", "requirement"));
+            }
+        }
+        if let Some(fix_task_id) = refs.fix_task_id {
+            let found = sqlx::query_scalar!(
+                "SELECT project_id FROM tasks WHERE id = $1 AND deleted_at IS NULL",
+                fix_task_id
+            )
+            .fetch_optional(&mut *conn)
+            .await?;
+            if found != Some(project_id) {
+                return Err(not_found("fix_task_id", "task"));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Loads a defect, optionally locking it.
+    async fn fetch(
+        id: Uuid,
+        for_update: bool,
+        conn: &mut PgConnection,
+    ) -> Result<Defect, ServiceError> {
+        let defect = if for_update {
+            sqlx::query_as!(
+                Defect,
+                r#"
+                SELECT id, project_id, code as "code!", title, description,
+                       severity as "severity: DefectSeverity",
+                       priority as "priority: DefectPriority",
+                       status as "status: DefectStatus",
+                       assignee_id, test_case_id, test_run_id, requirement_id, fix_task_id,
+                       reported_by, closed_at, created_at, updated_at, version
+                FROM defects
+                WHERE id = $1
+                FOR UPDATE
+                "#,
+                id
+            )
+            .fetch_optional(conn)
+            .await?
+        } else {
+            sqlx::query_as!(
+                Defect,
+                r#"
+                SELECT id, project_id, code as "code!", title, description,
+                       severity as "severity: DefectSeverity",
+                       priority as "priority: DefectPriority",
+                       status as "status: DefectStatus",
+                       assignee_id, test_case_id, test_run_id, requirement_id, fix_task_id,
+                       reported_by, closed_at, created_at, updated_at, version
+                FROM defects
+                WHERE id = $1
+                "#,
+                id
+            )
+            .fetch_optional(conn)
+            .await?
+        };
+
+        defect.ok_or(ServiceError::NotFound("Defect not found".into()))
+    }
+
+    /// Defects are hidden along with their project.
+    async fn ensure_visible(
+        defect: &Defect,
+        user_id: Uuid,
+        role: &UserRole,
+        conn: &mut PgConnection,
+    ) -> Result<(), ServiceError> {
+        if ProjectService::is_visible_to(defect.project_id, user_id, role, conn).await? {
+            Ok(())
+        } else {
+            Err(ServiceError::NotFound("Defect not found".into()))
+        }
+    }
+}
@@ -0,0 +1,966 @@
+use actix_web::web::Bytes;
+use sqlx::{PgConnection, PgPool};
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::errors::ServiceError;
+use crate::models::audit::{AuditContext, AuditEntity};
+use crate::models::document::{
+    Document, DocumentFilter, DocumentLink, DocumentLinkCreate, DocumentTarget, DocumentUpdate,
+    DocumentUpload, DocumentVersion, VersionUpload,
+};
+use crate::models::realtime::ChangeKind;
+use crate::models::user::UserRole;
+use crate::models::version::Versioned;
+use crate::services::audit_service::AuditService;
+use crate::services::project_service::ProjectService;
+use crate::services::realtime_service::RealtimeService;
+use crate::services::storage_service::{sha256_hex, DocumentStorage};
+
+/// Largest file accepted, per version.
+pub const MAX_DOCUMENT_BYTES: usize = 25 * 1024 * 1024;
+
+/// Content types accepted for upload. Anything else, including
+/// `application/octet-stream`, is rejected.
+pub const ALLOWED_CONTENT_TYPES: &[&str] = &[
+    "application/pdf",
+    "application/msword",
+    "application/vnd.openxmlformats-officedocument.wordprocessingml.document",
+    "application/vnd.ms-excel",
+    "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet",
+    "application/vnd.ms-powerpoint",
+    "application/vnd.openxmlformats-officedocument.presentationml.presentation",
+    "application/vnd.ms-project",
+    "application/vnd.oasis.opendocument.text",
+    "application/vnd.oasis.opendocument.spreadsheet",
+    "application/vnd.oasis.opendocument.presentation",
+    "application/json",
+    "application/xml",
+    "application/zip",
+    "image/gif",
+    "image/jpeg",
+    "image/png",
+    "image/webp",
+    "text/csv",
+    "text/html",
+    "text/markdown",
+    "text/plain",
+];
+
+/// How many queued deletions the cleanup job handles per run.
+const CLEANUP_BATCH: i64 = 100;
+
+pub struct DocumentService;
+
+impl DocumentService {
+    /// Stores a new document as version 1, optionally linked to one of the
+    /// project's records.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn upload(
+        upload: DocumentUpload,
+        content_type: Option<&str>,
+        content: Bytes,
+        user_id: Uuid,
+        role: &UserRole,
+        audit: &AuditContext,
+        storage: &dyn DocumentStorage,
+        pool: &PgPool,
+    ) -> Result<Document, ServiceError> {
+        upload.validate()?;
+        Self::validate_filename(&upload.filename)?;
+        let content_type = Self::check_content(content_type, &content)?;
+        let link = match (upload.target_type, upload.target_id) {
+            (Some(target_type), Some(target_id)) => Some((target_type, target_id)),
+            (None, None) => None,
+            _ => {
+                return Err(ServiceError::invalid_field(
+                    "target_id",
+                    "required",
+                    "target_type and target_id must be given together",
+                ))
+            }
+        };
+
+        // Checked again under lock below, but spares storing content for
+        // someone who may not add it
+        let mut conn = pool.acquire().await?;
+        if !ProjectService::is_visible_to(upload.project_id, user_id, role, &mut conn).await? {
+            return Err(ServiceError::NotFound("Project not found".into()));
+        }
+        drop(conn);
+
+        let document_id = Uuid::new_v4();
+        let version_id = Uuid::new_v4();
+        let storage_key = format!("{}/{}", document_id, version_id);
+        storage
+            .put(&storage_key, &content_type, content.clone())
+            .await?;
+
+        let result = async {
+            let mut tx = pool.begin().await?;
+            if !ProjectService::is_visible_to(upload.project_id, user_id, role, &mut tx).await? {
+                return Err(ServiceError::NotFound("Project not found".into()));
+            }
+            ProjectService::lock(upload.project_id, &mut tx)
+                .await?
+                .ensure_writable()?;
+            if let Some((target_type, target_id)) = link {
+                Self::check_target(target_type, target_id, upload.project_id, &mut tx).await?;
+            }
+
+            let title = upload.title.unwrap_or_else(|| upload.filename.clone());
+            sqlx::query!(
+                r#"
+                INSERT INTO documents (id, project_id, title, description, created_by)
+                VALUES ($1, $2, $3, $4, $5)
+                "#,
+                document_id,
+                upload.project_id,
+                title,
+                upload.description,
+                user_id
+            )
+            .execute(&mut *tx)
+            .await?;
+            Self::insert_version(
+                version_id,
+                document_id,
+                1,
+                &upload.filename,
+                &content_type,
+                &content,
+                &storage_key,
+                user_id,
+                &mut tx,
+            )
+            .await?;
+            let document = Self::fetch(document_id, false, &mut tx).await?;
+
+            AuditService::record_create(
+                &mut tx,
+                audit,
+                AuditEntity::Document,
+                document.id,
+                &document,
+            )
+            .await?;
+            if let Some((target_type, target_id)) = link {
+                Self::insert_link(document_id, target_type, target_id, audit, &mut tx).await?;
+            }
+            RealtimeService::publish_in(
+                ChangeKind::DocumentUploaded,
+                document.project_id,
+                document.id,
+                Some(document.version),
+                audit,
+                &mut tx,
+            )
+            .await?;
+            tx.commit().await?;
+
+            Ok(document)
+        }
+        .await;
+
+        if result.is_err() {
+            Self::discard(&storage_key, storage).await;
+        }
+        result
+    }
+
+    /// Stores new content for a document. Earlier versions stay available.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn add_version(
+        id: Uuid,
+        upload: VersionUpload,
+        content_type: Option<&str>,
+        content: Bytes,
+        expected_version: Option<i32>,
+        user_id: Uuid,
+        role: &UserRole,
+        audit: &AuditContext,
+        storage: &dyn DocumentStorage,
+        pool: &PgPool,
+    ) -> Result<Document, ServiceError> {
+        upload.validate()?;
+        if let Some(filename) = &upload.filename {
+            Self::validate_filename(filename)?;
+        }
+        let content_type = Self::check_content(content_type, &content)?;
+
+        let mut conn = pool.acquire().await?;
+        let current = Self::fetch(id, false, &mut conn).await?;
+        Self::ensure_visible(&current, user_id, role, &mut conn).await?;
+        current.check_version(expected_version)?;
+        drop(conn);
+
+        let version_id = Uuid::new_v4();
+        let storage_key = format!("{}/{}", id, version_id);
+        storage
+            .put(&storage_key, &content_type, content.clone())
+            .await?;
+
+        let result = async {
+            let mut tx = pool.begin().await?;
+            let existing = Self::fetch(id, true, &mut tx).await?;
+            Self::ensure_visible(&existing, user_id, role, &mut tx).await?;
+            existing.check_version(expected_version)?;
+            ProjectService::lock(existing.project_id, &mut tx)
+                .await?
+                .ensure_writable()?;
+
+            let filename = upload
+                .filename
+                .clone()
+                .unwrap_or_else(|| existing.filename.clone());
+            let number = existing.current_version + 1;
+            Self::insert_version(
+                version_id,
+                id,
+                number,
+                &filename,
+                &content_type,
+                &content,
+                &storage_key,
+                user_id,
+                &mut tx,
+            )
+            .await?;
+            sqlx::query!(
+                r#"
+                UPDATE documents
+                SET current_version = $2, updated_at = NOW(), version = version + 1
+                WHERE id = $1
+                "#,
+                id,
+                number
+            )
+            .execute(&mut *tx)
+            .await?;
+            let updated = Self::fetch(id, false, &mut tx).await?;
+
+            AuditService::record_update(
+                &mut tx,
+                audit,
+                AuditEntity::Document,
+                id,
+                &existing,
+                &updated,
+            )
+            .await?;
+            RealtimeService::publish_in(
+                ChangeKind::DocumentUploaded,
+                updated.project_id,
+                updated.id,
+                Some(updated.version),
+                audit,
+                &mut tx,
+            )
+            .await?;
+            tx.commit().await?;
+
+            Ok(updated)
+        }
+        .await;
+
+        if result.is_err() {
+            Self::discard(&storage_key, storage).await;
+        }
+        result
+    }
+
+    /// The documents of a project, or those linked to one of its records,
+    /// by title.
+    pub async fn list(
+        filter: &DocumentFilter,
+        user_id: Uuid,
+        role: &UserRole,
+        pool: &PgPool,
+    ) -> Result<Vec<Document>, ServiceError> {
+        let mut conn = pool.acquire().await?;
+        let documents = match (filter.target_type, filter.target_id, filter.project_id) {
+            (Some(target_type), Some(target_id), _) => {
+                Self::visible_target(target_type, target_id, user_id, role, &mut conn).await?;
+                sqlx::query_as!(
+                    Document,
+                    r#"
+                    SELECT d.id, d.project_id, d.title, d.description, d.current_version,
+                           v.filename, v.content_type, v.size_bytes, v.checksum,
+                           d.created_by, d.created_at, d.updated_at, d.version
+                    FROM documents d
+                    JOIN document_versions v
+                      ON v.document_id = d.id AND v.number = d.current_version
+                    JOIN document_links l ON l.document_id = d.id
+                    WHERE l.target_type = $1 AND l.target_id = $2
+                    ORDER BY d.title, d.id
+                    "#,
+                    target_type as DocumentTarget,
+                    target_id
+                )
+                .fetch_all(&mut *conn)
+                .await?
+            }
+            (None, None, Some(project_id)) => {
+                if !ProjectService::is_visible_to(project_id, user_id, role, &mut conn).await? {
+                    return Err(ServiceError::NotFound("Project not found".into()));
+                }
+                sqlx::query_as!(
+                    Document,
+                    r#"
+                    SELECT d.id, d.project_id, d.title, d.description, d.current_version,
+                           v.filename, v.content_type, v.size_bytes, v.checksum,
+                           d.created_by, d.created_at, d.updated_at, d.version
+                    FROM documents d
+                    JOIN document_versions v
+                      ON v.document_id = d.id AND v.number = d.current_version
+                    WHERE d.project_id = $1
+                    ORDER BY d.title, d.id
+                    "#,
+                    project_id
+                )
+                .fetch_all(&mut *conn)
+                .await?
+            }
+            _ => {
+                return Err(ServiceError::invalid_field(
+                    "project_id",
+                    "required",
+                    "filter by project_id, or by target_type and target_id",
+                ))
+            }
+        };
+
+        Ok(documents)
+    }
+
+    pub async fn get(
+        id: Uuid,
+        user_id: Uuid,
+        role: &UserRole,
+        pool: &PgPool,
+    ) -> Result<Document, ServiceError> {
+        let mut conn = pool.acquire().await?;
+        let document = Self::fetch(id, false, &mut conn).await?;
+        Self::ensure_visible(&document, user_id, role, &mut conn).await?;
+
+        Ok(document)
+    }
+
+    /// Changes a document's title or description.
+    pub async fn update(
+        id: Uuid,
+        update: DocumentUpdate,
+        expected_version: Option<i32>,
+        user_id: Uuid,
+        role: &UserRole,
+        audit: &AuditContext,
+        pool: &PgPool,
+    ) -> Result<Document, ServiceError> {
+        update.validate()?;
+
+        let mut tx = pool.begin().await?;
+        let existing = Self::fetch(id, true, &mut tx).await?;
+        Self::ensure_visible(&existing, user_id, role, &mut tx).await?;
+        existing.check_version(expected_version)?;
+        ProjectService::lock(existing.project_id, &mut tx)
+            .await?
+            .ensure_writable()?;
+
+        let title = update
+            .title
+            .apply_required("title", existing.title.clone())?;
+        let description = update.description.apply(existing.description.clone());
+        sqlx::query!(
+            r#"
+            UPDATE documents
+            SET title = $2, description = $3, updated_at = NOW(), version = version + 1
+            WHERE id = $1
+            "#,
+            id,
+            title,
+            description
+        )
+        .execute(&mut *tx)
+        .await?;
+        let updated = Self::fetch(id, false, &mut tx).await?;
+
+        AuditService::record_update(
+            &mut tx,
+            audit,
+            AuditEntity::Document,
+            id,
+            &existing,
+            &updated,
+        )
+        .await?;
+        tx.commit().await?;
+
+        Ok(updated)
+    }
+
+    /// Removes a document with all its versions and links. Only the user who
+    /// added it, an admin or a project manager may. The stored content is
+    /// removed afterwards by the `document_cleanup` job.
+    pub async fn delete(
+        id: Uuid,
+        expected_version: Option<i32>,
+        user_id: Uuid,
+        role: &UserRole,
+        audit: &AuditContext,
+        pool: &PgPool,
+    ) -> Result<(), ServiceError> {
+        let mut tx = pool.begin().await?;
+        let existing = Self::fetch(id, true, &mut tx).await?;
+        Self::ensure_visible(&existing, user_id, role, &mut tx).await?;
+        if existing.created_by != Some(user_id) && !role.sees_all_projects() {
+            return Err(ServiceError::Forbidden);
+        }
+        existing.check_version(expected_version)?;
+        ProjectService::lock(existing.project_id, &mut tx)
+            .await?
+            .ensure_writable()?;
+
+        sqlx::query!("DELETE FROM documents WHERE id = $1", id)
+            .execute(&mut *tx)
+            .await?;
+        AuditService::record_delete(&mut tx, audit, AuditEntity::Document, id, &existing).await?;
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    /// Every version of a document, oldest first.
+    pub async fn versions(
+        id: Uuid,
+        user_id: Uuid,
+        role: &UserRole,
+        pool: &PgPool,
+    ) -> Result<Vec<DocumentVersion>, ServiceError> {
+        let mut conn = pool.acquire().await?;
+        let document = Self::fetch(id, false, &mut conn).await?;
+        Self::ensure_visible(&document, user_id, role, &mut conn).await?;
+
+        let versions = sqlx::query_as!(
+            DocumentVersion,
+            r#"
+            SELECT id, document_id, number, filename, content_type, size_bytes,
+                   checksum, storage_key, uploaded_by, created_at
+            FROM document_versions
+            WHERE document_id = $1
+            ORDER BY number
+            "#,
+            id
+        )
+        .fetch_all(&mut *conn)
+        .await?;
+
+        Ok(versions)
+    }
+
+    /// The content of a version, the current one by default. Content that
+    /// no longer matches its checksum is not handed out.
+    pub async fn download(
+        id: Uuid,
+        number: Option<i32>,
+        user_id: Uuid,
+        role: &UserRole,
+        storage: &dyn DocumentStorage,
+        pool: &PgPool,
+    ) -> Result<(DocumentVersion, Bytes), ServiceError> {
+        let mut conn = pool.acquire().await?;
+        let document = Self::fetch(id, false, &mut conn).await?;
+        Self::ensure_visible(&document, user_id, role, &mut conn).await?;
+
+        let version = sqlx::query_as!(
+            DocumentVersion,
+            r#"
+            SELECT id, document_id, number, filename, content_type, size_bytes,
+                   checksum, storage_key, uploaded_by, created_at
+            FROM document_versions
+            WHERE document_id = $1 AND number = $2
+            "#,
+            id,
+            number.unwrap_or(document.current_version)
+        )
+        .fetch_optional(&mut *conn)
+        .await?
+        .ok_or(ServiceError::NotFound("Document version not found".into()))?;
+        drop(conn);
+
+        let content = storage.get(&version.storage_key).await?;
+        if content.len() as i64 != version.size_bytes || sha256_hex(&content) != version.checksum {
+            return Err(ServiceError::StorageError(format!(
+                "{}: content does not match its checksum",
+                version.storage_key
+            )));
+        }
+
+        Ok((version, content))
+    }
+
+    pub async fn links(
+        id: Uuid,
+        user_id: Uuid,
+        role: &UserRole,
+        pool: &PgPool,
+    ) -> Result<Vec<DocumentLink>, ServiceError> {
+        let mut conn = pool.acquire().await?;
+        let document = Self::fetch(id, false, &mut conn).await?;
+        Self::ensure_visible(&document, user_id, role, &mut conn).await?;
+
+        let links = sqlx::query_as!(
+            DocumentLink,
+            r#"
+            SELECT document_id, target_type as "target_type: DocumentTarget", target_id,
+                   created_by, created_at
+            FROM document_links
+            WHERE document_id = $1
+            ORDER BY created_at, target_id
+            "#,
+            id
+        )
+        .fetch_all(&mut *conn)
+        .await?;
+
+        Ok(links)
+    }
+
+    /// Attaches a document to a record of its project. Linking it again is
+    /// harmless.
+    pub async fn link(
+        id: Uuid,
+        link: DocumentLinkCreate,
+        user_id: Uuid,
+        role: &UserRole,
+        audit: &AuditContext,
+        pool: &PgPool,
+    ) -> Result<DocumentLink, ServiceError> {
+        let mut tx = pool.begin().await?;
+        let document = Self::fetch(id, true, &mut tx).await?;
+        Self::ensure_visible(&document, user_id, role, &mut tx).await?;
+        ProjectService::lock(document.project_id, &mut tx)
+            .await?
+            .ensure_writable()?;
+        Self::check_target(
+            link.target_type,
+            link.target_id,
+            document.project_id,
+            &mut tx,
+        )
+        .await?;
+
+        let link = Self::insert_link(id, link.target_type, link.target_id, audit, &mut tx).await?;
+        tx.commit().await?;
+
+        Ok(link)
+    }
+
+    pub async fn unlink(
+        id: Uuid,
+        target_type: DocumentTarget,
+        target_id: Uuid,
+        user_id: Uuid,
+        role: &UserRole,
+        audit: &AuditContext,
+        pool: &PgPool,
+    ) -> Result<(), ServiceError> {
+        let mut tx = pool.begin().await?;
+        let document = Self::fetch(id, true, &mut tx).await?;
+        Self::ensure_visible(&document, user_id, role, &mut tx).await?;
+        ProjectService::lock(document.project_id, &mut tx)
+            .await?
+            .ensure_writable()?;
+
+        let removed = sqlx::query_as!(
+            DocumentLink,
+            r#"
+            DELETE FROM document_links
+            WHERE document_id = $1 AND target_type = $2 AND target_id = $3
+            RETURNING document_id, target_type as "target_type: DocumentTarget", target_id,
+                      created_by, created_at
+            "#,
+            id,
+            target_type as DocumentTarget,
+            target_id
+        )
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or(ServiceError::NotFound("Document link not found".into()))?;
+
+        AuditService::record_delete(&mut tx, audit, AuditEntity::DocumentLink, id, &removed)
+            .await?;
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    /// Links documents to a record in the caller's transaction, e.g. to the
+    /// phase transition they were submitted with. Every document must
+    /// belong to `project_id`.
+    pub(crate) async fn link_in(
+        document_ids: &[Uuid],
+        target_type: DocumentTarget,
+        target_id: Uuid,
+        project_id: Uuid,
+        audit: &AuditContext,
+        conn: &mut PgConnection,
+    ) -> Result<(), ServiceError> {
+        let found = sqlx::query_scalar!(
+            r#"SELECT COUNT(*) as "count!" FROM documents WHERE id = ANY($1) AND project_id = $2"#,
+            document_ids,
+            project_id
+        )
+        .fetch_one(&mut *conn)
+        .await?;
+        let mut distinct = document_ids.to_vec();
+        distinct.sort();
+        distinct.dedup();
+        if found != distinct.len() as i64 {
+            return Err(ServiceError::invalid_field(
+                "documents",
+                "project",
+                "documents must belong to the project",
+            ));
+        }
+
+        for document_id in distinct {
+            Self::insert_link(document_id, target_type, target_id, audit, conn).await?;
+        }
+        Ok(())
+    }
+
+    /// Removes stored content whose version rows are gone. Returns how many
+    /// objects were removed; failures are left queued for the next run.
+    pub async fn remove_deleted_content(
+        storage: &dyn DocumentStorage,
+        pool: &PgPool,
+    ) -> Result<usize, ServiceError> {
+        let keys = sqlx::query_scalar!(
+            "SELECT storage_key FROM document_blob_deletions ORDER BY created_at LIMIT $1",
+            CLEANUP_BATCH
+        )
+        .fetch_all(pool)
+        .await?;
+
+        let mut removed = 0;
+        for key in keys {
+            match storage.delete(&key).await {
+                Ok(()) => {
+                    sqlx::query!(
+                        "DELETE FROM document_blob_deletions WHERE storage_key = $1",
+                        key
+                    )
+                    .execute(pool)
+                    .await?;
+                    removed += 1;
+                }
+                Err(e) => log::warn!("Failed to remove document content {}: {}", key, e),
+            }
+        }
+
+        Ok(removed)
+    }
+
+    /// Normalized content type of an upload, once the content passes the
+    /// size and type limits.
+    fn check_content(content_type: Option<&str>, content: &Bytes) -> Result<String, ServiceError> {
+        if content.is_empty() {
+            return Err(ServiceError::invalid_field(
+                "content",
+                "required",
+                "the file is empty",
+            ));
+        }
+        if content.len() > MAX_DOCUMENT_BYTES {
+            return Err(ServiceError::invalid_field(
+                "content",
+                "size",
+                format!(
+                    "files may be at most {} MiB",
+                    MAX_DOCUMENT_BYTES / 1024 / 1024
+                ),
+            ));
+        }
+
+        let content_type = content_type
+            .and_then(|value| value.split(';').next())
+            .map(|value| value.trim().to_ascii_lowercase())
+            .unwrap_or_default();
+        if !ALLOWED_CONTENT_TYPES.contains(&content_type.as_str()) {
+            return Err(ServiceError::invalid_field(
+                "content_type",
+                "type",
+                format!("'{}' files are not accepted", content_type),
+            ));
+        }
+
+        Ok(content_type)
+    }
+
+    /// Filenames end up in `Content-Disposition`, so they may not carry
+    /// paths, quotes or control characters.
+    fn validate_filename(filename: &str) -> Result<(), ServiceError> {
+        if filename.trim().is_empty()
+            || filename
+                .chars()
+                .any(|c| c.is_control() || matches!(c, '/' | '\\' | '"'))
+        {
+            return Err(ServiceError::invalid_field(
+                "filename",
+                "filename",
+                "must be a plain file name without paths, quotes or control characters",
+            ));
+        }
+        Ok(())
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    async fn insert_version(
+        id: Uuid,
+        document_id: Uuid,
+        number: i32,
+        filename: &str,
+        content_type: &str,
+        content: &Bytes,
+        storage_key: &str,
+        user_id: Uuid,
+        conn: &mut PgConnection,
+    ) -> Result<(), ServiceError> {
+        sqlx::query!(
+            r#"
+            INSERT INTO document_versions (
+                id, document_id, number, filename, content_type, size_bytes, checksum,
+                storage_key, uploaded_by
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+            "#,
+            id,
+            document_id,
+            number,
+            filename,
+            content_type,
+            content.len() as i64,
+            sha256_hex(content),
+            storage_key,
+            user_id
+        )
+        .execute(conn)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn insert_link(
+        document_id: Uuid,
+        target_type: DocumentTarget,
+        target_id: Uuid,
+        audit: &AuditContext,
+        conn: &mut PgConnection,
+    ) -> Result<DocumentLink, ServiceError> {
+        let created = sqlx::query_as!(
+            DocumentLink,
+            r#"
+            INSERT INTO document_links (document_id, target_type, target_id, created_by)
+            VALUES ($1, $2, $3, $4)
+            ON CONFLICT DO NOTHING
+            RETURNING document_id, target_type as "target_type: DocumentTarget", target_id,
+                      created_by, created_at
+            "#,
+            document_id,
+            target_type as DocumentTarget,
+            target_id,
+            audit.actor_id
+        )
+        .fetch_optional(&mut *conn)
+        .await?;
+
+        match created {
+            Some(link) => {
+                AuditService::record_create(
+                    &mut *conn,
+                    audit,
+                    AuditEntity::DocumentLink,
+                    document_id,
+                    &link,
+                )
+                .await?;
+                Ok(link)
+            }
+            None => {
+                let existing = sqlx::query_as!(
+                    DocumentLink,
+                    r#"
+                    SELECT document_id, target_type as "target_type: DocumentTarget", target_id,
+                           created_by, created_at
+                    FROM document_links
+                    WHERE document_id = $1 AND target_type = $2 AND target_id = $3
+                    "#,
+                    document_id,
+                    target_type as DocumentTarget,
+                    target_id
+                )
+                .fetch_one(&mut *conn)
+                .await?;
+                Ok(existing)
+            }
+        }
+    }
+
+    /// Best-effort removal of content stored for an upload that was not
+    /// saved.
+    async fn discard(storage_key: &str, storage: &dyn DocumentStorage) {
+        if let Err(e) = storage.delete(storage_key).await {
+            log::warn!(
+                "Failed to remove unsaved document content {}: {}",
+                storage_key,
+                e
+            );
+        }
+    }
+
+    /// Loads a document with its current version, optionally locking it.
+    async fn fetch(
+        id: Uuid,
+        for_update: bool,
+        conn: &mut PgConnection,
+    ) -> Result<Document, ServiceError> {
+        let document = if for_update {
+            sqlx::query_as!(
+                Document,
+                r#"
+                SELECT d.id, d.project_id, d.title, d.description, d.current_version,
+                       v.filename, v.content_type, v.size_bytes, v.checksum,
+                       d.created_by, d.created_at, d.updated_at, d.version
+                FROM documents d
+                JOIN document_versions v
+                  ON v.document_id = d.id AND v.number = d.current_version
+                WHERE d.id = $1
+                FOR UPDATE OF d
+                "#,
+                id
+            )
+            .fetch_optional(conn)
+            .await?
+        } else {
+            sqlx::query_as!(
+                Document,
+                r#"
+                SELECT d.id, d.project_id, d.title, d.description, d.current_version,
+                       v.filename, v.content_type, v.size_bytes, v.checksum,
+                       d.created_by, d.created_at, d.updated_at, d.version
+                FROM documents d
+                JOIN document_versions v
+                  ON v.document_id = d.id AND v.number = d.current_version
+                WHERE d.id = $1
+                "#,
+                id
+            )
+            .fetch_optional(conn)
+            .await?
+        };
+
+        document.ok_or(ServiceError::NotFound("Document not found".into()))
+    }
+
+    /// Documents are hidden along with their project.
+    async fn ensure_visible(
+        document: &Document,
+        user_id: Uuid,
+        role: &UserRole,
+        conn: &mut PgConnection,
+    ) -> Result<(), ServiceError> {
+        if ProjectService::is_visible_to(document.project_id, user_id, role, conn).await? {
+            Ok(())
+        } else {
+            Err(ServiceError::NotFound("Document not found".into()))
+        }
+    }
+
+    /// The project a record belongs to, provided the user can see it.
+    async fn visible_target(
+        target_type: DocumentTarget,
+        target_id: Uuid,
+        user_id: Uuid,
+        role: &UserRole,
+        conn: &mut PgConnection,
+    ) -> Result<Uuid, ServiceError> {
+        let project_id = Self::target_project(target_type, target_id, conn).await?;
+        match project_id {
+            Some(project_id)
+                if ProjectService::is_visible_to(project_id, user_id, role, conn).await? =>
+            {
+                Ok(project_id)
+            }
+            _ => Err(ServiceError::NotFound(format!(
+                "{} not found",
+                target_type.label()
+            ))),
+        }
+    }
+
+    /// Rejects links to records that do not exist or belong to another
+    /// project. Deliverables are milestone tasks.
+    async fn check_target(
+        target_type: DocumentTarget,
+        target_id: Uuid,
+        project_id: Uuid,
+        conn: &mut PgConnection,
+    ) -> Result<(), ServiceError> {
+        match Self::target_project(target_type, target_id, conn).await? {
+            Some(owner) if owner == project_id => Ok(()),
+            Some(_) => Err(ServiceError::invalid_field(
+                "target_id",
+                "project",
+                format!("{} belongs to another project", target_type.label()),
+            )),
+            None => Err(ServiceError::invalid_field(
+                "target_id",
+                "not_found",
+                format!("{} not found", target_type.label()),
+            )),
+        }
+    }
+
+    async fn target_project(
+        target_type: DocumentTarget,
+        target_id: Uuid,
+        conn: &mut PgConnection,
+    ) -> Result<Option<Uuid>, ServiceError> {
+        let project_id = match target_type {
+            DocumentTarget::Task => {
+                sqlx::query_scalar!(
+                    "SELECT project_id FROM tasks WHERE id = $1 AND deleted_at IS NULL",
+                    target_id
+                )
+                .fetch_optional(conn)
+                .await?
+            }
+            DocumentTarget::Deliverable => {
+                sqlx::query_scalar!(
+                "SELECT project_id FROM tasks WHERE id = $1 AND milestone AND deleted_at IS NULL",
+                target_id
+            )
+                .fetch_optional(conn)
+                .await?
+            }
+            DocumentTarget::PhaseTransition => {
+                sqlx::query_scalar!(
+                    "SELECT project_id FROM phase_transitions WHERE id = $1",
+                    target_id
+                )
+                .fetch_optional(conn)
+                .await?
+            }
+            DocumentTarget::GateReview => {
+                sqlx::query_scalar!(
+                    "SELECT project_id FROM gate_reviews WHERE id = $1",
+                    target_id
+                )
+                .fetch_optional(conn)
+                .await?
+            }
+        };
+
+        Ok(project_id)
+    }
+}
@@ -0,0 +1,135 @@
+use crate::errors::ServiceError;
+use lettre::message::Mailbox;
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+use std::env;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct OutgoingEmail {
+    pub to: String,
+    pub subject: String,
+    pub body: String,
+}
+
+enum Transport {
+    Smtp(AsyncSmtpTransport<Tokio1Executor>),
+    /// No SMTP server configured: messages are written to the log.
+    Log,
+    #[cfg(test)]
+    Memory(std::sync::Mutex<Vec<OutgoingEmail>>),
+}
+
+/// Outbound email. Configured from `SMTP_*` variables; without `SMTP_HOST`
+/// messages are only logged, which keeps local development self-contained.
+pub struct EmailService {
+    transport: Transport,
+    from: Mailbox,
+    /// Base URL of the frontend, used to build links in messages.
+    pub app_base_url: String,
+}
+
+impl EmailService {
+    pub fn from_env() -> Result<Self, String> {
+        let from = env::var("SMTP_FROM")
+            .unwrap_or_else(|_| "Waterfall Manager <no-reply@localhost>".to_string())
+            .parse()
+            .map_err(|e| format!("invalid SMTP_FROM: {}", e))?;
+        let app_base_url =
+            env::var("APP_BASE_URL").unwrap_or_else(|_| "http://localhost:3000".to_string());
+
+        let transport = match env::var("SMTP_HOST") {
+            Ok(host) if !host.is_empty() => {
+                let tls = env::var("SMTP_TLS").unwrap_or_else(|_| "starttls".to_string());
+                let mut builder = match tls.as_str() {
+                    "tls" => AsyncSmtpTransport::<Tokio1Executor>::relay(&host),
+                    "starttls" => AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&host),
+                    "none" => Ok(AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(
+                        &host,
+                    )),
+                    other => return Err(format!("invalid SMTP_TLS '{}'", other)),
+                }
+                .map_err(|e| format!("invalid SMTP_HOST: {}", e))?;
+
+                if let Ok(port) = env::var("SMTP_PORT") {
+                    builder =
+                        builder.port(port.parse().map_err(|_| "invalid SMTP_PORT".to_string())?);
+                }
+                if let (Ok(username), Ok(password)) =
+                    (env::var("SMTP_USERNAME"), env::var("SMTP_PASSWORD"))
+                {
+                    builder = builder.credentials(Credentials::new(username, password));
+                }
+                Transport::Smtp(builder.build())
+            }
+            _ => Transport::Log,
+        };
+
+        Ok(EmailService {
+            transport,
+            from,
+            app_base_url,
+        })
+    }
+
+    /// Captures messages in memory so tests can inspect them.
+    #[cfg(test)]
+    pub fn in_memory() -> Self {
+        EmailService {
+            transport: Transport::Memory(std::sync::Mutex::new(Vec::new())),
+            from: "Waterfall Manager <no-reply@localhost>".parse().unwrap(),
+            app_base_url: "http://localhost:3000".to_string(),
+        }
+    }
+
+    /// Points at an SMTP port nothing listens on, so every send fails.
+    #[cfg(test)]
+    pub fn unreachable() -> Self {
+        EmailService {
+            transport: Transport::Smtp(
+                AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous("127.0.0.1")
+                    .port(1)
+                    .build(),
+            ),
+            from: "Waterfall Manager <no-reply@localhost>".parse().unwrap(),
+            app_base_url: "http://localhost:3000".to_string(),
+        }
+    }
+
+    #[cfg(test)]
+    pub fn sent(&self) -> Vec<OutgoingEmail> {
+        match &self.transport {
+            Transport::Memory(sent) => sent.lock().unwrap().clone(),
+            _ => Vec::new(),
+        }
+    }
+
+    pub async fn send(&self, email: OutgoingEmail) -> Result<(), ServiceError> {
+        match &self.transport {
+            Transport::Smtp(mailer) => {
+                let to: Mailbox = email
+                    .to
+                    .parse()
+                    .map_err(|_| ServiceError::BadRequest("Invalid recipient address".into()))?;
+                let message = Message::builder()
+                    .from(self.from.clone())
+                    .to(to)
+                    .subject(email.subject)
+                    .body(email.body)
+                    .map_err(|e| {
+                        log::error!("Failed to build email: {:?}", e);
+                        ServiceError::InternalServerError
+                    })?;
+                mailer.send(message).await.map_err(|e| {
+                    log::error!("Failed to send email: {:?}", e);
+                    ServiceError::InternalServerError
+                })?;
+            }
+            Transport::Log => {
+                log::info!("Email to {} ({}):\n{}", email.to, email.subject, email.body);
+            }
+            #[cfg(test)]
+            Transport::Memory(sent) => sent.lock().unwrap().push(email),
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,464 @@
+use std::collections::{HashMap, HashSet};
+
+use bigdecimal::ToPrimitive;
+use chrono::{DateTime, Utc};
+use sqlx::types::BigDecimal;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::errors::ServiceError;
+use crate::models::export::{ExportFormat, PlanExport};
+use crate::models::project::Project;
+use crate::models::resource::Resource;
+use crate::models::task::{DependencyType, Task, TaskLink, TaskStatus};
+use crate::models::user::UserRole;
+use crate::services::project_service::ProjectService;
+use crate::services::resource_service::ResourceService;
+use crate::services::task_service::TaskService;
+
+const MSPDI_DATE_FORMAT: &str = "%Y-%m-%dT%H:%M:%S";
+const CSV_DATE_FORMAT: &str = "%Y-%m-%d %H:%M";
+/// MSPDI `LagFormat` for lags shown in minutes
+const LAG_FORMAT_MINUTES: u8 = 3;
+
+/// A project with its tasks in outline order, numbered from 1 like the UIDs
+/// of an MSPDI file.
+struct Plan {
+    project: Project,
+    tasks: Vec<PlanTask>,
+    links: HashMap<(Uuid, Uuid), TaskLink>,
+    resources: Vec<Resource>,
+}
+
+struct PlanTask {
+    task: Task,
+    uid: usize,
+    level: usize,
+    summary: bool,
+}
+
+impl Plan {
+    fn task_uids(&self) -> HashMap<Uuid, usize> {
+        self.tasks
+            .iter()
+            .map(|entry| (entry.task.id, entry.uid))
+            .collect()
+    }
+
+    fn resource_uids(&self) -> HashMap<Uuid, usize> {
+        self.resources
+            .iter()
+            .enumerate()
+            .map(|(index, resource)| (resource.id, index + 1))
+            .collect()
+    }
+
+    /// Predecessors of a task as (uid, type, lag in minutes).
+    fn predecessors(
+        &self,
+        task: &Task,
+        uids: &HashMap<Uuid, usize>,
+    ) -> Vec<(usize, DependencyType, i32)> {
+        task.dependencies
+            .iter()
+            .filter_map(|predecessor| {
+                let uid = *uids.get(predecessor)?;
+                let link = self.links.get(&(task.id, *predecessor));
+                Some(match link {
+                    Some(link) => (uid, link.link_type, link.lag_minutes),
+                    None => (uid, DependencyType::FinishToStart, 0),
+                })
+            })
+            .collect()
+    }
+}
+
+pub struct ExportService;
+
+impl ExportService {
+    /// Serializes the project, its tasks and the resources assigned to them.
+    pub async fn export(
+        project_id: Uuid,
+        format: ExportFormat,
+        user_id: Uuid,
+        role: &UserRole,
+        pool: &PgPool,
+    ) -> Result<PlanExport, ServiceError> {
+        let mut conn = pool.acquire().await?;
+        if !ProjectService::is_visible_to(project_id, user_id, role, &mut conn).await? {
+            return Err(ServiceError::NotFound("Project not found".into()));
+        }
+        drop(conn);
+
+        let plan = Self::load(project_id, pool).await?;
+        let name = slug(&plan.project.name);
+
+        Ok(match format {
+            ExportFormat::Mspdi => PlanExport {
+                content_type: "application/xml",
+                filename: format!("{}.xml", name),
+                body: Self::to_mspdi(&plan),
+            },
+            ExportFormat::Csv => PlanExport {
+                content_type: "text/csv",
+                filename: format!("{}.csv", name),
+                body: Self::to_csv(&plan),
+            },
+        })
+    }
+
+    async fn load(project_id: Uuid, pool: &PgPool) -> Result<Plan, ServiceError> {
+        let project = ProjectService::get_by_id(project_id, pool).await?;
+        let tasks = TaskService::get_by_project(project_id, pool).await?;
+        let links = TaskService::get_links_by_project(project_id, pool)
+            .await?
+            .into_iter()
+            .map(|link| ((link.task_id, link.predecessor_id), link))
+            .collect();
+        let assigned: Vec<Uuid> = tasks
+            .iter()
+            .flat_map(|task| task.assigned_to.iter().copied())
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .collect();
+        let resources = ResourceService::get_by_ids(&assigned, pool).await?;
+
+        Ok(Plan {
+            project,
+            tasks: outline(tasks),
+            links,
+            resources,
+        })
+    }
+
+    fn to_mspdi(plan: &Plan) -> String {
+        let task_uids = plan.task_uids();
+        let resource_uids = plan.resource_uids();
+        let project = &plan.project;
+        let mut xml = XmlWriter::default();
+
+        xml.line(r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>"#);
+        xml.open_with(
+            "Project",
+            r#" xmlns="http://schemas.microsoft.com/project""#,
+        );
+        xml.field("SaveVersion", "14");
+        xml.field("Name", &format!("{}.xml", project.name));
+        xml.field("Title", &project.name);
+        if let Some(description) = &project.description {
+            xml.field("Subject", description);
+        }
+        xml.field("StartDate", &mspdi_date(project.start_date));
+        xml.field("FinishDate", &mspdi_date(project.end_date));
+
+        xml.open("Tasks");
+        // MS Project expects the project summary task as UID 0
+        xml.open("Task");
+        xml.field("UID", "0");
+        xml.field("ID", "0");
+        xml.field("Name", &project.name);
+        xml.field("OutlineLevel", "0");
+        xml.field("Summary", "1");
+        xml.field("Start", &mspdi_date(project.start_date));
+        xml.field("Finish", &mspdi_date(project.end_date));
+        xml.close("Task");
+        for entry in &plan.tasks {
+            let task = &entry.task;
+            xml.open("Task");
+            xml.field("UID", &entry.uid.to_string());
+            xml.field("ID", &entry.uid.to_string());
+            xml.field("Name", &task.name);
+            if let Some(wbs) = &task.wbs {
+                xml.field("WBS", wbs);
+            }
+            xml.field("OutlineLevel", &entry.level.to_string());
+            xml.field("Summary", flag(entry.summary));
+            xml.field("Milestone", flag(task.milestone));
+            xml.field("Start", &mspdi_date(task.start_date));
+            xml.field("Finish", &mspdi_date(task.end_date));
+            xml.field("PercentComplete", &percent(&task.progress).to_string());
+            if let Some(description) = &task.description {
+                xml.field("Notes", description);
+            }
+            for (uid, link_type, lag_minutes) in plan.predecessors(task, &task_uids) {
+                xml.open("PredecessorLink");
+                xml.field("PredecessorUID", &uid.to_string());
+                xml.field("Type", &link_type.mspdi_code().to_string());
+                // Lags are written in tenths of a minute
+                xml.field("LinkLag", &(i64::from(lag_minutes) * 10).to_string());
+                xml.field("LagFormat", &LAG_FORMAT_MINUTES.to_string());
+                xml.close("PredecessorLink");
+            }
+            xml.close("Task");
+        }
+        xml.close("Tasks");
+
+        xml.open("Resources");
+        for (index, resource) in plan.resources.iter().enumerate() {
+            xml.open("Resource");
+            xml.field("UID", &(index + 1).to_string());
+            xml.field("ID", &(index + 1).to_string());
+            xml.field("Name", &resource.name);
+            xml.field("Type", "1");
+            xml.field("EmailAddress", &resource.email);
+            xml.field("Group", &resource.role);
+            let units = &resource.availability / BigDecimal::from(100);
+            xml.field("MaxUnits", &units.normalized().to_string());
+            xml.field(
+                "StandardRate",
+                &resource.hourly_rate.normalized().to_string(),
+            );
+            xml.close("Resource");
+        }
+        xml.close("Resources");
+
+        xml.open("Assignments");
+        let mut assignment_uid = 0;
+        for entry in &plan.tasks {
+            for resource_uid in entry
+                .task
+                .assigned_to
+                .iter()
+                .filter_map(|id| resource_uids.get(id))
+            {
+                assignment_uid += 1;
+                xml.open("Assignment");
+                xml.field("UID", &assignment_uid.to_string());
+                xml.field("TaskUID", &entry.uid.to_string());
+                xml.field("ResourceUID", &resource_uid.to_string());
+                xml.close("Assignment");
+            }
+        }
+        xml.close("Assignments");
+        xml.close("Project");
+
+        xml.out
+    }
+
+    fn to_csv(plan: &Plan) -> String {
+        let task_uids = plan.task_uids();
+        let resources: HashMap<Uuid, &Resource> = plan
+            .resources
+            .iter()
+            .map(|resource| (resource.id, resource))
+            .collect();
+
+        let mut out = String::new();
+        csv_row(
+            &mut out,
+            &[
+                "ID",
+                "WBS",
+                "Outline Level",
+                "Name",
+                "Description",
+                "Start",
+                "Finish",
+                "Milestone",
+                "Progress",
+                "Status",
+                "Predecessors",
+                "Resources",
+            ],
+        );
+        for entry in &plan.tasks {
+            let task = &entry.task;
+            // MS Project notation, e.g. `3FS+480m`
+            let predecessors: Vec<String> = plan
+                .predecessors(task, &task_uids)
+                .into_iter()
+                .map(|(uid, link_type, lag)| match lag {
+                    0 => format!("{}{}", uid, link_type.abbreviation()),
+                    lag => format!("{}{}{:+}m", uid, link_type.abbreviation(), lag),
+                })
+                .collect();
+            let assignees: Vec<&str> = task
+                .assigned_to
+                .iter()
+                .filter_map(|id| resources.get(id))
+                .map(|resource| resource.name.as_str())
+                .collect();
+            let status = match task.status {
+                TaskStatus::Pending => "pending",
+                TaskStatus::InProgress => "in_progress",
+                TaskStatus::Completed => "completed",
+            };
+
+            csv_row(
+                &mut out,
+                &[
+                    &entry.uid.to_string(),
+                    task.wbs.as_deref().unwrap_or_default(),
+                    &entry.level.to_string(),
+                    &task.name,
+                    task.description.as_deref().unwrap_or_default(),
+                    &task.start_date.format(CSV_DATE_FORMAT).to_string(),
+                    &task.end_date.format(CSV_DATE_FORMAT).to_string(),
+                    if task.milestone { "yes" } else { "no" },
+                    &task.progress.normalized().to_string(),
+                    status,
+                    &predecessors.join("; "),
+                    &assignees.join("; "),
+                ],
+            );
+        }
+        out
+    }
+}
+
+/// Orders tasks depth first, children after their parent, siblings by WBS
+/// code, then start date, then creation.
+fn outline(tasks: Vec<Task>) -> Vec<PlanTask> {
+    let ids: HashSet<Uuid> = tasks.iter().map(|task| task.id).collect();
+    let mut children: HashMap<Option<Uuid>, Vec<Task>> = HashMap::new();
+    for task in tasks {
+        // A task whose parent is gone moves to the top level
+        let parent = task.parent_id.filter(|parent| ids.contains(parent));
+        children.entry(parent).or_default().push(task);
+    }
+    for siblings in children.values_mut() {
+        siblings.sort_by(|a, b| {
+            wbs_key(&a.wbs)
+                .cmp(&wbs_key(&b.wbs))
+                .then(a.start_date.cmp(&b.start_date))
+                .then(a.created_at.cmp(&b.created_at))
+                .then(a.name.cmp(&b.name))
+        });
+        // Popped from the back below, so keep the first sibling last
+        siblings.reverse();
+    }
+
+    let mut ordered = Vec::new();
+    let mut stack: Vec<(Option<Uuid>, usize)> = vec![(None, 0)];
+    while let Some((parent, level)) = stack.last().copied() {
+        let Some(task) = children
+            .get_mut(&parent)
+            .and_then(|siblings| siblings.pop())
+        else {
+            stack.pop();
+            continue;
+        };
+        let summary = children.get(&Some(task.id)).is_some_and(|c| !c.is_empty());
+        stack.push((Some(task.id), level + 1));
+        ordered.push(PlanTask {
+            uid: ordered.len() + 1,
+            level: level + 1,
+            summary,
+            task,
+        });
+    }
+    ordered
+}
+
+/// Numeric WBS segments, so `1.10` sorts after `1.9`; tasks without a code
+/// come last.
+pub(crate) fn wbs_key(wbs: &Option<String>) -> (bool, Vec<u64>, String) {
+    match wbs {
+        Some(code) => (
+            false,
+            code.split('.')
+                .map(|segment| segment.parse().unwrap_or(u64::MAX))
+                .collect(),
+            code.clone(),
+        ),
+        None => (true, vec![], String::new()),
+    }
+}
+
+#[derive(Default)]
+struct XmlWriter {
+    out: String,
+    depth: usize,
+}
+
+impl XmlWriter {
+    fn line(&mut self, line: &str) {
+        self.out.push_str(&"    ".repeat(self.depth));
+        self.out.push_str(line);
+        self.out.push('\n');
+    }
+
+    fn open(&mut self, name: &str) {
+        self.open_with(name, "");
+    }
+
+    fn open_with(&mut self, name: &str, attributes: &str) {
+        self.line(&format!("<{}{}>", name, attributes));
+        self.depth += 1;
+    }
+
+    fn close(&mut self, name: &str) {
+        self.depth -= 1;
+        self.line(&format!("</{}>", name));
+    }
+
+    fn field(&mut self, name: &str, value: &str) {
+        self.line(&format!("<{0}>{1}</{0}>", name, escape_xml(value)));
+    }
+}
+
+fn escape_xml(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn csv_row(out: &mut String, fields: &[&str]) {
+    let fields: Vec<String> = fields.iter().map(|field| csv_field(field)).collect();
+    out.push_str(&fields.join(","));
+    out.push_str("\r\n");
+}
+
+fn csv_field(value: &str) -> String {
+    // Spreadsheets run cells starting with these as formulas
+    let value = if value.starts_with(['=', '+', '-', '@']) {
+        format!("'{}", value)
+    } else {
+        value.to_string()
+    };
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value
+    }
+}
+
+fn mspdi_date(date: DateTime<Utc>) -> String {
+    date.format(MSPDI_DATE_FORMAT).to_string()
+}
+
+fn flag(value: bool) -> &'static str {
+    if value {
+        "1"
+    } else {
+        "0"
+    }
+}
+
+/// MSPDI stores whole percentages.
+fn percent(progress: &BigDecimal) -> i64 {
+    progress.round(0).to_i64().unwrap_or(0)
+}
+
+fn slug(name: &str) -> String {
+    let slug = name
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("-")
+        .to_lowercase();
+    if slug.is_empty() {
+        "project".to_string()
+    } else {
+        slug
+    }
+}
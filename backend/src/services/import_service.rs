@@ -0,0 +1,571 @@
+use std::collections::{HashMap, HashSet};
+use std::str::FromStr;
+
+use chrono::{DateTime, Duration, NaiveDateTime, Utc};
+use roxmltree::{Document, Node};
+use sqlx::types::BigDecimal;
+use sqlx::PgPool;
+
+use crate::errors::ServiceError;
+use crate::models::audit::AuditContext;
+use crate::models::import::{
+    ImportReport, ImportWarning, ImportedLink, ImportedProject, ImportedResource, ImportedTask,
+    ResourceAction,
+};
+use crate::models::patch::Patch;
+use crate::models::project::ProjectCreate;
+use crate::models::resource::{Resource, ResourceCreate};
+use crate::models::task::{DependencyType, TaskCreate, TaskLink, TaskStatus, TaskUpdate};
+use crate::services::project_service::ProjectService;
+use crate::services::resource_service::ResourceService;
+use crate::services::task_service::TaskService;
+
+/// `ResourceUID` MS Project writes for assignments without a resource.
+const UNASSIGNED_RESOURCE_UID: i64 = -65535;
+/// `LagFormat` values for lags given as a percentage of the predecessor.
+const PERCENT_LAG_FORMATS: &[&str] = &["19", "20"];
+const MAX_NAME_LENGTH: usize = 255;
+/// Largest plan file accepted by the import endpoint.
+pub const MAX_IMPORT_BYTES: usize = 10 * 1024 * 1024;
+
+pub struct ImportService;
+
+impl ImportService {
+    /// Imports a Microsoft Project XML (MSPDI) plan as a new project with its
+    /// tasks, dependencies and resource assignments. With `dry_run` nothing
+    /// is written and the report shows how the plan would be mapped.
+    pub async fn import_mspdi(
+        xml: &str,
+        dry_run: bool,
+        audit: &AuditContext,
+        pool: &PgPool,
+    ) -> Result<ImportReport, ServiceError> {
+        let mut report = Self::parse_mspdi(xml)?;
+        report.dry_run = dry_run;
+        Self::match_resources(&mut report, pool).await?;
+        if dry_run {
+            return Ok(report);
+        }
+
+        let mut tx = pool.begin().await?;
+        let project = ProjectService::create_in(
+            ProjectCreate {
+                name: report.project.name.clone(),
+                description: report.project.description.clone(),
+                start_date: report.project.start_date,
+                end_date: report.project.end_date,
+                budget: BigDecimal::from(0),
+                client_id: None,
+            },
+            audit,
+            &mut tx,
+        )
+        .await?;
+        report.project_id = Some(project.id);
+
+        let mut resource_ids = HashMap::new();
+        for resource in &mut report.resources {
+            if resource.action == ResourceAction::Create {
+                let created = ResourceService::create_in(
+                    ResourceCreate {
+                        name: resource.name.clone(),
+                        email: resource.email.clone().unwrap_or_default(),
+                        role: resource.role.clone(),
+                        skills: vec![],
+                        availability: resource.availability.clone(),
+                        hourly_rate: resource.hourly_rate.clone(),
+                    },
+                    audit,
+                    &mut tx,
+                )
+                .await?;
+                resource.resource_id = Some(created.id);
+            }
+            if let Some(id) = resource.resource_id {
+                resource_ids.insert(resource.uid, id);
+            }
+        }
+
+        // Parents come before their children in outline order, so one pass
+        // creates the hierarchy. Links can point forward and are added after.
+        let mut task_ids = HashMap::new();
+        for task in &mut report.tasks {
+            let created = TaskService::create_in(
+                TaskCreate {
+                    name: task.name.clone(),
+                    description: task.description.clone(),
+                    project_id: project.id,
+                    assigned_to: task
+                        .resource_uid
+                        .and_then(|uid| resource_ids.get(&uid).copied()),
+                    start_date: task.start_date,
+                    end_date: task.end_date,
+                    dependencies: vec![],
+                    parent_id: task.parent_uid.and_then(|uid| task_ids.get(&uid).copied()),
+                    wbs: task.wbs.clone(),
+                    milestone: task.milestone,
+                },
+                audit,
+                &mut tx,
+            )
+            .await?;
+            task.task_id = Some(created.id);
+            task_ids.insert(task.uid, created.id);
+        }
+
+        for task in &report.tasks {
+            let Some(task_id) = task.task_id else {
+                continue;
+            };
+            if task.predecessors.is_empty() && task.progress == BigDecimal::from(0) {
+                continue;
+            }
+
+            let links: Vec<TaskLink> = task
+                .predecessors
+                .iter()
+                .map(|link| TaskLink {
+                    task_id,
+                    predecessor_id: task_ids[&link.predecessor_uid],
+                    link_type: link.link_type,
+                    lag_minutes: link.lag_minutes,
+                })
+                .collect();
+            let mut changes = TaskUpdate {
+                dependencies: Patch::Value(links.iter().map(|link| link.predecessor_id).collect()),
+                ..Default::default()
+            };
+            if task.progress > BigDecimal::from(0) {
+                changes.progress = Patch::Value(task.progress.clone());
+                changes.status = Patch::Value(if task.progress >= BigDecimal::from(100) {
+                    TaskStatus::Completed
+                } else {
+                    TaskStatus::InProgress
+                });
+            }
+            TaskService::update_in(task_id, changes, None, audit, &mut tx).await?;
+
+            let detailed: Vec<TaskLink> = links
+                .into_iter()
+                .filter(|link| {
+                    link.link_type != DependencyType::FinishToStart || link.lag_minutes != 0
+                })
+                .collect();
+            TaskService::set_links_in(task_id, &detailed, &mut tx).await?;
+        }
+        tx.commit().await?;
+
+        Ok(report)
+    }
+
+    /// Reads the plan without touching the database.
+    pub fn parse_mspdi(xml: &str) -> Result<ImportReport, ServiceError> {
+        let document = Document::parse(xml)
+            .map_err(|e| ServiceError::BadRequest(format!("Invalid XML: {}", e)))?;
+        let root = document.root_element();
+        if root.tag_name().name() != "Project" {
+            return Err(ServiceError::BadRequest(
+                "Not an MS Project XML file: the root element must be <Project>".into(),
+            ));
+        }
+
+        let mut warnings = Vec::new();
+        let resources = Self::parse_resources(root, &mut warnings);
+        let mut tasks = Self::parse_tasks(root, &mut warnings);
+        Self::parse_assignments(root, &mut tasks, &resources, &mut warnings);
+
+        let name = text(root, "Title")
+            .or_else(|| {
+                text(root, "Name").map(|name| {
+                    name.trim_end_matches(".xml")
+                        .trim_end_matches(".mpp")
+                        .to_string()
+                })
+            })
+            .ok_or_else(|| {
+                ServiceError::invalid_field("Title", "required", "the project has no title or name")
+            })?;
+        let start_date = date(root, "StartDate")
+            .or_else(|| tasks.iter().map(|task| task.start_date).min())
+            .ok_or_else(|| {
+                ServiceError::invalid_field(
+                    "StartDate",
+                    "required",
+                    "the project has no start date",
+                )
+            })?;
+        let mut end_date = date(root, "FinishDate")
+            .or_else(|| tasks.iter().map(|task| task.end_date).max())
+            .unwrap_or(start_date);
+        if end_date <= start_date {
+            end_date = start_date + Duration::days(1);
+            warnings.push(warning(
+                "Project",
+                "finish date is not after the start date; set to one day after the start",
+            ));
+        }
+
+        Ok(ImportReport {
+            dry_run: true,
+            project_id: None,
+            project: ImportedProject {
+                name: truncate(name, "Project", &mut warnings),
+                description: text(root, "Subject"),
+                start_date,
+                end_date,
+            },
+            tasks,
+            resources,
+            warnings,
+        })
+    }
+
+    fn parse_resources(root: Node, warnings: &mut Vec<ImportWarning>) -> Vec<ImportedResource> {
+        let mut resources = Vec::new();
+        for node in children(root, "Resources", "Resource") {
+            let Some(uid) = number(node, "UID") else {
+                warnings.push(warning("Resource", "has no UID; skipped"));
+                continue;
+            };
+            let element = format!("Resource {}", uid);
+            // UID 0 is MS Project's placeholder for unassigned work
+            if uid == 0 || flag(node, "IsNull") {
+                continue;
+            }
+            let Some(name) = text(node, "Name") else {
+                warnings.push(warning(&element, "has no name; skipped"));
+                continue;
+            };
+            // 1 = work, 0 = material and 2 = cost resources
+            if text(node, "Type").is_some_and(|kind| kind != "1") {
+                warnings.push(warning(&element, "is not a work resource; skipped"));
+                continue;
+            }
+
+            let mut availability = decimal(node, "MaxUnits")
+                .map(|units| units * BigDecimal::from(100))
+                .unwrap_or_else(|| BigDecimal::from(100));
+            if availability > BigDecimal::from(100) {
+                warnings.push(warning(&element, "max units above 100% capped at 100%"));
+                availability = BigDecimal::from(100);
+            }
+            let mut hourly_rate = decimal(node, "StandardRate").unwrap_or_default();
+            if hourly_rate < BigDecimal::from(0) {
+                warnings.push(warning(&element, "negative standard rate set to 0"));
+                hourly_rate = BigDecimal::from(0);
+            }
+
+            resources.push(ImportedResource {
+                uid,
+                name: truncate(name, &element, warnings),
+                email: text(node, "EmailAddress").map(|email| email.to_lowercase()),
+                role: text(node, "Group").unwrap_or_else(|| "Team Member".to_string()),
+                availability: availability.normalized(),
+                hourly_rate: hourly_rate.normalized(),
+                action: ResourceAction::Create,
+                resource_id: None,
+            });
+        }
+        resources
+    }
+
+    fn parse_tasks(root: Node, warnings: &mut Vec<ImportWarning>) -> Vec<ImportedTask> {
+        let mut tasks = Vec::new();
+        // Open summary tasks by outline level, to find each task's parent
+        let mut outline: Vec<(i64, i64)> = Vec::new();
+        for node in children(root, "Tasks", "Task") {
+            let Some(uid) = number(node, "UID") else {
+                warnings.push(warning("Task", "has no UID; skipped"));
+                continue;
+            };
+            let element = format!("Task {}", uid);
+            let level = number(node, "OutlineLevel").unwrap_or(1);
+            // Level 0 is the project summary task
+            if level == 0 || flag(node, "IsNull") {
+                continue;
+            }
+            let Some(name) = text(node, "Name") else {
+                warnings.push(warning(&element, "has no name; skipped"));
+                continue;
+            };
+            let (Some(start_date), Some(end_date)) = (date(node, "Start"), date(node, "Finish"))
+            else {
+                warnings.push(warning(&element, "has no start or finish date; skipped"));
+                continue;
+            };
+
+            while outline.last().is_some_and(|(open, _)| *open >= level) {
+                outline.pop();
+            }
+            let parent_uid = outline.last().map(|(_, uid)| *uid);
+            outline.push((level, uid));
+
+            let mut progress = number(node, "PercentComplete").unwrap_or(0);
+            if !(0..=100).contains(&progress) {
+                warnings.push(warning(&element, "percent complete outside 0-100 clamped"));
+                progress = progress.clamp(0, 100);
+            }
+
+            let mut predecessors = Vec::new();
+            for link in node
+                .children()
+                .filter(|child| child.tag_name().name() == "PredecessorLink")
+            {
+                let Some(predecessor_uid) = number(link, "PredecessorUID") else {
+                    warnings.push(warning(&element, "predecessor link without a UID ignored"));
+                    continue;
+                };
+                let link_type = text(link, "Type")
+                    .and_then(|code| code.parse().ok())
+                    .and_then(DependencyType::from_mspdi)
+                    .unwrap_or(DependencyType::FinishToStart);
+                let mut lag_minutes = number(link, "LinkLag").unwrap_or(0) / 10;
+                if lag_minutes != 0
+                    && text(link, "LagFormat")
+                        .is_some_and(|format| PERCENT_LAG_FORMATS.contains(&format.as_str()))
+                {
+                    warnings.push(warning(
+                        &element,
+                        &format!(
+                            "percentage lag on the link from task {} is not supported; imported without lag",
+                            predecessor_uid
+                        ),
+                    ));
+                    lag_minutes = 0;
+                }
+                predecessors.push(ImportedLink {
+                    predecessor_uid,
+                    link_type,
+                    lag_minutes: lag_minutes.clamp(i32::MIN as i64, i32::MAX as i64) as i32,
+                });
+            }
+
+            tasks.push(ImportedTask {
+                uid,
+                name: truncate(name, &element, warnings),
+                description: text(node, "Notes"),
+                wbs: text(node, "WBS").or_else(|| text(node, "OutlineNumber")),
+                parent_uid,
+                milestone: flag(node, "Milestone"),
+                start_date,
+                end_date,
+                progress: BigDecimal::from(progress),
+                predecessors,
+                resource_uid: None,
+                task_id: None,
+            });
+        }
+
+        // Links to tasks that were skipped or never existed cannot be kept
+        let known: HashSet<i64> = tasks.iter().map(|task| task.uid).collect();
+        for task in &mut tasks {
+            let uid = task.uid;
+            task.predecessors.retain(|link| {
+                let keep = known.contains(&link.predecessor_uid) && link.predecessor_uid != uid;
+                if !keep {
+                    warnings.push(warning(
+                        &format!("Task {}", uid),
+                        &format!("link from unknown task {} ignored", link.predecessor_uid),
+                    ));
+                }
+                keep
+            });
+        }
+        tasks
+    }
+
+    fn parse_assignments(
+        root: Node,
+        tasks: &mut [ImportedTask],
+        resources: &[ImportedResource],
+        warnings: &mut Vec<ImportWarning>,
+    ) {
+        let resource_uids: HashSet<i64> = resources.iter().map(|resource| resource.uid).collect();
+        for node in children(root, "Assignments", "Assignment") {
+            let element = number(node, "UID")
+                .map(|uid| format!("Assignment {}", uid))
+                .unwrap_or_else(|| "Assignment".to_string());
+            let (Some(task_uid), Some(resource_uid)) =
+                (number(node, "TaskUID"), number(node, "ResourceUID"))
+            else {
+                warnings.push(warning(&element, "has no task or resource UID; skipped"));
+                continue;
+            };
+            if resource_uid == UNASSIGNED_RESOURCE_UID || resource_uid == 0 {
+                continue;
+            }
+            let Some(task) = tasks.iter_mut().find(|task| task.uid == task_uid) else {
+                continue;
+            };
+            if !resource_uids.contains(&resource_uid) {
+                warnings.push(warning(
+                    &element,
+                    &format!("resource {} was not imported; skipped", resource_uid),
+                ));
+                continue;
+            }
+            // A task has a single assignee here
+            if task.resource_uid.is_some() {
+                warnings.push(warning(
+                    &element,
+                    &format!(
+                        "task {} already has an assignee; resource {} not assigned",
+                        task_uid, resource_uid
+                    ),
+                ));
+                continue;
+            }
+            task.resource_uid = Some(resource_uid);
+        }
+    }
+
+    /// Matches the plan's resources to existing ones by email, or by name for
+    /// resources without an email. Unmatched resources without an email
+    /// cannot be created and are skipped.
+    async fn match_resources(report: &mut ImportReport, pool: &PgPool) -> Result<(), ServiceError> {
+        // Only the candidates for this plan's resources are loaded
+        let emails: Vec<String> = report
+            .resources
+            .iter()
+            .filter_map(|resource| resource.email.as_ref().map(|email| email.to_lowercase()))
+            .collect();
+        let names: Vec<String> = report
+            .resources
+            .iter()
+            .filter(|resource| resource.email.is_none())
+            .map(|resource| resource.name.to_lowercase())
+            .collect();
+        let existing = sqlx::query_as!(
+            Resource,
+            r#"
+            SELECT id, name, email, role, skills, availability, hourly_rate, created_at, updated_at
+            FROM resources
+            WHERE lower(email) = ANY($1) OR lower(name) = ANY($2)
+            "#,
+            &emails,
+            &names
+        )
+        .fetch_all(pool)
+        .await?;
+
+        // Resources the plan lists twice are assigned through the first entry
+        let mut replaced = HashMap::new();
+        let mut emails: HashMap<String, i64> = HashMap::new();
+        for resource in &mut report.resources {
+            let Some(email) = &resource.email else {
+                continue;
+            };
+            if let Some(first) = emails.get(email) {
+                resource.action = ResourceAction::Skip;
+                replaced.insert(resource.uid, Some(*first));
+                report.warnings.push(warning(
+                    &format!("Resource {}", resource.uid),
+                    &format!("has the same email as resource {}; merged into it", first),
+                ));
+            } else {
+                emails.insert(email.clone(), resource.uid);
+            }
+        }
+
+        for resource in &mut report.resources {
+            if resource.action == ResourceAction::Skip {
+                continue;
+            }
+            let found = match &resource.email {
+                Some(email) => existing
+                    .iter()
+                    .find(|candidate| candidate.email.eq_ignore_ascii_case(email)),
+                None => existing
+                    .iter()
+                    .find(|candidate| candidate.name.eq_ignore_ascii_case(&resource.name)),
+            };
+            if let Some(found) = found {
+                resource.action = ResourceAction::Match;
+                resource.resource_id = Some(found.id);
+            } else if resource.email.is_none() {
+                resource.action = ResourceAction::Skip;
+                replaced.insert(resource.uid, None);
+                report.warnings.push(warning(
+                    &format!("Resource {}", resource.uid),
+                    "has no email address and matches no existing resource; skipped",
+                ));
+            }
+        }
+
+        for task in &mut report.tasks {
+            if let Some(replacement) = task.resource_uid.and_then(|uid| replaced.get(&uid)) {
+                task.resource_uid = *replacement;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn warning(element: &str, message: &str) -> ImportWarning {
+    ImportWarning {
+        element: element.to_string(),
+        message: message.to_string(),
+    }
+}
+
+fn truncate(name: String, element: &str, warnings: &mut Vec<ImportWarning>) -> String {
+    if name.chars().count() <= MAX_NAME_LENGTH {
+        return name;
+    }
+    warnings.push(warning(
+        element,
+        "name longer than 255 characters truncated",
+    ));
+    name.chars().take(MAX_NAME_LENGTH).collect()
+}
+
+/// `<Item>` children of the root's `<Collection>` element.
+fn children<'a, 'input>(
+    root: Node<'a, 'input>,
+    collection: &'a str,
+    item: &'a str,
+) -> impl Iterator<Item = Node<'a, 'input>> + 'a {
+    root.children()
+        .filter(move |node| node.tag_name().name() == collection)
+        .flat_map(|node| node.children())
+        .filter(move |node| node.tag_name().name() == item)
+}
+
+/// Trimmed text of a direct child element, if present and not blank.
+fn text(node: Node, name: &str) -> Option<String> {
+    node.children()
+        .find(|child| child.tag_name().name() == name)
+        .and_then(|child| child.text())
+        .map(|text| text.trim().to_string())
+        .filter(|text| !text.is_empty())
+}
+
+fn number(node: Node, name: &str) -> Option<i64> {
+    text(node, name).and_then(|text| {
+        text.parse()
+            .ok()
+            .or_else(|| text.parse::<f64>().ok().map(|value| value.round() as i64))
+    })
+}
+
+fn decimal(node: Node, name: &str) -> Option<BigDecimal> {
+    text(node, name).and_then(|text| BigDecimal::from_str(&text).ok())
+}
+
+fn flag(node: Node, name: &str) -> bool {
+    matches!(text(node, name).as_deref(), Some("1") | Some("true"))
+}
+
+/// MSPDI dates carry no offset; they are read as UTC.
+fn date(node: Node, name: &str) -> Option<DateTime<Utc>> {
+    text(node, name).and_then(|text| {
+        DateTime::parse_from_rfc3339(&text)
+            .map(|date| date.with_timezone(&Utc))
+            .ok()
+            .or_else(|| {
+                NaiveDateTime::parse_from_str(&text, "%Y-%m-%dT%H:%M:%S")
+                    .ok()
+                    .map(|date| date.and_utc())
+            })
+    })
+}
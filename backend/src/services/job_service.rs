@@ -0,0 +1,655 @@
+use chrono::{DateTime, Duration, Utc};
+use serde_json::json;
+use sqlx::{PgConnection, PgPool, Postgres, QueryBuilder};
+use std::env;
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::errors::ServiceError;
+use crate::models::cron::CronSchedule;
+use crate::models::job::{
+    Job, JobFilter, JobKind, JobSchedule, JobScheduleUpdate, JobStatus, JOB_SORT_FIELDS,
+};
+use crate::models::pagination::{Page, PageParams};
+use crate::models::template::DocumentGenerate;
+use crate::services::document_service::DocumentService;
+use crate::services::email_service::EmailService;
+use crate::services::notification_service::NotificationService;
+use crate::services::project_service::ProjectService;
+use crate::services::storage_service::DocumentStorage;
+use crate::services::template_service::TemplateService;
+use crate::services::trash_service::{PurgePolicy, TrashService};
+use crate::services::user_service::UserService;
+use crate::services::webhook_service::WebhookService;
+
+const DEFAULT_POLL_SECONDS: u64 = 30;
+/// A job running longer than this is assumed to belong to a runner that
+/// died, and is handed out again.
+const LEASE_MINUTES: i64 = 30;
+/// Delay before the first retry; doubles with each further attempt.
+const RETRY_BASE_SECONDS: i64 = 30;
+const RETRY_MAX_SECONDS: i64 = 60 * 60;
+
+/// Schedules created on first start. Admins can change or disable them
+/// afterwards; restarts leave existing rows alone.
+const DEFAULT_SCHEDULES: &[(&str, JobKind, &str)] = &[
+    ("overdue-tasks", JobKind::OverdueTasks, "0 1 * * *"),
+    ("schedule-metrics", JobKind::ScheduleMetrics, "30 1 * * *"),
+    ("trash-purge", JobKind::TrashPurge, "0 3 * * *"),
+    ("daily-digest", JobKind::DailyDigest, "0 7 * * *"),
+    (
+        "notification-emails",
+        JobKind::NotificationEmails,
+        "*/5 * * * *",
+    ),
+    (
+        "webhook-deliveries",
+        JobKind::WebhookDeliveries,
+        "* * * * *",
+    ),
+    ("document-cleanup", JobKind::DocumentCleanup, "*/15 * * * *"),
+];
+
+/// What jobs need from the rest of the application.
+#[derive(Clone)]
+pub struct JobContext {
+    pub pool: PgPool,
+    pub email: Arc<EmailService>,
+    /// `None` when purging is disabled
+    pub purge: Option<PurgePolicy>,
+    /// Client for webhook deliveries
+    pub http: reqwest::Client,
+    pub storage: Arc<dyn DocumentStorage>,
+}
+
+pub struct JobService;
+
+impl JobService {
+    /// How often the runner looks for due work, from
+    /// `JOB_POLL_INTERVAL_SECONDS` (default 30).
+    pub fn poll_interval_from_env() -> Result<std::time::Duration, String> {
+        let seconds = match env::var("JOB_POLL_INTERVAL_SECONDS") {
+            Ok(seconds) => seconds
+                .parse::<u64>()
+                .ok()
+                .filter(|seconds| *seconds > 0)
+                .ok_or_else(|| format!("invalid JOB_POLL_INTERVAL_SECONDS '{}'", seconds))?,
+            Err(_) => DEFAULT_POLL_SECONDS,
+        };
+        Ok(std::time::Duration::from_secs(seconds))
+    }
+
+    /// Creates the default schedules that do not exist yet.
+    pub async fn install_schedules(now: DateTime<Utc>, pool: &PgPool) -> Result<(), ServiceError> {
+        for (name, kind, cron) in DEFAULT_SCHEDULES {
+            let next_run_at = Self::parse_cron(cron)?
+                .next_after(now)
+                .ok_or(ServiceError::InternalServerError)?;
+            sqlx::query!(
+                r#"
+                INSERT INTO job_schedules (name, kind, cron, next_run_at)
+                VALUES ($1, $2, $3, $4)
+                ON CONFLICT (name) DO NOTHING
+                "#,
+                name,
+                *kind as JobKind,
+                cron,
+                next_run_at
+            )
+            .execute(pool)
+            .await?;
+        }
+
+        Ok(())
+    }
+
+    /// Queues a one-off job outside any schedule.
+    #[cfg(test)]
+    pub async fn enqueue(
+        kind: JobKind,
+        payload: serde_json::Value,
+        run_at: DateTime<Utc>,
+        pool: &PgPool,
+    ) -> Result<Job, ServiceError> {
+        let mut conn = pool.acquire().await?;
+        Self::enqueue_in(kind, payload, None, run_at, &mut conn).await
+    }
+
+    pub(crate) async fn enqueue_in(
+        kind: JobKind,
+        payload: serde_json::Value,
+        schedule: Option<&str>,
+        run_at: DateTime<Utc>,
+        conn: &mut PgConnection,
+    ) -> Result<Job, ServiceError> {
+        let job = sqlx::query_as!(
+            Job,
+            r#"
+            INSERT INTO jobs (kind, payload, schedule, run_at)
+            VALUES ($1, $2, $3, $4)
+            RETURNING id, kind as "kind: JobKind", payload, status as "status: JobStatus",
+                      schedule, attempts, max_attempts, run_at, last_error, locked_by,
+                      started_at, finished_at, created_at, updated_at
+            "#,
+            kind as JobKind,
+            payload,
+            schedule,
+            run_at
+        )
+        .fetch_one(conn)
+        .await?;
+
+        Ok(job)
+    }
+
+    /// Enqueues a job for every enabled schedule that is due and moves the
+    /// schedule on to its next slot. Slots missed while no runner was up
+    /// collapse into one run, and a schedule whose previous job has not
+    /// finished skips the slot. Returns the number of jobs enqueued.
+    pub async fn enqueue_due(now: DateTime<Utc>, pool: &PgPool) -> Result<usize, ServiceError> {
+        let mut tx = pool.begin().await?;
+        let due = sqlx::query!(
+            r#"
+            SELECT name, kind as "kind: JobKind", cron
+            FROM job_schedules
+            WHERE enabled AND next_run_at <= $1
+            FOR UPDATE SKIP LOCKED
+            "#,
+            now
+        )
+        .fetch_all(&mut *tx)
+        .await?;
+
+        let mut enqueued = 0;
+        for schedule in due {
+            let Some(next_run_at) = schedule
+                .cron
+                .parse::<CronSchedule>()
+                .ok()
+                .and_then(|cron| cron.next_after(now))
+            else {
+                log::error!(
+                    "Disabling schedule '{}': cron '{}' never fires",
+                    schedule.name,
+                    schedule.cron
+                );
+                sqlx::query!(
+                    "UPDATE job_schedules SET enabled = FALSE, updated_at = $2 WHERE name = $1",
+                    schedule.name,
+                    now
+                )
+                .execute(&mut *tx)
+                .await?;
+                continue;
+            };
+
+            let busy = sqlx::query_scalar!(
+                r#"
+                SELECT EXISTS (
+                    SELECT 1 FROM jobs
+                    WHERE schedule = $1 AND status IN ('pending', 'running')
+                ) as "exists!"
+                "#,
+                schedule.name
+            )
+            .fetch_one(&mut *tx)
+            .await?;
+            if !busy {
+                Self::enqueue_in(schedule.kind, json!({}), Some(&schedule.name), now, &mut tx)
+                    .await?;
+                enqueued += 1;
+            }
+
+            sqlx::query!(
+                r#"
+                UPDATE job_schedules
+                SET next_run_at = $2, last_enqueued_at = COALESCE($3, last_enqueued_at)
+                WHERE name = $1
+                "#,
+                schedule.name,
+                next_run_at,
+                (!busy).then_some(now)
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await?;
+
+        Ok(enqueued)
+    }
+
+    /// Hands jobs whose runner has not reported back within the lease to
+    /// the next runner, or fails them if they are out of attempts.
+    pub async fn reclaim_stale(now: DateTime<Utc>, pool: &PgPool) -> Result<u64, ServiceError> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE jobs
+            SET status = CASE WHEN attempts >= max_attempts
+                             THEN 'failed'::job_status ELSE 'pending'::job_status END,
+                finished_at = CASE WHEN attempts >= max_attempts THEN $1::TIMESTAMPTZ END,
+                run_at = $1,
+                last_error = 'abandoned by runner ' || COALESCE(locked_by, 'unknown'),
+                updated_at = $1
+            WHERE status = 'running' AND started_at < $2
+            "#,
+            now,
+            now - Duration::minutes(LEASE_MINUTES)
+        )
+        .execute(pool)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    /// Claims the oldest due job for `worker`. Concurrent runners skip rows
+    /// another one has locked, so no job is claimed twice.
+    pub async fn claim(
+        worker: &str,
+        now: DateTime<Utc>,
+        pool: &PgPool,
+    ) -> Result<Option<Job>, ServiceError> {
+        let job = sqlx::query_as!(
+            Job,
+            r#"
+            UPDATE jobs
+            SET status = 'running', attempts = attempts + 1, locked_by = $2,
+                started_at = $1, updated_at = $1
+            WHERE id = (
+                SELECT id FROM jobs
+                WHERE status = 'pending' AND run_at <= $1
+                ORDER BY run_at, created_at
+                LIMIT 1
+                FOR UPDATE SKIP LOCKED
+            )
+            RETURNING id, kind as "kind: JobKind", payload, status as "status: JobStatus",
+                      schedule, attempts, max_attempts, run_at, last_error, locked_by,
+                      started_at, finished_at, created_at, updated_at
+            "#,
+            now,
+            worker
+        )
+        .fetch_optional(pool)
+        .await?;
+
+        Ok(job)
+    }
+
+    /// Records the outcome of a claimed job. A failure is retried after an
+    /// exponential backoff until the job runs out of attempts. Returns
+    /// `None`, recording nothing, when the runner lost its lease and the job
+    /// was reclaimed in the meantime.
+    pub async fn finish(
+        job: &Job,
+        outcome: Result<(), ServiceError>,
+        now: DateTime<Utc>,
+        pool: &PgPool,
+    ) -> Result<Option<Job>, ServiceError> {
+        let (status, run_at, error, finished_at) = match outcome {
+            Ok(()) => (JobStatus::Succeeded, job.run_at, None, Some(now)),
+            Err(e) if job.attempts >= job.max_attempts => (
+                JobStatus::Failed,
+                job.run_at,
+                Some(e.to_string()),
+                Some(now),
+            ),
+            Err(e) => (
+                JobStatus::Pending,
+                now + Self::backoff(job.attempts),
+                Some(e.to_string()),
+                None,
+            ),
+        };
+
+        let finished = sqlx::query_as!(
+            Job,
+            r#"
+            UPDATE jobs
+            SET status = $2, run_at = $3, last_error = $4, finished_at = $5, updated_at = $6
+            WHERE id = $1 AND locked_by = $7 AND status = 'running'
+            RETURNING id, kind as "kind: JobKind", payload, status as "status: JobStatus",
+                      schedule, attempts, max_attempts, run_at, last_error, locked_by,
+                      started_at, finished_at, created_at, updated_at
+            "#,
+            job.id,
+            status as JobStatus,
+            run_at,
+            error,
+            finished_at,
+            now,
+            job.locked_by
+        )
+        .fetch_optional(pool)
+        .await?;
+
+        if finished.is_none() {
+            log::warn!(
+                "Job {} ({:?}) was reclaimed from {} before it finished; outcome not recorded",
+                job.id,
+                job.kind,
+                job.locked_by.as_deref().unwrap_or("unknown")
+            );
+        }
+        Ok(finished)
+    }
+
+    /// Delay before retrying a job that has failed `attempts` times.
+    pub fn backoff(attempts: i32) -> Duration {
+        let exponent = attempts.clamp(1, 16) as u32 - 1;
+        Duration::seconds((RETRY_BASE_SECONDS << exponent).min(RETRY_MAX_SECONDS))
+    }
+
+    /// Claims and runs due jobs until none are left. Returns the number run.
+    pub async fn run_pending(ctx: &JobContext, worker: &str) -> Result<usize, ServiceError> {
+        let mut ran = 0;
+        while let Some(job) = Self::claim(worker, Utc::now(), &ctx.pool).await? {
+            let outcome = Self::execute(&job, ctx).await;
+            if let Err(e) = &outcome {
+                log::warn!("Job {} ({:?}) failed: {}", job.id, job.kind, e);
+            }
+            Self::finish(&job, outcome, Utc::now(), &ctx.pool).await?;
+            ran += 1;
+        }
+
+        Ok(ran)
+    }
+
+    async fn execute(job: &Job, ctx: &JobContext) -> Result<(), ServiceError> {
+        let pool = &ctx.pool;
+        match job.kind {
+            JobKind::OverdueTasks => {
+                let raised = NotificationService::raise_overdue(Utc::now(), pool).await?;
+                log::info!("Raised {} overdue task notifications", raised);
+            }
+            JobKind::NotificationEmails => {
+                NotificationService::deliver_emails(&ctx.email, pool).await?;
+            }
+            JobKind::DailyDigest => {
+                let sent = NotificationService::send_digests(&ctx.email, pool).await?;
+                log::info!("Sent {} notification digests", sent);
+            }
+            JobKind::TrashPurge => {
+                // The payload may override the configured retention
+                let retention = match job.payload.get("retention_days") {
+                    Some(days) => Some(Duration::days(
+                        days.as_i64().filter(|d| *d > 0).ok_or_else(|| {
+                            ServiceError::BadRequest(format!("Invalid retention_days {}", days))
+                        })?,
+                    )),
+                    None => ctx.purge.as_ref().map(|policy| policy.retention),
+                };
+                let Some(retention) = retention else {
+                    log::info!("Trash purging disabled; skipping");
+                    return Ok(());
+                };
+                let summary = TrashService::purge(Utc::now() - retention, pool).await?;
+                log::info!(
+                    "Purged {} projects and {} tasks from the trash",
+                    summary.projects,
+                    summary.tasks
+                );
+            }
+            JobKind::WebhookDeliveries => {
+                WebhookService::deliver_due(&ctx.http, Utc::now(), pool).await?;
+            }
+            JobKind::ScheduleMetrics => {
+                let updated = ProjectService::recompute_schedule_metrics(Utc::now(), pool).await?;
+                log::info!("Recomputed schedule metrics for {} projects", updated);
+            }
+            JobKind::DocumentCleanup => {
+                let removed =
+                    DocumentService::remove_deleted_content(ctx.storage.as_ref(), pool).await?;
+                if removed > 0 {
+                    log::info!("Removed content of {} deleted document versions", removed);
+                }
+            }
+            JobKind::DocumentGeneration => {
+                let request: DocumentGenerate = serde_json::from_value(job.payload.clone())
+                    .map_err(|e| ServiceError::BadRequest(format!("Invalid payload: {}", e)))?;
+                let requested_by = job
+                    .payload
+                    .get("requested_by")
+                    .and_then(|id| id.as_str())
+                    .and_then(|id| Uuid::parse_str(id).ok())
+                    .ok_or_else(|| ServiceError::BadRequest("Invalid requested_by".into()))?;
+                match TemplateService::generate_for(
+                    request,
+                    requested_by,
+                    ctx.storage.as_ref(),
+                    pool,
+                )
+                .await
+                {
+                    Ok(document) => log::info!(
+                        "Generated {} version {}",
+                        document.title,
+                        document.current_version
+                    ),
+                    // Archived before the job got to it; nothing can be added now
+                    Err(ServiceError::ProjectArchived) => {
+                        log::warn!("Project archived; document not generated")
+                    }
+                    Err(e) => return Err(e),
+                }
+            }
+            JobKind::InvitationEmail => {
+                let id = job
+                    .payload
+                    .get("invitation_id")
+                    .and_then(|id| id.as_str())
+                    .and_then(|id| Uuid::parse_str(id).ok())
+                    .ok_or_else(|| ServiceError::BadRequest("Invalid invitation_id".into()))?;
+                if !UserService::send_invitation(id, &ctx.email, pool).await? {
+                    log::info!("Invitation {} is no longer pending; not sent", id);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Polls for due schedules and jobs on `interval` for the life of the
+    /// process.
+    pub fn spawn_runner(
+        ctx: JobContext,
+        interval: std::time::Duration,
+    ) -> tokio::task::JoinHandle<()> {
+        let worker = format!(
+            "{}-{}",
+            env::var("HOSTNAME").unwrap_or_else(|_| "runner".to_string()),
+            std::process::id()
+        );
+        tokio::spawn(async move {
+            if let Err(e) = Self::install_schedules(Utc::now(), &ctx.pool).await {
+                log::error!("Failed to install job schedules: {}", e);
+            }
+            let mut ticks = tokio::time::interval(interval);
+            loop {
+                ticks.tick().await;
+                let now = Utc::now();
+                match Self::reclaim_stale(now, &ctx.pool).await {
+                    Ok(0) => {}
+                    Ok(reclaimed) => log::warn!("Reclaimed {} abandoned jobs", reclaimed),
+                    Err(e) => log::error!("Failed to reclaim abandoned jobs: {}", e),
+                }
+                if let Err(e) = Self::enqueue_due(now, &ctx.pool).await {
+                    log::error!("Failed to enqueue scheduled jobs: {}", e);
+                }
+                if let Err(e) = Self::run_pending(&ctx, &worker).await {
+                    log::error!("Job runner failed: {}", e);
+                }
+            }
+        })
+    }
+
+    pub async fn list(
+        params: &PageParams,
+        filter: &JobFilter,
+        pool: &PgPool,
+    ) -> Result<Page<Job>, ServiceError> {
+        let order_by = params.order_by(JOB_SORT_FIELDS, "-created_at")?;
+
+        let mut count = QueryBuilder::new("SELECT COUNT(*) FROM jobs WHERE TRUE");
+        Self::push_filters(&mut count, filter);
+        let total: i64 = count.build_query_scalar().fetch_one(pool).await?;
+
+        let mut query = QueryBuilder::new(
+            r#"
+            SELECT id, kind, payload, status, schedule, attempts, max_attempts, run_at,
+                   last_error, locked_by, started_at, finished_at, created_at, updated_at
+            FROM jobs
+            WHERE TRUE"#,
+        );
+        Self::push_filters(&mut query, filter);
+        query
+            .push(order_by)
+            .push(" LIMIT ")
+            .push_bind(params.per_page())
+            .push(" OFFSET ")
+            .push_bind(params.offset()?);
+
+        let jobs = query.build_query_as::<Job>().fetch_all(pool).await?;
+
+        Ok(Page::new(jobs, total, params))
+    }
+
+    fn push_filters(query: &mut QueryBuilder<'_, Postgres>, filter: &JobFilter) {
+        if let Some(kind) = filter.kind {
+            query.push(" AND kind = ").push_bind(kind);
+        }
+        if let Some(status) = filter.status {
+            query.push(" AND status = ").push_bind(status);
+        }
+    }
+
+    pub async fn get(id: Uuid, pool: &PgPool) -> Result<Job, ServiceError> {
+        sqlx::query_as!(
+            Job,
+            r#"
+            SELECT id, kind as "kind: JobKind", payload, status as "status: JobStatus",
+                   schedule, attempts, max_attempts, run_at, last_error, locked_by,
+                   started_at, finished_at, created_at, updated_at
+            FROM jobs
+            WHERE id = $1
+            "#,
+            id
+        )
+        .fetch_optional(pool)
+        .await?
+        .ok_or(ServiceError::NotFound("Job not found".into()))
+    }
+
+    /// Puts a failed job back in the queue with a fresh set of attempts.
+    pub async fn retry(id: Uuid, now: DateTime<Utc>, pool: &PgPool) -> Result<Job, ServiceError> {
+        let job = sqlx::query_as!(
+            Job,
+            r#"
+            UPDATE jobs
+            SET status = 'pending', attempts = 0, run_at = $2, finished_at = NULL,
+                updated_at = $2
+            WHERE id = $1 AND status = 'failed'
+            RETURNING id, kind as "kind: JobKind", payload, status as "status: JobStatus",
+                      schedule, attempts, max_attempts, run_at, last_error, locked_by,
+                      started_at, finished_at, created_at, updated_at
+            "#,
+            id,
+            now
+        )
+        .fetch_optional(pool)
+        .await?;
+
+        match job {
+            Some(job) => Ok(job),
+            None => {
+                let job = Self::get(id, pool).await?;
+                Err(ServiceError::Conflict(format!(
+                    "Only failed jobs can be retried; this one is {:?}",
+                    job.status
+                )))
+            }
+        }
+    }
+
+    pub async fn list_schedules(pool: &PgPool) -> Result<Vec<JobSchedule>, ServiceError> {
+        let schedules = sqlx::query_as!(
+            JobSchedule,
+            r#"
+            SELECT name, kind as "kind: JobKind", cron, enabled, next_run_at,
+                   last_enqueued_at, updated_at
+            FROM job_schedules
+            ORDER BY name
+            "#
+        )
+        .fetch_all(pool)
+        .await?;
+
+        Ok(schedules)
+    }
+
+    /// Changes a schedule's cron expression or turns it on or off. The next
+    /// run is recomputed from `now`.
+    pub async fn update_schedule(
+        name: &str,
+        update: JobScheduleUpdate,
+        now: DateTime<Utc>,
+        pool: &PgPool,
+    ) -> Result<JobSchedule, ServiceError> {
+        let mut tx = pool.begin().await?;
+        let current = sqlx::query!(
+            "SELECT cron, enabled FROM job_schedules WHERE name = $1 FOR UPDATE",
+            name
+        )
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or(ServiceError::NotFound("Job schedule not found".into()))?;
+
+        let cron = update.cron.apply_required("cron", current.cron)?;
+        let enabled = update.enabled.apply_required("enabled", current.enabled)?;
+        let next_run_at = Self::parse_cron(&cron)?
+            .next_after(now)
+            .ok_or_else(|| ServiceError::invalid_field("cron", "cron", "schedule never fires"))?;
+
+        let schedule = sqlx::query_as!(
+            JobSchedule,
+            r#"
+            UPDATE job_schedules
+            SET cron = $2, enabled = $3, next_run_at = $4, updated_at = $5
+            WHERE name = $1
+            RETURNING name, kind as "kind: JobKind", cron, enabled, next_run_at,
+                      last_enqueued_at, updated_at
+            "#,
+            name,
+            cron,
+            enabled,
+            next_run_at,
+            now
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+        tx.commit().await?;
+
+        Ok(schedule)
+    }
+
+    /// Enqueues a schedule's job now, outside its cron slots.
+    pub async fn run_schedule_now(
+        name: &str,
+        now: DateTime<Utc>,
+        pool: &PgPool,
+    ) -> Result<Job, ServiceError> {
+        let mut conn = pool.acquire().await?;
+        let kind = sqlx::query_scalar!(
+            r#"SELECT kind as "kind: JobKind" FROM job_schedules WHERE name = $1"#,
+            name
+        )
+        .fetch_optional(&mut *conn)
+        .await?
+        .ok_or(ServiceError::NotFound("Job schedule not found".into()))?;
+
+        Self::enqueue_in(kind, json!({}), Some(name), now, &mut conn).await
+    }
+
+    fn parse_cron(cron: &str) -> Result<CronSchedule, ServiceError> {
+        cron.parse()
+            .map_err(|e: String| ServiceError::invalid_field("cron", "cron", e))
+    }
+}
@@ -1,20 +1,56 @@
 use crate::errors::ServiceError;
+use crate::models::audit::{AuditContext, AuditEntity};
+use crate::models::calendar::{GateReview, GateReviewCreate};
+use crate::models::document::DocumentTarget;
+use crate::models::job::JobKind;
 use crate::models::lifecycle::{LifecyclePhase, PhaseDetails, PhaseTransition};
+use crate::models::realtime::ChangeKind;
+use crate::models::template::{DocumentKind, OutputFormat};
+use crate::models::version::Versioned;
+use crate::models::webhook::WebhookEvent;
+use crate::services::audit_service::AuditService;
+use crate::services::defect_service::DefectService;
+use crate::services::document_service::DocumentService;
+use crate::services::job_service::JobService;
+use crate::services::notification_service::NotificationService;
+use crate::services::project_service::ProjectService;
+use crate::services::realtime_service::RealtimeService;
+use crate::services::testing_service::TestingService;
+use crate::services::webhook_service::WebhookService;
+use chrono::Utc;
 use log::info;
-use sqlx::PgPool;
+use serde_json::json;
+use sqlx::{PgConnection, PgPool};
 use uuid::Uuid;
+use validator::Validate;
 
 pub struct LifecycleService;
 
 impl LifecycleService {
     pub async fn transition_phase(
         transition: PhaseTransition,
-        approver_id: Uuid,
+        expected_version: Option<i32>,
+        audit: &AuditContext,
         pool: &PgPool,
     ) -> Result<PhaseDetails, ServiceError> {
+        let approver_id = audit.actor_id.ok_or(ServiceError::Forbidden)?;
+
         // Start transaction
         let mut tx = pool.begin().await?;
 
+        // A transition changes the project, so it is held to the project's version
+        let project = ProjectService::lock(transition.project_id, &mut tx).await?;
+        project.check_version(expected_version)?;
+        project.ensure_writable()?;
+
+        let previous_phase = sqlx::query_scalar!(
+            r#"SELECT current_phase as "current_phase: LifecyclePhase" FROM projects WHERE id = $1"#,
+            transition.project_id
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+        Self::check_exit_criteria(transition.project_id, transition.phase, &mut tx).await?;
+
         // Create phase transition record
         let phase_details = sqlx::query_as!(
             PhaseDetails,
@@ -40,7 +76,8 @@ impl LifecycleService {
             r#"
             UPDATE projects
             SET current_phase = $1,
-                updated_at = CURRENT_TIMESTAMP
+                updated_at = CURRENT_TIMESTAMP,
+                version = version + 1
             WHERE id = $2
             "#,
             transition.phase as LifecyclePhase,
@@ -49,6 +86,79 @@ impl LifecycleService {
         .execute(&mut *tx)
         .await?;
 
+        AuditService::record_create(
+            &mut tx,
+            audit,
+            AuditEntity::PhaseTransition,
+            phase_details.id,
+            &phase_details,
+        )
+        .await?;
+        if !transition.documents.is_empty() {
+            DocumentService::link_in(
+                &transition.documents,
+                DocumentTarget::PhaseTransition,
+                phase_details.id,
+                transition.project_id,
+                audit,
+                &mut tx,
+            )
+            .await?;
+        }
+        AuditService::record_update(
+            &mut tx,
+            audit,
+            AuditEntity::Project,
+            transition.project_id,
+            &json!({ "current_phase": previous_phase }),
+            &json!({ "current_phase": transition.phase }),
+        )
+        .await?;
+        NotificationService::phase_transitioned_in(
+            transition.project_id,
+            transition.phase,
+            audit,
+            &mut tx,
+        )
+        .await?;
+        WebhookService::emit_in(
+            WebhookEvent::ProjectPhaseChanged,
+            &json!({
+                "project_id": transition.project_id,
+                "previous_phase": previous_phase,
+                "phase": transition.phase,
+                "transition": phase_details,
+            }),
+            None,
+            &mut tx,
+        )
+        .await?;
+        RealtimeService::publish_in(
+            ChangeKind::PhaseTransitioned,
+            transition.project_id,
+            phase_details.id,
+            None,
+            audit,
+            &mut tx,
+        )
+        .await?;
+        // Closing the project files its closure report with the transition
+        if transition.phase == LifecyclePhase::Closed {
+            JobService::enqueue_in(
+                JobKind::DocumentGeneration,
+                json!({
+                    "project_id": transition.project_id,
+                    "kind": DocumentKind::ClosureReport,
+                    "format": OutputFormat::Pdf,
+                    "requested_by": approver_id,
+                }),
+                None,
+                Utc::now(),
+                &mut tx,
+            )
+            .await?;
+        }
+
         // Commit transaction
         tx.commit().await?;
 
@@ -100,4 +210,175 @@ impl LifecycleService {
 
         Ok(history)
     }
+
+    /// Applies the exit criteria of the latest gate review for the phase
+    /// being entered: a minimum pass rate, and no open critical defects.
+    async fn check_exit_criteria(
+        project_id: Uuid,
+        phase: LifecyclePhase,
+        conn: &mut PgConnection,
+    ) -> Result<(), ServiceError> {
+        let Some(review) = sqlx::query!(
+            r#"
+            SELECT min_pass_rate, no_open_critical_defects
+            FROM gate_reviews
+            WHERE project_id = $1 AND phase = $2
+            ORDER BY scheduled_at DESC
+            LIMIT 1
+            "#,
+            project_id,
+            phase as LifecyclePhase
+        )
+        .fetch_optional(&mut *conn)
+        .await?
+        else {
+            return Ok(());
+        };
+
+        if review.no_open_critical_defects {
+            let open = DefectService::open_counts_in(project_id, conn).await?;
+            if open.critical > 0 {
+                return Err(ServiceError::UnprocessableEntity(format!(
+                    "{} critical defect(s) are open; the gate review for {:?} requires none",
+                    open.critical, phase
+                )));
+            }
+        }
+
+        let Some(min_pass_rate) = review.min_pass_rate else {
+            return Ok(());
+        };
+        match TestingService::pass_rate_in(project_id, conn).await? {
+            Some(pass_rate) if pass_rate >= f64::from(min_pass_rate) => Ok(()),
+            Some(pass_rate) => Err(ServiceError::UnprocessableEntity(format!(
+                "The pass rate is {}%; the gate review for {:?} requires {}%",
+                pass_rate, phase, min_pass_rate
+            ))),
+            None => Err(ServiceError::UnprocessableEntity(format!(
+                "No test results yet; the gate review for {:?} requires a {}% pass rate",
+                phase, min_pass_rate
+            ))),
+        }
+    }
+
+    pub async fn schedule_gate_review(
+        project_id: Uuid,
+        review: GateReviewCreate,
+        audit: &AuditContext,
+        pool: &PgPool,
+    ) -> Result<GateReview, ServiceError> {
+        review.validate()?;
+
+        let mut tx = pool.begin().await?;
+        let project = ProjectService::lock(project_id, &mut tx).await?;
+        project.ensure_writable()?;
+
+        let created = sqlx::query_as!(
+            GateReview,
+            r#"
+            INSERT INTO gate_reviews (
+                project_id, phase, scheduled_at, duration_minutes, location, notes,
+                min_pass_rate, no_open_critical_defects, created_by
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+            RETURNING id, project_id, phase as "phase: LifecyclePhase", scheduled_at,
+                      duration_minutes, location, notes, min_pass_rate, no_open_critical_defects,
+                      created_by, created_at, updated_at, version
+            "#,
+            project_id,
+            review.phase as LifecyclePhase,
+            review.scheduled_at,
+            review.duration_minutes,
+            review.location,
+            review.notes,
+            review.min_pass_rate,
+            review.no_open_critical_defects,
+            audit.actor_id
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+
+        AuditService::record_create(
+            &mut tx,
+            audit,
+            AuditEntity::GateReview,
+            created.id,
+            &created,
+        )
+        .await?;
+        NotificationService::gate_review_scheduled_in(&created, audit, &mut tx).await?;
+        tx.commit().await?;
+
+        Ok(created)
+    }
+
+    pub async fn get_gate_reviews(
+        project_id: Uuid,
+        pool: &PgPool,
+    ) -> Result<Vec<GateReview>, ServiceError> {
+        let reviews = sqlx::query_as!(
+            GateReview,
+            r#"
+            SELECT id, project_id, phase as "phase: LifecyclePhase", scheduled_at,
+                   duration_minutes, location, notes, min_pass_rate, no_open_critical_defects,
+                   created_by, created_at, updated_at, version
+            FROM gate_reviews
+            WHERE project_id = $1
+            ORDER BY scheduled_at ASC
+            "#,
+            project_id
+        )
+        .fetch_all(pool)
+        .await?;
+
+        Ok(reviews)
+    }
+
+    pub async fn cancel_gate_review(
+        id: Uuid,
+        expected_version: Option<i32>,
+        audit: &AuditContext,
+        pool: &PgPool,
+    ) -> Result<(), ServiceError> {
+        let mut tx = pool.begin().await?;
+        let review = sqlx::query_as!(
+            GateReview,
+            r#"
+            SELECT id, project_id, phase as "phase: LifecyclePhase", scheduled_at,
+                   duration_minutes, location, notes, min_pass_rate, no_open_critical_defects,
+                   created_by, created_at, updated_at, version
+            FROM gate_reviews
+            WHERE id = $1
+            FOR UPDATE
+            "#,
+            id
+        )
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or(ServiceError::NotFound("Gate review not found".into()))?;
+        review.check_version(expected_version)?;
+        ProjectService::lock(review.project_id, &mut tx)
+            .await?
+            .ensure_writable()?;
+
+        sqlx::query!(
+            "DELETE FROM comments WHERE target_type = 'gate_review' AND target_id = $1",
+            id
+        )
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query!(
+            "DELETE FROM document_links WHERE target_type = 'gate_review' AND target_id = $1",
+            id
+        )
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query!("DELETE FROM gate_reviews WHERE id = $1", id)
+            .execute(&mut *tx)
+            .await?;
+        AuditService::record_delete(&mut tx, audit, AuditEntity::GateReview, id, &review).await?;
+        tx.commit().await?;
+
+        Ok(())
+    }
 }
@@ -1,5 +1,28 @@
+pub mod audit_service;
 pub mod auth_service;
+pub mod calendar_service;
+pub mod comment_service;
+pub mod defect_service;
+pub mod document_service;
+pub mod email_service;
+pub mod export_service;
+pub mod import_service;
+pub mod job_service;
 pub mod lifecycle_service;
+pub mod notification_service;
+pub mod oidc_service;
 pub mod project_service;
+pub mod realtime_service;
+pub mod render_service;
+pub mod requirement_service;
+pub mod resource_service;
+pub mod search_service;
+pub mod storage_service;
 pub mod task_service;
+pub mod template_service;
+pub mod testing_service;
+pub mod token_service;
+pub mod traceability_service;
+pub mod trash_service;
 pub mod user_service;
+pub mod webhook_service;
@@ -0,0 +1,754 @@
+use chrono::{DateTime, Utc};
+use serde_json::json;
+use sqlx::types::BigDecimal;
+use sqlx::{PgConnection, PgPool, Postgres, QueryBuilder};
+use uuid::Uuid;
+
+use crate::errors::ServiceError;
+use crate::models::audit::AuditContext;
+use crate::models::calendar::GateReview;
+use crate::models::comment::{Comment, CommentTarget};
+use crate::models::lifecycle::LifecyclePhase;
+use crate::models::notification::{
+    Notification, NotificationContent, NotificationEvent, NotificationFilter,
+    NotificationPreference, NOTIFICATION_SORT_FIELDS,
+};
+use crate::models::pagination::{Page, PageParams};
+use crate::models::task::Task;
+use crate::models::webhook::WebhookEvent;
+use crate::services::email_service::{EmailService, OutgoingEmail};
+use crate::services::webhook_service::WebhookService;
+
+/// Shares of the budget, in percent, that raise a notification once the
+/// planned cost reaches them.
+const BUDGET_THRESHOLDS: [i64; 2] = [80, 100];
+/// Planned cost counts this many working hours per calendar day of a task.
+const WORKING_HOURS_PER_DAY: i32 = 8;
+/// Emails sent per delivery run; the rest wait for the next one.
+const EMAIL_BATCH_SIZE: i64 = 100;
+
+struct OverdueTask {
+    id: Uuid,
+    name: String,
+    project_id: Uuid,
+    project_name: String,
+    end_date: DateTime<Utc>,
+    recipients: Vec<Uuid>,
+}
+
+struct PendingEmail {
+    id: Uuid,
+    to: String,
+    title: String,
+    body: String,
+    project_id: Option<Uuid>,
+}
+
+pub struct NotificationService;
+
+impl NotificationService {
+    /// Stores `content` for each active recipient according to their
+    /// preferences. Recipients who turned both channels off are skipped.
+    pub(crate) async fn notify_in(
+        content: &NotificationContent,
+        recipients: &[Uuid],
+        conn: &mut PgConnection,
+    ) -> Result<u64, ServiceError> {
+        if recipients.is_empty() {
+            return Ok(0);
+        }
+
+        let result = sqlx::query!(
+            r#"
+            INSERT INTO notifications (
+                user_id, event, title, body, project_id, task_id, dedupe_key, in_app, email
+            )
+            SELECT u.id, $2, $3, $4, $5, $6, $7,
+                   COALESCE(p.in_app, TRUE), COALESCE(p.email, FALSE)
+            FROM users u
+            LEFT JOIN notification_preferences p ON p.user_id = u.id AND p.event = $2
+            WHERE u.id = ANY($1) AND u.deactivated_at IS NULL
+              AND (COALESCE(p.in_app, TRUE) OR COALESCE(p.email, FALSE))
+            ON CONFLICT (user_id, dedupe_key) DO NOTHING
+            "#,
+            recipients,
+            content.event as NotificationEvent,
+            content.title,
+            content.body,
+            content.project_id,
+            content.task_id,
+            content.dedupe_key
+        )
+        .execute(conn)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    /// Tells users newly assigned to `task`, other than whoever assigned
+    /// them, about it.
+    pub(crate) async fn task_assigned_in(
+        task: &Task,
+        previous: &[Uuid],
+        audit: &AuditContext,
+        conn: &mut PgConnection,
+    ) -> Result<(), ServiceError> {
+        let added: Vec<Uuid> = task
+            .assigned_to
+            .iter()
+            .filter(|id| !previous.contains(id))
+            .copied()
+            .collect();
+        let recipients: Vec<Uuid> = Self::users_of(&added, conn)
+            .await?
+            .into_iter()
+            .filter(|id| Some(*id) != audit.actor_id)
+            .collect();
+        if recipients.is_empty() {
+            return Ok(());
+        }
+
+        let project = Self::project_name(task.project_id, conn).await?;
+        let content = NotificationContent {
+            event: NotificationEvent::TaskAssigned,
+            title: format!("Assigned: {}", task.name),
+            body: format!(
+                "You were assigned to \"{}\" in {}, due {}.",
+                task.name,
+                project,
+                format_date(task.end_date)
+            ),
+            project_id: Some(task.project_id),
+            task_id: Some(task.id),
+            dedupe_key: None,
+        };
+        Self::notify_in(&content, &recipients, conn).await?;
+
+        Ok(())
+    }
+
+    /// Warns the assignees of tasks depending on `task` when it now finishes
+    /// later than before.
+    pub(crate) async fn task_rescheduled_in(
+        previous: &Task,
+        task: &Task,
+        audit: &AuditContext,
+        conn: &mut PgConnection,
+    ) -> Result<(), ServiceError> {
+        if task.end_date <= previous.end_date {
+            return Ok(());
+        }
+
+        let dependents = sqlx::query!(
+            r#"
+            SELECT id, name, assigned_to
+            FROM tasks
+            WHERE $1 = ANY(dependencies) AND deleted_at IS NULL
+            "#,
+            task.id
+        )
+        .fetch_all(&mut *conn)
+        .await?;
+
+        for dependent in dependents {
+            let recipients: Vec<Uuid> = Self::users_of(&dependent.assigned_to, conn)
+                .await?
+                .into_iter()
+                .filter(|id| Some(*id) != audit.actor_id)
+                .collect();
+            let content = NotificationContent {
+                event: NotificationEvent::PredecessorSlipped,
+                title: format!("Predecessor slipped: {}", dependent.name),
+                body: format!(
+                    "\"{}\" now finishes {} instead of {}, which may delay \"{}\".",
+                    task.name,
+                    format_date(task.end_date),
+                    format_date(previous.end_date),
+                    dependent.name
+                ),
+                project_id: Some(task.project_id),
+                task_id: Some(dependent.id),
+                dedupe_key: None,
+            };
+            Self::notify_in(&content, &recipients, conn).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Tells a project's owner and everyone assigned in it that it entered
+    /// `phase`.
+    pub(crate) async fn phase_transitioned_in(
+        project_id: Uuid,
+        phase: LifecyclePhase,
+        audit: &AuditContext,
+        conn: &mut PgConnection,
+    ) -> Result<(), ServiceError> {
+        let project = sqlx::query!(
+            r#"
+            SELECT p.name,
+                   array_remove(
+                       ARRAY(
+                           SELECT DISTINCT ru.user_id FROM tasks t
+                           JOIN resource_users ru ON ru.resource_id = ANY(t.assigned_to)
+                           WHERE t.project_id = p.id AND t.deleted_at IS NULL
+                       ) || p.owner_id,
+                       NULL
+                   ) as "recipients!"
+            FROM projects p
+            WHERE p.id = $1
+            "#,
+            project_id
+        )
+        .fetch_one(&mut *conn)
+        .await?;
+
+        let recipients: Vec<Uuid> = project
+            .recipients
+            .into_iter()
+            .filter(|id| Some(*id) != audit.actor_id)
+            .collect();
+        let content = NotificationContent {
+            event: NotificationEvent::PhaseTransitioned,
+            title: format!("{} entered {:?}", project.name, phase),
+            body: format!("{} moved to the {:?} phase.", project.name, phase),
+            project_id: Some(project_id),
+            task_id: None,
+            dedupe_key: None,
+        };
+        Self::notify_in(&content, &recipients, conn).await?;
+
+        Ok(())
+    }
+
+    /// Tells users mentioned in `comment` about it. Authors mentioning
+    /// themselves are not notified.
+    pub(crate) async fn mentioned_in(
+        comment: &Comment,
+        mentioned: &[Uuid],
+        conn: &mut PgConnection,
+    ) -> Result<(), ServiceError> {
+        let recipients: Vec<Uuid> = mentioned
+            .iter()
+            .filter(|id| **id != comment.author_id)
+            .copied()
+            .collect();
+        if recipients.is_empty() {
+            return Ok(());
+        }
+
+        let author = sqlx::query_scalar!(
+            "SELECT full_name FROM users WHERE id = $1",
+            comment.author_id
+        )
+        .fetch_one(&mut *conn)
+        .await?;
+        let project = Self::project_name(comment.project_id, conn).await?;
+        let content = NotificationContent {
+            event: NotificationEvent::Mentioned,
+            title: format!("{} mentioned you", author),
+            body: format!(
+                "{} mentioned you in a comment in {}:\n\n{}",
+                author,
+                project,
+                excerpt(&comment.body)
+            ),
+            project_id: Some(comment.project_id),
+            task_id: (comment.target_type == CommentTarget::Task).then_some(comment.target_id),
+            dedupe_key: None,
+        };
+        Self::notify_in(&content, &recipients, conn).await?;
+
+        Ok(())
+    }
+
+    /// Asks the people who can approve phase transitions, admins and
+    /// project managers, to attend a newly scheduled gate review.
+    pub(crate) async fn gate_review_scheduled_in(
+        review: &GateReview,
+        audit: &AuditContext,
+        conn: &mut PgConnection,
+    ) -> Result<(), ServiceError> {
+        let approvers = sqlx::query_scalar!(
+            r#"
+            SELECT id FROM users
+            WHERE role IN ('admin', 'project_manager') AND deactivated_at IS NULL
+              AND id IS DISTINCT FROM $1
+            "#,
+            audit.actor_id
+        )
+        .fetch_all(&mut *conn)
+        .await?;
+
+        let project = Self::project_name(review.project_id, conn).await?;
+        let content = NotificationContent {
+            event: NotificationEvent::GateApprovalRequested,
+            title: format!("Gate review: {} {:?}", project, review.phase),
+            body: format!(
+                "Approval is requested for {} to enter the {:?} phase. The review is on {}{}.",
+                project,
+                review.phase,
+                format_date(review.scheduled_at),
+                review
+                    .location
+                    .as_ref()
+                    .map(|location| format!(" in {}", location))
+                    .unwrap_or_default()
+            ),
+            project_id: Some(review.project_id),
+            task_id: None,
+            dedupe_key: None,
+        };
+        Self::notify_in(&content, &approvers, conn).await?;
+
+        Ok(())
+    }
+
+    /// Compares the project's planned cost with its budget and tells the
+    /// owner and admins about each threshold reached, once per threshold.
+    ///
+    /// Planned cost is the sum over tasks and their assigned resources of
+    /// working hours in the task window, scaled by availability, times the
+    /// hourly rate.
+    pub(crate) async fn check_budget_in(
+        project_id: Uuid,
+        conn: &mut PgConnection,
+    ) -> Result<(), ServiceError> {
+        let project = sqlx::query!(
+            r#"
+            SELECT p.name, p.budget, p.owner_id,
+                   COALESCE((
+                       SELECT SUM(
+                           EXTRACT(EPOCH FROM (t.end_date - t.start_date)) / 86400 * $2
+                               * r.hourly_rate * r.availability / 100
+                       )
+                       FROM tasks t
+                       CROSS JOIN LATERAL unnest(t.assigned_to) AS a(id)
+                       JOIN resources r ON r.id = a.id
+                       WHERE t.project_id = p.id AND t.deleted_at IS NULL
+                   ), 0) as "planned_cost!"
+            FROM projects p
+            WHERE p.id = $1
+            "#,
+            project_id,
+            BigDecimal::from(WORKING_HOURS_PER_DAY)
+        )
+        .fetch_one(&mut *conn)
+        .await?;
+
+        if project.budget <= BigDecimal::from(0) {
+            return Ok(());
+        }
+        let crossed: Vec<i64> = BUDGET_THRESHOLDS
+            .into_iter()
+            .filter(|threshold| {
+                &project.planned_cost * BigDecimal::from(100)
+                    >= &project.budget * BigDecimal::from(*threshold)
+            })
+            .collect();
+        if crossed.is_empty() {
+            return Ok(());
+        }
+
+        let mut recipients = sqlx::query_scalar!(
+            "SELECT id FROM users WHERE role = 'admin' AND deactivated_at IS NULL"
+        )
+        .fetch_all(&mut *conn)
+        .await?;
+        recipients.extend(project.owner_id);
+
+        for threshold in crossed {
+            let content = NotificationContent {
+                event: NotificationEvent::BudgetThresholdCrossed,
+                title: format!("{} reached {}% of its budget", project.name, threshold),
+                body: format!(
+                    "The planned cost of {} is {} against a budget of {}.",
+                    project.name,
+                    project.planned_cost.round(2),
+                    project.budget
+                ),
+                project_id: Some(project_id),
+                task_id: None,
+                dedupe_key: Some(format!("budget:{}:{}", project_id, threshold)),
+            };
+            Self::notify_in(&content, &recipients, conn).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Notifies assignees and owners of unfinished tasks whose end date is
+    /// before `now`. Each task is reported once per end date, so moving the
+    /// deadline and missing it again notifies again. Returns the number of
+    /// notifications raised.
+    pub async fn raise_overdue(now: DateTime<Utc>, pool: &PgPool) -> Result<u64, ServiceError> {
+        let mut tx = pool.begin().await?;
+        let overdue = sqlx::query_as!(
+            OverdueTask,
+            r#"
+            SELECT t.id, t.name, t.project_id, p.name as project_name, t.end_date,
+                   array_remove(
+                       ARRAY(
+                           SELECT ru.user_id FROM resource_users ru
+                           WHERE ru.resource_id = ANY(t.assigned_to)
+                       ) || p.owner_id,
+                       NULL
+                   ) as "recipients!"
+            FROM tasks t
+            JOIN projects p ON p.id = t.project_id
+            WHERE t.end_date < $1 AND t.status <> 'completed' AND t.deleted_at IS NULL
+              AND p.deleted_at IS NULL AND p.archived_at IS NULL
+            "#,
+            now
+        )
+        .fetch_all(&mut *tx)
+        .await?;
+
+        let mut raised = 0;
+        for task in overdue {
+            let content = NotificationContent {
+                event: NotificationEvent::TaskOverdue,
+                title: format!("Overdue: {}", task.name),
+                body: format!(
+                    "\"{}\" in {} was due {} and is not completed.",
+                    task.name,
+                    task.project_name,
+                    format_date(task.end_date)
+                ),
+                project_id: Some(task.project_id),
+                task_id: Some(task.id),
+                dedupe_key: Some(format!("overdue:{}:{}", task.id, task.end_date.timestamp())),
+            };
+            raised += Self::notify_in(&content, &task.recipients, &mut tx).await?;
+            WebhookService::emit_in(
+                WebhookEvent::TaskOverdue,
+                &json!({
+                    "task_id": task.id,
+                    "name": task.name,
+                    "project_id": task.project_id,
+                    "end_date": task.end_date,
+                }),
+                content.dedupe_key.as_deref(),
+                &mut tx,
+            )
+            .await?;
+        }
+        tx.commit().await?;
+
+        Ok(raised)
+    }
+
+    /// Sends notifications waiting for the email channel. Failed sends stay
+    /// pending for the next run. Returns the number sent.
+    pub async fn deliver_emails(
+        email: &EmailService,
+        pool: &PgPool,
+    ) -> Result<usize, ServiceError> {
+        let mut tx = pool.begin().await?;
+        let pending = sqlx::query_as!(
+            PendingEmail,
+            r#"
+            SELECT n.id, u.email as to, n.title, n.body, n.project_id
+            FROM notifications n
+            JOIN users u ON u.id = n.user_id
+            WHERE n.email AND n.emailed_at IS NULL AND u.deactivated_at IS NULL
+            ORDER BY n.created_at
+            LIMIT $1
+            FOR UPDATE OF n SKIP LOCKED
+            "#,
+            EMAIL_BATCH_SIZE
+        )
+        .fetch_all(&mut *tx)
+        .await?;
+
+        let mut sent = 0;
+        for notification in pending {
+            let mut body = notification.body;
+            if let Some(project_id) = notification.project_id {
+                body.push_str(&format!(
+                    "\n\n{}/projects/{}",
+                    email.app_base_url, project_id
+                ));
+            }
+            let message = OutgoingEmail {
+                to: notification.to,
+                subject: notification.title,
+                body,
+            };
+            match email.send(message).await {
+                Ok(()) => {
+                    sqlx::query!(
+                        "UPDATE notifications SET emailed_at = NOW() WHERE id = $1",
+                        notification.id
+                    )
+                    .execute(&mut *tx)
+                    .await?;
+                    sent += 1;
+                }
+                Err(e) => log::warn!("Failed to email notification {}: {}", notification.id, e),
+            }
+        }
+        tx.commit().await?;
+
+        Ok(sent)
+    }
+
+    /// Emails each user one summary of the unread in-app notifications
+    /// they have not been emailed about, and marks those as digested so the
+    /// next digest only covers newer ones. Returns the number of emails sent.
+    pub async fn send_digests(email: &EmailService, pool: &PgPool) -> Result<usize, ServiceError> {
+        let mut tx = pool.begin().await?;
+        let pending = sqlx::query!(
+            r#"
+            SELECT n.id, n.user_id, u.email, n.title
+            FROM notifications n
+            JOIN users u ON u.id = n.user_id
+            WHERE n.in_app AND n.read_at IS NULL AND n.digested_at IS NULL
+              AND NOT n.email AND u.deactivated_at IS NULL
+            ORDER BY n.user_id, n.created_at
+            FOR UPDATE OF n SKIP LOCKED
+            "#
+        )
+        .fetch_all(&mut *tx)
+        .await?;
+
+        let mut sent = 0;
+        for recipient in pending.chunk_by(|a, b| a.user_id == b.user_id) {
+            let lines: Vec<String> = recipient
+                .iter()
+                .map(|notification| format!("- {}", notification.title))
+                .collect();
+            let message = OutgoingEmail {
+                to: recipient[0].email.clone(),
+                subject: format!("{} unread notifications", recipient.len()),
+                body: format!(
+                    "{}\n\n{}/notifications",
+                    lines.join("\n"),
+                    email.app_base_url
+                ),
+            };
+            match email.send(message).await {
+                Ok(()) => {
+                    let ids: Vec<Uuid> = recipient.iter().map(|n| n.id).collect();
+                    sqlx::query!(
+                        "UPDATE notifications SET digested_at = NOW() WHERE id = ANY($1)",
+                        &ids
+                    )
+                    .execute(&mut *tx)
+                    .await?;
+                    sent += 1;
+                }
+                Err(e) => log::warn!(
+                    "Failed to email digest to user {}: {}",
+                    recipient[0].user_id,
+                    e
+                ),
+            }
+        }
+        tx.commit().await?;
+
+        Ok(sent)
+    }
+
+    /// The user's in-app notifications, newest first by default.
+    pub async fn list(
+        user_id: Uuid,
+        params: &PageParams,
+        filter: &NotificationFilter,
+        pool: &PgPool,
+    ) -> Result<Page<Notification>, ServiceError> {
+        let order_by = params.order_by(NOTIFICATION_SORT_FIELDS, "-created_at")?;
+
+        let mut count = QueryBuilder::new("SELECT COUNT(*) FROM notifications WHERE in_app");
+        Self::push_filters(&mut count, user_id, filter);
+        let total: i64 = count.build_query_scalar().fetch_one(pool).await?;
+
+        let mut query = QueryBuilder::new(
+            r#"
+            SELECT id, event, title, body, project_id, task_id, read_at, created_at
+            FROM notifications
+            WHERE in_app"#,
+        );
+        Self::push_filters(&mut query, user_id, filter);
+        query
+            .push(order_by)
+            .push(" LIMIT ")
+            .push_bind(params.per_page())
+            .push(" OFFSET ")
+            .push_bind(params.offset()?);
+
+        let items = query
+            .build_query_as::<Notification>()
+            .fetch_all(pool)
+            .await?;
+
+        Ok(Page::new(items, total, params))
+    }
+
+    fn push_filters(
+        query: &mut QueryBuilder<'_, Postgres>,
+        user_id: Uuid,
+        filter: &NotificationFilter,
+    ) {
+        query.push(" AND user_id = ").push_bind(user_id);
+        if filter.unread {
+            query.push(" AND read_at IS NULL");
+        }
+    }
+
+    pub async fn unread_count(user_id: Uuid, pool: &PgPool) -> Result<i64, ServiceError> {
+        let count = sqlx::query_scalar!(
+            r#"
+            SELECT COUNT(*) as "count!" FROM notifications
+            WHERE user_id = $1 AND in_app AND read_at IS NULL
+            "#,
+            user_id
+        )
+        .fetch_one(pool)
+        .await?;
+
+        Ok(count)
+    }
+
+    /// Marks one of the user's notifications read. Marking it again keeps
+    /// the original time.
+    pub async fn mark_read(
+        id: Uuid,
+        user_id: Uuid,
+        pool: &PgPool,
+    ) -> Result<Notification, ServiceError> {
+        let notification = sqlx::query_as!(
+            Notification,
+            r#"
+            UPDATE notifications SET read_at = COALESCE(read_at, NOW())
+            WHERE id = $1 AND user_id = $2 AND in_app
+            RETURNING id, event as "event: NotificationEvent", title, body,
+                      project_id, task_id, read_at, created_at
+            "#,
+            id,
+            user_id
+        )
+        .fetch_optional(pool)
+        .await?
+        .ok_or(ServiceError::NotFound("Notification not found".into()))?;
+
+        Ok(notification)
+    }
+
+    /// Marks all of the user's notifications read and returns how many were
+    /// unread.
+    pub async fn mark_all_read(user_id: Uuid, pool: &PgPool) -> Result<u64, ServiceError> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE notifications SET read_at = NOW()
+            WHERE user_id = $1 AND in_app AND read_at IS NULL
+            "#,
+            user_id
+        )
+        .execute(pool)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    /// The user's effective preference for every event.
+    pub async fn get_preferences(
+        user_id: Uuid,
+        pool: &PgPool,
+    ) -> Result<Vec<NotificationPreference>, ServiceError> {
+        let stored = sqlx::query_as!(
+            NotificationPreference,
+            r#"
+            SELECT event as "event: NotificationEvent", in_app, email
+            FROM notification_preferences
+            WHERE user_id = $1
+            "#,
+            user_id
+        )
+        .fetch_all(pool)
+        .await?;
+
+        Ok(NotificationEvent::ALL
+            .into_iter()
+            .map(|event| {
+                stored
+                    .iter()
+                    .find(|preference| preference.event == event)
+                    .cloned()
+                    .unwrap_or_else(|| NotificationPreference::default_for(event))
+            })
+            .collect())
+    }
+
+    /// Stores preferences for the events listed; others keep their current
+    /// setting.
+    pub async fn update_preferences(
+        user_id: Uuid,
+        preferences: Vec<NotificationPreference>,
+        pool: &PgPool,
+    ) -> Result<Vec<NotificationPreference>, ServiceError> {
+        let mut tx = pool.begin().await?;
+        for preference in preferences {
+            sqlx::query!(
+                r#"
+                INSERT INTO notification_preferences (user_id, event, in_app, email)
+                VALUES ($1, $2, $3, $4)
+                ON CONFLICT (user_id, event)
+                DO UPDATE SET in_app = EXCLUDED.in_app, email = EXCLUDED.email
+                "#,
+                user_id,
+                preference.event as NotificationEvent,
+                preference.in_app,
+                preference.email
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await?;
+
+        Self::get_preferences(user_id, pool).await
+    }
+
+    async fn project_name(
+        project_id: Uuid,
+        conn: &mut PgConnection,
+    ) -> Result<String, ServiceError> {
+        let name = sqlx::query_scalar!("SELECT name FROM projects WHERE id = $1", project_id)
+            .fetch_one(conn)
+            .await?;
+
+        Ok(name)
+    }
+
+    /// The users behind the resources a task is assigned to.
+    async fn users_of(
+        resource_ids: &[Uuid],
+        conn: &mut PgConnection,
+    ) -> Result<Vec<Uuid>, ServiceError> {
+        if resource_ids.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let users = sqlx::query_scalar!(
+            r#"SELECT user_id as "user_id!" FROM resource_users WHERE resource_id = ANY($1)"#,
+            resource_ids
+        )
+        .fetch_all(conn)
+        .await?;
+
+        Ok(users)
+    }
+}
+
+fn format_date(date: DateTime<Utc>) -> String {
+    date.format("%Y-%m-%d %H:%M UTC").to_string()
+}
+
+/// The start of a comment body, short enough for a notification.
+fn excerpt(body: &str) -> String {
+    const MAX_CHARS: usize = 280;
+    match body.char_indices().nth(MAX_CHARS) {
+        Some((end, _)) => format!("{}…", &body[..end]),
+        None => body.to_string(),
+    }
+}
@@ -0,0 +1,408 @@
+use crate::errors::ServiceError;
+use crate::models::audit::AuditContext;
+use crate::models::auth::AuthResponse;
+use crate::models::user::UserRole;
+use crate::services::token_service::{random_token, TokenService};
+use crate::services::user_service::UserService;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use jsonwebtoken::jwk::JwkSet;
+use jsonwebtoken::{decode, decode_header, Algorithm, DecodingKey, Validation};
+use reqwest::Url;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use sqlx::PgPool;
+use std::collections::HashMap;
+use std::env;
+use tokio::sync::RwLock;
+
+/// Login requests older than this are rejected at the callback.
+const LOGIN_STATE_TTL_MINUTES: i32 = 10;
+
+/// Signature algorithms accepted on ID tokens. Symmetric algorithms are
+/// rejected so a token can never be verified with a shared client secret.
+const ALLOWED_ALGORITHMS: [Algorithm; 7] = [
+    Algorithm::RS256,
+    Algorithm::RS384,
+    Algorithm::RS512,
+    Algorithm::PS256,
+    Algorithm::ES256,
+    Algorithm::ES384,
+    Algorithm::EdDSA,
+];
+
+#[derive(Debug, Clone)]
+pub struct OidcConfig {
+    pub issuer_url: String,
+    pub client_id: String,
+    pub client_secret: Option<String>,
+    pub redirect_uri: String,
+    pub scopes: String,
+    /// Name of the ID token claim holding the user's groups or roles.
+    pub role_claim: String,
+    /// Claim value -> role, evaluated in order; the first match wins.
+    pub role_mapping: Vec<(String, UserRole)>,
+    pub default_role: UserRole,
+}
+
+impl OidcConfig {
+    /// Reads the `OIDC_*` environment variables. Returns `Ok(None)` when
+    /// single sign-on is not configured (`OIDC_ISSUER_URL` unset).
+    pub fn from_env() -> Result<Option<Self>, String> {
+        let issuer_url = match env::var("OIDC_ISSUER_URL") {
+            Ok(url) if !url.is_empty() => url,
+            _ => return Ok(None),
+        };
+        let required = |name: &str| {
+            env::var(name).map_err(|_| format!("{} must be set when OIDC_ISSUER_URL is set", name))
+        };
+
+        let role_mapping = match env::var("OIDC_ROLE_MAPPING") {
+            Ok(mapping) => parse_role_mapping(&mapping)?,
+            Err(_) => Vec::new(),
+        };
+        let default_role = match env::var("OIDC_DEFAULT_ROLE") {
+            Ok(role) => parse_role(&role)?,
+            Err(_) => UserRole::Developer,
+        };
+
+        Ok(Some(OidcConfig {
+            issuer_url,
+            client_id: required("OIDC_CLIENT_ID")?,
+            client_secret: env::var("OIDC_CLIENT_SECRET")
+                .ok()
+                .filter(|s| !s.is_empty()),
+            redirect_uri: required("OIDC_REDIRECT_URI")?,
+            scopes: env::var("OIDC_SCOPES").unwrap_or_else(|_| "openid email profile".to_string()),
+            role_claim: env::var("OIDC_ROLE_CLAIM").unwrap_or_else(|_| "groups".to_string()),
+            role_mapping,
+            default_role,
+        }))
+    }
+
+    /// Resolves the role for a set of ID token claims. The role claim may be
+    /// a single string or an array of strings.
+    pub fn map_role(&self, claims: &HashMap<String, serde_json::Value>) -> UserRole {
+        let values: Vec<&str> = match claims.get(&self.role_claim) {
+            Some(serde_json::Value::String(value)) => vec![value.as_str()],
+            Some(serde_json::Value::Array(values)) => {
+                values.iter().filter_map(|v| v.as_str()).collect()
+            }
+            _ => Vec::new(),
+        };
+
+        self.role_mapping
+            .iter()
+            .find(|(claim_value, _)| values.contains(&claim_value.as_str()))
+            .map(|(_, role)| role.clone())
+            .unwrap_or_else(|| self.default_role.clone())
+    }
+}
+
+/// Parses `OIDC_ROLE_MAPPING`, e.g. `pm-admins=admin,planners=project_manager`.
+pub fn parse_role_mapping(mapping: &str) -> Result<Vec<(String, UserRole)>, String> {
+    mapping
+        .split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .map(|entry| {
+            let (claim_value, role) = entry
+                .split_once('=')
+                .ok_or_else(|| format!("invalid OIDC_ROLE_MAPPING entry '{}'", entry))?;
+            Ok((claim_value.trim().to_string(), parse_role(role.trim())?))
+        })
+        .collect()
+}
+
+fn parse_role(role: &str) -> Result<UserRole, String> {
+    match role {
+        "admin" => Ok(UserRole::Admin),
+        "project_manager" => Ok(UserRole::ProjectManager),
+        "developer" => Ok(UserRole::Developer),
+        "qa_engineer" => Ok(UserRole::QaEngineer),
+        other => Err(format!("unknown role '{}'", other)),
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct ProviderMetadata {
+    issuer: String,
+    authorization_endpoint: String,
+    token_endpoint: String,
+    jwks_uri: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct TokenResponse {
+    id_token: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct IdTokenClaims {
+    sub: String,
+    email: Option<String>,
+    #[serde(default)]
+    email_verified: bool,
+    name: Option<String>,
+    nonce: Option<String>,
+    #[serde(flatten)]
+    extra: HashMap<String, serde_json::Value>,
+}
+
+/// OpenID Connect authorization-code flow with PKCE. Provider metadata and
+/// signing keys are fetched lazily and cached; the key set is refreshed
+/// when a token references an unknown `kid`.
+pub struct OidcService {
+    config: OidcConfig,
+    http: reqwest::Client,
+    metadata: RwLock<Option<ProviderMetadata>>,
+    jwks: RwLock<Option<JwkSet>>,
+}
+
+impl OidcService {
+    pub fn new(config: OidcConfig) -> Self {
+        OidcService {
+            config,
+            http: reqwest::Client::new(),
+            metadata: RwLock::new(None),
+            jwks: RwLock::new(None),
+        }
+    }
+
+    /// Starts a login: records state, nonce and PKCE verifier, and returns
+    /// the identity provider URL the browser should be redirected to.
+    pub async fn authorization_url(&self, pool: &PgPool) -> Result<String, ServiceError> {
+        let metadata = self.metadata().await?;
+
+        let state = random_token();
+        let nonce = random_token();
+        let code_verifier = random_token();
+        let code_challenge = URL_SAFE_NO_PAD.encode(Sha256::digest(code_verifier.as_bytes()));
+
+        sqlx::query!(
+            "DELETE FROM oidc_login_states WHERE created_at < NOW() - make_interval(mins => $1)",
+            LOGIN_STATE_TTL_MINUTES
+        )
+        .execute(pool)
+        .await?;
+
+        sqlx::query!(
+            "INSERT INTO oidc_login_states (state, code_verifier, nonce) VALUES ($1, $2, $3)",
+            state,
+            code_verifier,
+            nonce
+        )
+        .execute(pool)
+        .await?;
+
+        let url = Url::parse_with_params(
+            &metadata.authorization_endpoint,
+            &[
+                ("response_type", "code"),
+                ("client_id", self.config.client_id.as_str()),
+                ("redirect_uri", self.config.redirect_uri.as_str()),
+                ("scope", self.config.scopes.as_str()),
+                ("state", state.as_str()),
+                ("nonce", nonce.as_str()),
+                ("code_challenge", code_challenge.as_str()),
+                ("code_challenge_method", "S256"),
+            ],
+        )
+        .map_err(|e| ServiceError::IdentityProviderError(e.to_string()))?;
+
+        Ok(url.into())
+    }
+
+    /// Finishes a login: redeems the authorization code, validates the ID
+    /// token and provisions or updates the local user.
+    pub async fn complete_login(
+        &self,
+        code: &str,
+        state: &str,
+        tokens: &TokenService,
+        audit: &AuditContext,
+        pool: &PgPool,
+    ) -> Result<AuthResponse, ServiceError> {
+        let login = sqlx::query!(
+            r#"
+            DELETE FROM oidc_login_states
+            WHERE state = $1 AND created_at >= NOW() - make_interval(mins => $2)
+            RETURNING code_verifier, nonce
+            "#,
+            state,
+            LOGIN_STATE_TTL_MINUTES
+        )
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| ServiceError::Unauthorized("Unknown or expired login state".into()))?;
+
+        let metadata = self.metadata().await?;
+
+        let mut form = vec![
+            ("grant_type", "authorization_code"),
+            ("code", code),
+            ("redirect_uri", self.config.redirect_uri.as_str()),
+            ("client_id", self.config.client_id.as_str()),
+            ("code_verifier", login.code_verifier.as_str()),
+        ];
+        if let Some(secret) = &self.config.client_secret {
+            form.push(("client_secret", secret.as_str()));
+        }
+
+        let response = self
+            .http
+            .post(&metadata.token_endpoint)
+            .form(&form)
+            .send()
+            .await
+            .map_err(|e| ServiceError::IdentityProviderError(e.to_string()))?;
+        if !response.status().is_success() {
+            log::warn!("OIDC token endpoint returned {}", response.status());
+            return Err(ServiceError::Unauthorized(
+                "Authorization code was rejected".into(),
+            ));
+        }
+        let token_response: TokenResponse = response
+            .json()
+            .await
+            .map_err(|e| ServiceError::IdentityProviderError(e.to_string()))?;
+
+        let claims = self
+            .validate_id_token(&token_response.id_token, &metadata)
+            .await?;
+        if claims.nonce.as_deref() != Some(login.nonce.as_str()) {
+            return Err(ServiceError::Unauthorized("ID token nonce mismatch".into()));
+        }
+
+        let email = claims
+            .email
+            .clone()
+            .ok_or_else(|| ServiceError::Unauthorized("ID token has no email claim".into()))?;
+        let full_name = claims.name.clone().unwrap_or_else(|| email.clone());
+        let role = self.config.map_role(&claims.extra);
+
+        let user = UserService::provision_oidc_user(
+            &metadata.issuer,
+            &claims.sub,
+            &email,
+            claims.email_verified,
+            &full_name,
+            role,
+            audit,
+            pool,
+        )
+        .await?;
+
+        if user.deactivated_at.is_some() {
+            return Err(ServiceError::Unauthorized("Account is deactivated".into()));
+        }
+
+        log::info!("User {} signed in through OIDC", user.id);
+        tokens.issue(&user)
+    }
+
+    async fn validate_id_token(
+        &self,
+        id_token: &str,
+        metadata: &ProviderMetadata,
+    ) -> Result<IdTokenClaims, ServiceError> {
+        let header = decode_header(id_token)
+            .map_err(|_| ServiceError::Unauthorized("Malformed ID token".into()))?;
+        if !ALLOWED_ALGORITHMS.contains(&header.alg) {
+            return Err(ServiceError::Unauthorized(
+                "ID token signature algorithm not allowed".into(),
+            ));
+        }
+
+        let key = self.decoding_key(header.kid.as_deref(), metadata).await?;
+
+        let mut validation = Validation::new(header.alg);
+        validation.set_issuer(&[&metadata.issuer]);
+        validation.set_audience(&[&self.config.client_id]);
+        validation.set_required_spec_claims(&["exp", "iss", "aud", "sub"]);
+
+        decode::<IdTokenClaims>(id_token, &key, &validation)
+            .map(|data| data.claims)
+            .map_err(|e| {
+                log::warn!("Rejected OIDC ID token: {}", e);
+                ServiceError::Unauthorized("Invalid ID token".into())
+            })
+    }
+
+    async fn decoding_key(
+        &self,
+        kid: Option<&str>,
+        metadata: &ProviderMetadata,
+    ) -> Result<DecodingKey, ServiceError> {
+        if let Some(key) = self.cached_key(kid).await? {
+            return Ok(key);
+        }
+
+        // Unknown kid: the provider may have rotated its keys.
+        let jwks: JwkSet = self
+            .http
+            .get(&metadata.jwks_uri)
+            .send()
+            .await
+            .and_then(|r| r.error_for_status())
+            .map_err(|e| ServiceError::IdentityProviderError(e.to_string()))?
+            .json()
+            .await
+            .map_err(|e| ServiceError::IdentityProviderError(e.to_string()))?;
+        *self.jwks.write().await = Some(jwks);
+
+        self.cached_key(kid)
+            .await?
+            .ok_or_else(|| ServiceError::Unauthorized("ID token signed with unknown key".into()))
+    }
+
+    async fn cached_key(&self, kid: Option<&str>) -> Result<Option<DecodingKey>, ServiceError> {
+        let jwks = self.jwks.read().await;
+        let Some(jwks) = jwks.as_ref() else {
+            return Ok(None);
+        };
+
+        let jwk = match kid {
+            Some(kid) => jwks.find(kid),
+            None if jwks.keys.len() == 1 => jwks.keys.first(),
+            None => None,
+        };
+
+        jwk.map(|jwk| {
+            DecodingKey::from_jwk(jwk)
+                .map_err(|e| ServiceError::IdentityProviderError(e.to_string()))
+        })
+        .transpose()
+    }
+
+    async fn metadata(&self) -> Result<ProviderMetadata, ServiceError> {
+        if let Some(metadata) = self.metadata.read().await.as_ref() {
+            return Ok(metadata.clone());
+        }
+
+        let discovery_url = format!(
+            "{}/.well-known/openid-configuration",
+            self.config.issuer_url.trim_end_matches('/')
+        );
+        let metadata: ProviderMetadata = self
+            .http
+            .get(&discovery_url)
+            .send()
+            .await
+            .and_then(|r| r.error_for_status())
+            .map_err(|e| ServiceError::IdentityProviderError(e.to_string()))?
+            .json()
+            .await
+            .map_err(|e| ServiceError::IdentityProviderError(e.to_string()))?;
+
+        if metadata.issuer.trim_end_matches('/') != self.config.issuer_url.trim_end_matches('/') {
+            return Err(ServiceError::IdentityProviderError(format!(
+                "discovery document issuer '{}' does not match configured issuer",
+                metadata.issuer
+            )));
+        }
+
+        *self.metadata.write().await = Some(metadata.clone());
+        Ok(metadata)
+    }
+}
@@ -1,70 +1,249 @@
-use chrono::Utc;
-use sqlx::PgPool;
+use chrono::{DateTime, Utc};
+use sqlx::{PgConnection, PgPool, Postgres, QueryBuilder};
 use uuid::Uuid;
 use validator::Validate;
 
 use crate::errors::ServiceError;
-use crate::models::project::{Project, ProjectCreate, ProjectStatus, ProjectUpdate};
+use crate::models::audit::{AuditContext, AuditEntity};
+use crate::models::job::ScheduleMetrics;
+use crate::models::lifecycle::LifecyclePhase;
+use crate::models::pagination::{Page, PageParams};
+use crate::models::project::{
+    Project, ProjectCreate, ProjectFilter, ProjectStatus, ProjectUpdate, PROJECT_SORT_FIELDS,
+};
+use crate::models::realtime::ChangeKind;
+use crate::models::user::UserRole;
+use crate::models::version::Versioned;
+use crate::models::webhook::WebhookEvent;
+use crate::services::audit_service::AuditService;
+use crate::services::notification_service::NotificationService;
+use crate::services::realtime_service::RealtimeService;
+use crate::services::task_service::TaskService;
+use crate::services::webhook_service::WebhookService;
 
 pub struct ProjectService;
 
 impl ProjectService {
-    pub async fn get_all(pool: &PgPool) -> Result<Vec<Project>, ServiceError> {
-        let projects = sqlx::query_as!(
+    pub async fn get_all(
+        params: &PageParams,
+        filter: &ProjectFilter,
+        pool: &PgPool,
+    ) -> Result<Page<Project>, ServiceError> {
+        Self::list(params, filter, false, pool).await
+    }
+
+    /// Projects in the trash, most recently deleted first.
+    pub async fn get_trash(
+        params: &PageParams,
+        filter: &ProjectFilter,
+        pool: &PgPool,
+    ) -> Result<Page<Project>, ServiceError> {
+        Self::list(params, filter, true, pool).await
+    }
+
+    async fn list(
+        params: &PageParams,
+        filter: &ProjectFilter,
+        trashed: bool,
+        pool: &PgPool,
+    ) -> Result<Page<Project>, ServiceError> {
+        let default_sort = if trashed {
+            "-deleted_at"
+        } else {
+            "-created_at"
+        };
+        let order_by = params.order_by(PROJECT_SORT_FIELDS, default_sort)?;
+        let scope = if trashed {
+            " WHERE deleted_at IS NOT NULL"
+        } else {
+            " WHERE deleted_at IS NULL"
+        };
+
+        let mut count = QueryBuilder::new("SELECT COUNT(*) FROM projects");
+        count.push(scope);
+        Self::push_filters(&mut count, filter);
+        let total: i64 = count
+            .build_query_scalar()
+            .fetch_one(pool)
+            .await
+            .map_err(|e| {
+                log::error!("Database error: {:?}", e);
+                ServiceError::DatabaseError(e)
+            })?;
+
+        let mut query = QueryBuilder::new(
+            r#"
+            SELECT
+                id, name, description, start_date, end_date,
+                status, budget, client_id,
+                created_at, updated_at, version, deleted_at, archived_at
+            FROM projects"#,
+        );
+        query.push(scope);
+        Self::push_filters(&mut query, filter);
+        query
+            .push(order_by)
+            .push(" LIMIT ")
+            .push_bind(params.per_page())
+            .push(" OFFSET ")
+            .push_bind(params.offset()?);
+
+        let projects = query
+            .build_query_as::<Project>()
+            .fetch_all(pool)
+            .await
+            .map_err(|e| {
+                log::error!("Database error: {:?}", e);
+                ServiceError::DatabaseError(e)
+            })?;
+
+        Ok(Page::new(projects, total, params))
+    }
+
+    fn push_filters<'a>(query: &mut QueryBuilder<'a, Postgres>, filter: &'a ProjectFilter) {
+        if let Some(status) = &filter.status {
+            query.push(" AND status = ").push_bind(status);
+        }
+        if let Some(phase) = &filter.phase {
+            query.push(" AND current_phase = ").push_bind(phase);
+        }
+        if let Some(starts_after) = filter.starts_after {
+            query.push(" AND start_date >= ").push_bind(starts_after);
+        }
+        if let Some(ends_before) = filter.ends_before {
+            query.push(" AND end_date <= ").push_bind(ends_before);
+        }
+        if filter.archived == Some(true) {
+            query.push(" AND archived_at IS NOT NULL");
+        } else {
+            query.push(" AND archived_at IS NULL");
+        }
+    }
+
+    pub async fn get_by_id(id: Uuid, pool: &PgPool) -> Result<Project, ServiceError> {
+        let project = sqlx::query_as!(
             Project,
             r#"
             SELECT
                 id, name, description, start_date, end_date,
                 status as "status: ProjectStatus", budget, client_id,
-                created_at, updated_at
+                created_at, updated_at, version, deleted_at, archived_at
             FROM projects
-            ORDER BY created_at DESC
-            "#
+            WHERE id = $1 AND deleted_at IS NULL
+            "#,
+            id
         )
-        .fetch_all(pool)
+        .fetch_optional(pool)
         .await
         .map_err(|e| {
             log::error!("Database error: {:?}", e);
             ServiceError::DatabaseError(e)
         })?;
 
-        Ok(projects)
+        project.ok_or(ServiceError::NotFound("Project not found".to_string()))
     }
 
-    pub async fn get_by_id(id: Uuid, pool: &PgPool) -> Result<Project, ServiceError> {
+    /// Locks the row for the rest of the transaction and returns it, so the
+    /// audit entry captures the state the change was applied to. Projects in
+    /// the trash are not found.
+    pub(crate) async fn lock(id: Uuid, conn: &mut PgConnection) -> Result<Project, ServiceError> {
         let project = sqlx::query_as!(
             Project,
             r#"
             SELECT
                 id, name, description, start_date, end_date,
                 status as "status: ProjectStatus", budget, client_id,
-                created_at, updated_at
+                created_at, updated_at, version, deleted_at, archived_at
             FROM projects
-            WHERE id = $1
+            WHERE id = $1 AND deleted_at IS NULL
+            FOR UPDATE
             "#,
             id
         )
-        .fetch_optional(pool)
-        .await
-        .map_err(|e| {
-            log::error!("Database error: {:?}", e);
-            ServiceError::DatabaseError(e)
-        })?;
+        .fetch_optional(conn)
+        .await?;
 
         project.ok_or(ServiceError::NotFound("Project not found".to_string()))
     }
 
+    /// Admins and project managers see every project, others the projects
+    /// they own or have tasks assigned in; the rule itself is the
+    /// `project_visible_to` SQL function, which list queries use directly.
+    /// Projects in the trash are hidden from everyone.
+    pub(crate) async fn is_visible_to(
+        project_id: Uuid,
+        user_id: Uuid,
+        role: &UserRole,
+        conn: &mut PgConnection,
+    ) -> Result<bool, ServiceError> {
+        let visible = sqlx::query_scalar!(
+            r#"
+            SELECT EXISTS (
+                SELECT 1 FROM projects p
+                WHERE p.id = $1 AND p.deleted_at IS NULL
+                  AND project_visible_to(p.id, $3, $2)
+            ) as "exists!"
+            "#,
+            project_id,
+            role.sees_all_projects(),
+            user_id
+        )
+        .fetch_one(conn)
+        .await?;
+
+        Ok(visible)
+    }
+
+    /// Like `lock`, but for a project in the trash.
+    async fn lock_trashed(id: Uuid, conn: &mut PgConnection) -> Result<Project, ServiceError> {
+        let project = sqlx::query_as!(
+            Project,
+            r#"
+            SELECT
+                id, name, description, start_date, end_date,
+                status as "status: ProjectStatus", budget, client_id,
+                created_at, updated_at, version, deleted_at, archived_at
+            FROM projects
+            WHERE id = $1 AND deleted_at IS NOT NULL
+            FOR UPDATE
+            "#,
+            id
+        )
+        .fetch_optional(conn)
+        .await?;
+
+        project.ok_or(ServiceError::NotFound(
+            "Project not found in trash".to_string(),
+        ))
+    }
+
     pub async fn create(
         new_project: ProjectCreate,
+        audit: &AuditContext,
         pool: &PgPool,
+    ) -> Result<Project, ServiceError> {
+        let mut tx = pool.begin().await?;
+        let project = Self::create_in(new_project, audit, &mut tx).await?;
+        tx.commit().await?;
+
+        Ok(project)
+    }
+
+    /// `create` within the caller's transaction.
+    pub(crate) async fn create_in(
+        new_project: ProjectCreate,
+        audit: &AuditContext,
+        conn: &mut PgConnection,
     ) -> Result<Project, ServiceError> {
         // Validate the project
         new_project.validate()?;
 
         // Additional validation for dates
         if new_project.end_date <= new_project.start_date {
-            return Err(ServiceError::ValidationError(
-                "end date must be after start date".to_string(),
+            return Err(ServiceError::invalid_field(
+                "end_date",
+                "date_order",
+                "end date must be after start date",
             ));
         }
 
@@ -73,11 +252,11 @@ impl ProjectService {
         let project = sqlx::query_as!(
             Project,
             r#"
-            INSERT INTO projects (name, description, start_date, end_date, status, budget, client_id, created_at, updated_at)
-            VALUES ($1, $2, $3, $4, 'planning', $5, $6, $7, $7)
+            INSERT INTO projects (name, description, start_date, end_date, status, budget, client_id, owner_id, created_at, updated_at)
+            VALUES ($1, $2, $3, $4, 'planning', $5, $6, $7, $8, $8)
             RETURNING id, name, description, start_date, end_date,
                       status as "status: ProjectStatus", budget, client_id,
-                      created_at, updated_at
+                      created_at, updated_at, version, deleted_at, archived_at
             "#,
             new_project.name,
             new_project.description,
@@ -85,47 +264,65 @@ impl ProjectService {
             new_project.end_date,
             new_project.budget,
             new_project.client_id,
+            audit.actor_id,
             now
         )
-        .fetch_one(pool)
+        .fetch_one(&mut *conn)
         .await
         .map_err(|e| {
             log::error!("Database error: {:?}", e);
             ServiceError::DatabaseError(e)
         })?;
 
+        AuditService::record_create(conn, audit, AuditEntity::Project, project.id, &project)
+            .await?;
+        WebhookService::emit_in(WebhookEvent::ProjectCreated, &project, None, conn).await?;
+
         Ok(project)
     }
 
     pub async fn update(
         id: Uuid,
         update: ProjectUpdate,
+        expected_version: Option<i32>,
+        audit: &AuditContext,
         pool: &PgPool,
     ) -> Result<Project, ServiceError> {
+        let mut tx = pool.begin().await?;
+
         // First, get the existing project to make sure it exists
-        let existing = Self::get_by_id(id, pool).await?;
+        let existing = Self::lock(id, &mut tx).await?;
+        existing.check_version(expected_version)?;
+        existing.ensure_writable()?;
 
         let now = Utc::now();
 
-        // Update only the fields that were provided
-        let name = update.name.unwrap_or(existing.name);
-        let description = update.description.or(existing.description);
-        let start_date = update.start_date.unwrap_or(existing.start_date);
-        let end_date = update.end_date.unwrap_or(existing.end_date);
-        let status = update.status.unwrap_or(existing.status);
-        let budget = update.budget.unwrap_or(existing.budget);
-        let client_id = update.client_id.or(existing.client_id);
+        // Merge the patch into the current state
+        let name = update.name.apply_required("name", existing.name.clone())?;
+        let description = update.description.apply(existing.description.clone());
+        let start_date = update
+            .start_date
+            .apply_required("start_date", existing.start_date)?;
+        let end_date = update
+            .end_date
+            .apply_required("end_date", existing.end_date)?;
+        let status = update.status.apply_required("status", existing.status)?;
+        let budget = update
+            .budget
+            .apply_required("budget", existing.budget.clone())?;
+        let client_id = update.client_id.apply(existing.client_id);
 
         let updated_project = sqlx::query_as!(
             Project,
             r#"
             UPDATE projects
             SET name = $1, description = $2, start_date = $3, end_date = $4,
-                status = $5, budget = $6, client_id = $7, updated_at = $8
+                status = $5, budget = $6, client_id = $7, updated_at = $8,
+                version = version + 1
             WHERE id = $9
             RETURNING id, name, description, start_date, end_date,
                       status as "status: ProjectStatus", budget, client_id,
-                      created_at, updated_at
+                      created_at, updated_at, version, deleted_at, archived_at
             "#,
             name,
             description,
@@ -137,29 +334,293 @@ impl ProjectService {
             now,
             id
         )
-        .fetch_one(pool)
+        .fetch_one(&mut *tx)
         .await
         .map_err(|e| {
             log::error!("Database error: {:?}", e);
             ServiceError::DatabaseError(e)
         })?;
 
+        AuditService::record_update(
+            &mut tx,
+            audit,
+            AuditEntity::Project,
+            id,
+            &existing,
+            &updated_project,
+        )
+        .await?;
+        if updated_project.budget != existing.budget {
+            NotificationService::check_budget_in(id, &mut tx).await?;
+        }
+        WebhookService::emit_in(
+            WebhookEvent::ProjectUpdated,
+            &updated_project,
+            None,
+            &mut tx,
+        )
+        .await?;
+        tx.commit().await?;
+
         Ok(updated_project)
     }
 
-    pub async fn delete(id: Uuid, pool: &PgPool) -> Result<(), ServiceError> {
-        let result = sqlx::query!("DELETE FROM projects WHERE id = $1", id)
-            .execute(pool)
-            .await
-            .map_err(|e| {
-                log::error!("Database error: {:?}", e);
-                ServiceError::DatabaseError(e)
-            })?;
-
-        if result.rows_affected() == 0 {
+    /// Moves the project and its tasks to the trash. Nothing is removed
+    /// until the purge job runs.
+    pub async fn delete(
+        id: Uuid,
+        expected_version: Option<i32>,
+        user_id: Uuid,
+        role: &UserRole,
+        audit: &AuditContext,
+        pool: &PgPool,
+    ) -> Result<(), ServiceError> {
+        let mut tx = pool.begin().await?;
+        if !Self::is_visible_to(id, user_id, role, &mut tx).await? {
             return Err(ServiceError::NotFound("Project not found".to_string()));
         }
+        let existing = Self::lock(id, &mut tx).await?;
+        existing.check_version(expected_version)?;
+        existing.ensure_writable()?;
+
+        let deleted_at = sqlx::query_scalar!(
+            r#"
+            UPDATE projects
+            SET deleted_at = NOW(), version = version + 1
+            WHERE id = $1
+            RETURNING deleted_at as "deleted_at!"
+            "#,
+            id
+        )
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(|e| {
+            log::error!("Database error: {:?}", e);
+            ServiceError::DatabaseError(e)
+        })?;
+
+        TaskService::trash_project_tasks(id, deleted_at, audit, &mut tx).await?;
+        AuditService::record_delete(&mut tx, audit, AuditEntity::Project, id, &existing).await?;
+        WebhookService::emit_in(WebhookEvent::ProjectDeleted, &existing, None, &mut tx).await?;
+        tx.commit().await?;
 
         Ok(())
     }
+
+    /// Takes the project out of the trash together with the tasks that were
+    /// trashed along with it. Tasks deleted on their own beforehand stay in
+    /// the trash.
+    pub async fn restore(
+        id: Uuid,
+        expected_version: Option<i32>,
+        audit: &AuditContext,
+        pool: &PgPool,
+    ) -> Result<Project, ServiceError> {
+        let mut tx = pool.begin().await?;
+        let trashed = Self::lock_trashed(id, &mut tx).await?;
+        trashed.check_version(expected_version)?;
+
+        let project = sqlx::query_as!(
+            Project,
+            r#"
+            UPDATE projects
+            SET deleted_at = NULL, version = version + 1
+            WHERE id = $1
+            RETURNING id, name, description, start_date, end_date,
+                      status as "status: ProjectStatus", budget, client_id,
+                      created_at, updated_at, version, deleted_at, archived_at
+            "#,
+            id
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+
+        AuditService::record_restore(&mut tx, audit, AuditEntity::Project, id, &trashed, &project)
+            .await?;
+        WebhookService::emit_in(WebhookEvent::ProjectRestored, &project, None, &mut tx).await?;
+        RealtimeService::publish_in(
+            ChangeKind::ProjectRestored,
+            project.id,
+            project.id,
+            Some(project.version),
+            audit,
+            &mut tx,
+        )
+        .await?;
+        if let Some(deleted_at) = trashed.deleted_at {
+            TaskService::restore_project_tasks(id, deleted_at, audit, &mut tx).await?;
+        }
+        tx.commit().await?;
+
+        Ok(project)
+    }
+
+    /// Freezes a closed project: it and everything belonging to it become
+    /// read-only, and it drops out of default listings.
+    pub async fn archive(
+        id: Uuid,
+        expected_version: Option<i32>,
+        audit: &AuditContext,
+        pool: &PgPool,
+    ) -> Result<Project, ServiceError> {
+        let mut tx = pool.begin().await?;
+        let existing = Self::lock(id, &mut tx).await?;
+        existing.check_version(expected_version)?;
+        existing.ensure_writable()?;
+
+        let phase = sqlx::query_scalar!(
+            r#"SELECT current_phase as "current_phase: LifecyclePhase" FROM projects WHERE id = $1"#,
+            id
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+        if phase != LifecyclePhase::Closed {
+            return Err(ServiceError::UnprocessableEntity(
+                "Only projects in the closed phase can be archived".into(),
+            ));
+        }
+
+        let project = Self::set_archived_at(id, Some(Utc::now()), &mut tx).await?;
+        AuditService::record_update(
+            &mut tx,
+            audit,
+            AuditEntity::Project,
+            id,
+            &existing,
+            &project,
+        )
+        .await?;
+        WebhookService::emit_in(WebhookEvent::ProjectUpdated, &project, None, &mut tx).await?;
+        tx.commit().await?;
+
+        Ok(project)
+    }
+
+    /// Makes an archived project editable again.
+    pub async fn unarchive(
+        id: Uuid,
+        expected_version: Option<i32>,
+        audit: &AuditContext,
+        pool: &PgPool,
+    ) -> Result<Project, ServiceError> {
+        let mut tx = pool.begin().await?;
+        let existing = Self::lock(id, &mut tx).await?;
+        existing.check_version(expected_version)?;
+        if existing.archived_at.is_none() {
+            return Err(ServiceError::UnprocessableEntity(
+                "Project is not archived".into(),
+            ));
+        }
+
+        let project = Self::set_archived_at(id, None, &mut tx).await?;
+        AuditService::record_update(
+            &mut tx,
+            audit,
+            AuditEntity::Project,
+            id,
+            &existing,
+            &project,
+        )
+        .await?;
+        WebhookService::emit_in(WebhookEvent::ProjectUpdated, &project, None, &mut tx).await?;
+        tx.commit().await?;
+
+        Ok(project)
+    }
+
+    async fn set_archived_at(
+        id: Uuid,
+        archived_at: Option<DateTime<Utc>>,
+        conn: &mut PgConnection,
+    ) -> Result<Project, ServiceError> {
+        let project = sqlx::query_as!(
+            Project,
+            r#"
+            UPDATE projects
+            SET archived_at = $1, updated_at = NOW(), version = version + 1
+            WHERE id = $2
+            RETURNING id, name, description, start_date, end_date,
+                      status as "status: ProjectStatus", budget, client_id,
+                      created_at, updated_at, version, deleted_at, archived_at
+            "#,
+            archived_at,
+            id
+        )
+        .fetch_one(conn)
+        .await?;
+
+        Ok(project)
+    }
+
+    /// Recomputes progress against plan for every live, unarchived project
+    /// and returns how many were updated. Tasks are weighted by duration,
+    /// milestones counting as one minute.
+    pub async fn recompute_schedule_metrics(
+        now: DateTime<Utc>,
+        pool: &PgPool,
+    ) -> Result<u64, ServiceError> {
+        let result = sqlx::query!(
+            r#"
+            INSERT INTO project_schedule_metrics (
+                project_id, task_count, completed_count, overdue_count,
+                percent_complete, percent_planned, computed_at
+            )
+            SELECT p.id,
+                   COUNT(t.id)::INTEGER,
+                   (COUNT(t.id) FILTER (WHERE t.status = 'completed'))::INTEGER,
+                   (COUNT(t.id) FILTER (WHERE t.status <> 'completed' AND t.end_date < $1))::INTEGER,
+                   COALESCE(ROUND(SUM(t.progress * w.weight) / NULLIF(SUM(w.weight), 0), 2), 0),
+                   COALESCE(ROUND(SUM(w.elapsed) * 100 / NULLIF(SUM(w.weight), 0), 2), 0),
+                   $1
+            FROM projects p
+            LEFT JOIN tasks t ON t.project_id = p.id AND t.deleted_at IS NULL
+            LEFT JOIN LATERAL (
+                SELECT d.weight, LEAST(GREATEST(EXTRACT(EPOCH FROM ($1 - t.start_date)), 0), d.weight) AS elapsed
+                FROM (SELECT GREATEST(EXTRACT(EPOCH FROM (t.end_date - t.start_date)), 60) AS weight) d
+            ) w ON t.id IS NOT NULL
+            WHERE p.deleted_at IS NULL AND p.archived_at IS NULL
+            GROUP BY p.id
+            ON CONFLICT (project_id) DO UPDATE SET
+                task_count = EXCLUDED.task_count,
+                completed_count = EXCLUDED.completed_count,
+                overdue_count = EXCLUDED.overdue_count,
+                percent_complete = EXCLUDED.percent_complete,
+                percent_planned = EXCLUDED.percent_planned,
+                computed_at = EXCLUDED.computed_at
+            "#,
+            now
+        )
+        .execute(pool)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    /// The metrics last computed by the `schedule_metrics` job.
+    pub async fn get_schedule_metrics(
+        id: Uuid,
+        user_id: Uuid,
+        role: &UserRole,
+        pool: &PgPool,
+    ) -> Result<ScheduleMetrics, ServiceError> {
+        let mut conn = pool.acquire().await?;
+        if !Self::is_visible_to(id, user_id, role, &mut conn).await? {
+            return Err(ServiceError::NotFound("Project not found".to_string()));
+        }
+        sqlx::query_as!(
+            ScheduleMetrics,
+            r#"
+            SELECT project_id, task_count, completed_count, overdue_count,
+                   percent_complete, percent_planned, computed_at
+            FROM project_schedule_metrics
+            WHERE project_id = $1
+            "#,
+            id
+        )
+        .fetch_optional(&mut *conn)
+        .await?
+        .ok_or(ServiceError::NotFound(
+            "Schedule metrics have not been computed yet".into(),
+        ))
+    }
 }
@@ -0,0 +1,259 @@
+use actix_web::web::Bytes;
+use chrono::{DateTime, Utc};
+use futures::stream::{self, Stream, StreamExt};
+use sqlx::postgres::PgListener;
+use sqlx::{PgConnection, PgPool};
+use std::collections::HashSet;
+use tokio::sync::broadcast::{self, error::RecvError};
+use uuid::Uuid;
+
+use crate::errors::ServiceError;
+use crate::models::audit::AuditContext;
+use crate::models::realtime::{ChangeEvent, ChangeKind};
+use crate::models::user::UserRole;
+
+/// Postgres channel every instance listens on.
+const CHANNEL: &str = "project_changes";
+/// Events buffered per instance before slow streams start missing some.
+const BUFFER: usize = 1024;
+/// Comment lines sent this often keep proxies from closing idle streams.
+const KEEP_ALIVE_SECONDS: u64 = 15;
+/// How long clients wait before reconnecting, sent as the SSE `retry` field.
+const RETRY_MILLISECONDS: u64 = 3000;
+/// Last frame of a stream whose token expired or whose account was
+/// deactivated.
+const EXPIRED_FRAME: &str = "event: expired\ndata: {}\n\n";
+
+/// Hands change events received from Postgres to this instance's open
+/// streams. Every instance runs one, so a change made through any of them
+/// reaches clients connected to all of them.
+#[derive(Clone)]
+pub struct Broadcaster {
+    sender: broadcast::Sender<ChangeEvent>,
+}
+
+impl Default for Broadcaster {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Broadcaster {
+    pub fn new() -> Self {
+        let (sender, _) = broadcast::channel(BUFFER);
+        Broadcaster { sender }
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<ChangeEvent> {
+        self.sender.subscribe()
+    }
+
+    /// Starts listening for change notifications. Returns once the
+    /// subscription is in place; the listener reconnects by itself if the
+    /// connection drops.
+    pub async fn listen(&self, pool: &PgPool) -> Result<tokio::task::JoinHandle<()>, ServiceError> {
+        let mut listener = PgListener::connect_with(pool).await?;
+        listener.listen(CHANNEL).await?;
+
+        let sender = self.sender.clone();
+        Ok(tokio::spawn(async move {
+            loop {
+                match listener.recv().await {
+                    Ok(notification) => {
+                        match serde_json::from_str::<ChangeEvent>(notification.payload()) {
+                            // Fails only when nobody is connected
+                            Ok(event) => {
+                                let _ = sender.send(event);
+                            }
+                            Err(e) => log::warn!("Ignoring malformed change notification: {}", e),
+                        }
+                    }
+                    Err(e) => {
+                        log::error!("Change notification listener failed: {}", e);
+                        tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+                    }
+                }
+            }
+        }))
+    }
+}
+
+/// Projects whose changes a stream may carry.
+#[derive(Debug, PartialEq)]
+pub enum Visibility {
+    All,
+    Projects(HashSet<Uuid>),
+}
+
+impl Visibility {
+    pub fn allows(&self, project_id: Uuid) -> bool {
+        match self {
+            Visibility::All => true,
+            Visibility::Projects(projects) => projects.contains(&project_id),
+        }
+    }
+}
+
+/// Who a stream is for and how long their token is good for.
+pub struct StreamSession {
+    pub user_id: Uuid,
+    pub expires_at: DateTime<Utc>,
+    pub pool: PgPool,
+}
+
+impl StreamSession {
+    async fn is_active(&self) -> bool {
+        let deactivated = sqlx::query_scalar!(
+            "SELECT deactivated_at IS NOT NULL FROM users WHERE id = $1",
+            self.user_id
+        )
+        .fetch_optional(&self.pool)
+        .await;
+        match deactivated {
+            Ok(Some(Some(false))) => true,
+            Ok(_) => false,
+            // A database hiccup is no reason to drop the client
+            Err(e) => {
+                log::warn!("Could not check stream account: {}", e);
+                true
+            }
+        }
+    }
+}
+
+pub struct RealtimeService;
+
+impl RealtimeService {
+    /// Announces a change to every instance. Postgres delivers the
+    /// notification when the caller's transaction commits, and drops it if
+    /// the transaction rolls back.
+    pub(crate) async fn publish_in(
+        kind: ChangeKind,
+        project_id: Uuid,
+        entity_id: Uuid,
+        version: Option<i32>,
+        audit: &AuditContext,
+        conn: &mut PgConnection,
+    ) -> Result<(), ServiceError> {
+        let event = ChangeEvent {
+            id: Uuid::new_v4(),
+            kind,
+            project_id,
+            entity_id,
+            version,
+            actor_id: audit.actor_id,
+            occurred_at: Utc::now(),
+        };
+        let payload = serde_json::to_string(&event).map_err(|e| {
+            log::error!("Failed to serialize change event: {:?}", e);
+            ServiceError::InternalServerError
+        })?;
+        sqlx::query("SELECT pg_notify($1, $2)")
+            .bind(CHANNEL)
+            .bind(payload)
+            .execute(&mut *conn)
+            .await?;
+
+        Ok(())
+    }
+
+    /// The projects a user's stream covers: one project if asked for,
+    /// otherwise every project the user can see. For users who do not see
+    /// all projects the set is fixed when the stream opens; projects they
+    /// join later appear after reconnecting.
+    pub async fn visibility(
+        user_id: Uuid,
+        role: &UserRole,
+        project_id: Option<Uuid>,
+        pool: &PgPool,
+    ) -> Result<Visibility, ServiceError> {
+        if project_id.is_none() && role.sees_all_projects() {
+            return Ok(Visibility::All);
+        }
+
+        let projects = sqlx::query_scalar!(
+            r#"
+            SELECT p.id
+            FROM projects p
+            WHERE p.deleted_at IS NULL
+              AND ($1::UUID IS NULL OR p.id = $1)
+              AND project_visible_to(p.id, $3, $2)
+            "#,
+            project_id,
+            role.sees_all_projects(),
+            user_id
+        )
+        .fetch_all(pool)
+        .await?;
+
+        if project_id.is_some() && projects.is_empty() {
+            return Err(ServiceError::NotFound("Project not found".into()));
+        }
+        Ok(Visibility::Projects(projects.into_iter().collect()))
+    }
+
+    /// Server-Sent Events for the changes `visibility` allows. A stream that
+    /// falls too far behind gets a `resync` event telling the client to
+    /// reload what it shows. When the caller's token expires, or their
+    /// account is found deactivated at a keep-alive, the stream ends with an
+    /// `expired` event so the client reconnects with a fresh token.
+    pub fn stream(
+        events: broadcast::Receiver<ChangeEvent>,
+        visibility: Visibility,
+        session: StreamSession,
+    ) -> impl Stream<Item = Result<Bytes, ServiceError>> {
+        let period = std::time::Duration::from_secs(KEEP_ALIVE_SECONDS);
+        let keep_alive = tokio::time::interval_at(tokio::time::Instant::now() + period, period);
+        let deadline = tokio::time::Instant::now()
+            + (session.expires_at - Utc::now())
+                .to_std()
+                .unwrap_or_default();
+        let opening =
+            stream::once(async { Ok(Bytes::from(format!("retry: {}\n\n", RETRY_MILLISECONDS))) });
+
+        let changes = stream::unfold(
+            Some((events, keep_alive, visibility, session)),
+            move |state| async move {
+                let (mut events, mut keep_alive, visibility, session) = state?;
+                loop {
+                    let frame = tokio::select! {
+                        received = events.recv() => match received {
+                            Ok(event) if visibility.allows(event.project_id) => {
+                                Self::frame(&event)
+                            }
+                            Ok(_) => continue,
+                            Err(RecvError::Lagged(missed)) => {
+                                format!("event: resync\ndata: {{\"missed\":{}}}\n\n", missed)
+                            }
+                            Err(RecvError::Closed) => return None,
+                        },
+                        _ = tokio::time::sleep_until(deadline) => {
+                            return Some((Ok(Bytes::from(EXPIRED_FRAME)), None));
+                        }
+                        _ = keep_alive.tick() => {
+                            if !session.is_active().await {
+                                return Some((Ok(Bytes::from(EXPIRED_FRAME)), None));
+                            }
+                            ": keep-alive\n\n".to_string()
+                        }
+                    };
+                    return Some((
+                        Ok(Bytes::from(frame)),
+                        Some((events, keep_alive, visibility, session)),
+                    ));
+                }
+            },
+        );
+
+        opening.chain(changes)
+    }
+
+    fn frame(event: &ChangeEvent) -> String {
+        format!(
+            "id: {}\nevent: {}\ndata: {}\n\n",
+            event.id,
+            event.kind.as_str(),
+            serde_json::to_string(event).unwrap_or_default()
+        )
+    }
+}
@@ -0,0 +1,729 @@
+use crate::models::mustache::escape_html;
+
+/// A4 in points.
+const PAGE_WIDTH: f32 = 595.0;
+const PAGE_HEIGHT: f32 = 842.0;
+const MARGIN: f32 = 56.0;
+const BODY_SIZE: f32 = 10.5;
+const CODE_SIZE: f32 = 9.0;
+const LEADING: f32 = 1.35;
+const LIST_INDENT: f32 = 16.0;
+const CELL_PADDING: f32 = 4.0;
+
+/// Helvetica advance widths for ASCII 32–126, in thousandths of the size.
+const HELVETICA: [u16; 95] = [
+    278, 278, 355, 556, 556, 889, 667, 191, 333, 333, 389, 584, 278, 333, 278, 278, 556, 556, 556,
+    556, 556, 556, 556, 556, 556, 556, 278, 278, 584, 584, 584, 556, 1015, 667, 667, 722, 722, 667,
+    611, 778, 722, 278, 500, 667, 556, 833, 722, 778, 667, 778, 722, 667, 611, 722, 667, 944, 667,
+    667, 611, 278, 278, 278, 469, 556, 333, 556, 556, 500, 556, 556, 278, 556, 556, 222, 222, 500,
+    222, 833, 556, 556, 556, 556, 333, 500, 278, 556, 500, 722, 500, 500, 500, 334, 260, 334, 584,
+];
+
+/// Helvetica-Bold advance widths for ASCII 32–126.
+const HELVETICA_BOLD: [u16; 95] = [
+    278, 333, 474, 556, 556, 889, 722, 238, 333, 333, 389, 584, 278, 333, 278, 278, 556, 556, 556,
+    556, 556, 556, 556, 556, 556, 556, 333, 333, 584, 584, 584, 611, 975, 722, 722, 722, 722, 667,
+    611, 778, 722, 278, 556, 722, 611, 833, 722, 778, 667, 778, 722, 667, 611, 722, 667, 944, 667,
+    667, 611, 333, 278, 333, 584, 556, 333, 556, 611, 556, 611, 556, 333, 611, 611, 278, 278, 556,
+    278, 889, 611, 611, 611, 611, 389, 556, 333, 611, 556, 778, 556, 556, 500, 389, 280, 389, 584,
+];
+
+/// Turns the Markdown generated documents are written in into HTML and PDF.
+///
+/// Covers what templates need: ATX headings, paragraphs, `-`/`*` and
+/// numbered lists, pipe tables, fenced code, horizontal rules, and inline
+/// `**strong**`, `*emphasis*`, `_emphasis_`, `` `code` `` and backslash
+/// escapes. Raw HTML in Markdown is shown as text.
+pub struct RenderService;
+
+impl RenderService {
+    /// A complete HTML page for the Markdown.
+    pub fn markdown_to_html(markdown: &str, title: &str) -> String {
+        let mut body = String::new();
+        for block in parse(markdown) {
+            match block {
+                Block::Heading(level, text) => {
+                    body.push_str(&format!("<h{0}>{1}</h{0}>\n", level, inline_html(&text)))
+                }
+                Block::Paragraph(text) => {
+                    body.push_str(&format!("<p>{}</p>\n", inline_html(&text)))
+                }
+                Block::List { ordered, items } => {
+                    let tag = if ordered { "ol" } else { "ul" };
+                    body.push_str(&format!("<{}>\n", tag));
+                    for item in items {
+                        body.push_str(&format!("<li>{}</li>\n", inline_html(&item)));
+                    }
+                    body.push_str(&format!("</{}>\n", tag));
+                }
+                Block::Table { header, rows } => {
+                    body.push_str("<table>\n<thead>\n<tr>");
+                    for cell in &header {
+                        body.push_str(&format!("<th>{}</th>", inline_html(cell)));
+                    }
+                    body.push_str("</tr>\n</thead>\n<tbody>\n");
+                    for row in rows {
+                        body.push_str("<tr>");
+                        for cell in row {
+                            body.push_str(&format!("<td>{}</td>", inline_html(&cell)));
+                        }
+                        body.push_str("</tr>\n");
+                    }
+                    body.push_str("</tbody>\n</table>\n");
+                }
+                Block::Code(code) => {
+                    body.push_str(&format!("<pre><code>{}</code></pre>\n", escape_html(&code)))
+                }
+                Block::Rule => body.push_str("<hr>\n"),
+            }
+        }
+
+        format!(
+            concat!(
+                "<!DOCTYPE html>\n",
+                "<html lang=\"en\">\n",
+                "<head>\n",
+                "<meta charset=\"utf-8\">\n",
+                "<title>{}</title>\n",
+                "<style>\n",
+                "body {{ font-family: Helvetica, Arial, sans-serif; max-width: 50em; margin: 2em auto; line-height: 1.4; }}\n",
+                "table {{ border-collapse: collapse; width: 100%; }}\n",
+                "th, td {{ border: 1px solid #ccc; padding: 0.25em 0.5em; text-align: left; }}\n",
+                "</style>\n",
+                "</head>\n",
+                "<body>\n",
+                "{}",
+                "</body>\n",
+                "</html>\n"
+            ),
+            escape_html(title),
+            body
+        )
+    }
+
+    /// A4 PDF of the Markdown in the standard Helvetica and Courier fonts,
+    /// with the title and page numbers in the footer. Characters outside
+    /// Windows-1252 print as `?`.
+    pub fn markdown_to_pdf(markdown: &str, title: &str) -> Vec<u8> {
+        let mut layout = Layout::new();
+        for block in parse(markdown) {
+            match block {
+                Block::Heading(level, text) => {
+                    let size = match level {
+                        1 => 18.0,
+                        2 => 14.0,
+                        3 => 12.0,
+                        _ => BODY_SIZE + 0.5,
+                    };
+                    layout.space(size * 0.8);
+                    layout.paragraph(&inline_text(&text), Font::Bold, size, 0.0);
+                    layout.space(size * 0.3);
+                }
+                Block::Paragraph(text) => {
+                    layout.paragraph(&inline_text(&text), Font::Regular, BODY_SIZE, 0.0);
+                    layout.space(BODY_SIZE * 0.6);
+                }
+                Block::List { ordered, items } => {
+                    for (i, item) in items.iter().enumerate() {
+                        let marker = if ordered {
+                            format!("{}.", i + 1)
+                        } else {
+                            "\u{2022}".to_string()
+                        };
+                        layout.list_item(&marker, &inline_text(item));
+                    }
+                    layout.space(BODY_SIZE * 0.6);
+                }
+                Block::Table { header, rows } => {
+                    let header: Vec<String> = header.iter().map(|c| inline_text(c)).collect();
+                    layout.table_row(&header, Font::Bold);
+                    for row in rows {
+                        let row: Vec<String> = row.iter().map(|c| inline_text(c)).collect();
+                        layout.table_row(&row, Font::Regular);
+                    }
+                    layout.space(BODY_SIZE * 0.6);
+                }
+                Block::Code(code) => {
+                    for line in code.lines() {
+                        layout.paragraph(line, Font::Mono, CODE_SIZE, 0.0);
+                    }
+                    layout.space(BODY_SIZE * 0.6);
+                }
+                Block::Rule => layout.rule(),
+            }
+        }
+        layout.finish(title)
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum Block {
+    Heading(usize, String),
+    Paragraph(String),
+    List {
+        ordered: bool,
+        items: Vec<String>,
+    },
+    Table {
+        header: Vec<String>,
+        rows: Vec<Vec<String>>,
+    },
+    Code(String),
+    Rule,
+}
+
+fn parse(markdown: &str) -> Vec<Block> {
+    let lines: Vec<&str> = markdown.lines().collect();
+    let mut blocks = Vec::new();
+    let mut i = 0;
+
+    while i < lines.len() {
+        let line = lines[i].trim_end();
+        let trimmed = line.trim_start();
+
+        if trimmed.is_empty() {
+            i += 1;
+        } else if trimmed.starts_with("```") {
+            let mut code = Vec::new();
+            i += 1;
+            while i < lines.len() && !lines[i].trim_start().starts_with("```") {
+                code.push(lines[i]);
+                i += 1;
+            }
+            blocks.push(Block::Code(code.join("\n")));
+            i += 1;
+        } else if let Some((level, text)) = heading(trimmed) {
+            blocks.push(Block::Heading(level, text.to_string()));
+            i += 1;
+        } else if is_rule(trimmed) {
+            blocks.push(Block::Rule);
+            i += 1;
+        } else if trimmed.starts_with('|')
+            && lines
+                .get(i + 1)
+                .is_some_and(|next| is_table_separator(next.trim()))
+        {
+            let header = cells(trimmed);
+            let mut rows = Vec::new();
+            i += 2;
+            while i < lines.len() && lines[i].trim_start().starts_with('|') {
+                let mut row = cells(lines[i].trim());
+                row.resize(header.len(), String::new());
+                rows.push(row);
+                i += 1;
+            }
+            blocks.push(Block::Table { header, rows });
+        } else if let Some((ordered, _)) = list_item(trimmed) {
+            let mut items = Vec::new();
+            while let Some(line) = lines.get(i) {
+                match list_item(line.trim()) {
+                    Some((o, text)) if o == ordered => items.push(text.to_string()),
+                    // Indented lines continue the item above
+                    None if line.starts_with("  ") && !line.trim().is_empty() => {
+                        if let Some(last) = items.last_mut() {
+                            last.push(' ');
+                            last.push_str(line.trim());
+                        }
+                    }
+                    _ => break,
+                }
+                i += 1;
+            }
+            blocks.push(Block::List { ordered, items });
+        } else {
+            let mut text = trimmed.to_string();
+            i += 1;
+            while let Some(line) = lines.get(i) {
+                let next = line.trim();
+                if next.is_empty()
+                    || next.starts_with("```")
+                    || next.starts_with('|')
+                    || heading(next).is_some()
+                    || is_rule(next)
+                    || list_item(next).is_some()
+                {
+                    break;
+                }
+                text.push(' ');
+                text.push_str(next);
+                i += 1;
+            }
+            blocks.push(Block::Paragraph(text));
+        }
+    }
+
+    blocks
+}
+
+fn heading(line: &str) -> Option<(usize, &str)> {
+    let level = line.chars().take_while(|c| *c == '#').count();
+    if !(1..=6).contains(&level) {
+        return None;
+    }
+    let rest = &line[level..];
+    if rest.is_empty() {
+        Some((level, ""))
+    } else if rest.starts_with(' ') {
+        Some((level, rest.trim()))
+    } else {
+        None
+    }
+}
+
+fn is_rule(line: &str) -> bool {
+    let compact: String = line.chars().filter(|c| *c != ' ').collect();
+    compact.len() >= 3
+        && (compact.chars().all(|c| c == '-')
+            || compact.chars().all(|c| c == '*')
+            || compact.chars().all(|c| c == '_'))
+}
+
+fn is_table_separator(line: &str) -> bool {
+    line.starts_with('|')
+        && line.contains('-')
+        && line
+            .chars()
+            .all(|c| matches!(c, '|' | '-' | ':' | ' ' | '\t'))
+}
+
+fn list_item(line: &str) -> Option<(bool, &str)> {
+    if let Some(text) = line.strip_prefix("- ").or_else(|| line.strip_prefix("* ")) {
+        return Some((false, text.trim()));
+    }
+    let digits = line.chars().take_while(|c| c.is_ascii_digit()).count();
+    if digits > 0 {
+        if let Some(text) = line[digits..].strip_prefix(". ") {
+            return Some((true, text.trim()));
+        }
+    }
+    None
+}
+
+/// Splits a table row on pipes that are not escaped.
+fn cells(row: &str) -> Vec<String> {
+    let row = row.strip_prefix('|').unwrap_or(row);
+    let mut cells = Vec::new();
+    let mut cell = String::new();
+    let mut chars = row.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                cell.push(c);
+                if let Some(next) = chars.next() {
+                    cell.push(next);
+                }
+            }
+            '|' => cells.push(std::mem::take(&mut cell).trim().to_string()),
+            _ => cell.push(c),
+        }
+    }
+    if !cell.trim().is_empty() {
+        cells.push(cell.trim().to_string());
+    }
+    cells
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum Style {
+    Plain,
+    Strong,
+    Emphasis,
+    Code,
+}
+
+/// Splits inline Markdown into styled runs with escapes resolved.
+fn spans(text: &str) -> Vec<(Style, String)> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut spans: Vec<(Style, String)> = Vec::new();
+    let mut plain = String::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        if c == '\\' && chars.get(i + 1).is_some_and(|n| n.is_ascii_punctuation()) {
+            plain.push(chars[i + 1]);
+            i += 2;
+            continue;
+        }
+        let (style, delimiter): (Style, &[char]) = match c {
+            '`' => (Style::Code, &['`']),
+            '*' if chars.get(i + 1) == Some(&'*') => (Style::Strong, &['*', '*']),
+            '*' => (Style::Emphasis, &['*']),
+            // Underscores inside words, as in snake_case, stay literal
+            '_' if i == 0 || !chars[i - 1].is_alphanumeric() => (Style::Emphasis, &['_']),
+            _ => {
+                plain.push(c);
+                i += 1;
+                continue;
+            }
+        };
+        let open = i + delimiter.len();
+        match closing(&chars, open, delimiter, style == Style::Code) {
+            Some(close) if close > open => {
+                if !plain.is_empty() {
+                    spans.push((Style::Plain, std::mem::take(&mut plain)));
+                }
+                let inner: String = chars[open..close].iter().collect();
+                let inner = if style == Style::Code {
+                    inner
+                } else {
+                    unescape(&inner)
+                };
+                spans.push((style, inner));
+                i = close + delimiter.len();
+            }
+            _ => {
+                plain.extend(delimiter);
+                i = open;
+            }
+        }
+    }
+    if !plain.is_empty() {
+        spans.push((Style::Plain, plain));
+    }
+    spans
+}
+
+fn closing(chars: &[char], from: usize, delimiter: &[char], verbatim: bool) -> Option<usize> {
+    let mut i = from;
+    while i + delimiter.len() <= chars.len() {
+        if !verbatim && chars[i] == '\\' {
+            i += 2;
+            continue;
+        }
+        if chars[i..i + delimiter.len()] == *delimiter {
+            let after = chars.get(i + delimiter.len());
+            if delimiter != ['_'] || !after.is_some_and(|c| c.is_alphanumeric()) {
+                return Some(i);
+            }
+        }
+        i += 1;
+    }
+    None
+}
+
+fn unescape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == '\\' && chars.peek().is_some_and(|n| n.is_ascii_punctuation()) {
+            continue;
+        }
+        out.push(c);
+    }
+    out
+}
+
+fn inline_html(text: &str) -> String {
+    spans(text)
+        .into_iter()
+        .map(|(style, text)| {
+            let text = escape_html(&text);
+            match style {
+                Style::Plain => text,
+                Style::Strong => format!("<strong>{}</strong>", text),
+                Style::Emphasis => format!("<em>{}</em>", text),
+                Style::Code => format!("<code>{}</code>", text),
+            }
+        })
+        .collect()
+}
+
+fn inline_text(text: &str) -> String {
+    spans(text).into_iter().map(|(_, text)| text).collect()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Font {
+    Regular,
+    Bold,
+    Mono,
+}
+
+impl Font {
+    fn resource(&self) -> &'static str {
+        match self {
+            Font::Regular => "F1",
+            Font::Bold => "F2",
+            Font::Mono => "F3",
+        }
+    }
+
+    fn width(&self, text: &str, size: f32) -> f32 {
+        let units: u32 = text
+            .chars()
+            .map(|c| match self {
+                Font::Mono => 600,
+                _ => {
+                    let table = if *self == Font::Bold {
+                        &HELVETICA_BOLD
+                    } else {
+                        &HELVETICA
+                    };
+                    match c as u32 {
+                        code @ 32..=126 => table[(code - 32) as usize] as u32,
+                        _ => 556,
+                    }
+                }
+            })
+            .sum();
+        units as f32 * size / 1000.0
+    }
+}
+
+/// Lays text out top to bottom onto as many pages as it takes.
+struct Layout {
+    pages: Vec<String>,
+    current: String,
+    y: f32,
+}
+
+impl Layout {
+    fn new() -> Self {
+        Layout {
+            pages: Vec::new(),
+            current: String::new(),
+            y: PAGE_HEIGHT - MARGIN,
+        }
+    }
+
+    fn content_width() -> f32 {
+        PAGE_WIDTH - 2.0 * MARGIN
+    }
+
+    /// Starts a new page unless `height` more fits on this one.
+    fn reserve(&mut self, height: f32) {
+        if self.y - height < MARGIN && !self.current.is_empty() {
+            self.pages.push(std::mem::take(&mut self.current));
+            self.y = PAGE_HEIGHT - MARGIN;
+        }
+    }
+
+    fn space(&mut self, height: f32) {
+        self.y -= height;
+    }
+
+    fn text(&mut self, x: f32, text: &str, font: Font, size: f32) {
+        self.current.push_str(&format!(
+            "BT /{} {:.1} Tf {:.2} {:.2} Td ({}) Tj ET\n",
+            font.resource(),
+            size,
+            x,
+            self.y,
+            pdf_string(text)
+        ));
+    }
+
+    fn line(&mut self, x1: f32, x2: f32) {
+        self.current.push_str(&format!(
+            "0.6 G 0.5 w {:.2} {:.2} m {:.2} {:.2} l S 0 G\n",
+            x1, self.y, x2, self.y
+        ));
+    }
+
+    fn paragraph(&mut self, text: &str, font: Font, size: f32, indent: f32) {
+        for line in wrap(text, font, size, Self::content_width() - indent) {
+            self.reserve(size * LEADING);
+            self.y -= size * LEADING;
+            self.text(MARGIN + indent, &line, font, size);
+        }
+    }
+
+    fn list_item(&mut self, marker: &str, text: &str) {
+        let lines = wrap(
+            text,
+            Font::Regular,
+            BODY_SIZE,
+            Self::content_width() - LIST_INDENT,
+        );
+        for (i, line) in lines.iter().enumerate() {
+            self.reserve(BODY_SIZE * LEADING);
+            self.y -= BODY_SIZE * LEADING;
+            if i == 0 {
+                self.text(MARGIN + 4.0, marker, Font::Regular, BODY_SIZE);
+            }
+            self.text(MARGIN + LIST_INDENT, line, Font::Regular, BODY_SIZE);
+        }
+    }
+
+    fn table_row(&mut self, cells: &[String], font: Font) {
+        if cells.is_empty() {
+            return;
+        }
+        let column = Self::content_width() / cells.len() as f32;
+        let wrapped: Vec<Vec<String>> = cells
+            .iter()
+            .map(|cell| wrap(cell, font, BODY_SIZE, column - 2.0 * CELL_PADDING))
+            .collect();
+        let lines = wrapped.iter().map(Vec::len).max().unwrap_or(1).max(1);
+        let height = lines as f32 * BODY_SIZE * LEADING + CELL_PADDING;
+
+        self.reserve(height);
+        let top = self.y;
+        for (i, cell) in wrapped.iter().enumerate() {
+            self.y = top;
+            for line in cell {
+                self.y -= BODY_SIZE * LEADING;
+                self.text(
+                    MARGIN + i as f32 * column + CELL_PADDING,
+                    line,
+                    font,
+                    BODY_SIZE,
+                );
+            }
+        }
+        self.y = top - height;
+        self.line(MARGIN, PAGE_WIDTH - MARGIN);
+    }
+
+    fn rule(&mut self) {
+        self.reserve(BODY_SIZE);
+        self.y -= BODY_SIZE / 2.0;
+        self.line(MARGIN, PAGE_WIDTH - MARGIN);
+        self.y -= BODY_SIZE / 2.0;
+    }
+
+    fn finish(mut self, title: &str) -> Vec<u8> {
+        if !self.current.is_empty() || self.pages.is_empty() {
+            self.pages.push(std::mem::take(&mut self.current));
+        }
+        let count = self.pages.len();
+
+        // Catalog, page tree and fonts come first; each page then takes two
+        // objects, the page and its content
+        let mut objects = vec![
+            "<< /Type /Catalog /Pages 2 0 R >>".to_string(),
+            format!(
+                "<< /Type /Pages /Kids [{}] /Count {} >>",
+                (0..count)
+                    .map(|i| format!("{} 0 R", 7 + 2 * i))
+                    .collect::<Vec<_>>()
+                    .join(" "),
+                count
+            ),
+            font_object("Helvetica"),
+            font_object("Helvetica-Bold"),
+            font_object("Courier"),
+            format!("<< /Title ({}) >>", pdf_string(title)),
+        ];
+        for (i, content) in self.pages.iter().enumerate() {
+            let footer = format!("{} \u{2013} page {} of {}", title, i + 1, count);
+            let footer_width = Font::Regular.width(&footer, 8.0);
+            let stream = format!(
+                "{}BT /F1 8.0 Tf {:.2} {:.2} Td ({}) Tj ET\n",
+                content,
+                (PAGE_WIDTH - footer_width) / 2.0,
+                MARGIN / 2.0,
+                pdf_string(&footer)
+            );
+            objects.push(format!(
+                concat!(
+                    "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {} {}] ",
+                    "/Resources << /Font << /F1 3 0 R /F2 4 0 R /F3 5 0 R >> >> ",
+                    "/Contents {} 0 R >>"
+                ),
+                PAGE_WIDTH,
+                PAGE_HEIGHT,
+                8 + 2 * i
+            ));
+            objects.push(format!(
+                "<< /Length {} >>\nstream\n{}\nendstream",
+                stream.len(),
+                stream
+            ));
+        }
+
+        let mut pdf = b"%PDF-1.4\n%\xe2\xe3\xcf\xd3\n".to_vec();
+        let mut offsets = Vec::with_capacity(objects.len());
+        for (i, object) in objects.iter().enumerate() {
+            offsets.push(pdf.len());
+            pdf.extend_from_slice(format!("{} 0 obj\n{}\nendobj\n", i + 1, object).as_bytes());
+        }
+        let xref = pdf.len();
+        pdf.extend_from_slice(
+            format!("xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1).as_bytes(),
+        );
+        for offset in offsets {
+            pdf.extend_from_slice(format!("{:010} 00000 n \n", offset).as_bytes());
+        }
+        pdf.extend_from_slice(
+            format!(
+                "trailer\n<< /Size {} /Root 1 0 R /Info 6 0 R >>\nstartxref\n{}\n%%EOF\n",
+                objects.len() + 1,
+                xref
+            )
+            .as_bytes(),
+        );
+        pdf
+    }
+}
+
+fn font_object(name: &str) -> String {
+    format!(
+        "<< /Type /Font /Subtype /Type1 /BaseFont /{} /Encoding /WinAnsiEncoding >>",
+        name
+    )
+}
+
+/// Breaks text into lines no wider than `width`, splitting words that are
+/// longer than a line.
+fn wrap(text: &str, font: Font, size: f32, width: f32) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut line = String::new();
+    for word in text.split_whitespace() {
+        let candidate = if line.is_empty() {
+            word.to_string()
+        } else {
+            format!("{} {}", line, word)
+        };
+        if font.width(&candidate, size) <= width {
+            line = candidate;
+            continue;
+        }
+        if !line.is_empty() {
+            lines.push(std::mem::take(&mut line));
+        }
+        for c in word.chars() {
+            line.push(c);
+            if font.width(&line, size) > width && line.chars().count() > 1 {
+                line.pop();
+                lines.push(std::mem::replace(&mut line, c.to_string()));
+            }
+        }
+    }
+    if !line.is_empty() || lines.is_empty() {
+        lines.push(line);
+    }
+    lines
+}
+
+/// A PDF literal string in Windows-1252, the standard fonts' encoding.
+fn pdf_string(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        let byte = match c {
+            '\u{20}'..='\u{7e}' => c as u8,
+            '\u{a0}'..='\u{ff}' => c as u32 as u8,
+            '\u{20ac}' => 0x80,
+            '\u{2026}' => 0x85,
+            '\u{2013}' => 0x96,
+            '\u{2014}' => 0x97,
+            '\u{2018}' => 0x91,
+            '\u{2019}' => 0x92,
+            '\u{201c}' => 0x93,
+            '\u{201d}' => 0x94,
+            '\u{2022}' => 0x95,
+            '\u{2122}' => 0x99,
+            _ => b'?',
+        };
+        match byte {
+            b'(' | b')' | b'\\' => {
+                out.push('\\');
+                out.push(byte as char);
+            }
+            0x20..=0x7e => out.push(byte as char),
+            _ => out.push_str(&format!("\\{:03o}", byte)),
+        }
+    }
+    out
+}
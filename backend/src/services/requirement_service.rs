@@ -0,0 +1,364 @@
+use sqlx::{PgConnection, PgPool};
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::errors::ServiceError;
+use crate::models::audit::{AuditContext, AuditEntity};
+use crate::models::requirement::{
+    Requirement, RequirementCreate, RequirementFilter, RequirementPriority, RequirementRevision,
+    RequirementStatus, RequirementType, RequirementUpdate,
+};
+use crate::models::traceability::TraceEntity;
+use crate::models::user::UserRole;
+use crate::models::version::Versioned;
+use crate::services::audit_service::AuditService;
+use crate::services::project_service::ProjectService;
+use crate::services::traceability_service::TraceabilityService;
+
+pub struct RequirementService;
+
+impl RequirementService {
+    /// Adds a requirement under the project's next number.
+    pub async fn create(
+        new_requirement: RequirementCreate,
+        user_id: Uuid,
+        role: &UserRole,
+        audit: &AuditContext,
+        pool: &PgPool,
+    ) -> Result<Requirement, ServiceError> {
+        new_requirement.validate()?;
+
+        let mut tx = pool.begin().await?;
+        if !ProjectService::is_visible_to(new_requirement.project_id, user_id, role, &mut tx)
+            .await?
+        {
+            return Err(ServiceError::NotFound("Project not found".into()));
+        }
+        ProjectService::lock(new_requirement.project_id, &mut tx)
+            .await?
+            .ensure_writable()?;
+
+        let number = sqlx::query_scalar!(
+            r#"
+            UPDATE projects SET last_requirement_number = last_requirement_number + 1
+            WHERE id = $1
+            RETURNING last_requirement_number
+            "#,
+            new_requirement.project_id
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+        let id = sqlx::query_scalar!(
+            r#"
+            INSERT INTO requirements (
+                project_id, number, title, description, type, priority, status,
+                acceptance_criteria, created_by
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+            RETURNING id
+            "#,
+            new_requirement.project_id,
+            number,
+            new_requirement.title,
+            new_requirement.description,
+            new_requirement.requirement_type as RequirementType,
+            new_requirement
+                .priority
+                .unwrap_or(RequirementPriority::Medium) as RequirementPriority,
+            new_requirement.status.unwrap_or(RequirementStatus::Draft) as RequirementStatus,
+            new_requirement.acceptance_criteria,
+            user_id
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+        Self::record_revision(id, user_id, &mut tx).await?;
+        let requirement = Self::fetch(id, false, &mut tx).await?;
+
+        AuditService::record_create(
+            &mut tx,
+            audit,
+            AuditEntity::Requirement,
+            requirement.id,
+            &requirement,
+        )
+        .await?;
+        tx.commit().await?;
+
+        Ok(requirement)
+    }
+
+    /// A project's requirements in number order.
+    pub async fn list(
+        filter: &RequirementFilter,
+        user_id: Uuid,
+        role: &UserRole,
+        pool: &PgPool,
+    ) -> Result<Vec<Requirement>, ServiceError> {
+        let mut conn = pool.acquire().await?;
+        if !ProjectService::is_visible_to(filter.project_id, user_id, role, &mut conn).await? {
+            return Err(ServiceError::NotFound("Project not found".into()));
+        }
+
+        let requirements = sqlx::query_as!(
+            Requirement,
+            r#"
+            SELECT id, project_id, code as "code!", title, description,
+                   type as "requirement_type: RequirementType",
+                   priority as "priority: RequirementPriority",
+                   status as "status: RequirementStatus",
+                   acceptance_criteria, created_by, created_at, updated_at, version
+            FROM requirements
+            WHERE project_id = $1
+              AND ($2::requirement_type IS NULL OR type = $2)
+              AND ($3::requirement_priority IS NULL OR priority = $3)
+              AND ($4::requirement_status IS NULL OR status = $4)
+            ORDER BY number
+            "#,
+            filter.project_id,
+            filter.requirement_type as Option<RequirementType>,
+            filter.priority as Option<RequirementPriority>,
+            filter.status as Option<RequirementStatus>
+        )
+        .fetch_all(&mut *conn)
+        .await?;
+
+        Ok(requirements)
+    }
+
+    pub async fn get(
+        id: Uuid,
+        user_id: Uuid,
+        role: &UserRole,
+        pool: &PgPool,
+    ) -> Result<Requirement, ServiceError> {
+        let mut conn = pool.acquire().await?;
+        let requirement = Self::fetch(id, false, &mut conn).await?;
+        Self::ensure_visible(&requirement, user_id, role, &mut conn).await?;
+
+        Ok(requirement)
+    }
+
+    /// Changes a requirement, making a new version of it. A patch that
+    /// changes nothing leaves the version as it is.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn update(
+        id: Uuid,
+        update: RequirementUpdate,
+        expected_version: Option<i32>,
+        user_id: Uuid,
+        role: &UserRole,
+        audit: &AuditContext,
+        pool: &PgPool,
+    ) -> Result<Requirement, ServiceError> {
+        update.validate()?;
+
+        let mut tx = pool.begin().await?;
+        let existing = Self::fetch(id, true, &mut tx).await?;
+        Self::ensure_visible(&existing, user_id, role, &mut tx).await?;
+        existing.check_version(expected_version)?;
+        ProjectService::lock(existing.project_id, &mut tx)
+            .await?
+            .ensure_writable()?;
+
+        let title = update
+            .title
+            .apply_required("title", existing.title.clone())?;
+        let description = update.description.apply(existing.description.clone());
+        let requirement_type = update
+            .requirement_type
+            .apply_required("type", existing.requirement_type)?;
+        let priority = update
+            .priority
+            .apply_required("priority", existing.priority)?;
+        let status = update.status.apply_required("status", existing.status)?;
+        let acceptance_criteria = update
+            .acceptance_criteria
+            .apply(existing.acceptance_criteria.clone());
+        if title == existing.title
+            && description == existing.description
+            && requirement_type == existing.requirement_type
+            && priority == existing.priority
+            && status == existing.status
+            && acceptance_criteria == existing.acceptance_criteria
+        {
+            return Ok(existing);
+        }
+
+        sqlx::query!(
+            r#"
+            UPDATE requirements
+            SET title = $2, description = $3, type = $4, priority = $5, status = $6,
+                acceptance_criteria = $7, updated_at = NOW(), version = version + 1
+            WHERE id = $1
+            "#,
+            id,
+            title,
+            description,
+            requirement_type as RequirementType,
+            priority as RequirementPriority,
+            status as RequirementStatus,
+            acceptance_criteria
+        )
+        .execute(&mut *tx)
+        .await?;
+        Self::record_revision(id, user_id, &mut tx).await?;
+        let updated = Self::fetch(id, false, &mut tx).await?;
+
+        AuditService::record_update(
+            &mut tx,
+            audit,
+            AuditEntity::Requirement,
+            id,
+            &existing,
+            &updated,
+        )
+        .await?;
+        tx.commit().await?;
+
+        Ok(updated)
+    }
+
+    /// Removes a requirement with its versions and trace links. Its number
+    /// is not reused.
+    pub async fn delete(
+        id: Uuid,
+        expected_version: Option<i32>,
+        user_id: Uuid,
+        role: &UserRole,
+        audit: &AuditContext,
+        pool: &PgPool,
+    ) -> Result<(), ServiceError> {
+        let mut tx = pool.begin().await?;
+        let existing = Self::fetch(id, true, &mut tx).await?;
+        Self::ensure_visible(&existing, user_id, role, &mut tx).await?;
+        existing.check_version(expected_version)?;
+        ProjectService::lock(existing.project_id, &mut tx)
+            .await?
+            .ensure_writable()?;
+
+        TraceabilityService::remove_links_in(TraceEntity::Requirement, &[id], &mut tx).await?;
+        sqlx::query!("DELETE FROM requirements WHERE id = $1", id)
+            .execute(&mut *tx)
+            .await?;
+        AuditService::record_delete(&mut tx, audit, AuditEntity::Requirement, id, &existing)
+            .await?;
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    /// Every version of a requirement, oldest first; the last is current.
+    pub async fn revisions(
+        id: Uuid,
+        user_id: Uuid,
+        role: &UserRole,
+        pool: &PgPool,
+    ) -> Result<Vec<RequirementRevision>, ServiceError> {
+        let mut conn = pool.acquire().await?;
+        let requirement = Self::fetch(id, false, &mut conn).await?;
+        Self::ensure_visible(&requirement, user_id, role, &mut conn).await?;
+
+        let revisions = sqlx::query_as!(
+            RequirementRevision,
+            r#"
+            SELECT requirement_id, version, title, description,
+                   type as "requirement_type: RequirementType",
+                   priority as "priority: RequirementPriority",
+                   status as "status: RequirementStatus",
+                   acceptance_criteria, changed_by, created_at
+            FROM requirement_revisions
+            WHERE requirement_id = $1
+            ORDER BY version
+            "#,
+            id
+        )
+        .fetch_all(&mut *conn)
+        .await?;
+
+        Ok(revisions)
+    }
+
+    /// Copies the requirement's current content into its revisions.
+    async fn record_revision(
+        id: Uuid,
+        user_id: Uuid,
+        conn: &mut PgConnection,
+    ) -> Result<(), ServiceError> {
+        sqlx::query!(
+            r#"
+            INSERT INTO requirement_revisions (
+                requirement_id, version, title, description, type, priority, status,
+                acceptance_criteria, changed_by
+            )
+            SELECT id, version, title, description, type, priority, status,
+                   acceptance_criteria, $2
+            FROM requirements
+            WHERE id = $1
+            "#,
+            id,
+            user_id
+        )
+        .execute(conn)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Loads a requirement, optionally locking it.
+    async fn fetch(
+        id: Uuid,
+        for_update: bool,
+        conn: &mut PgConnection,
+    ) -> Result<Requirement, ServiceError> {
+        let requirement = if for_update {
+            sqlx::query_as!(
+                Requirement,
+                r#"
+                SELECT id, project_id, code as "code!", title, description,
+                       type as "requirement_type: RequirementType",
+                       priority as "priority: RequirementPriority",
+                       status as "status: RequirementStatus",
+                       acceptance_criteria, created_by, created_at, updated_at, version
+                FROM requirements
+                WHERE id = $1
+                FOR UPDATE
+                "#,
+                id
+            )
+            .fetch_optional(conn)
+            .await?
+        } else {
+            sqlx::query_as!(
+                Requirement,
+                r#"
+                SELECT id, project_id, code as "code!", title, description,
+                       type as "requirement_type: RequirementType",
+                       priority as "priority: RequirementPriority",
+                       status as "status: RequirementStatus",
+                       acceptance_criteria, created_by, created_at, updated_at, version
+                FROM requirements
+                WHERE id = $1
+                "#,
+                id
+            )
+            .fetch_optional(conn)
+            .await?
+        };
+
+        requirement.ok_or(ServiceError::NotFound("Requirement not found".into()))
+    }
+
+    /// Requirements are hidden along with their project.
+    async fn ensure_visible(
+        requirement: &Requirement,
+        user_id: Uuid,
+        role: &UserRole,
+        conn: &mut PgConnection,
+    ) -> Result<(), ServiceError> {
+        if ProjectService::is_visible_to(requirement.project_id, user_id, role, conn).await? {
+            Ok(())
+        } else {
+            Err(ServiceError::NotFound("Requirement not found".into()))
+        }
+    }
+}
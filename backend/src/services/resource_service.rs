@@ -0,0 +1,216 @@
+use crate::errors::ServiceError;
+use crate::models::audit::{AuditContext, AuditEntity};
+use crate::models::pagination::{Page, PageParams};
+use crate::models::resource::{Resource, ResourceCreate, ResourceUpdate, RESOURCE_SORT_FIELDS};
+use crate::services::audit_service::AuditService;
+use sqlx::{PgConnection, PgPool, QueryBuilder};
+use uuid::Uuid;
+use validator::Validate;
+
+pub struct ResourceService;
+
+impl ResourceService {
+    pub async fn get_all(
+        params: &PageParams,
+        pool: &PgPool,
+    ) -> Result<Page<Resource>, ServiceError> {
+        let order_by = params.order_by(RESOURCE_SORT_FIELDS, "name")?;
+
+        let total = sqlx::query_scalar!(r#"SELECT COUNT(*) as "count!" FROM resources"#)
+            .fetch_one(pool)
+            .await?;
+
+        let mut query = QueryBuilder::new(
+            r#"
+            SELECT id, name, email, role, skills, availability, hourly_rate, created_at, updated_at
+            FROM resources"#,
+        );
+        query
+            .push(order_by)
+            .push(" LIMIT ")
+            .push_bind(params.per_page())
+            .push(" OFFSET ")
+            .push_bind(params.offset()?);
+
+        let resources = query.build_query_as::<Resource>().fetch_all(pool).await?;
+
+        Ok(Page::new(resources, total, params))
+    }
+
+    pub async fn get_by_id(id: Uuid, pool: &PgPool) -> Result<Resource, ServiceError> {
+        let resource = sqlx::query_as!(
+            Resource,
+            r#"
+            SELECT id, name, email, role, skills, availability, hourly_rate, created_at, updated_at
+            FROM resources
+            WHERE id = $1
+            "#,
+            id
+        )
+        .fetch_optional(pool)
+        .await?
+        .ok_or(ServiceError::NotFound("Resource not found".into()))?;
+
+        Ok(resource)
+    }
+
+    /// The resources among `ids`; ids that are not resources are ignored.
+    pub async fn get_by_ids(ids: &[Uuid], pool: &PgPool) -> Result<Vec<Resource>, ServiceError> {
+        let resources = sqlx::query_as!(
+            Resource,
+            r#"
+            SELECT id, name, email, role, skills, availability, hourly_rate, created_at, updated_at
+            FROM resources
+            WHERE id = ANY($1)
+            ORDER BY name
+            "#,
+            ids
+        )
+        .fetch_all(pool)
+        .await?;
+
+        Ok(resources)
+    }
+
+    /// Locks the row for the rest of the transaction and returns it.
+    async fn lock(id: Uuid, conn: &mut PgConnection) -> Result<Resource, ServiceError> {
+        let resource = sqlx::query_as!(
+            Resource,
+            r#"
+            SELECT id, name, email, role, skills, availability, hourly_rate, created_at, updated_at
+            FROM resources
+            WHERE id = $1
+            FOR UPDATE
+            "#,
+            id
+        )
+        .fetch_optional(conn)
+        .await?
+        .ok_or(ServiceError::NotFound("Resource not found".into()))?;
+
+        Ok(resource)
+    }
+
+    pub async fn create(
+        resource: ResourceCreate,
+        audit: &AuditContext,
+        pool: &PgPool,
+    ) -> Result<Resource, ServiceError> {
+        let mut tx = pool.begin().await?;
+        let resource = Self::create_in(resource, audit, &mut tx).await?;
+        tx.commit().await?;
+
+        Ok(resource)
+    }
+
+    /// `create` within the caller's transaction.
+    pub(crate) async fn create_in(
+        resource: ResourceCreate,
+        audit: &AuditContext,
+        conn: &mut PgConnection,
+    ) -> Result<Resource, ServiceError> {
+        resource.validate()?;
+
+        let resource = sqlx::query_as!(
+            Resource,
+            r#"
+            INSERT INTO resources (name, email, role, skills, availability, hourly_rate)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            RETURNING id, name, email, role, skills, availability, hourly_rate, created_at, updated_at
+            "#,
+            resource.name,
+            resource.email,
+            resource.role,
+            &resource.skills,
+            resource.availability,
+            resource.hourly_rate
+        )
+        .fetch_one(&mut *conn)
+        .await?;
+
+        AuditService::record_create(conn, audit, AuditEntity::Resource, resource.id, &resource)
+            .await?;
+
+        Ok(resource)
+    }
+
+    pub async fn update(
+        id: Uuid,
+        update: ResourceUpdate,
+        audit: &AuditContext,
+        pool: &PgPool,
+    ) -> Result<Resource, ServiceError> {
+        let mut tx = pool.begin().await?;
+        let current = Self::lock(id, &mut tx).await?;
+
+        // Merge the patch into the current state
+        let name = update.name.apply_required("name", current.name.clone())?;
+        let email = update
+            .email
+            .apply_required("email", current.email.clone())?;
+        let role = update.role.apply_required("role", current.role.clone())?;
+        let skills = update
+            .skills
+            .apply(Some(current.skills.clone()))
+            .unwrap_or_default();
+        let availability = update
+            .availability
+            .apply_required("availability", current.availability.clone())?;
+        let hourly_rate = update
+            .hourly_rate
+            .apply_required("hourly_rate", current.hourly_rate.clone())?;
+
+        let resource = sqlx::query_as!(
+            Resource,
+            r#"
+            UPDATE resources
+            SET
+                name = $1,
+                email = $2,
+                role = $3,
+                skills = $4,
+                availability = $5,
+                hourly_rate = $6,
+                updated_at = CURRENT_TIMESTAMP
+            WHERE id = $7
+            RETURNING id, name, email, role, skills, availability, hourly_rate, created_at, updated_at
+            "#,
+            name,
+            email,
+            role,
+            &skills,
+            availability,
+            hourly_rate,
+            id
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+
+        AuditService::record_update(
+            &mut tx,
+            audit,
+            AuditEntity::Resource,
+            id,
+            &current,
+            &resource,
+        )
+        .await?;
+        tx.commit().await?;
+
+        Ok(resource)
+    }
+
+    pub async fn delete(id: Uuid, audit: &AuditContext, pool: &PgPool) -> Result<(), ServiceError> {
+        let mut tx = pool.begin().await?;
+        let current = Self::lock(id, &mut tx).await?;
+
+        sqlx::query!("DELETE FROM resources WHERE id = $1", id)
+            .execute(&mut *tx)
+            .await?;
+
+        AuditService::record_delete(&mut tx, audit, AuditEntity::Resource, id, &current).await?;
+        tx.commit().await?;
+
+        Ok(())
+    }
+}
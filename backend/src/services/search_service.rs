@@ -0,0 +1,112 @@
+use crate::errors::ServiceError;
+use crate::models::search::{SearchHit, SearchResults};
+use crate::models::user::UserRole;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+const DEFAULT_LIMIT: i64 = 10;
+const MAX_LIMIT: i64 = 50;
+
+pub struct SearchService;
+
+impl SearchService {
+    /// Ranks full-text matches across projects, tasks and phase transition
+    /// descriptions. Only projects visible to the caller are searched.
+    /// Snippets escape the matched text, so they are safe to render as HTML.
+    pub async fn search(
+        query: &str,
+        limit: Option<i64>,
+        user_id: Uuid,
+        role: &UserRole,
+        pool: &PgPool,
+    ) -> Result<SearchResults, ServiceError> {
+        let query = query.trim();
+        if query.is_empty() {
+            return Err(ServiceError::invalid_field(
+                "q",
+                "required",
+                "Search query must not be empty",
+            ));
+        }
+        let limit = limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT);
+        let sees_all = role.sees_all_projects();
+
+        let projects = sqlx::query_as!(
+            SearchHit,
+            r#"
+            SELECT
+                p.id, p.id AS project_id, p.name AS project_name, p.name AS title,
+                ts_headline('english', html_escape(p.name || ' ' || coalesce(p.description, '')), q,
+                            'StartSel=<mark>, StopSel=</mark>, MaxFragments=2') AS "snippet!",
+                ts_rank(p.search_vector, q) AS "rank!"
+            FROM projects p, websearch_to_tsquery('english', $1) q
+            WHERE p.search_vector @@ q AND p.deleted_at IS NULL
+              AND project_visible_to(p.id, $3, $2)
+            ORDER BY 6 DESC
+            LIMIT $4
+            "#,
+            query,
+            sees_all,
+            user_id,
+            limit
+        )
+        .fetch_all(pool)
+        .await?;
+
+        let tasks = sqlx::query_as!(
+            SearchHit,
+            r#"
+            SELECT
+                t.id, t.project_id, p.name AS project_name, t.name AS title,
+                ts_headline('english', html_escape(t.name || ' ' || coalesce(t.description, '')), q,
+                            'StartSel=<mark>, StopSel=</mark>, MaxFragments=2') AS "snippet!",
+                ts_rank(t.search_vector, q) AS "rank!"
+            FROM tasks t
+            JOIN projects p ON p.id = t.project_id,
+            websearch_to_tsquery('english', $1) q
+            WHERE t.search_vector @@ q AND t.deleted_at IS NULL AND p.deleted_at IS NULL
+              AND project_visible_to(p.id, $3, $2)
+            ORDER BY 6 DESC
+            LIMIT $4
+            "#,
+            query,
+            sees_all,
+            user_id,
+            limit
+        )
+        .fetch_all(pool)
+        .await?;
+
+        let phase_transitions = sqlx::query_as!(
+            SearchHit,
+            r#"
+            SELECT
+                pt.id, pt.project_id, p.name AS project_name,
+                p.name || ': ' || pt.phase::text AS "title!",
+                ts_headline('english', html_escape(pt.description), q,
+                            'StartSel=<mark>, StopSel=</mark>, MaxFragments=2') AS "snippet!",
+                ts_rank(pt.search_vector, q) AS "rank!"
+            FROM phase_transitions pt
+            JOIN projects p ON p.id = pt.project_id,
+            websearch_to_tsquery('english', $1) q
+            WHERE pt.search_vector @@ q AND p.deleted_at IS NULL
+              AND project_visible_to(p.id, $3, $2)
+            ORDER BY 6 DESC
+            LIMIT $4
+            "#,
+            query,
+            sees_all,
+            user_id,
+            limit
+        )
+        .fetch_all(pool)
+        .await?;
+
+        Ok(SearchResults {
+            query: query.to_string(),
+            projects,
+            tasks,
+            phase_transitions,
+        })
+    }
+}
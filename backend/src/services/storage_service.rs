@@ -0,0 +1,366 @@
+use actix_web::web::Bytes;
+use chrono::{DateTime, Utc};
+use futures::future::BoxFuture;
+use reqwest::{Method, StatusCode, Url};
+use ring::hmac;
+use sha2::{Digest, Sha256};
+use std::env;
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+
+use crate::errors::ServiceError;
+
+pub type StorageFuture<'a, T> = BoxFuture<'a, Result<T, ServiceError>>;
+
+/// Where document content is kept. Keys are generated by the application
+/// and look like `{document_id}/{version_id}`.
+pub trait DocumentStorage: Send + Sync {
+    fn put<'a>(
+        &'a self,
+        key: &'a str,
+        content_type: &'a str,
+        content: Bytes,
+    ) -> StorageFuture<'a, ()>;
+
+    fn get<'a>(&'a self, key: &'a str) -> StorageFuture<'a, Bytes>;
+
+    /// Deleting an object that does not exist succeeds.
+    fn delete<'a>(&'a self, key: &'a str) -> StorageFuture<'a, ()>;
+}
+
+/// Storage configured by `DOCUMENT_STORAGE`: `local` (the default) keeps
+/// files under `DOCUMENT_STORAGE_DIR`, `s3` uses the bucket described by the
+/// `S3_*` variables.
+pub fn from_env() -> Result<Arc<dyn DocumentStorage>, String> {
+    let backend = env::var("DOCUMENT_STORAGE").unwrap_or_else(|_| "local".to_string());
+    match backend.as_str() {
+        "local" => {
+            let root =
+                env::var("DOCUMENT_STORAGE_DIR").unwrap_or_else(|_| "./data/documents".to_string());
+            Ok(Arc::new(LocalStorage::new(root)))
+        }
+        "s3" => {
+            let required = |name: &str| {
+                env::var(name)
+                    .ok()
+                    .filter(|value| !value.is_empty())
+                    .ok_or_else(|| format!("{} must be set for S3 document storage", name))
+            };
+            Ok(Arc::new(S3Storage::new(S3Config {
+                endpoint: required("S3_ENDPOINT")?,
+                bucket: required("S3_BUCKET")?,
+                region: env::var("S3_REGION").unwrap_or_else(|_| "us-east-1".to_string()),
+                access_key_id: required("S3_ACCESS_KEY_ID")?,
+                secret_access_key: required("S3_SECRET_ACCESS_KEY")?,
+            })?))
+        }
+        other => Err(format!("invalid DOCUMENT_STORAGE '{}'", other)),
+    }
+}
+
+/// Hex SHA-256 of `data`.
+pub fn sha256_hex(data: &[u8]) -> String {
+    hex(&Sha256::digest(data))
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// Files in a directory on the server, one per key.
+pub struct LocalStorage {
+    root: PathBuf,
+}
+
+impl LocalStorage {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        LocalStorage { root: root.into() }
+    }
+
+    fn path(&self, key: &str) -> Result<PathBuf, ServiceError> {
+        // Keys are generated, but never let one escape the root
+        let relative = Path::new(key);
+        if key.is_empty()
+            || !relative
+                .components()
+                .all(|component| matches!(component, Component::Normal(_)))
+        {
+            return Err(ServiceError::StorageError(format!(
+                "invalid storage key '{}'",
+                key
+            )));
+        }
+        Ok(self.root.join(relative))
+    }
+}
+
+fn io_error(key: &str, e: std::io::Error) -> ServiceError {
+    ServiceError::StorageError(format!("{}: {}", key, e))
+}
+
+impl DocumentStorage for LocalStorage {
+    fn put<'a>(
+        &'a self,
+        key: &'a str,
+        _content_type: &'a str,
+        content: Bytes,
+    ) -> StorageFuture<'a, ()> {
+        Box::pin(async move {
+            let path = self.path(key)?;
+            if let Some(parent) = path.parent() {
+                tokio::fs::create_dir_all(parent)
+                    .await
+                    .map_err(|e| io_error(key, e))?;
+            }
+            // Write aside and rename, so readers never see a partial file
+            let partial = path.with_extension("partial");
+            tokio::fs::write(&partial, &content)
+                .await
+                .map_err(|e| io_error(key, e))?;
+            tokio::fs::rename(&partial, &path)
+                .await
+                .map_err(|e| io_error(key, e))
+        })
+    }
+
+    fn get<'a>(&'a self, key: &'a str) -> StorageFuture<'a, Bytes> {
+        Box::pin(async move {
+            let content = tokio::fs::read(self.path(key)?)
+                .await
+                .map_err(|e| io_error(key, e))?;
+            Ok(Bytes::from(content))
+        })
+    }
+
+    fn delete<'a>(&'a self, key: &'a str) -> StorageFuture<'a, ()> {
+        Box::pin(async move {
+            let path = self.path(key)?;
+            match tokio::fs::remove_file(&path).await {
+                Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(io_error(key, e)),
+                _ => {}
+            }
+            // Drop the document's directory with its last version; this
+            // fails harmlessly while others remain
+            if let Some(parent) = path.parent().filter(|parent| *parent != self.root) {
+                tokio::fs::remove_dir(parent).await.ok();
+            }
+            Ok(())
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct S3Config {
+    /// Base URL of the service, e.g. `https://s3.eu-west-1.amazonaws.com`
+    /// or `http://localhost:9000` for MinIO
+    pub endpoint: String,
+    pub bucket: String,
+    pub region: String,
+    pub access_key_id: String,
+    pub secret_access_key: String,
+}
+
+/// An S3-compatible bucket, addressed path-style (`{endpoint}/{bucket}/{key}`)
+/// so MinIO and other stand-ins work without DNS setup. Requests are signed
+/// with AWS Signature Version 4.
+pub struct S3Storage {
+    config: S3Config,
+    endpoint: Url,
+    http: reqwest::Client,
+}
+
+impl S3Storage {
+    pub fn new(config: S3Config) -> Result<Self, String> {
+        let endpoint = Url::parse(&config.endpoint)
+            .ok()
+            .filter(|url| matches!(url.scheme(), "http" | "https") && url.host_str().is_some())
+            .ok_or_else(|| format!("invalid S3_ENDPOINT '{}'", config.endpoint))?;
+        let http = reqwest::Client::builder()
+            .timeout(std::time::Duration::from_secs(60))
+            .build()
+            .map_err(|e| format!("failed to build S3 client: {}", e))?;
+        Ok(S3Storage {
+            config,
+            endpoint,
+            http,
+        })
+    }
+
+    async fn send(
+        &self,
+        method: Method,
+        key: &str,
+        content_type: Option<&str>,
+        body: Bytes,
+    ) -> Result<reqwest::Response, ServiceError> {
+        let path = format!(
+            "{}/{}/{}",
+            self.endpoint.path().trim_end_matches('/'),
+            uri_encode(&self.config.bucket, false),
+            uri_encode(key, true)
+        );
+        let mut url = self.endpoint.clone();
+        url.set_path(&path);
+        let host = match url.port() {
+            Some(port) => format!("{}:{}", url.host_str().unwrap_or_default(), port),
+            None => url.host_str().unwrap_or_default().to_string(),
+        };
+
+        let now = Utc::now();
+        let amz_date = now.format("%Y%m%dT%H%M%SZ").to_string();
+        let payload_hash = sha256_hex(&body);
+        let mut headers = vec![
+            ("host", host.as_str()),
+            ("x-amz-content-sha256", payload_hash.as_str()),
+            ("x-amz-date", amz_date.as_str()),
+        ];
+        if let Some(content_type) = content_type {
+            headers.push(("content-type", content_type));
+        }
+        let authorization = sigv4_authorization(
+            &self.config,
+            "s3",
+            method.as_str(),
+            &path,
+            "",
+            &headers,
+            &payload_hash,
+            now,
+        );
+
+        let mut request = self
+            .http
+            .request(method, url)
+            .header("x-amz-content-sha256", &payload_hash)
+            .header("x-amz-date", &amz_date)
+            .header("authorization", authorization);
+        if let Some(content_type) = content_type {
+            request = request.header("content-type", content_type);
+        }
+        request
+            .body(body)
+            .send()
+            .await
+            .map_err(|e| ServiceError::StorageError(format!("{}: {}", key, e)))
+    }
+}
+
+async fn s3_error(key: &str, response: reqwest::Response) -> ServiceError {
+    let status = response.status();
+    let body = response.text().await.unwrap_or_default();
+    ServiceError::StorageError(format!("{}: S3 answered {}: {}", key, status, body))
+}
+
+impl DocumentStorage for S3Storage {
+    fn put<'a>(
+        &'a self,
+        key: &'a str,
+        content_type: &'a str,
+        content: Bytes,
+    ) -> StorageFuture<'a, ()> {
+        Box::pin(async move {
+            let response = self
+                .send(Method::PUT, key, Some(content_type), content)
+                .await?;
+            if !response.status().is_success() {
+                return Err(s3_error(key, response).await);
+            }
+            Ok(())
+        })
+    }
+
+    fn get<'a>(&'a self, key: &'a str) -> StorageFuture<'a, Bytes> {
+        Box::pin(async move {
+            let response = self.send(Method::GET, key, None, Bytes::new()).await?;
+            if !response.status().is_success() {
+                return Err(s3_error(key, response).await);
+            }
+            response
+                .bytes()
+                .await
+                .map_err(|e| ServiceError::StorageError(format!("{}: {}", key, e)))
+        })
+    }
+
+    fn delete<'a>(&'a self, key: &'a str) -> StorageFuture<'a, ()> {
+        Box::pin(async move {
+            let response = self.send(Method::DELETE, key, None, Bytes::new()).await?;
+            if !response.status().is_success() && response.status() != StatusCode::NOT_FOUND {
+                return Err(s3_error(key, response).await);
+            }
+            Ok(())
+        })
+    }
+}
+
+/// Percent-encodes everything but unreserved characters, and `/` when
+/// encoding a path.
+fn uri_encode(value: &str, keep_slash: bool) -> String {
+    value
+        .bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                (byte as char).to_string()
+            }
+            b'/' if keep_slash => "/".to_string(),
+            _ => format!("%{:02X}", byte),
+        })
+        .collect()
+}
+
+/// `Authorization` header for a request signed with AWS Signature Version 4.
+/// `headers` are the signed headers with lowercase names; `path` and `query`
+/// must already be in canonical (encoded, sorted) form.
+#[allow(clippy::too_many_arguments)]
+pub fn sigv4_authorization(
+    config: &S3Config,
+    service: &str,
+    method: &str,
+    path: &str,
+    query: &str,
+    headers: &[(&str, &str)],
+    payload_hash: &str,
+    now: DateTime<Utc>,
+) -> String {
+    let amz_date = now.format("%Y%m%dT%H%M%SZ").to_string();
+    let date = now.format("%Y%m%d").to_string();
+
+    let mut headers = headers.to_vec();
+    headers.sort_by_key(|(name, _)| *name);
+    let canonical_headers: String = headers
+        .iter()
+        .map(|(name, value)| format!("{}:{}\n", name, value.trim()))
+        .collect();
+    let signed_headers = headers
+        .iter()
+        .map(|(name, _)| *name)
+        .collect::<Vec<_>>()
+        .join(";");
+    let canonical_request = format!(
+        "{}\n{}\n{}\n{}\n{}\n{}",
+        method, path, query, canonical_headers, signed_headers, payload_hash
+    );
+
+    let scope = format!("{}/{}/{}/aws4_request", date, config.region, service);
+    let string_to_sign = format!(
+        "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+        amz_date,
+        scope,
+        sha256_hex(canonical_request.as_bytes())
+    );
+
+    let sign = |key: &[u8], data: &str| {
+        hmac::sign(&hmac::Key::new(hmac::HMAC_SHA256, key), data.as_bytes())
+    };
+    let secret = format!("AWS4{}", config.secret_access_key);
+    let key = sign(secret.as_bytes(), &date);
+    let key = sign(key.as_ref(), &config.region);
+    let key = sign(key.as_ref(), service);
+    let key = sign(key.as_ref(), "aws4_request");
+    let signature = hex(sign(key.as_ref(), &string_to_sign).as_ref());
+
+    format!(
+        "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+        config.access_key_id, scope, signed_headers, signature
+    )
+}
@@ -1,65 +1,244 @@
 use crate::errors::ServiceError;
-use crate::models::task::{Task, TaskCreate, TaskStatus, TaskUpdate};
-use sqlx::PgPool;
+use crate::models::audit::{AuditContext, AuditEntity};
+use crate::models::pagination::{Page, PageParams};
+use crate::models::patch::Patch;
+use crate::models::realtime::ChangeKind;
+use crate::models::task::{
+    BulkTaskOperation, BulkTaskRequest, BulkTaskResponse, BulkTaskResult, DependencyType, Task,
+    TaskCreate, TaskFilter, TaskLink, TaskStatus, TaskUpdate, MAX_BULK_OPERATIONS,
+    TASK_SORT_FIELDS,
+};
+use crate::models::version::Versioned;
+use crate::models::webhook::WebhookEvent;
+use crate::services::audit_service::AuditService;
+use crate::services::notification_service::NotificationService;
+use crate::services::realtime_service::RealtimeService;
+use crate::services::webhook_service::WebhookService;
+use chrono::{DateTime, Utc};
+use sqlx::{PgConnection, PgPool, Postgres, QueryBuilder};
 use uuid::Uuid;
+use validator::Validate;
 
 pub struct TaskService;
 
 impl TaskService {
-    pub async fn get_all(db: &PgPool) -> Result<Vec<Task>, ServiceError> {
-        let tasks = sqlx::query_as!(
+    pub async fn get_all(
+        params: &PageParams,
+        filter: &TaskFilter,
+        db: &PgPool,
+    ) -> Result<Page<Task>, ServiceError> {
+        Self::list(params, filter, false, db).await
+    }
+
+    /// Tasks in the trash, including those trashed with their project, most
+    /// recently deleted first.
+    pub async fn get_trash(
+        params: &PageParams,
+        filter: &TaskFilter,
+        db: &PgPool,
+    ) -> Result<Page<Task>, ServiceError> {
+        Self::list(params, filter, true, db).await
+    }
+
+    async fn list(
+        params: &PageParams,
+        filter: &TaskFilter,
+        trashed: bool,
+        db: &PgPool,
+    ) -> Result<Page<Task>, ServiceError> {
+        let default_sort = if trashed {
+            "-deleted_at"
+        } else {
+            "-created_at"
+        };
+        let order_by = params.order_by(TASK_SORT_FIELDS, default_sort)?;
+        let scope = if trashed {
+            " WHERE deleted_at IS NOT NULL"
+        } else {
+            " WHERE deleted_at IS NULL"
+        };
+
+        let mut count = QueryBuilder::new("SELECT COUNT(*) FROM tasks");
+        count.push(scope);
+        Self::push_filters(&mut count, filter);
+        let total: i64 = count.build_query_scalar().fetch_one(db).await?;
+
+        let mut query = QueryBuilder::new(
+            r#"
+            SELECT
+                id, name, description, project_id, assigned_to,
+                status, progress,
+                start_date, end_date, dependencies, parent_id, wbs, milestone,
+                created_at, updated_at, version, deleted_at
+            FROM tasks"#,
+        );
+        query.push(scope);
+        Self::push_filters(&mut query, filter);
+        query
+            .push(order_by)
+            .push(" LIMIT ")
+            .push_bind(params.per_page())
+            .push(" OFFSET ")
+            .push_bind(params.offset()?);
+
+        let tasks = query.build_query_as::<Task>().fetch_all(db).await?;
+
+        Ok(Page::new(tasks, total, params))
+    }
+
+    fn push_filters<'a>(query: &mut QueryBuilder<'a, Postgres>, filter: &'a TaskFilter) {
+        if let Some(project_id) = filter.project_id {
+            query.push(" AND project_id = ").push_bind(project_id);
+        }
+        if let Some(status) = &filter.status {
+            query.push(" AND status = ").push_bind(status);
+        }
+        if let Some(assignee) = filter.assignee {
+            query
+                .push(" AND ")
+                .push_bind(assignee)
+                .push(" = ANY(assigned_to)");
+        }
+        match filter.overdue {
+            Some(true) => {
+                query.push(" AND end_date < NOW() AND status <> 'completed'");
+            }
+            Some(false) => {
+                query.push(" AND NOT (end_date < NOW() AND status <> 'completed')");
+            }
+            None => {}
+        }
+    }
+
+    pub async fn get_by_id(id: Uuid, db: &PgPool) -> Result<Task, ServiceError> {
+        let task = sqlx::query_as!(
             Task,
             r#"
             SELECT
                 id, name, description, project_id, assigned_to,
                 status as "status: TaskStatus", progress,
-                start_date, end_date, dependencies,
-                created_at, updated_at
+                start_date, end_date, dependencies, parent_id, wbs, milestone,
+                created_at, updated_at, version, deleted_at
             FROM tasks
-            ORDER BY created_at DESC
-            "#
+            WHERE id = $1 AND deleted_at IS NULL
+            "#,
+            id
         )
-        .fetch_all(db)
-        .await?;
+        .fetch_optional(db)
+        .await?
+        .ok_or(ServiceError::NotFound("Task not found".into()))?;
 
-        Ok(tasks)
+        Ok(task)
     }
 
-    pub async fn get_by_id(id: Uuid, db: &PgPool) -> Result<Task, ServiceError> {
+    /// Locks the row for the rest of the transaction and returns it. Tasks
+    /// in the trash are not found.
+    async fn lock(id: Uuid, conn: &mut PgConnection) -> Result<Task, ServiceError> {
         let task = sqlx::query_as!(
             Task,
             r#"
             SELECT
                 id, name, description, project_id, assigned_to,
                 status as "status: TaskStatus", progress,
-                start_date, end_date, dependencies,
-                created_at, updated_at
+                start_date, end_date, dependencies, parent_id, wbs, milestone,
+                created_at, updated_at, version, deleted_at
             FROM tasks
-            WHERE id = $1
+            WHERE id = $1 AND deleted_at IS NULL
+            FOR UPDATE
             "#,
             id
         )
-        .fetch_optional(db)
+        .fetch_optional(conn)
         .await?
         .ok_or(ServiceError::NotFound("Task not found".into()))?;
 
         Ok(task)
     }
 
-    pub async fn create(task: TaskCreate, db: &PgPool) -> Result<Task, ServiceError> {
+    /// Like `lock`, but for a task in the trash.
+    async fn lock_trashed(id: Uuid, conn: &mut PgConnection) -> Result<Task, ServiceError> {
+        let task = sqlx::query_as!(
+            Task,
+            r#"
+            SELECT
+                id, name, description, project_id, assigned_to,
+                status as "status: TaskStatus", progress,
+                start_date, end_date, dependencies, parent_id, wbs, milestone,
+                created_at, updated_at, version, deleted_at
+            FROM tasks
+            WHERE id = $1 AND deleted_at IS NOT NULL
+            FOR UPDATE
+            "#,
+            id
+        )
+        .fetch_optional(conn)
+        .await?
+        .ok_or(ServiceError::NotFound("Task not found in trash".into()))?;
+
+        Ok(task)
+    }
+
+    /// Fails unless the project exists, is not in the trash and is not
+    /// archived. The row is share-locked so the project cannot be trashed or
+    /// archived until the caller's transaction ends.
+    async fn require_writable_project(
+        project_id: Uuid,
+        conn: &mut PgConnection,
+    ) -> Result<(), ServiceError> {
+        let archived_at = sqlx::query_scalar!(
+            "SELECT archived_at FROM projects WHERE id = $1 AND deleted_at IS NULL FOR SHARE",
+            project_id
+        )
+        .fetch_optional(conn)
+        .await?
+        .ok_or_else(|| {
+            ServiceError::invalid_field("project_id", "not_found", "project not found")
+        })?;
+
+        match archived_at {
+            Some(_) => Err(ServiceError::ProjectArchived),
+            None => Ok(()),
+        }
+    }
+
+    pub async fn create(
+        task: TaskCreate,
+        audit: &AuditContext,
+        db: &PgPool,
+    ) -> Result<Task, ServiceError> {
+        let mut tx = db.begin().await?;
+        let task = Self::create_in(task, audit, &mut tx).await?;
+        tx.commit().await?;
+
+        Ok(task)
+    }
+
+    /// `create` within the caller's transaction.
+    pub(crate) async fn create_in(
+        task: TaskCreate,
+        audit: &AuditContext,
+        conn: &mut PgConnection,
+    ) -> Result<Task, ServiceError> {
+        task.validate()?;
+        Self::require_writable_project(task.project_id, conn).await?;
+        if let Some(parent_id) = task.parent_id {
+            Self::check_parent(None, parent_id, task.project_id, conn).await?;
+        }
+
         let task = sqlx::query_as!(
             Task,
             r#"
             INSERT INTO tasks (
                 name, description, project_id, assigned_to,
-                status, progress, start_date, end_date, dependencies
+                status, progress, start_date, end_date, dependencies,
+                parent_id, wbs, milestone
             )
-            VALUES ($1, $2, $3, $4, 'pending', 0, $5, $6, $7)
+            VALUES ($1, $2, $3, $4, 'pending', 0, $5, $6, $7, $8, $9, $10)
             RETURNING
                 id, name, description, project_id, assigned_to,
                 status as "status: TaskStatus", progress,
-                start_date, end_date, dependencies,
-                created_at, updated_at
+                start_date, end_date, dependencies, parent_id, wbs, milestone,
+                created_at, updated_at, version, deleted_at
             "#,
             task.name,
             task.description,
@@ -67,54 +246,257 @@ impl TaskService {
             &task.assigned_to.map(|id| vec![id]).unwrap_or_default(),
             task.start_date,
             task.end_date,
-            &task.dependencies
+            &task.dependencies,
+            task.parent_id,
+            task.wbs,
+            task.milestone
+        )
+        .fetch_one(&mut *conn)
+        .await?;
+
+        AuditService::record_create(conn, audit, AuditEntity::Task, task.id, &task).await?;
+        NotificationService::task_assigned_in(&task, &[], audit, conn).await?;
+        NotificationService::check_budget_in(task.project_id, conn).await?;
+        WebhookService::emit_in(WebhookEvent::TaskCreated, &task, None, conn).await?;
+        RealtimeService::publish_in(
+            ChangeKind::TaskCreated,
+            task.project_id,
+            task.id,
+            Some(task.version),
+            audit,
+            conn,
         )
-        .fetch_one(db)
         .await?;
 
         Ok(task)
     }
 
-    pub async fn update(id: Uuid, task: TaskUpdate, db: &PgPool) -> Result<Task, ServiceError> {
-        // let current = Self::get_by_id(id, db).await?;
+    pub async fn update(
+        id: Uuid,
+        task: TaskUpdate,
+        expected_version: Option<i32>,
+        audit: &AuditContext,
+        db: &PgPool,
+    ) -> Result<Task, ServiceError> {
+        let mut tx = db.begin().await?;
+        let task = Self::update_in(id, task, expected_version, audit, &mut tx).await?;
+        tx.commit().await?;
+
+        Ok(task)
+    }
+
+    /// `update` within the caller's transaction.
+    pub(crate) async fn update_in(
+        id: Uuid,
+        task: TaskUpdate,
+        expected_version: Option<i32>,
+        audit: &AuditContext,
+        conn: &mut PgConnection,
+    ) -> Result<Task, ServiceError> {
+        task.validate()?;
+        let current = Self::lock(id, conn).await?;
+        current.check_version(expected_version)?;
+        Self::require_writable_project(current.project_id, conn).await?;
+
+        // Merge the patch into the current state. A task has at most one
+        // assignee through this API; `null` unassigns it.
+        let name = task.name.apply_required("name", current.name.clone())?;
+        let description = task.description.apply(current.description.clone());
+        let assigned_to = match task.assigned_to {
+            Patch::Absent => current.assigned_to.clone(),
+            Patch::Null => vec![],
+            Patch::Value(id) => vec![id],
+        };
+        let status = task.status.apply_required("status", current.status)?;
+        let progress = task
+            .progress
+            .apply_required("progress", current.progress.clone())?;
+        let start_date = task
+            .start_date
+            .apply_required("start_date", current.start_date)?;
+        let end_date = task.end_date.apply_required("end_date", current.end_date)?;
+        let dependencies = task
+            .dependencies
+            .apply(Some(current.dependencies.clone()))
+            .unwrap_or_default();
+        let parent_id = task.parent_id.apply(current.parent_id);
+        if let Some(parent_id) = parent_id.filter(|parent| Some(*parent) != current.parent_id) {
+            Self::check_parent(Some(id), parent_id, current.project_id, conn).await?;
+        }
+        let wbs = task.wbs.apply(current.wbs.clone());
+        let milestone = task
+            .milestone
+            .apply_required("milestone", current.milestone)?;
 
         let task = sqlx::query_as!(
             Task,
             r#"
             UPDATE tasks
             SET
-                name = COALESCE($1, name),
-                description = COALESCE($2, description),
-                assigned_to = COALESCE($3, assigned_to),
-                status = COALESCE($4, status),
-                progress = COALESCE($5, progress),
-                start_date = COALESCE($6, start_date),
-                end_date = COALESCE($7, end_date),
-                dependencies = COALESCE($8, dependencies),
-                updated_at = NOW()
-            WHERE id = $9
+                name = $1,
+                description = $2,
+                assigned_to = $3,
+                status = $4,
+                progress = $5,
+                start_date = $6,
+                end_date = $7,
+                dependencies = $8,
+                parent_id = $9,
+                wbs = $10,
+                milestone = $11,
+                updated_at = NOW(),
+                version = version + 1
+            WHERE id = $12
             RETURNING
                 id, name, description, project_id, assigned_to,
                 status as "status: TaskStatus", progress,
-                start_date, end_date, dependencies,
-                created_at, updated_at
+                start_date, end_date, dependencies, parent_id, wbs, milestone,
+                created_at, updated_at, version, deleted_at
             "#,
-            task.name,
-            task.description,
-            &task.assigned_to.map(|id| vec![id]).unwrap_or_default(),
-            task.status as Option<TaskStatus>,
-            task.progress,
-            task.start_date,
-            task.end_date,
-            &task.dependencies.unwrap_or_default(),
+            name,
+            description,
+            &assigned_to,
+            status as TaskStatus,
+            progress,
+            start_date,
+            end_date,
+            &dependencies,
+            parent_id,
+            wbs,
+            milestone,
             id
         )
-        .fetch_one(db)
+        .fetch_one(&mut *conn)
+        .await?;
+
+        // Link details only exist for current predecessors
+        sqlx::query!(
+            "DELETE FROM task_links WHERE task_id = $1 AND predecessor_id <> ALL($2)",
+            id,
+            &task.dependencies
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        AuditService::record_update(conn, audit, AuditEntity::Task, id, &current, &task).await?;
+        NotificationService::task_assigned_in(&task, &current.assigned_to, audit, conn).await?;
+        NotificationService::task_rescheduled_in(&current, &task, audit, conn).await?;
+        NotificationService::check_budget_in(task.project_id, conn).await?;
+        WebhookService::emit_in(WebhookEvent::TaskUpdated, &task, None, conn).await?;
+        RealtimeService::publish_in(
+            ChangeKind::TaskUpdated,
+            task.project_id,
+            task.id,
+            Some(task.version),
+            audit,
+            conn,
+        )
         .await?;
 
         Ok(task)
     }
 
+    /// A parent must be a live task of the same project and must not sit
+    /// below `task_id` in the hierarchy.
+    async fn check_parent(
+        task_id: Option<Uuid>,
+        parent_id: Uuid,
+        project_id: Uuid,
+        conn: &mut PgConnection,
+    ) -> Result<(), ServiceError> {
+        let parent_project = sqlx::query_scalar!(
+            "SELECT project_id FROM tasks WHERE id = $1 AND deleted_at IS NULL",
+            parent_id
+        )
+        .fetch_optional(&mut *conn)
+        .await?;
+        if parent_project != Some(project_id) {
+            return Err(ServiceError::invalid_field(
+                "parent_id",
+                "invalid_reference",
+                "parent must be a task of the same project",
+            ));
+        }
+
+        if let Some(task_id) = task_id {
+            let cycle = sqlx::query_scalar!(
+                r#"
+                WITH RECURSIVE ancestors AS (
+                    SELECT id, parent_id FROM tasks WHERE id = $1
+                    UNION
+                    SELECT t.id, t.parent_id FROM tasks t
+                    JOIN ancestors a ON t.id = a.parent_id
+                )
+                SELECT EXISTS (SELECT 1 FROM ancestors WHERE id = $2) as "exists!"
+                "#,
+                parent_id,
+                task_id
+            )
+            .fetch_one(&mut *conn)
+            .await?;
+            if cycle {
+                return Err(ServiceError::invalid_field(
+                    "parent_id",
+                    "cycle",
+                    "a task cannot be nested under itself",
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Link types and lags for the project's live tasks.
+    pub async fn get_links_by_project(
+        project_id: Uuid,
+        db: &PgPool,
+    ) -> Result<Vec<TaskLink>, ServiceError> {
+        let links = sqlx::query_as!(
+            TaskLink,
+            r#"
+            SELECT
+                l.task_id, l.predecessor_id,
+                l.link_type as "link_type: DependencyType", l.lag_minutes
+            FROM task_links l
+            JOIN tasks t ON t.id = l.task_id
+            WHERE t.project_id = $1 AND t.deleted_at IS NULL
+            ORDER BY t.created_at, l.predecessor_id
+            "#,
+            project_id
+        )
+        .fetch_all(db)
+        .await?;
+
+        Ok(links)
+    }
+
+    /// Records the type and lag of predecessors already listed in the task's
+    /// `dependencies`. Plain finish-to-start links without lag need no entry.
+    pub(crate) async fn set_links_in(
+        task_id: Uuid,
+        links: &[TaskLink],
+        conn: &mut PgConnection,
+    ) -> Result<(), ServiceError> {
+        for link in links {
+            sqlx::query!(
+                r#"
+                INSERT INTO task_links (task_id, predecessor_id, link_type, lag_minutes)
+                VALUES ($1, $2, $3, $4)
+                ON CONFLICT (task_id, predecessor_id)
+                DO UPDATE SET link_type = EXCLUDED.link_type, lag_minutes = EXCLUDED.lag_minutes
+                "#,
+                task_id,
+                link.predecessor_id,
+                link.link_type as DependencyType,
+                link.lag_minutes
+            )
+            .execute(&mut *conn)
+            .await?;
+        }
+
+        Ok(())
+    }
+
     pub async fn get_by_project(project_id: Uuid, db: &PgPool) -> Result<Vec<Task>, ServiceError> {
         let tasks = sqlx::query_as!(
             Task,
@@ -122,10 +504,10 @@ impl TaskService {
             SELECT
                 id, name, description, project_id, assigned_to,
                 status as "status: TaskStatus", progress,
-                start_date, end_date, dependencies,
-                created_at, updated_at
+                start_date, end_date, dependencies, parent_id, wbs, milestone,
+                created_at, updated_at, version, deleted_at
             FROM tasks
-            WHERE project_id = $1
+            WHERE project_id = $1 AND deleted_at IS NULL
             ORDER BY created_at DESC
             "#,
             project_id
@@ -146,10 +528,10 @@ impl TaskService {
             SELECT
                 id, name, description, project_id, assigned_to,
                 status as "status: TaskStatus", progress,
-                start_date, end_date, dependencies,
-                created_at, updated_at
+                start_date, end_date, dependencies, parent_id, wbs, milestone,
+                created_at, updated_at, version, deleted_at
             FROM tasks
-            WHERE assigned_to = $1
+            WHERE assigned_to = $1 AND deleted_at IS NULL
             ORDER BY created_at DESC
             "#,
             &vec![resource_id]
@@ -160,15 +542,298 @@ impl TaskService {
         Ok(tasks)
     }
 
-    pub async fn delete(id: Uuid, db: &PgPool) -> Result<(), ServiceError> {
-        let result = sqlx::query!("DELETE FROM tasks WHERE id = $1", id)
-            .execute(db)
+    /// Moves the task to the trash.
+    pub async fn delete(
+        id: Uuid,
+        expected_version: Option<i32>,
+        audit: &AuditContext,
+        db: &PgPool,
+    ) -> Result<(), ServiceError> {
+        let mut tx = db.begin().await?;
+        Self::delete_in(id, expected_version, audit, &mut tx).await?;
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    /// `delete` within the caller's transaction.
+    pub(crate) async fn delete_in(
+        id: Uuid,
+        expected_version: Option<i32>,
+        audit: &AuditContext,
+        conn: &mut PgConnection,
+    ) -> Result<(), ServiceError> {
+        let current = Self::lock(id, conn).await?;
+        current.check_version(expected_version)?;
+        Self::require_writable_project(current.project_id, conn).await?;
+
+        sqlx::query!(
+            "UPDATE tasks SET deleted_at = NOW(), version = version + 1 WHERE id = $1",
+            id
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        AuditService::record_delete(conn, audit, AuditEntity::Task, id, &current).await?;
+        WebhookService::emit_in(WebhookEvent::TaskDeleted, &current, None, conn).await?;
+        RealtimeService::publish_in(
+            ChangeKind::TaskDeleted,
+            current.project_id,
+            current.id,
+            Some(current.version + 1),
+            audit,
+            conn,
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    /// Applies every operation in one transaction. All items are validated
+    /// before any is applied; if one fails during execution the whole batch
+    /// is rolled back and the error names the operation's index.
+    pub async fn bulk(
+        request: BulkTaskRequest,
+        audit: &AuditContext,
+        db: &PgPool,
+    ) -> Result<BulkTaskResponse, ServiceError> {
+        let operations = request.operations;
+        if operations.is_empty() || operations.len() > MAX_BULK_OPERATIONS {
+            return Err(ServiceError::invalid_field(
+                "operations",
+                "length",
+                format!(
+                    "operations must contain between 1 and {} items",
+                    MAX_BULK_OPERATIONS
+                ),
+            ));
+        }
+
+        let mut invalid = Vec::new();
+        for (index, operation) in operations.iter().enumerate() {
+            let checked = match operation {
+                BulkTaskOperation::Create { task } => task.validate(),
+                BulkTaskOperation::Update { changes, .. } => changes.validate(),
+                BulkTaskOperation::Delete { .. } => Ok(()),
+            };
+            if let Err(errors) = checked {
+                let prefix = Self::bulk_prefix(index, operation);
+                invalid.extend(
+                    ServiceError::from(errors)
+                        .field_errors()
+                        .into_iter()
+                        .map(|field| field.prefixed(&prefix)),
+                );
+            }
+        }
+        if !invalid.is_empty() {
+            return Err(ServiceError::ValidationError(invalid));
+        }
+
+        let mut tx = db.begin().await?;
+        let mut results = Vec::with_capacity(operations.len());
+        for (index, operation) in operations.into_iter().enumerate() {
+            let prefix = Self::bulk_prefix(index, &operation);
+            let result = match operation {
+                BulkTaskOperation::Create { task } => Self::create_in(task, audit, &mut tx)
+                    .await
+                    .map(|task| BulkTaskResult::Created { index, task }),
+                BulkTaskOperation::Update {
+                    id,
+                    version,
+                    changes,
+                } => Self::update_in(id, changes, version, audit, &mut tx)
+                    .await
+                    .map(|task| BulkTaskResult::Updated { index, task }),
+                BulkTaskOperation::Delete { id, version } => {
+                    Self::delete_in(id, version, audit, &mut tx)
+                        .await
+                        .map(|_| BulkTaskResult::Deleted { index, id })
+                }
+            };
+            // Dropping the transaction on error rolls back earlier operations
+            results.push(result.map_err(|error| ServiceError::BulkOperationFailed {
+                index,
+                prefix,
+                error: Box::new(error),
+            })?);
+        }
+        tx.commit().await?;
+
+        Ok(BulkTaskResponse { results })
+    }
+
+    /// Where an operation's fields sit in the request body.
+    fn bulk_prefix(index: usize, operation: &BulkTaskOperation) -> String {
+        match operation {
+            BulkTaskOperation::Create { .. } => format!("operations[{}].task", index),
+            BulkTaskOperation::Update { .. } => format!("operations[{}].changes", index),
+            BulkTaskOperation::Delete { .. } => format!("operations[{}]", index),
+        }
+    }
+
+    /// Takes the task out of the trash. A task trashed with its project can
+    /// only come back by restoring the project.
+    pub async fn restore(
+        id: Uuid,
+        expected_version: Option<i32>,
+        audit: &AuditContext,
+        db: &PgPool,
+    ) -> Result<Task, ServiceError> {
+        let mut tx = db.begin().await?;
+        let trashed = Self::lock_trashed(id, &mut tx).await?;
+        trashed.check_version(expected_version)?;
+        Self::require_writable_project(trashed.project_id, &mut tx)
+            .await
+            .map_err(|e| match e {
+                ServiceError::ValidationError(_) => ServiceError::UnprocessableEntity(
+                    "The task's project is in the trash; restore the project instead".into(),
+                ),
+                e => e,
+            })?;
+
+        let task = sqlx::query_as!(
+            Task,
+            r#"
+            UPDATE tasks
+            SET deleted_at = NULL, version = version + 1
+            WHERE id = $1
+            RETURNING
+                id, name, description, project_id, assigned_to,
+                status as "status: TaskStatus", progress,
+                start_date, end_date, dependencies, parent_id, wbs, milestone,
+                created_at, updated_at, version, deleted_at
+            "#,
+            id
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+
+        AuditService::record_restore(&mut tx, audit, AuditEntity::Task, id, &trashed, &task)
             .await?;
+        WebhookService::emit_in(WebhookEvent::TaskRestored, &task, None, &mut tx).await?;
+        RealtimeService::publish_in(
+            ChangeKind::TaskRestored,
+            task.project_id,
+            task.id,
+            Some(task.version),
+            audit,
+            &mut tx,
+        )
+        .await?;
+        tx.commit().await?;
+
+        Ok(task)
+    }
+
+    /// Trashes the project's remaining tasks with the project's own
+    /// `deleted_at`, which marks them as cascaded for `restore_project_tasks`.
+    pub(crate) async fn trash_project_tasks(
+        project_id: Uuid,
+        deleted_at: DateTime<Utc>,
+        audit: &AuditContext,
+        conn: &mut PgConnection,
+    ) -> Result<(), ServiceError> {
+        let tasks = sqlx::query_as!(
+            Task,
+            r#"
+            SELECT
+                id, name, description, project_id, assigned_to,
+                status as "status: TaskStatus", progress,
+                start_date, end_date, dependencies, parent_id, wbs, milestone,
+                created_at, updated_at, version, deleted_at
+            FROM tasks
+            WHERE project_id = $1 AND deleted_at IS NULL
+            FOR UPDATE
+            "#,
+            project_id
+        )
+        .fetch_all(&mut *conn)
+        .await?;
+
+        sqlx::query!(
+            r#"
+            UPDATE tasks
+            SET deleted_at = $2, version = version + 1
+            WHERE project_id = $1 AND deleted_at IS NULL
+            "#,
+            project_id,
+            deleted_at
+        )
+        .execute(&mut *conn)
+        .await?;
 
-        if result.rows_affected() == 0 {
-            return Err(ServiceError::NotFound("Task not found".into()));
+        for task in &tasks {
+            AuditService::record_delete(conn, audit, AuditEntity::Task, task.id, task).await?;
+            WebhookService::emit_in(WebhookEvent::TaskDeleted, task, None, conn).await?;
+            RealtimeService::publish_in(
+                ChangeKind::TaskDeleted,
+                task.project_id,
+                task.id,
+                Some(task.version + 1),
+                audit,
+                conn,
+            )
+            .await?;
         }
+        Ok(())
+    }
+
+    /// Restores the tasks `trash_project_tasks` trashed at `deleted_at`.
+    pub(crate) async fn restore_project_tasks(
+        project_id: Uuid,
+        deleted_at: DateTime<Utc>,
+        audit: &AuditContext,
+        conn: &mut PgConnection,
+    ) -> Result<(), ServiceError> {
+        let trashed = sqlx::query_as!(
+            Task,
+            r#"
+            SELECT
+                id, name, description, project_id, assigned_to,
+                status as "status: TaskStatus", progress,
+                start_date, end_date, dependencies, parent_id, wbs, milestone,
+                created_at, updated_at, version, deleted_at
+            FROM tasks
+            WHERE project_id = $1 AND deleted_at = $2
+            FOR UPDATE
+            "#,
+            project_id,
+            deleted_at
+        )
+        .fetch_all(&mut *conn)
+        .await?;
 
+        for before in &trashed {
+            let after = sqlx::query_as!(
+                Task,
+                r#"
+                UPDATE tasks
+                SET deleted_at = NULL, version = version + 1
+                WHERE id = $1
+                RETURNING
+                    id, name, description, project_id, assigned_to,
+                    status as "status: TaskStatus", progress,
+                    start_date, end_date, dependencies, parent_id, wbs, milestone,
+                    created_at, updated_at, version, deleted_at
+                "#,
+                before.id
+            )
+            .fetch_one(&mut *conn)
+            .await?;
+            AuditService::record_restore(conn, audit, AuditEntity::Task, after.id, before, &after)
+                .await?;
+            WebhookService::emit_in(WebhookEvent::TaskRestored, &after, None, conn).await?;
+            RealtimeService::publish_in(
+                ChangeKind::TaskRestored,
+                after.project_id,
+                after.id,
+                Some(after.version),
+                audit,
+                conn,
+            )
+            .await?;
+        }
         Ok(())
     }
 }
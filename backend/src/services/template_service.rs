@@ -0,0 +1,775 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use serde_json::{json, Value};
+use sqlx::{PgConnection, PgPool};
+use std::collections::HashMap;
+use std::str::FromStr;
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::errors::ServiceError;
+use crate::models::audit::{AuditContext, AuditEntity};
+use crate::models::calendar::GateReview;
+use crate::models::document::{
+    Document, DocumentLinkCreate, DocumentTarget, DocumentUpload, VersionUpload,
+};
+use crate::models::lifecycle::LifecyclePhase;
+use crate::models::mustache::{escape_html, escape_markdown, Template};
+use crate::models::requirement::{RequirementPriority, RequirementStatus, RequirementType};
+use crate::models::template::{
+    BuiltInTemplate, DocumentGenerate, DocumentKind, DocumentTemplate, DocumentTemplateCreate,
+    DocumentTemplateUpdate, OutputFormat, TemplateFilter, TemplateFormat,
+};
+use crate::models::user::UserRole;
+use crate::models::version::Versioned;
+use crate::services::audit_service::AuditService;
+use crate::services::document_service::DocumentService;
+use crate::services::export_service::wbs_key;
+use crate::services::project_service::ProjectService;
+use crate::services::render_service::RenderService;
+use crate::services::resource_service::ResourceService;
+use crate::services::storage_service::DocumentStorage;
+use crate::services::task_service::TaskService;
+
+const DATE_FORMAT: &str = "%Y-%m-%d";
+const DATE_TIME_FORMAT: &str = "%Y-%m-%d %H:%M UTC";
+
+pub struct TemplateService;
+
+impl TemplateService {
+    pub async fn list(
+        filter: &TemplateFilter,
+        pool: &PgPool,
+    ) -> Result<Vec<DocumentTemplate>, ServiceError> {
+        let templates = sqlx::query_as!(
+            DocumentTemplate,
+            r#"
+            SELECT id, kind as "kind: DocumentKind", name, format as "format: TemplateFormat",
+                   body, active, created_by, created_at, updated_at, version
+            FROM document_templates
+            WHERE $1::document_kind IS NULL OR kind = $1
+            ORDER BY kind, active DESC, name
+            "#,
+            filter.kind as Option<DocumentKind>
+        )
+        .fetch_all(pool)
+        .await?;
+
+        Ok(templates)
+    }
+
+    pub async fn get(id: Uuid, pool: &PgPool) -> Result<DocumentTemplate, ServiceError> {
+        let mut conn = pool.acquire().await?;
+        Self::fetch(id, false, &mut conn).await
+    }
+
+    /// The template shipped with the application for a kind.
+    pub fn built_in(kind: DocumentKind) -> BuiltInTemplate {
+        let body = match kind {
+            DocumentKind::RequirementsSpecification => {
+                include_str!("templates/requirements_specification.md")
+            }
+            DocumentKind::PhaseReport => include_str!("templates/phase_report.md"),
+            DocumentKind::ClosureReport => include_str!("templates/closure_report.md"),
+        };
+        BuiltInTemplate {
+            kind,
+            format: TemplateFormat::Markdown,
+            body: body.to_string(),
+        }
+    }
+
+    pub async fn create(
+        template: DocumentTemplateCreate,
+        audit: &AuditContext,
+        pool: &PgPool,
+    ) -> Result<DocumentTemplate, ServiceError> {
+        template.validate()?;
+        Self::check_syntax(&template.body)?;
+
+        let mut tx = pool.begin().await?;
+        if template.active {
+            Self::deactivate(template.kind, None, audit, &mut tx).await?;
+        }
+        let created = sqlx::query_as!(
+            DocumentTemplate,
+            r#"
+            INSERT INTO document_templates (kind, name, format, body, active, created_by)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            RETURNING id, kind as "kind: DocumentKind", name, format as "format: TemplateFormat",
+                      body, active, created_by, created_at, updated_at, version
+            "#,
+            template.kind as DocumentKind,
+            template.name,
+            template.format as TemplateFormat,
+            template.body,
+            template.active,
+            audit.actor_id
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+
+        AuditService::record_create(
+            &mut tx,
+            audit,
+            AuditEntity::DocumentTemplate,
+            created.id,
+            &created,
+        )
+        .await?;
+        tx.commit().await?;
+
+        Ok(created)
+    }
+
+    pub async fn update(
+        id: Uuid,
+        update: DocumentTemplateUpdate,
+        expected_version: Option<i32>,
+        audit: &AuditContext,
+        pool: &PgPool,
+    ) -> Result<DocumentTemplate, ServiceError> {
+        update.validate()?;
+
+        let mut tx = pool.begin().await?;
+        let existing = Self::fetch(id, true, &mut tx).await?;
+        existing.check_version(expected_version)?;
+
+        let name = update.name.apply_required("name", existing.name.clone())?;
+        let format = update.format.apply_required("format", existing.format)?;
+        let body = update.body.apply_required("body", existing.body.clone())?;
+        Self::check_syntax(&body)?;
+        let active = update.active.apply_required("active", existing.active)?;
+        if active && !existing.active {
+            Self::deactivate(existing.kind, Some(id), audit, &mut tx).await?;
+        }
+
+        let updated = sqlx::query_as!(
+            DocumentTemplate,
+            r#"
+            UPDATE document_templates
+            SET name = $2, format = $3, body = $4, active = $5,
+                updated_at = NOW(), version = version + 1
+            WHERE id = $1
+            RETURNING id, kind as "kind: DocumentKind", name, format as "format: TemplateFormat",
+                      body, active, created_by, created_at, updated_at, version
+            "#,
+            id,
+            name,
+            format as TemplateFormat,
+            body,
+            active
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+
+        AuditService::record_update(
+            &mut tx,
+            audit,
+            AuditEntity::DocumentTemplate,
+            id,
+            &existing,
+            &updated,
+        )
+        .await?;
+        tx.commit().await?;
+
+        Ok(updated)
+    }
+
+    /// Removes a template. Its kind falls back to the built-in template if
+    /// it was the active one.
+    pub async fn delete(
+        id: Uuid,
+        expected_version: Option<i32>,
+        audit: &AuditContext,
+        pool: &PgPool,
+    ) -> Result<(), ServiceError> {
+        let mut tx = pool.begin().await?;
+        let existing = Self::fetch(id, true, &mut tx).await?;
+        existing.check_version(expected_version)?;
+
+        sqlx::query!("DELETE FROM document_templates WHERE id = $1", id)
+            .execute(&mut *tx)
+            .await?;
+        AuditService::record_delete(&mut tx, audit, AuditEntity::DocumentTemplate, id, &existing)
+            .await?;
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    /// Renders a document of the project and stores it as the next version
+    /// of the project's document of that kind and format, linked to the
+    /// transition into the kind's phase and to the gate review, if given.
+    pub async fn generate(
+        request: DocumentGenerate,
+        user_id: Uuid,
+        role: &UserRole,
+        audit: &AuditContext,
+        storage: &dyn DocumentStorage,
+        pool: &PgPool,
+    ) -> Result<Document, ServiceError> {
+        let mut conn = pool.acquire().await?;
+        if !ProjectService::is_visible_to(request.project_id, user_id, role, &mut conn).await? {
+            return Err(ServiceError::NotFound("Project not found".into()));
+        }
+        let gate_review = match request.gate_review_id {
+            Some(id) => Some(Self::gate_review(id, request.project_id, &mut conn).await?),
+            None => None,
+        };
+        let (format, body) = Self::template_for(request.kind, &mut conn).await?;
+        if format == TemplateFormat::Html && request.format != OutputFormat::Html {
+            return Err(ServiceError::invalid_field(
+                "format",
+                "unsupported",
+                "the active template is HTML, which renders to HTML only",
+            ));
+        }
+        drop(conn);
+
+        let (context, transition_id) =
+            Self::context(request.kind, request.project_id, gate_review.as_ref(), pool).await?;
+        let title = request.kind.label();
+        let content = Self::render(format, &body, request.format, title, &context)?;
+        let filename = format!("{}.{}", request.kind.slug(), request.format.extension());
+        let content_type = Some(request.format.content_type());
+
+        let existing = sqlx::query_scalar!(
+            r#"
+            SELECT document_id FROM generated_documents
+            WHERE project_id = $1 AND kind = $2 AND format = $3
+            "#,
+            request.project_id,
+            request.kind as DocumentKind,
+            request.format as OutputFormat
+        )
+        .fetch_optional(pool)
+        .await?;
+        let document = match existing {
+            Some(document_id) => {
+                DocumentService::add_version(
+                    document_id,
+                    VersionUpload {
+                        filename: Some(filename),
+                    },
+                    content_type,
+                    content.into(),
+                    None,
+                    user_id,
+                    role,
+                    audit,
+                    storage,
+                    pool,
+                )
+                .await?
+            }
+            None => {
+                let document = DocumentService::upload(
+                    DocumentUpload {
+                        project_id: request.project_id,
+                        filename,
+                        title: Some(format!(
+                            "{} ({})",
+                            title,
+                            request.format.extension().to_uppercase()
+                        )),
+                        description: Some("Generated from the project's records".into()),
+                        target_type: None,
+                        target_id: None,
+                    },
+                    content_type,
+                    content.into(),
+                    user_id,
+                    role,
+                    audit,
+                    storage,
+                    pool,
+                )
+                .await?;
+                sqlx::query!(
+                    r#"
+                    INSERT INTO generated_documents (project_id, kind, format, document_id)
+                    VALUES ($1, $2, $3, $4)
+                    ON CONFLICT DO NOTHING
+                    "#,
+                    request.project_id,
+                    request.kind as DocumentKind,
+                    request.format as OutputFormat,
+                    document.id
+                )
+                .execute(pool)
+                .await?;
+                document
+            }
+        };
+
+        let targets = transition_id
+            .map(|id| (DocumentTarget::PhaseTransition, id))
+            .into_iter()
+            .chain(gate_review.map(|review| (DocumentTarget::GateReview, review.id)));
+        for (target_type, target_id) in targets {
+            DocumentService::link(
+                document.id,
+                DocumentLinkCreate {
+                    target_type,
+                    target_id,
+                },
+                user_id,
+                role,
+                audit,
+                pool,
+            )
+            .await?;
+        }
+
+        Ok(document)
+    }
+
+    /// Generates a document on behalf of `user_id`, as the
+    /// `document_generation` job does.
+    pub async fn generate_for(
+        request: DocumentGenerate,
+        user_id: Uuid,
+        storage: &dyn DocumentStorage,
+        pool: &PgPool,
+    ) -> Result<Document, ServiceError> {
+        let role = sqlx::query_scalar!(
+            r#"SELECT role as "role: UserRole" FROM users WHERE id = $1"#,
+            user_id
+        )
+        .fetch_optional(pool)
+        .await?
+        .ok_or(ServiceError::NotFound("User not found".into()))?;
+        let audit = AuditContext {
+            actor_id: Some(user_id),
+            request_id: None,
+        };
+        Self::generate(request, user_id, &role, &audit, storage, pool).await
+    }
+
+    /// Renders a template against `context` into the output format.
+    pub fn render(
+        format: TemplateFormat,
+        body: &str,
+        output: OutputFormat,
+        title: &str,
+        context: &Value,
+    ) -> Result<Vec<u8>, ServiceError> {
+        let template = Template::from_str(body)
+            .map_err(|e| ServiceError::UnprocessableEntity(format!("Invalid template: {}", e)))?;
+        let content = match (format, output) {
+            (TemplateFormat::Html, OutputFormat::Html) => {
+                template.render(context, escape_html).into_bytes()
+            }
+            (TemplateFormat::Html, _) => {
+                return Err(ServiceError::invalid_field(
+                    "format",
+                    "unsupported",
+                    "HTML templates render to HTML only",
+                ))
+            }
+            (TemplateFormat::Markdown, output) => {
+                let markdown = template.render(context, escape_markdown);
+                match output {
+                    OutputFormat::Markdown => markdown.into_bytes(),
+                    OutputFormat::Html => {
+                        RenderService::markdown_to_html(&markdown, title).into_bytes()
+                    }
+                    OutputFormat::Pdf => RenderService::markdown_to_pdf(&markdown, title),
+                }
+            }
+        };
+
+        Ok(content)
+    }
+
+    /// The data templates are rendered against, and the transition into the
+    /// kind's phase if the project has made it.
+    async fn context(
+        kind: DocumentKind,
+        project_id: Uuid,
+        gate_review: Option<&GateReview>,
+        pool: &PgPool,
+    ) -> Result<(Value, Option<Uuid>), ServiceError> {
+        let project = ProjectService::get_by_id(project_id, pool).await?;
+        let current_phase = sqlx::query_scalar!(
+            r#"SELECT current_phase as "current_phase: LifecyclePhase" FROM projects WHERE id = $1"#,
+            project_id
+        )
+        .fetch_one(pool)
+        .await?;
+        let phase = gate_review
+            .map(|review| review.phase)
+            .or(kind.phase())
+            .unwrap_or(current_phase);
+
+        let mut tasks = TaskService::get_by_project(project_id, pool).await?;
+        tasks.sort_by_key(|task| wbs_key(&task.wbs));
+        let assigned: Vec<Uuid> = tasks
+            .iter()
+            .flat_map(|task| task.assigned_to.iter().copied())
+            .collect();
+        let resources: HashMap<Uuid, String> = ResourceService::get_by_ids(&assigned, pool)
+            .await?
+            .into_iter()
+            .map(|resource| (resource.id, resource.name))
+            .collect();
+        let tasks: Vec<Value> = tasks
+            .iter()
+            .map(|task| {
+                json!({
+                    "wbs": task.wbs,
+                    "name": task.name,
+                    "description": task.description,
+                    "start_date": date(task.start_date),
+                    "end_date": date(task.end_date),
+                    "status": label(&task.status),
+                    "completed": task.status == crate::models::task::TaskStatus::Completed,
+                    "progress": task.progress.round(0).to_string(),
+                    "milestone": task.milestone,
+                    "assignees": task
+                        .assigned_to
+                        .iter()
+                        .filter_map(|id| resources.get(id).cloned())
+                        .collect::<Vec<_>>()
+                        .join(", "),
+                })
+            })
+            .collect();
+        let milestones: Vec<Value> = tasks
+            .iter()
+            .filter(|task| task["milestone"] == json!(true))
+            .cloned()
+            .collect();
+        let count = |items: &[Value], status: &str| {
+            items.iter().filter(|task| task["status"] == status).count()
+        };
+
+        let transitions = sqlx::query!(
+            r#"
+            SELECT pt.id, pt.phase as "phase: LifecyclePhase", pt.description,
+                   pt.created_at, u.full_name as "approver?"
+            FROM phase_transitions pt
+            LEFT JOIN users u ON u.id = pt.approved_by
+            WHERE pt.project_id = $1
+            ORDER BY pt.created_at, pt.id
+            "#,
+            project_id
+        )
+        .fetch_all(pool)
+        .await?;
+        let transition_id = transitions
+            .iter()
+            .rev()
+            .find(|transition| transition.phase == phase)
+            .map(|transition| transition.id);
+        let phases: Vec<Value> = transitions
+            .iter()
+            .map(|transition| {
+                json!({
+                    "phase": label(&transition.phase),
+                    "description": transition.description,
+                    "approved_by": transition.approver.as_deref().unwrap_or("an unknown user"),
+                    "approved_at": date(transition.created_at),
+                })
+            })
+            .collect();
+
+        let gate_reviews: Vec<Value> = sqlx::query_as!(
+            GateReview,
+            r#"
+            SELECT id, project_id, phase as "phase: LifecyclePhase", scheduled_at,
+                   duration_minutes, location, notes, min_pass_rate, no_open_critical_defects,
+                   created_by, created_at, updated_at, version
+            FROM gate_reviews
+            WHERE project_id = $1
+            ORDER BY scheduled_at
+            "#,
+            project_id
+        )
+        .fetch_all(pool)
+        .await?
+        .iter()
+        .map(gate_review_json)
+        .collect();
+
+        let requirements: Vec<Value> = sqlx::query!(
+            r#"
+            SELECT code as "code!", title, description,
+                   type as "requirement_type: RequirementType",
+                   priority as "priority: RequirementPriority",
+                   status as "status: RequirementStatus",
+                   acceptance_criteria
+            FROM requirements
+            WHERE project_id = $1 AND status <> 'rejected'
+            ORDER BY number
+            "#,
+            project_id
+        )
+        .fetch_all(pool)
+        .await?
+        .into_iter()
+        .map(|requirement| {
+            json!({
+                "code": requirement.code,
+                "title": requirement.title,
+                "description": requirement.description,
+                "type": label(&requirement.requirement_type),
+                "priority": label(&requirement.priority),
+                "status": label(&requirement.status),
+                "acceptance_criteria": requirement.acceptance_criteria,
+            })
+        })
+        .collect();
+
+        // Generated documents are left out so they do not list each other
+        let documents: Vec<Value> = sqlx::query!(
+            r#"
+            SELECT d.title, d.current_version, v.filename, d.updated_at
+            FROM documents d
+            JOIN document_versions v ON v.document_id = d.id AND v.number = d.current_version
+            WHERE d.project_id = $1
+              AND NOT EXISTS (SELECT 1 FROM generated_documents g WHERE g.document_id = d.id)
+            ORDER BY d.title, d.id
+            "#,
+            project_id
+        )
+        .fetch_all(pool)
+        .await?
+        .into_iter()
+        .map(|document| {
+            json!({
+                "title": document.title,
+                "current_version": document.current_version,
+                "filename": document.filename,
+                "updated_at": date(document.updated_at),
+            })
+        })
+        .collect();
+
+        let context = json!({
+            "title": kind.label(),
+            "generated_at": Utc::now().format(DATE_TIME_FORMAT).to_string(),
+            "phase": label(&phase),
+            "project": {
+                "name": project.name,
+                "description": project.description,
+                "status": label(&project.status),
+                "current_phase": label(&current_phase),
+                "start_date": date(project.start_date),
+                "end_date": date(project.end_date),
+                "budget": project.budget.to_string(),
+                "archived": project.archived_at.is_some(),
+            },
+            "summary": {
+                "tasks": tasks.len(),
+                "completed": count(&tasks, "Completed"),
+                "in_progress": count(&tasks, "In progress"),
+                "pending": count(&tasks, "Pending"),
+                "milestones": milestones.len(),
+                "milestones_completed": count(&milestones, "Completed"),
+                "requirements": requirements.len(),
+            },
+            "has": {
+                "tasks": !tasks.is_empty(),
+                "milestones": !milestones.is_empty(),
+                "requirements": !requirements.is_empty(),
+                "phases": !phases.is_empty(),
+                "gate_reviews": !gate_reviews.is_empty(),
+                "documents": !documents.is_empty(),
+            },
+            "tasks": tasks,
+            "milestones": milestones,
+            "requirements": requirements,
+            "phases": phases,
+            "gate_review": gate_review.map(gate_review_json),
+            "gate_reviews": gate_reviews,
+            "documents": documents,
+        });
+
+        Ok((context, transition_id))
+    }
+
+    /// The active template for a kind, else the built-in one.
+    async fn template_for(
+        kind: DocumentKind,
+        conn: &mut PgConnection,
+    ) -> Result<(TemplateFormat, String), ServiceError> {
+        let active = sqlx::query!(
+            r#"
+            SELECT format as "format: TemplateFormat", body
+            FROM document_templates
+            WHERE kind = $1 AND active
+            "#,
+            kind as DocumentKind
+        )
+        .fetch_optional(&mut *conn)
+        .await?;
+
+        Ok(match active {
+            Some(template) => (template.format, template.body),
+            None => {
+                let built_in = Self::built_in(kind);
+                (built_in.format, built_in.body)
+            }
+        })
+    }
+
+    async fn gate_review(
+        id: Uuid,
+        project_id: Uuid,
+        conn: &mut PgConnection,
+    ) -> Result<GateReview, ServiceError> {
+        sqlx::query_as!(
+            GateReview,
+            r#"
+            SELECT id, project_id, phase as "phase: LifecyclePhase", scheduled_at,
+                   duration_minutes, location, notes, min_pass_rate, no_open_critical_defects,
+                   created_by, created_at, updated_at, version
+            FROM gate_reviews
+            WHERE id = $1 AND project_id = $2
+            "#,
+            id,
+            project_id
+        )
+        .fetch_optional(&mut *conn)
+        .await?
+        .ok_or_else(|| {
+            ServiceError::invalid_field(
+                "gate_review_id",
+                "not_found",
+                "no such gate review in the project",
+            )
+        })
+    }
+
+    /// Clears the active flag of the kind's templates other than `except`.
+    async fn deactivate(
+        kind: DocumentKind,
+        except: Option<Uuid>,
+        audit: &AuditContext,
+        conn: &mut PgConnection,
+    ) -> Result<(), ServiceError> {
+        let previous = sqlx::query_as!(
+            DocumentTemplate,
+            r#"
+            SELECT id, kind as "kind: DocumentKind", name, format as "format: TemplateFormat",
+                   body, active, created_by, created_at, updated_at, version
+            FROM document_templates
+            WHERE kind = $1 AND active AND id IS DISTINCT FROM $2
+            FOR UPDATE
+            "#,
+            kind as DocumentKind,
+            except
+        )
+        .fetch_optional(&mut *conn)
+        .await?;
+        let Some(previous) = previous else {
+            return Ok(());
+        };
+
+        let deactivated = sqlx::query_as!(
+            DocumentTemplate,
+            r#"
+            UPDATE document_templates
+            SET active = FALSE, updated_at = NOW(), version = version + 1
+            WHERE id = $1
+            RETURNING id, kind as "kind: DocumentKind", name, format as "format: TemplateFormat",
+                      body, active, created_by, created_at, updated_at, version
+            "#,
+            previous.id
+        )
+        .fetch_one(&mut *conn)
+        .await?;
+        AuditService::record_update(
+            conn,
+            audit,
+            AuditEntity::DocumentTemplate,
+            previous.id,
+            &previous,
+            &deactivated,
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    fn check_syntax(body: &str) -> Result<(), ServiceError> {
+        Template::from_str(body)
+            .map(|_| ())
+            .map_err(|e| ServiceError::invalid_field("body", "syntax", &e))
+    }
+
+    async fn fetch(
+        id: Uuid,
+        for_update: bool,
+        conn: &mut PgConnection,
+    ) -> Result<DocumentTemplate, ServiceError> {
+        let template = if for_update {
+            sqlx::query_as!(
+                DocumentTemplate,
+                r#"
+                SELECT id, kind as "kind: DocumentKind", name, format as "format: TemplateFormat",
+                       body, active, created_by, created_at, updated_at, version
+                FROM document_templates
+                WHERE id = $1
+                FOR UPDATE
+                "#,
+                id
+            )
+            .fetch_optional(&mut *conn)
+            .await?
+        } else {
+            sqlx::query_as!(
+                DocumentTemplate,
+                r#"
+                SELECT id, kind as "kind: DocumentKind", name, format as "format: TemplateFormat",
+                       body, active, created_by, created_at, updated_at, version
+                FROM document_templates
+                WHERE id = $1
+                "#,
+                id
+            )
+            .fetch_optional(&mut *conn)
+            .await?
+        };
+
+        template.ok_or(ServiceError::NotFound("Template not found".into()))
+    }
+}
+
+fn date(value: DateTime<Utc>) -> String {
+    value.format(DATE_FORMAT).to_string()
+}
+
+/// An enum value as words, e.g. `InProgress` as "In progress".
+fn label<T: Serialize>(value: &T) -> String {
+    let name = serde_json::to_value(value)
+        .ok()
+        .and_then(|value| value.as_str().map(str::to_string))
+        .unwrap_or_default();
+    let mut out = String::with_capacity(name.len() + 4);
+    for (i, c) in name.chars().enumerate() {
+        if c == '_' {
+            out.push(' ');
+        } else if i > 0 && c.is_uppercase() {
+            out.push(' ');
+            out.extend(c.to_lowercase());
+        } else if i == 0 {
+            out.extend(c.to_uppercase());
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+fn gate_review_json(review: &GateReview) -> Value {
+    json!({
+        "phase": label(&review.phase),
+        "scheduled_at": review.scheduled_at.format(DATE_TIME_FORMAT).to_string(),
+        "location": review.location,
+        "notes": review.notes,
+    })
+}
@@ -0,0 +1,834 @@
+use std::collections::HashMap;
+
+use sqlx::{PgConnection, PgPool};
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::errors::ServiceError;
+use crate::models::audit::{AuditContext, AuditEntity};
+use crate::models::patch::Patch;
+use crate::models::testing::{
+    RequirementCoverage, TestCase, TestCaseCreate, TestCaseFilter, TestCaseUpdate, TestLevel,
+    TestOutcome, TestReport, TestResult, TestResultRecord, TestRun, TestRunCreate, TestRunDetails,
+    TestRunFilter, TestStep, TestSuite, TestSuiteCreate, TestSuiteUpdate, TestTally,
+};
+use crate::models::traceability::TraceEntity;
+use crate::models::user::UserRole;
+use crate::models::version::Versioned;
+use crate::services::audit_service::AuditService;
+use crate::services::project_service::ProjectService;
+use crate::services::traceability_service::TraceabilityService;
+
+pub struct TestingService;
+
+impl TestingService {
+    pub async fn create_suite(
+        new_suite: TestSuiteCreate,
+        user_id: Uuid,
+        role: &UserRole,
+        audit: &AuditContext,
+        pool: &PgPool,
+    ) -> Result<TestSuite, ServiceError> {
+        new_suite.validate()?;
+
+        let mut tx = pool.begin().await?;
+        if !ProjectService::is_visible_to(new_suite.project_id, user_id, role, &mut tx).await? {
+            return Err(ServiceError::NotFound("Project not found".into()));
+        }
+        ProjectService::lock(new_suite.project_id, &mut tx)
+            .await?
+            .ensure_writable()?;
+
+        let suite = sqlx::query_as!(
+            TestSuite,
+            r#"
+            INSERT INTO test_suites (project_id, name, description, level, created_by)
+            VALUES ($1, $2, $3, $4, $5)
+            RETURNING id, project_id, name, description, level as "level: TestLevel",
+                      created_by, created_at, updated_at, version
+            "#,
+            new_suite.project_id,
+            new_suite.name,
+            new_suite.description,
+            new_suite.level as TestLevel,
+            user_id
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+
+        AuditService::record_create(&mut tx, audit, AuditEntity::TestSuite, suite.id, &suite)
+            .await?;
+        tx.commit().await?;
+
+        Ok(suite)
+    }
+
+    /// A project's test suites by name.
+    pub async fn list_suites(
+        project_id: Uuid,
+        user_id: Uuid,
+        role: &UserRole,
+        pool: &PgPool,
+    ) -> Result<Vec<TestSuite>, ServiceError> {
+        let mut conn = pool.acquire().await?;
+        if !ProjectService::is_visible_to(project_id, user_id, role, &mut conn).await? {
+            return Err(ServiceError::NotFound("Project not found".into()));
+        }
+
+        let suites = sqlx::query_as!(
+            TestSuite,
+            r#"
+            SELECT id, project_id, name, description, level as "level: TestLevel",
+                   created_by, created_at, updated_at, version
+            FROM test_suites
+            WHERE project_id = $1
+            ORDER BY name, id
+            "#,
+            project_id
+        )
+        .fetch_all(&mut *conn)
+        .await?;
+
+        Ok(suites)
+    }
+
+    pub async fn get_suite(
+        id: Uuid,
+        user_id: Uuid,
+        role: &UserRole,
+        pool: &PgPool,
+    ) -> Result<TestSuite, ServiceError> {
+        let mut conn = pool.acquire().await?;
+        let suite = Self::fetch_suite(id, false, &mut conn).await?;
+        Self::ensure_visible(suite.project_id, "Test suite", user_id, role, &mut conn).await?;
+
+        Ok(suite)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub async fn update_suite(
+        id: Uuid,
+        update: TestSuiteUpdate,
+        expected_version: Option<i32>,
+        user_id: Uuid,
+        role: &UserRole,
+        audit: &AuditContext,
+        pool: &PgPool,
+    ) -> Result<TestSuite, ServiceError> {
+        update.validate()?;
+
+        let mut tx = pool.begin().await?;
+        let existing = Self::fetch_suite(id, true, &mut tx).await?;
+        Self::ensure_visible(existing.project_id, "Test suite", user_id, role, &mut tx).await?;
+        existing.check_version(expected_version)?;
+        ProjectService::lock(existing.project_id, &mut tx)
+            .await?
+            .ensure_writable()?;
+
+        let name = update.name.apply_required("name", existing.name.clone())?;
+        let description = update.description.apply(existing.description.clone());
+        let level = update.level.apply_required("level", existing.level)?;
+        let updated = sqlx::query_as!(
+            TestSuite,
+            r#"
+            UPDATE test_suites
+            SET name = $2, description = $3, level = $4, updated_at = NOW(),
+                version = version + 1
+            WHERE id = $1
+            RETURNING id, project_id, name, description, level as "level: TestLevel",
+                      created_by, created_at, updated_at, version
+            "#,
+            id,
+            name,
+            description,
+            level as TestLevel
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+
+        AuditService::record_update(
+            &mut tx,
+            audit,
+            AuditEntity::TestSuite,
+            id,
+            &existing,
+            &updated,
+        )
+        .await?;
+        tx.commit().await?;
+
+        Ok(updated)
+    }
+
+    /// Removes a suite with its test cases, their results and trace links.
+    pub async fn delete_suite(
+        id: Uuid,
+        expected_version: Option<i32>,
+        user_id: Uuid,
+        role: &UserRole,
+        audit: &AuditContext,
+        pool: &PgPool,
+    ) -> Result<(), ServiceError> {
+        let mut tx = pool.begin().await?;
+        let existing = Self::fetch_suite(id, true, &mut tx).await?;
+        Self::ensure_visible(existing.project_id, "Test suite", user_id, role, &mut tx).await?;
+        existing.check_version(expected_version)?;
+        ProjectService::lock(existing.project_id, &mut tx)
+            .await?
+            .ensure_writable()?;
+
+        let case_ids = sqlx::query_scalar!("SELECT id FROM test_cases WHERE suite_id = $1", id)
+            .fetch_all(&mut *tx)
+            .await?;
+        TraceabilityService::remove_links_in(TraceEntity::TestCase, &case_ids, &mut tx).await?;
+        sqlx::query!("DELETE FROM test_suites WHERE id = $1", id)
+            .execute(&mut *tx)
+            .await?;
+        AuditService::record_delete(&mut tx, audit, AuditEntity::TestSuite, id, &existing).await?;
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    /// Adds a test case to a suite, with its steps in the order given.
+    pub async fn create_case(
+        new_case: TestCaseCreate,
+        user_id: Uuid,
+        role: &UserRole,
+        audit: &AuditContext,
+        pool: &PgPool,
+    ) -> Result<TestCase, ServiceError> {
+        new_case.validate()?;
+
+        let mut tx = pool.begin().await?;
+        let suite = Self::fetch_suite(new_case.suite_id, false, &mut tx).await?;
+        Self::ensure_visible(suite.project_id, "Test suite", user_id, role, &mut tx).await?;
+        ProjectService::lock(suite.project_id, &mut tx)
+            .await?
+            .ensure_writable()?;
+
+        let id = sqlx::query_scalar!(
+            r#"
+            INSERT INTO test_cases (
+                suite_id, project_id, title, description, preconditions, expected_result,
+                created_by
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            RETURNING id
+            "#,
+            suite.id,
+            suite.project_id,
+            new_case.title,
+            new_case.description,
+            new_case.preconditions,
+            new_case.expected_result,
+            user_id
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+        Self::write_steps(id, &new_case.steps, &mut tx).await?;
+        let case = Self::fetch_case(id, false, &mut tx).await?;
+
+        AuditService::record_create(&mut tx, audit, AuditEntity::TestCase, id, &case).await?;
+        tx.commit().await?;
+
+        Ok(case)
+    }
+
+    /// The test cases of a suite or project, by title.
+    pub async fn list_cases(
+        filter: &TestCaseFilter,
+        user_id: Uuid,
+        role: &UserRole,
+        pool: &PgPool,
+    ) -> Result<Vec<TestCase>, ServiceError> {
+        let mut conn = pool.acquire().await?;
+        let project_id = match (filter.project_id, filter.suite_id) {
+            (_, Some(suite_id)) => {
+                let suite = Self::fetch_suite(suite_id, false, &mut conn).await?;
+                Self::ensure_visible(suite.project_id, "Test suite", user_id, role, &mut conn)
+                    .await?;
+                suite.project_id
+            }
+            (Some(project_id), None) => {
+                if !ProjectService::is_visible_to(project_id, user_id, role, &mut conn).await? {
+                    return Err(ServiceError::NotFound("Project not found".into()));
+                }
+                project_id
+            }
+            (None, None) => {
+                return Err(ServiceError::invalid_field(
+                    "project_id",
+                    "required",
+                    "project_id or suite_id is required",
+                ))
+            }
+        };
+
+        let ids = sqlx::query_scalar!(
+            r#"
+            SELECT id FROM test_cases
+            WHERE project_id = $1 AND ($2::uuid IS NULL OR suite_id = $2)
+            ORDER BY title, id
+            "#,
+            project_id,
+            filter.suite_id
+        )
+        .fetch_all(&mut *conn)
+        .await?;
+        let mut cases = Vec::with_capacity(ids.len());
+        for id in ids {
+            cases.push(Self::fetch_case(id, false, &mut conn).await?);
+        }
+
+        Ok(cases)
+    }
+
+    pub async fn get_case(
+        id: Uuid,
+        user_id: Uuid,
+        role: &UserRole,
+        pool: &PgPool,
+    ) -> Result<TestCase, ServiceError> {
+        let mut conn = pool.acquire().await?;
+        let case = Self::fetch_case(id, false, &mut conn).await?;
+        Self::ensure_visible(case.project_id, "Test case", user_id, role, &mut conn).await?;
+
+        Ok(case)
+    }
+
+    /// Changes a test case. Steps given replace all of its steps.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn update_case(
+        id: Uuid,
+        update: TestCaseUpdate,
+        expected_version: Option<i32>,
+        user_id: Uuid,
+        role: &UserRole,
+        audit: &AuditContext,
+        pool: &PgPool,
+    ) -> Result<TestCase, ServiceError> {
+        update.validate()?;
+        if let Patch::Value(steps) = &update.steps {
+            for step in steps {
+                step.validate()?;
+            }
+        }
+
+        let mut tx = pool.begin().await?;
+        let existing = Self::fetch_case(id, true, &mut tx).await?;
+        Self::ensure_visible(existing.project_id, "Test case", user_id, role, &mut tx).await?;
+        existing.check_version(expected_version)?;
+        ProjectService::lock(existing.project_id, &mut tx)
+            .await?
+            .ensure_writable()?;
+
+        let title = update
+            .title
+            .apply_required("title", existing.title.clone())?;
+        let description = update.description.apply(existing.description.clone());
+        let preconditions = update.preconditions.apply(existing.preconditions.clone());
+        let expected_result = update
+            .expected_result
+            .apply(existing.expected_result.clone());
+        sqlx::query!(
+            r#"
+            UPDATE test_cases
+            SET title = $2, description = $3, preconditions = $4, expected_result = $5,
+                updated_at = NOW(), version = version + 1
+            WHERE id = $1
+            "#,
+            id,
+            title,
+            description,
+            preconditions,
+            expected_result
+        )
+        .execute(&mut *tx)
+        .await?;
+        if !matches!(update.steps, Patch::Absent) {
+            let steps = update.steps.apply(None).unwrap_or_default();
+            sqlx::query!("DELETE FROM test_steps WHERE test_case_id = $1", id)
+                .execute(&mut *tx)
+                .await?;
+            Self::write_steps(id, &steps, &mut tx).await?;
+        }
+        let updated = Self::fetch_case(id, false, &mut tx).await?;
+
+        AuditService::record_update(
+            &mut tx,
+            audit,
+            AuditEntity::TestCase,
+            id,
+            &existing,
+            &updated,
+        )
+        .await?;
+        tx.commit().await?;
+
+        Ok(updated)
+    }
+
+    /// Removes a test case with its results and trace links.
+    pub async fn delete_case(
+        id: Uuid,
+        expected_version: Option<i32>,
+        user_id: Uuid,
+        role: &UserRole,
+        audit: &AuditContext,
+        pool: &PgPool,
+    ) -> Result<(), ServiceError> {
+        let mut tx = pool.begin().await?;
+        let existing = Self::fetch_case(id, true, &mut tx).await?;
+        Self::ensure_visible(existing.project_id, "Test case", user_id, role, &mut tx).await?;
+        existing.check_version(expected_version)?;
+        ProjectService::lock(existing.project_id, &mut tx)
+            .await?
+            .ensure_writable()?;
+
+        TraceabilityService::remove_links_in(TraceEntity::TestCase, &[id], &mut tx).await?;
+        sqlx::query!("DELETE FROM test_cases WHERE id = $1", id)
+            .execute(&mut *tx)
+            .await?;
+        AuditService::record_delete(&mut tx, audit, AuditEntity::TestCase, id, &existing).await?;
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    /// Starts a run of a project's test cases against a build.
+    pub async fn create_run(
+        new_run: TestRunCreate,
+        user_id: Uuid,
+        role: &UserRole,
+        audit: &AuditContext,
+        pool: &PgPool,
+    ) -> Result<TestRun, ServiceError> {
+        new_run.validate()?;
+
+        let mut tx = pool.begin().await?;
+        if !ProjectService::is_visible_to(new_run.project_id, user_id, role, &mut tx).await? {
+            return Err(ServiceError::NotFound("Project not found".into()));
+        }
+        ProjectService::lock(new_run.project_id, &mut tx)
+            .await?
+            .ensure_writable()?;
+
+        let run = sqlx::query_as!(
+            TestRun,
+            r#"
+            INSERT INTO test_runs (project_id, build, cycle, notes, created_by)
+            VALUES ($1, $2, $3, $4, $5)
+            RETURNING id, project_id, build, cycle, notes, created_by, created_at
+            "#,
+            new_run.project_id,
+            new_run.build,
+            new_run.cycle,
+            new_run.notes,
+            user_id
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+
+        AuditService::record_create(&mut tx, audit, AuditEntity::TestRun, run.id, &run).await?;
+        tx.commit().await?;
+
+        Ok(run)
+    }
+
+    /// A project's test runs, newest first.
+    pub async fn list_runs(
+        filter: &TestRunFilter,
+        user_id: Uuid,
+        role: &UserRole,
+        pool: &PgPool,
+    ) -> Result<Vec<TestRun>, ServiceError> {
+        let mut conn = pool.acquire().await?;
+        if !ProjectService::is_visible_to(filter.project_id, user_id, role, &mut conn).await? {
+            return Err(ServiceError::NotFound("Project not found".into()));
+        }
+
+        let runs = sqlx::query_as!(
+            TestRun,
+            r#"
+            SELECT id, project_id, build, cycle, notes, created_by, created_at
+            FROM test_runs
+            WHERE project_id = $1 AND ($2::text IS NULL OR cycle = $2)
+            ORDER BY created_at DESC, id
+            "#,
+            filter.project_id,
+            filter.cycle
+        )
+        .fetch_all(&mut *conn)
+        .await?;
+
+        Ok(runs)
+    }
+
+    /// A run with its results in the order they were recorded.
+    pub async fn get_run(
+        id: Uuid,
+        user_id: Uuid,
+        role: &UserRole,
+        pool: &PgPool,
+    ) -> Result<TestRunDetails, ServiceError> {
+        let mut conn = pool.acquire().await?;
+        let run = Self::fetch_run(id, &mut conn).await?;
+        Self::ensure_visible(run.project_id, "Test run", user_id, role, &mut conn).await?;
+
+        let results = sqlx::query_as!(
+            TestResult,
+            r#"
+            SELECT run_id, test_case_id, outcome as "outcome: TestOutcome", notes,
+                   tester_id, executed_at
+            FROM test_results
+            WHERE run_id = $1
+            ORDER BY executed_at, test_case_id
+            "#,
+            id
+        )
+        .fetch_all(&mut *conn)
+        .await?;
+
+        Ok(TestRunDetails {
+            run,
+            summary: TestTally::count(results.iter().map(|result| Some(result.outcome))),
+            results,
+        })
+    }
+
+    /// Records the outcome of a test case in a run, with the caller as the
+    /// tester. Recording the case again replaces its result.
+    pub async fn record_result(
+        run_id: Uuid,
+        record: TestResultRecord,
+        user_id: Uuid,
+        role: &UserRole,
+        audit: &AuditContext,
+        pool: &PgPool,
+    ) -> Result<TestResult, ServiceError> {
+        let mut tx = pool.begin().await?;
+        let run = Self::fetch_run(run_id, &mut tx).await?;
+        Self::ensure_visible(run.project_id, "Test run", user_id, role, &mut tx).await?;
+        ProjectService::lock(run.project_id, &mut tx)
+            .await?
+            .ensure_writable()?;
+
+        let case_project = sqlx::query_scalar!(
+            "SELECT project_id FROM test_cases WHERE id = $1",
+            record.test_case_id
+        )
+        .fetch_optional(&mut *tx)
+        .await?;
+        if case_project != Some(run.project_id) {
+            return Err(ServiceError::invalid_field(
+                "test_case_id",
+                "not_found",
+                "the test case does not belong to the run's project",
+            ));
+        }
+
+        let previous = sqlx::query_as!(
+            TestResult,
+            r#"
+            SELECT run_id, test_case_id, outcome as "outcome: TestOutcome", notes,
+                   tester_id, executed_at
+            FROM test_results
+            WHERE run_id = $1 AND test_case_id = $2
+            FOR UPDATE
+            "#,
+            run_id,
+            record.test_case_id
+        )
+        .fetch_optional(&mut *tx)
+        .await?;
+        let result = sqlx::query_as!(
+            TestResult,
+            r#"
+            INSERT INTO test_results (run_id, test_case_id, outcome, notes, tester_id)
+            VALUES ($1, $2, $3, $4, $5)
+            ON CONFLICT (run_id, test_case_id) DO UPDATE
+            SET outcome = EXCLUDED.outcome, notes = EXCLUDED.notes,
+                tester_id = EXCLUDED.tester_id, executed_at = NOW()
+            RETURNING run_id, test_case_id, outcome as "outcome: TestOutcome", notes,
+                      tester_id, executed_at
+            "#,
+            run_id,
+            record.test_case_id,
+            record.outcome as TestOutcome,
+            record.notes,
+            user_id
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+
+        match &previous {
+            Some(previous) => {
+                AuditService::record_update(
+                    &mut tx,
+                    audit,
+                    AuditEntity::TestResult,
+                    run_id,
+                    previous,
+                    &result,
+                )
+                .await?
+            }
+            None => {
+                AuditService::record_create(
+                    &mut tx,
+                    audit,
+                    AuditEntity::TestResult,
+                    run_id,
+                    &result,
+                )
+                .await?
+            }
+        }
+        tx.commit().await?;
+
+        Ok(result)
+    }
+
+    /// Pass rates of a project's test cases, each counted by its latest
+    /// result, overall and for every requirement but the rejected ones.
+    pub async fn report(
+        project_id: Uuid,
+        user_id: Uuid,
+        role: &UserRole,
+        pool: &PgPool,
+    ) -> Result<TestReport, ServiceError> {
+        let mut conn = pool.acquire().await?;
+        if !ProjectService::is_visible_to(project_id, user_id, role, &mut conn).await? {
+            return Err(ServiceError::NotFound("Project not found".into()));
+        }
+
+        let outcomes = Self::latest_outcomes(project_id, &mut conn).await?;
+        let requirements = sqlx::query!(
+            r#"
+            SELECT id, code as "code!", title
+            FROM requirements
+            WHERE project_id = $1 AND status <> 'rejected'
+            ORDER BY number
+            "#,
+            project_id
+        )
+        .fetch_all(&mut *conn)
+        .await?;
+        let mut realisations = TraceabilityService::realisations(project_id, &mut conn).await?;
+
+        let requirements = requirements
+            .into_iter()
+            .map(|requirement| {
+                let test_cases = realisations
+                    .remove(&requirement.id)
+                    .map(|realisation| realisation.test_cases)
+                    .unwrap_or_default();
+                let tally = TestTally::count(
+                    test_cases
+                        .iter()
+                        .filter(|id| outcomes.contains_key(id))
+                        .map(|id| outcomes[id]),
+                );
+
+                RequirementCoverage {
+                    requirement_id: requirement.id,
+                    code: requirement.code,
+                    title: requirement.title,
+                    untested: tally.test_cases == 0,
+                    tally,
+                }
+            })
+            .collect();
+
+        Ok(TestReport {
+            project_id,
+            overall: TestTally::count(outcomes.into_values()),
+            requirements,
+        })
+    }
+
+    /// Pass rate of a project's test cases by their latest results; `None`
+    /// before any ran.
+    pub(crate) async fn pass_rate_in(
+        project_id: Uuid,
+        conn: &mut PgConnection,
+    ) -> Result<Option<f64>, ServiceError> {
+        let outcomes = Self::latest_outcomes(project_id, conn).await?;
+        Ok(TestTally::count(outcomes.into_values()).pass_rate)
+    }
+
+    /// Every test case of a project with the outcome it last had in any run.
+    async fn latest_outcomes(
+        project_id: Uuid,
+        conn: &mut PgConnection,
+    ) -> Result<HashMap<Uuid, Option<TestOutcome>>, ServiceError> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT c.id, latest.outcome as "outcome?: TestOutcome"
+            FROM test_cases c
+            LEFT JOIN LATERAL (
+                SELECT r.outcome FROM test_results r
+                WHERE r.test_case_id = c.id
+                ORDER BY r.executed_at DESC
+                LIMIT 1
+            ) latest ON TRUE
+            WHERE c.project_id = $1
+            "#,
+            project_id
+        )
+        .fetch_all(conn)
+        .await?;
+
+        Ok(rows.into_iter().map(|row| (row.id, row.outcome)).collect())
+    }
+
+    /// Stores steps under positions 1, 2, … in the order given.
+    async fn write_steps(
+        test_case_id: Uuid,
+        steps: &[TestStep],
+        conn: &mut PgConnection,
+    ) -> Result<(), ServiceError> {
+        let actions: Vec<String> = steps.iter().map(|step| step.action.clone()).collect();
+        let expected: Vec<String> = steps
+            .iter()
+            .map(|step| step.expected_result.clone())
+            .collect();
+        sqlx::query!(
+            r#"
+            INSERT INTO test_steps (test_case_id, position, action, expected_result)
+            SELECT $1, step.position::int, step.action, step.expected_result
+            FROM UNNEST($2::text[], $3::text[])
+                 WITH ORDINALITY AS step(action, expected_result, position)
+            "#,
+            test_case_id,
+            &actions,
+            &expected
+        )
+        .execute(conn)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Loads a test suite, optionally locking it.
+    async fn fetch_suite(
+        id: Uuid,
+        for_update: bool,
+        conn: &mut PgConnection,
+    ) -> Result<TestSuite, ServiceError> {
+        let suite = if for_update {
+            sqlx::query_as!(
+                TestSuite,
+                r#"
+                SELECT id, project_id, name, description, level as "level: TestLevel",
+                       created_by, created_at, updated_at, version
+                FROM test_suites
+                WHERE id = $1
+                FOR UPDATE
+                "#,
+                id
+            )
+            .fetch_optional(conn)
+            .await?
+        } else {
+            sqlx::query_as!(
+                TestSuite,
+                r#"
+                SELECT id, project_id, name, description, level as "level: TestLevel",
+                       created_by, created_at, updated_at, version
+                FROM test_suites
+                WHERE id = $1
+                "#,
+                id
+            )
+            .fetch_optional(conn)
+            .await?
+        };
+
+        suite.ok_or(ServiceError::NotFound("Test suite not found".into()))
+    }
+
+    /// Loads a test case with its steps, optionally locking it.
+    async fn fetch_case(
+        id: Uuid,
+        for_update: bool,
+        conn: &mut PgConnection,
+    ) -> Result<TestCase, ServiceError> {
+        if for_update {
+            sqlx::query!("SELECT id FROM test_cases WHERE id = $1 FOR UPDATE", id)
+                .fetch_optional(&mut *conn)
+                .await?;
+        }
+        let row = sqlx::query!(
+            r#"
+            SELECT id, suite_id, project_id, title, description, preconditions,
+                   expected_result, created_by, created_at, updated_at, version
+            FROM test_cases
+            WHERE id = $1
+            "#,
+            id
+        )
+        .fetch_optional(&mut *conn)
+        .await?
+        .ok_or(ServiceError::NotFound("Test case not found".into()))?;
+        let steps = sqlx::query_as!(
+            TestStep,
+            r#"
+            SELECT action, expected_result
+            FROM test_steps
+            WHERE test_case_id = $1
+            ORDER BY position
+            "#,
+            id
+        )
+        .fetch_all(&mut *conn)
+        .await?;
+
+        Ok(TestCase {
+            id: row.id,
+            suite_id: row.suite_id,
+            project_id: row.project_id,
+            title: row.title,
+            description: row.description,
+            preconditions: row.preconditions,
+            steps,
+            expected_result: row.expected_result,
+            created_by: row.created_by,
+            created_at: row.created_at,
+            updated_at: row.updated_at,
+            version: row.version,
+        })
+    }
+
+    async fn fetch_run(id: Uuid, conn: &mut PgConnection) -> Result<TestRun, ServiceError> {
+        let run = sqlx::query_as!(
+            TestRun,
+            r#"
+            SELECT id, project_id, build, cycle, notes, created_by, created_at
+            FROM test_runs
+            WHERE id = $1
+            "#,
+            id
+        )
+        .fetch_optional(conn)
+        .await?;
+
+        run.ok_or(ServiceError::NotFound("Test run not found".into()))
+    }
+
+    /// Test records are hidden along with their project.
+    async fn ensure_visible(
+        project_id: Uuid,
+        label: &str,
+        user_id: Uuid,
+        role: &UserRole,
+        conn: &mut PgConnection,
+    ) -> Result<(), ServiceError> {
+        if ProjectService::is_visible_to(project_id, user_id, role, conn).await? {
+            Ok(())
+        } else {
+            Err(ServiceError::NotFound(format!("{} not found", label)))
+        }
+    }
+}
@@ -0,0 +1,226 @@
+use crate::errors::ServiceError;
+use crate::models::auth::{AuthResponse, Claims};
+use crate::models::user::User;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use chrono::{Duration, Utc};
+use jsonwebtoken::errors::ErrorKind;
+use jsonwebtoken::jwk::{
+    AlgorithmParameters, CommonParameters, EllipticCurve, Jwk, JwkSet, KeyAlgorithm,
+    OctetKeyPairParameters, OctetKeyPairType, PublicKeyUse, RSAKeyParameters, RSAKeyType,
+};
+use jsonwebtoken::{
+    decode, decode_header, encode, Algorithm, DecodingKey, EncodingKey, Header, Validation,
+};
+use rand::RngCore;
+use ring::signature::{Ed25519KeyPair, KeyPair, RsaKeyPair};
+use sha2::{Digest, Sha256};
+use std::env;
+use std::fs;
+use std::path::Path;
+
+/// Lifetime of issued access tokens.
+const TOKEN_TTL_HOURS: i64 = 24;
+
+struct SigningKey {
+    kid: String,
+    algorithm: Algorithm,
+    encoding: EncodingKey,
+    decoding: DecodingKey,
+    jwk: Jwk,
+}
+
+impl SigningKey {
+    /// Parses a PKCS#8 (RSA or Ed25519) or PKCS#1 (RSA) private key in PEM
+    /// form and derives the public JWK published for it.
+    fn from_pem(kid: &str, pem_bytes: &[u8]) -> Result<Self, String> {
+        let parsed = pem::parse(pem_bytes).map_err(|e| format!("key '{}': {}", kid, e))?;
+        let der = parsed.contents();
+
+        let (algorithm, encoding, parameters) = match parsed.tag() {
+            "RSA PRIVATE KEY" | "PRIVATE KEY" => {
+                if let Ok(key_pair) = Ed25519KeyPair::from_pkcs8_maybe_unchecked(der) {
+                    let x = URL_SAFE_NO_PAD.encode(key_pair.public_key().as_ref());
+                    (
+                        Algorithm::EdDSA,
+                        EncodingKey::from_ed_der(der),
+                        AlgorithmParameters::OctetKeyPair(OctetKeyPairParameters {
+                            key_type: OctetKeyPairType::OctetKeyPair,
+                            curve: EllipticCurve::Ed25519,
+                            x,
+                        }),
+                    )
+                } else {
+                    let key_pair = RsaKeyPair::from_pkcs8(der)
+                        .or_else(|_| RsaKeyPair::from_der(der))
+                        .map_err(|e| format!("key '{}': unsupported private key: {}", kid, e))?;
+                    let components: ring::rsa::PublicKeyComponents<Vec<u8>> =
+                        key_pair.public().into();
+                    let encoding = EncodingKey::from_rsa_pem(pem_bytes)
+                        .map_err(|e| format!("key '{}': {}", kid, e))?;
+                    (
+                        Algorithm::RS256,
+                        encoding,
+                        AlgorithmParameters::RSA(RSAKeyParameters {
+                            key_type: RSAKeyType::RSA,
+                            n: URL_SAFE_NO_PAD.encode(&components.n),
+                            e: URL_SAFE_NO_PAD.encode(&components.e),
+                        }),
+                    )
+                }
+            }
+            other => return Err(format!("key '{}': unsupported PEM block '{}'", kid, other)),
+        };
+
+        let jwk = Jwk {
+            common: CommonParameters {
+                public_key_use: Some(PublicKeyUse::Signature),
+                key_algorithm: Some(match algorithm {
+                    Algorithm::EdDSA => KeyAlgorithm::EdDSA,
+                    _ => KeyAlgorithm::RS256,
+                }),
+                key_id: Some(kid.to_string()),
+                ..Default::default()
+            },
+            algorithm: parameters,
+        };
+        let decoding = DecodingKey::from_jwk(&jwk).map_err(|e| format!("key '{}': {}", kid, e))?;
+
+        Ok(SigningKey {
+            kid: kid.to_string(),
+            algorithm,
+            encoding,
+            decoding,
+            jwk,
+        })
+    }
+}
+
+/// Issues and verifies access tokens with asymmetric keys loaded once at
+/// startup. Every loaded key is accepted for verification and published in
+/// the JWKS document; only the active key signs new tokens, so keys can be
+/// rotated by adding a new key, switching the active kid, and removing the
+/// old key once its tokens have expired.
+pub struct TokenService {
+    keys: Vec<SigningKey>,
+    active: usize,
+}
+
+impl TokenService {
+    /// Loads every `<kid>.pem` file in `JWT_KEYS_DIR`. `JWT_ACTIVE_KID`
+    /// selects the signing key and may be omitted when only one key exists.
+    pub fn from_env() -> Result<Self, String> {
+        let dir = env::var("JWT_KEYS_DIR").map_err(|_| "JWT_KEYS_DIR must be set".to_string())?;
+        let active_kid = env::var("JWT_ACTIVE_KID").ok();
+        Self::from_dir(Path::new(&dir), active_kid.as_deref())
+    }
+
+    pub fn from_dir(dir: &Path, active_kid: Option<&str>) -> Result<Self, String> {
+        let entries = fs::read_dir(dir)
+            .map_err(|e| format!("cannot read JWT_KEYS_DIR '{}': {}", dir.display(), e))?;
+
+        let mut pems = Vec::new();
+        for entry in entries {
+            let path = entry.map_err(|e| e.to_string())?.path();
+            if path.extension().and_then(|ext| ext.to_str()) != Some("pem") {
+                continue;
+            }
+            let kid = path
+                .file_stem()
+                .and_then(|stem| stem.to_str())
+                .ok_or_else(|| format!("invalid key file name '{}'", path.display()))?
+                .to_string();
+            let pem = fs::read(&path).map_err(|e| format!("{}: {}", path.display(), e))?;
+            pems.push((kid, pem));
+        }
+        pems.sort_by(|a, b| a.0.cmp(&b.0));
+
+        let active_kid = match active_kid {
+            Some(kid) => kid.to_string(),
+            None if pems.len() == 1 => pems[0].0.clone(),
+            None => return Err("JWT_ACTIVE_KID must be set when several keys are loaded".into()),
+        };
+
+        Self::from_pem_keys(&pems, &active_kid)
+    }
+
+    pub fn from_pem_keys(pems: &[(String, Vec<u8>)], active_kid: &str) -> Result<Self, String> {
+        let keys = pems
+            .iter()
+            .map(|(kid, pem)| SigningKey::from_pem(kid, pem))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let active = keys
+            .iter()
+            .position(|key| key.kid == active_kid)
+            .ok_or_else(|| format!("active signing key '{}' was not loaded", active_kid))?;
+
+        Ok(TokenService { keys, active })
+    }
+
+    pub fn active_kid(&self) -> &str {
+        &self.keys[self.active].kid
+    }
+
+    pub fn issue(&self, user: &User) -> Result<AuthResponse, ServiceError> {
+        let key = &self.keys[self.active];
+        let expiration = (Utc::now() + Duration::hours(TOKEN_TTL_HOURS)).timestamp() as usize;
+
+        let claims = Claims {
+            sub: user.id.to_string(),
+            email: user.email.clone(),
+            role: serde_json::to_string(&user.role)
+                .map_err(|_e| ServiceError::InternalServerError)?,
+            exp: expiration,
+        };
+
+        let mut header = Header::new(key.algorithm);
+        header.kid = Some(key.kid.clone());
+        let token = encode(&header, &claims, &key.encoding)?;
+
+        Ok(AuthResponse {
+            token,
+            token_type: "Bearer".to_string(),
+            user_id: user.id,
+        })
+    }
+
+    pub fn verify(&self, token: &str) -> Result<Claims, ServiceError> {
+        let header =
+            decode_header(token).map_err(|_| ServiceError::Unauthorized("Invalid token".into()))?;
+        let kid = header
+            .kid
+            .ok_or_else(|| ServiceError::Unauthorized("Invalid token".into()))?;
+        let key = self
+            .keys
+            .iter()
+            .find(|key| key.kid == kid && key.algorithm == header.alg)
+            .ok_or_else(|| ServiceError::Unauthorized("Invalid token".into()))?;
+
+        decode::<Claims>(token, &key.decoding, &Validation::new(key.algorithm))
+            .map(|data| data.claims)
+            .map_err(|e| match e.kind() {
+                ErrorKind::ExpiredSignature => ServiceError::TokenExpired,
+                _ => ServiceError::Unauthorized("Invalid token".into()),
+            })
+    }
+
+    pub fn jwks(&self) -> JwkSet {
+        JwkSet {
+            keys: self.keys.iter().map(|key| key.jwk.clone()).collect(),
+        }
+    }
+}
+
+/// Random URL-safe opaque token (256 bits).
+pub fn random_token() -> String {
+    let mut bytes = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    URL_SAFE_NO_PAD.encode(bytes)
+}
+
+/// Digest stored in place of an opaque token, so a database leak does not
+/// expose usable tokens.
+pub fn hash_token(token: &str) -> String {
+    URL_SAFE_NO_PAD.encode(Sha256::digest(token.as_bytes()))
+}
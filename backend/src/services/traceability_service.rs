@@ -0,0 +1,723 @@
+use std::collections::{HashMap, HashSet};
+
+use sqlx::{PgConnection, PgPool};
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::errors::ServiceError;
+use crate::models::audit::{AuditContext, AuditEntity};
+use crate::models::requirement::{RequirementPriority, RequirementStatus};
+use crate::models::traceability::{
+    DesignItem, DesignItemCreate, DesignItemUpdate, TraceEntity, TraceLink, TraceLinkCreate,
+    TraceLinkFilter, TraceRef, TraceabilityMatrix, TraceabilityRow,
+};
+use crate::models::user::UserRole;
+use crate::models::version::Versioned;
+use crate::services::audit_service::AuditService;
+use crate::services::export_service::wbs_key;
+use crate::services::project_service::ProjectService;
+
+pub struct TraceabilityService;
+
+/// The records a requirement traces to, directly or further down the chain.
+#[derive(Debug, Default)]
+pub(crate) struct Realisation {
+    pub design_items: HashSet<Uuid>,
+    pub tasks: HashSet<Uuid>,
+    pub test_cases: HashSet<Uuid>,
+}
+
+impl TraceabilityService {
+    pub async fn create_design_item(
+        new_item: DesignItemCreate,
+        user_id: Uuid,
+        role: &UserRole,
+        audit: &AuditContext,
+        pool: &PgPool,
+    ) -> Result<DesignItem, ServiceError> {
+        new_item.validate()?;
+
+        let mut tx = pool.begin().await?;
+        if !ProjectService::is_visible_to(new_item.project_id, user_id, role, &mut tx).await? {
+            return Err(ServiceError::NotFound("Project not found".into()));
+        }
+        ProjectService::lock(new_item.project_id, &mut tx)
+            .await?
+            .ensure_writable()?;
+
+        let item = sqlx::query_as!(
+            DesignItem,
+            r#"
+            INSERT INTO design_items (project_id, title, description, created_by)
+            VALUES ($1, $2, $3, $4)
+            RETURNING id, project_id, title, description, created_by,
+                      created_at, updated_at, version
+            "#,
+            new_item.project_id,
+            new_item.title,
+            new_item.description,
+            user_id
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+
+        AuditService::record_create(&mut tx, audit, AuditEntity::DesignItem, item.id, &item)
+            .await?;
+        tx.commit().await?;
+
+        Ok(item)
+    }
+
+    /// A project's design items by title.
+    pub async fn list_design_items(
+        project_id: Uuid,
+        user_id: Uuid,
+        role: &UserRole,
+        pool: &PgPool,
+    ) -> Result<Vec<DesignItem>, ServiceError> {
+        let mut conn = pool.acquire().await?;
+        if !ProjectService::is_visible_to(project_id, user_id, role, &mut conn).await? {
+            return Err(ServiceError::NotFound("Project not found".into()));
+        }
+
+        let items = sqlx::query_as!(
+            DesignItem,
+            r#"
+            SELECT id, project_id, title, description, created_by,
+                   created_at, updated_at, version
+            FROM design_items
+            WHERE project_id = $1
+            ORDER BY title, id
+            "#,
+            project_id
+        )
+        .fetch_all(&mut *conn)
+        .await?;
+
+        Ok(items)
+    }
+
+    pub async fn get_design_item(
+        id: Uuid,
+        user_id: Uuid,
+        role: &UserRole,
+        pool: &PgPool,
+    ) -> Result<DesignItem, ServiceError> {
+        let mut conn = pool.acquire().await?;
+        let item = Self::fetch_design_item(id, false, &mut conn).await?;
+        Self::ensure_visible(item.project_id, user_id, role, &mut conn).await?;
+
+        Ok(item)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub async fn update_design_item(
+        id: Uuid,
+        update: DesignItemUpdate,
+        expected_version: Option<i32>,
+        user_id: Uuid,
+        role: &UserRole,
+        audit: &AuditContext,
+        pool: &PgPool,
+    ) -> Result<DesignItem, ServiceError> {
+        update.validate()?;
+
+        let mut tx = pool.begin().await?;
+        let existing = Self::fetch_design_item(id, true, &mut tx).await?;
+        Self::ensure_visible(existing.project_id, user_id, role, &mut tx).await?;
+        existing.check_version(expected_version)?;
+        ProjectService::lock(existing.project_id, &mut tx)
+            .await?
+            .ensure_writable()?;
+
+        let title = update
+            .title
+            .apply_required("title", existing.title.clone())?;
+        let description = update.description.apply(existing.description.clone());
+        let updated = sqlx::query_as!(
+            DesignItem,
+            r#"
+            UPDATE design_items
+            SET title = $2, description = $3, updated_at = NOW(), version = version + 1
+            WHERE id = $1
+            RETURNING id, project_id, title, description, created_by,
+                      created_at, updated_at, version
+            "#,
+            id,
+            title,
+            description
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+
+        AuditService::record_update(
+            &mut tx,
+            audit,
+            AuditEntity::DesignItem,
+            id,
+            &existing,
+            &updated,
+        )
+        .await?;
+        tx.commit().await?;
+
+        Ok(updated)
+    }
+
+    /// Removes a design item and its trace links.
+    pub async fn delete_design_item(
+        id: Uuid,
+        expected_version: Option<i32>,
+        user_id: Uuid,
+        role: &UserRole,
+        audit: &AuditContext,
+        pool: &PgPool,
+    ) -> Result<(), ServiceError> {
+        let mut tx = pool.begin().await?;
+        let existing = Self::fetch_design_item(id, true, &mut tx).await?;
+        Self::ensure_visible(existing.project_id, user_id, role, &mut tx).await?;
+        existing.check_version(expected_version)?;
+        ProjectService::lock(existing.project_id, &mut tx)
+            .await?
+            .ensure_writable()?;
+
+        Self::remove_links_in(TraceEntity::DesignItem, &[id], &mut tx).await?;
+        sqlx::query!("DELETE FROM design_items WHERE id = $1", id)
+            .execute(&mut *tx)
+            .await?;
+        AuditService::record_delete(&mut tx, audit, AuditEntity::DesignItem, id, &existing).await?;
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    /// Traces a record to one that realises it. Both must belong to the same
+    /// project; linking them again is harmless.
+    pub async fn link(
+        link: TraceLinkCreate,
+        user_id: Uuid,
+        role: &UserRole,
+        audit: &AuditContext,
+        pool: &PgPool,
+    ) -> Result<TraceLink, ServiceError> {
+        if !link.source_type.may_trace_to(link.target_type) {
+            return Err(ServiceError::invalid_field(
+                "target_type",
+                "trace",
+                format!(
+                    "a {} cannot be traced to a {}",
+                    link.source_type.label().to_lowercase(),
+                    link.target_type.label().to_lowercase()
+                ),
+            ));
+        }
+
+        let mut tx = pool.begin().await?;
+        let project_id = match Self::entity_project(link.source_type, link.source_id, &mut tx)
+            .await?
+        {
+            Some(project_id)
+                if ProjectService::is_visible_to(project_id, user_id, role, &mut tx).await? =>
+            {
+                project_id
+            }
+            _ => {
+                return Err(ServiceError::NotFound(format!(
+                    "{} not found",
+                    link.source_type.label()
+                )))
+            }
+        };
+        match Self::entity_project(link.target_type, link.target_id, &mut tx).await? {
+            Some(owner) if owner == project_id => {}
+            Some(_) => {
+                return Err(ServiceError::invalid_field(
+                    "target_id",
+                    "project",
+                    format!("{} belongs to another project", link.target_type.label()),
+                ))
+            }
+            None => {
+                return Err(ServiceError::invalid_field(
+                    "target_id",
+                    "not_found",
+                    format!("{} not found", link.target_type.label()),
+                ))
+            }
+        }
+        ProjectService::lock(project_id, &mut tx)
+            .await?
+            .ensure_writable()?;
+
+        let created = sqlx::query_as!(
+            TraceLink,
+            r#"
+            INSERT INTO trace_links (
+                project_id, source_type, source_id, target_type, target_id, created_by
+            )
+            VALUES ($1, $2, $3, $4, $5, $6)
+            ON CONFLICT DO NOTHING
+            RETURNING id, project_id, source_type as "source_type: TraceEntity", source_id,
+                      target_type as "target_type: TraceEntity", target_id,
+                      created_by, created_at
+            "#,
+            project_id,
+            link.source_type as TraceEntity,
+            link.source_id,
+            link.target_type as TraceEntity,
+            link.target_id,
+            user_id
+        )
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        let link = match created {
+            Some(created) => {
+                AuditService::record_create(
+                    &mut tx,
+                    audit,
+                    AuditEntity::TraceLink,
+                    created.id,
+                    &created,
+                )
+                .await?;
+                created
+            }
+            None => {
+                sqlx::query_as!(
+                    TraceLink,
+                    r#"
+                SELECT id, project_id, source_type as "source_type: TraceEntity", source_id,
+                       target_type as "target_type: TraceEntity", target_id,
+                       created_by, created_at
+                FROM trace_links
+                WHERE source_type = $1 AND source_id = $2 AND target_type = $3 AND target_id = $4
+                "#,
+                    link.source_type as TraceEntity,
+                    link.source_id,
+                    link.target_type as TraceEntity,
+                    link.target_id
+                )
+                .fetch_one(&mut *tx)
+                .await?
+            }
+        };
+        tx.commit().await?;
+
+        Ok(link)
+    }
+
+    /// The links of a project, or those from or to one of its records,
+    /// oldest first.
+    pub async fn links(
+        filter: &TraceLinkFilter,
+        user_id: Uuid,
+        role: &UserRole,
+        pool: &PgPool,
+    ) -> Result<Vec<TraceLink>, ServiceError> {
+        let mut conn = pool.acquire().await?;
+        let (project_id, entity) = match (filter.entity_type, filter.entity_id, filter.project_id) {
+            (Some(entity_type), Some(entity_id), _) => {
+                match Self::entity_project(entity_type, entity_id, &mut conn).await? {
+                    Some(project_id) => (project_id, Some((entity_type, entity_id))),
+                    None => {
+                        return Err(ServiceError::NotFound(format!(
+                            "{} not found",
+                            entity_type.label()
+                        )))
+                    }
+                }
+            }
+            (None, None, Some(project_id)) => (project_id, None),
+            _ => {
+                return Err(ServiceError::invalid_field(
+                    "project_id",
+                    "required",
+                    "filter by project_id, or by entity_type and entity_id",
+                ))
+            }
+        };
+        if !ProjectService::is_visible_to(project_id, user_id, role, &mut conn).await? {
+            return Err(ServiceError::NotFound(match entity {
+                Some((entity_type, _)) => format!("{} not found", entity_type.label()),
+                None => "Project not found".into(),
+            }));
+        }
+
+        let (entity_type, entity_id) = entity.unzip();
+        let links = sqlx::query_as!(
+            TraceLink,
+            r#"
+            SELECT id, project_id, source_type as "source_type: TraceEntity", source_id,
+                   target_type as "target_type: TraceEntity", target_id,
+                   created_by, created_at
+            FROM trace_links
+            WHERE project_id = $1
+              AND ($2::trace_entity IS NULL
+                   OR (source_type = $2 AND source_id = $3)
+                   OR (target_type = $2 AND target_id = $3))
+            ORDER BY created_at, id
+            "#,
+            project_id,
+            entity_type as Option<TraceEntity>,
+            entity_id
+        )
+        .fetch_all(&mut *conn)
+        .await?;
+
+        Ok(links)
+    }
+
+    pub async fn unlink(
+        id: Uuid,
+        user_id: Uuid,
+        role: &UserRole,
+        audit: &AuditContext,
+        pool: &PgPool,
+    ) -> Result<(), ServiceError> {
+        let mut tx = pool.begin().await?;
+        let link = sqlx::query_as!(
+            TraceLink,
+            r#"
+            SELECT id, project_id, source_type as "source_type: TraceEntity", source_id,
+                   target_type as "target_type: TraceEntity", target_id,
+                   created_by, created_at
+            FROM trace_links
+            WHERE id = $1
+            FOR UPDATE
+            "#,
+            id
+        )
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or(ServiceError::NotFound("Trace link not found".into()))?;
+        if !ProjectService::is_visible_to(link.project_id, user_id, role, &mut tx).await? {
+            return Err(ServiceError::NotFound("Trace link not found".into()));
+        }
+        ProjectService::lock(link.project_id, &mut tx)
+            .await?
+            .ensure_writable()?;
+
+        sqlx::query!("DELETE FROM trace_links WHERE id = $1", id)
+            .execute(&mut *tx)
+            .await?;
+        AuditService::record_delete(&mut tx, audit, AuditEntity::TraceLink, id, &link).await?;
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    /// Every requirement of a project but the rejected ones, in number
+    /// order, with the design items, tasks and test cases that realise it.
+    /// Tasks in the trash do not count.
+    pub async fn matrix(
+        project_id: Uuid,
+        user_id: Uuid,
+        role: &UserRole,
+        pool: &PgPool,
+    ) -> Result<TraceabilityMatrix, ServiceError> {
+        let mut conn = pool.acquire().await?;
+        if !ProjectService::is_visible_to(project_id, user_id, role, &mut conn).await? {
+            return Err(ServiceError::NotFound("Project not found".into()));
+        }
+
+        let requirements = sqlx::query!(
+            r#"
+            SELECT id, code as "code!", title,
+                   priority as "priority: RequirementPriority",
+                   status as "status: RequirementStatus"
+            FROM requirements
+            WHERE project_id = $1 AND status <> 'rejected'
+            ORDER BY number
+            "#,
+            project_id
+        )
+        .fetch_all(&mut *conn)
+        .await?;
+        let design_index: HashMap<Uuid, TraceRef> = sqlx::query!(
+            "SELECT id, title FROM design_items WHERE project_id = $1",
+            project_id
+        )
+        .fetch_all(&mut *conn)
+        .await?
+        .into_iter()
+        .map(|item| {
+            (
+                item.id,
+                TraceRef {
+                    id: item.id,
+                    wbs: None,
+                    title: item.title,
+                },
+            )
+        })
+        .collect();
+        let task_index: HashMap<Uuid, TraceRef> = sqlx::query!(
+            "SELECT id, wbs, name FROM tasks WHERE project_id = $1 AND deleted_at IS NULL",
+            project_id
+        )
+        .fetch_all(&mut *conn)
+        .await?
+        .into_iter()
+        .map(|task| {
+            (
+                task.id,
+                TraceRef {
+                    id: task.id,
+                    wbs: task.wbs,
+                    title: task.name,
+                },
+            )
+        })
+        .collect();
+        let test_case_index: HashMap<Uuid, TraceRef> = sqlx::query!(
+            "SELECT id, title FROM test_cases WHERE project_id = $1",
+            project_id
+        )
+        .fetch_all(&mut *conn)
+        .await?
+        .into_iter()
+        .map(|case| {
+            (
+                case.id,
+                TraceRef {
+                    id: case.id,
+                    wbs: None,
+                    title: case.title,
+                },
+            )
+        })
+        .collect();
+        let mut realisations = Self::realisations(project_id, &mut conn).await?;
+
+        let refs = |ids: &HashSet<Uuid>, index: &HashMap<Uuid, TraceRef>| -> Vec<TraceRef> {
+            let mut refs: Vec<TraceRef> =
+                ids.iter().filter_map(|id| index.get(id).cloned()).collect();
+            refs.sort_by(|a, b| {
+                wbs_key(&a.wbs)
+                    .cmp(&wbs_key(&b.wbs))
+                    .then(a.title.cmp(&b.title))
+            });
+            refs
+        };
+        let rows: Vec<TraceabilityRow> = requirements
+            .into_iter()
+            .map(|requirement| {
+                let realisation = realisations.remove(&requirement.id).unwrap_or_default();
+                let tasks = refs(&realisation.tasks, &task_index);
+                let test_cases = refs(&realisation.test_cases, &test_case_index);
+
+                TraceabilityRow {
+                    requirement_id: requirement.id,
+                    code: requirement.code,
+                    title: requirement.title,
+                    priority: requirement.priority,
+                    status: requirement.status,
+                    design_items: refs(&realisation.design_items, &design_index),
+                    untasked: tasks.is_empty(),
+                    untested: test_cases.is_empty(),
+                    tasks,
+                    test_cases,
+                }
+            })
+            .collect();
+
+        Ok(TraceabilityMatrix {
+            project_id,
+            untasked: rows.iter().filter(|row| row.untasked).count(),
+            untested: rows.iter().filter(|row| row.untested).count(),
+            rows,
+        })
+    }
+
+    /// What realises each requirement of a project that has links, by id.
+    /// Tasks reached through a design item count, and so do test cases
+    /// reached through a task. Tasks in the trash are left out, along with
+    /// the test cases reached only through them.
+    pub(crate) async fn realisations(
+        project_id: Uuid,
+        conn: &mut PgConnection,
+    ) -> Result<HashMap<Uuid, Realisation>, ServiceError> {
+        let live_tasks: HashSet<Uuid> = sqlx::query_scalar!(
+            "SELECT id FROM tasks WHERE project_id = $1 AND deleted_at IS NULL",
+            project_id
+        )
+        .fetch_all(&mut *conn)
+        .await?
+        .into_iter()
+        .collect();
+
+        let mut targets: HashMap<(TraceEntity, Uuid), Vec<(TraceEntity, Uuid)>> = HashMap::new();
+        for link in sqlx::query!(
+            r#"
+            SELECT source_type as "source_type: TraceEntity", source_id,
+                   target_type as "target_type: TraceEntity", target_id
+            FROM trace_links
+            WHERE project_id = $1
+            "#,
+            project_id
+        )
+        .fetch_all(&mut *conn)
+        .await?
+        {
+            targets
+                .entry((link.source_type, link.source_id))
+                .or_default()
+                .push((link.target_type, link.target_id));
+        }
+        let linked = |source: (TraceEntity, Uuid), kind: TraceEntity| -> Vec<Uuid> {
+            targets
+                .get(&source)
+                .into_iter()
+                .flatten()
+                .filter(|(target_type, _)| *target_type == kind)
+                .map(|(_, target_id)| *target_id)
+                .collect()
+        };
+
+        let mut realisations = HashMap::new();
+        for &(source_type, requirement_id) in targets.keys() {
+            if source_type != TraceEntity::Requirement {
+                continue;
+            }
+            let source = (TraceEntity::Requirement, requirement_id);
+            let design_items: HashSet<Uuid> = linked(source, TraceEntity::DesignItem)
+                .into_iter()
+                .collect();
+            let mut tasks: HashSet<Uuid> = linked(source, TraceEntity::Task).into_iter().collect();
+            for design_id in &design_items {
+                tasks.extend(linked(
+                    (TraceEntity::DesignItem, *design_id),
+                    TraceEntity::Task,
+                ));
+            }
+            tasks.retain(|task_id| live_tasks.contains(task_id));
+            let mut test_cases: HashSet<Uuid> =
+                linked(source, TraceEntity::TestCase).into_iter().collect();
+            for task_id in &tasks {
+                test_cases.extend(linked((TraceEntity::Task, *task_id), TraceEntity::TestCase));
+            }
+
+            realisations.insert(
+                requirement_id,
+                Realisation {
+                    design_items,
+                    tasks,
+                    test_cases,
+                },
+            );
+        }
+
+        Ok(realisations)
+    }
+
+    /// Removes the links from or to records that are being deleted, in the
+    /// caller's transaction.
+    pub(crate) async fn remove_links_in(
+        entity_type: TraceEntity,
+        ids: &[Uuid],
+        conn: &mut PgConnection,
+    ) -> Result<(), ServiceError> {
+        sqlx::query!(
+            r#"
+            DELETE FROM trace_links
+            WHERE (source_type = $1 AND source_id = ANY($2))
+               OR (target_type = $1 AND target_id = ANY($2))
+            "#,
+            entity_type as TraceEntity,
+            ids
+        )
+        .execute(conn)
+        .await?;
+
+        Ok(())
+    }
+
+    /// The project a record belongs to, if it exists. Tasks in the trash
+    /// cannot be linked.
+    async fn entity_project(
+        entity_type: TraceEntity,
+        id: Uuid,
+        conn: &mut PgConnection,
+    ) -> Result<Option<Uuid>, ServiceError> {
+        let project_id = match entity_type {
+            TraceEntity::Requirement => {
+                sqlx::query_scalar!("SELECT project_id FROM requirements WHERE id = $1", id)
+                    .fetch_optional(conn)
+                    .await?
+            }
+            TraceEntity::DesignItem => {
+                sqlx::query_scalar!("SELECT project_id FROM design_items WHERE id = $1", id)
+                    .fetch_optional(conn)
+                    .await?
+            }
+            TraceEntity::Task => {
+                sqlx::query_scalar!(
+                    "SELECT project_id FROM tasks WHERE id = $1 AND deleted_at IS NULL",
+                    id
+                )
+                .fetch_optional(conn)
+                .await?
+            }
+            TraceEntity::TestCase => {
+                sqlx::query_scalar!("SELECT project_id FROM test_cases WHERE id = $1", id)
+                    .fetch_optional(conn)
+                    .await?
+            }
+        };
+
+        Ok(project_id)
+    }
+
+    /// Loads a design item, optionally locking it.
+    async fn fetch_design_item(
+        id: Uuid,
+        for_update: bool,
+        conn: &mut PgConnection,
+    ) -> Result<DesignItem, ServiceError> {
+        let item = if for_update {
+            sqlx::query_as!(
+                DesignItem,
+                r#"
+                SELECT id, project_id, title, description, created_by,
+                       created_at, updated_at, version
+                FROM design_items
+                WHERE id = $1
+                FOR UPDATE
+                "#,
+                id
+            )
+            .fetch_optional(conn)
+            .await?
+        } else {
+            sqlx::query_as!(
+                DesignItem,
+                r#"
+                SELECT id, project_id, title, description, created_by,
+                       created_at, updated_at, version
+                FROM design_items
+                WHERE id = $1
+                "#,
+                id
+            )
+            .fetch_optional(conn)
+            .await?
+        };
+
+        item.ok_or(ServiceError::NotFound("Design item not found".into()))
+    }
+
+    /// Design items are hidden along with their project.
+    async fn ensure_visible(
+        project_id: Uuid,
+        user_id: Uuid,
+        role: &UserRole,
+        conn: &mut PgConnection,
+    ) -> Result<(), ServiceError> {
+        if ProjectService::is_visible_to(project_id, user_id, role, conn).await? {
+            Ok(())
+        } else {
+            Err(ServiceError::NotFound("Design item not found".into()))
+        }
+    }
+}
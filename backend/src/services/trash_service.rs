@@ -0,0 +1,134 @@
+use crate::errors::ServiceError;
+use crate::models::audit::{AuditContext, AuditEntity};
+use crate::models::project::{Project, ProjectStatus};
+use crate::models::task::{Task, TaskStatus};
+use crate::models::traceability::TraceEntity;
+use crate::services::audit_service::AuditService;
+use crate::services::traceability_service::TraceabilityService;
+use chrono::{DateTime, Duration, Utc};
+use sqlx::PgPool;
+use std::env;
+
+const DEFAULT_RETENTION_DAYS: i64 = 30;
+
+/// How long trashed projects and tasks are kept before the `trash_purge` job
+/// removes them. Configured with `TRASH_RETENTION_DAYS` (default 30, `0`
+/// disables purging).
+#[derive(Debug, Clone, PartialEq)]
+pub struct PurgePolicy {
+    pub retention: Duration,
+}
+
+impl PurgePolicy {
+    pub fn from_env() -> Result<Option<Self>, String> {
+        let retention_days = match env::var("TRASH_RETENTION_DAYS") {
+            Ok(days) => days
+                .parse::<i64>()
+                .ok()
+                .filter(|days| *days >= 0)
+                .ok_or_else(|| format!("invalid TRASH_RETENTION_DAYS '{}'", days))?,
+            Err(_) => DEFAULT_RETENTION_DAYS,
+        };
+        if retention_days == 0 {
+            return Ok(None);
+        }
+
+        Ok(Some(PurgePolicy {
+            retention: Duration::days(retention_days),
+        }))
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct PurgeSummary {
+    pub projects: usize,
+    pub tasks: usize,
+}
+
+pub struct TrashService;
+
+impl TrashService {
+    /// Permanently removes projects and tasks that went to the trash before
+    /// `cutoff`. Tasks of a purged project go with it regardless of when
+    /// they were trashed.
+    pub async fn purge(cutoff: DateTime<Utc>, pool: &PgPool) -> Result<PurgeSummary, ServiceError> {
+        // Purges are performed by the system rather than a user
+        let audit = AuditContext::default();
+        let mut tx = pool.begin().await?;
+
+        let projects = sqlx::query_as!(
+            Project,
+            r#"
+            SELECT
+                id, name, description, start_date, end_date,
+                status as "status: ProjectStatus", budget, client_id,
+                created_at, updated_at, version, deleted_at, archived_at
+            FROM projects
+            WHERE deleted_at < $1
+            FOR UPDATE
+            "#,
+            cutoff
+        )
+        .fetch_all(&mut *tx)
+        .await?;
+        let project_ids: Vec<_> = projects.iter().map(|project| project.id).collect();
+
+        let tasks = sqlx::query_as!(
+            Task,
+            r#"
+            SELECT
+                id, name, description, project_id, assigned_to,
+                status as "status: TaskStatus", progress,
+                start_date, end_date, dependencies, parent_id, wbs, milestone,
+                created_at, updated_at, version, deleted_at
+            FROM tasks
+            WHERE deleted_at < $1 OR project_id = ANY($2)
+            FOR UPDATE
+            "#,
+            cutoff,
+            &project_ids
+        )
+        .fetch_all(&mut *tx)
+        .await?;
+        let task_ids: Vec<_> = tasks.iter().map(|task| task.id).collect();
+
+        for task in &tasks {
+            AuditService::record_purge(&mut tx, &audit, AuditEntity::Task, task.id, task).await?;
+        }
+        for project in &projects {
+            AuditService::record_purge(&mut tx, &audit, AuditEntity::Project, project.id, project)
+                .await?;
+        }
+
+        // Comments, documents and trace links cascade with their project, but
+        // not with a task
+        sqlx::query!(
+            "DELETE FROM comments WHERE target_type = 'task' AND target_id = ANY($1)",
+            &task_ids
+        )
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query!(
+            r#"
+            DELETE FROM document_links
+            WHERE target_type IN ('task', 'deliverable') AND target_id = ANY($1)
+            "#,
+            &task_ids
+        )
+        .execute(&mut *tx)
+        .await?;
+        TraceabilityService::remove_links_in(TraceEntity::Task, &task_ids, &mut tx).await?;
+        sqlx::query!("DELETE FROM tasks WHERE id = ANY($1)", &task_ids)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query!("DELETE FROM projects WHERE id = ANY($1)", &project_ids)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+
+        Ok(PurgeSummary {
+            projects: projects.len(),
+            tasks: tasks.len(),
+        })
+    }
+}
@@ -1,30 +1,60 @@
 use crate::errors::ServiceError;
-use crate::models::user::{User, UserCreate, UserUpdate};
-use bcrypt::{hash, DEFAULT_COST};
-use sqlx::PgPool;
+use crate::models::audit::{AuditContext, AuditEntity};
+use crate::models::job::JobKind;
+use crate::models::pagination::{Page, PageParams};
+use crate::models::patch::Patch;
+use crate::models::user::{
+    Invitation, InvitationAccept, InvitationCreate, ProfileUpdate, User, UserCreate, UserFilter,
+    UserRole, UserUpdate, USER_SORT_FIELDS,
+};
+use crate::models::version::Versioned;
+use crate::services::audit_service::AuditService;
+use crate::services::email_service::{EmailService, OutgoingEmail};
+use crate::services::job_service::JobService;
+use crate::services::token_service::{hash_token, random_token};
+use bcrypt::{hash, verify, DEFAULT_COST};
+use chrono::Utc;
+use serde_json::json;
+use sqlx::{PgConnection, PgPool, Postgres, QueryBuilder};
 use uuid::Uuid;
 
+/// How long an invitation link stays valid.
+const INVITATION_TTL_DAYS: i32 = 7;
+
 pub struct UserService;
 
 impl UserService {
-    pub async fn create(user: UserCreate, pool: &PgPool) -> Result<User, ServiceError> {
+    pub async fn create(
+        user: UserCreate,
+        audit: &AuditContext,
+        pool: &PgPool,
+    ) -> Result<User, ServiceError> {
         let password_hash = hash(user.password.as_bytes(), DEFAULT_COST)?;
+        let mut tx = pool.begin().await?;
 
         let user = sqlx::query_as!(
             User,
             r#"
             INSERT INTO users (email, password_hash, full_name, role)
             VALUES ($1, $2, $3, $4)
-            RETURNING id, email, password_hash, full_name, role as "role: _", created_at, updated_at
+            RETURNING id, email, password_hash, full_name, role as "role: _", deactivated_at, created_at, updated_at, version
             "#,
             user.email,
             password_hash,
             user.full_name,
             user.role as _
         )
-        .fetch_one(pool)
+        .fetch_one(&mut *tx)
         .await?;
 
+        // Self-registration has no prior actor; attribute it to the new account
+        let audit = AuditContext {
+            actor_id: audit.actor_id.or(Some(user.id)),
+            request_id: audit.request_id.clone(),
+        };
+        AuditService::record_create(&mut tx, &audit, AuditEntity::User, user.id, &user).await?;
+        tx.commit().await?;
+
         Ok(user)
     }
 
@@ -32,7 +62,7 @@ impl UserService {
         let user = sqlx::query_as!(
             User,
             r#"
-            SELECT id, email, password_hash, full_name, role as "role: _", created_at, updated_at
+            SELECT id, email, password_hash, full_name, role as "role: _", deactivated_at, created_at, updated_at, version
             FROM users
             WHERE id = $1
             "#,
@@ -45,64 +75,645 @@ impl UserService {
         Ok(user)
     }
 
-    pub async fn update(id: Uuid, user: UserUpdate, pool: &PgPool) -> Result<User, ServiceError> {
-        let current_user = Self::get_by_id(id, pool).await?;
+    /// Locks the row for the rest of the transaction and returns it.
+    async fn lock(id: Uuid, conn: &mut PgConnection) -> Result<User, ServiceError> {
+        let user = sqlx::query_as!(
+            User,
+            r#"
+            SELECT id, email, password_hash, full_name, role as "role: _", deactivated_at, created_at, updated_at, version
+            FROM users
+            WHERE id = $1
+            FOR UPDATE
+            "#,
+            id
+        )
+        .fetch_optional(conn)
+        .await?
+        .ok_or(ServiceError::NotFound("User not found".into()))?;
+
+        Ok(user)
+    }
+
+    pub async fn update(
+        id: Uuid,
+        user: UserUpdate,
+        expected_version: Option<i32>,
+        audit: &AuditContext,
+        pool: &PgPool,
+    ) -> Result<User, ServiceError> {
+        let mut tx = pool.begin().await?;
+        let current_user = Self::lock(id, &mut tx).await?;
+        current_user.check_version(expected_version)?;
 
-        let password_hash = if let Some(password) = user.password {
-            hash(password.as_bytes(), DEFAULT_COST)?
-        } else {
-            current_user.password_hash
+        let email = user
+            .email
+            .apply_required("email", current_user.email.clone())?;
+        let password_hash = match user.password {
+            Patch::Absent => current_user.password_hash.clone(),
+            Patch::Null => {
+                return Err(ServiceError::invalid_field(
+                    "password",
+                    "required",
+                    "cannot be null",
+                ))
+            }
+            Patch::Value(password) => Some(hash(password.as_bytes(), DEFAULT_COST)?),
         };
+        let full_name = user
+            .full_name
+            .apply_required("full_name", current_user.full_name.clone())?;
+        let role = user
+            .role
+            .apply_required("role", current_user.role.clone())?;
 
         let user = sqlx::query_as!(
             User,
             r#"
             UPDATE users
             SET
-                email = COALESCE($1, email),
+                email = $1,
                 password_hash = $2,
-                full_name = COALESCE($3, full_name),
-                role = COALESCE($4, role),
-                updated_at = CURRENT_TIMESTAMP
+                full_name = $3,
+                role = $4,
+                updated_at = CURRENT_TIMESTAMP,
+                version = version + 1
             WHERE id = $5
-            RETURNING id, email, password_hash, full_name, role as "role: _", created_at, updated_at
+            RETURNING id, email, password_hash, full_name, role as "role: _", deactivated_at, created_at, updated_at, version
             "#,
-            user.email,
+            email,
             password_hash,
-            user.full_name,
-            user.role as _,
+            full_name,
+            role as _,
             id
         )
-        .fetch_one(pool)
+        .fetch_one(&mut *tx)
         .await?;
 
+        AuditService::record_update(&mut tx, audit, AuditEntity::User, id, &current_user, &user)
+            .await?;
+        tx.commit().await?;
+
         Ok(user)
     }
 
-    pub async fn delete(id: Uuid, pool: &PgPool) -> Result<(), ServiceError> {
-        let result = sqlx::query!("DELETE FROM users WHERE id = $1", id)
-            .execute(pool)
+    /// Deactivated users keep their history (tasks, approvals) but can no
+    /// longer sign in or use existing tokens.
+    pub async fn deactivate(
+        id: Uuid,
+        expected_version: Option<i32>,
+        audit: &AuditContext,
+        pool: &PgPool,
+    ) -> Result<User, ServiceError> {
+        let mut tx = pool.begin().await?;
+        let current_user = Self::lock(id, &mut tx).await?;
+        current_user.check_version(expected_version)?;
+
+        let user = sqlx::query_as!(
+            User,
+            r#"
+            UPDATE users
+            SET deactivated_at = COALESCE(deactivated_at, CURRENT_TIMESTAMP),
+                updated_at = CURRENT_TIMESTAMP,
+                version = version + 1
+            WHERE id = $1
+            RETURNING id, email, password_hash, full_name, role as "role: _", deactivated_at, created_at, updated_at, version
+            "#,
+            id
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+
+        AuditService::record_update(&mut tx, audit, AuditEntity::User, id, &current_user, &user)
             .await?;
+        tx.commit().await?;
 
-        if result.rows_affected() == 0 {
-            return Err(ServiceError::NotFound("User not found".into()));
-        }
+        Ok(user)
+    }
 
-        Ok(())
+    pub async fn reactivate(
+        id: Uuid,
+        expected_version: Option<i32>,
+        audit: &AuditContext,
+        pool: &PgPool,
+    ) -> Result<User, ServiceError> {
+        let mut tx = pool.begin().await?;
+        let current_user = Self::lock(id, &mut tx).await?;
+        current_user.check_version(expected_version)?;
+
+        let user = sqlx::query_as!(
+            User,
+            r#"
+            UPDATE users
+            SET deactivated_at = NULL, updated_at = CURRENT_TIMESTAMP, version = version + 1
+            WHERE id = $1
+            RETURNING id, email, password_hash, full_name, role as "role: _", deactivated_at, created_at, updated_at, version
+            "#,
+            id
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+
+        AuditService::record_update(&mut tx, audit, AuditEntity::User, id, &current_user, &user)
+            .await?;
+        tx.commit().await?;
+
+        Ok(user)
     }
 
-    pub async fn list(pool: &PgPool) -> Result<Vec<User>, ServiceError> {
-        let users = sqlx::query_as!(
+    pub async fn update_profile(
+        id: Uuid,
+        update: ProfileUpdate,
+        expected_version: Option<i32>,
+        audit: &AuditContext,
+        pool: &PgPool,
+    ) -> Result<User, ServiceError> {
+        let mut tx = pool.begin().await?;
+        let current_user = Self::lock(id, &mut tx).await?;
+        current_user.check_version(expected_version)?;
+
+        let password_hash = match update.new_password {
+            Some(new_password) => {
+                let current_hash = current_user.password_hash.as_deref().ok_or_else(|| {
+                    ServiceError::UnprocessableEntity(
+                        "Accounts signed in through single sign-on have no password".into(),
+                    )
+                })?;
+                let current_password = update.current_password.ok_or_else(|| {
+                    ServiceError::invalid_field("current_password", "required", "is required")
+                })?;
+                if !verify(current_password.as_bytes(), current_hash)? {
+                    return Err(ServiceError::InvalidCredentials);
+                }
+                Some(hash(new_password.as_bytes(), DEFAULT_COST)?)
+            }
+            None => current_user.password_hash.clone(),
+        };
+
+        let user = sqlx::query_as!(
             User,
             r#"
-            SELECT id, email, password_hash, full_name, role as "role: _", created_at, updated_at
-            FROM users
+            UPDATE users
+            SET
+                full_name = COALESCE($1, full_name),
+                password_hash = $2,
+                updated_at = CURRENT_TIMESTAMP,
+                version = version + 1
+            WHERE id = $3
+            RETURNING id, email, password_hash, full_name, role as "role: _", deactivated_at, created_at, updated_at, version
+            "#,
+            update.full_name,
+            password_hash,
+            id
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+
+        AuditService::record_update(&mut tx, audit, AuditEntity::User, id, &current_user, &user)
+            .await?;
+        tx.commit().await?;
+
+        Ok(user)
+    }
+
+    /// Records an invitation and queues the email with its single-use link,
+    /// so a failed send is retried. An expired invitation for the address is
+    /// revoked first.
+    pub async fn invite(
+        invitation: InvitationCreate,
+        audit: &AuditContext,
+        pool: &PgPool,
+    ) -> Result<Invitation, ServiceError> {
+        let invited_by = audit.actor_id.ok_or(ServiceError::Forbidden)?;
+        let existing =
+            sqlx::query_scalar!("SELECT id FROM users WHERE email = $1", invitation.email)
+                .fetch_optional(pool)
+                .await?;
+        if existing.is_some() {
+            return Err(ServiceError::Conflict(
+                "A user with this email already exists".into(),
+            ));
+        }
+
+        let mut tx = pool.begin().await?;
+        let expired = sqlx::query_as!(
+            Invitation,
+            r#"
+            SELECT id, email, full_name, role as "role: _", invited_by, expires_at,
+                   accepted_at, revoked_at, created_at
+            FROM user_invitations
+            WHERE email = $1 AND accepted_at IS NULL AND revoked_at IS NULL
+              AND expires_at <= NOW()
+            FOR UPDATE
+            "#,
+            invitation.email
+        )
+        .fetch_optional(&mut *tx)
+        .await?;
+        if let Some(expired) = expired {
+            Self::revoke_in(&expired, audit, &mut tx).await?;
+        }
+
+        let created = sqlx::query_as!(
+            Invitation,
+            r#"
+            INSERT INTO user_invitations (email, full_name, role, token_hash, invited_by, expires_at)
+            VALUES ($1, $2, $3, $4, $5, NOW() + make_interval(days => $6))
+            ON CONFLICT (email) WHERE accepted_at IS NULL AND revoked_at IS NULL DO NOTHING
+            RETURNING id, email, full_name, role as "role: _", invited_by, expires_at,
+                      accepted_at, revoked_at, created_at
+            "#,
+            invitation.email,
+            invitation.full_name,
+            invitation.role as _,
+            // Nobody holds this token; the email job issues the one it sends
+            hash_token(&random_token()),
+            invited_by,
+            INVITATION_TTL_DAYS
+        )
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| {
+            ServiceError::Conflict("This email already has a pending invitation".into())
+        })?;
+
+        AuditService::record_create(
+            &mut tx,
+            audit,
+            AuditEntity::Invitation,
+            created.id,
+            &created,
+        )
+        .await?;
+        JobService::enqueue_in(
+            JobKind::InvitationEmail,
+            json!({ "invitation_id": created.id }),
+            None,
+            Utc::now(),
+            &mut tx,
+        )
+        .await?;
+        tx.commit().await?;
+
+        Ok(created)
+    }
+
+    /// Emails a pending invitation its link with a freshly issued token, so
+    /// links from earlier attempts stop working. Returns false without
+    /// sending when the invitation was accepted, revoked or expired since.
+    pub(crate) async fn send_invitation(
+        id: Uuid,
+        email: &EmailService,
+        pool: &PgPool,
+    ) -> Result<bool, ServiceError> {
+        let token = random_token();
+        let invitation = sqlx::query_as!(
+            Invitation,
+            r#"
+            UPDATE user_invitations SET token_hash = $2
+            WHERE id = $1 AND accepted_at IS NULL AND revoked_at IS NULL AND expires_at > NOW()
+            RETURNING id, email, full_name, role as "role: _", invited_by, expires_at,
+                      accepted_at, revoked_at, created_at
+            "#,
+            id,
+            hash_token(&token)
+        )
+        .fetch_optional(pool)
+        .await?;
+        let Some(invitation) = invitation else {
+            return Ok(false);
+        };
+
+        email
+            .send(OutgoingEmail {
+                to: invitation.email,
+                subject: "You have been invited to Waterfall Manager".to_string(),
+                body: format!(
+                    "Hello {},\n\nYou have been invited to join Waterfall Manager. \
+                     Set your password here (the link expires on {}):\n\n{}/accept-invite?token={}\n",
+                    invitation.full_name,
+                    invitation.expires_at.format("%Y-%m-%d"),
+                    email.app_base_url,
+                    token
+                ),
+            })
+            .await?;
+
+        Ok(true)
+    }
+
+    pub async fn list_invitations(pool: &PgPool) -> Result<Vec<Invitation>, ServiceError> {
+        let invitations = sqlx::query_as!(
+            Invitation,
+            r#"
+            SELECT id, email, full_name, role as "role: _", invited_by, expires_at,
+                   accepted_at, revoked_at, created_at
+            FROM user_invitations
+            WHERE accepted_at IS NULL AND revoked_at IS NULL
             ORDER BY created_at DESC
             "#
         )
         .fetch_all(pool)
         .await?;
 
-        Ok(users)
+        Ok(invitations)
+    }
+
+    pub async fn revoke_invitation(
+        id: Uuid,
+        audit: &AuditContext,
+        pool: &PgPool,
+    ) -> Result<(), ServiceError> {
+        let mut tx = pool.begin().await?;
+        let pending = sqlx::query_as!(
+            Invitation,
+            r#"
+            SELECT id, email, full_name, role as "role: _", invited_by, expires_at,
+                   accepted_at, revoked_at, created_at
+            FROM user_invitations
+            WHERE id = $1 AND accepted_at IS NULL AND revoked_at IS NULL
+            FOR UPDATE
+            "#,
+            id
+        )
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or(ServiceError::NotFound("Invitation not found".into()))?;
+
+        Self::revoke_in(&pending, audit, &mut tx).await?;
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    async fn revoke_in(
+        pending: &Invitation,
+        audit: &AuditContext,
+        conn: &mut PgConnection,
+    ) -> Result<(), ServiceError> {
+        let revoked = sqlx::query_as!(
+            Invitation,
+            r#"
+            UPDATE user_invitations SET revoked_at = CURRENT_TIMESTAMP
+            WHERE id = $1
+            RETURNING id, email, full_name, role as "role: _", invited_by, expires_at,
+                      accepted_at, revoked_at, created_at
+            "#,
+            pending.id
+        )
+        .fetch_one(&mut *conn)
+        .await?;
+
+        AuditService::record_update(
+            conn,
+            audit,
+            AuditEntity::Invitation,
+            pending.id,
+            pending,
+            &revoked,
+        )
+        .await
+    }
+
+    /// Redeems an invitation token: creates the account with the chosen
+    /// password and marks the invitation as used, atomically.
+    pub async fn accept_invitation(
+        accept: InvitationAccept,
+        audit: &AuditContext,
+        pool: &PgPool,
+    ) -> Result<User, ServiceError> {
+        let mut tx = pool.begin().await?;
+
+        let pending = sqlx::query_as!(
+            Invitation,
+            r#"
+            SELECT id, email, full_name, role as "role: _", invited_by, expires_at,
+                   accepted_at, revoked_at, created_at
+            FROM user_invitations
+            WHERE token_hash = $1 AND accepted_at IS NULL AND revoked_at IS NULL
+              AND expires_at > NOW()
+            FOR UPDATE
+            "#,
+            hash_token(&accept.token)
+        )
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| ServiceError::BadRequest("Invalid or expired invitation".into()))?;
+
+        let accepted = sqlx::query_as!(
+            Invitation,
+            r#"
+            UPDATE user_invitations SET accepted_at = CURRENT_TIMESTAMP
+            WHERE id = $1
+            RETURNING id, email, full_name, role as "role: _", invited_by, expires_at,
+                      accepted_at, revoked_at, created_at
+            "#,
+            pending.id
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+
+        let password_hash = hash(accept.password.as_bytes(), DEFAULT_COST)?;
+        let user = sqlx::query_as!(
+            User,
+            r#"
+            INSERT INTO users (email, password_hash, full_name, role)
+            VALUES ($1, $2, $3, $4)
+            RETURNING id, email, password_hash, full_name, role as "role: _", deactivated_at, created_at, updated_at, version
+            "#,
+            accepted.email,
+            password_hash,
+            accepted.full_name,
+            accepted.role.clone() as _
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+
+        let audit = AuditContext {
+            actor_id: Some(user.id),
+            request_id: audit.request_id.clone(),
+        };
+        AuditService::record_update(
+            &mut tx,
+            &audit,
+            AuditEntity::Invitation,
+            pending.id,
+            &pending,
+            &accepted,
+        )
+        .await?;
+        AuditService::record_create(&mut tx, &audit, AuditEntity::User, user.id, &user).await?;
+
+        tx.commit().await?;
+        Ok(user)
+    }
+
+    /// Finds the user linked to an OIDC identity, creating one on first
+    /// sign-in. An existing password account is linked by email only when
+    /// the identity provider has verified that address. The name and role
+    /// are refreshed from the provider's claims, and the user only changes
+    /// when they differ.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn provision_oidc_user(
+        issuer: &str,
+        subject: &str,
+        email: &str,
+        email_verified: bool,
+        full_name: &str,
+        role: UserRole,
+        audit: &AuditContext,
+        pool: &PgPool,
+    ) -> Result<User, ServiceError> {
+        let mut tx = pool.begin().await?;
+
+        let linked_id = sqlx::query_scalar!(
+            "SELECT id FROM users WHERE oidc_issuer = $1 AND oidc_subject = $2",
+            issuer,
+            subject
+        )
+        .fetch_optional(&mut *tx)
+        .await?;
+        if let Some(id) = linked_id {
+            let before = Self::lock(id, &mut tx).await?;
+            if before.full_name == full_name && before.role == role {
+                tx.commit().await?;
+                return Ok(before);
+            }
+
+            let user = sqlx::query_as!(
+                User,
+                r#"
+                UPDATE users
+                SET full_name = $2, role = $3, updated_at = CURRENT_TIMESTAMP, version = version + 1
+                WHERE id = $1
+                RETURNING id, email, password_hash, full_name, role as "role: _", deactivated_at, created_at, updated_at, version
+                "#,
+                id,
+                full_name,
+                role as _
+            )
+            .fetch_one(&mut *tx)
+            .await?;
+
+            let audit = AuditContext {
+                actor_id: Some(id),
+                request_id: audit.request_id.clone(),
+            };
+            AuditService::record_update(&mut tx, &audit, AuditEntity::User, id, &before, &user)
+                .await?;
+            tx.commit().await?;
+            return Ok(user);
+        }
+
+        let existing = sqlx::query_scalar!("SELECT id FROM users WHERE email = $1", email)
+            .fetch_optional(&mut *tx)
+            .await?;
+        if let Some(id) = existing {
+            if !email_verified {
+                return Err(ServiceError::Unauthorized(
+                    "An account with this email already exists".into(),
+                ));
+            }
+
+            let before = Self::lock(id, &mut tx).await?;
+            let user = sqlx::query_as!(
+                User,
+                r#"
+                UPDATE users
+                SET oidc_issuer = $1, oidc_subject = $2, role = $4, updated_at = CURRENT_TIMESTAMP, version = version + 1
+                WHERE id = $3 AND oidc_issuer IS NULL
+                RETURNING id, email, password_hash, full_name, role as "role: _", deactivated_at, created_at, updated_at, version
+                "#,
+                issuer,
+                subject,
+                id,
+                role as _
+            )
+            .fetch_optional(&mut *tx)
+            .await?
+            .ok_or_else(|| {
+                ServiceError::Unauthorized(
+                    "This email is linked to another identity provider account".into(),
+                )
+            })?;
+
+            let audit = AuditContext {
+                actor_id: Some(id),
+                request_id: audit.request_id.clone(),
+            };
+            AuditService::record_update(&mut tx, &audit, AuditEntity::User, id, &before, &user)
+                .await?;
+            tx.commit().await?;
+            return Ok(user);
+        }
+
+        let user = sqlx::query_as!(
+            User,
+            r#"
+            INSERT INTO users (email, password_hash, full_name, role, oidc_issuer, oidc_subject)
+            VALUES ($1, NULL, $2, $3, $4, $5)
+            RETURNING id, email, password_hash, full_name, role as "role: _", deactivated_at, created_at, updated_at, version
+            "#,
+            email,
+            full_name,
+            role as _,
+            issuer,
+            subject
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+
+        let audit = AuditContext {
+            actor_id: Some(user.id),
+            request_id: audit.request_id.clone(),
+        };
+        AuditService::record_create(&mut tx, &audit, AuditEntity::User, user.id, &user).await?;
+        tx.commit().await?;
+
+        Ok(user)
+    }
+
+    pub async fn list(
+        params: &PageParams,
+        filter: &UserFilter,
+        pool: &PgPool,
+    ) -> Result<Page<User>, ServiceError> {
+        let order_by = params.order_by(USER_SORT_FIELDS, "-created_at")?;
+
+        let mut count = QueryBuilder::new("SELECT COUNT(*) FROM users WHERE TRUE");
+        Self::push_filters(&mut count, filter);
+        let total: i64 = count.build_query_scalar().fetch_one(pool).await?;
+
+        let mut query = QueryBuilder::new(
+            r#"
+            SELECT id, email, password_hash, full_name, role, deactivated_at, created_at, updated_at, version
+            FROM users
+            WHERE TRUE"#,
+        );
+        Self::push_filters(&mut query, filter);
+        query
+            .push(order_by)
+            .push(" LIMIT ")
+            .push_bind(params.per_page())
+            .push(" OFFSET ")
+            .push_bind(params.offset()?);
+
+        let users = query.build_query_as::<User>().fetch_all(pool).await?;
+
+        Ok(Page::new(users, total, params))
+    }
+
+    fn push_filters<'a>(query: &mut QueryBuilder<'a, Postgres>, filter: &'a UserFilter) {
+        if let Some(role) = &filter.role {
+            query.push(" AND role = ").push_bind(role);
+        }
+        match filter.active {
+            Some(true) => {
+                query.push(" AND deactivated_at IS NULL");
+            }
+            Some(false) => {
+                query.push(" AND deactivated_at IS NOT NULL");
+            }
+            None => {}
+        }
     }
 }
@@ -0,0 +1,476 @@
+use chrono::{DateTime, Utc};
+use reqwest::header::CONTENT_TYPE;
+use reqwest::Url;
+use ring::hmac;
+use serde::Serialize;
+use serde_json::json;
+use sqlx::{PgConnection, PgPool, Postgres, QueryBuilder};
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::errors::ServiceError;
+use crate::models::audit::{AuditContext, AuditEntity};
+use crate::models::pagination::{Page, PageParams};
+use crate::models::patch::Patch;
+use crate::models::webhook::{
+    DeliveryFilter, DeliveryStatus, Webhook, WebhookCreate, WebhookCreated, WebhookDelivery,
+    WebhookEvent, WebhookUpdate, DELIVERY_SORT_FIELDS,
+};
+use crate::services::audit_service::AuditService;
+use crate::services::job_service::JobService;
+use crate::services::token_service::random_token;
+
+/// Attempts before a delivery is given up; with the job backoff the last
+/// one comes about two hours after the first.
+const MAX_ATTEMPTS: i32 = 8;
+const REQUEST_TIMEOUT_SECONDS: u64 = 10;
+/// How much of a receiver's response is kept in the delivery log.
+const RESPONSE_BODY_LIMIT: usize = 1024;
+
+/// A due delivery together with what is needed to send it.
+struct DueDelivery {
+    id: Uuid,
+    event: WebhookEvent,
+    payload: serde_json::Value,
+    attempts: i32,
+    url: String,
+    secret: String,
+}
+
+/// What came back from one attempt.
+struct Attempt {
+    response_status: Option<i32>,
+    response_body: Option<String>,
+    error: Option<String>,
+}
+
+pub struct WebhookService;
+
+impl WebhookService {
+    /// HTTP client for deliveries; receivers get a few seconds to answer.
+    pub fn http_client() -> reqwest::Client {
+        reqwest::Client::builder()
+            .timeout(std::time::Duration::from_secs(REQUEST_TIMEOUT_SECONDS))
+            .user_agent("waterfall-manager-webhooks")
+            .build()
+            .expect("Failed to build webhook HTTP client")
+    }
+
+    /// Hex HMAC-SHA256 of `{timestamp}.{body}` under `secret`, as sent in
+    /// `X-Webhook-Signature`. Including the timestamp lets receivers reject
+    /// replayed requests.
+    pub fn sign(secret: &str, timestamp: i64, body: &[u8]) -> String {
+        let key = hmac::Key::new(hmac::HMAC_SHA256, secret.as_bytes());
+        let mut context = hmac::Context::with_key(&key);
+        context.update(timestamp.to_string().as_bytes());
+        context.update(b".");
+        context.update(body);
+        context
+            .sign()
+            .as_ref()
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect()
+    }
+
+    /// Queues `event` for every active webhook subscribed to it, in the
+    /// caller's transaction, so nothing is sent for a change that rolls
+    /// back. With a `dedupe_key` each webhook gets the event at most once.
+    pub(crate) async fn emit_in<T: Serialize>(
+        event: WebhookEvent,
+        data: &T,
+        dedupe_key: Option<&str>,
+        conn: &mut PgConnection,
+    ) -> Result<u64, ServiceError> {
+        let payload = json!({
+            "id": Uuid::new_v4(),
+            "event": event,
+            "occurred_at": Utc::now(),
+            "data": data,
+        });
+        let result = sqlx::query!(
+            r#"
+            INSERT INTO webhook_deliveries (webhook_id, event, payload, dedupe_key)
+            SELECT id, $1, $2, $3
+            FROM webhooks
+            WHERE active AND $1 = ANY(events)
+            ON CONFLICT (webhook_id, dedupe_key) DO NOTHING
+            "#,
+            event as WebhookEvent,
+            payload,
+            dedupe_key
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    /// Sends every delivery due at `now`, one transaction each so a slow
+    /// receiver only holds its own row. Failures are retried with the job
+    /// backoff until the delivery runs out of attempts. Returns the number
+    /// of attempts made.
+    pub async fn deliver_due(
+        client: &reqwest::Client,
+        now: DateTime<Utc>,
+        pool: &PgPool,
+    ) -> Result<usize, ServiceError> {
+        let mut attempted = 0;
+        loop {
+            let mut tx = pool.begin().await?;
+            let Some(due) = sqlx::query_as!(
+                DueDelivery,
+                r#"
+                SELECT d.id, d.event as "event: WebhookEvent", d.payload, d.attempts,
+                       w.url, w.secret
+                FROM webhook_deliveries d
+                JOIN webhooks w ON w.id = d.webhook_id
+                WHERE d.status = 'pending' AND d.next_attempt_at <= $1 AND w.active
+                ORDER BY d.next_attempt_at, d.created_at
+                LIMIT 1
+                FOR UPDATE OF d SKIP LOCKED
+                "#,
+                now
+            )
+            .fetch_optional(&mut *tx)
+            .await?
+            else {
+                break;
+            };
+
+            let attempt = Self::attempt(client, &due).await;
+            let attempts = due.attempts + 1;
+            let (status, next_attempt_at, delivered_at) = match attempt.error {
+                None => (DeliveryStatus::Succeeded, now, Some(Utc::now())),
+                Some(_) if attempts >= MAX_ATTEMPTS => (DeliveryStatus::Failed, now, None),
+                Some(_) => (
+                    DeliveryStatus::Pending,
+                    now + JobService::backoff(attempts),
+                    None,
+                ),
+            };
+            if let Some(error) = &attempt.error {
+                log::warn!(
+                    "Webhook delivery {} to {} failed: {}",
+                    due.id,
+                    due.url,
+                    error
+                );
+            }
+
+            sqlx::query!(
+                r#"
+                UPDATE webhook_deliveries
+                SET status = $2, attempts = $3, next_attempt_at = $4, response_status = $5,
+                    response_body = $6, last_error = $7, delivered_at = $8
+                WHERE id = $1
+                "#,
+                due.id,
+                status as DeliveryStatus,
+                attempts,
+                next_attempt_at,
+                attempt.response_status,
+                attempt.response_body,
+                attempt.error,
+                delivered_at
+            )
+            .execute(&mut *tx)
+            .await?;
+            tx.commit().await?;
+            attempted += 1;
+        }
+
+        Ok(attempted)
+    }
+
+    async fn attempt(client: &reqwest::Client, due: &DueDelivery) -> Attempt {
+        let body = due.payload.to_string().into_bytes();
+        let timestamp = Utc::now().timestamp();
+        let request = client
+            .post(&due.url)
+            .header(CONTENT_TYPE, "application/json")
+            .header("X-Webhook-Event", due.event.as_str())
+            .header("X-Webhook-Delivery", due.id.to_string())
+            .header("X-Webhook-Timestamp", timestamp.to_string())
+            .header(
+                "X-Webhook-Signature",
+                format!("sha256={}", Self::sign(&due.secret, timestamp, &body)),
+            )
+            .body(body);
+
+        let response = match request.send().await {
+            Ok(response) => response,
+            Err(e) => {
+                return Attempt {
+                    response_status: None,
+                    response_body: None,
+                    error: Some(e.to_string()),
+                }
+            }
+        };
+        let status = response.status();
+        let body: String = response
+            .text()
+            .await
+            .unwrap_or_default()
+            .chars()
+            .take(RESPONSE_BODY_LIMIT)
+            .collect();
+        Attempt {
+            response_status: Some(status.as_u16() as i32),
+            response_body: Some(body),
+            error: (!status.is_success()).then(|| format!("Receiver answered {}", status)),
+        }
+    }
+
+    pub async fn create(
+        webhook: WebhookCreate,
+        audit: &AuditContext,
+        pool: &PgPool,
+    ) -> Result<WebhookCreated, ServiceError> {
+        webhook.validate()?;
+        Self::validate_url(&webhook.url)?;
+        let secret = webhook.secret.unwrap_or_else(random_token);
+
+        let mut tx = pool.begin().await?;
+        let created = sqlx::query_as!(
+            Webhook,
+            r#"
+            INSERT INTO webhooks (url, secret, events, description, active, created_by)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            RETURNING id, url, events as "events: Vec<WebhookEvent>", description, active,
+                      created_by, created_at, updated_at
+            "#,
+            webhook.url,
+            secret,
+            &webhook.events as &[WebhookEvent],
+            webhook.description,
+            webhook.active,
+            audit.actor_id
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+
+        AuditService::record_create(&mut tx, audit, AuditEntity::Webhook, created.id, &created)
+            .await?;
+        tx.commit().await?;
+
+        Ok(WebhookCreated {
+            webhook: created,
+            secret,
+        })
+    }
+
+    pub async fn list(pool: &PgPool) -> Result<Vec<Webhook>, ServiceError> {
+        let webhooks = sqlx::query_as!(
+            Webhook,
+            r#"
+            SELECT id, url, events as "events: Vec<WebhookEvent>", description, active,
+                   created_by, created_at, updated_at
+            FROM webhooks
+            ORDER BY created_at
+            "#
+        )
+        .fetch_all(pool)
+        .await?;
+
+        Ok(webhooks)
+    }
+
+    pub async fn get(id: Uuid, pool: &PgPool) -> Result<Webhook, ServiceError> {
+        let mut conn = pool.acquire().await?;
+        Self::fetch(id, false, &mut conn).await
+    }
+
+    async fn fetch(
+        id: Uuid,
+        for_update: bool,
+        conn: &mut PgConnection,
+    ) -> Result<Webhook, ServiceError> {
+        let mut query = QueryBuilder::<Postgres>::new(
+            r#"
+            SELECT id, url, events, description, active, created_by, created_at, updated_at
+            FROM webhooks
+            WHERE id = "#,
+        );
+        query.push_bind(id);
+        if for_update {
+            query.push(" FOR UPDATE");
+        }
+        query
+            .build_query_as::<Webhook>()
+            .fetch_optional(&mut *conn)
+            .await?
+            .ok_or(ServiceError::NotFound("Webhook not found".into()))
+    }
+
+    pub async fn update(
+        id: Uuid,
+        update: WebhookUpdate,
+        audit: &AuditContext,
+        pool: &PgPool,
+    ) -> Result<Webhook, ServiceError> {
+        let mut tx = pool.begin().await?;
+        let existing = Self::fetch(id, true, &mut tx).await?;
+
+        let url = update.url.apply_required("url", existing.url.clone())?;
+        Self::validate_url(&url)?;
+        let events = update
+            .events
+            .apply_required("events", existing.events.clone())?;
+        if events.is_empty() {
+            return Err(ServiceError::invalid_field(
+                "events",
+                "length",
+                "subscribe to at least one event",
+            ));
+        }
+        let secret = match update.secret {
+            Patch::Value(secret) if !(16..=255).contains(&secret.len()) => {
+                return Err(ServiceError::invalid_field(
+                    "secret",
+                    "length",
+                    "secret must be between 16 and 255 characters",
+                ))
+            }
+            Patch::Absent => None,
+            patch => Some(patch.apply_required("secret", String::new())?),
+        };
+        let description = update.description.apply(existing.description.clone());
+        let active = update.active.apply_required("active", existing.active)?;
+
+        let updated = sqlx::query_as!(
+            Webhook,
+            r#"
+            UPDATE webhooks
+            SET url = $2, events = $3, description = $4, active = $5,
+                secret = COALESCE($6, secret), updated_at = NOW()
+            WHERE id = $1
+            RETURNING id, url, events as "events: Vec<WebhookEvent>", description, active,
+                      created_by, created_at, updated_at
+            "#,
+            id,
+            url,
+            &events as &[WebhookEvent],
+            description,
+            active,
+            secret
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+
+        AuditService::record_update(
+            &mut tx,
+            audit,
+            AuditEntity::Webhook,
+            id,
+            &existing,
+            &updated,
+        )
+        .await?;
+        tx.commit().await?;
+
+        Ok(updated)
+    }
+
+    /// Removes the webhook together with its delivery log.
+    pub async fn delete(id: Uuid, audit: &AuditContext, pool: &PgPool) -> Result<(), ServiceError> {
+        let mut tx = pool.begin().await?;
+        let existing = Self::fetch(id, true, &mut tx).await?;
+        sqlx::query!("DELETE FROM webhooks WHERE id = $1", id)
+            .execute(&mut *tx)
+            .await?;
+        AuditService::record_delete(&mut tx, audit, AuditEntity::Webhook, id, &existing).await?;
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    /// The webhook's delivery log, newest first by default.
+    pub async fn list_deliveries(
+        webhook_id: Uuid,
+        params: &PageParams,
+        filter: &DeliveryFilter,
+        pool: &PgPool,
+    ) -> Result<Page<WebhookDelivery>, ServiceError> {
+        Self::get(webhook_id, pool).await?;
+        let order_by = params.order_by(DELIVERY_SORT_FIELDS, "-created_at")?;
+
+        let mut count =
+            QueryBuilder::new("SELECT COUNT(*) FROM webhook_deliveries WHERE webhook_id = ");
+        count.push_bind(webhook_id);
+        Self::push_filters(&mut count, filter);
+        let total: i64 = count.build_query_scalar().fetch_one(pool).await?;
+
+        let mut query = QueryBuilder::new(
+            r#"
+            SELECT id, webhook_id, event, payload, status, attempts, next_attempt_at,
+                   response_status, response_body, last_error, delivered_at, redelivery_of,
+                   created_at
+            FROM webhook_deliveries
+            WHERE webhook_id = "#,
+        );
+        query.push_bind(webhook_id);
+        Self::push_filters(&mut query, filter);
+        query
+            .push(order_by)
+            .push(" LIMIT ")
+            .push_bind(params.per_page())
+            .push(" OFFSET ")
+            .push_bind(params.offset()?);
+
+        let deliveries = query
+            .build_query_as::<WebhookDelivery>()
+            .fetch_all(pool)
+            .await?;
+
+        Ok(Page::new(deliveries, total, params))
+    }
+
+    fn push_filters(query: &mut QueryBuilder<'_, Postgres>, filter: &DeliveryFilter) {
+        if let Some(status) = filter.status {
+            query.push(" AND status = ").push_bind(status);
+        }
+        if let Some(event) = filter.event {
+            query.push(" AND event = ").push_bind(event);
+        }
+    }
+
+    /// Sends a logged delivery again as a new delivery with the same
+    /// payload, so receivers can recognise the event by its `id`.
+    pub async fn redeliver(
+        webhook_id: Uuid,
+        delivery_id: Uuid,
+        pool: &PgPool,
+    ) -> Result<WebhookDelivery, ServiceError> {
+        sqlx::query_as!(
+            WebhookDelivery,
+            r#"
+            INSERT INTO webhook_deliveries (webhook_id, event, payload, redelivery_of)
+            SELECT webhook_id, event, payload, id
+            FROM webhook_deliveries
+            WHERE id = $1 AND webhook_id = $2
+            RETURNING id, webhook_id, event as "event: WebhookEvent", payload,
+                      status as "status: DeliveryStatus", attempts, next_attempt_at,
+                      response_status, response_body, last_error, delivered_at,
+                      redelivery_of, created_at
+            "#,
+            delivery_id,
+            webhook_id
+        )
+        .fetch_optional(pool)
+        .await?
+        .ok_or(ServiceError::NotFound("Webhook delivery not found".into()))
+    }
+
+    fn validate_url(url: &str) -> Result<(), ServiceError> {
+        match Url::parse(url) {
+            Ok(parsed) if matches!(parsed.scheme(), "http" | "https") => Ok(()),
+            _ => Err(ServiceError::invalid_field(
+                "url",
+                "url",
+                "must be an absolute http or https URL",
+            )),
+        }
+    }
+}
@@ -0,0 +1,187 @@
+#[cfg(test)]
+mod tests {
+    use crate::errors::ServiceError;
+    use crate::models::audit::{AuditAction, AuditContext, AuditEntity};
+    use crate::models::lifecycle::{LifecyclePhase, PhaseTransition};
+    use crate::models::pagination::PageParams;
+    use crate::models::patch::Patch;
+    use crate::models::project::{Project, ProjectFilter, ProjectUpdate};
+    use crate::models::task::TaskUpdate;
+    use crate::models::user::UserRole;
+    use crate::routes;
+    use crate::services::audit_service::AuditService;
+    use crate::services::lifecycle_service::LifecycleService;
+    use crate::services::project_service::ProjectService;
+    use crate::services::task_service::TaskService;
+    use crate::tests::test_helpers::{
+        audit_as, cleanup_test_db, create_project, create_task, create_user, new_task,
+        setup_test_db, test_token_service,
+    };
+    use actix_web::{test, web, App};
+    use bigdecimal::BigDecimal;
+    use serde_json::Value;
+    use serial_test::serial;
+    use sqlx::PgPool;
+
+    async fn close(project: &Project, audit: &AuditContext, pool: &PgPool) {
+        LifecycleService::transition_phase(
+            PhaseTransition {
+                project_id: project.id,
+                phase: LifecyclePhase::Closed,
+                description: "Signed off".to_string(),
+                attachments: None,
+                documents: vec![],
+            },
+            None,
+            audit,
+            pool,
+        )
+        .await
+        .unwrap();
+    }
+
+    #[actix_rt::test]
+    #[serial]
+    async fn test_archived_projects_are_read_only() {
+        let pool = setup_test_db().await;
+        let pm = create_user("pm@example.com", UserRole::ProjectManager, &pool).await;
+        let audit = audit_as(pm.id);
+        let project = create_project("Finished Project", &audit, &pool).await;
+        let task = create_task(new_task("Final Task", project.id), &audit, &pool).await;
+
+        // Only closed projects can be archived
+        assert!(matches!(
+            ProjectService::archive(project.id, None, &audit, &pool).await,
+            Err(ServiceError::UnprocessableEntity(_))
+        ));
+        close(&project, &audit, &pool).await;
+        let archived = ProjectService::archive(project.id, None, &audit, &pool)
+            .await
+            .unwrap();
+        assert!(archived.archived_at.is_some());
+
+        // The project, its tasks and its phase records are frozen
+        let rename = ProjectUpdate {
+            name: Patch::Value("Renamed".to_string()),
+            ..Default::default()
+        };
+        assert!(matches!(
+            ProjectService::update(project.id, rename, None, &audit, &pool).await,
+            Err(ServiceError::ProjectArchived)
+        ));
+        assert!(matches!(
+            ProjectService::delete(project.id, None, pm.id, &pm.role, &audit, &pool).await,
+            Err(ServiceError::ProjectArchived)
+        ));
+        assert!(matches!(
+            ProjectService::archive(project.id, None, &audit, &pool).await,
+            Err(ServiceError::ProjectArchived)
+        ));
+        assert!(matches!(
+            TaskService::create(new_task("Final Task", project.id), &audit, &pool).await,
+            Err(ServiceError::ProjectArchived)
+        ));
+        let progress = TaskUpdate {
+            progress: Patch::Value(BigDecimal::from(50)),
+            ..Default::default()
+        };
+        assert!(matches!(
+            TaskService::update(task.id, progress, None, &audit, &pool).await,
+            Err(ServiceError::ProjectArchived)
+        ));
+        assert!(matches!(
+            TaskService::delete(task.id, None, &audit, &pool).await,
+            Err(ServiceError::ProjectArchived)
+        ));
+        let reopen = PhaseTransition {
+            project_id: project.id,
+            phase: LifecyclePhase::Maintenance,
+            description: "Reopen".to_string(),
+            attachments: None,
+            documents: vec![],
+        };
+        assert!(matches!(
+            LifecycleService::transition_phase(reopen, None, &audit, &pool).await,
+            Err(ServiceError::ProjectArchived)
+        ));
+
+        // Hidden from default listings but still readable for reporting
+        let page =
+            ProjectService::get_all(&PageParams::default(), &ProjectFilter::default(), &pool)
+                .await
+                .unwrap();
+        assert_eq!(page.total, 0);
+        let filter = ProjectFilter {
+            archived: Some(true),
+            ..Default::default()
+        };
+        let page = ProjectService::get_all(&PageParams::default(), &filter, &pool)
+            .await
+            .unwrap();
+        assert_eq!(page.total, 1);
+        assert!(ProjectService::get_by_id(project.id, &pool).await.is_ok());
+        assert!(TaskService::get_by_id(task.id, &pool).await.is_ok());
+
+        cleanup_test_db(&pool).await;
+    }
+
+    #[actix_rt::test]
+    #[serial]
+    async fn test_unarchive_requires_admin_and_is_audited() {
+        let pool = setup_test_db().await;
+        let tokens = test_token_service();
+        let admin = create_user("admin@example.com", UserRole::Admin, &pool).await;
+        let pm = create_user("pm@example.com", UserRole::ProjectManager, &pool).await;
+        let admin_token = tokens.issue(&admin).unwrap().token;
+        let pm_token = tokens.issue(&pm).unwrap().token;
+        let project = create_project("Finished Project", &audit_as(pm.id), &pool).await;
+        close(&project, &audit_as(pm.id), &pool).await;
+
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(pool.clone()))
+                .app_data(web::Data::new(tokens))
+                .configure(routes::config),
+        )
+        .await;
+
+        let req = test::TestRequest::post()
+            .uri(&format!("/api/projects/{}/archive", project.id))
+            .insert_header(("Authorization", format!("Bearer {}", pm_token)))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 200);
+
+        let req = test::TestRequest::put()
+            .uri(&format!("/api/projects/{}", project.id))
+            .insert_header(("Authorization", format!("Bearer {}", pm_token)))
+            .set_json(serde_json::json!({ "name": "Renamed" }))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 409);
+        let body: Value = test::read_body_json(resp).await;
+        assert_eq!(body["code"], "project_archived");
+
+        let req = test::TestRequest::post()
+            .uri(&format!("/api/projects/{}/unarchive", project.id))
+            .insert_header(("Authorization", format!("Bearer {}", pm_token)))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 403);
+
+        let req = test::TestRequest::post()
+            .uri(&format!("/api/projects/{}/unarchive", project.id))
+            .insert_header(("Authorization", format!("Bearer {}", admin_token)))
+            .to_request();
+        let body: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(body["archived_at"], Value::Null);
+
+        let history = AuditService::history(AuditEntity::Project, project.id, &pool)
+            .await
+            .unwrap();
+        let unarchived = history.last().unwrap();
+        assert_eq!(unarchived.action, AuditAction::Update);
+        assert_eq!(unarchived.actor_id, Some(admin.id));
+        assert!(unarchived.changes.get("archived_at").is_some());
+
+        cleanup_test_db(&pool).await;
+    }
+}
@@ -0,0 +1,235 @@
+#[cfg(test)]
+mod tests {
+    use crate::middleware::request_id;
+    use crate::models::audit::{AuditAction, AuditContext, AuditEntity};
+    use crate::models::patch::Patch;
+    use crate::models::project::{ProjectCreate, ProjectUpdate};
+    use crate::models::user::UserRole;
+    use crate::routes;
+    use crate::services::audit_service::AuditService;
+    use crate::services::project_service::ProjectService;
+    use crate::tests::test_helpers::{
+        audit_as, cleanup_test_db, create_user, setup_test_db, test_token_service,
+    };
+    use actix_web::{middleware::from_fn, test, web, App};
+    use bigdecimal::{BigDecimal, FromPrimitive};
+    use chrono::{Duration, Utc};
+    use serde_json::json;
+    use serial_test::serial;
+    use uuid::Uuid;
+
+    #[actix_rt::test]
+    #[serial]
+    async fn test_project_changes_are_audited() {
+        let pool = setup_test_db().await;
+        let admin = create_user("admin@example.com", UserRole::Admin, &pool).await;
+        let audit = AuditContext {
+            actor_id: Some(admin.id),
+            request_id: Some("req-project".to_string()),
+        };
+
+        let project = ProjectService::create(
+            ProjectCreate {
+                name: "Audited Project".to_string(),
+                description: None,
+                start_date: Utc::now(),
+                end_date: Utc::now() + Duration::days(30),
+                budget: BigDecimal::from_f64(1000.0).unwrap(),
+                client_id: None,
+            },
+            &audit,
+            &pool,
+        )
+        .await
+        .unwrap();
+
+        ProjectService::update(
+            project.id,
+            ProjectUpdate {
+                name: Patch::Value("Renamed Project".to_string()),
+                description: Patch::Absent,
+                start_date: Patch::Absent,
+                end_date: Patch::Absent,
+                status: Patch::Absent,
+                budget: Patch::Absent,
+                client_id: Patch::Absent,
+            },
+            None,
+            &audit,
+            &pool,
+        )
+        .await
+        .unwrap();
+
+        ProjectService::delete(project.id, None, admin.id, &admin.role, &audit, &pool)
+            .await
+            .unwrap();
+
+        let history = AuditService::history(AuditEntity::Project, project.id, &pool)
+            .await
+            .unwrap();
+        let actions: Vec<AuditAction> = history.iter().map(|entry| entry.action).collect();
+        assert_eq!(
+            actions,
+            vec![
+                AuditAction::Create,
+                AuditAction::Update,
+                AuditAction::Delete
+            ]
+        );
+        assert!(history.iter().all(|entry| entry.actor_id == Some(admin.id)
+            && entry.request_id.as_deref() == Some("req-project")));
+
+        let update = &history[1];
+        assert_eq!(update.changes["name"]["before"], "Audited Project");
+        assert_eq!(update.changes["name"]["after"], "Renamed Project");
+        assert!(update.changes.get("budget").is_none());
+
+        assert!(history[0].before.is_none());
+        assert!(history[2].after.is_none());
+        assert_eq!(
+            history[2].before.as_ref().unwrap()["name"],
+            "Renamed Project"
+        );
+
+        cleanup_test_db(&pool).await;
+    }
+
+    #[actix_rt::test]
+    #[serial]
+    async fn test_failed_mutation_leaves_no_audit_entry() {
+        let pool = setup_test_db().await;
+        let admin = create_user("admin@example.com", UserRole::Admin, &pool).await;
+
+        let result = ProjectService::delete(
+            Uuid::new_v4(),
+            None,
+            admin.id,
+            &admin.role,
+            &audit_as(admin.id),
+            &pool,
+        )
+        .await;
+        assert!(result.is_err());
+
+        let count: i64 =
+            sqlx::query_scalar("SELECT COUNT(*) FROM audit_log WHERE entity_type = 'project'")
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+        assert_eq!(count, 0);
+
+        cleanup_test_db(&pool).await;
+    }
+
+    #[actix_rt::test]
+    #[serial]
+    async fn test_audit_log_is_append_only() {
+        let pool = setup_test_db().await;
+        let admin = create_user("admin@example.com", UserRole::Admin, &pool).await;
+
+        let history = AuditService::history(AuditEntity::User, admin.id, &pool)
+            .await
+            .unwrap();
+        assert_eq!(history.len(), 1);
+        let entry_id = history[0].id;
+
+        let update = sqlx::query("UPDATE audit_log SET entity_type = 'tampered' WHERE id = $1")
+            .bind(entry_id)
+            .execute(&pool)
+            .await;
+        assert!(update.is_err());
+
+        let delete = sqlx::query("DELETE FROM audit_log WHERE id = $1")
+            .bind(entry_id)
+            .execute(&pool)
+            .await;
+        assert!(delete.is_err());
+
+        let history = AuditService::history(AuditEntity::User, admin.id, &pool)
+            .await
+            .unwrap();
+        assert_eq!(history.len(), 1);
+        assert_eq!(history[0].entity_type, "user");
+
+        cleanup_test_db(&pool).await;
+    }
+
+    #[actix_rt::test]
+    #[serial]
+    async fn test_audit_endpoints_are_admin_only_and_correlate_requests() {
+        let pool = setup_test_db().await;
+        let tokens = test_token_service();
+        let admin = create_user("admin@example.com", UserRole::Admin, &pool).await;
+        let developer = create_user("dev@example.com", UserRole::Developer, &pool).await;
+        let admin_token = tokens.issue(&admin).unwrap().token;
+        let developer_token = tokens.issue(&developer).unwrap().token;
+
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(pool.clone()))
+                .app_data(web::Data::new(tokens))
+                .wrap(from_fn(request_id::request_id))
+                .configure(routes::config),
+        )
+        .await;
+
+        let req = test::TestRequest::post()
+            .uri("/api/resources")
+            .insert_header(("Authorization", format!("Bearer {}", admin_token)))
+            .insert_header((request_id::REQUEST_ID_HEADER, "req-resource-1"))
+            .set_json(json!({
+                "name": "Jane Engineer",
+                "email": "jane@example.com",
+                "role": "Engineer",
+                "skills": ["rust"],
+                "availability": "80.00",
+                "hourly_rate": "95.00"
+            }))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 201);
+        assert_eq!(
+            resp.headers().get(request_id::REQUEST_ID_HEADER).unwrap(),
+            "req-resource-1"
+        );
+
+        // A generated ID is echoed when the caller does not send one
+        let req = test::TestRequest::get()
+            .uri("/api/resources")
+            .insert_header(("Authorization", format!("Bearer {}", developer_token)))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 200);
+        assert!(resp.headers().contains_key(request_id::REQUEST_ID_HEADER));
+
+        let req = test::TestRequest::get()
+            .uri("/api/audit?request_id=req-resource-1")
+            .insert_header(("Authorization", format!("Bearer {}", developer_token)))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 403);
+
+        let req = test::TestRequest::get()
+            .uri("/api/audit?request_id=req-resource-1")
+            .insert_header(("Authorization", format!("Bearer {}", admin_token)))
+            .to_request();
+        let page: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(page["total"], 1);
+        let entry = &page["items"][0];
+        assert_eq!(entry["entity_type"], "resource");
+        assert_eq!(entry["action"], "Create");
+        assert_eq!(entry["actor_id"], admin.id.to_string());
+        assert_eq!(entry["after"]["name"], "Jane Engineer");
+
+        let resource_id = entry["entity_id"].as_str().unwrap().to_string();
+        let req = test::TestRequest::get()
+            .uri(&format!("/api/audit/resource/{}", resource_id))
+            .insert_header(("Authorization", format!("Bearer {}", admin_token)))
+            .to_request();
+        let history: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(history.as_array().unwrap().len(), 1);
+
+        cleanup_test_db(&pool).await;
+    }
+}
@@ -1,15 +1,17 @@
 #[cfg(test)]
 mod tests {
+    use crate::models::audit::AuditContext;
     use crate::models::auth::LoginCredentials;
     use crate::models::user::{UserCreate, UserRole};
     use crate::services::auth_service::AuthService;
-    use crate::tests::test_helpers::setup_test_db;
+    use crate::tests::test_helpers::{setup_test_db, test_token_service};
     use serial_test::serial;
 
     #[actix_rt::test]
     #[serial]
     async fn test_registration_and_login() {
         let pool = setup_test_db().await;
+        let tokens = test_token_service();
 
         // Test registration
         let user_create = UserCreate {
@@ -19,7 +21,8 @@ mod tests {
             role: UserRole::Developer,
         };
 
-        let register_result = AuthService::register(user_create, &pool).await;
+        let register_result =
+            AuthService::register(user_create, &tokens, &AuditContext::default(), &pool).await;
         assert!(register_result.is_ok());
         let auth_response = register_result.unwrap();
         assert!(!auth_response.token.is_empty());
@@ -30,7 +33,7 @@ mod tests {
             password: "password123".to_string(),
         };
 
-        let login_result = AuthService::login(credentials, &pool).await;
+        let login_result = AuthService::login(credentials, &tokens, &pool).await;
         assert!(login_result.is_ok());
         let login_response = login_result.unwrap();
         assert!(!login_response.token.is_empty());
@@ -40,7 +43,7 @@ mod tests {
             email: "test_auth@example.com".to_string(),
             password: "wrongpassword".to_string(),
         };
-        let invalid_login = AuthService::login(invalid_credentials, &pool).await;
+        let invalid_login = AuthService::login(invalid_credentials, &tokens, &pool).await;
         assert!(invalid_login.is_err());
 
         // Test non-existent user
@@ -48,7 +51,7 @@ mod tests {
             email: "nonexistent@example.com".to_string(),
             password: "password123".to_string(),
         };
-        let nonexistent_login = AuthService::login(nonexistent_credentials, &pool).await;
+        let nonexistent_login = AuthService::login(nonexistent_credentials, &tokens, &pool).await;
         assert!(nonexistent_login.is_err());
     }
 }
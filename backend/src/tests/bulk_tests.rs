@@ -0,0 +1,226 @@
+#[cfg(test)]
+mod tests {
+    use crate::errors::ServiceError;
+    use crate::models::audit::{AuditContext, AuditEntity};
+    use crate::models::patch::Patch;
+    use crate::models::task::{
+        BulkTaskOperation, BulkTaskRequest, BulkTaskResult, TaskStatus, TaskUpdate,
+        MAX_BULK_OPERATIONS,
+    };
+    use crate::models::user::UserRole;
+    use crate::routes;
+    use crate::services::audit_service::AuditService;
+    use crate::services::task_service::TaskService;
+    use crate::tests::test_helpers::{
+        audit_as, cleanup_test_db, create_project, create_task, create_user, new_task,
+        setup_test_db, test_token_service,
+    };
+    use actix_web::{test, web, App};
+    use bigdecimal::BigDecimal;
+    use chrono::{Duration, Utc};
+    use serde_json::{json, Value};
+    use serial_test::serial;
+    use sqlx::PgPool;
+    use uuid::Uuid;
+
+    async fn task_count(pool: &PgPool) -> i64 {
+        sqlx::query_scalar!("SELECT COUNT(*) FROM tasks")
+            .fetch_one(pool)
+            .await
+            .unwrap()
+            .unwrap_or(0)
+    }
+
+    #[actix_rt::test]
+    #[serial]
+    async fn test_bulk_applies_mixed_operations_in_order() {
+        let pool = setup_test_db().await;
+        let user = create_user("pm@example.com", UserRole::ProjectManager, &pool).await;
+        let audit = audit_as(user.id);
+        let project = create_project("Bulk Project", &audit, &pool).await;
+        let existing = create_task(new_task("Existing", project.id), &audit, &pool).await;
+        let doomed = create_task(new_task("Doomed", project.id), &audit, &pool).await;
+
+        let request = BulkTaskRequest {
+            operations: vec![
+                BulkTaskOperation::Create {
+                    task: new_task("Created", project.id),
+                },
+                BulkTaskOperation::Update {
+                    id: existing.id,
+                    version: Some(existing.version),
+                    changes: TaskUpdate {
+                        status: Patch::Value(TaskStatus::InProgress),
+                        progress: Patch::Value(BigDecimal::from(40)),
+                        assigned_to: Patch::Value(user.id),
+                        ..Default::default()
+                    },
+                },
+                BulkTaskOperation::Delete {
+                    id: doomed.id,
+                    version: None,
+                },
+            ],
+        };
+        let response = TaskService::bulk(request, &audit, &pool).await.unwrap();
+        assert_eq!(response.results.len(), 3);
+
+        let created = match &response.results[0] {
+            BulkTaskResult::Created { index: 0, task } => task,
+            other => panic!("unexpected result {:?}", other),
+        };
+        assert_eq!(created.name, "Created");
+        match &response.results[1] {
+            BulkTaskResult::Updated { index: 1, task } => {
+                assert_eq!(task.status, TaskStatus::InProgress);
+                assert_eq!(task.progress, BigDecimal::from(40));
+                assert_eq!(task.assigned_to, vec![user.id]);
+                assert_eq!(task.version, existing.version + 1);
+            }
+            other => panic!("unexpected result {:?}", other),
+        }
+        assert!(matches!(
+            response.results[2],
+            BulkTaskResult::Deleted { index: 2, id } if id == doomed.id
+        ));
+        assert!(matches!(
+            TaskService::get_by_id(doomed.id, &pool).await,
+            Err(ServiceError::NotFound(_))
+        ));
+
+        // Each operation is audited like its single-task counterpart
+        let history = AuditService::history(AuditEntity::Task, created.id, &pool)
+            .await
+            .unwrap();
+        assert_eq!(history.len(), 1);
+        assert_eq!(history[0].actor_id, Some(user.id));
+
+        cleanup_test_db(&pool).await;
+    }
+
+    #[actix_rt::test]
+    #[serial]
+    async fn test_bulk_validates_every_item_before_applying() {
+        let pool = setup_test_db().await;
+        let audit = AuditContext::default();
+        let project = create_project("Bulk Project", &audit, &pool).await;
+
+        let request = BulkTaskRequest {
+            operations: vec![
+                BulkTaskOperation::Create {
+                    task: new_task("Valid", project.id),
+                },
+                BulkTaskOperation::Create {
+                    task: new_task("", project.id),
+                },
+                BulkTaskOperation::Update {
+                    id: Uuid::new_v4(),
+                    version: None,
+                    changes: TaskUpdate {
+                        progress: Patch::Value(BigDecimal::from(150)),
+                        ..Default::default()
+                    },
+                },
+            ],
+        };
+        let fields: Vec<String> = match TaskService::bulk(request, &audit, &pool).await {
+            Err(ServiceError::ValidationError(fields)) => {
+                fields.into_iter().map(|field| field.field).collect()
+            }
+            other => panic!("expected a validation error, got {:?}", other),
+        };
+        assert_eq!(
+            fields,
+            vec!["operations[1].task.name", "operations[2].changes.progress"]
+        );
+        assert_eq!(task_count(&pool).await, 0);
+
+        let empty = BulkTaskRequest { operations: vec![] };
+        assert!(matches!(
+            TaskService::bulk(empty, &audit, &pool).await,
+            Err(ServiceError::ValidationError(_))
+        ));
+        let too_many = BulkTaskRequest {
+            operations: (0..=MAX_BULK_OPERATIONS)
+                .map(|_| BulkTaskOperation::Create {
+                    task: new_task("Overflow", project.id),
+                })
+                .collect(),
+        };
+        assert!(matches!(
+            TaskService::bulk(too_many, &audit, &pool).await,
+            Err(ServiceError::ValidationError(_))
+        ));
+
+        cleanup_test_db(&pool).await;
+    }
+
+    #[actix_rt::test]
+    #[serial]
+    async fn test_bulk_rolls_back_when_an_operation_fails() {
+        let pool = setup_test_db().await;
+        let tokens = test_token_service();
+        let user = create_user("pm@example.com", UserRole::ProjectManager, &pool).await;
+        let token = tokens.issue(&user).unwrap().token;
+        let project = create_project("Bulk Project", &audit_as(user.id), &pool).await;
+
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(pool.clone()))
+                .app_data(web::Data::new(tokens))
+                .configure(routes::config),
+        )
+        .await;
+
+        let task = |name: &str| {
+            json!({
+                "name": name,
+                "project_id": project.id,
+                "start_date": Utc::now(),
+                "end_date": Utc::now() + Duration::days(7),
+                "dependencies": []
+            })
+        };
+        let req = test::TestRequest::post()
+            .uri("/api/tasks/bulk")
+            .insert_header(("Authorization", format!("Bearer {}", token)))
+            .set_json(json!({
+                "operations": [
+                    { "op": "create", "task": task("First") },
+                    { "op": "create", "task": task("Second") },
+                    { "op": "delete", "id": Uuid::new_v4() }
+                ]
+            }))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 404);
+        let body: Value = test::read_body_json(resp).await;
+        assert_eq!(body["code"], "not_found");
+        assert_eq!(body["errors"][0]["field"], "operations[2]");
+        assert_eq!(task_count(&pool).await, 0);
+
+        let req = test::TestRequest::post()
+            .uri("/api/tasks/bulk")
+            .insert_header(("Authorization", format!("Bearer {}", token)))
+            .set_json(json!({
+                "operations": [
+                    { "op": "create", "task": task("First") },
+                    { "op": "create", "task": task("Second") }
+                ]
+            }))
+            .to_request();
+        let body: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(body["results"][1]["op"], "created");
+        assert_eq!(body["results"][1]["index"], 1);
+        assert_eq!(body["results"][1]["task"]["name"], "Second");
+        assert_eq!(task_count(&pool).await, 2);
+
+        let req = test::TestRequest::post()
+            .uri("/api/tasks/bulk")
+            .set_json(json!({ "operations": [] }))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 401);
+
+        cleanup_test_db(&pool).await;
+    }
+}
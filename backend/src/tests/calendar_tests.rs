@@ -0,0 +1,269 @@
+#[cfg(test)]
+mod tests {
+    use crate::errors::ServiceError;
+    use crate::models::audit::AuditContext;
+    use crate::models::calendar::{CalendarFeedCreate, CalendarFeedCreated, GateReviewCreate};
+    use crate::models::lifecycle::LifecyclePhase;
+    use crate::models::project::{Project, ProjectCreate};
+    use crate::models::task::TaskCreate;
+    use crate::models::user::UserRole;
+    use crate::routes;
+    use crate::services::calendar_service::CalendarService;
+    use crate::services::lifecycle_service::LifecycleService;
+    use crate::services::project_service::ProjectService;
+    use crate::services::user_service::UserService;
+    use crate::tests::test_helpers::{
+        cleanup_test_db, create_resource, create_task, create_user, new_task, setup_test_db,
+        test_token_service,
+    };
+    use actix_web::{test, web, App};
+    use bigdecimal::BigDecimal;
+    use chrono::{Duration, TimeZone, Utc};
+    use serde_json::json;
+    use serial_test::serial;
+    use sqlx::PgPool;
+
+    async fn create_project(name: &str, pool: &PgPool) -> Project {
+        ProjectService::create(
+            ProjectCreate {
+                name: name.to_string(),
+                description: None,
+                start_date: Utc.with_ymd_and_hms(2025, 7, 1, 9, 0, 0).unwrap(),
+                end_date: Utc.with_ymd_and_hms(2025, 9, 30, 17, 0, 0).unwrap(),
+                budget: BigDecimal::from(5000),
+                client_id: None,
+            },
+            &AuditContext::default(),
+            pool,
+        )
+        .await
+        .unwrap()
+    }
+
+    /// Unfolds continuation lines so assertions can match whole properties.
+    fn unfold(ics: &str) -> String {
+        ics.replace("\r\n ", "")
+    }
+
+    fn token_of(feed: &CalendarFeedCreated) -> &str {
+        feed.url
+            .strip_prefix("/api/calendar/")
+            .and_then(|url| url.strip_suffix(".ics"))
+            .unwrap()
+    }
+
+    #[actix_rt::test]
+    #[serial]
+    async fn test_user_feed_lists_assignments_and_gate_reviews() {
+        let pool = setup_test_db().await;
+        let audit = AuditContext::default();
+        let developer = create_user("dev@example.com", UserRole::Developer, &pool).await;
+        let developer_resource = create_resource(&developer, &pool).await;
+        let project = create_project("Warehouse rollout", &pool).await;
+        let other = create_project("Elsewhere", &pool).await;
+
+        let start = Utc.with_ymd_and_hms(2025, 7, 1, 9, 0, 0).unwrap();
+        let task = create_task(
+            TaskCreate {
+                description: Some("Line one\nline two; with, punctuation".to_string()),
+                assigned_to: Some(developer_resource),
+                start_date: start,
+                end_date: start + Duration::days(3),
+                ..new_task("Install racking", project.id)
+            },
+            &audit,
+            &pool,
+        )
+        .await;
+        let milestone = create_task(
+            TaskCreate {
+                assigned_to: Some(developer_resource),
+                start_date: start,
+                end_date: start,
+                milestone: true,
+                ..new_task("Go live", project.id)
+            },
+            &audit,
+            &pool,
+        )
+        .await;
+        create_task(new_task("Not mine", project.id), &audit, &pool).await;
+        let review = LifecycleService::schedule_gate_review(
+            project.id,
+            GateReviewCreate {
+                phase: LifecyclePhase::Design,
+                scheduled_at: Utc.with_ymd_and_hms(2025, 7, 10, 14, 0, 0).unwrap(),
+                duration_minutes: 90,
+                location: Some("Room 4".to_string()),
+                notes: None,
+                min_pass_rate: None,
+                no_open_critical_defects: false,
+            },
+            &audit,
+            &pool,
+        )
+        .await
+        .unwrap();
+        LifecycleService::schedule_gate_review(
+            other.id,
+            GateReviewCreate {
+                phase: LifecyclePhase::Design,
+                scheduled_at: Utc.with_ymd_and_hms(2025, 7, 11, 14, 0, 0).unwrap(),
+                duration_minutes: 60,
+                location: None,
+                notes: None,
+                min_pass_rate: None,
+                no_open_critical_defects: false,
+            },
+            &audit,
+            &pool,
+        )
+        .await
+        .unwrap();
+
+        let feed = CalendarService::create_feed(
+            CalendarFeedCreate::default(),
+            developer.id,
+            &developer.role,
+            &audit,
+            &pool,
+        )
+        .await
+        .unwrap();
+        let ics = CalendarService::render_feed(token_of(&feed), &pool)
+            .await
+            .unwrap();
+
+        assert!(ics.starts_with("BEGIN:VCALENDAR\r\nVERSION:2.0\r\n"));
+        assert!(ics.ends_with("END:VCALENDAR\r\n"));
+        assert!(ics.split("\r\n").all(|line| line.len() <= 75));
+        let unfolded = unfold(&ics);
+        assert_eq!(unfolded.matches("BEGIN:VEVENT").count(), 3);
+        assert!(unfolded.contains(&format!("UID:task-{}@waterfall-manager", task.id)));
+        assert!(unfolded.contains("DTSTART:20250701T090000Z\r\nDTEND:20250704T090000Z"));
+        assert!(unfolded.contains(
+            "DESCRIPTION:Warehouse rollout\\n\\nLine one\\nline two\\; with\\, punctuation"
+        ));
+        assert!(unfolded.contains(&format!("UID:task-{}@waterfall-manager", milestone.id)));
+        assert!(unfolded.contains("SUMMARY:Milestone: Go live"));
+        assert!(unfolded.contains(&format!("UID:gate-review-{}@waterfall-manager", review.id)));
+        assert!(unfolded.contains("SUMMARY:Warehouse rollout: Design gate review"));
+        assert!(unfolded.contains("DTSTART:20250710T140000Z\r\nDTEND:20250710T153000Z"));
+        assert!(!unfolded.contains("Not mine"));
+        assert!(!unfolded.contains("Elsewhere"));
+
+        // Refreshing an unchanged feed yields the same document, so clients
+        // update events in place
+        let again = CalendarService::render_feed(token_of(&feed), &pool)
+            .await
+            .unwrap();
+        assert_eq!(again, ics);
+
+        cleanup_test_db(&pool).await;
+    }
+
+    #[actix_rt::test]
+    #[serial]
+    async fn test_project_feed_requires_visibility() {
+        let pool = setup_test_db().await;
+        let audit = AuditContext::default();
+        let developer = create_user("dev@example.com", UserRole::Developer, &pool).await;
+        let manager = create_user("pm@example.com", UserRole::ProjectManager, &pool).await;
+        let project = create_project("Warehouse rollout", &pool).await;
+        create_task(new_task("Unassigned", project.id), &audit, &pool).await;
+
+        let request = || CalendarFeedCreate {
+            project_id: Some(project.id),
+        };
+        let denied =
+            CalendarService::create_feed(request(), developer.id, &developer.role, &audit, &pool)
+                .await;
+        assert!(matches!(denied, Err(ServiceError::NotFound(_))));
+
+        let feed =
+            CalendarService::create_feed(request(), manager.id, &manager.role, &audit, &pool)
+                .await
+                .unwrap();
+        let ics = CalendarService::render_feed(token_of(&feed), &pool)
+            .await
+            .unwrap();
+        assert!(ics.contains("X-WR-CALNAME:Warehouse rollout"));
+        assert!(ics.contains("SUMMARY:Unassigned"));
+
+        // A deactivated owner's feeds stop working
+        UserService::deactivate(manager.id, None, &audit, &pool)
+            .await
+            .unwrap();
+        let revoked = CalendarService::render_feed(token_of(&feed), &pool).await;
+        assert!(matches!(revoked, Err(ServiceError::NotFound(_))));
+
+        cleanup_test_db(&pool).await;
+    }
+
+    #[actix_rt::test]
+    #[serial]
+    async fn test_feed_endpoints() {
+        let pool = setup_test_db().await;
+        let tokens = test_token_service();
+        let developer = create_user("dev@example.com", UserRole::Developer, &pool).await;
+        let bearer = format!("Bearer {}", tokens.issue(&developer).unwrap().token);
+
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(pool.clone()))
+                .app_data(web::Data::new(tokens))
+                .configure(routes::config),
+        )
+        .await;
+
+        let req = test::TestRequest::post()
+            .uri("/api/calendar/feeds")
+            .insert_header(("Authorization", bearer.clone()))
+            .set_json(json!({}))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 201);
+        let feed: CalendarFeedCreated = test::read_body_json(resp).await;
+        assert_eq!(feed.feed.user_id, developer.id);
+
+        // The feed URL works without a bearer token
+        let req = test::TestRequest::get().uri(&feed.url).to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 200);
+        assert_eq!(
+            resp.headers().get("Content-Type").unwrap(),
+            "text/calendar; charset=utf-8"
+        );
+
+        let req = test::TestRequest::get()
+            .uri("/api/calendar/feeds")
+            .insert_header(("Authorization", bearer.clone()))
+            .to_request();
+        let feeds: Vec<serde_json::Value> =
+            test::read_body_json(test::call_service(&app, req).await).await;
+        assert_eq!(feeds.len(), 1);
+        assert!(feeds[0].get("token_hash").is_none());
+
+        let req = test::TestRequest::delete()
+            .uri(&format!("/api/calendar/feeds/{}", feed.feed.id))
+            .insert_header(("Authorization", bearer.clone()))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 204);
+
+        let req = test::TestRequest::get().uri(&feed.url).to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 404);
+
+        let req = test::TestRequest::get()
+            .uri("/api/calendar/not-a-real-token.ics")
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 404);
+
+        let req = test::TestRequest::post()
+            .uri("/api/calendar/feeds")
+            .set_json(json!({}))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 401);
+
+        cleanup_test_db(&pool).await;
+    }
+}
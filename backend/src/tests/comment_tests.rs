@@ -0,0 +1,384 @@
+#[cfg(test)]
+mod tests {
+    use crate::errors::ServiceError;
+    use crate::models::comment::{CommentCreate, CommentTarget, CommentUpdate};
+    use crate::models::notification::{Notification, NotificationEvent, NotificationFilter};
+    use crate::models::pagination::PageParams;
+    use crate::models::task::{Task, TaskCreate};
+    use crate::models::user::{User, UserRole};
+    use crate::routes;
+    use crate::services::comment_service::CommentService;
+    use crate::services::notification_service::NotificationService;
+    use crate::tests::test_helpers::{
+        audit_as, cleanup_test_db, create_named_user, create_project, create_resource, create_task,
+        new_task, setup_test_db, test_token_service,
+    };
+    use actix_web::{test, web, App};
+    use serde_json::{json, Value};
+    use serial_test::serial;
+    use sqlx::PgPool;
+    use uuid::Uuid;
+
+    fn on_task(task: &Task, parent_id: Option<Uuid>, body: &str) -> CommentCreate {
+        CommentCreate {
+            target_type: CommentTarget::Task,
+            target_id: task.id,
+            parent_id,
+            body: body.to_string(),
+        }
+    }
+
+    async fn mentions_of(user: &User, pool: &PgPool) -> Vec<Notification> {
+        NotificationService::list(
+            user.id,
+            &PageParams::default(),
+            &NotificationFilter::default(),
+            pool,
+        )
+        .await
+        .unwrap()
+        .items
+        .into_iter()
+        .filter(|notification| notification.event == NotificationEvent::Mentioned)
+        .collect()
+    }
+
+    #[actix_rt::test]
+    async fn test_mentioned_emails() {
+        assert_eq!(
+            CommentService::mentioned_emails(
+                "@Dev@Example.com please check. cc @qa@example.com, @dev@example.com."
+            ),
+            vec!["dev@example.com", "qa@example.com"]
+        );
+        // Plain addresses, bare handles and lone signs are not mentions
+        assert!(CommentService::mentioned_emails("mail dev@example.com").is_empty());
+        assert!(CommentService::mentioned_emails("thanks @dev!").is_empty());
+        assert!(CommentService::mentioned_emails("@ @@ @x@localhost").is_empty());
+        assert_eq!(
+            CommentService::mentioned_emails("(@ops@example.co.uk)"),
+            vec!["ops@example.co.uk"]
+        );
+    }
+
+    #[actix_rt::test]
+    #[serial]
+    async fn test_threads_resolve_mentions_within_the_project() {
+        let pool = setup_test_db().await;
+        let manager =
+            create_named_user("pm@example.com", "Pat", UserRole::ProjectManager, &pool).await;
+        let developer =
+            create_named_user("dev@example.com", "Dana", UserRole::Developer, &pool).await;
+        let developer_resource = create_resource(&developer, &pool).await;
+        let outsider =
+            create_named_user("qa@example.com", "Quinn", UserRole::QaEngineer, &pool).await;
+        let audit = audit_as(manager.id);
+        let project = create_project("Billing", &audit, &pool).await;
+        let task = create_task(
+            TaskCreate {
+                assigned_to: Some(developer_resource),
+                ..new_task("Write test plan", project.id)
+            },
+            &audit,
+            &pool,
+        )
+        .await;
+        let other = create_project("Payroll", &audit, &pool).await;
+        let other_task = create_task(new_task("Write test plan", other.id), &audit, &pool).await;
+
+        let comment = CommentService::create(
+            on_task(
+                &task,
+                None,
+                "@dev@example.com can you review? cc @qa@example.com",
+            ),
+            manager.id,
+            &manager.role,
+            &audit,
+            &pool,
+        )
+        .await
+        .unwrap();
+        assert_eq!(comment.project_id, project.id);
+        assert_eq!(comment.mentions, vec![developer.id]);
+
+        let notified = mentions_of(&developer, &pool).await;
+        assert_eq!(notified.len(), 1);
+        assert_eq!(notified[0].title, "Pat mentioned you");
+        assert_eq!(notified[0].task_id, Some(task.id));
+        assert!(mentions_of(&outsider, &pool).await.is_empty());
+
+        let reply = CommentService::create(
+            on_task(&task, Some(comment.id), "Done, looks **good**."),
+            developer.id,
+            &developer.role,
+            &audit_as(developer.id),
+            &pool,
+        )
+        .await
+        .unwrap();
+        assert_eq!(reply.parent_id, Some(comment.id));
+
+        let misplaced = CommentService::create(
+            on_task(&other_task, Some(comment.id), "Wrong thread"),
+            manager.id,
+            &manager.role,
+            &audit,
+            &pool,
+        )
+        .await;
+        assert!(matches!(misplaced, Err(ServiceError::ValidationError(_))));
+
+        // Users who cannot see a project cannot read or join its discussion
+        let hidden = CommentService::create(
+            on_task(&task, None, "Hello"),
+            outsider.id,
+            &outsider.role,
+            &audit_as(outsider.id),
+            &pool,
+        )
+        .await;
+        assert!(matches!(hidden, Err(ServiceError::NotFound(_))));
+        let hidden = CommentService::get(comment.id, outsider.id, &outsider.role, &pool).await;
+        assert!(matches!(hidden, Err(ServiceError::NotFound(_))));
+
+        let thread = CommentService::list(
+            CommentTarget::Task,
+            task.id,
+            developer.id,
+            &developer.role,
+            &pool,
+        )
+        .await
+        .unwrap();
+        let ids: Vec<Uuid> = thread.iter().map(|comment| comment.id).collect();
+        assert_eq!(ids, vec![comment.id, reply.id]);
+
+        cleanup_test_db(&pool).await;
+    }
+
+    #[actix_rt::test]
+    #[serial]
+    async fn test_edits_keep_history_and_deletes_leave_a_tombstone() {
+        let pool = setup_test_db().await;
+        let manager =
+            create_named_user("pm@example.com", "Pat", UserRole::ProjectManager, &pool).await;
+        let developer =
+            create_named_user("dev@example.com", "Dana", UserRole::Developer, &pool).await;
+        let developer_resource = create_resource(&developer, &pool).await;
+        let audit = audit_as(manager.id);
+        let project = create_project("Billing", &audit, &pool).await;
+        let task = create_task(
+            TaskCreate {
+                assigned_to: Some(developer_resource),
+                ..new_task("Write test plan", project.id)
+            },
+            &audit,
+            &pool,
+        )
+        .await;
+
+        let comment = CommentService::create(
+            on_task(&task, None, "First draft"),
+            manager.id,
+            &manager.role,
+            &audit,
+            &pool,
+        )
+        .await
+        .unwrap();
+        let reply = CommentService::create(
+            on_task(&task, Some(comment.id), "Agreed"),
+            developer.id,
+            &developer.role,
+            &audit_as(developer.id),
+            &pool,
+        )
+        .await
+        .unwrap();
+
+        let edit = |body: &str| CommentUpdate {
+            body: body.to_string(),
+        };
+        let stale = CommentService::update(
+            comment.id,
+            edit("Second draft"),
+            Some(3),
+            manager.id,
+            &manager.role,
+            &audit,
+            &pool,
+        )
+        .await;
+        assert!(matches!(
+            stale,
+            Err(ServiceError::PreconditionFailed { .. })
+        ));
+        let not_author = CommentService::update(
+            comment.id,
+            edit("Hijacked"),
+            None,
+            developer.id,
+            &developer.role,
+            &audit_as(developer.id),
+            &pool,
+        )
+        .await;
+        assert!(matches!(not_author, Err(ServiceError::Forbidden)));
+
+        let edited = CommentService::update(
+            comment.id,
+            edit("Second draft, @dev@example.com"),
+            Some(1),
+            manager.id,
+            &manager.role,
+            &audit,
+            &pool,
+        )
+        .await
+        .unwrap();
+        assert_eq!(edited.version, 2);
+        assert!(edited.edited_at.is_some());
+        assert_eq!(mentions_of(&developer, &pool).await.len(), 1);
+
+        // Mentions carried over by a later edit are not notified again
+        CommentService::update(
+            comment.id,
+            edit("Final draft, @dev@example.com"),
+            Some(2),
+            manager.id,
+            &manager.role,
+            &audit,
+            &pool,
+        )
+        .await
+        .unwrap();
+        assert_eq!(mentions_of(&developer, &pool).await.len(), 1);
+
+        let revisions = CommentService::revisions(comment.id, developer.id, &developer.role, &pool)
+            .await
+            .unwrap();
+        let bodies: Vec<&str> = revisions
+            .iter()
+            .map(|revision| revision.body.as_str())
+            .collect();
+        assert_eq!(
+            bodies,
+            vec!["First draft", "Second draft, @dev@example.com"]
+        );
+
+        let not_author = CommentService::delete(
+            comment.id,
+            None,
+            developer.id,
+            &developer.role,
+            &audit_as(developer.id),
+            &pool,
+        )
+        .await;
+        assert!(matches!(not_author, Err(ServiceError::Forbidden)));
+        CommentService::delete(
+            comment.id,
+            Some(3),
+            manager.id,
+            &manager.role,
+            &audit,
+            &pool,
+        )
+        .await
+        .unwrap();
+
+        let thread = CommentService::list(
+            CommentTarget::Task,
+            task.id,
+            developer.id,
+            &developer.role,
+            &pool,
+        )
+        .await
+        .unwrap();
+        assert_eq!(thread.len(), 2);
+        assert!(thread[0].deleted_at.is_some());
+        assert_eq!(thread[0].body, "");
+        assert!(thread[0].mentions.is_empty());
+        assert_eq!(thread[1].id, reply.id);
+        assert_eq!(thread[1].parent_id, Some(comment.id));
+
+        let history = CommentService::revisions(comment.id, manager.id, &manager.role, &pool).await;
+        assert!(matches!(history, Err(ServiceError::NotFound(_))));
+
+        cleanup_test_db(&pool).await;
+    }
+
+    #[actix_rt::test]
+    #[serial]
+    async fn test_activity_timeline_interleaves_changes_and_comments() {
+        let pool = setup_test_db().await;
+        let tokens = test_token_service();
+        let manager =
+            create_named_user("pm@example.com", "Pat", UserRole::ProjectManager, &pool).await;
+        let developer =
+            create_named_user("dev@example.com", "Dana", UserRole::Developer, &pool).await;
+        let developer_resource = create_resource(&developer, &pool).await;
+        let bearer = format!("Bearer {}", tokens.issue(&developer).unwrap().token);
+        let audit = audit_as(manager.id);
+        let project = create_project("Billing", &audit, &pool).await;
+        let task = create_task(
+            TaskCreate {
+                assigned_to: Some(developer_resource),
+                ..new_task("Write test plan", project.id)
+            },
+            &audit,
+            &pool,
+        )
+        .await;
+
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(pool.clone()))
+                .app_data(web::Data::new(tokens))
+                .configure(routes::config),
+        )
+        .await;
+
+        let req = test::TestRequest::post()
+            .uri("/api/comments")
+            .insert_header(("Authorization", bearer.clone()))
+            .set_json(json!({
+                "target_type": "task",
+                "target_id": task.id,
+                "body": "Starting on this today"
+            }))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 201);
+        assert_eq!(resp.headers().get("etag").unwrap(), "\"1\"");
+        let comment: Value = test::read_body_json(resp).await;
+        assert_eq!(comment["author_id"], json!(developer.id));
+
+        let req = test::TestRequest::get()
+            .uri(&format!("/api/activity/task/{}", task.id))
+            .insert_header(("Authorization", bearer.clone()))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 200);
+        let activity: Vec<Value> = test::read_body_json(resp).await;
+        assert_eq!(activity.len(), 2);
+        assert_eq!(activity[0]["kind"], "change");
+        assert_eq!(activity[0]["action"], "Create");
+        assert_eq!(activity[1]["kind"], "comment");
+        assert_eq!(activity[1]["body"], "Starting on this today");
+
+        let req = test::TestRequest::get()
+            .uri(&format!(
+                "/api/comments?target_type=project&target_id={}",
+                Uuid::new_v4()
+            ))
+            .insert_header(("Authorization", bearer))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 404);
+
+        cleanup_test_db(&pool).await;
+    }
+}
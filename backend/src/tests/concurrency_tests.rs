@@ -0,0 +1,235 @@
+#[cfg(test)]
+mod tests {
+    use crate::errors::ServiceError;
+    use crate::models::lifecycle::{LifecyclePhase, PhaseTransition};
+    use crate::models::patch::Patch;
+    use crate::models::project::{ProjectCreate, ProjectUpdate};
+    use crate::models::task::TaskCreate;
+    use crate::models::user::UserRole;
+    use crate::routes;
+    use crate::services::lifecycle_service::LifecycleService;
+    use crate::services::project_service::ProjectService;
+    use crate::services::task_service::TaskService;
+    use crate::services::user_service::UserService;
+    use crate::tests::test_helpers::{
+        audit_as, cleanup_test_db, create_user, setup_test_db, test_token_service,
+    };
+    use actix_web::{test, web, App};
+    use bigdecimal::{BigDecimal, FromPrimitive};
+    use chrono::{Duration, Utc};
+    use serde_json::json;
+    use serial_test::serial;
+
+    fn new_project() -> ProjectCreate {
+        ProjectCreate {
+            name: "Versioned Project".to_string(),
+            description: None,
+            start_date: Utc::now(),
+            end_date: Utc::now() + Duration::days(30),
+            budget: BigDecimal::from_f64(5000.0).unwrap(),
+            client_id: None,
+        }
+    }
+
+    #[actix_rt::test]
+    #[serial]
+    async fn test_stale_task_writes_are_rejected() {
+        let pool = setup_test_db().await;
+        let tokens = test_token_service();
+        let first_pm = create_user("pm1@example.com", UserRole::ProjectManager, &pool).await;
+        let second_pm = create_user("pm2@example.com", UserRole::ProjectManager, &pool).await;
+        let first_token = tokens.issue(&first_pm).unwrap().token;
+        let second_token = tokens.issue(&second_pm).unwrap().token;
+
+        let project = ProjectService::create(new_project(), &audit_as(first_pm.id), &pool)
+            .await
+            .unwrap();
+        let task = TaskService::create(
+            TaskCreate {
+                name: "Shared Task".to_string(),
+                description: None,
+                project_id: project.id,
+                assigned_to: None,
+                start_date: Utc::now(),
+                end_date: Utc::now() + Duration::days(7),
+                dependencies: vec![],
+                parent_id: None,
+                wbs: None,
+                milestone: false,
+            },
+            &audit_as(first_pm.id),
+            &pool,
+        )
+        .await
+        .unwrap();
+
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(pool.clone()))
+                .app_data(web::Data::new(tokens))
+                .configure(routes::config),
+        )
+        .await;
+
+        // Both managers read the task at version 1
+        let req = test::TestRequest::get()
+            .uri(&format!("/api/tasks/{}", task.id))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        let etag = resp
+            .headers()
+            .get("ETag")
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .to_string();
+        assert_eq!(etag, "\"1\"");
+
+        let req = test::TestRequest::put()
+            .uri(&format!("/api/tasks/{}", task.id))
+            .insert_header(("Authorization", format!("Bearer {}", first_token)))
+            .insert_header(("If-Match", etag.as_str()))
+            .set_json(json!({ "name": "First Edit" }))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 200);
+        assert_eq!(resp.headers().get("ETag").unwrap(), "\"2\"");
+
+        // The second write was based on version 1 and must not win silently
+        let req = test::TestRequest::put()
+            .uri(&format!("/api/tasks/{}", task.id))
+            .insert_header(("Authorization", format!("Bearer {}", second_token)))
+            .insert_header(("If-Match", etag.as_str()))
+            .set_json(json!({ "name": "Second Edit" }))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 412);
+        assert_eq!(resp.headers().get("ETag").unwrap(), "\"2\"");
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(body["current"]["name"], "First Edit");
+        assert_eq!(body["current"]["version"], 2);
+
+        let req = test::TestRequest::put()
+            .uri(&format!("/api/tasks/{}/progress", task.id))
+            .insert_header(("Authorization", format!("Bearer {}", second_token)))
+            .insert_header(("If-Match", etag.as_str()))
+            .set_json(json!(50))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 412);
+
+        let req = test::TestRequest::delete()
+            .uri(&format!("/api/tasks/{}", task.id))
+            .insert_header(("Authorization", format!("Bearer {}", second_token)))
+            .insert_header(("If-Match", etag.as_str()))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 412);
+
+        let req = test::TestRequest::put()
+            .uri(&format!("/api/tasks/{}/progress", task.id))
+            .insert_header(("Authorization", format!("Bearer {}", second_token)))
+            .insert_header(("If-Match", "W/\"2\""))
+            .set_json(json!(50))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 400);
+
+        // Writes without If-Match stay unconditional
+        let req = test::TestRequest::put()
+            .uri(&format!("/api/tasks/{}/progress", task.id))
+            .insert_header(("Authorization", format!("Bearer {}", second_token)))
+            .set_json(json!(50))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 200);
+        assert_eq!(resp.headers().get("ETag").unwrap(), "\"3\"");
+
+        cleanup_test_db(&pool).await;
+    }
+
+    #[actix_rt::test]
+    #[serial]
+    async fn test_phase_transition_is_held_to_project_version() {
+        let pool = setup_test_db().await;
+        let pm = create_user("pm@example.com", UserRole::ProjectManager, &pool).await;
+        let audit = audit_as(pm.id);
+
+        let project = ProjectService::create(new_project(), &audit, &pool)
+            .await
+            .unwrap();
+        assert_eq!(project.version, 1);
+
+        LifecycleService::transition_phase(
+            PhaseTransition {
+                project_id: project.id,
+                phase: LifecyclePhase::Design,
+                description: "Requirements signed off".to_string(),
+                attachments: None,
+                documents: vec![],
+            },
+            Some(1),
+            &audit,
+            &pool,
+        )
+        .await
+        .unwrap();
+
+        let project = ProjectService::get_by_id(project.id, &pool).await.unwrap();
+        assert_eq!(project.version, 2);
+
+        let stale = ProjectService::update(
+            project.id,
+            ProjectUpdate {
+                name: Patch::Value("Stale Rename".to_string()),
+                description: Patch::Absent,
+                start_date: Patch::Absent,
+                end_date: Patch::Absent,
+                status: Patch::Absent,
+                budget: Patch::Absent,
+                client_id: Patch::Absent,
+            },
+            Some(1),
+            &audit,
+            &pool,
+        )
+        .await;
+        match stale {
+            Err(ServiceError::PreconditionFailed { version, current }) => {
+                assert_eq!(version, 2);
+                assert_eq!(current["name"], "Versioned Project");
+            }
+            other => panic!("expected a precondition failure, got {:?}", other),
+        }
+
+        let stale = LifecycleService::transition_phase(
+            PhaseTransition {
+                project_id: project.id,
+                phase: LifecyclePhase::Implementation,
+                description: "Design approved".to_string(),
+                attachments: None,
+                documents: vec![],
+            },
+            Some(1),
+            &audit,
+            &pool,
+        )
+        .await;
+        assert!(matches!(
+            stale,
+            Err(ServiceError::PreconditionFailed { version: 2, .. })
+        ));
+
+        let user = UserService::deactivate(pm.id, Some(pm.version), &audit, &pool)
+            .await
+            .unwrap();
+        assert_eq!(user.version, pm.version + 1);
+        assert!(
+            UserService::reactivate(pm.id, Some(pm.version), &audit, &pool)
+                .await
+                .is_err()
+        );
+
+        cleanup_test_db(&pool).await;
+    }
+}
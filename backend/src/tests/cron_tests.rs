@@ -0,0 +1,66 @@
+#[cfg(test)]
+mod tests {
+    use crate::models::cron::CronSchedule;
+    use chrono::{DateTime, TimeZone, Utc};
+
+    fn at(y: i32, mo: u32, d: u32, h: u32, mi: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(y, mo, d, h, mi, 0).unwrap()
+    }
+
+    fn next(cron: &str, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        cron.parse::<CronSchedule>().unwrap().next_after(after)
+    }
+
+    #[test]
+    fn test_rejects_malformed_expressions() {
+        for cron in [
+            "* * * *",
+            "* * * * * *",
+            "60 * * * *",
+            "* 24 * * *",
+            "* * 0 * *",
+            "* * * 13 *",
+            "* * * * 8",
+            "*/0 * * * *",
+            "5-1 * * * *",
+            "a * * * *",
+        ] {
+            assert!(cron.parse::<CronSchedule>().is_err(), "{}", cron);
+        }
+        let error = "61 * * * *".parse::<CronSchedule>().unwrap_err();
+        assert_eq!(error, "minute field '61' must stay within 0-59");
+    }
+
+    #[test]
+    fn test_next_after_is_strictly_later() {
+        let start = at(2025, 3, 10, 8, 7);
+        assert_eq!(next("*/15 * * * *", start), Some(at(2025, 3, 10, 8, 15)));
+        assert_eq!(
+            next("*/15 * * * *", at(2025, 3, 10, 8, 15)),
+            Some(at(2025, 3, 10, 8, 30))
+        );
+        assert_eq!(next("0 7 * * *", start), Some(at(2025, 3, 11, 7, 0)));
+        assert_eq!(next("5/20 9-10 * * *", start), Some(at(2025, 3, 10, 9, 5)));
+        assert_eq!(next("0 0 1 1 *", start), Some(at(2026, 1, 1, 0, 0)));
+        assert_eq!(next("0 12 * 6,8 *", start), Some(at(2025, 6, 1, 12, 0)));
+    }
+
+    #[test]
+    fn test_day_fields() {
+        // 10 March 2025 is a Monday; 7 and 0 both mean Sunday
+        let monday = at(2025, 3, 10, 8, 0);
+        assert_eq!(next("0 9 * * 7", monday), Some(at(2025, 3, 16, 9, 0)));
+        assert_eq!(next("0 9 * * 0", monday), Some(at(2025, 3, 16, 9, 0)));
+        assert_eq!(next("0 9 * * 1-5", monday), Some(at(2025, 3, 10, 9, 0)));
+        // With both day fields restricted either one matches
+        assert_eq!(next("0 9 15 * 5", monday), Some(at(2025, 3, 14, 9, 0)));
+        assert_eq!(next("0 9 29 2 *", monday), Some(at(2028, 2, 29, 9, 0)));
+        assert_eq!(next("0 0 31 2 *", monday), None);
+    }
+
+    #[test]
+    fn test_display_normalizes_whitespace() {
+        let cron: CronSchedule = " 0  7 * *   1-5 ".parse().unwrap();
+        assert_eq!(cron.to_string(), "0 7 * * 1-5");
+    }
+}
@@ -0,0 +1,519 @@
+#[cfg(test)]
+mod tests {
+    use crate::errors::ServiceError;
+    use crate::models::calendar::GateReviewCreate;
+    use crate::models::defect::{Defect, DefectCreate, DefectSeverity, DefectStatus, DefectUpdate};
+    use crate::models::lifecycle::{LifecyclePhase, PhaseTransition};
+    use crate::models::patch::Patch;
+    use crate::models::requirement::{RequirementCreate, RequirementType};
+    use crate::models::testing::{
+        TestCase, TestCaseCreate, TestLevel, TestOutcome, TestResultRecord, TestRun, TestRunCreate,
+        TestSuiteCreate,
+    };
+    use crate::models::user::{User, UserRole};
+    use crate::routes;
+    use crate::services::defect_service::DefectService;
+    use crate::services::lifecycle_service::LifecycleService;
+    use crate::services::requirement_service::RequirementService;
+    use crate::services::testing_service::TestingService;
+    use crate::tests::test_helpers::{
+        audit_as, cleanup_test_db, create_project, create_resource, create_task, create_user,
+        new_task, setup_test_db, test_token_service,
+    };
+    use actix_web::{test, web, App};
+    use chrono::{TimeZone, Utc};
+    use serde_json::{json, Value};
+    use serial_test::serial;
+    use sqlx::PgPool;
+    use uuid::Uuid;
+
+    /// A test case executed once in a fresh run with the given outcome.
+    async fn execute_case(
+        project_id: Uuid,
+        outcome: TestOutcome,
+        user: &User,
+        pool: &PgPool,
+    ) -> (TestCase, TestRun) {
+        let audit = audit_as(user.id);
+        let suite = TestingService::create_suite(
+            TestSuiteCreate {
+                project_id,
+                name: "System tests".to_string(),
+                description: None,
+                level: TestLevel::System,
+            },
+            user.id,
+            &user.role,
+            &audit,
+            pool,
+        )
+        .await
+        .unwrap();
+        let case = TestingService::create_case(
+            TestCaseCreate {
+                suite_id: suite.id,
+                title: "Totals add up".to_string(),
+                description: None,
+                preconditions: None,
+                steps: vec![],
+                expected_result: None,
+            },
+            user.id,
+            &user.role,
+            &audit,
+            pool,
+        )
+        .await
+        .unwrap();
+        let run = TestingService::create_run(
+            TestRunCreate {
+                project_id,
+                build: "2.3.0".to_string(),
+                cycle: None,
+                notes: None,
+            },
+            user.id,
+            &user.role,
+            &audit,
+            pool,
+        )
+        .await
+        .unwrap();
+        TestingService::record_result(
+            run.id,
+            TestResultRecord {
+                test_case_id: case.id,
+                outcome,
+                notes: None,
+            },
+            user.id,
+            &user.role,
+            &audit,
+            pool,
+        )
+        .await
+        .unwrap();
+        (case, run)
+    }
+
+    fn defect(project_id: Uuid, title: &str, severity: DefectSeverity) -> DefectCreate {
+        DefectCreate {
+            project_id,
+            title: title.to_string(),
+            description: None,
+            severity,
+            priority: None,
+            assignee_id: None,
+            test_case_id: None,
+            test_run_id: None,
+            requirement_id: None,
+            fix_task_id: None,
+        }
+    }
+
+    async fn raise(new_defect: DefectCreate, user: &User, pool: &PgPool) -> Defect {
+        DefectService::create(new_defect, user.id, &user.role, &audit_as(user.id), pool)
+            .await
+            .unwrap()
+    }
+
+    async fn move_to(
+        id: Uuid,
+        status: DefectStatus,
+        user: &User,
+        pool: &PgPool,
+    ) -> Result<Defect, ServiceError> {
+        DefectService::update(
+            id,
+            DefectUpdate {
+                status: Patch::Value(status),
+                ..Default::default()
+            },
+            None,
+            user.id,
+            &user.role,
+            &audit_as(user.id),
+            pool,
+        )
+        .await
+    }
+
+    #[actix_rt::test]
+    #[serial]
+    async fn test_defects_are_raised_from_failed_results() {
+        let pool = setup_test_db().await;
+        let qa = create_user("qa@example.com", UserRole::QaEngineer, &pool).await;
+        let developer = create_user("dev@example.com", UserRole::Developer, &pool).await;
+        let developer_resource = create_resource(&developer, &pool).await;
+        let audit = audit_as(qa.id);
+        let project = create_project("Billing", &audit, &pool).await;
+        let other = create_project("Payroll", &audit, &pool).await;
+        let requirement = RequirementService::create(
+            RequirementCreate {
+                project_id: project.id,
+                title: "Invoices".to_string(),
+                description: None,
+                requirement_type: RequirementType::Functional,
+                priority: None,
+                status: None,
+                acceptance_criteria: None,
+            },
+            qa.id,
+            &qa.role,
+            &audit,
+            &pool,
+        )
+        .await
+        .unwrap();
+        let fix = create_task(new_task("Fix rounding", project.id), &audit, &pool).await;
+        let (failed_case, failed_run) =
+            execute_case(project.id, TestOutcome::Failed, &qa, &pool).await;
+        let (passed_case, passed_run) =
+            execute_case(project.id, TestOutcome::Passed, &qa, &pool).await;
+
+        let raised = raise(
+            DefectCreate {
+                assignee_id: Some(developer.id),
+                test_case_id: Some(failed_case.id),
+                test_run_id: Some(failed_run.id),
+                requirement_id: Some(requirement.id),
+                fix_task_id: Some(fix.id),
+                ..defect(project.id, "Totals are off by a cent", DefectSeverity::High)
+            },
+            &qa,
+            &pool,
+        )
+        .await;
+        assert_eq!(raised.code, "DEF-001");
+        assert_eq!(raised.status, DefectStatus::New);
+        assert_eq!(raised.reported_by, Some(qa.id));
+        assert_eq!(raised.fix_task_id, Some(fix.id));
+
+        for invalid in [
+            // The case passed in that run
+            DefectCreate {
+                test_case_id: Some(passed_case.id),
+                test_run_id: Some(passed_run.id),
+                ..defect(project.id, "Not a failure", DefectSeverity::Low)
+            },
+            // A run without the case that failed in it
+            DefectCreate {
+                test_run_id: Some(failed_run.id),
+                ..defect(project.id, "No case", DefectSeverity::Low)
+            },
+            DefectCreate {
+                requirement_id: Some(requirement.id),
+                ..defect(other.id, "Wrong project", DefectSeverity::Low)
+            },
+        ] {
+            let result = DefectService::create(invalid, qa.id, &qa.role, &audit, &pool).await;
+            assert!(matches!(result, Err(ServiceError::ValidationError(_))));
+        }
+
+        // Developers on the project cannot delete a defect they did not report
+        sqlx::query!(
+            "UPDATE tasks SET assigned_to = ARRAY[$1::uuid] WHERE id = $2",
+            developer_resource,
+            fix.id
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+        let result = DefectService::delete(
+            raised.id,
+            None,
+            developer.id,
+            &developer.role,
+            &audit_as(developer.id),
+            &pool,
+        )
+        .await;
+        assert!(matches!(result, Err(ServiceError::Forbidden)));
+
+        // Numbers of deleted defects are not reused
+        DefectService::delete(raised.id, None, qa.id, &qa.role, &audit, &pool)
+            .await
+            .unwrap();
+        let next = raise(
+            defect(project.id, "Slow export", DefectSeverity::Low),
+            &qa,
+            &pool,
+        )
+        .await;
+        assert_eq!(next.code, "DEF-002");
+
+        cleanup_test_db(&pool).await;
+    }
+
+    #[actix_rt::test]
+    #[serial]
+    async fn test_defect_status_follows_the_workflow() {
+        let pool = setup_test_db().await;
+        let qa = create_user("qa@example.com", UserRole::QaEngineer, &pool).await;
+        let project = create_project("Billing", &audit_as(qa.id), &pool).await;
+        let raised = raise(
+            defect(project.id, "Totals are off by a cent", DefectSeverity::High),
+            &qa,
+            &pool,
+        )
+        .await;
+
+        let skipped = move_to(raised.id, DefectStatus::Fixing, &qa, &pool).await;
+        assert!(matches!(skipped, Err(ServiceError::ValidationError(_))));
+
+        for status in [
+            DefectStatus::Triaged,
+            DefectStatus::Fixing,
+            DefectStatus::Verified,
+            // The retest turned up more
+            DefectStatus::Fixing,
+            DefectStatus::Verified,
+        ] {
+            let moved = move_to(raised.id, status, &qa, &pool).await.unwrap();
+            assert_eq!(moved.status, status);
+            assert_eq!(moved.closed_at, None);
+        }
+        let closed = move_to(raised.id, DefectStatus::Closed, &qa, &pool)
+            .await
+            .unwrap();
+        assert!(closed.closed_at.is_some());
+        assert_eq!(closed.version, 7);
+
+        // Editing a closed defect keeps the time it was closed
+        let edited = DefectService::update(
+            raised.id,
+            DefectUpdate {
+                title: Patch::Value("Totals are off by one cent".to_string()),
+                ..Default::default()
+            },
+            Some(7),
+            qa.id,
+            &qa.role,
+            &audit_as(qa.id),
+            &pool,
+        )
+        .await
+        .unwrap();
+        assert_eq!(edited.closed_at, closed.closed_at);
+
+        let reopened = move_to(raised.id, DefectStatus::Triaged, &qa, &pool)
+            .await
+            .unwrap();
+        assert_eq!(reopened.closed_at, None);
+
+        cleanup_test_db(&pool).await;
+    }
+
+    #[actix_rt::test]
+    #[serial]
+    async fn test_open_critical_defects_block_deployment() {
+        let pool = setup_test_db().await;
+        let manager = create_user("pm@example.com", UserRole::ProjectManager, &pool).await;
+        let audit = audit_as(manager.id);
+        let project = create_project("Billing", &audit, &pool).await;
+        raise(
+            defect(project.id, "Crash on save", DefectSeverity::Critical),
+            &manager,
+            &pool,
+        )
+        .await;
+        raise(
+            defect(project.id, "Typo", DefectSeverity::Low),
+            &manager,
+            &pool,
+        )
+        .await;
+        let fixed = raise(
+            defect(project.id, "Slow export", DefectSeverity::High),
+            &manager,
+            &pool,
+        )
+        .await;
+        for status in [
+            DefectStatus::Triaged,
+            DefectStatus::Fixing,
+            DefectStatus::Verified,
+        ] {
+            move_to(fixed.id, status, &manager, &pool).await.unwrap();
+        }
+
+        let counts = DefectService::open_counts(project.id, manager.id, &manager.role, &pool)
+            .await
+            .unwrap();
+        assert_eq!(
+            (counts.low, counts.medium, counts.high, counts.critical),
+            (1, 0, 0, 1)
+        );
+        assert_eq!(counts.total, 2);
+
+        let transition = |phase| PhaseTransition {
+            project_id: project.id,
+            phase,
+            description: "Next phase".to_string(),
+            attachments: None,
+            documents: vec![],
+        };
+        // Without a gate review asking for it, nothing is checked
+        LifecycleService::transition_phase(
+            transition(LifecyclePhase::Testing),
+            None,
+            &audit,
+            &pool,
+        )
+        .await
+        .unwrap();
+        LifecycleService::schedule_gate_review(
+            project.id,
+            GateReviewCreate {
+                phase: LifecyclePhase::Deployment,
+                scheduled_at: Utc.with_ymd_and_hms(2025, 11, 3, 10, 0, 0).unwrap(),
+                duration_minutes: 60,
+                location: None,
+                notes: None,
+                min_pass_rate: None,
+                no_open_critical_defects: true,
+            },
+            &audit,
+            &pool,
+        )
+        .await
+        .unwrap();
+        let blocked = LifecycleService::transition_phase(
+            transition(LifecyclePhase::Deployment),
+            None,
+            &audit,
+            &pool,
+        )
+        .await;
+        assert!(matches!(blocked, Err(ServiceError::UnprocessableEntity(_))));
+
+        // Only the latest review for the phase counts
+        LifecycleService::schedule_gate_review(
+            project.id,
+            GateReviewCreate {
+                phase: LifecyclePhase::Deployment,
+                scheduled_at: Utc.with_ymd_and_hms(2025, 11, 10, 10, 0, 0).unwrap(),
+                duration_minutes: 60,
+                location: None,
+                notes: Some("Crash accepted as a known issue".to_string()),
+                min_pass_rate: None,
+                no_open_critical_defects: false,
+            },
+            &audit,
+            &pool,
+        )
+        .await
+        .unwrap();
+        let details = LifecycleService::transition_phase(
+            transition(LifecyclePhase::Deployment),
+            None,
+            &audit,
+            &pool,
+        )
+        .await
+        .unwrap();
+        assert_eq!(details.phase, LifecyclePhase::Deployment);
+
+        cleanup_test_db(&pool).await;
+    }
+
+    #[actix_rt::test]
+    #[serial]
+    async fn test_defects_over_http() {
+        let pool = setup_test_db().await;
+        let tokens = test_token_service();
+        let qa = create_user("qa@example.com", UserRole::QaEngineer, &pool).await;
+        let developer = create_user("dev@example.com", UserRole::Developer, &pool).await;
+        let developer_resource = create_resource(&developer, &pool).await;
+        let qa_bearer = format!("Bearer {}", tokens.issue(&qa).unwrap().token);
+        let developer_bearer = format!("Bearer {}", tokens.issue(&developer).unwrap().token);
+        let audit = audit_as(qa.id);
+        let project = create_project("Billing", &audit, &pool).await;
+        let task = create_task(new_task("Fix rounding", project.id), &audit, &pool).await;
+        sqlx::query!(
+            "UPDATE tasks SET assigned_to = ARRAY[$1::uuid] WHERE id = $2",
+            developer_resource,
+            task.id
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+        let (case, run) = execute_case(project.id, TestOutcome::Failed, &qa, &pool).await;
+
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(pool.clone()))
+                .app_data(web::Data::new(tokens))
+                .configure(routes::config),
+        )
+        .await;
+
+        let req = test::TestRequest::post()
+            .uri("/api/defects")
+            .insert_header(("Authorization", qa_bearer.clone()))
+            .set_json(json!({
+                "project_id": project.id,
+                "title": "Totals are off by a cent",
+                "severity": "critical",
+                "assignee_id": developer.id,
+                "test_case_id": case.id,
+                "test_run_id": run.id
+            }))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 201);
+        let raised: Value = test::read_body_json(resp).await;
+        assert_eq!(raised["code"], "DEF-001");
+        assert_eq!(raised["priority"], "medium");
+
+        // The assignee moves it along and names the fix task
+        let uri = format!("/api/defects/{}", raised["id"].as_str().unwrap());
+        let req = test::TestRequest::patch()
+            .uri(&uri)
+            .insert_header(("Authorization", developer_bearer.clone()))
+            .insert_header(("If-Match", "\"1\""))
+            .set_json(json!({ "status": "triaged", "fix_task_id": task.id }))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 200);
+        let req = test::TestRequest::patch()
+            .uri(&uri)
+            .insert_header(("Authorization", developer_bearer.clone()))
+            .set_json(json!({ "status": "verified" }))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 422);
+
+        let req = test::TestRequest::get()
+            .uri(&format!(
+                "/api/defects?project_id={}&open=true&assignee_id={}",
+                project.id, developer.id
+            ))
+            .insert_header(("Authorization", developer_bearer.clone()))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 200);
+        let defects: Vec<Value> = test::read_body_json(resp).await;
+        assert_eq!(defects.len(), 1);
+        assert_eq!(defects[0]["fix_task_id"], json!(task.id));
+
+        let req = test::TestRequest::get()
+            .uri(&format!("/api/projects/{}/open-defects", project.id))
+            .insert_header(("Authorization", developer_bearer.clone()))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 200);
+        let counts: Value = test::read_body_json(resp).await;
+        assert_eq!(counts["critical"], 1);
+        assert_eq!(counts["total"], 1);
+
+        let req = test::TestRequest::delete()
+            .uri(&uri)
+            .insert_header(("Authorization", developer_bearer))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 403);
+
+        cleanup_test_db(&pool).await;
+    }
+}
@@ -0,0 +1,595 @@
+#[cfg(test)]
+mod tests {
+    use crate::errors::ServiceError;
+    use crate::models::document::{
+        DocumentFilter, DocumentLinkCreate, DocumentTarget, DocumentUpload, VersionUpload,
+    };
+    use crate::models::lifecycle::{LifecyclePhase, PhaseTransition};
+    use crate::models::task::TaskCreate;
+    use crate::models::user::UserRole;
+    use crate::routes;
+    use crate::services::document_service::DocumentService;
+    use crate::services::lifecycle_service::LifecycleService;
+    use crate::services::storage_service::{
+        sha256_hex, sigv4_authorization, DocumentStorage, LocalStorage, S3Config, S3Storage,
+    };
+    use crate::tests::test_helpers::{
+        audit_as, cleanup_test_db, create_project, create_resource, create_task, create_user,
+        new_task, setup_test_db, test_token_service,
+    };
+    use actix_web::{test, web, App, HttpRequest, HttpResponse, HttpServer};
+    use chrono::{NaiveDateTime, TimeZone, Utc};
+    use serial_test::serial;
+    use std::collections::HashMap;
+    use std::path::PathBuf;
+    use std::sync::{Arc, Mutex};
+    use uuid::Uuid;
+
+    const PDF: &[u8] = b"%PDF-1.7 requirements v1";
+
+    fn temp_storage() -> (PathBuf, LocalStorage) {
+        let root = std::env::temp_dir().join(format!("documents-{}", Uuid::new_v4()));
+        (root.clone(), LocalStorage::new(root))
+    }
+
+    fn upload(project_id: Uuid, target: Option<(DocumentTarget, Uuid)>) -> DocumentUpload {
+        DocumentUpload {
+            project_id,
+            filename: "srs.pdf".to_string(),
+            title: Some("System requirements".to_string()),
+            description: None,
+            target_type: target.map(|(target_type, _)| target_type),
+            target_id: target.map(|(_, target_id)| target_id),
+        }
+    }
+
+    #[actix_rt::test]
+    async fn test_sigv4_matches_reference_signature() {
+        // From the AWS Signature Version 4 test suite (get-vanilla)
+        let config = S3Config {
+            endpoint: "https://example.amazonaws.com".to_string(),
+            bucket: String::new(),
+            region: "us-east-1".to_string(),
+            access_key_id: "AKIDEXAMPLE".to_string(),
+            secret_access_key: "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY".to_string(),
+        };
+        let authorization = sigv4_authorization(
+            &config,
+            "service",
+            "GET",
+            "/",
+            "",
+            &[
+                ("x-amz-date", "20150830T123600Z"),
+                ("host", "example.amazonaws.com"),
+            ],
+            &sha256_hex(b""),
+            Utc.with_ymd_and_hms(2015, 8, 30, 12, 36, 0).unwrap(),
+        );
+        assert_eq!(
+            authorization,
+            "AWS4-HMAC-SHA256 Credential=AKIDEXAMPLE/20150830/us-east-1/service/aws4_request, \
+             SignedHeaders=host;x-amz-date, \
+             Signature=5fa00fa31553b73ebf1942676e86291e8372ff2a2260956d9b8aae1d763fbf31"
+        );
+    }
+
+    /// Enough of S3 for the storage: path-style objects, with every request
+    /// checked against its signature.
+    struct FakeS3 {
+        config: S3Config,
+        objects: Mutex<HashMap<String, Vec<u8>>>,
+    }
+
+    async fn s3_object(req: HttpRequest, body: web::Bytes, s3: web::Data<FakeS3>) -> HttpResponse {
+        let header = |name: &str| {
+            req.headers()
+                .get(name)
+                .map(|value| value.to_str().unwrap().to_string())
+                .unwrap_or_default()
+        };
+        let amz_date = header("x-amz-date");
+        let payload_hash = header("x-amz-content-sha256");
+        let host = header("host");
+        let content_type = header("content-type");
+        let mut signed = vec![
+            ("host", host.as_str()),
+            ("x-amz-content-sha256", payload_hash.as_str()),
+            ("x-amz-date", amz_date.as_str()),
+        ];
+        if !content_type.is_empty() {
+            signed.push(("content-type", content_type.as_str()));
+        }
+        let now = NaiveDateTime::parse_from_str(&amz_date, "%Y%m%dT%H%M%SZ")
+            .unwrap()
+            .and_utc();
+        let expected = sigv4_authorization(
+            &s3.config,
+            "s3",
+            req.method().as_str(),
+            req.path(),
+            "",
+            &signed,
+            &payload_hash,
+            now,
+        );
+        if header("authorization") != expected || payload_hash != sha256_hex(&body) {
+            return HttpResponse::Forbidden().body("SignatureDoesNotMatch");
+        }
+
+        let mut objects = s3.objects.lock().unwrap();
+        match req.method().as_str() {
+            "PUT" => {
+                objects.insert(req.path().to_string(), body.to_vec());
+                HttpResponse::Ok().finish()
+            }
+            "GET" => match objects.get(req.path()) {
+                Some(object) => HttpResponse::Ok().body(object.clone()),
+                None => HttpResponse::NotFound().body("NoSuchKey"),
+            },
+            _ => {
+                objects.remove(req.path());
+                HttpResponse::NoContent().finish()
+            }
+        }
+    }
+
+    #[actix_rt::test]
+    async fn test_s3_storage_signs_requests() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let config = S3Config {
+            endpoint: format!("http://{}", listener.local_addr().unwrap()),
+            bucket: "documents".to_string(),
+            region: "us-east-1".to_string(),
+            access_key_id: "minioadmin".to_string(),
+            secret_access_key: "minioadmin-secret".to_string(),
+        };
+        let s3 = web::Data::new(FakeS3 {
+            config: config.clone(),
+            objects: Mutex::new(HashMap::new()),
+        });
+        let app_s3 = s3.clone();
+        let server = HttpServer::new(move || {
+            App::new()
+                .app_data(app_s3.clone())
+                .default_service(web::to(s3_object))
+        })
+        .workers(1)
+        .listen(listener)
+        .unwrap()
+        .run();
+        actix_rt::spawn(server);
+
+        let storage = S3Storage::new(config.clone()).unwrap();
+        let key = format!("{}/{}", Uuid::new_v4(), Uuid::new_v4());
+        storage
+            .put(&key, "application/pdf", web::Bytes::from_static(PDF))
+            .await
+            .unwrap();
+        assert!(s3
+            .objects
+            .lock()
+            .unwrap()
+            .contains_key(&format!("/documents/{}", key)));
+        assert_eq!(storage.get(&key).await.unwrap(), PDF);
+
+        storage.delete(&key).await.unwrap();
+        assert!(matches!(
+            storage.get(&key).await,
+            Err(ServiceError::StorageError(_))
+        ));
+
+        let forged = S3Storage::new(S3Config {
+            secret_access_key: "wrong".to_string(),
+            ..config
+        })
+        .unwrap();
+        assert!(matches!(
+            forged
+                .put(&key, "application/pdf", web::Bytes::from_static(PDF))
+                .await,
+            Err(ServiceError::StorageError(_))
+        ));
+    }
+
+    #[actix_rt::test]
+    #[serial]
+    async fn test_versions_keep_checksummed_content() {
+        let pool = setup_test_db().await;
+        let (root, storage) = temp_storage();
+        let manager = create_user("pm@example.com", UserRole::ProjectManager, &pool).await;
+        let developer = create_user("dev@example.com", UserRole::Developer, &pool).await;
+        let developer_resource = create_resource(&developer, &pool).await;
+        let outsider = create_user("qa@example.com", UserRole::QaEngineer, &pool).await;
+        let audit = audit_as(manager.id);
+        let dev_audit = audit_as(developer.id);
+        let project = create_project("Billing", &audit, &pool).await;
+        let task = create_task(
+            TaskCreate {
+                assigned_to: Some(developer_resource),
+                ..new_task("Write requirements", project.id)
+            },
+            &audit,
+            &pool,
+        )
+        .await;
+        let milestone = create_task(
+            TaskCreate {
+                end_date: Utc.with_ymd_and_hms(2025, 7, 1, 0, 0, 0).unwrap(),
+                milestone: true,
+                ..new_task("Requirements signed off", project.id)
+            },
+            &audit,
+            &pool,
+        )
+        .await;
+
+        let not_deliverable = DocumentService::upload(
+            upload(project.id, Some((DocumentTarget::Deliverable, task.id))),
+            Some("application/pdf"),
+            web::Bytes::from_static(PDF),
+            developer.id,
+            &developer.role,
+            &dev_audit,
+            &storage,
+            &pool,
+        )
+        .await;
+        assert!(matches!(
+            not_deliverable,
+            Err(ServiceError::ValidationError(_))
+        ));
+        let wrong_type = DocumentService::upload(
+            upload(project.id, None),
+            Some("application/octet-stream"),
+            web::Bytes::from_static(PDF),
+            developer.id,
+            &developer.role,
+            &dev_audit,
+            &storage,
+            &pool,
+        )
+        .await;
+        assert!(matches!(wrong_type, Err(ServiceError::ValidationError(_))));
+        let hidden = DocumentService::upload(
+            upload(project.id, None),
+            Some("application/pdf"),
+            web::Bytes::from_static(PDF),
+            outsider.id,
+            &outsider.role,
+            &audit_as(outsider.id),
+            &storage,
+            &pool,
+        )
+        .await;
+        assert!(matches!(hidden, Err(ServiceError::NotFound(_))));
+        // Rejected uploads leave nothing behind
+        assert!(!root.exists() || std::fs::read_dir(&root).unwrap().next().is_none());
+
+        let document = DocumentService::upload(
+            upload(
+                project.id,
+                Some((DocumentTarget::Deliverable, milestone.id)),
+            ),
+            Some("application/pdf; charset=binary"),
+            web::Bytes::from_static(PDF),
+            developer.id,
+            &developer.role,
+            &dev_audit,
+            &storage,
+            &pool,
+        )
+        .await
+        .unwrap();
+        assert_eq!(document.current_version, 1);
+        assert_eq!(document.content_type, "application/pdf");
+        assert_eq!(document.size_bytes, PDF.len() as i64);
+        assert_eq!(document.checksum, sha256_hex(PDF));
+
+        let stale = DocumentService::add_version(
+            document.id,
+            VersionUpload { filename: None },
+            Some("application/pdf"),
+            web::Bytes::from_static(b"%PDF-1.7 requirements v2"),
+            Some(7),
+            developer.id,
+            &developer.role,
+            &dev_audit,
+            &storage,
+            &pool,
+        )
+        .await;
+        assert!(matches!(
+            stale,
+            Err(ServiceError::PreconditionFailed { .. })
+        ));
+        let revised = DocumentService::add_version(
+            document.id,
+            VersionUpload {
+                filename: Some("srs-v2.pdf".to_string()),
+            },
+            Some("application/pdf"),
+            web::Bytes::from_static(b"%PDF-1.7 requirements v2"),
+            Some(document.version),
+            manager.id,
+            &manager.role,
+            &audit,
+            &storage,
+            &pool,
+        )
+        .await
+        .unwrap();
+        assert_eq!(revised.current_version, 2);
+        assert_eq!(revised.version, document.version + 1);
+        assert_eq!(revised.filename, "srs-v2.pdf");
+
+        let versions = DocumentService::versions(document.id, developer.id, &developer.role, &pool)
+            .await
+            .unwrap();
+        let numbers: Vec<i32> = versions.iter().map(|version| version.number).collect();
+        assert_eq!(numbers, vec![1, 2]);
+        let (first, content) = DocumentService::download(
+            document.id,
+            Some(1),
+            developer.id,
+            &developer.role,
+            &storage,
+            &pool,
+        )
+        .await
+        .unwrap();
+        assert_eq!(first.filename, "srs.pdf");
+        assert_eq!(content, PDF);
+
+        let linked = DocumentService::list(
+            &DocumentFilter {
+                project_id: None,
+                target_type: Some(DocumentTarget::Deliverable),
+                target_id: Some(milestone.id),
+            },
+            developer.id,
+            &developer.role,
+            &pool,
+        )
+        .await
+        .unwrap();
+        assert_eq!(linked.len(), 1);
+        DocumentService::link(
+            document.id,
+            DocumentLinkCreate {
+                target_type: DocumentTarget::Task,
+                target_id: task.id,
+            },
+            developer.id,
+            &developer.role,
+            &dev_audit,
+            &pool,
+        )
+        .await
+        .unwrap();
+        let links = DocumentService::links(document.id, manager.id, &manager.role, &pool)
+            .await
+            .unwrap();
+        assert_eq!(links.len(), 2);
+
+        let transition = LifecycleService::transition_phase(
+            PhaseTransition {
+                project_id: project.id,
+                phase: LifecyclePhase::Requirements,
+                description: "Requirements baselined".to_string(),
+                attachments: None,
+                documents: vec![document.id],
+            },
+            None,
+            &audit,
+            &pool,
+        )
+        .await
+        .unwrap();
+        let submitted = DocumentService::list(
+            &DocumentFilter {
+                project_id: None,
+                target_type: Some(DocumentTarget::PhaseTransition),
+                target_id: Some(transition.id),
+            },
+            developer.id,
+            &developer.role,
+            &pool,
+        )
+        .await
+        .unwrap();
+        assert_eq!(submitted[0].id, document.id);
+
+        let hidden = DocumentService::get(document.id, outsider.id, &outsider.role, &pool).await;
+        assert!(matches!(hidden, Err(ServiceError::NotFound(_))));
+
+        // Content changed behind the application's back is not served
+        std::fs::write(root.join(&first.storage_key), b"tampered").unwrap();
+        let tampered = DocumentService::download(
+            document.id,
+            Some(1),
+            manager.id,
+            &manager.role,
+            &storage,
+            &pool,
+        )
+        .await;
+        assert!(matches!(tampered, Err(ServiceError::StorageError(_))));
+
+        cleanup_test_db(&pool).await;
+        std::fs::remove_dir_all(root).ok();
+    }
+
+    #[actix_rt::test]
+    #[serial]
+    async fn test_deleted_documents_are_removed_from_storage() {
+        let pool = setup_test_db().await;
+        let (root, storage) = temp_storage();
+        let manager = create_user("pm@example.com", UserRole::ProjectManager, &pool).await;
+        let developer = create_user("dev@example.com", UserRole::Developer, &pool).await;
+        let developer_resource = create_resource(&developer, &pool).await;
+        let audit = audit_as(manager.id);
+        let project = create_project("Billing", &audit, &pool).await;
+        create_task(
+            TaskCreate {
+                assigned_to: Some(developer_resource),
+                ..new_task("Write requirements", project.id)
+            },
+            &audit,
+            &pool,
+        )
+        .await;
+
+        let document = DocumentService::upload(
+            upload(project.id, None),
+            Some("text/plain"),
+            web::Bytes::from_static(b"minutes"),
+            manager.id,
+            &manager.role,
+            &audit,
+            &storage,
+            &pool,
+        )
+        .await
+        .unwrap();
+        let document = DocumentService::add_version(
+            document.id,
+            VersionUpload { filename: None },
+            Some("text/plain"),
+            web::Bytes::from_static(b"minutes, corrected"),
+            None,
+            manager.id,
+            &manager.role,
+            &audit,
+            &storage,
+            &pool,
+        )
+        .await
+        .unwrap();
+        let stored = std::fs::read_dir(root.join(document.id.to_string()))
+            .unwrap()
+            .count();
+        assert_eq!(stored, 2);
+
+        let not_uploader = DocumentService::delete(
+            document.id,
+            None,
+            developer.id,
+            &developer.role,
+            &audit_as(developer.id),
+            &pool,
+        )
+        .await;
+        assert!(matches!(not_uploader, Err(ServiceError::Forbidden)));
+        DocumentService::delete(
+            document.id,
+            Some(document.version),
+            manager.id,
+            &manager.role,
+            &audit,
+            &pool,
+        )
+        .await
+        .unwrap();
+        let gone = DocumentService::get(document.id, manager.id, &manager.role, &pool).await;
+        assert!(matches!(gone, Err(ServiceError::NotFound(_))));
+
+        let removed = DocumentService::remove_deleted_content(&storage, &pool)
+            .await
+            .unwrap();
+        assert_eq!(removed, 2);
+        assert!(!root.join(document.id.to_string()).exists());
+        assert_eq!(
+            DocumentService::remove_deleted_content(&storage, &pool)
+                .await
+                .unwrap(),
+            0
+        );
+
+        cleanup_test_db(&pool).await;
+        std::fs::remove_dir_all(root).ok();
+    }
+
+    #[actix_rt::test]
+    #[serial]
+    async fn test_upload_and_download_over_http() {
+        let pool = setup_test_db().await;
+        let tokens = test_token_service();
+        let (root, storage) = temp_storage();
+        let manager = create_user("pm@example.com", UserRole::ProjectManager, &pool).await;
+        let outsider = create_user("qa@example.com", UserRole::QaEngineer, &pool).await;
+        let bearer = format!("Bearer {}", tokens.issue(&manager).unwrap().token);
+        let outsider_bearer = format!("Bearer {}", tokens.issue(&outsider).unwrap().token);
+        let project = create_project("Billing", &audit_as(manager.id), &pool).await;
+
+        let storage: Arc<dyn DocumentStorage> = Arc::new(storage);
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(pool.clone()))
+                .app_data(web::Data::new(tokens))
+                .app_data(web::Data::from(storage))
+                .configure(routes::config),
+        )
+        .await;
+
+        let req = test::TestRequest::post()
+            .uri(&format!(
+                "/api/documents?project_id={}&filename=plan.pdf",
+                project.id
+            ))
+            .insert_header(("Authorization", bearer.clone()))
+            .insert_header(("Content-Type", "application/pdf"))
+            .set_payload(PDF)
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 201);
+        assert_eq!(resp.headers().get("etag").unwrap(), "\"1\"");
+        let document: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(document["title"], "plan.pdf");
+        assert_eq!(document["checksum"], sha256_hex(PDF));
+
+        let req = test::TestRequest::get()
+            .uri(&format!(
+                "/api/documents/{}/content",
+                document["id"].as_str().unwrap()
+            ))
+            .insert_header(("Authorization", bearer.clone()))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 200);
+        assert_eq!(
+            resp.headers().get("content-disposition").unwrap(),
+            "attachment; filename=\"plan.pdf\""
+        );
+        assert_eq!(
+            resp.headers().get("content-type").unwrap(),
+            "application/pdf"
+        );
+        assert_eq!(test::read_body(resp).await, PDF);
+
+        let req = test::TestRequest::get()
+            .uri(&format!(
+                "/api/documents/{}/content",
+                document["id"].as_str().unwrap()
+            ))
+            .insert_header(("Authorization", outsider_bearer))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 404);
+
+        let req = test::TestRequest::post()
+            .uri(&format!(
+                "/api/documents?project_id={}&filename=..%2Fetc%2Fpasswd",
+                project.id
+            ))
+            .insert_header(("Authorization", bearer))
+            .insert_header(("Content-Type", "text/plain"))
+            .set_payload("root")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 422);
+
+        cleanup_test_db(&pool).await;
+        std::fs::remove_dir_all(root).ok();
+    }
+}
@@ -0,0 +1,175 @@
+#[cfg(test)]
+mod tests {
+    use crate::errors::{ServiceError, PROBLEM_CONTENT_TYPE};
+    use crate::middleware::request_id;
+    use crate::models::auth::Claims;
+    use crate::models::user::{UserCreate, UserRole};
+    use crate::routes;
+    use crate::tests::test_helpers::{
+        cleanup_test_db, create_user, setup_test_db, test_token_service,
+    };
+    use actix_web::{middleware::from_fn, test, web, App, ResponseError};
+    use chrono::Utc;
+    use jsonwebtoken::{encode, Algorithm, EncodingKey, Header};
+    use serde_json::{json, Value};
+    use serial_test::serial;
+    use std::path::Path;
+    use validator::Validate;
+
+    #[actix_rt::test]
+    async fn test_validation_errors_are_reported_per_field() {
+        let invalid = UserCreate {
+            email: "not-an-email".to_string(),
+            password: "short".to_string(),
+            full_name: "".to_string(),
+            role: UserRole::Developer,
+        };
+
+        match ServiceError::from(invalid.validate().unwrap_err()) {
+            ServiceError::ValidationError(fields) => {
+                let names: Vec<_> = fields.iter().map(|f| f.field.as_str()).collect();
+                assert_eq!(names, vec!["email", "full_name", "password"]);
+                assert_eq!(fields[0].code, "email");
+                assert_eq!(fields[2].code, "length");
+                assert!(fields.iter().all(|f| !f.message.is_empty()));
+            }
+            other => panic!("expected validation error, got {:?}", other),
+        }
+    }
+
+    #[actix_rt::test]
+    #[serial]
+    async fn test_errors_are_problem_documents() {
+        let pool = setup_test_db().await;
+        let tokens = test_token_service();
+        let admin = create_user("admin@example.com", UserRole::Admin, &pool).await;
+        let token = tokens.issue(&admin).unwrap().token;
+
+        let app = test::init_service(
+            App::new()
+                .wrap(from_fn(request_id::request_id))
+                .app_data(web::Data::new(pool.clone()))
+                .app_data(web::Data::new(tokens))
+                .configure(routes::config),
+        )
+        .await;
+
+        // Field-level validation failures are 422 with an errors array
+        let req = test::TestRequest::post()
+            .uri("/api/users")
+            .insert_header(("Authorization", format!("Bearer {}", token)))
+            .insert_header((request_id::REQUEST_ID_HEADER, "req-invalid-user"))
+            .set_json(json!({
+                "email": "not-an-email",
+                "password": "password123",
+                "full_name": "Someone",
+                "role": "Developer"
+            }))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 422);
+        assert_eq!(
+            resp.headers().get("content-type").unwrap(),
+            PROBLEM_CONTENT_TYPE
+        );
+        let body: Value = test::read_body_json(resp).await;
+        assert_eq!(body["code"], "validation_failed");
+        assert_eq!(body["status"], 422);
+        assert_eq!(body["type"], "/problems/validation-failed");
+        assert_eq!(body["request_id"], "req-invalid-user");
+        assert_eq!(body["errors"][0]["field"], "email");
+        assert_eq!(body["errors"][0]["code"], "email");
+
+        // Self-registration is validated the same way
+        let req = test::TestRequest::post()
+            .uri("/api/auth/register")
+            .set_json(json!({
+                "email": "not-an-email",
+                "password": "",
+                "full_name": "Someone",
+                "role": "Developer"
+            }))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 422);
+        let body: Value = test::read_body_json(resp).await;
+        assert_eq!(body["code"], "validation_failed");
+        assert_eq!(body["errors"][0]["field"], "email");
+        assert_eq!(body["errors"][1]["field"], "password");
+
+        // A duplicate email is a conflict on the email field, not a 500
+        let req = test::TestRequest::post()
+            .uri("/api/users")
+            .insert_header(("Authorization", format!("Bearer {}", token)))
+            .set_json(json!({
+                "email": "admin@example.com",
+                "password": "password123",
+                "full_name": "Duplicate",
+                "role": "Developer"
+            }))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 409);
+        let body: Value = test::read_body_json(resp).await;
+        assert_eq!(body["code"], "conflict");
+        assert_eq!(body["errors"][0]["field"], "email");
+        assert_eq!(body["errors"][0]["code"], "unique");
+        assert!(body["request_id"].is_string());
+
+        // Malformed bodies share the problem shape
+        let req = test::TestRequest::post()
+            .uri("/api/users")
+            .insert_header(("Authorization", format!("Bearer {}", token)))
+            .insert_header(("Content-Type", "application/json"))
+            .set_payload("{not json")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 400);
+        let body: Value = test::read_body_json(resp).await;
+        assert_eq!(body["code"], "bad_request");
+
+        // Expired tokens are distinguishable from invalid ones
+        let pem = std::fs::read(
+            Path::new(env!("CARGO_MANIFEST_DIR"))
+                .join("src/tests/fixtures/jwt_keys/test-ed25519.pem"),
+        )
+        .unwrap();
+        let claims = Claims {
+            sub: admin.id.to_string(),
+            email: admin.email.clone(),
+            role: "\"Admin\"".to_string(),
+            exp: (Utc::now().timestamp() - 3600) as usize,
+        };
+        let mut header = Header::new(Algorithm::EdDSA);
+        header.kid = Some("test-ed25519".to_string());
+        let expired = encode(&header, &claims, &EncodingKey::from_ed_pem(&pem).unwrap()).unwrap();
+
+        let req = test::TestRequest::get()
+            .uri("/api/me")
+            .insert_header(("Authorization", format!("Bearer {}", expired)))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 401);
+        let body: Value = test::read_body_json(resp).await;
+        assert_eq!(body["code"], "token_expired");
+
+        let req = test::TestRequest::get()
+            .uri("/api/me")
+            .insert_header(("Authorization", "Bearer garbage"))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 401);
+        let body: Value = test::read_body_json(resp).await;
+        assert_eq!(body["code"], "unauthorized");
+
+        cleanup_test_db(&pool).await;
+    }
+
+    #[actix_rt::test]
+    async fn test_internal_details_are_not_exposed() {
+        let err = ServiceError::DatabaseError(sqlx::Error::RowNotFound);
+        assert_eq!(err.status_code(), 500);
+        assert_eq!(err.code(), "database_error");
+        assert!(!format!("{:?}", err.error_response().body()).contains("RowNotFound"));
+    }
+}
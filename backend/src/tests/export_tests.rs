@@ -0,0 +1,269 @@
+#[cfg(test)]
+mod tests {
+    use crate::models::audit::AuditContext;
+    use crate::models::export::ExportFormat;
+    use crate::models::import::{ImportReport, ResourceAction};
+    use crate::models::project::{Project, ProjectCreate};
+    use crate::models::task::TaskCreate;
+    use crate::models::user::UserRole;
+    use crate::routes;
+    use crate::services::export_service::ExportService;
+    use crate::services::import_service::ImportService;
+    use crate::services::project_service::ProjectService;
+    use crate::services::task_service::TaskService;
+    use crate::tests::test_helpers::{
+        audit_as, cleanup_test_db, create_task, create_user, new_task, setup_test_db,
+        test_token_service,
+    };
+    use actix_web::{test, web, App};
+    use bigdecimal::BigDecimal;
+    use chrono::{TimeZone, Utc};
+    use serial_test::serial;
+    use sqlx::PgPool;
+    use uuid::Uuid;
+
+    const PLAN: &str = include_str!("fixtures/sample_plan.xml");
+
+    /// The parts of a plan an export has to preserve, keyed by task name
+    /// since UIDs are renumbered.
+    fn summary(report: &ImportReport) -> Vec<String> {
+        let name = |uid: i64| {
+            report
+                .tasks
+                .iter()
+                .find(|task| task.uid == uid)
+                .map(|task| task.name.clone())
+                .unwrap()
+        };
+        let email = |uid: i64| {
+            report
+                .resources
+                .iter()
+                .find(|resource| resource.uid == uid)
+                .and_then(|resource| resource.email.clone())
+        };
+        let mut tasks: Vec<String> = report
+            .tasks
+            .iter()
+            .map(|task| {
+                let links: Vec<String> = task
+                    .predecessors
+                    .iter()
+                    .map(|link| {
+                        format!(
+                            "{}:{:?}:{}",
+                            name(link.predecessor_uid),
+                            link.link_type,
+                            link.lag_minutes
+                        )
+                    })
+                    .collect();
+                format!(
+                    "{} wbs={:?} parent={:?} milestone={} {}..{} progress={} links={:?} resource={:?} notes={:?}",
+                    task.name,
+                    task.wbs,
+                    task.parent_uid.map(name),
+                    task.milestone,
+                    task.start_date,
+                    task.end_date,
+                    task.progress,
+                    links,
+                    task.resource_uid.and_then(email),
+                    task.description
+                )
+            })
+            .collect();
+        tasks.sort();
+        tasks
+    }
+
+    async fn create_project(audit: &AuditContext, pool: &PgPool) -> Project {
+        ProjectService::create(
+            ProjectCreate {
+                name: "Budget, Q3 \"Final\"".to_string(),
+                description: None,
+                start_date: Utc.with_ymd_and_hms(2025, 7, 1, 9, 0, 0).unwrap(),
+                end_date: Utc.with_ymd_and_hms(2025, 9, 30, 17, 0, 0).unwrap(),
+                budget: BigDecimal::from(5000),
+                client_id: None,
+            },
+            audit,
+            pool,
+        )
+        .await
+        .unwrap()
+    }
+
+    #[actix_rt::test]
+    #[serial]
+    async fn test_mspdi_export_round_trips_through_import() {
+        let pool = setup_test_db().await;
+        let pm = create_user("pm@example.com", UserRole::ProjectManager, &pool).await;
+        let audit = audit_as(pm.id);
+        let imported = ImportService::import_mspdi(PLAN, false, &audit, &pool)
+            .await
+            .unwrap();
+
+        let export = ExportService::export(
+            imported.project_id.unwrap(),
+            ExportFormat::Mspdi,
+            pm.id,
+            &pm.role,
+            &pool,
+        )
+        .await
+        .unwrap();
+        assert_eq!(export.content_type, "application/xml");
+        assert_eq!(export.filename, "warehouse-rollout.xml");
+
+        // Everything the import kept comes back out unchanged
+        let original = ImportService::parse_mspdi(PLAN).unwrap();
+        let exported = ImportService::parse_mspdi(&export.body).unwrap();
+        assert_eq!(exported.project.name, original.project.name);
+        assert_eq!(exported.project.description, original.project.description);
+        assert_eq!(exported.project.start_date, original.project.start_date);
+        assert_eq!(exported.project.end_date, original.project.end_date);
+        assert_eq!(summary(&exported), summary(&imported));
+        assert!(exported.warnings.is_empty());
+
+        // And importing the export again reuses the resources it created
+        let again = ImportService::import_mspdi(&export.body, false, &audit, &pool)
+            .await
+            .unwrap();
+        assert!(again
+            .resources
+            .iter()
+            .all(|resource| resource.action == ResourceAction::Match));
+        assert_eq!(summary(&again), summary(&imported));
+        let links = TaskService::get_links_by_project(again.project_id.unwrap(), &pool)
+            .await
+            .unwrap();
+        assert_eq!(links.len(), 3);
+
+        cleanup_test_db(&pool).await;
+    }
+
+    #[actix_rt::test]
+    #[serial]
+    async fn test_csv_export_lists_tasks_with_predecessors() {
+        let pool = setup_test_db().await;
+        let pm = create_user("pm@example.com", UserRole::ProjectManager, &pool).await;
+        let audit = audit_as(pm.id);
+        let project = create_project(&audit, &pool).await;
+        let description = Some("Totals, by region".to_string());
+        let first = create_task(
+            TaskCreate {
+                description: description.clone(),
+                ..new_task("Collect", project.id)
+            },
+            &audit,
+            &pool,
+        )
+        .await;
+        create_task(
+            TaskCreate {
+                description,
+                dependencies: vec![first.id],
+                ..new_task("=SUM(A1:A9)", project.id)
+            },
+            &audit,
+            &pool,
+        )
+        .await;
+
+        let export = ExportService::export(project.id, ExportFormat::Csv, pm.id, &pm.role, &pool)
+            .await
+            .unwrap();
+        assert_eq!(export.filename, "budget-q3-final.csv");
+        let rows: Vec<&str> = export.body.split("\r\n").collect();
+        assert_eq!(
+            rows[0],
+            "ID,WBS,Outline Level,Name,Description,Start,Finish,Milestone,Progress,Status,Predecessors,Resources"
+        );
+        assert_eq!(
+            rows[1],
+            "1,,1,Collect,\"Totals, by region\",2025-07-01 00:00,2025-07-06 00:00,no,0,pending,,"
+        );
+        // Cells that spreadsheets would evaluate are neutralized
+        assert_eq!(
+            rows[2],
+            "2,,1,'=SUM(A1:A9),\"Totals, by region\",2025-07-01 00:00,2025-07-06 00:00,no,0,pending,1FS,"
+        );
+
+        cleanup_test_db(&pool).await;
+    }
+
+    #[actix_rt::test]
+    #[serial]
+    async fn test_export_endpoint() {
+        let pool = setup_test_db().await;
+        let tokens = test_token_service();
+        let developer = create_user("dev@example.com", UserRole::Developer, &pool).await;
+        let outsider = create_user("other@example.com", UserRole::Developer, &pool).await;
+        let token = tokens.issue(&developer).unwrap().token;
+        let outsider_token = tokens.issue(&outsider).unwrap().token;
+        let project = create_project(&audit_as(developer.id), &pool).await;
+
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(pool.clone()))
+                .app_data(web::Data::new(tokens))
+                .configure(routes::config),
+        )
+        .await;
+
+        let req = test::TestRequest::get()
+            .uri(&format!("/api/projects/{}/export", project.id))
+            .insert_header(("Authorization", format!("Bearer {}", token)))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 200);
+        assert_eq!(
+            resp.headers().get("Content-Type").unwrap(),
+            "application/xml; charset=utf-8"
+        );
+        assert_eq!(
+            resp.headers().get("Content-Disposition").unwrap(),
+            "attachment; filename=\"budget-q3-final.xml\""
+        );
+        let body = test::read_body(resp).await;
+        let body = std::str::from_utf8(&body).unwrap();
+        assert!(body.contains("<Title>Budget, Q3 &quot;Final&quot;</Title>"));
+
+        let req = test::TestRequest::get()
+            .uri(&format!("/api/projects/{}/export?format=csv", project.id))
+            .insert_header(("Authorization", format!("Bearer {}", token)))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(
+            resp.headers().get("Content-Type").unwrap(),
+            "text/csv; charset=utf-8"
+        );
+
+        let req = test::TestRequest::get()
+            .uri(&format!("/api/projects/{}/export?format=pdf", project.id))
+            .insert_header(("Authorization", format!("Bearer {}", token)))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 400);
+
+        let req = test::TestRequest::get()
+            .uri(&format!("/api/projects/{}/export", Uuid::new_v4()))
+            .insert_header(("Authorization", format!("Bearer {}", token)))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 404);
+
+        // Projects the caller cannot see are not found
+        let req = test::TestRequest::get()
+            .uri(&format!("/api/projects/{}/export", project.id))
+            .insert_header(("Authorization", format!("Bearer {}", outsider_token)))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 404);
+
+        let req = test::TestRequest::get()
+            .uri(&format!("/api/projects/{}/export", project.id))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 401);
+
+        cleanup_test_db(&pool).await;
+    }
+}
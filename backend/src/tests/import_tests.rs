@@ -0,0 +1,333 @@
+#[cfg(test)]
+mod tests {
+    use crate::errors::ServiceError;
+    use crate::models::audit::AuditContext;
+    use crate::models::import::ResourceAction;
+    use crate::models::notification::{NotificationEvent, NotificationFilter};
+    use crate::models::pagination::PageParams;
+    use crate::models::resource::ResourceCreate;
+    use crate::models::task::{DependencyType, TaskStatus};
+    use crate::models::user::UserRole;
+    use crate::routes;
+    use crate::services::import_service::ImportService;
+    use crate::services::notification_service::NotificationService;
+    use crate::services::project_service::ProjectService;
+    use crate::services::resource_service::ResourceService;
+    use crate::services::search_service::SearchService;
+    use crate::services::task_service::TaskService;
+    use crate::tests::test_helpers::{
+        audit_as, cleanup_test_db, create_user, setup_test_db, test_token_service,
+    };
+    use actix_web::{test as actix_test, web, App};
+    use bigdecimal::BigDecimal;
+    use serde_json::Value;
+    use serial_test::serial;
+    use sqlx::PgPool;
+
+    const PLAN: &str = include_str!("fixtures/sample_plan.xml");
+
+    async fn create_engineer(pool: &PgPool) {
+        ResourceService::create(
+            ResourceCreate {
+                name: "Existing Engineer".to_string(),
+                email: "engineer@example.com".to_string(),
+                role: "Engineer".to_string(),
+                skills: vec![],
+                availability: BigDecimal::from(100),
+                hourly_rate: BigDecimal::from(90),
+            },
+            &AuditContext::default(),
+            pool,
+        )
+        .await
+        .unwrap();
+    }
+
+    #[test]
+    fn test_parse_maps_hierarchy_links_and_assignments() {
+        let report = ImportService::parse_mspdi(PLAN).unwrap();
+
+        assert_eq!(report.project.name, "Warehouse Rollout");
+        assert_eq!(
+            report.project.description.as_deref(),
+            Some("Roll out the new warehouse system")
+        );
+
+        // The project summary task and blank rows are not tasks
+        let uids: Vec<i64> = report.tasks.iter().map(|task| task.uid).collect();
+        assert_eq!(uids, vec![1, 2, 3, 4, 6]);
+        let task = |uid: i64| report.tasks.iter().find(|task| task.uid == uid).unwrap();
+        assert_eq!(task(1).parent_uid, None);
+        assert_eq!(task(2).parent_uid, Some(1));
+        assert_eq!(task(3).parent_uid, Some(1));
+        assert_eq!(task(4).parent_uid, None);
+        assert_eq!(task(3).wbs.as_deref(), Some("1.2"));
+        assert!(task(6).milestone);
+        assert_eq!(task(3).progress, BigDecimal::from(40));
+
+        // Lags are stored in tenths of a minute
+        let link = &task(3).predecessors[0];
+        assert_eq!(link.predecessor_uid, 2);
+        assert_eq!(link.link_type, DependencyType::FinishToStart);
+        assert_eq!(link.lag_minutes, 480);
+        let build: Vec<_> = task(4)
+            .predecessors
+            .iter()
+            .map(|link| (link.predecessor_uid, link.link_type, link.lag_minutes))
+            .collect();
+        assert_eq!(
+            build,
+            vec![
+                (3, DependencyType::StartToStart, 0),
+                (6, DependencyType::FinishToFinish, -240),
+            ]
+        );
+        assert_eq!(task(6).predecessors[0].lag_minutes, 0);
+
+        assert_eq!(task(2).resource_uid, Some(1));
+        assert_eq!(task(4).resource_uid, Some(2));
+        assert_eq!(task(6).resource_uid, None);
+
+        let resources: Vec<i64> = report.resources.iter().map(|r| r.uid).collect();
+        assert_eq!(resources, vec![1, 2, 3]);
+        let ada = &report.resources[0];
+        assert_eq!(ada.email.as_deref(), Some("ada@example.com"));
+        assert_eq!(ada.role, "Business Analyst");
+        assert_eq!(ada.availability, BigDecimal::from(50));
+
+        let flagged: Vec<&str> = report
+            .warnings
+            .iter()
+            .map(|warning| warning.element.as_str())
+            .collect();
+        assert_eq!(
+            flagged,
+            vec!["Resource 4", "Task 6", "Task 4", "Assignment 3"]
+        );
+    }
+
+    #[test]
+    fn test_parse_rejects_files_that_are_not_mspdi() {
+        assert!(matches!(
+            ImportService::parse_mspdi("<Project><Tasks>"),
+            Err(ServiceError::BadRequest(_))
+        ));
+        assert!(matches!(
+            ImportService::parse_mspdi("<Workbook/>"),
+            Err(ServiceError::BadRequest(_))
+        ));
+        assert!(matches!(
+            ImportService::parse_mspdi("<Project><Tasks/></Project>"),
+            Err(ServiceError::ValidationError(_))
+        ));
+    }
+
+    #[actix_rt::test]
+    #[serial]
+    async fn test_import_creates_the_plan() {
+        let pool = setup_test_db().await;
+        let pm = create_user("pm@example.com", UserRole::ProjectManager, &pool).await;
+        create_engineer(&pool).await;
+
+        let report = ImportService::import_mspdi(PLAN, false, &audit_as(pm.id), &pool)
+            .await
+            .unwrap();
+        assert!(!report.dry_run);
+        let project = ProjectService::get_by_id(report.project_id.unwrap(), &pool)
+            .await
+            .unwrap();
+        assert_eq!(project.name, "Warehouse Rollout");
+
+        let actions: Vec<_> = report.resources.iter().map(|r| r.action).collect();
+        assert_eq!(
+            actions,
+            vec![
+                ResourceAction::Create,
+                ResourceAction::Match,
+                ResourceAction::Skip
+            ]
+        );
+        let ada = report.resources[0].resource_id.unwrap();
+        let engineer = report.resources[1].resource_id.unwrap();
+
+        let id = |uid: i64| {
+            report
+                .tasks
+                .iter()
+                .find(|task| task.uid == uid)
+                .and_then(|task| task.task_id)
+                .unwrap()
+        };
+        let review = TaskService::get_by_id(id(2), &pool).await.unwrap();
+        assert_eq!(review.parent_id, Some(id(1)));
+        assert_eq!(review.assigned_to, vec![ada]);
+        assert_eq!(review.status, TaskStatus::Completed);
+        let design = TaskService::get_by_id(id(3), &pool).await.unwrap();
+        assert_eq!(design.status, TaskStatus::InProgress);
+        assert_eq!(design.dependencies, vec![id(2)]);
+        // The contractor has no email, so the task stays unassigned
+        assert!(design.assigned_to.is_empty());
+        let build = TaskService::get_by_id(id(4), &pool).await.unwrap();
+        assert_eq!(build.assigned_to, vec![engineer]);
+        assert_eq!(build.dependencies, vec![id(3), id(6)]);
+        assert!(
+            TaskService::get_by_id(id(6), &pool)
+                .await
+                .unwrap()
+                .milestone
+        );
+
+        // Only links that differ from plain finish-to-start are detailed
+        let links: Vec<_> = TaskService::get_links_by_project(project.id, &pool)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|link| {
+                (
+                    link.task_id,
+                    link.predecessor_id,
+                    link.link_type,
+                    link.lag_minutes,
+                )
+            })
+            .collect();
+        assert_eq!(links.len(), 3);
+        assert!(links.contains(&(id(3), id(2), DependencyType::FinishToStart, 480)));
+        assert!(links.contains(&(id(4), id(3), DependencyType::StartToStart, 0)));
+        assert!(links.contains(&(id(4), id(6), DependencyType::FinishToFinish, -240)));
+
+        cleanup_test_db(&pool).await;
+    }
+
+    #[actix_rt::test]
+    #[serial]
+    async fn test_imported_assignments_reach_the_resources_users() {
+        let pool = setup_test_db().await;
+        let pm = create_user("pm@example.com", UserRole::ProjectManager, &pool).await;
+        // Ada is a resource in the plan with the same email
+        let ada = create_user("ada@example.com", UserRole::Developer, &pool).await;
+        let outsider = create_user("qa@example.com", UserRole::QaEngineer, &pool).await;
+
+        let report = ImportService::import_mspdi(PLAN, false, &audit_as(pm.id), &pool)
+            .await
+            .unwrap();
+        let project_id = report.project_id.unwrap();
+        let review = report
+            .tasks
+            .iter()
+            .find(|task| task.uid == 2)
+            .and_then(|task| task.task_id)
+            .unwrap();
+
+        let inbox = NotificationService::list(
+            ada.id,
+            &PageParams::default(),
+            &NotificationFilter::default(),
+            &pool,
+        )
+        .await
+        .unwrap()
+        .items;
+        assert_eq!(inbox.len(), 1);
+        assert_eq!(inbox[0].event, NotificationEvent::TaskAssigned);
+        assert_eq!(inbox[0].task_id, Some(review));
+
+        let mut conn = pool.acquire().await.unwrap();
+        assert!(
+            ProjectService::is_visible_to(project_id, ada.id, &ada.role, &mut conn)
+                .await
+                .unwrap()
+        );
+        assert!(
+            !ProjectService::is_visible_to(project_id, outsider.id, &outsider.role, &mut conn)
+                .await
+                .unwrap()
+        );
+        drop(conn);
+
+        let results = SearchService::search("review", None, ada.id, &ada.role, &pool)
+            .await
+            .unwrap();
+        assert!(results.tasks.iter().any(|hit| hit.id == review));
+        let results = SearchService::search("review", None, outsider.id, &outsider.role, &pool)
+            .await
+            .unwrap();
+        assert!(results.tasks.is_empty());
+
+        cleanup_test_db(&pool).await;
+    }
+
+    #[actix_rt::test]
+    #[serial]
+    async fn test_import_endpoint() {
+        let pool = setup_test_db().await;
+        let tokens = test_token_service();
+        let pm = create_user("pm@example.com", UserRole::ProjectManager, &pool).await;
+        let developer = create_user("dev@example.com", UserRole::Developer, &pool).await;
+        let pm_token = tokens.issue(&pm).unwrap().token;
+        let dev_token = tokens.issue(&developer).unwrap().token;
+
+        let app = actix_test::init_service(
+            App::new()
+                .app_data(web::Data::new(pool.clone()))
+                .app_data(web::Data::new(tokens))
+                .configure(routes::config),
+        )
+        .await;
+
+        let req = actix_test::TestRequest::post()
+            .uri("/api/projects/import")
+            .insert_header(("Authorization", format!("Bearer {}", dev_token)))
+            .insert_header(("Content-Type", "application/xml"))
+            .set_payload(PLAN)
+            .to_request();
+        assert_eq!(actix_test::call_service(&app, req).await.status(), 403);
+
+        let req = actix_test::TestRequest::post()
+            .uri("/api/projects/import?dry_run=true")
+            .insert_header(("Authorization", format!("Bearer {}", pm_token)))
+            .insert_header(("Content-Type", "application/xml"))
+            .set_payload(PLAN)
+            .to_request();
+        let resp = actix_test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 200);
+        let body: Value = actix_test::read_body_json(resp).await;
+        assert_eq!(body["dry_run"], true);
+        assert_eq!(body["project_id"], Value::Null);
+        assert_eq!(body["tasks"].as_array().unwrap().len(), 5);
+        assert_eq!(body["resources"][0]["action"], "create");
+        assert_eq!(
+            body["tasks"][2]["predecessors"][0]["link_type"],
+            "finish_to_start"
+        );
+        let projects: i64 = sqlx::query_scalar!(r#"SELECT COUNT(*) as "count!" FROM projects"#)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(projects, 0);
+
+        let req = actix_test::TestRequest::post()
+            .uri("/api/projects/import")
+            .insert_header(("Authorization", format!("Bearer {}", pm_token)))
+            .insert_header(("Content-Type", "application/xml"))
+            .set_payload(PLAN)
+            .to_request();
+        let resp = actix_test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 201);
+        let body: Value = actix_test::read_body_json(resp).await;
+        assert!(body["project_id"].is_string());
+        assert!(body["tasks"][0]["task_id"].is_string());
+
+        let req = actix_test::TestRequest::post()
+            .uri("/api/projects/import")
+            .insert_header(("Authorization", format!("Bearer {}", pm_token)))
+            .set_payload("not xml")
+            .to_request();
+        let resp = actix_test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 400);
+        let body: Value = actix_test::read_body_json(resp).await;
+        assert_eq!(body["code"], "bad_request");
+
+        cleanup_test_db(&pool).await;
+    }
+}
@@ -3,7 +3,7 @@ mod tests {
     use crate::models::auth::AuthResponse;
     use crate::models::user::{UserCreate, UserRole};
     use crate::routes;
-    use crate::tests::test_helpers::setup_test_db;
+    use crate::tests::test_helpers::{setup_test_db, test_token_service};
     use actix_web::{test, web, App};
     use serial_test::serial;
 
@@ -16,6 +16,7 @@ mod tests {
         test::init_service(
             App::new()
                 .app_data(web::Data::new(pool.clone()))
+                .app_data(web::Data::new(test_token_service()))
                 .configure(routes::config),
         )
         .await
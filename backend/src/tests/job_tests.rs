@@ -0,0 +1,538 @@
+#[cfg(test)]
+mod tests {
+    use crate::errors::ServiceError;
+    use crate::models::job::{Job, JobKind, JobSchedule, JobScheduleUpdate, JobStatus};
+    use crate::models::pagination::Page;
+    use crate::models::patch::Patch;
+    use crate::models::task::{TaskCreate, TaskStatus, TaskUpdate};
+    use crate::models::user::UserRole;
+    use crate::routes;
+    use crate::services::email_service::EmailService;
+    use crate::services::job_service::JobService;
+    use crate::services::notification_service::NotificationService;
+    use crate::services::project_service::ProjectService;
+    use crate::services::task_service::TaskService;
+    use crate::tests::test_helpers::{
+        audit_as, cleanup_test_db, create_project, create_resource, create_task, create_user,
+        job_context, new_task, setup_test_db, test_token_service,
+    };
+    use actix_web::{test, web, App};
+    use bigdecimal::BigDecimal;
+    use chrono::{Duration, TimeZone, Utc};
+    use serde_json::{json, Value};
+    use serial_test::serial;
+    use std::str::FromStr;
+    use uuid::Uuid;
+
+    #[actix_rt::test]
+    #[serial]
+    async fn test_runners_never_claim_the_same_job() {
+        let pool = setup_test_db().await;
+        let now = Utc::now();
+        let first = JobService::enqueue(JobKind::OverdueTasks, json!({}), now, &pool)
+            .await
+            .unwrap();
+        let second = JobService::enqueue(JobKind::DailyDigest, json!({}), now, &pool)
+            .await
+            .unwrap();
+        JobService::enqueue(
+            JobKind::TrashPurge,
+            json!({}),
+            now + Duration::hours(1),
+            &pool,
+        )
+        .await
+        .unwrap();
+
+        // Another runner holds the oldest job: it is skipped, not waited on
+        let mut tx = pool.begin().await.unwrap();
+        sqlx::query("SELECT id FROM jobs WHERE id = $1 FOR UPDATE")
+            .bind(first.id)
+            .execute(&mut *tx)
+            .await
+            .unwrap();
+        let claimed = JobService::claim("b", now, &pool).await.unwrap().unwrap();
+        assert_eq!(claimed.id, second.id);
+        assert_eq!(claimed.status, JobStatus::Running);
+        assert_eq!(claimed.attempts, 1);
+        assert_eq!(claimed.locked_by.as_deref(), Some("b"));
+        assert!(JobService::claim("b", now, &pool).await.unwrap().is_none());
+        tx.rollback().await.unwrap();
+
+        let claimed = JobService::claim("a", now, &pool).await.unwrap().unwrap();
+        assert_eq!(claimed.id, first.id);
+        // Not due yet
+        assert!(JobService::claim("a", now, &pool).await.unwrap().is_none());
+
+        // A runner that vanished gives its jobs back after the lease
+        assert_eq!(JobService::reclaim_stale(now, &pool).await.unwrap(), 0);
+        let later = now + Duration::hours(2);
+        assert_eq!(JobService::reclaim_stale(later, &pool).await.unwrap(), 2);
+        let job = JobService::get(first.id, &pool).await.unwrap();
+        assert_eq!(job.status, JobStatus::Pending);
+        assert_eq!(job.last_error.as_deref(), Some("abandoned by runner a"));
+
+        // The slow runner reporting back late does not overwrite the new run
+        let mut reclaimed = Vec::new();
+        while let Some(job) = JobService::claim("b", later, &pool).await.unwrap() {
+            reclaimed.push(job);
+        }
+        let reclaimed = reclaimed
+            .into_iter()
+            .find(|job| job.id == first.id)
+            .unwrap();
+        assert!(JobService::finish(&claimed, Ok(()), later, &pool)
+            .await
+            .unwrap()
+            .is_none());
+        let job = JobService::get(first.id, &pool).await.unwrap();
+        assert_eq!(job.status, JobStatus::Running);
+        assert_eq!(job.locked_by.as_deref(), Some("b"));
+        let finished = JobService::finish(&reclaimed, Ok(()), later, &pool)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(finished.status, JobStatus::Succeeded);
+
+        cleanup_test_db(&pool).await;
+    }
+
+    #[actix_rt::test]
+    #[serial]
+    async fn test_failed_jobs_back_off_then_give_up() {
+        let pool = setup_test_db().await;
+        assert_eq!(JobService::backoff(1), Duration::seconds(30));
+        assert_eq!(JobService::backoff(3), Duration::seconds(120));
+        assert_eq!(JobService::backoff(12), Duration::hours(1));
+
+        let mut now = Utc::now();
+        let job = JobService::enqueue(
+            JobKind::TrashPurge,
+            json!({ "retention_days": "x" }),
+            now,
+            &pool,
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            JobService::run_pending(&job_context(&pool, EmailService::in_memory()), "a")
+                .await
+                .unwrap(),
+            1
+        );
+        let job = JobService::get(job.id, &pool).await.unwrap();
+        assert_eq!(job.status, JobStatus::Pending);
+        assert_eq!(job.attempts, 1);
+        assert!(job
+            .last_error
+            .as_deref()
+            .unwrap()
+            .contains("retention_days"));
+        assert!(JobService::claim("a", now, &pool).await.unwrap().is_none());
+
+        let mut job = job;
+        for attempt in 2..=5 {
+            now = job.run_at;
+            let claimed = JobService::claim("a", now, &pool).await.unwrap().unwrap();
+            assert_eq!(claimed.attempts, attempt);
+            job = JobService::finish(
+                &claimed,
+                Err(ServiceError::BadRequest("still broken".into())),
+                now,
+                &pool,
+            )
+            .await
+            .unwrap()
+            .unwrap();
+        }
+        assert_eq!(job.status, JobStatus::Failed);
+        assert_eq!(job.finished_at, Some(now));
+        assert!(JobService::claim("a", now + Duration::days(1), &pool)
+            .await
+            .unwrap()
+            .is_none());
+
+        let retried = JobService::retry(job.id, now, &pool).await.unwrap();
+        assert_eq!(retried.status, JobStatus::Pending);
+        assert_eq!(retried.attempts, 0);
+        assert!(matches!(
+            JobService::retry(job.id, now, &pool).await,
+            Err(ServiceError::Conflict(_))
+        ));
+        assert!(matches!(
+            JobService::retry(Uuid::new_v4(), now, &pool).await,
+            Err(ServiceError::NotFound(_))
+        ));
+
+        cleanup_test_db(&pool).await;
+    }
+
+    #[actix_rt::test]
+    #[serial]
+    async fn test_schedules_enqueue_once_per_slot() {
+        let pool = setup_test_db().await;
+        let now = Utc.with_ymd_and_hms(2025, 7, 1, 0, 58, 0).unwrap();
+        JobService::install_schedules(now, &pool).await.unwrap();
+        let schedules = JobService::list_schedules(&pool).await.unwrap();
+        let overdue = schedules
+            .iter()
+            .find(|s| s.name == "overdue-tasks")
+            .unwrap();
+        assert_eq!(overdue.kind, JobKind::OverdueTasks);
+        assert_eq!(
+            overdue.next_run_at,
+            Utc.with_ymd_and_hms(2025, 7, 1, 1, 0, 0).unwrap()
+        );
+        // Installing again leaves existing schedules alone
+        JobService::install_schedules(now + Duration::days(1), &pool)
+            .await
+            .unwrap();
+        let again = JobService::list_schedules(&pool).await.unwrap();
+        assert_eq!(again.len(), schedules.len());
+        assert!(again
+            .iter()
+            .all(|s| s.next_run_at < now + Duration::days(1)));
+
+        // 01:00 for overdue tasks, notification emails, webhook deliveries and
+        // document cleanup
+        let tick = Utc.with_ymd_and_hms(2025, 7, 1, 1, 0, 0).unwrap();
+        assert_eq!(JobService::enqueue_due(tick, &pool).await.unwrap(), 4);
+        assert_eq!(JobService::enqueue_due(tick, &pool).await.unwrap(), 0);
+
+        // Five minutes on, no job has run yet: the slots are skipped
+        let tick = tick + Duration::minutes(5);
+        assert_eq!(JobService::enqueue_due(tick, &pool).await.unwrap(), 0);
+        let emails = JobService::list_schedules(&pool)
+            .await
+            .unwrap()
+            .into_iter()
+            .find(|s| s.name == "notification-emails")
+            .unwrap();
+        assert_eq!(emails.next_run_at, tick + Duration::minutes(5));
+        assert_eq!(emails.last_enqueued_at, Some(tick - Duration::minutes(5)));
+
+        JobService::run_pending(&job_context(&pool, EmailService::in_memory()), "a")
+            .await
+            .unwrap();
+        let tick = tick + Duration::minutes(5);
+        assert_eq!(JobService::enqueue_due(tick, &pool).await.unwrap(), 2);
+
+        let updated = JobService::update_schedule(
+            "notification-emails",
+            JobScheduleUpdate {
+                enabled: Patch::Value(false),
+                ..Default::default()
+            },
+            tick,
+            &pool,
+        )
+        .await
+        .unwrap();
+        assert!(!updated.enabled);
+        JobService::run_pending(&job_context(&pool, EmailService::in_memory()), "a")
+            .await
+            .unwrap();
+        // 01:15 for webhook deliveries and document cleanup
+        let tick = tick + Duration::minutes(5);
+        assert_eq!(JobService::enqueue_due(tick, &pool).await.unwrap(), 2);
+        assert!(matches!(
+            JobService::update_schedule(
+                "notification-emails",
+                JobScheduleUpdate {
+                    cron: Patch::Value("0 0 31 2 *".to_string()),
+                    ..Default::default()
+                },
+                tick,
+                &pool,
+            )
+            .await,
+            Err(ServiceError::ValidationError(_))
+        ));
+
+        cleanup_test_db(&pool).await;
+    }
+
+    #[actix_rt::test]
+    #[serial]
+    async fn test_schedule_metrics_and_digests() {
+        let pool = setup_test_db().await;
+        let manager = create_user("pm@example.com", UserRole::ProjectManager, &pool).await;
+        let developer = create_user("dev@example.com", UserRole::Developer, &pool).await;
+        let developer_resource = create_resource(&developer, &pool).await;
+        let audit = audit_as(manager.id);
+        let project = create_project("Depot refit", &audit, &pool).await;
+
+        let day = |day: u32| Utc.with_ymd_and_hms(2025, 7, day, 0, 0, 0).unwrap();
+        let done = create_task(
+            TaskCreate {
+                start_date: day(1),
+                end_date: day(4),
+                ..new_task("Strip out", project.id)
+            },
+            &audit,
+            &pool,
+        )
+        .await;
+        TaskService::update(
+            done.id,
+            TaskUpdate {
+                status: Patch::Value(TaskStatus::Completed),
+                progress: Patch::Value(BigDecimal::from(100)),
+                ..Default::default()
+            },
+            None,
+            &audit,
+            &pool,
+        )
+        .await
+        .unwrap();
+        let late = create_task(
+            TaskCreate {
+                assigned_to: Some(developer_resource),
+                start_date: day(2),
+                end_date: day(4),
+                ..new_task("Rewire", project.id)
+            },
+            &audit,
+            &pool,
+        )
+        .await;
+        TaskService::update(
+            late.id,
+            TaskUpdate {
+                progress: Patch::Value(BigDecimal::from(20)),
+                ..Default::default()
+            },
+            None,
+            &audit,
+            &pool,
+        )
+        .await
+        .unwrap();
+        create_task(
+            TaskCreate {
+                assigned_to: Some(developer_resource),
+                start_date: day(4),
+                end_date: day(7),
+                ..new_task("Fit out", project.id)
+            },
+            &audit,
+            &pool,
+        )
+        .await;
+
+        assert!(matches!(
+            ProjectService::get_schedule_metrics(project.id, manager.id, &manager.role, &pool)
+                .await,
+            Err(ServiceError::NotFound(_))
+        ));
+        let now = Utc.with_ymd_and_hms(2025, 7, 5, 12, 0, 0).unwrap();
+        assert_eq!(
+            ProjectService::recompute_schedule_metrics(now, &pool)
+                .await
+                .unwrap(),
+            1
+        );
+        // 8 task-days in all, 6.5 of them elapsed
+        let metrics =
+            ProjectService::get_schedule_metrics(project.id, manager.id, &manager.role, &pool)
+                .await
+                .unwrap();
+        assert_eq!(metrics.task_count, 3);
+        assert_eq!(metrics.completed_count, 1);
+        assert_eq!(metrics.overdue_count, 1);
+        assert_eq!(
+            metrics.percent_complete,
+            BigDecimal::from_str("42.50").unwrap()
+        );
+        assert_eq!(
+            metrics.percent_planned,
+            BigDecimal::from_str("81.25").unwrap()
+        );
+        assert_eq!(metrics.computed_at, now);
+
+        // Both assignments are unread in the inbox and go out in one digest
+        let email = EmailService::in_memory();
+        assert_eq!(
+            NotificationService::send_digests(&email, &pool)
+                .await
+                .unwrap(),
+            1
+        );
+        let sent = email.sent();
+        assert_eq!(sent[0].to, "dev@example.com");
+        assert_eq!(sent[0].subject, "2 unread notifications");
+        assert!(sent[0]
+            .body
+            .starts_with("- Assigned: Rewire\n- Assigned: Fit out"));
+        assert_eq!(
+            NotificationService::send_digests(&email, &pool)
+                .await
+                .unwrap(),
+            0
+        );
+
+        cleanup_test_db(&pool).await;
+    }
+
+    #[actix_rt::test]
+    #[serial]
+    async fn test_job_endpoints() {
+        let pool = setup_test_db().await;
+        let tokens = test_token_service();
+        let admin = create_user("admin@example.com", UserRole::Admin, &pool).await;
+        let developer = create_user("dev@example.com", UserRole::Developer, &pool).await;
+        let admin_bearer = format!("Bearer {}", tokens.issue(&admin).unwrap().token);
+        let dev_bearer = format!("Bearer {}", tokens.issue(&developer).unwrap().token);
+        let project = create_project("Depot refit", &audit_as(admin.id), &pool).await;
+        JobService::install_schedules(Utc::now(), &pool)
+            .await
+            .unwrap();
+
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(pool.clone()))
+                .app_data(web::Data::new(tokens))
+                .configure(routes::config),
+        )
+        .await;
+        let request = |method: &str, uri: &str, bearer: &str| {
+            let req = match method {
+                "GET" => test::TestRequest::get(),
+                "POST" => test::TestRequest::post(),
+                _ => test::TestRequest::patch(),
+            };
+            req.uri(uri)
+                .insert_header(("Authorization", bearer.to_string()))
+        };
+
+        for uri in ["/api/jobs", "/api/jobs/schedules"] {
+            let resp =
+                test::call_service(&app, request("GET", uri, &dev_bearer).to_request()).await;
+            assert_eq!(resp.status(), 403);
+        }
+
+        let resp = test::call_service(
+            &app,
+            request(
+                "POST",
+                "/api/jobs/schedules/schedule-metrics/run",
+                &admin_bearer,
+            )
+            .to_request(),
+        )
+        .await;
+        assert_eq!(resp.status(), 202);
+        let job: Job = test::read_body_json(resp).await;
+        assert_eq!(job.kind, JobKind::ScheduleMetrics);
+        assert_eq!(job.schedule.as_deref(), Some("schedule-metrics"));
+        let resp = test::call_service(
+            &app,
+            request("POST", "/api/jobs/schedules/nightly/run", &admin_bearer).to_request(),
+        )
+        .await;
+        assert_eq!(resp.status(), 404);
+
+        let resp = test::call_service(
+            &app,
+            request(
+                "GET",
+                &format!("/api/projects/{}/schedule-metrics", project.id),
+                &admin_bearer,
+            )
+            .to_request(),
+        )
+        .await;
+        assert_eq!(resp.status(), 404);
+        JobService::run_pending(&job_context(&pool, EmailService::in_memory()), "a")
+            .await
+            .unwrap();
+        let metrics: Value = test::call_and_read_body_json(
+            &app,
+            request(
+                "GET",
+                &format!("/api/projects/{}/schedule-metrics", project.id),
+                &admin_bearer,
+            )
+            .to_request(),
+        )
+        .await;
+        assert_eq!(metrics["task_count"], 0);
+        // The developer has no part in the project
+        let resp = test::call_service(
+            &app,
+            request(
+                "GET",
+                &format!("/api/projects/{}/schedule-metrics", project.id),
+                &dev_bearer,
+            )
+            .to_request(),
+        )
+        .await;
+        assert_eq!(resp.status(), 404);
+
+        let failing = JobService::enqueue(
+            JobKind::TrashPurge,
+            json!({ "retention_days": 0 }),
+            Utc::now(),
+            &pool,
+        )
+        .await
+        .unwrap();
+        sqlx::query("UPDATE jobs SET max_attempts = 1 WHERE id = $1")
+            .bind(failing.id)
+            .execute(&pool)
+            .await
+            .unwrap();
+        JobService::run_pending(&job_context(&pool, EmailService::in_memory()), "a")
+            .await
+            .unwrap();
+
+        let page: Page<Job> = test::call_and_read_body_json(
+            &app,
+            request("GET", "/api/jobs?status=failed", &admin_bearer).to_request(),
+        )
+        .await;
+        assert_eq!(page.total, 1);
+        assert_eq!(page.items[0].id, failing.id);
+        let page: Page<Job> = test::call_and_read_body_json(
+            &app,
+            request("GET", "/api/jobs?kind=schedule_metrics", &admin_bearer).to_request(),
+        )
+        .await;
+        assert_eq!(page.items[0].status, JobStatus::Succeeded);
+
+        let retry_uri = format!("/api/jobs/{}/retry", failing.id);
+        let job: Job = test::call_and_read_body_json(
+            &app,
+            request("POST", &retry_uri, &admin_bearer).to_request(),
+        )
+        .await;
+        assert_eq!(job.status, JobStatus::Pending);
+        let resp = test::call_service(
+            &app,
+            request("POST", &retry_uri, &admin_bearer).to_request(),
+        )
+        .await;
+        assert_eq!(resp.status(), 409);
+
+        let schedule: JobSchedule = test::call_and_read_body_json(
+            &app,
+            request("PATCH", "/api/jobs/schedules/daily-digest", &admin_bearer)
+                .set_json(json!({ "cron": "0 6 * * 1-5" }))
+                .to_request(),
+        )
+        .await;
+        assert_eq!(schedule.cron, "0 6 * * 1-5");
+        let resp = test::call_service(
+            &app,
+            request("PATCH", "/api/jobs/schedules/daily-digest", &admin_bearer)
+                .set_json(json!({ "cron": "every morning" }))
+                .to_request(),
+        )
+        .await;
+        assert_eq!(resp.status(), 422);
+
+        cleanup_test_db(&pool).await;
+    }
+}
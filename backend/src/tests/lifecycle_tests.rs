@@ -1,20 +1,16 @@
 #[cfg(test)]
 mod tests {
-    use super::*;
     use crate::{
-        errors::ServiceError,
         models::{
+            audit::AuditContext,
             auth::AuthResponse,
             lifecycle::{LifecyclePhase, PhaseTransition},
             project::ProjectCreate,
             user::{UserCreate, UserRole},
         },
         routes,
-        services::{
-            auth_service::AuthService, lifecycle_service::LifecycleService,
-            project_service::ProjectService,
-        },
-        tests::test_helpers::{cleanup_test_db, setup_test_db},
+        services::{auth_service::AuthService, project_service::ProjectService},
+        tests::test_helpers::{cleanup_test_db, setup_test_db, test_token_service},
     };
     use actix_web::{test, web, App};
     use bigdecimal::{BigDecimal, FromPrimitive};
@@ -32,7 +28,14 @@ mod tests {
             full_name: "Project Manager".to_string(),
             role: UserRole::ProjectManager,
         };
-        let auth_response = AuthService::register(pm_user, pool).await.unwrap();
+        let auth_response = AuthService::register(
+            pm_user,
+            &test_token_service(),
+            &AuditContext::default(),
+            pool,
+        )
+        .await
+        .unwrap();
 
         // Create a test project
         let project = ProjectCreate {
@@ -43,7 +46,9 @@ mod tests {
             budget: BigDecimal::from_f64(10000.0).unwrap(),
             client_id: None,
         };
-        let created_project = ProjectService::create(project, pool).await.unwrap();
+        let created_project = ProjectService::create(project, &AuditContext::default(), pool)
+            .await
+            .unwrap();
 
         (auth_response, created_project.id)
     }
@@ -56,6 +61,7 @@ mod tests {
         let app = test::init_service(
             App::new()
                 .app_data(web::Data::new(pool.clone()))
+                .app_data(web::Data::new(test_token_service()))
                 .configure(routes::config),
         )
         .await;
@@ -67,7 +73,14 @@ mod tests {
             full_name: "Developer".to_string(),
             role: UserRole::Developer,
         };
-        let dev_response = AuthService::register(developer, &pool).await.unwrap();
+        let dev_response = AuthService::register(
+            developer,
+            &test_token_service(),
+            &AuditContext::default(),
+            &pool,
+        )
+        .await
+        .unwrap();
 
         // Create test project
         let (_, project_id) = create_test_user_and_project(&pool).await;
@@ -78,6 +91,7 @@ mod tests {
             phase: LifecyclePhase::Requirements,
             description: "Unauthorized transition".to_string(),
             attachments: None,
+            documents: vec![],
         };
 
         let req = test::TestRequest::post()
@@ -99,6 +113,7 @@ mod tests {
         let app = test::init_service(
             App::new()
                 .app_data(web::Data::new(pool.clone()))
+                .app_data(web::Data::new(test_token_service()))
                 .configure(routes::config),
         )
         .await;
@@ -118,6 +133,7 @@ mod tests {
                 phase,
                 description: description.to_string(),
                 attachments: None,
+                documents: vec![],
             };
 
             let req = test::TestRequest::post()
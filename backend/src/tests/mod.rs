@@ -1,7 +1,31 @@
+pub mod archive_tests;
+pub mod audit_tests;
 pub mod auth_tests;
+pub mod bulk_tests;
+pub mod calendar_tests;
+pub mod comment_tests;
+pub mod concurrency_tests;
+pub mod cron_tests;
+pub mod defect_tests;
+pub mod document_tests;
+pub mod error_tests;
+pub mod export_tests;
+pub mod import_tests;
 pub mod integration_tests;
+pub mod job_tests;
 pub mod lifecycle_tests;
+pub mod notification_tests;
+pub mod oidc_tests;
+pub mod patch_tests;
 pub mod project_tests;
+pub mod realtime_tests;
+pub mod requirement_tests;
+pub mod search_tests;
 pub mod task_tests;
+pub mod template_tests;
 pub mod test_helpers;
+pub mod testing_tests;
+pub mod token_tests;
+pub mod trash_tests;
 pub mod user_tests;
+pub mod webhook_tests;
@@ -0,0 +1,418 @@
+#[cfg(test)]
+mod tests {
+    use crate::models::audit::AuditContext;
+    use crate::models::calendar::GateReviewCreate;
+    use crate::models::lifecycle::{LifecyclePhase, PhaseTransition};
+    use crate::models::notification::{
+        Notification, NotificationEvent, NotificationFilter, NotificationPreference,
+    };
+    use crate::models::pagination::{Page, PageParams};
+    use crate::models::patch::Patch;
+    use crate::models::project::{Project, ProjectCreate, ProjectUpdate};
+    use crate::models::task::{TaskCreate, TaskUpdate};
+    use crate::models::user::{User, UserRole};
+    use crate::routes;
+    use crate::services::email_service::EmailService;
+    use crate::services::lifecycle_service::LifecycleService;
+    use crate::services::notification_service::NotificationService;
+    use crate::services::project_service::ProjectService;
+    use crate::services::task_service::TaskService;
+    use crate::tests::test_helpers::{
+        cleanup_test_db, create_resource, create_task, create_user, new_task, setup_test_db,
+        test_token_service,
+    };
+    use actix_web::{test, web, App};
+    use bigdecimal::BigDecimal;
+    use chrono::{Duration, TimeZone, Utc};
+    use serde_json::json;
+    use serial_test::serial;
+    use sqlx::PgPool;
+
+    fn acting_as(user: &User) -> AuditContext {
+        AuditContext {
+            actor_id: Some(user.id),
+            request_id: None,
+        }
+    }
+
+    async fn create_project(budget: i64, audit: &AuditContext, pool: &PgPool) -> Project {
+        ProjectService::create(
+            ProjectCreate {
+                name: "Warehouse rollout".to_string(),
+                description: None,
+                start_date: Utc.with_ymd_and_hms(2025, 7, 1, 9, 0, 0).unwrap(),
+                end_date: Utc.with_ymd_and_hms(2025, 9, 30, 17, 0, 0).unwrap(),
+                budget: BigDecimal::from(budget),
+                client_id: None,
+            },
+            audit,
+            pool,
+        )
+        .await
+        .unwrap()
+    }
+
+    async fn inbox(user: &User, pool: &PgPool) -> Vec<Notification> {
+        NotificationService::list(
+            user.id,
+            &PageParams::default(),
+            &NotificationFilter::default(),
+            pool,
+        )
+        .await
+        .unwrap()
+        .items
+    }
+
+    fn events(notifications: &[Notification]) -> Vec<NotificationEvent> {
+        notifications.iter().map(|n| n.event).collect()
+    }
+
+    #[actix_rt::test]
+    #[serial]
+    async fn test_changes_notify_the_people_involved() {
+        let pool = setup_test_db().await;
+        let admin = create_user("admin@example.com", UserRole::Admin, &pool).await;
+        let manager = create_user("pm@example.com", UserRole::ProjectManager, &pool).await;
+        let first = create_user("first@example.com", UserRole::Developer, &pool).await;
+        let second = create_user("second@example.com", UserRole::Developer, &pool).await;
+        let first_resource = create_resource(&first, &pool).await;
+        let second_resource = create_resource(&second, &pool).await;
+        let audit = acting_as(&manager);
+        let project = create_project(0, &audit, &pool).await;
+
+        let upstream = create_task(
+            TaskCreate {
+                assigned_to: Some(first_resource),
+                ..new_task("Pour foundations", project.id)
+            },
+            &audit,
+            &pool,
+        )
+        .await;
+        create_task(
+            TaskCreate {
+                assigned_to: Some(second_resource),
+                dependencies: vec![upstream.id],
+                ..new_task("Raise walls", project.id)
+            },
+            &audit,
+            &pool,
+        )
+        .await;
+
+        let received = inbox(&first, &pool).await;
+        assert_eq!(events(&received), vec![NotificationEvent::TaskAssigned]);
+        assert_eq!(received[0].title, "Assigned: Pour foundations");
+        assert_eq!(received[0].task_id, Some(upstream.id));
+        // Nobody is told about their own changes
+        assert!(inbox(&manager, &pool).await.is_empty());
+
+        // Finishing earlier is not a slip; finishing later is
+        let reschedule = |days: i64| TaskUpdate {
+            end_date: Patch::Value(upstream.end_date + Duration::days(days)),
+            ..Default::default()
+        };
+        let upstream = TaskService::update(upstream.id, reschedule(-1), None, &audit, &pool)
+            .await
+            .unwrap();
+        assert_eq!(inbox(&second, &pool).await.len(), 1);
+        TaskService::update(upstream.id, reschedule(2), None, &audit, &pool)
+            .await
+            .unwrap();
+        let received = inbox(&second, &pool).await;
+        assert_eq!(received[0].event, NotificationEvent::PredecessorSlipped);
+        assert!(received[0]
+            .body
+            .contains("\"Pour foundations\" now finishes"));
+
+        LifecycleService::transition_phase(
+            PhaseTransition {
+                project_id: project.id,
+                phase: LifecyclePhase::Requirements,
+                description: "Scope agreed".to_string(),
+                attachments: None,
+                documents: vec![],
+            },
+            None,
+            &audit,
+            &pool,
+        )
+        .await
+        .unwrap();
+        for user in [&first, &second] {
+            let received = inbox(user, &pool).await;
+            assert_eq!(received[0].event, NotificationEvent::PhaseTransitioned);
+            assert_eq!(received[0].title, "Warehouse rollout entered Requirements");
+        }
+
+        LifecycleService::schedule_gate_review(
+            project.id,
+            GateReviewCreate {
+                phase: LifecyclePhase::Design,
+                scheduled_at: Utc.with_ymd_and_hms(2025, 7, 10, 14, 0, 0).unwrap(),
+                duration_minutes: 60,
+                location: Some("Room 4".to_string()),
+                notes: None,
+                min_pass_rate: None,
+                no_open_critical_defects: false,
+            },
+            &audit,
+            &pool,
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            events(&inbox(&admin, &pool).await),
+            vec![NotificationEvent::GateApprovalRequested]
+        );
+        assert!(inbox(&manager, &pool).await.is_empty());
+        assert_eq!(inbox(&first, &pool).await.len(), 2);
+
+        cleanup_test_db(&pool).await;
+    }
+
+    #[actix_rt::test]
+    #[serial]
+    async fn test_conditions_notify_once_on_the_chosen_channels() {
+        let pool = setup_test_db().await;
+        let manager = create_user("pm@example.com", UserRole::ProjectManager, &pool).await;
+        let developer = create_user("dev@example.com", UserRole::Developer, &pool).await;
+        let audit = acting_as(&manager);
+        NotificationService::update_preferences(
+            developer.id,
+            vec![NotificationPreference {
+                event: NotificationEvent::TaskOverdue,
+                in_app: false,
+                email: true,
+            }],
+            &pool,
+        )
+        .await
+        .unwrap();
+
+        // Five days of a full-time resource at 60/hour is 2400, 80% of the
+        // budget
+        let resource = create_resource(&developer, &pool).await;
+        let project = create_project(3000, &audit, &pool).await;
+        let task = create_task(
+            TaskCreate {
+                assigned_to: Some(resource),
+                ..new_task("Survey site", project.id)
+            },
+            &audit,
+            &pool,
+        )
+        .await;
+        let received = inbox(&manager, &pool).await;
+        assert_eq!(
+            events(&received),
+            vec![NotificationEvent::BudgetThresholdCrossed]
+        );
+        assert_eq!(
+            received[0].title,
+            "Warehouse rollout reached 80% of its budget"
+        );
+
+        // Still over 80% after another change: no repeat. Cutting the budget
+        // crosses 100%.
+        TaskService::update(
+            task.id,
+            TaskUpdate {
+                name: Patch::Value("Survey the site".to_string()),
+                ..Default::default()
+            },
+            None,
+            &audit,
+            &pool,
+        )
+        .await
+        .unwrap();
+        assert_eq!(inbox(&manager, &pool).await.len(), 1);
+        ProjectService::update(
+            project.id,
+            ProjectUpdate {
+                budget: Patch::Value(BigDecimal::from(2000)),
+                ..Default::default()
+            },
+            None,
+            &audit,
+            &pool,
+        )
+        .await
+        .unwrap();
+        let received = inbox(&manager, &pool).await;
+        assert_eq!(received.len(), 2);
+        assert_eq!(
+            received[0].title,
+            "Warehouse rollout reached 100% of its budget"
+        );
+
+        // The task ended in 2025 and is still pending
+        let now = Utc::now();
+        let raised = NotificationService::raise_overdue(now, &pool)
+            .await
+            .unwrap();
+        assert_eq!(raised, 2);
+        assert_eq!(
+            NotificationService::raise_overdue(now, &pool)
+                .await
+                .unwrap(),
+            0
+        );
+        // The developer only wanted email
+        assert!(!events(&inbox(&developer, &pool).await).contains(&NotificationEvent::TaskOverdue));
+        assert_eq!(
+            events(&inbox(&manager, &pool).await)[0],
+            NotificationEvent::TaskOverdue
+        );
+
+        let email = EmailService::in_memory();
+        assert_eq!(
+            NotificationService::deliver_emails(&email, &pool)
+                .await
+                .unwrap(),
+            1
+        );
+        let sent = email.sent();
+        assert_eq!(sent[0].to, "dev@example.com");
+        assert_eq!(sent[0].subject, "Overdue: Survey the site");
+        assert!(sent[0].body.ends_with(&format!("/projects/{}", project.id)));
+        assert_eq!(
+            NotificationService::deliver_emails(&email, &pool)
+                .await
+                .unwrap(),
+            0
+        );
+
+        cleanup_test_db(&pool).await;
+    }
+
+    #[actix_rt::test]
+    #[serial]
+    async fn test_inbox_endpoints() {
+        let pool = setup_test_db().await;
+        let tokens = test_token_service();
+        let manager = create_user("pm@example.com", UserRole::ProjectManager, &pool).await;
+        let developer = create_user("dev@example.com", UserRole::Developer, &pool).await;
+        let other = create_user("other@example.com", UserRole::Developer, &pool).await;
+        let developer_resource = create_resource(&developer, &pool).await;
+        let other_resource = create_resource(&other, &pool).await;
+        let bearer = format!("Bearer {}", tokens.issue(&developer).unwrap().token);
+        let audit = acting_as(&manager);
+        let project = create_project(0, &audit, &pool).await;
+        for name in ["Survey", "Design", "Build"] {
+            create_task(
+                TaskCreate {
+                    assigned_to: Some(developer_resource),
+                    ..new_task(name, project.id)
+                },
+                &audit,
+                &pool,
+            )
+            .await;
+        }
+        create_task(
+            TaskCreate {
+                assigned_to: Some(other_resource),
+                ..new_task("Theirs", project.id)
+            },
+            &audit,
+            &pool,
+        )
+        .await;
+        let theirs = inbox(&other, &pool).await.remove(0);
+
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(pool.clone()))
+                .app_data(web::Data::new(tokens))
+                .configure(routes::config),
+        )
+        .await;
+        let get = |uri: &str| {
+            test::TestRequest::get()
+                .uri(uri)
+                .insert_header(("Authorization", bearer.clone()))
+                .to_request()
+        };
+        let post = |uri: &str| {
+            test::TestRequest::post()
+                .uri(uri)
+                .insert_header(("Authorization", bearer.clone()))
+                .to_request()
+        };
+
+        let page: Page<Notification> =
+            test::call_and_read_body_json(&app, get("/api/notifications?per_page=2")).await;
+        assert_eq!(page.total, 3);
+        assert_eq!(page.items[0].title, "Assigned: Build");
+        assert_eq!(
+            page.links.next.as_deref(),
+            Some("/api/notifications?per_page=2&page=2")
+        );
+
+        let read: Notification = test::call_and_read_body_json(
+            &app,
+            post(&format!("/api/notifications/{}/read", page.items[0].id)),
+        )
+        .await;
+        assert!(read.read_at.is_some());
+        let count: serde_json::Value =
+            test::call_and_read_body_json(&app, get("/api/notifications/unread-count")).await;
+        assert_eq!(count, json!({ "unread": 2 }));
+        let unread: Page<Notification> =
+            test::call_and_read_body_json(&app, get("/api/notifications?unread=true")).await;
+        assert_eq!(unread.total, 2);
+
+        // Other users' notifications are out of reach
+        let resp = test::call_service(
+            &app,
+            post(&format!("/api/notifications/{}/read", theirs.id)),
+        )
+        .await;
+        assert_eq!(resp.status(), 404);
+
+        let resp = test::call_service(&app, post("/api/notifications/read-all")).await;
+        assert_eq!(resp.status(), 204);
+        let count: serde_json::Value =
+            test::call_and_read_body_json(&app, get("/api/notifications/unread-count")).await;
+        assert_eq!(count, json!({ "unread": 0 }));
+
+        let preferences: Vec<NotificationPreference> =
+            test::call_and_read_body_json(&app, get("/api/notifications/preferences")).await;
+        assert_eq!(preferences.len(), 7);
+        assert!(preferences.iter().all(|p| p.in_app && !p.email));
+
+        let req = test::TestRequest::put()
+            .uri("/api/notifications/preferences")
+            .insert_header(("Authorization", bearer.clone()))
+            .set_json(json!([{ "event": "task_assigned", "in_app": true, "email": true }]))
+            .to_request();
+        let preferences: Vec<NotificationPreference> =
+            test::call_and_read_body_json(&app, req).await;
+        assert_eq!(
+            preferences[0],
+            NotificationPreference {
+                event: NotificationEvent::TaskAssigned,
+                in_app: true,
+                email: true,
+            }
+        );
+        assert!(!preferences[1].email);
+
+        let req = test::TestRequest::put()
+            .uri("/api/notifications/preferences")
+            .insert_header(("Authorization", bearer.clone()))
+            .set_json(json!([{ "event": "lunch_served", "in_app": true, "email": true }]))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 400);
+
+        let req = test::TestRequest::get()
+            .uri("/api/notifications")
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 401);
+
+        cleanup_test_db(&pool).await;
+    }
+}
@@ -0,0 +1,389 @@
+#[cfg(test)]
+mod tests {
+    use crate::errors::ServiceError;
+    use crate::models::audit::AuditContext;
+    use crate::models::auth::{AuthSettingsUpdate, LoginCredentials};
+    use crate::models::user::{UserCreate, UserRole};
+    use crate::services::auth_service::AuthService;
+    use crate::services::oidc_service::{parse_role_mapping, OidcConfig, OidcService};
+    use crate::services::user_service::UserService;
+    use crate::tests::test_helpers::{
+        audit_as, cleanup_test_db, setup_test_db, test_token_service,
+    };
+    use actix_web::{get, post, web, App, HttpResponse, HttpServer};
+    use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+    use base64::Engine;
+    use chrono::Utc;
+    use jsonwebtoken::{encode, Algorithm, EncodingKey, Header};
+    use reqwest::Url;
+    use serde::Deserialize;
+    use serial_test::serial;
+    use sha2::{Digest, Sha256};
+    use std::collections::HashMap;
+    use std::sync::Mutex;
+    use uuid::Uuid;
+
+    const MOCK_KEY_PEM: &[u8] = include_bytes!("fixtures/mock_idp_rsa.pem");
+    const MOCK_KEY_ID: &str = "mock-key-1";
+    const MOCK_KEY_MODULUS: &str = "ujQ-XUWkI5P7J5N3hAtIVleyuHHDV99XICjDn8Lemr4QguErvAGcUuyW_3JykL_MH3lNj2k6Chio4pJUwxjG_Ru3uOhDfKBn6r-KbA-SuORflmNy7KXTnfBHhBH7MKwZJjSTFpwSm7m-ZBiSHz5IhLEdnfbxVOCQXGvALOGV0KlGFxLCHg1-Lne7VcJnhEst87sswUtdZVrDNJXk0pFVAcrl4R-yZTDNZaTc2z5-RWbGo210mBE3sQNz9RjhlDg0DK_U-6JkpmsPOrOXASGnwl6WkyCFt9MtL-pokJlBZblKmfneXT4rRPj7RkWN9ZPJ31gXBD3DOep_eUatucSO6Q";
+    const CLIENT_ID: &str = "waterfall-test";
+
+    /// An authorization the mock IdP has "approved", keyed by code.
+    struct PendingCode {
+        code_challenge: String,
+        nonce: String,
+        claims: serde_json::Value,
+    }
+
+    struct MockIdp {
+        issuer: String,
+        codes: Mutex<HashMap<String, PendingCode>>,
+    }
+
+    #[get("/.well-known/openid-configuration")]
+    async fn discovery(idp: web::Data<MockIdp>) -> HttpResponse {
+        HttpResponse::Ok().json(serde_json::json!({
+            "issuer": idp.issuer,
+            "authorization_endpoint": format!("{}/authorize", idp.issuer),
+            "token_endpoint": format!("{}/token", idp.issuer),
+            "jwks_uri": format!("{}/jwks", idp.issuer),
+        }))
+    }
+
+    #[get("/jwks")]
+    async fn jwks() -> HttpResponse {
+        HttpResponse::Ok().json(serde_json::json!({
+            "keys": [{
+                "kty": "RSA",
+                "kid": MOCK_KEY_ID,
+                "use": "sig",
+                "alg": "RS256",
+                "n": MOCK_KEY_MODULUS,
+                "e": "AQAB",
+            }]
+        }))
+    }
+
+    #[derive(Deserialize)]
+    struct TokenRequest {
+        grant_type: String,
+        code: String,
+        client_id: String,
+        code_verifier: String,
+    }
+
+    #[post("/token")]
+    async fn token(idp: web::Data<MockIdp>, form: web::Form<TokenRequest>) -> HttpResponse {
+        let pending = idp.codes.lock().unwrap().remove(&form.code);
+        let Some(pending) = pending else {
+            return HttpResponse::BadRequest().json(serde_json::json!({"error": "invalid_grant"}));
+        };
+
+        let challenge = URL_SAFE_NO_PAD.encode(Sha256::digest(form.code_verifier.as_bytes()));
+        if form.grant_type != "authorization_code"
+            || form.client_id != CLIENT_ID
+            || challenge != pending.code_challenge
+        {
+            return HttpResponse::BadRequest().json(serde_json::json!({"error": "invalid_grant"}));
+        }
+
+        let mut claims = pending.claims;
+        claims["iss"] = idp.issuer.clone().into();
+        claims["aud"] = CLIENT_ID.into();
+        claims["exp"] = (Utc::now().timestamp() + 300).into();
+        claims["nonce"] = pending.nonce.into();
+
+        let mut header = Header::new(Algorithm::RS256);
+        header.kid = Some(MOCK_KEY_ID.to_string());
+        let id_token = encode(
+            &header,
+            &claims,
+            &EncodingKey::from_rsa_pem(MOCK_KEY_PEM).unwrap(),
+        )
+        .unwrap();
+
+        HttpResponse::Ok().json(serde_json::json!({
+            "access_token": "opaque",
+            "token_type": "Bearer",
+            "id_token": id_token,
+        }))
+    }
+
+    async fn start_mock_idp() -> web::Data<MockIdp> {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let issuer = format!("http://{}", listener.local_addr().unwrap());
+        let idp = web::Data::new(MockIdp {
+            issuer,
+            codes: Mutex::new(HashMap::new()),
+        });
+
+        let app_idp = idp.clone();
+        let server = HttpServer::new(move || {
+            App::new()
+                .app_data(app_idp.clone())
+                .service(discovery)
+                .service(jwks)
+                .service(token)
+        })
+        .workers(1)
+        .listen(listener)
+        .unwrap()
+        .run();
+        actix_rt::spawn(server);
+
+        idp
+    }
+
+    fn oidc_service(issuer: &str) -> OidcService {
+        OidcService::new(OidcConfig {
+            issuer_url: issuer.to_string(),
+            client_id: CLIENT_ID.to_string(),
+            client_secret: None,
+            redirect_uri: "http://localhost:3000/auth/callback".to_string(),
+            scopes: "openid email profile".to_string(),
+            role_claim: "groups".to_string(),
+            role_mapping: parse_role_mapping("pm-admins=admin,planners=project_manager").unwrap(),
+            default_role: UserRole::Developer,
+        })
+    }
+
+    /// Simulates the browser leg: follows the authorization URL and has the
+    /// mock IdP approve it with the given claims. Returns (code, state).
+    fn approve_login(
+        idp: &MockIdp,
+        authorization_url: &str,
+        claims: serde_json::Value,
+    ) -> (String, String) {
+        let url = Url::parse(authorization_url).unwrap();
+        let params: HashMap<String, String> = url.query_pairs().into_owned().collect();
+        assert_eq!(params["code_challenge_method"], "S256");
+        assert_eq!(params["client_id"], CLIENT_ID);
+
+        let code = Uuid::new_v4().to_string();
+        idp.codes.lock().unwrap().insert(
+            code.clone(),
+            PendingCode {
+                code_challenge: params["code_challenge"].clone(),
+                nonce: params["nonce"].clone(),
+                claims,
+            },
+        );
+        (code, params["state"].clone())
+    }
+
+    #[actix_rt::test]
+    #[serial]
+    async fn test_oidc_login_provisions_user_with_mapped_role() {
+        let pool = setup_test_db().await;
+        let idp = start_mock_idp().await;
+        let oidc = oidc_service(&idp.issuer);
+        let tokens = test_token_service();
+
+        let url = oidc.authorization_url(&pool).await.unwrap();
+        let (code, state) = approve_login(
+            &idp,
+            &url,
+            serde_json::json!({
+                "sub": "idp-user-42",
+                "email": "sso.user@example.com",
+                "email_verified": true,
+                "name": "SSO User",
+                "groups": ["staff", "planners"],
+            }),
+        );
+
+        let response = oidc
+            .complete_login(&code, &state, &tokens, &AuditContext::default(), &pool)
+            .await
+            .unwrap();
+        let user = UserService::get_by_id(response.user_id, &pool)
+            .await
+            .unwrap();
+        assert_eq!(user.email, "sso.user@example.com");
+        assert_eq!(user.full_name, "SSO User");
+        assert_eq!(user.role, UserRole::ProjectManager);
+        assert!(user.password_hash.is_none());
+
+        // The state is single use
+        let replay = oidc
+            .complete_login(&code, &state, &tokens, &AuditContext::default(), &pool)
+            .await;
+        assert!(matches!(replay, Err(ServiceError::Unauthorized(_))));
+
+        // Signing in again updates the role from the IdP instead of duplicating the user
+        let url = oidc.authorization_url(&pool).await.unwrap();
+        let (code, state) = approve_login(
+            &idp,
+            &url,
+            serde_json::json!({
+                "sub": "idp-user-42",
+                "email": "sso.user@example.com",
+                "email_verified": true,
+                "name": "SSO User",
+                "groups": ["pm-admins"],
+            }),
+        );
+        let second = oidc
+            .complete_login(&code, &state, &tokens, &AuditContext::default(), &pool)
+            .await
+            .unwrap();
+        assert_eq!(second.user_id, response.user_id);
+        let user = UserService::get_by_id(second.user_id, &pool).await.unwrap();
+        assert_eq!(user.role, UserRole::Admin);
+
+        // Unchanged claims leave the user, and its version, alone
+        let url = oidc.authorization_url(&pool).await.unwrap();
+        let (code, state) = approve_login(
+            &idp,
+            &url,
+            serde_json::json!({
+                "sub": "idp-user-42",
+                "email": "sso.user@example.com",
+                "email_verified": true,
+                "name": "SSO User",
+                "groups": ["pm-admins"],
+            }),
+        );
+        oidc.complete_login(&code, &state, &tokens, &AuditContext::default(), &pool)
+            .await
+            .unwrap();
+        let unchanged = UserService::get_by_id(second.user_id, &pool).await.unwrap();
+        assert_eq!(unchanged, user);
+
+        // SSO-only accounts cannot log in with a password
+        let password_login = AuthService::login(
+            LoginCredentials {
+                email: "sso.user@example.com".to_string(),
+                password: "password123".to_string(),
+            },
+            &tokens,
+            &pool,
+        )
+        .await;
+        assert!(matches!(
+            password_login,
+            Err(ServiceError::InvalidCredentials)
+        ));
+
+        cleanup_test_db(&pool).await;
+    }
+
+    #[actix_rt::test]
+    #[serial]
+    async fn test_oidc_rejects_unverified_email_collision() {
+        let pool = setup_test_db().await;
+        let idp = start_mock_idp().await;
+        let oidc = oidc_service(&idp.issuer);
+        let tokens = test_token_service();
+
+        UserService::create(
+            UserCreate {
+                email: "taken@example.com".to_string(),
+                password: "password123".to_string(),
+                full_name: "Existing User".to_string(),
+                role: UserRole::Developer,
+            },
+            &AuditContext::default(),
+            &pool,
+        )
+        .await
+        .unwrap();
+
+        let url = oidc.authorization_url(&pool).await.unwrap();
+        let (code, state) = approve_login(
+            &idp,
+            &url,
+            serde_json::json!({
+                "sub": "idp-user-7",
+                "email": "taken@example.com",
+                "email_verified": false,
+            }),
+        );
+
+        let result = oidc
+            .complete_login(&code, &state, &tokens, &AuditContext::default(), &pool)
+            .await;
+        assert!(matches!(result, Err(ServiceError::Unauthorized(_))));
+
+        cleanup_test_db(&pool).await;
+    }
+
+    #[actix_rt::test]
+    #[serial]
+    async fn test_password_login_can_be_disabled() {
+        let pool = setup_test_db().await;
+
+        let admin = UserService::create(
+            UserCreate {
+                email: "admin@example.com".to_string(),
+                password: "password123".to_string(),
+                full_name: "Admin".to_string(),
+                role: UserRole::Admin,
+            },
+            &AuditContext::default(),
+            &pool,
+        )
+        .await
+        .unwrap();
+
+        // Without single sign-on, nobody could sign in any more
+        let result = AuthService::update_settings(
+            AuthSettingsUpdate {
+                password_login_enabled: false,
+            },
+            false,
+            &audit_as(admin.id),
+            &pool,
+        )
+        .await;
+        assert!(matches!(result, Err(ServiceError::ValidationError(_))));
+
+        let settings = AuthService::update_settings(
+            AuthSettingsUpdate {
+                password_login_enabled: false,
+            },
+            true,
+            &audit_as(admin.id),
+            &pool,
+        )
+        .await
+        .unwrap();
+        assert!(!settings.password_login_enabled);
+        assert_eq!(settings.updated_by, Some(admin.id));
+
+        let credentials = LoginCredentials {
+            email: "admin@example.com".to_string(),
+            password: "password123".to_string(),
+        };
+        let result = AuthService::login(credentials, &test_token_service(), &pool).await;
+        assert!(matches!(result, Err(ServiceError::Forbidden)));
+
+        cleanup_test_db(&pool).await;
+    }
+
+    #[test]
+    fn test_role_mapping() {
+        let oidc_config = OidcConfig {
+            issuer_url: "http://idp".to_string(),
+            client_id: CLIENT_ID.to_string(),
+            client_secret: None,
+            redirect_uri: "http://localhost/callback".to_string(),
+            scopes: "openid".to_string(),
+            role_claim: "roles".to_string(),
+            role_mapping: parse_role_mapping("admins=admin, qa=qa_engineer").unwrap(),
+            default_role: UserRole::Developer,
+        };
+
+        let claims = |value: serde_json::Value| HashMap::from([("roles".to_string(), value)]);
+        assert_eq!(
+            oidc_config.map_role(&claims("qa".into())),
+            UserRole::QaEngineer
+        );
+        assert_eq!(
+            oidc_config.map_role(&claims(serde_json::json!(["qa", "admins"]))),
+            UserRole::Admin
+        );
+        assert_eq!(oidc_config.map_role(&HashMap::new()), UserRole::Developer);
+        assert!(parse_role_mapping("admins=superuser").is_err());
+    }
+}
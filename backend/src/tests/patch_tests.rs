@@ -0,0 +1,463 @@
+#[cfg(test)]
+mod tests {
+    use crate::errors::ServiceError;
+    use crate::models::audit::AuditContext;
+    use crate::models::patch::Patch;
+    use crate::models::project::{Project, ProjectCreate, ProjectStatus, ProjectUpdate};
+    use crate::models::resource::{Resource, ResourceCreate, ResourceUpdate};
+    use crate::models::task::{Task, TaskCreate, TaskStatus, TaskUpdate};
+    use crate::models::user::{User, UserCreate, UserRole, UserUpdate};
+    use crate::routes;
+    use crate::services::project_service::ProjectService;
+    use crate::services::resource_service::ResourceService;
+    use crate::services::task_service::TaskService;
+    use crate::services::user_service::UserService;
+    use crate::tests::test_helpers::{cleanup_test_db, setup_test_db, test_token_service};
+    use actix_web::{test, web, App};
+    use bigdecimal::BigDecimal;
+    use chrono::{Duration, Utc};
+    use serde_json::{json, Value};
+    use serial_test::serial;
+    use sqlx::PgPool;
+    use uuid::Uuid;
+    use validator::Validate;
+
+    fn is_validation_error<T: std::fmt::Debug>(result: Result<T, ServiceError>) -> bool {
+        matches!(result, Err(ServiceError::ValidationError(_)))
+    }
+
+    async fn patch_project(id: Uuid, body: Value, pool: &PgPool) -> Result<Project, ServiceError> {
+        let update: ProjectUpdate = serde_json::from_value(body).unwrap();
+        update.validate()?;
+        ProjectService::update(id, update, None, &AuditContext::default(), pool).await
+    }
+
+    async fn patch_task(id: Uuid, body: Value, pool: &PgPool) -> Result<Task, ServiceError> {
+        let update: TaskUpdate = serde_json::from_value(body).unwrap();
+        update.validate()?;
+        TaskService::update(id, update, None, &AuditContext::default(), pool).await
+    }
+
+    async fn patch_user(id: Uuid, body: Value, pool: &PgPool) -> Result<User, ServiceError> {
+        let update: UserUpdate = serde_json::from_value(body).unwrap();
+        update.validate()?;
+        UserService::update(id, update, None, &AuditContext::default(), pool).await
+    }
+
+    async fn patch_resource(
+        id: Uuid,
+        body: Value,
+        pool: &PgPool,
+    ) -> Result<Resource, ServiceError> {
+        let update: ResourceUpdate = serde_json::from_value(body).unwrap();
+        update.validate()?;
+        ResourceService::update(id, update, &AuditContext::default(), pool).await
+    }
+
+    async fn create_project(pool: &PgPool) -> Project {
+        ProjectService::create(
+            ProjectCreate {
+                name: "Patched Project".to_string(),
+                description: Some("Original description".to_string()),
+                start_date: Utc::now(),
+                end_date: Utc::now() + Duration::days(30),
+                budget: BigDecimal::from(1000),
+                client_id: Some(Uuid::new_v4()),
+            },
+            &AuditContext::default(),
+            pool,
+        )
+        .await
+        .unwrap()
+    }
+
+    #[actix_rt::test]
+    async fn test_merge_patch_distinguishes_absent_null_and_value() {
+        let update: TaskUpdate =
+            serde_json::from_value(json!({ "description": null, "name": "Renamed" })).unwrap();
+        assert_eq!(update.description, Patch::Null);
+        assert_eq!(update.name, Patch::Value("Renamed".to_string()));
+        assert_eq!(update.assigned_to, Patch::Absent);
+        assert_eq!(update.dependencies, Patch::Absent);
+
+        assert_eq!(Patch::Absent.apply(Some(1)), Some(1));
+        assert_eq!(Patch::<i32>::Null.apply(Some(1)), None);
+        assert_eq!(Patch::Value(2).apply(Some(1)), Some(2));
+        assert!(is_validation_error(
+            Patch::<i32>::Null.apply_required("name", 1)
+        ));
+
+        // Validation only looks at values actually being written
+        let update: UserUpdate =
+            serde_json::from_value(json!({ "email": "not-an-email" })).unwrap();
+        assert!(update.validate().is_err());
+        let update: UserUpdate = serde_json::from_value(json!({ "email": null })).unwrap();
+        assert!(update.validate().is_ok());
+    }
+
+    #[actix_rt::test]
+    #[serial]
+    async fn test_project_merge_patch_fields() {
+        let pool = setup_test_db().await;
+        let original = create_project(&pool).await;
+        let id = original.id;
+
+        // An empty patch changes nothing
+        let project = patch_project(id, json!({}), &pool).await.unwrap();
+        assert_eq!(project.name, original.name);
+        assert_eq!(project.description, original.description);
+        assert_eq!(project.client_id, original.client_id);
+
+        let project = patch_project(id, json!({ "name": "Renamed" }), &pool)
+            .await
+            .unwrap();
+        assert_eq!(project.name, "Renamed");
+        assert_eq!(project.description, original.description);
+        assert!(is_validation_error(
+            patch_project(id, json!({ "name": null }), &pool).await
+        ));
+        assert!(is_validation_error(
+            patch_project(id, json!({ "name": "" }), &pool).await
+        ));
+
+        let project = patch_project(id, json!({ "description": null }), &pool)
+            .await
+            .unwrap();
+        assert_eq!(project.description, None);
+        assert_eq!(project.name, "Renamed");
+        let project = patch_project(id, json!({ "description": "Restored" }), &pool)
+            .await
+            .unwrap();
+        assert_eq!(project.description.as_deref(), Some("Restored"));
+
+        let project = patch_project(id, json!({ "client_id": null }), &pool)
+            .await
+            .unwrap();
+        assert_eq!(project.client_id, None);
+        let client_id = Uuid::new_v4();
+        let project = patch_project(id, json!({ "client_id": client_id }), &pool)
+            .await
+            .unwrap();
+        assert_eq!(project.client_id, Some(client_id));
+
+        let start = original.start_date + Duration::days(1);
+        let end = original.end_date + Duration::days(1);
+        let project = patch_project(id, json!({ "start_date": start, "end_date": end }), &pool)
+            .await
+            .unwrap();
+        assert_eq!(project.start_date.timestamp(), start.timestamp());
+        assert_eq!(project.end_date.timestamp(), end.timestamp());
+        assert!(is_validation_error(
+            patch_project(id, json!({ "start_date": null }), &pool).await
+        ));
+        assert!(is_validation_error(
+            patch_project(id, json!({ "end_date": null }), &pool).await
+        ));
+
+        let project = patch_project(id, json!({ "status": "Testing" }), &pool)
+            .await
+            .unwrap();
+        assert_eq!(project.status, ProjectStatus::Testing);
+        assert!(is_validation_error(
+            patch_project(id, json!({ "status": null }), &pool).await
+        ));
+
+        let project = patch_project(id, json!({ "budget": "2500.00" }), &pool)
+            .await
+            .unwrap();
+        assert_eq!(project.budget, BigDecimal::from(2500));
+        assert!(is_validation_error(
+            patch_project(id, json!({ "budget": null }), &pool).await
+        ));
+        assert!(is_validation_error(
+            patch_project(id, json!({ "budget": "-1" }), &pool).await
+        ));
+
+        cleanup_test_db(&pool).await;
+    }
+
+    #[actix_rt::test]
+    #[serial]
+    async fn test_task_merge_patch_fields() {
+        let pool = setup_test_db().await;
+        let project = create_project(&pool).await;
+        let assignee = Uuid::new_v4();
+        let dependency = Uuid::new_v4();
+        let original = TaskService::create(
+            TaskCreate {
+                name: "Patched Task".to_string(),
+                description: Some("Original description".to_string()),
+                project_id: project.id,
+                assigned_to: Some(assignee),
+                start_date: Utc::now(),
+                end_date: Utc::now() + Duration::days(7),
+                dependencies: vec![dependency],
+                parent_id: None,
+                wbs: None,
+                milestone: false,
+            },
+            &AuditContext::default(),
+            &pool,
+        )
+        .await
+        .unwrap();
+        let id = original.id;
+
+        // Leaving fields out keeps them, rather than emptying the arrays
+        let task = patch_task(id, json!({ "name": "Renamed" }), &pool)
+            .await
+            .unwrap();
+        assert_eq!(task.name, "Renamed");
+        assert_eq!(task.description, original.description);
+        assert_eq!(task.assigned_to, vec![assignee]);
+        assert_eq!(task.dependencies, vec![dependency]);
+        assert!(is_validation_error(
+            patch_task(id, json!({ "name": null }), &pool).await
+        ));
+
+        let task = patch_task(id, json!({ "description": null }), &pool)
+            .await
+            .unwrap();
+        assert_eq!(task.description, None);
+        let task = patch_task(id, json!({ "description": "Restored" }), &pool)
+            .await
+            .unwrap();
+        assert_eq!(task.description.as_deref(), Some("Restored"));
+
+        let task = patch_task(id, json!({ "assigned_to": null }), &pool)
+            .await
+            .unwrap();
+        assert!(task.assigned_to.is_empty());
+        let task = patch_task(id, json!({ "assigned_to": assignee }), &pool)
+            .await
+            .unwrap();
+        assert_eq!(task.assigned_to, vec![assignee]);
+
+        let task = patch_task(id, json!({ "dependencies": null }), &pool)
+            .await
+            .unwrap();
+        assert!(task.dependencies.is_empty());
+        let task = patch_task(id, json!({ "dependencies": [dependency] }), &pool)
+            .await
+            .unwrap();
+        assert_eq!(task.dependencies, vec![dependency]);
+
+        let task = patch_task(id, json!({ "status": "InProgress" }), &pool)
+            .await
+            .unwrap();
+        assert_eq!(task.status, TaskStatus::InProgress);
+        assert!(is_validation_error(
+            patch_task(id, json!({ "status": null }), &pool).await
+        ));
+
+        let task = patch_task(id, json!({ "progress": "40" }), &pool)
+            .await
+            .unwrap();
+        assert_eq!(task.progress, BigDecimal::from(40));
+        assert!(is_validation_error(
+            patch_task(id, json!({ "progress": null }), &pool).await
+        ));
+
+        let start = original.start_date + Duration::days(1);
+        let end = original.end_date + Duration::days(1);
+        let task = patch_task(id, json!({ "start_date": start, "end_date": end }), &pool)
+            .await
+            .unwrap();
+        assert_eq!(task.start_date.timestamp(), start.timestamp());
+        assert_eq!(task.end_date.timestamp(), end.timestamp());
+        assert!(is_validation_error(
+            patch_task(id, json!({ "start_date": null }), &pool).await
+        ));
+        assert!(is_validation_error(
+            patch_task(id, json!({ "end_date": null }), &pool).await
+        ));
+
+        cleanup_test_db(&pool).await;
+    }
+
+    #[actix_rt::test]
+    #[serial]
+    async fn test_user_merge_patch_fields() {
+        let pool = setup_test_db().await;
+        let original = UserService::create(
+            UserCreate {
+                email: "patched@example.com".to_string(),
+                password: "password123".to_string(),
+                full_name: "Patched User".to_string(),
+                role: UserRole::Developer,
+            },
+            &AuditContext::default(),
+            &pool,
+        )
+        .await
+        .unwrap();
+        let id = original.id;
+
+        let user = patch_user(id, json!({ "email": "renamed@example.com" }), &pool)
+            .await
+            .unwrap();
+        assert_eq!(user.email, "renamed@example.com");
+        assert_eq!(user.full_name, original.full_name);
+        assert_eq!(user.password_hash, original.password_hash);
+        assert!(is_validation_error(
+            patch_user(id, json!({ "email": null }), &pool).await
+        ));
+        assert!(is_validation_error(
+            patch_user(id, json!({ "email": "nope" }), &pool).await
+        ));
+
+        let user = patch_user(id, json!({ "full_name": "Renamed User" }), &pool)
+            .await
+            .unwrap();
+        assert_eq!(user.full_name, "Renamed User");
+        assert!(is_validation_error(
+            patch_user(id, json!({ "full_name": null }), &pool).await
+        ));
+
+        let user = patch_user(id, json!({ "role": "QaEngineer" }), &pool)
+            .await
+            .unwrap();
+        assert_eq!(user.role, UserRole::QaEngineer);
+        assert!(is_validation_error(
+            patch_user(id, json!({ "role": null }), &pool).await
+        ));
+
+        let user = patch_user(id, json!({ "password": "new-password" }), &pool)
+            .await
+            .unwrap();
+        assert_ne!(user.password_hash, original.password_hash);
+        assert!(is_validation_error(
+            patch_user(id, json!({ "password": null }), &pool).await
+        ));
+        assert!(is_validation_error(
+            patch_user(id, json!({ "password": "short" }), &pool).await
+        ));
+
+        cleanup_test_db(&pool).await;
+    }
+
+    #[actix_rt::test]
+    #[serial]
+    async fn test_resource_merge_patch_fields() {
+        let pool = setup_test_db().await;
+        let original = ResourceService::create(
+            ResourceCreate {
+                name: "Patched Resource".to_string(),
+                email: "resource@example.com".to_string(),
+                role: "Engineer".to_string(),
+                skills: vec!["rust".to_string()],
+                availability: BigDecimal::from(80),
+                hourly_rate: BigDecimal::from(95),
+            },
+            &AuditContext::default(),
+            &pool,
+        )
+        .await
+        .unwrap();
+        let id = original.id;
+
+        let resource = patch_resource(id, json!({ "name": "Renamed" }), &pool)
+            .await
+            .unwrap();
+        assert_eq!(resource.name, "Renamed");
+        assert_eq!(resource.skills, original.skills);
+        assert!(is_validation_error(
+            patch_resource(id, json!({ "name": null }), &pool).await
+        ));
+
+        let resource = patch_resource(id, json!({ "email": "moved@example.com" }), &pool)
+            .await
+            .unwrap();
+        assert_eq!(resource.email, "moved@example.com");
+        assert!(is_validation_error(
+            patch_resource(id, json!({ "email": null }), &pool).await
+        ));
+
+        let resource = patch_resource(id, json!({ "role": "Architect" }), &pool)
+            .await
+            .unwrap();
+        assert_eq!(resource.role, "Architect");
+        assert!(is_validation_error(
+            patch_resource(id, json!({ "role": null }), &pool).await
+        ));
+
+        let resource = patch_resource(id, json!({ "skills": null }), &pool)
+            .await
+            .unwrap();
+        assert!(resource.skills.is_empty());
+        let resource = patch_resource(id, json!({ "skills": ["sql", "go"] }), &pool)
+            .await
+            .unwrap();
+        assert_eq!(resource.skills, vec!["sql".to_string(), "go".to_string()]);
+
+        let resource = patch_resource(id, json!({ "availability": "50" }), &pool)
+            .await
+            .unwrap();
+        assert_eq!(resource.availability, BigDecimal::from(50));
+        assert!(is_validation_error(
+            patch_resource(id, json!({ "availability": null }), &pool).await
+        ));
+        assert!(is_validation_error(
+            patch_resource(id, json!({ "availability": "150" }), &pool).await
+        ));
+
+        let resource = patch_resource(id, json!({ "hourly_rate": "120" }), &pool)
+            .await
+            .unwrap();
+        assert_eq!(resource.hourly_rate, BigDecimal::from(120));
+        assert!(is_validation_error(
+            patch_resource(id, json!({ "hourly_rate": null }), &pool).await
+        ));
+
+        cleanup_test_db(&pool).await;
+    }
+
+    #[actix_rt::test]
+    #[serial]
+    async fn test_patch_route_accepts_merge_patch_documents() {
+        let pool = setup_test_db().await;
+        let tokens = test_token_service();
+        let pm = UserService::create(
+            UserCreate {
+                email: "pm@example.com".to_string(),
+                password: "password123".to_string(),
+                full_name: "Project Manager".to_string(),
+                role: UserRole::ProjectManager,
+            },
+            &AuditContext::default(),
+            &pool,
+        )
+        .await
+        .unwrap();
+        let token = tokens.issue(&pm).unwrap().token;
+        let project = create_project(&pool).await;
+
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(pool.clone()))
+                .app_data(web::Data::new(tokens))
+                .configure(routes::config),
+        )
+        .await;
+
+        let req = test::TestRequest::patch()
+            .uri(&format!("/api/projects/{}", project.id))
+            .insert_header(("Authorization", format!("Bearer {}", token)))
+            .insert_header(("Content-Type", "application/merge-patch+json"))
+            .set_payload(json!({ "description": null }).to_string())
+            .to_request();
+        let body: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(body["description"], Value::Null);
+        assert_eq!(body["name"], "Patched Project");
+
+        let req = test::TestRequest::patch()
+            .uri(&format!("/api/projects/{}", project.id))
+            .insert_header(("Authorization", format!("Bearer {}", token)))
+            .set_json(json!({ "name": null }))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 422);
+
+        cleanup_test_db(&pool).await;
+    }
+}
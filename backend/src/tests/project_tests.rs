@@ -1,11 +1,17 @@
 #[cfg(test)]
 mod tests {
-    use crate::models::project::{ProjectCreate, ProjectStatus, ProjectUpdate};
+    use crate::errors::ServiceError;
+    use crate::models::audit::AuditContext;
+    use crate::models::pagination::PageParams;
+    use crate::models::patch::Patch;
+    use crate::models::project::{ProjectCreate, ProjectFilter, ProjectStatus, ProjectUpdate};
+    use crate::models::user::UserRole;
     use crate::services::project_service::ProjectService;
     use crate::tests::test_helpers::{cleanup_test_db, setup_test_db};
     use bigdecimal::{BigDecimal, FromPrimitive};
     use chrono::{Duration, Utc};
     use serial_test::serial;
+    use uuid::Uuid;
 
     #[actix_rt::test]
     #[serial]
@@ -22,7 +28,9 @@ mod tests {
             client_id: None,
         };
 
-        let created_project = ProjectService::create(new_project, &pool).await.unwrap();
+        let created_project = ProjectService::create(new_project, &AuditContext::default(), &pool)
+            .await
+            .unwrap();
         assert_eq!(created_project.name, "Test Project");
 
         // Test Read
@@ -33,23 +41,37 @@ mod tests {
 
         // Test Update
         let update = ProjectUpdate {
-            name: Some("Updated Project".to_string()),
-            description: None,
-            start_date: None,
-            end_date: None,
-            status: Some(ProjectStatus::Development),
-            budget: None,
-            client_id: None,
+            name: Patch::Value("Updated Project".to_string()),
+            description: Patch::Absent,
+            start_date: Patch::Absent,
+            end_date: Patch::Absent,
+            status: Patch::Value(ProjectStatus::Development),
+            budget: Patch::Absent,
+            client_id: Patch::Absent,
         };
 
-        let updated_project = ProjectService::update(created_project.id, update, &pool)
-            .await
-            .unwrap();
+        let updated_project = ProjectService::update(
+            created_project.id,
+            update,
+            None,
+            &AuditContext::default(),
+            &pool,
+        )
+        .await
+        .unwrap();
         assert_eq!(updated_project.name, "Updated Project");
         assert_eq!(updated_project.status, ProjectStatus::Development);
 
         // Test Delete
-        let delete_result = ProjectService::delete(created_project.id, &pool).await;
+        let delete_result = ProjectService::delete(
+            created_project.id,
+            None,
+            Uuid::nil(),
+            &UserRole::Admin,
+            &AuditContext::default(),
+            &pool,
+        )
+        .await;
         assert!(delete_result.is_ok());
 
         // Verify deletion
@@ -73,7 +95,7 @@ mod tests {
             client_id: None,
         };
 
-        let result = ProjectService::create(invalid_project, &pool).await;
+        let result = ProjectService::create(invalid_project, &AuditContext::default(), &pool).await;
         assert!(result.is_err());
         if let Err(e) = result {
             assert!(e.to_string().contains("end date must be after start date"));
@@ -89,7 +111,7 @@ mod tests {
             client_id: None,
         };
 
-        let result = ProjectService::create(invalid_project, &pool).await;
+        let result = ProjectService::create(invalid_project, &AuditContext::default(), &pool).await;
         assert!(result.is_err());
         if let Err(e) = result {
             assert!(e.to_string().contains("budget must be non-negative"));
@@ -105,10 +127,96 @@ mod tests {
             client_id: None,
         };
 
-        let result = ProjectService::create(invalid_project, &pool).await;
+        let result = ProjectService::create(invalid_project, &AuditContext::default(), &pool).await;
         assert!(result.is_err());
         if let Err(e) = result {
             assert!(e.to_string().contains("length"));
         }
     }
+
+    #[actix_rt::test]
+    #[serial]
+    async fn test_project_list_pagination_and_filters() {
+        let pool = setup_test_db().await;
+
+        for i in 0..5 {
+            let project = ProjectCreate {
+                name: format!("Project {}", i),
+                description: None,
+                start_date: Utc::now() + Duration::days(i * 10),
+                end_date: Utc::now() + Duration::days(i * 10 + 30),
+                budget: BigDecimal::from_f64(1000.0).unwrap(),
+                client_id: None,
+            };
+            let created = ProjectService::create(project, &AuditContext::default(), &pool)
+                .await
+                .unwrap();
+            if i % 2 == 0 {
+                let update = ProjectUpdate {
+                    name: Patch::Absent,
+                    description: Patch::Absent,
+                    start_date: Patch::Absent,
+                    end_date: Patch::Absent,
+                    status: Patch::Value(ProjectStatus::Development),
+                    budget: Patch::Absent,
+                    client_id: Patch::Absent,
+                };
+                ProjectService::update(created.id, update, None, &AuditContext::default(), &pool)
+                    .await
+                    .unwrap();
+            }
+        }
+
+        // Second page of two, sorted by name
+        let params = PageParams {
+            page: Some(2),
+            per_page: Some(2),
+            sort: Some("name".to_string()),
+        };
+        let page = ProjectService::get_all(&params, &ProjectFilter::default(), &pool)
+            .await
+            .unwrap()
+            .with_links("/api/projects", "sort=name&page=2&per_page=2");
+        assert_eq!(page.total, 5);
+        let names: Vec<_> = page.items.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, vec!["Project 2", "Project 3"]);
+        assert_eq!(
+            page.links.next.as_deref(),
+            Some("/api/projects?sort=name&per_page=2&page=3")
+        );
+        assert_eq!(
+            page.links.prev.as_deref(),
+            Some("/api/projects?sort=name&per_page=2&page=1")
+        );
+
+        // Status and date range filters
+        let filter = ProjectFilter {
+            status: Some(ProjectStatus::Development),
+            starts_after: Some(Utc::now() + Duration::days(5)),
+            ..Default::default()
+        };
+        let page = ProjectService::get_all(&PageParams::default(), &filter, &pool)
+            .await
+            .unwrap();
+        assert_eq!(page.total, 2);
+        assert!(page.links.next.is_none());
+
+        // Only whitelisted fields can be sorted on
+        let params = PageParams {
+            sort: Some("budget; DROP TABLE projects".to_string()),
+            ..Default::default()
+        };
+        let result = ProjectService::get_all(&params, &ProjectFilter::default(), &pool).await;
+        assert!(result.is_err());
+
+        // Pages too far out to address are rejected
+        let params = PageParams {
+            page: Some(i64::MAX),
+            ..Default::default()
+        };
+        let result = ProjectService::get_all(&params, &ProjectFilter::default(), &pool).await;
+        assert!(matches!(result, Err(ServiceError::ValidationError(_))));
+
+        cleanup_test_db(&pool).await;
+    }
 }
@@ -0,0 +1,231 @@
+#[cfg(test)]
+mod tests {
+    use crate::errors::ServiceError;
+    use crate::models::lifecycle::{LifecyclePhase, PhaseTransition};
+    use crate::models::patch::Patch;
+    use crate::models::realtime::{ChangeEvent, ChangeKind};
+    use crate::models::task::{TaskCreate, TaskUpdate};
+    use crate::models::user::UserRole;
+    use crate::routes;
+    use crate::services::lifecycle_service::LifecycleService;
+    use crate::services::realtime_service::{
+        Broadcaster, RealtimeService, StreamSession, Visibility,
+    };
+    use crate::services::task_service::TaskService;
+    use crate::tests::test_helpers::{
+        audit_as, cleanup_test_db, create_project, create_resource, create_task, create_user,
+        new_task, setup_test_db, test_token_service,
+    };
+    use actix_web::body::MessageBody;
+    use actix_web::{test, web, App};
+    use chrono::{Duration, Utc};
+    use futures::StreamExt;
+    use serial_test::serial;
+    use std::fmt::Debug;
+    use std::pin::Pin;
+    use tokio::sync::broadcast;
+    use tokio::time::timeout;
+
+    const WAIT: std::time::Duration = std::time::Duration::from_secs(5);
+
+    fn rename(name: &str) -> TaskUpdate {
+        TaskUpdate {
+            name: Patch::Value(name.to_string()),
+            ..Default::default()
+        }
+    }
+
+    async fn next_event(events: &mut broadcast::Receiver<ChangeEvent>) -> ChangeEvent {
+        timeout(WAIT, events.recv())
+            .await
+            .expect("no change event arrived")
+            .unwrap()
+    }
+
+    async fn next_chunk<B>(body: &mut B) -> String
+    where
+        B: MessageBody + Unpin,
+        B::Error: Debug,
+    {
+        let chunk = timeout(
+            WAIT,
+            futures::future::poll_fn(|cx| Pin::new(&mut *body).poll_next(cx)),
+        )
+        .await
+        .expect("no chunk arrived")
+        .expect("stream ended")
+        .unwrap();
+        String::from_utf8(chunk.to_vec()).unwrap()
+    }
+
+    #[actix_rt::test]
+    #[serial]
+    async fn test_committed_changes_are_broadcast() {
+        let pool = setup_test_db().await;
+        let broadcaster = Broadcaster::new();
+        broadcaster.listen(&pool).await.unwrap();
+        let mut events = broadcaster.subscribe();
+        let manager = create_user("pm@example.com", UserRole::ProjectManager, &pool).await;
+        let audit = audit_as(manager.id);
+        let project = create_project("Fibre rollout", &audit, &pool).await;
+
+        let task = create_task(new_task("Lay cable", project.id), &audit, &pool).await;
+        let event = next_event(&mut events).await;
+        assert_eq!(event.kind, ChangeKind::TaskCreated);
+        assert_eq!(event.project_id, project.id);
+        assert_eq!(event.entity_id, task.id);
+        assert_eq!(event.version, Some(1));
+        assert_eq!(event.actor_id, Some(manager.id));
+
+        // A change that rolls back announces nothing
+        let stale = TaskService::update(task.id, rename("Stale"), Some(7), &audit, &pool).await;
+        assert!(matches!(
+            stale,
+            Err(ServiceError::PreconditionFailed { .. })
+        ));
+        TaskService::update(task.id, rename("Lay fibre"), Some(1), &audit, &pool)
+            .await
+            .unwrap();
+        let event = next_event(&mut events).await;
+        assert_eq!(event.kind, ChangeKind::TaskUpdated);
+        assert_eq!(event.version, Some(2));
+
+        let transition = LifecycleService::transition_phase(
+            PhaseTransition {
+                project_id: project.id,
+                phase: LifecyclePhase::Requirements,
+                description: "Survey approved".to_string(),
+                attachments: None,
+                documents: vec![],
+            },
+            None,
+            &audit,
+            &pool,
+        )
+        .await
+        .unwrap();
+        let event = next_event(&mut events).await;
+        assert_eq!(event.kind, ChangeKind::PhaseTransitioned);
+        assert_eq!(event.entity_id, transition.id);
+
+        TaskService::delete(task.id, None, &audit, &pool)
+            .await
+            .unwrap();
+        let event = next_event(&mut events).await;
+        assert_eq!(event.kind, ChangeKind::TaskDeleted);
+        assert_eq!(event.version, Some(3));
+
+        // Restoring it brings it back into open views
+        TaskService::restore(task.id, None, &audit, &pool)
+            .await
+            .unwrap();
+        let event = next_event(&mut events).await;
+        assert_eq!(event.kind, ChangeKind::TaskRestored);
+        assert_eq!(event.entity_id, task.id);
+        assert_eq!(event.version, Some(4));
+
+        cleanup_test_db(&pool).await;
+    }
+
+    #[actix_rt::test]
+    #[serial]
+    async fn test_streams_carry_only_visible_projects() {
+        let pool = setup_test_db().await;
+        let tokens = test_token_service();
+        let broadcaster = Broadcaster::new();
+        broadcaster.listen(&pool).await.unwrap();
+        let manager = create_user("pm@example.com", UserRole::ProjectManager, &pool).await;
+        let developer = create_user("dev@example.com", UserRole::Developer, &pool).await;
+        let developer_resource = create_resource(&developer, &pool).await;
+        let bearer = format!("Bearer {}", tokens.issue(&developer).unwrap().token);
+        let audit = audit_as(manager.id);
+        let mine = create_project("Mine", &audit, &pool).await;
+        let other = create_project("Other", &audit, &pool).await;
+        let my_task = create_task(
+            TaskCreate {
+                assigned_to: Some(developer_resource),
+                ..new_task("Lay cable", mine.id)
+            },
+            &audit,
+            &pool,
+        )
+        .await;
+        let other_task = create_task(new_task("Lay cable", other.id), &audit, &pool).await;
+
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(pool.clone()))
+                .app_data(web::Data::new(tokens))
+                .app_data(web::Data::new(broadcaster))
+                .configure(routes::config),
+        )
+        .await;
+        let get = |uri: String| {
+            test::TestRequest::get()
+                .uri(&uri)
+                .insert_header(("Authorization", bearer.clone()))
+                .to_request()
+        };
+
+        let resp =
+            test::call_service(&app, get(format!("/api/events?project_id={}", other.id))).await;
+        assert_eq!(resp.status(), 404);
+
+        let resp = test::call_service(&app, get("/api/events".to_string())).await;
+        assert_eq!(resp.status(), 200);
+        assert_eq!(
+            resp.headers().get("content-type").unwrap(),
+            "text/event-stream"
+        );
+        let mut body = resp.into_body();
+        assert_eq!(next_chunk(&mut body).await, "retry: 3000\n\n");
+
+        TaskService::update(other_task.id, rename("Hidden"), None, &audit, &pool)
+            .await
+            .unwrap();
+        TaskService::update(my_task.id, rename("Visible"), None, &audit, &pool)
+            .await
+            .unwrap();
+        let frame = next_chunk(&mut body).await;
+        let lines: Vec<&str> = frame.lines().collect();
+        assert!(lines[0].starts_with("id: "));
+        assert_eq!(lines[1], "event: task.updated");
+        let event: ChangeEvent =
+            serde_json::from_str(lines[2].strip_prefix("data: ").unwrap()).unwrap();
+        assert_eq!(event.project_id, mine.id);
+        assert_eq!(event.entity_id, my_task.id);
+        assert!(frame.ends_with("\n\n"));
+
+        cleanup_test_db(&pool).await;
+    }
+
+    #[actix_rt::test]
+    #[serial]
+    async fn test_streams_end_when_the_token_expires() {
+        let pool = setup_test_db().await;
+        let broadcaster = Broadcaster::new();
+        let developer = create_user("dev@example.com", UserRole::Developer, &pool).await;
+
+        let stream = RealtimeService::stream(
+            broadcaster.subscribe(),
+            Visibility::All,
+            StreamSession {
+                user_id: developer.id,
+                expires_at: Utc::now() + Duration::seconds(1),
+                pool: pool.clone(),
+            },
+        );
+        let frames: Vec<String> = timeout(WAIT, stream.collect::<Vec<_>>())
+            .await
+            .expect("the stream did not end")
+            .into_iter()
+            .map(|frame| String::from_utf8(frame.unwrap().to_vec()).unwrap())
+            .collect();
+        assert_eq!(
+            frames,
+            vec!["retry: 3000\n\n", "event: expired\ndata: {}\n\n"]
+        );
+
+        cleanup_test_db(&pool).await;
+    }
+}
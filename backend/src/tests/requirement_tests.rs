@@ -0,0 +1,572 @@
+#[cfg(test)]
+mod tests {
+    use crate::errors::ServiceError;
+    use crate::models::patch::Patch;
+    use crate::models::requirement::{
+        Requirement, RequirementCreate, RequirementPriority, RequirementStatus, RequirementType,
+        RequirementUpdate,
+    };
+    use crate::models::task::TaskCreate;
+    use crate::models::template::{DocumentGenerate, DocumentKind, OutputFormat};
+    use crate::models::traceability::{
+        DesignItemCreate, TraceEntity, TraceLinkCreate, TraceLinkFilter,
+    };
+    use crate::models::user::{User, UserRole};
+    use crate::routes;
+    use crate::services::document_service::DocumentService;
+    use crate::services::requirement_service::RequirementService;
+    use crate::services::storage_service::LocalStorage;
+    use crate::services::task_service::TaskService;
+    use crate::services::template_service::TemplateService;
+    use crate::services::traceability_service::TraceabilityService;
+    use crate::tests::test_helpers::{
+        audit_as, cleanup_test_db, create_project, create_resource, create_task, create_user,
+        new_task, setup_test_db, test_token_service,
+    };
+    use actix_web::{test, web, App};
+    use serde_json::{json, Value};
+    use serial_test::serial;
+    use sqlx::PgPool;
+    use uuid::Uuid;
+
+    async fn create_requirement(
+        project_id: Uuid,
+        title: &str,
+        user: &User,
+        pool: &PgPool,
+    ) -> Requirement {
+        RequirementService::create(
+            RequirementCreate {
+                project_id,
+                title: title.to_string(),
+                description: None,
+                requirement_type: RequirementType::Functional,
+                priority: None,
+                status: None,
+                acceptance_criteria: None,
+            },
+            user.id,
+            &user.role,
+            &audit_as(user.id),
+            pool,
+        )
+        .await
+        .unwrap()
+    }
+
+    fn trace(
+        source_type: TraceEntity,
+        source_id: Uuid,
+        target_type: TraceEntity,
+        target_id: Uuid,
+    ) -> TraceLinkCreate {
+        TraceLinkCreate {
+            source_type,
+            source_id,
+            target_type,
+            target_id,
+        }
+    }
+
+    #[actix_rt::test]
+    #[serial]
+    async fn test_requirements_are_numbered_per_project_and_versioned() {
+        let pool = setup_test_db().await;
+        let manager = create_user("pm@example.com", UserRole::ProjectManager, &pool).await;
+        let audit = audit_as(manager.id);
+        let billing = create_project("Billing", &audit, &pool).await;
+        let portal = create_project("Portal", &audit, &pool).await;
+
+        let login = create_requirement(billing.id, "Users log in with SSO", &manager, &pool).await;
+        let export =
+            create_requirement(billing.id, "Invoices export to PDF", &manager, &pool).await;
+        let other = create_requirement(portal.id, "Portal home page", &manager, &pool).await;
+        assert_eq!(login.code, "REQ-001");
+        assert_eq!(export.code, "REQ-002");
+        assert_eq!(other.code, "REQ-001");
+        assert_eq!(login.priority, RequirementPriority::Medium);
+        assert_eq!(login.status, RequirementStatus::Draft);
+
+        let approved = RequirementService::update(
+            login.id,
+            RequirementUpdate {
+                status: Patch::Value(RequirementStatus::Approved),
+                acceptance_criteria: Patch::Value("Okta and Azure AD both work".to_string()),
+                ..Default::default()
+            },
+            Some(1),
+            manager.id,
+            &manager.role,
+            &audit,
+            &pool,
+        )
+        .await
+        .unwrap();
+        assert_eq!(approved.version, 2);
+        assert_eq!(approved.code, "REQ-001");
+
+        // A patch that changes nothing makes no version
+        let unchanged = RequirementService::update(
+            login.id,
+            RequirementUpdate {
+                status: Patch::Value(RequirementStatus::Approved),
+                ..Default::default()
+            },
+            Some(2),
+            manager.id,
+            &manager.role,
+            &audit,
+            &pool,
+        )
+        .await
+        .unwrap();
+        assert_eq!(unchanged.version, 2);
+
+        let stale = RequirementService::update(
+            login.id,
+            RequirementUpdate {
+                title: Patch::Value("Users log in".to_string()),
+                ..Default::default()
+            },
+            Some(1),
+            manager.id,
+            &manager.role,
+            &audit,
+            &pool,
+        )
+        .await;
+        assert!(matches!(
+            stale,
+            Err(ServiceError::PreconditionFailed { version: 2, .. })
+        ));
+
+        let revisions = RequirementService::revisions(login.id, manager.id, &manager.role, &pool)
+            .await
+            .unwrap();
+        assert_eq!(revisions.len(), 2);
+        assert_eq!(revisions[0].version, 1);
+        assert_eq!(revisions[0].status, RequirementStatus::Draft);
+        assert_eq!(revisions[0].acceptance_criteria, None);
+        assert_eq!(revisions[1].status, RequirementStatus::Approved);
+        assert_eq!(revisions[1].changed_by, Some(manager.id));
+
+        // Numbers are not reused after a deletion
+        RequirementService::delete(export.id, None, manager.id, &manager.role, &audit, &pool)
+            .await
+            .unwrap();
+        let next = create_requirement(billing.id, "Dunning letters", &manager, &pool).await;
+        assert_eq!(next.code, "REQ-003");
+
+        cleanup_test_db(&pool).await;
+    }
+
+    #[actix_rt::test]
+    #[serial]
+    async fn test_trace_links_follow_the_chain_within_a_project() {
+        let pool = setup_test_db().await;
+        let manager = create_user("pm@example.com", UserRole::ProjectManager, &pool).await;
+        let audit = audit_as(manager.id);
+        let billing = create_project("Billing", &audit, &pool).await;
+        let portal = create_project("Portal", &audit, &pool).await;
+        let requirement = create_requirement(billing.id, "Invoices", &manager, &pool).await;
+        let task = create_task(
+            TaskCreate {
+                wbs: Some("1.1".to_string()),
+                ..new_task("Build invoicing", billing.id)
+            },
+            &audit,
+            &pool,
+        )
+        .await;
+        let foreign_task = create_task(
+            TaskCreate {
+                wbs: Some("1.1".to_string()),
+                ..new_task("Build portal", portal.id)
+            },
+            &audit,
+            &pool,
+        )
+        .await;
+
+        let link = TraceabilityService::link(
+            trace(
+                TraceEntity::Requirement,
+                requirement.id,
+                TraceEntity::Task,
+                task.id,
+            ),
+            manager.id,
+            &manager.role,
+            &audit,
+            &pool,
+        )
+        .await
+        .unwrap();
+        assert_eq!(link.project_id, billing.id);
+
+        // Linking again returns the existing link
+        let again = TraceabilityService::link(
+            trace(
+                TraceEntity::Requirement,
+                requirement.id,
+                TraceEntity::Task,
+                task.id,
+            ),
+            manager.id,
+            &manager.role,
+            &audit,
+            &pool,
+        )
+        .await
+        .unwrap();
+        assert_eq!(again.id, link.id);
+
+        let backwards = TraceabilityService::link(
+            trace(
+                TraceEntity::Task,
+                task.id,
+                TraceEntity::Requirement,
+                requirement.id,
+            ),
+            manager.id,
+            &manager.role,
+            &audit,
+            &pool,
+        )
+        .await;
+        assert!(matches!(backwards, Err(ServiceError::ValidationError(_))));
+
+        let across = TraceabilityService::link(
+            trace(
+                TraceEntity::Requirement,
+                requirement.id,
+                TraceEntity::Task,
+                foreign_task.id,
+            ),
+            manager.id,
+            &manager.role,
+            &audit,
+            &pool,
+        )
+        .await;
+        match across {
+            Err(ServiceError::ValidationError(fields)) => {
+                assert_eq!(fields[0].field, "target_id");
+                assert_eq!(fields[0].code, "project");
+            }
+            other => panic!("expected a validation error, got {:?}", other),
+        }
+
+        // Deleting the requirement takes its links with it
+        RequirementService::delete(
+            requirement.id,
+            None,
+            manager.id,
+            &manager.role,
+            &audit,
+            &pool,
+        )
+        .await
+        .unwrap();
+        let links = TraceabilityService::links(
+            &TraceLinkFilter {
+                project_id: Some(billing.id),
+                entity_type: None,
+                entity_id: None,
+            },
+            manager.id,
+            &manager.role,
+            &pool,
+        )
+        .await
+        .unwrap();
+        assert!(links.is_empty());
+
+        cleanup_test_db(&pool).await;
+    }
+
+    #[actix_rt::test]
+    #[serial]
+    async fn test_traceability_matrix_flags_requirements_without_tasks() {
+        let pool = setup_test_db().await;
+        let manager = create_user("pm@example.com", UserRole::ProjectManager, &pool).await;
+        let audit = audit_as(manager.id);
+        let project = create_project("Billing", &audit, &pool).await;
+        let invoices = create_requirement(project.id, "Invoices", &manager, &pool).await;
+        let reminders = create_requirement(project.id, "Reminders", &manager, &pool).await;
+        let reports = create_requirement(project.id, "Reports", &manager, &pool).await;
+        let rejected = create_requirement(project.id, "Fax support", &manager, &pool).await;
+        RequirementService::update(
+            rejected.id,
+            RequirementUpdate {
+                status: Patch::Value(RequirementStatus::Rejected),
+                ..Default::default()
+            },
+            None,
+            manager.id,
+            &manager.role,
+            &audit,
+            &pool,
+        )
+        .await
+        .unwrap();
+        let design = TraceabilityService::create_design_item(
+            DesignItemCreate {
+                project_id: project.id,
+                title: "Invoice generator".to_string(),
+                description: None,
+            },
+            manager.id,
+            &manager.role,
+            &audit,
+            &pool,
+        )
+        .await
+        .unwrap();
+        let generator = create_task(
+            TaskCreate {
+                wbs: Some("1.2".to_string()),
+                ..new_task("Build generator", project.id)
+            },
+            &audit,
+            &pool,
+        )
+        .await;
+        let templates = create_task(
+            TaskCreate {
+                wbs: Some("1.10".to_string()),
+                ..new_task("Invoice templates", project.id)
+            },
+            &audit,
+            &pool,
+        )
+        .await;
+        let scheduler = create_task(
+            TaskCreate {
+                wbs: Some("2.1".to_string()),
+                ..new_task("Reminder scheduler", project.id)
+            },
+            &audit,
+            &pool,
+        )
+        .await;
+
+        for link in [
+            trace(
+                TraceEntity::Requirement,
+                invoices.id,
+                TraceEntity::DesignItem,
+                design.id,
+            ),
+            trace(
+                TraceEntity::DesignItem,
+                design.id,
+                TraceEntity::Task,
+                templates.id,
+            ),
+            trace(
+                TraceEntity::DesignItem,
+                design.id,
+                TraceEntity::Task,
+                generator.id,
+            ),
+            trace(
+                TraceEntity::Requirement,
+                invoices.id,
+                TraceEntity::Task,
+                generator.id,
+            ),
+            trace(
+                TraceEntity::Requirement,
+                reminders.id,
+                TraceEntity::Task,
+                scheduler.id,
+            ),
+        ] {
+            TraceabilityService::link(link, manager.id, &manager.role, &audit, &pool)
+                .await
+                .unwrap();
+        }
+
+        let matrix = TraceabilityService::matrix(project.id, manager.id, &manager.role, &pool)
+            .await
+            .unwrap();
+        let codes: Vec<&str> = matrix.rows.iter().map(|row| row.code.as_str()).collect();
+        assert_eq!(codes, vec!["REQ-001", "REQ-002", "REQ-003"]);
+        let row = &matrix.rows[0];
+        assert_eq!(row.design_items.len(), 1);
+        assert_eq!(row.design_items[0].title, "Invoice generator");
+        // Through the design item and directly, each task once, in WBS order
+        let wbs: Vec<_> = row.tasks.iter().map(|task| task.wbs.as_deref()).collect();
+        assert_eq!(wbs, vec![Some("1.2"), Some("1.10")]);
+        assert!(!row.untasked);
+        assert!(!matrix.rows[1].untasked);
+        assert!(matrix.rows[2].untasked);
+        assert_eq!(matrix.untasked, 1);
+        assert_eq!(reports.code, "REQ-003");
+
+        // Tasks in the trash no longer implement anything
+        TaskService::delete(scheduler.id, None, &audit, &pool)
+            .await
+            .unwrap();
+        let matrix = TraceabilityService::matrix(project.id, manager.id, &manager.role, &pool)
+            .await
+            .unwrap();
+        assert!(matrix.rows[1].untasked);
+        assert_eq!(matrix.untasked, 2);
+
+        // The requirements specification lists the project's requirements
+        let storage = LocalStorage::new(
+            std::env::temp_dir().join(format!("requirements-{}", Uuid::new_v4())),
+        );
+        let document = TemplateService::generate(
+            DocumentGenerate {
+                project_id: project.id,
+                kind: DocumentKind::RequirementsSpecification,
+                format: OutputFormat::Markdown,
+                gate_review_id: None,
+            },
+            manager.id,
+            &manager.role,
+            &audit,
+            &storage,
+            &pool,
+        )
+        .await
+        .unwrap();
+        let (_, content) = DocumentService::download(
+            document.id,
+            None,
+            manager.id,
+            &manager.role,
+            &storage,
+            &pool,
+        )
+        .await
+        .unwrap();
+        let markdown = String::from_utf8(content.to_vec()).unwrap();
+        assert!(markdown.contains("| REQ-001 | Invoices | Functional | Medium | Draft |"));
+        assert!(markdown.contains("### REQ-003: Reports"));
+        assert!(!markdown.contains("Fax support"));
+
+        cleanup_test_db(&pool).await;
+    }
+
+    #[actix_rt::test]
+    #[serial]
+    async fn test_requirements_and_traceability_over_http() {
+        let pool = setup_test_db().await;
+        let tokens = test_token_service();
+        let manager = create_user("pm@example.com", UserRole::ProjectManager, &pool).await;
+        let developer = create_user("dev@example.com", UserRole::Developer, &pool).await;
+        let developer_resource = create_resource(&developer, &pool).await;
+        let manager_bearer = format!("Bearer {}", tokens.issue(&manager).unwrap().token);
+        let developer_bearer = format!("Bearer {}", tokens.issue(&developer).unwrap().token);
+        let audit = audit_as(manager.id);
+        let project = create_project("Billing", &audit, &pool).await;
+        let task = create_task(
+            TaskCreate {
+                wbs: Some("1.1".to_string()),
+                ..new_task("Build invoicing", project.id)
+            },
+            &audit,
+            &pool,
+        )
+        .await;
+        sqlx::query!(
+            "UPDATE tasks SET assigned_to = ARRAY[$1::uuid] WHERE id = $2",
+            developer_resource,
+            task.id
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(pool.clone()))
+                .app_data(web::Data::new(tokens))
+                .configure(routes::config),
+        )
+        .await;
+
+        let body = json!({
+            "project_id": project.id,
+            "title": "Invoices",
+            "type": "non_functional",
+            "priority": "high"
+        });
+        let req = test::TestRequest::post()
+            .uri("/api/requirements")
+            .insert_header(("Authorization", developer_bearer.clone()))
+            .set_json(&body)
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 403);
+
+        let req = test::TestRequest::post()
+            .uri("/api/requirements")
+            .insert_header(("Authorization", manager_bearer.clone()))
+            .set_json(&body)
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 201);
+        assert_eq!(resp.headers().get("etag").unwrap(), "\"1\"");
+        let requirement: Value = test::read_body_json(resp).await;
+        assert_eq!(requirement["code"], "REQ-001");
+        assert_eq!(requirement["type"], "non_functional");
+
+        let req = test::TestRequest::patch()
+            .uri(&format!(
+                "/api/requirements/{}",
+                requirement["id"].as_str().unwrap()
+            ))
+            .insert_header(("Authorization", manager_bearer.clone()))
+            .insert_header(("If-Match", "\"1\""))
+            .set_json(json!({ "title": "Invoices in EUR and USD" }))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 200);
+        assert_eq!(resp.headers().get("etag").unwrap(), "\"2\"");
+
+        // Developers can trace the work they do
+        let req = test::TestRequest::post()
+            .uri("/api/trace-links")
+            .insert_header(("Authorization", developer_bearer.clone()))
+            .set_json(json!({
+                "source_type": "requirement",
+                "source_id": requirement["id"],
+                "target_type": "task",
+                "target_id": task.id
+            }))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 201);
+
+        let req = test::TestRequest::get()
+            .uri(&format!("/api/projects/{}/traceability", project.id))
+            .insert_header(("Authorization", developer_bearer.clone()))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 200);
+        let matrix: Value = test::read_body_json(resp).await;
+        assert_eq!(matrix["untasked"], 0);
+        assert_eq!(matrix["rows"][0]["title"], "Invoices in EUR and USD");
+        assert_eq!(matrix["rows"][0]["tasks"][0]["wbs"], "1.1");
+
+        let req = test::TestRequest::get()
+            .uri(&format!(
+                "/api/requirements/{}/revisions",
+                requirement["id"].as_str().unwrap()
+            ))
+            .insert_header(("Authorization", developer_bearer))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 200);
+        let revisions: Vec<Value> = test::read_body_json(resp).await;
+        assert_eq!(revisions.len(), 2);
+        assert_eq!(revisions[0]["title"], "Invoices");
+
+        cleanup_test_db(&pool).await;
+    }
+}
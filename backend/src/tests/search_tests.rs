@@ -0,0 +1,152 @@
+#[cfg(test)]
+mod tests {
+    use crate::models::audit::AuditContext;
+    use crate::models::lifecycle::{LifecyclePhase, PhaseTransition};
+    use crate::models::project::ProjectCreate;
+    use crate::models::task::TaskCreate;
+    use crate::models::user::UserRole;
+    use crate::services::lifecycle_service::LifecycleService;
+    use crate::services::project_service::ProjectService;
+    use crate::services::search_service::SearchService;
+    use crate::tests::test_helpers::{
+        audit_as, cleanup_test_db, create_resource, create_task, create_user, new_task,
+        setup_test_db,
+    };
+    use bigdecimal::{BigDecimal, FromPrimitive};
+    use chrono::{Duration, Utc};
+    use serial_test::serial;
+    use sqlx::PgPool;
+    use uuid::Uuid;
+
+    async fn create_project(name: &str, description: &str, pool: &PgPool) -> Uuid {
+        let project = ProjectCreate {
+            name: name.to_string(),
+            description: Some(description.to_string()),
+            start_date: Utc::now(),
+            end_date: Utc::now() + Duration::days(30),
+            budget: BigDecimal::from_f64(1000.0).unwrap(),
+            client_id: None,
+        };
+        ProjectService::create(project, &AuditContext::default(), pool)
+            .await
+            .unwrap()
+            .id
+    }
+
+    #[actix_rt::test]
+    #[serial]
+    async fn test_search_ranks_and_groups_matches() {
+        let pool = setup_test_db().await;
+        let admin = create_user("admin@example.com", UserRole::Admin, &pool)
+            .await
+            .id;
+
+        let erp = create_project("ERP Rollout", "Replace the legacy ERP system", &pool).await;
+        create_task(
+            new_task("Data migration", erp),
+            &AuditContext::default(),
+            &pool,
+        )
+        .await;
+        create_task(
+            new_task("Vendor selection", erp),
+            &AuditContext::default(),
+            &pool,
+        )
+        .await;
+        LifecycleService::transition_phase(
+            PhaseTransition {
+                project_id: erp,
+                phase: LifecyclePhase::Design,
+                description: "Approved the migration plan".to_string(),
+                attachments: None,
+                documents: vec![],
+            },
+            None,
+            &audit_as(admin),
+            &pool,
+        )
+        .await
+        .unwrap();
+
+        let results = SearchService::search("migration", None, admin, &UserRole::Admin, &pool)
+            .await
+            .unwrap();
+        assert!(results.projects.is_empty());
+        assert_eq!(results.tasks.len(), 1);
+        assert_eq!(results.tasks[0].project_name, "ERP Rollout");
+        assert!(results.tasks[0].snippet.contains("<mark>migration</mark>"));
+        assert_eq!(results.phase_transitions.len(), 1);
+
+        // Name matches rank above description matches
+        create_project("Payroll", "Payroll module for the ERP", &pool).await;
+        let results = SearchService::search("ERP", None, admin, &UserRole::Admin, &pool)
+            .await
+            .unwrap();
+        assert_eq!(results.projects.len(), 2);
+        assert_eq!(results.projects[0].title, "ERP Rollout");
+
+        // Text users wrote is escaped in the snippet
+        create_project("Billing", "<img src=x onerror=alert(1)> invoices", &pool).await;
+        let results = SearchService::search("invoices", None, admin, &UserRole::Admin, &pool)
+            .await
+            .unwrap();
+        assert_eq!(results.projects.len(), 1);
+        let snippet = &results.projects[0].snippet;
+        assert!(snippet.contains("&lt;img src=x onerror=alert(1)&gt; <mark>invoices</mark>"));
+        assert!(!snippet.contains("<img"));
+
+        let empty = SearchService::search("  ", None, admin, &UserRole::Admin, &pool).await;
+        assert!(empty.is_err());
+
+        cleanup_test_db(&pool).await;
+    }
+
+    #[actix_rt::test]
+    #[serial]
+    async fn test_search_respects_project_visibility() {
+        let pool = setup_test_db().await;
+        let user = create_user("dev@example.com", UserRole::Developer, &pool).await;
+        let developer = user.id;
+        let developer_resource = create_resource(&user, &pool).await;
+
+        let assigned = create_project("Billing migration", "Move billing", &pool).await;
+        create_task(
+            TaskCreate {
+                assigned_to: Some(developer_resource),
+                ..new_task("Migrate invoices", assigned)
+            },
+            &AuditContext::default(),
+            &pool,
+        )
+        .await;
+        let other = create_project("CRM migration", "Move CRM", &pool).await;
+        create_task(
+            new_task("Migrate contacts", other),
+            &AuditContext::default(),
+            &pool,
+        )
+        .await;
+
+        let results =
+            SearchService::search("migration", None, developer, &UserRole::Developer, &pool)
+                .await
+                .unwrap();
+        assert_eq!(results.projects.len(), 1);
+        assert_eq!(results.projects[0].id, assigned);
+        assert!(results.tasks.iter().all(|hit| hit.project_id == assigned));
+
+        let results = SearchService::search(
+            "migration",
+            None,
+            developer,
+            &UserRole::ProjectManager,
+            &pool,
+        )
+        .await
+        .unwrap();
+        assert_eq!(results.projects.len(), 2);
+
+        cleanup_test_db(&pool).await;
+    }
+}
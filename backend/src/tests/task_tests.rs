@@ -1,19 +1,18 @@
+use crate::models::audit::AuditContext;
+use crate::models::pagination::PageParams;
+use crate::models::patch::Patch;
 use crate::models::project::ProjectCreate;
 #[cfg(test)]
 use crate::models::task::TaskUpdate;
 use crate::tests::test_helpers::setup_test_db;
 use crate::{
-    models::task::{TaskCreate, TaskStatus},
+    models::task::{TaskCreate, TaskFilter, TaskStatus},
     services::{project_service::ProjectService, task_service::TaskService},
-    tests::test_helpers::cleanup_test_db,
 };
 use bigdecimal::{BigDecimal, FromPrimitive};
 use chrono::{Duration, Utc};
 use serial_test::serial;
-use sqlx::{
-    postgres::{PgPoolOptions, PgQueryResult},
-    Executor, PgPool,
-};
+use sqlx::PgPool;
 use uuid::Uuid;
 
 async fn create_test_project(pool: &PgPool) -> Uuid {
@@ -25,7 +24,9 @@ async fn create_test_project(pool: &PgPool) -> Uuid {
         budget: BigDecimal::from_f64(10000.0).unwrap(),
         client_id: None,
     };
-    let created_project = ProjectService::create(project, pool).await.unwrap();
+    let created_project = ProjectService::create(project, &AuditContext::default(), pool)
+        .await
+        .unwrap();
     created_project.id
 }
 
@@ -43,9 +44,12 @@ async fn test_create_task() {
         start_date: Utc::now(),
         end_date: Utc::now() + Duration::days(7),
         dependencies: vec![],
+        parent_id: None,
+        wbs: None,
+        milestone: false,
     };
 
-    let result = TaskService::create(new_task, &pool).await;
+    let result = TaskService::create(new_task, &AuditContext::default(), &pool).await;
     assert!(result.is_ok());
 
     let task = result.unwrap();
@@ -70,9 +74,14 @@ async fn test_get_task() {
         start_date: Utc::now(),
         end_date: Utc::now() + Duration::days(7),
         dependencies: vec![],
+        parent_id: None,
+        wbs: None,
+        milestone: false,
     };
 
-    let created = TaskService::create(new_task, &pool).await.unwrap();
+    let created = TaskService::create(new_task, &AuditContext::default(), &pool)
+        .await
+        .unwrap();
 
     let result = TaskService::get_by_id(created.id, &pool).await;
     assert!(result.is_ok());
@@ -97,15 +106,20 @@ async fn test_get_all_tasks() {
             start_date: Utc::now(),
             end_date: Utc::now() + Duration::days(7),
             dependencies: vec![],
+            parent_id: None,
+            wbs: None,
+            milestone: false,
         };
 
-        TaskService::create(new_task, &pool).await.unwrap();
+        TaskService::create(new_task, &AuditContext::default(), &pool)
+            .await
+            .unwrap();
     }
 
-    let result = TaskService::get_all(&pool).await;
+    let result = TaskService::get_all(&PageParams::default(), &TaskFilter::default(), &pool).await;
     assert!(result.is_ok());
 
-    let tasks = result.unwrap();
+    let tasks = result.unwrap().items;
     assert_eq!(tasks.len(), 3);
     assert_eq!(tasks[0].name, "Task 3");
     assert_eq!(tasks[1].name, "Task 2");
@@ -126,22 +140,31 @@ async fn test_update_task() {
         start_date: Utc::now(),
         end_date: Utc::now() + Duration::days(7),
         dependencies: vec![],
+        parent_id: None,
+        wbs: None,
+        milestone: false,
     };
 
-    let created = TaskService::create(new_task, &pool).await.unwrap();
+    let created = TaskService::create(new_task, &AuditContext::default(), &pool)
+        .await
+        .unwrap();
 
     let update = TaskUpdate {
-        name: Some("Updated Task".to_string()),
-        description: Some("Updated Description".to_string()),
-        assigned_to: None,
-        status: Some(TaskStatus::InProgress),
-        progress: Some(BigDecimal::from_f64(50.0).unwrap()),
-        start_date: None,
-        end_date: None,
-        dependencies: Some(vec![]),
+        name: Patch::Value("Updated Task".to_string()),
+        description: Patch::Value("Updated Description".to_string()),
+        assigned_to: Patch::Absent,
+        status: Patch::Value(TaskStatus::InProgress),
+        progress: Patch::Value(BigDecimal::from_f64(50.0).unwrap()),
+        start_date: Patch::Absent,
+        end_date: Patch::Absent,
+        dependencies: Patch::Value(vec![]),
+        parent_id: Patch::Absent,
+        wbs: Patch::Absent,
+        milestone: Patch::Absent,
     };
 
-    let result = TaskService::update(created.id, update, &pool).await;
+    let result =
+        TaskService::update(created.id, update, None, &AuditContext::default(), &pool).await;
     assert!(result.is_ok());
 
     let task = result.unwrap();
@@ -165,11 +188,16 @@ async fn test_delete_task() {
         start_date: Utc::now(),
         end_date: Utc::now() + Duration::days(7),
         dependencies: vec![],
+        parent_id: None,
+        wbs: None,
+        milestone: false,
     };
 
-    let created = TaskService::create(new_task, &pool).await.unwrap();
+    let created = TaskService::create(new_task, &AuditContext::default(), &pool)
+        .await
+        .unwrap();
 
-    let result = TaskService::delete(created.id, &pool).await;
+    let result = TaskService::delete(created.id, None, &AuditContext::default(), &pool).await;
     assert!(result.is_ok());
 
     let get_result = TaskService::get_by_id(created.id, &pool).await;
@@ -193,9 +221,14 @@ async fn test_get_tasks_by_resource() {
             start_date: Utc::now(),
             end_date: Utc::now() + Duration::days(7),
             dependencies: vec![],
+            parent_id: None,
+            wbs: None,
+            milestone: false,
         };
 
-        TaskService::create(new_task, &pool).await.unwrap();
+        TaskService::create(new_task, &AuditContext::default(), &pool)
+            .await
+            .unwrap();
     }
 
     // Create a task not assigned to the resource
@@ -207,8 +240,13 @@ async fn test_get_tasks_by_resource() {
         start_date: Utc::now(),
         end_date: Utc::now() + Duration::days(7),
         dependencies: vec![],
+        parent_id: None,
+        wbs: None,
+        milestone: false,
     };
-    TaskService::create(unassigned_task, &pool).await.unwrap();
+    TaskService::create(unassigned_task, &AuditContext::default(), &pool)
+        .await
+        .unwrap();
 
     let result = TaskService::get_by_resource(resource_id, &pool).await;
     assert!(result.is_ok());
@@ -219,3 +257,65 @@ async fn test_get_tasks_by_resource() {
         assert!(task.assigned_to.contains(&resource_id));
     }
 }
+
+#[actix_rt::test]
+#[serial]
+async fn test_filter_tasks_by_assignee_and_overdue() {
+    let pool = setup_test_db().await;
+    let project_id = create_test_project(&pool).await;
+    let assignee = Uuid::new_v4();
+
+    // One overdue task for the assignee, one on schedule for someone else
+    let overdue = TaskCreate {
+        name: "Overdue Task".to_string(),
+        description: None,
+        project_id,
+        assigned_to: Some(assignee),
+        start_date: Utc::now() - Duration::days(14),
+        end_date: Utc::now() - Duration::days(7),
+        dependencies: vec![],
+        parent_id: None,
+        wbs: None,
+        milestone: false,
+    };
+    TaskService::create(overdue, &AuditContext::default(), &pool)
+        .await
+        .unwrap();
+
+    let on_schedule = TaskCreate {
+        name: "On Schedule Task".to_string(),
+        description: None,
+        project_id,
+        assigned_to: Some(Uuid::new_v4()),
+        start_date: Utc::now(),
+        end_date: Utc::now() + Duration::days(7),
+        dependencies: vec![],
+        parent_id: None,
+        wbs: None,
+        milestone: false,
+    };
+    TaskService::create(on_schedule, &AuditContext::default(), &pool)
+        .await
+        .unwrap();
+
+    let filter = TaskFilter {
+        assignee: Some(assignee),
+        ..Default::default()
+    };
+    let page = TaskService::get_all(&PageParams::default(), &filter, &pool)
+        .await
+        .unwrap();
+    assert_eq!(page.total, 1);
+    assert_eq!(page.items[0].name, "Overdue Task");
+
+    let filter = TaskFilter {
+        project_id: Some(project_id),
+        overdue: Some(true),
+        ..Default::default()
+    };
+    let page = TaskService::get_all(&PageParams::default(), &filter, &pool)
+        .await
+        .unwrap();
+    assert_eq!(page.total, 1);
+    assert_eq!(page.items[0].name, "Overdue Task");
+}
@@ -0,0 +1,685 @@
+#[cfg(test)]
+mod tests {
+    use crate::errors::ServiceError;
+    use crate::models::audit::AuditContext;
+    use crate::models::calendar::GateReviewCreate;
+    use crate::models::document::{DocumentFilter, DocumentTarget};
+    use crate::models::lifecycle::{LifecyclePhase, PhaseTransition};
+    use crate::models::mustache::{escape_html, escape_markdown, Template};
+    use crate::models::project::{Project, ProjectCreate};
+    use crate::models::task::TaskCreate;
+    use crate::models::template::{
+        DocumentGenerate, DocumentKind, DocumentTemplateCreate, OutputFormat, TemplateFormat,
+    };
+    use crate::models::user::UserRole;
+    use crate::routes;
+    use crate::services::document_service::DocumentService;
+    use crate::services::email_service::EmailService;
+    use crate::services::job_service::{JobContext, JobService};
+    use crate::services::lifecycle_service::LifecycleService;
+    use crate::services::project_service::ProjectService;
+    use crate::services::render_service::RenderService;
+    use crate::services::storage_service::{DocumentStorage, LocalStorage};
+    use crate::services::task_service::TaskService;
+    use crate::services::template_service::TemplateService;
+    use crate::services::webhook_service::WebhookService;
+    use crate::tests::test_helpers::{
+        audit_as, cleanup_test_db, create_named_user, create_user, setup_test_db,
+        test_token_service,
+    };
+    use actix_web::{test, web, App};
+    use bigdecimal::BigDecimal;
+    use chrono::{TimeZone, Utc};
+    use serde_json::json;
+    use serial_test::serial;
+    use sqlx::PgPool;
+    use std::path::PathBuf;
+    use std::str::FromStr;
+    use std::sync::Arc;
+    use uuid::Uuid;
+
+    fn temp_storage() -> (PathBuf, Arc<dyn DocumentStorage>) {
+        let root = std::env::temp_dir().join(format!("templates-{}", Uuid::new_v4()));
+        (root.clone(), Arc::new(LocalStorage::new(root)))
+    }
+
+    async fn create_project(audit: &AuditContext, pool: &PgPool) -> Project {
+        let project = ProjectService::create(
+            ProjectCreate {
+                name: "Billing | Phase 2".to_string(),
+                description: Some("Replace the *legacy* invoicing system".to_string()),
+                start_date: Utc.with_ymd_and_hms(2025, 7, 1, 0, 0, 0).unwrap(),
+                end_date: Utc.with_ymd_and_hms(2025, 9, 30, 0, 0, 0).unwrap(),
+                budget: BigDecimal::from(50000),
+                client_id: None,
+            },
+            audit,
+            pool,
+        )
+        .await
+        .unwrap();
+        for (wbs, name, milestone) in [
+            ("1.10", "Sign-off", true),
+            ("1.2", "Interview finance", false),
+        ] {
+            TaskService::create(
+                TaskCreate {
+                    name: name.to_string(),
+                    description: None,
+                    project_id: project.id,
+                    assigned_to: None,
+                    start_date: Utc.with_ymd_and_hms(2025, 7, 1, 0, 0, 0).unwrap(),
+                    end_date: Utc.with_ymd_and_hms(2025, 7, 15, 0, 0, 0).unwrap(),
+                    dependencies: vec![],
+                    parent_id: None,
+                    wbs: Some(wbs.to_string()),
+                    milestone,
+                },
+                audit,
+                pool,
+            )
+            .await
+            .unwrap();
+        }
+        project
+    }
+
+    async fn transition(
+        project_id: Uuid,
+        phase: LifecyclePhase,
+        audit: &AuditContext,
+        pool: &PgPool,
+    ) -> Uuid {
+        LifecycleService::transition_phase(
+            PhaseTransition {
+                project_id,
+                phase,
+                description: format!("Entering {:?}", phase),
+                attachments: None,
+                documents: vec![],
+            },
+            None,
+            audit,
+            pool,
+        )
+        .await
+        .unwrap()
+        .id
+    }
+
+    fn generate(project_id: Uuid, kind: DocumentKind, format: OutputFormat) -> DocumentGenerate {
+        DocumentGenerate {
+            project_id,
+            kind,
+            format,
+            gate_review_id: None,
+        }
+    }
+
+    #[actix_rt::test]
+    async fn test_template_sections_and_escaping() {
+        let template = Template::from_str(concat!(
+            "# {{title}}\n",
+            "{{#items}}\n",
+            "- {{name}} ({{owner.name}}, for {{title}})\n",
+            "{{/items}}\n",
+            "{{^items}}\n",
+            "None.\n",
+            "{{/items}}\n",
+            "{{! not shown }}\n",
+            "{{{raw}}} {{& raw}} {{#flag}}{{.}}{{/flag}}"
+        ))
+        .unwrap();
+        let context = json!({
+            "title": "A <b>",
+            "raw": "<i>",
+            "flag": "on",
+            "items": [
+                { "name": "one", "owner": { "name": "Ada" } },
+                { "name": "two_x|y", "owner": null },
+            ],
+        });
+        // Section lines leave no blank lines behind; names fall back to
+        // enclosing scopes
+        assert_eq!(
+            template.render(&context, escape_html),
+            "# A &lt;b&gt;\n- one (Ada, for A &lt;b&gt;)\n- two_x|y (, for A &lt;b&gt;)\n<i> <i> on"
+        );
+        assert_eq!(
+            template.render(&json!({ "items": [], "raw": "" }), escape_markdown),
+            "# \nNone.\n  "
+        );
+        assert_eq!(escape_markdown("a|b_c\nd"), "a\\|b\\_c d");
+
+        assert_eq!(
+            Template::from_str("{{#a}}x{{/b}}").unwrap_err(),
+            "line 1: {{/b}} closes {{#a}}"
+        );
+        assert_eq!(
+            Template::from_str("\n{{^a}}x").unwrap_err(),
+            "line 2: {{^a}} is never closed"
+        );
+        assert_eq!(
+            Template::from_str("x {{name").unwrap_err(),
+            "line 1: unclosed tag"
+        );
+        assert!(Template::from_str("{{/a}}").is_err());
+        assert!(Template::from_str("{{ }}").is_err());
+    }
+
+    #[actix_rt::test]
+    async fn test_markdown_renders_to_html_and_pdf() {
+        let markdown = concat!(
+            "# Spec & scope\n",
+            "\n",
+            "Some **bold**, *emphasis* and `code`; snake_case stays.\n",
+            "Continued \\*literally\\*.\n",
+            "\n",
+            "| WBS | Task |\n",
+            "|---|---|\n",
+            "| 1.2 | Pipe \\| inside |\n",
+            "\n",
+            "- first\n",
+            "- <script>\n",
+            "\n",
+            "1. one\n",
+            "\n",
+            "---\n",
+        );
+        let html = RenderService::markdown_to_html(markdown, "Spec");
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(html.contains("<title>Spec</title>"));
+        assert!(html.contains("<h1>Spec &amp; scope</h1>"));
+        assert!(html.contains(
+            "<p>Some <strong>bold</strong>, <em>emphasis</em> and <code>code</code>; \
+             snake_case stays. Continued *literally*.</p>"
+        ));
+        assert!(html.contains("<tr><th>WBS</th><th>Task</th></tr>"));
+        assert!(html.contains("<tr><td>1.2</td><td>Pipe | inside</td></tr>"));
+        assert!(html.contains("<ul>\n<li>first</li>\n<li>&lt;script&gt;</li>\n</ul>"));
+        assert!(html.contains("<ol>\n<li>one</li>\n</ol>"));
+        assert!(html.contains("<hr>"));
+
+        let pdf = RenderService::markdown_to_pdf(markdown, "Spec (draft)");
+        let text = String::from_utf8_lossy(&pdf);
+        assert!(text.starts_with("%PDF-1.4"));
+        assert!(text.ends_with("%%EOF\n"));
+        assert!(text.contains("(Spec & scope) Tj"));
+        assert!(text.contains("(Pipe | inside) Tj"));
+        assert!(text.contains("/Title (Spec \\(draft\\))"));
+        assert!(text.contains("(Spec \\(draft\\) \\226 page 1 of 1) Tj"));
+        // Every cross-reference entry points at its object
+        let xref = pdf.windows(6).rposition(|w| w == b"\nxref\n").unwrap() + 1;
+        let entries: Vec<usize> = std::str::from_utf8(&pdf[xref..])
+            .unwrap()
+            .lines()
+            .skip(3)
+            .take_while(|line| line.ends_with(" n "))
+            .map(|line| line[..10].parse().unwrap())
+            .collect();
+        assert_eq!(entries.len(), 8);
+        for (i, offset) in entries.iter().enumerate() {
+            assert!(pdf[*offset..].starts_with(format!("{} 0 obj", i + 1).as_bytes()));
+        }
+
+        // Long documents flow onto further pages
+        let long = "A line of requirements text.\n\n".repeat(100);
+        let pdf = RenderService::markdown_to_pdf(&long, "Long");
+        let text = String::from_utf8_lossy(&pdf);
+        assert!(text.contains("/Count 3 >>"));
+        assert!(text.contains("(Long \\226 page 3 of 3) Tj"));
+    }
+
+    #[actix_rt::test]
+    #[serial]
+    async fn test_generated_documents_are_versioned_and_attached_to_the_gate() {
+        let pool = setup_test_db().await;
+        let (root, storage) = temp_storage();
+        let manager = create_named_user(
+            "pm@example.com",
+            "Grace Hopper",
+            UserRole::ProjectManager,
+            &pool,
+        )
+        .await;
+        let admin = create_user("admin@example.com", UserRole::Admin, &pool).await;
+        let developer = create_user("dev@example.com", UserRole::Developer, &pool).await;
+        let audit = audit_as(manager.id);
+        let project = create_project(&audit, &pool).await;
+        let transition_id =
+            transition(project.id, LifecyclePhase::Requirements, &audit, &pool).await;
+        let review = LifecycleService::schedule_gate_review(
+            project.id,
+            GateReviewCreate {
+                phase: LifecyclePhase::Requirements,
+                scheduled_at: Utc.with_ymd_and_hms(2025, 7, 20, 14, 0, 0).unwrap(),
+                duration_minutes: 60,
+                location: Some("Room 4".to_string()),
+                notes: None,
+                min_pass_rate: None,
+                no_open_critical_defects: false,
+            },
+            &audit,
+            &pool,
+        )
+        .await
+        .unwrap();
+
+        let mut request = generate(
+            project.id,
+            DocumentKind::RequirementsSpecification,
+            OutputFormat::Markdown,
+        );
+        request.gate_review_id = Some(review.id);
+        let first = TemplateService::generate(
+            request,
+            manager.id,
+            &manager.role,
+            &audit,
+            storage.as_ref(),
+            &pool,
+        )
+        .await
+        .unwrap();
+        assert_eq!(first.title, "Requirements Specification (MD)");
+        assert_eq!(first.filename, "requirements-specification.md");
+        assert_eq!(first.content_type, "text/markdown");
+        assert_eq!(first.current_version, 1);
+
+        let (_, content) = DocumentService::download(
+            first.id,
+            None,
+            manager.id,
+            &manager.role,
+            storage.as_ref(),
+            &pool,
+        )
+        .await
+        .unwrap();
+        let markdown = String::from_utf8(content.to_vec()).unwrap();
+        assert!(markdown.starts_with("# Requirements Specification: Billing \\| Phase 2\n"));
+        assert!(markdown.contains("Replace the \\*legacy\\* invoicing system"));
+        // Tasks in WBS order, milestones listed as deliverables
+        let breakdown = &markdown[markdown.find("## 4. Work breakdown").unwrap()..];
+        let interview = breakdown.find("| 1.2 | Interview finance |").unwrap();
+        let sign_off = breakdown.find("| 1.10 | Sign-off |").unwrap();
+        assert!(interview < sign_off);
+        assert!(markdown.contains("| 1.10 | Sign-off | 2025-07-15 | Pending |"));
+        assert!(markdown.contains("- **Requirements**, approved by Grace Hopper on "));
+        assert!(markdown.contains(
+            "The Requirements gate review is scheduled for 2025-07-20 14:00 UTC at Room 4."
+        ));
+
+        // Generating again adds a version and keeps the links
+        let second = TemplateService::generate(
+            generate(
+                project.id,
+                DocumentKind::RequirementsSpecification,
+                OutputFormat::Markdown,
+            ),
+            manager.id,
+            &manager.role,
+            &audit,
+            storage.as_ref(),
+            &pool,
+        )
+        .await
+        .unwrap();
+        assert_eq!(second.id, first.id);
+        assert_eq!(second.current_version, 2);
+        let links = DocumentService::links(first.id, manager.id, &manager.role, &pool)
+            .await
+            .unwrap();
+        let mut targets: Vec<_> = links
+            .iter()
+            .map(|link| (link.target_type, link.target_id))
+            .collect();
+        targets.sort_by_key(|(target_type, _)| *target_type == DocumentTarget::GateReview);
+        assert_eq!(
+            targets,
+            vec![
+                (DocumentTarget::PhaseTransition, transition_id),
+                (DocumentTarget::GateReview, review.id)
+            ]
+        );
+
+        // The organisation's active template replaces the built-in one
+        let template = TemplateService::create(
+            DocumentTemplateCreate {
+                kind: DocumentKind::RequirementsSpecification,
+                name: "House style".to_string(),
+                format: TemplateFormat::Html,
+                body: "<h1>{{project.name}}</h1>{{#tasks}}<p>{{name}}</p>{{/tasks}}".to_string(),
+                active: true,
+            },
+            &audit_as(admin.id),
+            &pool,
+        )
+        .await
+        .unwrap();
+        let html = TemplateService::generate(
+            generate(
+                project.id,
+                DocumentKind::RequirementsSpecification,
+                OutputFormat::Html,
+            ),
+            manager.id,
+            &manager.role,
+            &audit,
+            storage.as_ref(),
+            &pool,
+        )
+        .await
+        .unwrap();
+        assert_ne!(html.id, first.id);
+        let (_, content) = DocumentService::download(
+            html.id,
+            None,
+            manager.id,
+            &manager.role,
+            storage.as_ref(),
+            &pool,
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            content,
+            "<h1>Billing | Phase 2</h1><p>Interview finance</p><p>Sign-off</p>".as_bytes()
+        );
+        let err = TemplateService::generate(
+            generate(
+                project.id,
+                DocumentKind::RequirementsSpecification,
+                OutputFormat::Pdf,
+            ),
+            manager.id,
+            &manager.role,
+            &audit,
+            storage.as_ref(),
+            &pool,
+        )
+        .await
+        .unwrap_err();
+        assert!(
+            matches!(err, ServiceError::ValidationError(ref errors) if errors[0].field == "format")
+        );
+
+        // Deleting it falls back to the built-in Markdown template
+        TemplateService::delete(template.id, None, &audit_as(admin.id), &pool)
+            .await
+            .unwrap();
+        let pdf = TemplateService::generate(
+            generate(
+                project.id,
+                DocumentKind::RequirementsSpecification,
+                OutputFormat::Pdf,
+            ),
+            manager.id,
+            &manager.role,
+            &audit,
+            storage.as_ref(),
+            &pool,
+        )
+        .await
+        .unwrap();
+        assert_eq!(pdf.content_type, "application/pdf");
+
+        // Each format is a document of its own, and the project stays
+        // hidden from those outside it
+        let documents = DocumentService::list(
+            &DocumentFilter {
+                project_id: Some(project.id),
+                target_type: None,
+                target_id: None,
+            },
+            manager.id,
+            &manager.role,
+            &pool,
+        )
+        .await
+        .unwrap();
+        assert_eq!(documents.len(), 3);
+        let err = TemplateService::generate(
+            generate(
+                project.id,
+                DocumentKind::PhaseReport,
+                OutputFormat::Markdown,
+            ),
+            developer.id,
+            &developer.role,
+            &audit_as(developer.id),
+            storage.as_ref(),
+            &pool,
+        )
+        .await
+        .unwrap_err();
+        assert!(matches!(err, ServiceError::NotFound(_)));
+
+        cleanup_test_db(&pool).await;
+        std::fs::remove_dir_all(root).ok();
+    }
+
+    #[actix_rt::test]
+    #[serial]
+    async fn test_closing_a_project_files_its_closure_report() {
+        let pool = setup_test_db().await;
+        let (root, storage) = temp_storage();
+        let manager = create_user("pm@example.com", UserRole::ProjectManager, &pool).await;
+        let audit = audit_as(manager.id);
+        let project = create_project(&audit, &pool).await;
+        let closed = transition(project.id, LifecyclePhase::Closed, &audit, &pool).await;
+
+        let context = JobContext {
+            pool: pool.clone(),
+            email: Arc::new(EmailService::in_memory()),
+            purge: None,
+            http: WebhookService::http_client(),
+            storage: storage.clone(),
+        };
+        assert_eq!(JobService::run_pending(&context, "a").await.unwrap(), 1);
+
+        let documents = DocumentService::list(
+            &DocumentFilter {
+                project_id: None,
+                target_type: Some(DocumentTarget::PhaseTransition),
+                target_id: Some(closed),
+            },
+            manager.id,
+            &manager.role,
+            &pool,
+        )
+        .await
+        .unwrap();
+        assert_eq!(documents.len(), 1);
+        assert_eq!(documents[0].title, "Closure Report (PDF)");
+        assert_eq!(documents[0].created_by, Some(manager.id));
+        let (_, content) = DocumentService::download(
+            documents[0].id,
+            None,
+            manager.id,
+            &manager.role,
+            storage.as_ref(),
+            &pool,
+        )
+        .await
+        .unwrap();
+        let text = String::from_utf8_lossy(&content);
+        assert!(text.starts_with("%PDF-1.4"));
+        assert!(text.contains("(Closure Report: Billing | Phase 2) Tj"));
+        assert!(text.contains("(Closed) Tj"));
+
+        // The report stays with the project once it is archived
+        ProjectService::archive(project.id, None, &audit, &pool)
+            .await
+            .unwrap();
+        assert!(
+            DocumentService::get(documents[0].id, manager.id, &manager.role, &pool)
+                .await
+                .is_ok()
+        );
+
+        cleanup_test_db(&pool).await;
+        std::fs::remove_dir_all(root).ok();
+    }
+
+    #[actix_rt::test]
+    #[serial]
+    async fn test_templates_over_http() {
+        let pool = setup_test_db().await;
+        let tokens = test_token_service();
+        let (root, storage) = temp_storage();
+        let admin = create_user("admin@example.com", UserRole::Admin, &pool).await;
+        let manager = create_user("pm@example.com", UserRole::ProjectManager, &pool).await;
+        let developer = create_user("dev@example.com", UserRole::Developer, &pool).await;
+        let admin_bearer = format!("Bearer {}", tokens.issue(&admin).unwrap().token);
+        let manager_bearer = format!("Bearer {}", tokens.issue(&manager).unwrap().token);
+        let developer_bearer = format!("Bearer {}", tokens.issue(&developer).unwrap().token);
+        let project = create_project(&audit_as(manager.id), &pool).await;
+
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(pool.clone()))
+                .app_data(web::Data::new(tokens))
+                .app_data(web::Data::from(storage))
+                .configure(routes::config),
+        )
+        .await;
+
+        let body = json!({
+            "kind": "phase_report",
+            "name": "Short report",
+            "format": "markdown",
+            "body": "# {{project.name}} in {{phase}}",
+        });
+        let req = test::TestRequest::post()
+            .uri("/api/document-templates")
+            .insert_header(("Authorization", manager_bearer.clone()))
+            .set_json(&body)
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 403);
+
+        let req = test::TestRequest::post()
+            .uri("/api/document-templates")
+            .insert_header(("Authorization", admin_bearer.clone()))
+            .set_json(json!({
+                "kind": "phase_report",
+                "name": "Broken",
+                "format": "markdown",
+                "body": "{{#tasks}}",
+            }))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 422);
+        let problem: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(problem["errors"][0]["field"], "body");
+
+        let req = test::TestRequest::post()
+            .uri("/api/document-templates")
+            .insert_header(("Authorization", admin_bearer.clone()))
+            .set_json(&body)
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 201);
+        let first: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(first["active"], true);
+
+        // A second active template takes over from the first
+        let req = test::TestRequest::post()
+            .uri("/api/document-templates")
+            .insert_header(("Authorization", admin_bearer.clone()))
+            .set_json(json!({
+                "kind": "phase_report",
+                "name": "Shorter report",
+                "format": "markdown",
+                "body": "{{project.name}}",
+            }))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 201);
+        let req = test::TestRequest::get()
+            .uri("/api/document-templates?kind=phase_report")
+            .insert_header(("Authorization", developer_bearer.clone()))
+            .to_request();
+        let templates: Vec<serde_json::Value> =
+            test::read_body_json(test::call_service(&app, req).await).await;
+        let active: Vec<_> = templates
+            .iter()
+            .map(|t| (t["name"].as_str().unwrap(), t["active"].as_bool().unwrap()))
+            .collect();
+        assert_eq!(
+            active,
+            vec![("Shorter report", true), ("Short report", false)]
+        );
+
+        let req = test::TestRequest::patch()
+            .uri(&format!(
+                "/api/document-templates/{}",
+                first["id"].as_str().unwrap()
+            ))
+            .insert_header(("Authorization", admin_bearer.clone()))
+            .insert_header(("If-Match", "\"1\""))
+            .set_json(json!({ "active": true }))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 412);
+        let req = test::TestRequest::patch()
+            .uri(&format!(
+                "/api/document-templates/{}",
+                first["id"].as_str().unwrap()
+            ))
+            .insert_header(("Authorization", admin_bearer.clone()))
+            .insert_header(("If-Match", "\"2\""))
+            .set_json(json!({ "active": true }))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 200);
+        assert_eq!(resp.headers().get("etag").unwrap(), "\"3\"");
+
+        let req = test::TestRequest::get()
+            .uri("/api/document-templates/built-in/requirements_specification")
+            .insert_header(("Authorization", developer_bearer.clone()))
+            .to_request();
+        let built_in: serde_json::Value =
+            test::read_body_json(test::call_service(&app, req).await).await;
+        assert_eq!(built_in["format"], "markdown");
+        assert!(built_in["body"]
+            .as_str()
+            .unwrap()
+            .starts_with("# {{title}}: {{project.name}}"));
+
+        let request = json!({
+            "project_id": project.id,
+            "kind": "phase_report",
+            "format": "markdown",
+        });
+        let req = test::TestRequest::post()
+            .uri("/api/documents/generate")
+            .insert_header(("Authorization", developer_bearer))
+            .set_json(&request)
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 403);
+        let req = test::TestRequest::post()
+            .uri("/api/documents/generate")
+            .insert_header(("Authorization", manager_bearer.clone()))
+            .set_json(&request)
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 201);
+        let document: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(document["title"], "Phase Report (MD)");
+
+        let req = test::TestRequest::get()
+            .uri(&format!(
+                "/api/documents/{}/content",
+                document["id"].as_str().unwrap()
+            ))
+            .insert_header(("Authorization", manager_bearer))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(
+            test::read_body(resp).await,
+            "# Billing \\| Phase 2 in Proposal".as_bytes()
+        );
+
+        cleanup_test_db(&pool).await;
+        std::fs::remove_dir_all(root).ok();
+    }
+}
@@ -1,5 +1,25 @@
+use crate::models::audit::AuditContext;
+use crate::models::project::{Project, ProjectCreate};
+use crate::models::resource::ResourceCreate;
+use crate::models::task::{Task, TaskCreate};
+use crate::models::user::{User, UserCreate, UserRole};
+use crate::services::email_service::EmailService;
+use crate::services::job_service::JobContext;
+use crate::services::project_service::ProjectService;
+use crate::services::resource_service::ResourceService;
+use crate::services::storage_service::LocalStorage;
+use crate::services::task_service::TaskService;
+use crate::services::token_service::TokenService;
+use crate::services::user_service::UserService;
+use crate::services::webhook_service::WebhookService;
+use bigdecimal::BigDecimal;
+use chrono::{Duration, TimeZone, Utc};
 use dotenv::dotenv;
 use sqlx::{postgres::PgPoolOptions, Executor, PgPool};
+use std::path::Path;
+use std::sync::Arc;
+use uuid::Uuid;
+
 pub async fn setup_test_db() -> PgPool {
     dotenv().ok();
     let database_url = std::env::var("DATABASE_URL_TEST").unwrap_or_else(|_| {
@@ -43,12 +63,134 @@ pub async fn setup_test_db() -> PgPool {
 
 pub async fn cleanup_test_db(pool: &PgPool) {
     // Clean up all tables after tests
-    let tables = vec!["tasks", "projects", "resources", "users"];
+    let tables = vec![
+        "audit_log",
+        "jobs",
+        "tasks",
+        "projects",
+        "resources",
+        "users",
+    ];
     for table in tables {
         let query = format!("TRUNCATE TABLE {} CASCADE", table);
         sqlx::query(&query)
             .execute(pool)
             .await
-            .expect(&format!("Failed to truncate {}", table));
+            .unwrap_or_else(|_| panic!("Failed to truncate {}", table));
+    }
+}
+
+/// Token service backed by the fixture keys, signing with the Ed25519 key.
+pub fn test_token_service() -> TokenService {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("src/tests/fixtures/jwt_keys");
+    TokenService::from_dir(&dir, Some("test-ed25519")).expect("Failed to load test signing keys")
+}
+
+/// Audit context attributing changes to the given user.
+pub fn audit_as(user_id: Uuid) -> AuditContext {
+    AuditContext {
+        actor_id: Some(user_id),
+        request_id: None,
+    }
+}
+
+/// A user with the password `password123`.
+pub async fn create_user(email: &str, role: UserRole, pool: &PgPool) -> User {
+    create_named_user(email, "Test User", role, pool).await
+}
+
+pub async fn create_named_user(
+    email: &str,
+    full_name: &str,
+    role: UserRole,
+    pool: &PgPool,
+) -> User {
+    UserService::create(
+        UserCreate {
+            email: email.to_string(),
+            password: "password123".to_string(),
+            full_name: full_name.to_string(),
+            role,
+        },
+        &AuditContext::default(),
+        pool,
+    )
+    .await
+    .unwrap()
+}
+
+/// A full-time resource at 60/hour sharing `user`'s email, so tasks assigned
+/// to it are the user's. Returns its id.
+pub async fn create_resource(user: &User, pool: &PgPool) -> Uuid {
+    ResourceService::create(
+        ResourceCreate {
+            name: user.full_name.clone(),
+            email: user.email.clone(),
+            role: "Engineer".to_string(),
+            skills: vec![],
+            availability: BigDecimal::from(100),
+            hourly_rate: BigDecimal::from(60),
+        },
+        &AuditContext::default(),
+        pool,
+    )
+    .await
+    .unwrap()
+    .id
+}
+
+/// A project running through the second half of 2025, with no budget,
+/// owned by the audit context's actor.
+pub async fn create_project(name: &str, audit: &AuditContext, pool: &PgPool) -> Project {
+    ProjectService::create(
+        ProjectCreate {
+            name: name.to_string(),
+            description: None,
+            start_date: Utc.with_ymd_and_hms(2025, 7, 1, 0, 0, 0).unwrap(),
+            end_date: Utc.with_ymd_and_hms(2025, 12, 31, 0, 0, 0).unwrap(),
+            budget: BigDecimal::from(0),
+            client_id: None,
+        },
+        audit,
+        pool,
+    )
+    .await
+    .unwrap()
+}
+
+/// An unassigned five-day task starting on 1 July 2025, inside the window of
+/// `create_project`. Change other fields with struct update syntax.
+pub fn new_task(name: &str, project_id: Uuid) -> TaskCreate {
+    let start = Utc.with_ymd_and_hms(2025, 7, 1, 0, 0, 0).unwrap();
+    TaskCreate {
+        name: name.to_string(),
+        description: None,
+        project_id,
+        assigned_to: None,
+        start_date: start,
+        end_date: start + Duration::days(5),
+        dependencies: vec![],
+        parent_id: None,
+        wbs: None,
+        milestone: false,
+    }
+}
+
+/// Creates `task`, usually `new_task` with some fields changed.
+pub async fn create_task(task: TaskCreate, audit: &AuditContext, pool: &PgPool) -> Task {
+    TaskService::create(task, audit, pool).await.unwrap()
+}
+
+/// Runs jobs against `pool` and sends their emails through `email`, with
+/// trash purging off and documents in a temporary directory.
+pub fn job_context(pool: &PgPool, email: EmailService) -> JobContext {
+    JobContext {
+        pool: pool.clone(),
+        email: Arc::new(email),
+        purge: None,
+        http: WebhookService::http_client(),
+        storage: Arc::new(LocalStorage::new(
+            std::env::temp_dir().join("job-test-documents"),
+        )),
     }
 }
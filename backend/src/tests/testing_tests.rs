@@ -0,0 +1,625 @@
+#[cfg(test)]
+mod tests {
+    use crate::errors::ServiceError;
+    use crate::models::calendar::GateReviewCreate;
+    use crate::models::lifecycle::{LifecyclePhase, PhaseTransition};
+    use crate::models::patch::Patch;
+    use crate::models::requirement::{Requirement, RequirementCreate, RequirementType};
+    use crate::models::testing::{
+        TestCase, TestCaseCreate, TestCaseFilter, TestCaseUpdate, TestLevel, TestOutcome,
+        TestResultRecord, TestRun, TestRunCreate, TestStep, TestSuite, TestSuiteCreate,
+    };
+    use crate::models::traceability::{TraceEntity, TraceLinkCreate, TraceLinkFilter};
+    use crate::models::user::{User, UserRole};
+    use crate::routes;
+    use crate::services::lifecycle_service::LifecycleService;
+    use crate::services::requirement_service::RequirementService;
+    use crate::services::task_service::TaskService;
+    use crate::services::testing_service::TestingService;
+    use crate::services::traceability_service::TraceabilityService;
+    use crate::tests::test_helpers::{
+        audit_as, cleanup_test_db, create_project, create_resource, create_task, create_user,
+        new_task, setup_test_db, test_token_service,
+    };
+    use actix_web::{test, web, App};
+    use chrono::{TimeZone, Utc};
+    use serde_json::{json, Value};
+    use serial_test::serial;
+    use sqlx::PgPool;
+    use uuid::Uuid;
+
+    async fn create_suite(project_id: Uuid, name: &str, user: &User, pool: &PgPool) -> TestSuite {
+        TestingService::create_suite(
+            TestSuiteCreate {
+                project_id,
+                name: name.to_string(),
+                description: None,
+                level: TestLevel::System,
+            },
+            user.id,
+            &user.role,
+            &audit_as(user.id),
+            pool,
+        )
+        .await
+        .unwrap()
+    }
+
+    fn step(action: &str, expected_result: &str) -> TestStep {
+        TestStep {
+            action: action.to_string(),
+            expected_result: expected_result.to_string(),
+        }
+    }
+
+    async fn create_case(suite_id: Uuid, title: &str, user: &User, pool: &PgPool) -> TestCase {
+        TestingService::create_case(
+            TestCaseCreate {
+                suite_id,
+                title: title.to_string(),
+                description: None,
+                preconditions: None,
+                steps: vec![step("Run it", "It works")],
+                expected_result: None,
+            },
+            user.id,
+            &user.role,
+            &audit_as(user.id),
+            pool,
+        )
+        .await
+        .unwrap()
+    }
+
+    async fn create_run(project_id: Uuid, build: &str, user: &User, pool: &PgPool) -> TestRun {
+        TestingService::create_run(
+            TestRunCreate {
+                project_id,
+                build: build.to_string(),
+                cycle: Some("System test cycle 1".to_string()),
+                notes: None,
+            },
+            user.id,
+            &user.role,
+            &audit_as(user.id),
+            pool,
+        )
+        .await
+        .unwrap()
+    }
+
+    async fn record(
+        run_id: Uuid,
+        test_case_id: Uuid,
+        outcome: TestOutcome,
+        user: &User,
+        pool: &PgPool,
+    ) -> Result<(), ServiceError> {
+        TestingService::record_result(
+            run_id,
+            TestResultRecord {
+                test_case_id,
+                outcome,
+                notes: None,
+            },
+            user.id,
+            &user.role,
+            &audit_as(user.id),
+            pool,
+        )
+        .await
+        .map(|_| ())
+    }
+
+    async fn create_requirement(
+        project_id: Uuid,
+        title: &str,
+        user: &User,
+        pool: &PgPool,
+    ) -> Requirement {
+        RequirementService::create(
+            RequirementCreate {
+                project_id,
+                title: title.to_string(),
+                description: None,
+                requirement_type: RequirementType::Functional,
+                priority: None,
+                status: None,
+                acceptance_criteria: None,
+            },
+            user.id,
+            &user.role,
+            &audit_as(user.id),
+            pool,
+        )
+        .await
+        .unwrap()
+    }
+
+    async fn link(
+        source_type: TraceEntity,
+        source_id: Uuid,
+        target_type: TraceEntity,
+        target_id: Uuid,
+        user: &User,
+        pool: &PgPool,
+    ) {
+        TraceabilityService::link(
+            TraceLinkCreate {
+                source_type,
+                source_id,
+                target_type,
+                target_id,
+            },
+            user.id,
+            &user.role,
+            &audit_as(user.id),
+            pool,
+        )
+        .await
+        .unwrap();
+    }
+
+    #[actix_rt::test]
+    #[serial]
+    async fn test_test_cases_keep_their_steps_in_order() {
+        let pool = setup_test_db().await;
+        let qa = create_user("qa@example.com", UserRole::QaEngineer, &pool).await;
+        let audit = audit_as(qa.id);
+        let project = create_project("Billing", &audit, &pool).await;
+        let suite = create_suite(project.id, "Invoicing", &qa, &pool).await;
+
+        let case = TestingService::create_case(
+            TestCaseCreate {
+                suite_id: suite.id,
+                title: "Reject invoices without an amount".to_string(),
+                description: None,
+                preconditions: Some("Logged in as a clerk".to_string()),
+                steps: vec![
+                    step("Open a new invoice", "The form is empty"),
+                    step(
+                        "Submit without an amount",
+                        "\"Amount is required\" is shown",
+                    ),
+                ],
+                expected_result: Some("No invoice is created".to_string()),
+            },
+            qa.id,
+            &qa.role,
+            &audit,
+            &pool,
+        )
+        .await
+        .unwrap();
+        assert_eq!(case.project_id, project.id);
+        assert_eq!(case.steps.len(), 2);
+        assert_eq!(case.steps[1].action, "Submit without an amount");
+
+        // Steps given replace all the steps; other fields are kept
+        let updated = TestingService::update_case(
+            case.id,
+            TestCaseUpdate {
+                steps: Patch::Value(vec![
+                    step("Open a new invoice", "The form is empty"),
+                    step("Enter a zero amount", "The field is marked"),
+                    step("Submit", "\"Amount must be positive\" is shown"),
+                ]),
+                ..Default::default()
+            },
+            Some(1),
+            qa.id,
+            &qa.role,
+            &audit,
+            &pool,
+        )
+        .await
+        .unwrap();
+        assert_eq!(updated.version, 2);
+        let actions: Vec<&str> = updated.steps.iter().map(|s| s.action.as_str()).collect();
+        assert_eq!(
+            actions,
+            vec!["Open a new invoice", "Enter a zero amount", "Submit"]
+        );
+        assert_eq!(
+            updated.preconditions.as_deref(),
+            Some("Logged in as a clerk")
+        );
+
+        let blank = TestingService::update_case(
+            case.id,
+            TestCaseUpdate {
+                steps: Patch::Value(vec![step("", "Something")]),
+                ..Default::default()
+            },
+            None,
+            qa.id,
+            &qa.role,
+            &audit,
+            &pool,
+        )
+        .await;
+        assert!(matches!(blank, Err(ServiceError::ValidationError(_))));
+
+        // Deleting the suite takes its cases and their trace links along
+        let requirement = create_requirement(project.id, "Invoices", &qa, &pool).await;
+        link(
+            TraceEntity::Requirement,
+            requirement.id,
+            TraceEntity::TestCase,
+            case.id,
+            &qa,
+            &pool,
+        )
+        .await;
+        TestingService::delete_suite(suite.id, None, qa.id, &qa.role, &audit, &pool)
+            .await
+            .unwrap();
+        let cases = TestingService::list_cases(
+            &TestCaseFilter {
+                project_id: Some(project.id),
+                suite_id: None,
+            },
+            qa.id,
+            &qa.role,
+            &pool,
+        )
+        .await
+        .unwrap();
+        assert!(cases.is_empty());
+        let links = TraceabilityService::links(
+            &TraceLinkFilter {
+                project_id: Some(project.id),
+                entity_type: None,
+                entity_id: None,
+            },
+            qa.id,
+            &qa.role,
+            &pool,
+        )
+        .await
+        .unwrap();
+        assert!(links.is_empty());
+
+        cleanup_test_db(&pool).await;
+    }
+
+    #[actix_rt::test]
+    #[serial]
+    async fn test_recording_a_result_again_replaces_it() {
+        let pool = setup_test_db().await;
+        let qa = create_user("qa@example.com", UserRole::QaEngineer, &pool).await;
+        let manager = create_user("pm@example.com", UserRole::ProjectManager, &pool).await;
+        let audit = audit_as(qa.id);
+        let project = create_project("Billing", &audit, &pool).await;
+        let other = create_project("Payroll", &audit, &pool).await;
+        let suite = create_suite(project.id, "Invoicing", &qa, &pool).await;
+        let totals = create_case(suite.id, "Totals add up", &qa, &pool).await;
+        let rounding = create_case(suite.id, "Rounding", &qa, &pool).await;
+        let other_suite = create_suite(other.id, "Payslips", &qa, &pool).await;
+        let payslip = create_case(other_suite.id, "Payslip totals", &qa, &pool).await;
+        let run = create_run(project.id, "2.3.0-rc1", &qa, &pool).await;
+
+        record(run.id, totals.id, TestOutcome::Failed, &qa, &pool)
+            .await
+            .unwrap();
+        record(run.id, rounding.id, TestOutcome::Passed, &qa, &pool)
+            .await
+            .unwrap();
+        record(run.id, totals.id, TestOutcome::Passed, &manager, &pool)
+            .await
+            .unwrap();
+        let err = record(run.id, payslip.id, TestOutcome::Passed, &qa, &pool).await;
+        assert!(matches!(err, Err(ServiceError::ValidationError(_))));
+
+        let details = TestingService::get_run(run.id, qa.id, &qa.role, &pool)
+            .await
+            .unwrap();
+        assert_eq!(details.results.len(), 2);
+        let result = details
+            .results
+            .iter()
+            .find(|result| result.test_case_id == totals.id)
+            .unwrap();
+        assert_eq!(result.outcome, TestOutcome::Passed);
+        assert_eq!(result.tester_id, Some(manager.id));
+        assert_eq!(details.summary.passed, 2);
+        assert_eq!(details.summary.pass_rate, Some(100.0));
+
+        cleanup_test_db(&pool).await;
+    }
+
+    #[actix_rt::test]
+    #[serial]
+    async fn test_report_counts_the_latest_result_per_requirement() {
+        let pool = setup_test_db().await;
+        let manager = create_user("pm@example.com", UserRole::ProjectManager, &pool).await;
+        let audit = audit_as(manager.id);
+        let project = create_project("Billing", &audit, &pool).await;
+        let invoices = create_requirement(project.id, "Invoices", &manager, &pool).await;
+        let reminders = create_requirement(project.id, "Reminders", &manager, &pool).await;
+        create_requirement(project.id, "Reports", &manager, &pool).await;
+        let scheduler =
+            create_task(new_task("Reminder scheduler", project.id), &audit, &pool).await;
+        let suite = create_suite(project.id, "Invoicing", &manager, &pool).await;
+        let totals = create_case(suite.id, "Totals add up", &manager, &pool).await;
+        let schedule = create_case(suite.id, "Reminders go out", &manager, &pool).await;
+        let export = create_case(suite.id, "Export", &manager, &pool).await;
+        link(
+            TraceEntity::Requirement,
+            invoices.id,
+            TraceEntity::TestCase,
+            totals.id,
+            &manager,
+            &pool,
+        )
+        .await;
+        link(
+            TraceEntity::Requirement,
+            reminders.id,
+            TraceEntity::Task,
+            scheduler.id,
+            &manager,
+            &pool,
+        )
+        .await;
+        link(
+            TraceEntity::Task,
+            scheduler.id,
+            TraceEntity::TestCase,
+            schedule.id,
+            &manager,
+            &pool,
+        )
+        .await;
+
+        let first = create_run(project.id, "2.3.0-rc1", &manager, &pool).await;
+        record(first.id, totals.id, TestOutcome::Failed, &manager, &pool)
+            .await
+            .unwrap();
+        record(first.id, schedule.id, TestOutcome::Blocked, &manager, &pool)
+            .await
+            .unwrap();
+        let second = create_run(project.id, "2.3.0-rc2", &manager, &pool).await;
+        record(second.id, totals.id, TestOutcome::Passed, &manager, &pool)
+            .await
+            .unwrap();
+
+        let report = TestingService::report(project.id, manager.id, &manager.role, &pool)
+            .await
+            .unwrap();
+        assert_eq!(report.overall.test_cases, 3);
+        assert_eq!(report.overall.passed, 1);
+        assert_eq!(report.overall.blocked, 1);
+        assert_eq!(report.overall.not_run, 1);
+        assert_eq!(report.overall.pass_rate, Some(50.0));
+        let codes: Vec<&str> = report
+            .requirements
+            .iter()
+            .map(|r| r.code.as_str())
+            .collect();
+        assert_eq!(codes, vec!["REQ-001", "REQ-002", "REQ-003"]);
+        // The failure in the first run is superseded by the pass in the second
+        assert_eq!(report.requirements[0].tally.pass_rate, Some(100.0));
+        // Covered through the task that implements it
+        assert_eq!(report.requirements[1].tally.blocked, 1);
+        assert!(!report.requirements[1].untested);
+        assert!(report.requirements[2].untested);
+        assert_eq!(report.requirements[2].tally.pass_rate, None);
+        assert_eq!(export.project_id, project.id);
+
+        let matrix = TraceabilityService::matrix(project.id, manager.id, &manager.role, &pool)
+            .await
+            .unwrap();
+        assert_eq!(matrix.rows[0].test_cases[0].title, "Totals add up");
+        assert_eq!(matrix.rows[1].test_cases[0].title, "Reminders go out");
+        assert_eq!(matrix.untested, 1);
+
+        // A trashed task no longer leads to the cases that test it
+        TaskService::delete(scheduler.id, None, &audit, &pool)
+            .await
+            .unwrap();
+        let matrix = TraceabilityService::matrix(project.id, manager.id, &manager.role, &pool)
+            .await
+            .unwrap();
+        assert!(matrix.rows[1].untested);
+        assert_eq!(matrix.untested, 2);
+
+        cleanup_test_db(&pool).await;
+    }
+
+    #[actix_rt::test]
+    #[serial]
+    async fn test_gate_review_pass_rate_is_an_exit_criterion() {
+        let pool = setup_test_db().await;
+        let manager = create_user("pm@example.com", UserRole::ProjectManager, &pool).await;
+        let audit = audit_as(manager.id);
+        let project = create_project("Billing", &audit, &pool).await;
+        let suite = create_suite(project.id, "Invoicing", &manager, &pool).await;
+        let totals = create_case(suite.id, "Totals add up", &manager, &pool).await;
+        let rounding = create_case(suite.id, "Rounding", &manager, &pool).await;
+        LifecycleService::schedule_gate_review(
+            project.id,
+            GateReviewCreate {
+                phase: LifecyclePhase::Deployment,
+                scheduled_at: Utc.with_ymd_and_hms(2025, 11, 3, 10, 0, 0).unwrap(),
+                duration_minutes: 60,
+                location: None,
+                notes: None,
+                min_pass_rate: Some(90),
+                no_open_critical_defects: false,
+            },
+            &audit,
+            &pool,
+        )
+        .await
+        .unwrap();
+        let transition = |phase| PhaseTransition {
+            project_id: project.id,
+            phase,
+            description: "Next phase".to_string(),
+            attachments: None,
+            documents: vec![],
+        };
+
+        LifecycleService::transition_phase(
+            transition(LifecyclePhase::Testing),
+            None,
+            &audit,
+            &pool,
+        )
+        .await
+        .unwrap();
+        let result = LifecycleService::transition_phase(
+            transition(LifecyclePhase::Deployment),
+            None,
+            &audit,
+            &pool,
+        )
+        .await;
+        assert!(matches!(result, Err(ServiceError::UnprocessableEntity(_))));
+
+        let run = create_run(project.id, "2.3.0", &manager, &pool).await;
+        record(run.id, totals.id, TestOutcome::Passed, &manager, &pool)
+            .await
+            .unwrap();
+        record(run.id, rounding.id, TestOutcome::Failed, &manager, &pool)
+            .await
+            .unwrap();
+        let result = LifecycleService::transition_phase(
+            transition(LifecyclePhase::Deployment),
+            None,
+            &audit,
+            &pool,
+        )
+        .await;
+        assert!(matches!(result, Err(ServiceError::UnprocessableEntity(_))));
+
+        let retest = create_run(project.id, "2.3.1", &manager, &pool).await;
+        record(retest.id, rounding.id, TestOutcome::Passed, &manager, &pool)
+            .await
+            .unwrap();
+        let details = LifecycleService::transition_phase(
+            transition(LifecyclePhase::Deployment),
+            None,
+            &audit,
+            &pool,
+        )
+        .await
+        .unwrap();
+        assert_eq!(details.phase, LifecyclePhase::Deployment);
+
+        cleanup_test_db(&pool).await;
+    }
+
+    #[actix_rt::test]
+    #[serial]
+    async fn test_test_management_over_http() {
+        let pool = setup_test_db().await;
+        let tokens = test_token_service();
+        let qa = create_user("qa@example.com", UserRole::QaEngineer, &pool).await;
+        let developer = create_user("dev@example.com", UserRole::Developer, &pool).await;
+        let developer_resource = create_resource(&developer, &pool).await;
+        let qa_bearer = format!("Bearer {}", tokens.issue(&qa).unwrap().token);
+        let developer_bearer = format!("Bearer {}", tokens.issue(&developer).unwrap().token);
+        let audit = audit_as(qa.id);
+        let project = create_project("Billing", &audit, &pool).await;
+        let task = create_task(new_task("Send invoices", project.id), &audit, &pool).await;
+        sqlx::query!(
+            "UPDATE tasks SET assigned_to = ARRAY[$1::uuid] WHERE id = $2",
+            developer_resource,
+            task.id
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(pool.clone()))
+                .app_data(web::Data::new(tokens))
+                .configure(routes::config),
+        )
+        .await;
+
+        let body = json!({
+            "project_id": project.id,
+            "name": "Acceptance",
+            "level": "acceptance"
+        });
+        let req = test::TestRequest::post()
+            .uri("/api/test-suites")
+            .insert_header(("Authorization", developer_bearer.clone()))
+            .set_json(&body)
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 403);
+
+        let req = test::TestRequest::post()
+            .uri("/api/test-suites")
+            .insert_header(("Authorization", qa_bearer.clone()))
+            .set_json(&body)
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 201);
+        let suite: Value = test::read_body_json(resp).await;
+
+        let req = test::TestRequest::post()
+            .uri("/api/test-cases")
+            .insert_header(("Authorization", qa_bearer.clone()))
+            .set_json(json!({
+                "suite_id": suite["id"],
+                "title": "Clerk sends an invoice",
+                "steps": [{ "action": "Send the invoice", "expected_result": "It is sent" }]
+            }))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 201);
+        let case: Value = test::read_body_json(resp).await;
+        assert_eq!(case["steps"][0]["expected_result"], "It is sent");
+
+        let req = test::TestRequest::post()
+            .uri("/api/test-runs")
+            .insert_header(("Authorization", qa_bearer.clone()))
+            .set_json(json!({ "project_id": project.id, "build": "2.3.0", "cycle": "UAT" }))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 201);
+        let run: Value = test::read_body_json(resp).await;
+
+        // Anyone on the project may execute cases
+        let req = test::TestRequest::post()
+            .uri(&format!(
+                "/api/test-runs/{}/results",
+                run["id"].as_str().unwrap()
+            ))
+            .insert_header(("Authorization", developer_bearer.clone()))
+            .set_json(json!({ "test_case_id": case["id"], "outcome": "failed" }))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 200);
+        let result: Value = test::read_body_json(resp).await;
+        assert_eq!(result["tester_id"], json!(developer.id));
+
+        let req = test::TestRequest::get()
+            .uri(&format!("/api/test-runs/{}", run["id"].as_str().unwrap()))
+            .insert_header(("Authorization", developer_bearer.clone()))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 200);
+        let details: Value = test::read_body_json(resp).await;
+        assert_eq!(details["build"], "2.3.0");
+        assert_eq!(details["summary"]["failed"], 1);
+
+        let req = test::TestRequest::get()
+            .uri(&format!("/api/projects/{}/test-report", project.id))
+            .insert_header(("Authorization", developer_bearer))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 200);
+        let report: Value = test::read_body_json(resp).await;
+        assert_eq!(report["overall"]["test_cases"], 1);
+        assert_eq!(report["overall"]["pass_rate"], 0.0);
+
+        cleanup_test_db(&pool).await;
+    }
+}
@@ -0,0 +1,134 @@
+#[cfg(test)]
+mod tests {
+    use crate::errors::ServiceError;
+    use crate::models::auth::Claims;
+    use crate::models::user::{User, UserRole};
+    use crate::routes;
+    use crate::services::token_service::TokenService;
+    use crate::tests::test_helpers::test_token_service;
+    use actix_web::{web, App};
+    use chrono::Utc;
+    use jsonwebtoken::jwk::JwkSet;
+    use jsonwebtoken::{
+        decode, decode_header, encode, Algorithm, DecodingKey, EncodingKey, Header, Validation,
+    };
+    use std::path::{Path, PathBuf};
+    use uuid::Uuid;
+
+    fn keys_dir() -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("src/tests/fixtures/jwt_keys")
+    }
+
+    fn test_user() -> User {
+        User {
+            id: Uuid::new_v4(),
+            email: "tokens@example.com".to_string(),
+            password_hash: None,
+            full_name: "Token User".to_string(),
+            role: UserRole::ProjectManager,
+            deactivated_at: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            version: 1,
+        }
+    }
+
+    #[test]
+    fn test_issue_and_verify_round_trip() {
+        let tokens = test_token_service();
+        let user = test_user();
+
+        let response = tokens.issue(&user).unwrap();
+        let header = decode_header(&response.token).unwrap();
+        assert_eq!(header.alg, Algorithm::EdDSA);
+        assert_eq!(header.kid.as_deref(), Some("test-ed25519"));
+
+        let claims = tokens.verify(&response.token).unwrap();
+        assert_eq!(claims.sub, user.id.to_string());
+        assert_eq!(claims.email, user.email);
+    }
+
+    #[test]
+    fn test_tokens_survive_key_rotation() {
+        let old = TokenService::from_dir(&keys_dir(), Some("test-ed25519")).unwrap();
+        let rotated = TokenService::from_dir(&keys_dir(), Some("test-rsa")).unwrap();
+        let user = test_user();
+
+        // Tokens signed before the rotation still verify afterwards
+        let before = old.issue(&user).unwrap();
+        assert!(rotated.verify(&before.token).is_ok());
+
+        let after = rotated.issue(&user).unwrap();
+        assert_eq!(decode_header(&after.token).unwrap().alg, Algorithm::RS256);
+        assert!(rotated.verify(&after.token).is_ok());
+
+        // Once the old key is removed its tokens are rejected
+        let rsa_pem = std::fs::read(keys_dir().join("test-rsa.pem")).unwrap();
+        let rsa_only =
+            TokenService::from_pem_keys(&[("test-rsa".to_string(), rsa_pem)], "test-rsa").unwrap();
+        assert!(matches!(
+            rsa_only.verify(&before.token),
+            Err(ServiceError::Unauthorized(_))
+        ));
+    }
+
+    #[test]
+    fn test_expired_token_is_rejected() {
+        let tokens = test_token_service();
+        let pem = std::fs::read(keys_dir().join("test-ed25519.pem")).unwrap();
+
+        let claims = Claims {
+            sub: Uuid::new_v4().to_string(),
+            email: "expired@example.com".to_string(),
+            role: "\"Developer\"".to_string(),
+            exp: (Utc::now().timestamp() - 3600) as usize,
+        };
+        let mut header = Header::new(Algorithm::EdDSA);
+        header.kid = Some("test-ed25519".to_string());
+        let token = encode(&header, &claims, &EncodingKey::from_ed_pem(&pem).unwrap()).unwrap();
+
+        match tokens.verify(&token) {
+            Err(ServiceError::TokenExpired) => {}
+            other => panic!(
+                "expected expired token error, got {:?}",
+                other.map(|c| c.sub)
+            ),
+        }
+    }
+
+    #[test]
+    fn test_invalid_key_configuration_fails_at_load() {
+        // Two keys and no active kid is ambiguous
+        assert!(TokenService::from_dir(&keys_dir(), None).is_err());
+        assert!(TokenService::from_dir(&keys_dir(), Some("missing")).is_err());
+        assert!(
+            TokenService::from_pem_keys(&[("bad".to_string(), b"not a key".to_vec())], "bad")
+                .is_err()
+        );
+    }
+
+    #[actix_rt::test]
+    async fn test_jwks_endpoint_verifies_issued_tokens() {
+        let tokens = test_token_service();
+        let token = tokens.issue(&test_user()).unwrap().token;
+
+        let app = actix_web::test::init_service(
+            App::new()
+                .app_data(web::Data::new(tokens))
+                .configure(routes::config),
+        )
+        .await;
+        let req = actix_web::test::TestRequest::get()
+            .uri("/.well-known/jwks.json")
+            .to_request();
+        let jwks: JwkSet = actix_web::test::call_and_read_body_json(&app, req).await;
+        assert_eq!(jwks.keys.len(), 2);
+        assert!(jwks.find("test-rsa").is_some());
+
+        // Another service verifies the token using only the published key set
+        let kid = decode_header(&token).unwrap().kid.unwrap();
+        let key = DecodingKey::from_jwk(jwks.find(&kid).unwrap()).unwrap();
+        let claims = decode::<Claims>(&token, &key, &Validation::new(Algorithm::EdDSA));
+        assert!(claims.is_ok());
+    }
+}
@@ -0,0 +1,285 @@
+#[cfg(test)]
+mod tests {
+    use crate::errors::ServiceError;
+    use crate::models::audit::{AuditAction, AuditContext, AuditEntity, AuditFilter};
+    use crate::models::pagination::PageParams;
+    use crate::models::project::ProjectFilter;
+    use crate::models::task::TaskFilter;
+    use crate::models::user::UserRole;
+    use crate::models::webhook::{WebhookCreate, WebhookEvent};
+    use crate::routes;
+    use crate::services::audit_service::AuditService;
+    use crate::services::project_service::ProjectService;
+    use crate::services::search_service::SearchService;
+    use crate::services::task_service::TaskService;
+    use crate::services::trash_service::TrashService;
+    use crate::services::webhook_service::WebhookService;
+    use crate::tests::test_helpers::{
+        audit_as, cleanup_test_db, create_project, create_task, create_user, new_task,
+        setup_test_db, test_token_service,
+    };
+    use actix_web::{test, web, App};
+    use chrono::{Duration, Utc};
+    use serde_json::Value;
+    use serial_test::serial;
+    use sqlx::PgPool;
+    use uuid::Uuid;
+
+    async fn task_trash(pool: &PgPool) -> Vec<Uuid> {
+        TaskService::get_trash(&PageParams::default(), &TaskFilter::default(), pool)
+            .await
+            .unwrap()
+            .items
+            .iter()
+            .map(|task| task.id)
+            .collect()
+    }
+
+    #[actix_rt::test]
+    #[serial]
+    async fn test_deleted_projects_are_hidden_and_restored_with_their_tasks() {
+        let pool = setup_test_db().await;
+        let pm = create_user("pm@example.com", UserRole::ProjectManager, &pool).await;
+        let audit = audit_as(pm.id);
+
+        let project = create_project("Quarterly Plan", &audit, &pool).await;
+        let kept = create_task(new_task("Cascaded Task", project.id), &audit, &pool).await;
+        let removed_earlier =
+            create_task(new_task("Deleted Earlier", project.id), &audit, &pool).await;
+        TaskService::delete(removed_earlier.id, None, &audit, &pool)
+            .await
+            .unwrap();
+
+        ProjectService::delete(project.id, None, pm.id, &pm.role, &audit, &pool)
+            .await
+            .unwrap();
+
+        // Gone from every default query
+        assert!(matches!(
+            ProjectService::get_by_id(project.id, &pool).await,
+            Err(ServiceError::NotFound(_))
+        ));
+        assert!(matches!(
+            TaskService::get_by_id(kept.id, &pool).await,
+            Err(ServiceError::NotFound(_))
+        ));
+        let live =
+            ProjectService::get_all(&PageParams::default(), &ProjectFilter::default(), &pool)
+                .await
+                .unwrap();
+        assert_eq!(live.total, 0);
+        assert!(TaskService::get_by_project(project.id, &pool)
+            .await
+            .unwrap()
+            .is_empty());
+        let hits = SearchService::search("Quarterly", None, pm.id, &UserRole::Admin, &pool)
+            .await
+            .unwrap();
+        assert!(hits.projects.is_empty());
+
+        // Trashed rows can no longer be edited or receive new tasks
+        assert!(matches!(
+            ProjectService::delete(project.id, None, pm.id, &pm.role, &audit, &pool).await,
+            Err(ServiceError::NotFound(_))
+        ));
+        assert!(matches!(
+            TaskService::create(new_task("Late Task", project.id), &audit, &pool).await,
+            Err(ServiceError::ValidationError(_))
+        ));
+
+        // Both tasks are in the trash, and so is the project
+        let trash =
+            ProjectService::get_trash(&PageParams::default(), &ProjectFilter::default(), &pool)
+                .await
+                .unwrap();
+        assert_eq!(trash.total, 1);
+        assert!(trash.items[0].deleted_at.is_some());
+        assert_eq!(task_trash(&pool).await.len(), 2);
+
+        // A task trashed with its project comes back only with the project
+        assert!(matches!(
+            TaskService::restore(kept.id, None, &audit, &pool).await,
+            Err(ServiceError::UnprocessableEntity(_))
+        ));
+
+        // Subscribers learn that the project and its task are back
+        WebhookService::create(
+            WebhookCreate {
+                url: "http://127.0.0.1:9/hooks".to_string(),
+                secret: None,
+                events: vec![WebhookEvent::ProjectRestored, WebhookEvent::TaskRestored],
+                description: None,
+                active: true,
+            },
+            &audit,
+            &pool,
+        )
+        .await
+        .unwrap();
+        let restored = ProjectService::restore(project.id, None, &audit, &pool)
+            .await
+            .unwrap();
+        assert!(restored.deleted_at.is_none());
+        assert!(TaskService::get_by_id(kept.id, &pool).await.is_ok());
+        let events = sqlx::query_scalar!(
+            r#"SELECT event as "event: WebhookEvent" FROM webhook_deliveries ORDER BY created_at, event"#
+        )
+        .fetch_all(&pool)
+        .await
+        .unwrap();
+        assert_eq!(
+            events,
+            vec![WebhookEvent::ProjectRestored, WebhookEvent::TaskRestored]
+        );
+        // The task deleted on its own stays in the trash until restored itself
+        assert_eq!(task_trash(&pool).await, vec![removed_earlier.id]);
+        let task = TaskService::restore(removed_earlier.id, None, &audit, &pool)
+            .await
+            .unwrap();
+        assert!(task.deleted_at.is_none());
+        assert!(task_trash(&pool).await.is_empty());
+
+        let filter = AuditFilter {
+            entity_type: Some(AuditEntity::Task),
+            entity_id: Some(kept.id),
+            ..Default::default()
+        };
+        let actions: Vec<_> = AuditService::list(&PageParams::default(), &filter, &pool)
+            .await
+            .unwrap()
+            .items
+            .iter()
+            .map(|entry| entry.action)
+            .collect();
+        assert!(actions.contains(&AuditAction::Delete));
+        assert!(actions.contains(&AuditAction::Restore));
+
+        cleanup_test_db(&pool).await;
+    }
+
+    #[actix_rt::test]
+    #[serial]
+    async fn test_purge_removes_expired_trash_only() {
+        let pool = setup_test_db().await;
+        let audit = AuditContext::default();
+
+        let expired = create_project("Expired Project", &audit, &pool).await;
+        let expired_task = create_task(new_task("Expired Task", expired.id), &audit, &pool).await;
+        let live = create_project("Live Project", &audit, &pool).await;
+        let live_task = create_task(new_task("Live Task", live.id), &audit, &pool).await;
+        let recent_task = create_task(new_task("Recently Deleted", live.id), &audit, &pool).await;
+
+        ProjectService::delete(
+            expired.id,
+            None,
+            Uuid::nil(),
+            &UserRole::Admin,
+            &audit,
+            &pool,
+        )
+        .await
+        .unwrap();
+        sqlx::query!(
+            "UPDATE projects SET deleted_at = NOW() - INTERVAL '40 days' WHERE id = $1",
+            expired.id
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+        TaskService::delete(recent_task.id, None, &audit, &pool)
+            .await
+            .unwrap();
+
+        let summary = TrashService::purge(Utc::now() - Duration::days(30), &pool)
+            .await
+            .unwrap();
+        assert_eq!(summary.projects, 1);
+        assert_eq!(summary.tasks, 1);
+
+        let remaining: Vec<Uuid> = sqlx::query_scalar!("SELECT id FROM tasks ORDER BY name")
+            .fetch_all(&pool)
+            .await
+            .unwrap();
+        assert_eq!(remaining, vec![live_task.id, recent_task.id]);
+        assert!(!remaining.contains(&expired_task.id));
+        assert!(matches!(
+            ProjectService::restore(expired.id, None, &audit, &pool).await,
+            Err(ServiceError::NotFound(_))
+        ));
+
+        let purged = AuditService::history(AuditEntity::Project, expired.id, &pool)
+            .await
+            .unwrap();
+        assert_eq!(purged.last().unwrap().action, AuditAction::Purge);
+
+        cleanup_test_db(&pool).await;
+    }
+
+    #[actix_rt::test]
+    #[serial]
+    async fn test_trash_endpoints() {
+        let pool = setup_test_db().await;
+        let tokens = test_token_service();
+        let pm = create_user("pm@example.com", UserRole::ProjectManager, &pool).await;
+        let developer = create_user("dev@example.com", UserRole::Developer, &pool).await;
+        let pm_token = tokens.issue(&pm).unwrap().token;
+        let dev_token = tokens.issue(&developer).unwrap().token;
+        let project = create_project("Trashed Project", &audit_as(pm.id), &pool).await;
+
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(pool.clone()))
+                .app_data(web::Data::new(tokens))
+                .configure(routes::config),
+        )
+        .await;
+
+        // Only admins and project managers can trash a project
+        let req = test::TestRequest::delete()
+            .uri(&format!("/api/projects/{}", project.id))
+            .insert_header(("Authorization", format!("Bearer {}", dev_token)))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 403);
+
+        let req = test::TestRequest::delete()
+            .uri(&format!("/api/projects/{}", project.id))
+            .insert_header(("Authorization", format!("Bearer {}", pm_token)))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 204);
+
+        let req = test::TestRequest::get()
+            .uri(&format!("/api/projects/{}", project.id))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 404);
+
+        let req = test::TestRequest::get()
+            .uri("/api/projects/trash")
+            .insert_header(("Authorization", format!("Bearer {}", dev_token)))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 403);
+
+        let req = test::TestRequest::get()
+            .uri("/api/projects/trash")
+            .insert_header(("Authorization", format!("Bearer {}", pm_token)))
+            .to_request();
+        let body: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(body["total"], 1);
+        assert_eq!(body["items"][0]["id"], project.id.to_string());
+
+        let req = test::TestRequest::post()
+            .uri(&format!("/api/projects/{}/restore", project.id))
+            .insert_header(("Authorization", format!("Bearer {}", pm_token)))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 200);
+        assert_eq!(resp.headers().get("ETag").unwrap(), "\"3\"");
+
+        let req = test::TestRequest::post()
+            .uri(&format!("/api/projects/{}/restore", project.id))
+            .insert_header(("Authorization", format!("Bearer {}", pm_token)))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 404);
+
+        cleanup_test_db(&pool).await;
+    }
+}
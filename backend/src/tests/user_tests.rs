@@ -1,8 +1,22 @@
 #[cfg(test)]
 mod tests {
-    use crate::models::user::{UserCreate, UserRole, UserUpdate};
+    use crate::errors::ServiceError;
+    use crate::models::audit::AuditContext;
+    use crate::models::auth::LoginCredentials;
+    use crate::models::patch::Patch;
+    use crate::models::user::{
+        InvitationAccept, InvitationCreate, ProfileUpdate, UserCreate, UserRole, UserUpdate,
+    };
+    use crate::routes;
+    use crate::services::auth_service::AuthService;
+    use crate::services::email_service::EmailService;
+    use crate::services::job_service::JobService;
     use crate::services::user_service::UserService;
-    use crate::tests::test_helpers::{setup_test_db, cleanup_test_db};
+    use crate::tests::test_helpers::{
+        audit_as, cleanup_test_db, job_context, setup_test_db, test_token_service,
+    };
+    use actix_web::{test, web, App};
+    use serde_json::json;
     use serial_test::serial;
     use validator::Validate;
 
@@ -19,44 +33,349 @@ mod tests {
             role: UserRole::Developer,
         };
 
-        let created_user = UserService::create(new_user, &pool).await.unwrap();
+        let created_user = UserService::create(new_user, &AuditContext::default(), &pool)
+            .await
+            .unwrap();
         assert_eq!(created_user.email, "test@example.com");
         assert_eq!(created_user.full_name, "Test User");
 
         // Test Read
-        let found_user = UserService::get_by_id(created_user.id, &pool).await.unwrap();
+        let found_user = UserService::get_by_id(created_user.id, &pool)
+            .await
+            .unwrap();
         assert_eq!(found_user.id, created_user.id);
 
         // Test Update
         let update = UserUpdate {
-            email: Some("updated@example.com".to_string()),
-            password: None,
-            full_name: Some("Updated User".to_string()),
-            role: Some(UserRole::ProjectManager),
+            email: Patch::Value("updated@example.com".to_string()),
+            password: Patch::Absent,
+            full_name: Patch::Value("Updated User".to_string()),
+            role: Patch::Value(UserRole::ProjectManager),
         };
 
-        let updated_user = UserService::update(created_user.id, update, &pool)
-            .await
-            .unwrap();
+        let updated_user = UserService::update(
+            created_user.id,
+            update,
+            None,
+            &AuditContext::default(),
+            &pool,
+        )
+        .await
+        .unwrap();
         assert_eq!(updated_user.email, "updated@example.com");
         assert_eq!(updated_user.full_name, "Updated User");
 
-        // Test Delete
-        let delete_result = UserService::delete(created_user.id, &pool).await;
-        assert!(delete_result.is_ok());
+        // Test Deactivate
+        let deactivated =
+            UserService::deactivate(created_user.id, None, &AuditContext::default(), &pool)
+                .await
+                .unwrap();
+        assert!(deactivated.deactivated_at.is_some());
+
+        // Deactivated users are kept
+        let found_user = UserService::get_by_id(created_user.id, &pool)
+            .await
+            .unwrap();
+        assert!(found_user.deactivated_at.is_some());
 
-        // Verify deletion
-        let find_result = UserService::get_by_id(created_user.id, &pool).await;
-        assert!(find_result.is_err());
+        let reactivated =
+            UserService::reactivate(created_user.id, None, &AuditContext::default(), &pool)
+                .await
+                .unwrap();
+        assert!(reactivated.deactivated_at.is_none());
 
         cleanup_test_db(&pool).await;
     }
 
     #[actix_rt::test]
     #[serial]
-    async fn test_user_validation() {
+    async fn test_invitation_flow() {
+        let pool = setup_test_db().await;
+        let admin = UserService::create(
+            UserCreate {
+                email: "admin@example.com".to_string(),
+                password: "password123".to_string(),
+                full_name: "Admin".to_string(),
+                role: UserRole::Admin,
+            },
+            &AuditContext::default(),
+            &pool,
+        )
+        .await
+        .unwrap();
+
+        let invitation = InvitationCreate {
+            email: "invitee@example.com".to_string(),
+            full_name: "Invited User".to_string(),
+            role: UserRole::ProjectManager,
+        };
+        let created = UserService::invite(invitation, &audit_as(admin.id), &pool)
+            .await
+            .unwrap();
+        assert_eq!(created.email, "invitee@example.com");
+
+        // A job sends the email, and a failed send is retried
+        let unreachable = job_context(&pool, EmailService::unreachable());
+        assert_eq!(JobService::run_pending(&unreachable, "a").await.unwrap(), 1);
+        sqlx::query!("UPDATE jobs SET run_at = NOW() WHERE status = 'pending'")
+            .execute(&pool)
+            .await
+            .unwrap();
+        let sender = job_context(&pool, EmailService::in_memory());
+        assert_eq!(JobService::run_pending(&sender, "a").await.unwrap(), 1);
+        assert_eq!(sender.email.sent().len(), 1);
+
+        // A second pending invitation for the same address is rejected
+        let duplicate = InvitationCreate {
+            email: "invitee@example.com".to_string(),
+            full_name: "Invited User".to_string(),
+            role: UserRole::Developer,
+        };
+        assert!(UserService::invite(duplicate, &audit_as(admin.id), &pool)
+            .await
+            .is_err());
+
+        // Once it has expired, the address can be invited again
+        sqlx::query!(
+            "UPDATE user_invitations SET expires_at = NOW() - INTERVAL '1 day' WHERE id = $1",
+            created.id
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+        let reinvited = InvitationCreate {
+            email: "invitee@example.com".to_string(),
+            full_name: "Invited User".to_string(),
+            role: UserRole::ProjectManager,
+        };
+        let renewed = UserService::invite(reinvited, &audit_as(admin.id), &pool)
+            .await
+            .unwrap();
+        assert_eq!(JobService::run_pending(&sender, "a").await.unwrap(), 1);
+        let pending = UserService::list_invitations(&pool).await.unwrap();
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].id, renewed.id);
+
+        let sent = sender.email.sent();
+        assert_eq!(sent.len(), 2);
+        assert_eq!(sent[1].to, "invitee@example.com");
+        let token = sent[1]
+            .body
+            .split("token=")
+            .nth(1)
+            .unwrap()
+            .trim()
+            .to_string();
+
+        let user = UserService::accept_invitation(
+            InvitationAccept {
+                token: token.clone(),
+                password: "newpassword".to_string(),
+            },
+            &AuditContext::default(),
+            &pool,
+        )
+        .await
+        .unwrap();
+        assert_eq!(user.role, UserRole::ProjectManager);
+
+        // The link is single-use
+        let reused = UserService::accept_invitation(
+            InvitationAccept {
+                token,
+                password: "newpassword".to_string(),
+            },
+            &AuditContext::default(),
+            &pool,
+        )
+        .await;
+        assert!(reused.is_err());
+
+        let login = AuthService::login(
+            LoginCredentials {
+                email: "invitee@example.com".to_string(),
+                password: "newpassword".to_string(),
+            },
+            &test_token_service(),
+            &pool,
+        )
+        .await;
+        assert!(login.is_ok());
+        assert!(UserService::list_invitations(&pool)
+            .await
+            .unwrap()
+            .is_empty());
+
+        cleanup_test_db(&pool).await;
+    }
+
+    #[actix_rt::test]
+    #[serial]
+    async fn test_deactivated_user_cannot_log_in() {
+        let pool = setup_test_db().await;
+        let user = UserService::create(
+            UserCreate {
+                email: "leaver@example.com".to_string(),
+                password: "password123".to_string(),
+                full_name: "Leaver".to_string(),
+                role: UserRole::Developer,
+            },
+            &AuditContext::default(),
+            &pool,
+        )
+        .await
+        .unwrap();
+        UserService::deactivate(user.id, None, &AuditContext::default(), &pool)
+            .await
+            .unwrap();
+
+        let login = AuthService::login(
+            LoginCredentials {
+                email: "leaver@example.com".to_string(),
+                password: "password123".to_string(),
+            },
+            &test_token_service(),
+            &pool,
+        )
+        .await;
+        assert!(matches!(login, Err(ServiceError::Unauthorized(_))));
+
+        cleanup_test_db(&pool).await;
+    }
+
+    #[actix_rt::test]
+    #[serial]
+    async fn test_profile_password_change_requires_current_password() {
+        let pool = setup_test_db().await;
+        let user = UserService::create(
+            UserCreate {
+                email: "self@example.com".to_string(),
+                password: "password123".to_string(),
+                full_name: "Self Service".to_string(),
+                role: UserRole::Developer,
+            },
+            &AuditContext::default(),
+            &pool,
+        )
+        .await
+        .unwrap();
+
+        let missing = ProfileUpdate {
+            full_name: None,
+            current_password: None,
+            new_password: Some("changed123".to_string()),
+        };
+        assert!(UserService::update_profile(
+            user.id,
+            missing,
+            None,
+            &AuditContext::default(),
+            &pool
+        )
+        .await
+        .is_err());
+
+        let wrong = ProfileUpdate {
+            full_name: None,
+            current_password: Some("wrongpassword".to_string()),
+            new_password: Some("changed123".to_string()),
+        };
+        assert!(matches!(
+            UserService::update_profile(user.id, wrong, None, &AuditContext::default(), &pool)
+                .await,
+            Err(ServiceError::InvalidCredentials)
+        ));
+
+        let valid = ProfileUpdate {
+            full_name: Some("Renamed".to_string()),
+            current_password: Some("password123".to_string()),
+            new_password: Some("changed123".to_string()),
+        };
+        let updated =
+            UserService::update_profile(user.id, valid, None, &AuditContext::default(), &pool)
+                .await
+                .unwrap();
+        assert_eq!(updated.full_name, "Renamed");
+
+        // The serialized user never includes the password hash
+        let body = serde_json::to_value(&updated).unwrap();
+        assert!(body.get("password_hash").is_none());
+
+        cleanup_test_db(&pool).await;
+    }
+
+    #[actix_rt::test]
+    #[serial]
+    async fn test_only_admins_manage_users() {
         let pool = setup_test_db().await;
+        let tokens = test_token_service();
+        let developer = UserService::create(
+            UserCreate {
+                email: "dev@example.com".to_string(),
+                password: "password123".to_string(),
+                full_name: "Developer".to_string(),
+                role: UserRole::Developer,
+            },
+            &AuditContext::default(),
+            &pool,
+        )
+        .await
+        .unwrap();
+        let token = tokens.issue(&developer).unwrap().token;
+
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(pool.clone()))
+                .app_data(web::Data::new(tokens))
+                .configure(routes::config),
+        )
+        .await;
+
+        let req = test::TestRequest::post()
+            .uri("/api/users")
+            .insert_header(("Authorization", format!("Bearer {}", token)))
+            .set_json(json!({
+                "email": "new-admin@example.com",
+                "password": "password123",
+                "full_name": "New Admin",
+                "role": "Admin"
+            }))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 403);
 
+        // Unauthenticated requests are rejected outright
+        let req = test::TestRequest::get().uri("/api/users").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 401);
+
+        // Self-registration cannot grant a privileged role
+        let req = test::TestRequest::post()
+            .uri("/api/auth/register")
+            .set_json(json!({
+                "email": "sneaky@example.com",
+                "password": "password123",
+                "full_name": "Sneaky",
+                "role": "Admin"
+            }))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 403);
+
+        let req = test::TestRequest::get()
+            .uri("/api/me")
+            .insert_header(("Authorization", format!("Bearer {}", token)))
+            .to_request();
+        let me: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(me["email"], "dev@example.com");
+        assert!(me.get("password_hash").is_none());
+
+        cleanup_test_db(&pool).await;
+    }
+
+    #[actix_rt::test]
+    #[serial]
+    async fn test_user_validation() {
         // Test invalid email
         let invalid_user = UserCreate {
             email: "invalid_email".to_string(),
@@ -66,7 +385,10 @@ mod tests {
         };
 
         let validation_result = invalid_user.validate();
-        assert!(validation_result.is_err(), "Expected validation error for invalid email");
+        assert!(
+            validation_result.is_err(),
+            "Expected validation error for invalid email"
+        );
 
         // Test password too short
         let invalid_user = UserCreate {
@@ -77,6 +399,9 @@ mod tests {
         };
 
         let validation_result = invalid_user.validate();
-        assert!(validation_result.is_err(), "Expected validation error for short password");
+        assert!(
+            validation_result.is_err(),
+            "Expected validation error for short password"
+        );
     }
 }
@@ -0,0 +1,427 @@
+#[cfg(test)]
+mod tests {
+    use crate::errors::ServiceError;
+    use crate::models::lifecycle::{LifecyclePhase, PhaseTransition};
+    use crate::models::pagination::{Page, PageParams};
+    use crate::models::patch::Patch;
+    use crate::models::task::TaskUpdate;
+    use crate::models::user::UserRole;
+    use crate::models::webhook::{
+        DeliveryFilter, DeliveryStatus, WebhookCreate, WebhookCreated, WebhookDelivery,
+        WebhookEvent, WebhookUpdate,
+    };
+    use crate::routes;
+    use crate::services::lifecycle_service::LifecycleService;
+    use crate::services::notification_service::NotificationService;
+    use crate::services::task_service::TaskService;
+    use crate::services::webhook_service::WebhookService;
+    use crate::tests::test_helpers::{
+        audit_as, cleanup_test_db, create_project, create_task, create_user, new_task,
+        setup_test_db, test_token_service,
+    };
+    use actix_web::http::StatusCode;
+    use actix_web::{test, web, App, HttpRequest, HttpResponse, HttpServer};
+    use chrono::{Duration, TimeZone, Utc};
+    use serde_json::{json, Value};
+    use serial_test::serial;
+    use sqlx::PgPool;
+    use std::sync::atomic::{AtomicU16, Ordering};
+    use std::sync::Mutex;
+    use uuid::Uuid;
+
+    /// A request as the receiver saw it.
+    struct Received {
+        event: String,
+        delivery: String,
+        timestamp: i64,
+        signature: String,
+        body: Vec<u8>,
+    }
+
+    struct Receiver {
+        url: String,
+        /// Status code the receiver answers with
+        status: AtomicU16,
+        requests: Mutex<Vec<Received>>,
+    }
+
+    async fn receive(
+        req: HttpRequest,
+        body: web::Bytes,
+        receiver: web::Data<Receiver>,
+    ) -> HttpResponse {
+        let header = |name: &str| {
+            req.headers()
+                .get(name)
+                .map(|value| value.to_str().unwrap().to_string())
+                .unwrap_or_default()
+        };
+        receiver.requests.lock().unwrap().push(Received {
+            event: header("X-Webhook-Event"),
+            delivery: header("X-Webhook-Delivery"),
+            timestamp: header("X-Webhook-Timestamp").parse().unwrap(),
+            signature: header("X-Webhook-Signature"),
+            body: body.to_vec(),
+        });
+        let status = StatusCode::from_u16(receiver.status.load(Ordering::SeqCst)).unwrap();
+        HttpResponse::build(status).body("thanks")
+    }
+
+    async fn start_receiver() -> web::Data<Receiver> {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let receiver = web::Data::new(Receiver {
+            url: format!("http://{}/hooks", listener.local_addr().unwrap()),
+            status: AtomicU16::new(200),
+            requests: Mutex::new(Vec::new()),
+        });
+
+        let app_receiver = receiver.clone();
+        let server = HttpServer::new(move || {
+            App::new()
+                .app_data(app_receiver.clone())
+                .route("/hooks", web::post().to(receive))
+        })
+        .workers(1)
+        .listen(listener)
+        .unwrap()
+        .run();
+        actix_rt::spawn(server);
+
+        receiver
+    }
+
+    async fn deliveries(webhook_id: Uuid, pool: &PgPool) -> Vec<WebhookDelivery> {
+        WebhookService::list_deliveries(
+            webhook_id,
+            &PageParams::default(),
+            &DeliveryFilter::default(),
+            pool,
+        )
+        .await
+        .unwrap()
+        .items
+    }
+
+    #[actix_rt::test]
+    #[serial]
+    async fn test_subscribed_events_are_signed_and_delivered() {
+        let pool = setup_test_db().await;
+        let receiver = start_receiver().await;
+        let admin = create_user("admin@example.com", UserRole::Admin, &pool).await;
+        let audit = audit_as(admin.id);
+        let created = WebhookService::create(
+            WebhookCreate {
+                url: receiver.url.clone(),
+                secret: None,
+                events: vec![
+                    WebhookEvent::ProjectPhaseChanged,
+                    WebhookEvent::TaskUpdated,
+                    WebhookEvent::TaskOverdue,
+                ],
+                description: Some("ERP".to_string()),
+                active: true,
+            },
+            &audit,
+            &pool,
+        )
+        .await
+        .unwrap();
+        let webhook_id = created.webhook.id;
+
+        // Creations are not subscribed to
+        let project = create_project("Billing migration", &audit, &pool).await;
+        let task = create_task(new_task("Map ledgers", project.id), &audit, &pool).await;
+        assert!(deliveries(webhook_id, &pool).await.is_empty());
+
+        let rename = |name: &str| TaskUpdate {
+            name: Patch::Value(name.to_string()),
+            ..Default::default()
+        };
+        TaskService::update(task.id, rename("Map GL accounts"), None, &audit, &pool)
+            .await
+            .unwrap();
+        // A change that rolls back sends nothing
+        let stale = TaskService::update(task.id, rename("Stale"), Some(1), &audit, &pool).await;
+        assert!(matches!(
+            stale,
+            Err(ServiceError::PreconditionFailed { .. })
+        ));
+        LifecycleService::transition_phase(
+            PhaseTransition {
+                project_id: project.id,
+                phase: LifecyclePhase::Requirements,
+                description: "Kick-off done".to_string(),
+                attachments: None,
+                documents: vec![],
+            },
+            None,
+            &audit,
+            &pool,
+        )
+        .await
+        .unwrap();
+        // The task ended in 2025; repeated checks send it once
+        for _ in 0..2 {
+            NotificationService::raise_overdue(Utc::now(), &pool)
+                .await
+                .unwrap();
+        }
+
+        let client = WebhookService::http_client();
+        assert_eq!(
+            WebhookService::deliver_due(&client, Utc::now(), &pool)
+                .await
+                .unwrap(),
+            3
+        );
+        let requests = std::mem::take(&mut *receiver.requests.lock().unwrap());
+        let events: Vec<&str> = requests.iter().map(|r| r.event.as_str()).collect();
+        assert_eq!(
+            events,
+            vec!["task.updated", "project.phase_changed", "task.overdue"]
+        );
+        for request in requests.iter() {
+            let expected = WebhookService::sign(&created.secret, request.timestamp, &request.body);
+            assert_eq!(request.signature, format!("sha256={}", expected));
+        }
+        let body: Value = serde_json::from_slice(&requests[0].body).unwrap();
+        assert_eq!(body["event"], "task.updated");
+        assert_eq!(body["data"]["name"], "Map GL accounts");
+        let body: Value = serde_json::from_slice(&requests[1].body).unwrap();
+        assert_eq!(body["data"]["previous_phase"], "Proposal");
+        assert_eq!(body["data"]["phase"], "Requirements");
+        let body: Value = serde_json::from_slice(&requests[2].body).unwrap();
+        assert_eq!(body["data"]["task_id"], json!(task.id));
+
+        let logged = deliveries(webhook_id, &pool).await;
+        assert!(logged
+            .iter()
+            .all(|d| d.status == DeliveryStatus::Succeeded && d.response_status == Some(200)));
+        assert!(logged
+            .iter()
+            .any(|d| d.id.to_string() == requests[0].delivery));
+        assert_eq!(logged[0].response_body.as_deref(), Some("thanks"));
+
+        cleanup_test_db(&pool).await;
+    }
+
+    #[actix_rt::test]
+    #[serial]
+    async fn test_failed_deliveries_back_off_until_they_give_up() {
+        let pool = setup_test_db().await;
+        let receiver = start_receiver().await;
+        receiver.status.store(503, Ordering::SeqCst);
+        let admin = create_user("admin@example.com", UserRole::Admin, &pool).await;
+        let audit = audit_as(admin.id);
+        let webhook = WebhookService::create(
+            WebhookCreate {
+                url: receiver.url.clone(),
+                secret: Some("0123456789abcdef".to_string()),
+                events: vec![WebhookEvent::ProjectCreated],
+                description: None,
+                active: true,
+            },
+            &audit,
+            &pool,
+        )
+        .await
+        .unwrap()
+        .webhook;
+        create_project("Billing migration", &audit, &pool).await;
+
+        let client = WebhookService::http_client();
+        let mut now = Utc.with_ymd_and_hms(2030, 1, 1, 9, 0, 0).unwrap();
+        assert_eq!(
+            WebhookService::deliver_due(&client, now, &pool)
+                .await
+                .unwrap(),
+            1
+        );
+        let delivery = deliveries(webhook.id, &pool).await.remove(0);
+        assert_eq!(delivery.status, DeliveryStatus::Pending);
+        assert_eq!(delivery.attempts, 1);
+        assert_eq!(delivery.response_status, Some(503));
+        assert_eq!(delivery.next_attempt_at, now + Duration::seconds(30));
+        assert_eq!(
+            WebhookService::deliver_due(&client, now, &pool)
+                .await
+                .unwrap(),
+            0
+        );
+
+        for _ in 2..=8 {
+            now = deliveries(webhook.id, &pool).await[0].next_attempt_at;
+            WebhookService::deliver_due(&client, now, &pool)
+                .await
+                .unwrap();
+        }
+        let delivery = deliveries(webhook.id, &pool).await.remove(0);
+        assert_eq!(delivery.status, DeliveryStatus::Failed);
+        assert_eq!(delivery.attempts, 8);
+        assert_eq!(receiver.requests.lock().unwrap().len(), 8);
+
+        // Paused webhooks get no new deliveries
+        WebhookService::update(
+            webhook.id,
+            WebhookUpdate {
+                active: Patch::Value(false),
+                ..Default::default()
+            },
+            &audit,
+            &pool,
+        )
+        .await
+        .unwrap();
+        create_project("Billing migration", &audit, &pool).await;
+        assert_eq!(deliveries(webhook.id, &pool).await.len(), 1);
+
+        cleanup_test_db(&pool).await;
+    }
+
+    #[actix_rt::test]
+    #[serial]
+    async fn test_webhook_endpoints() {
+        let pool = setup_test_db().await;
+        let receiver = start_receiver().await;
+        let tokens = test_token_service();
+        let admin = create_user("admin@example.com", UserRole::Admin, &pool).await;
+        let manager = create_user("pm@example.com", UserRole::ProjectManager, &pool).await;
+        let admin_bearer = format!("Bearer {}", tokens.issue(&admin).unwrap().token);
+        let pm_bearer = format!("Bearer {}", tokens.issue(&manager).unwrap().token);
+
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(pool.clone()))
+                .app_data(web::Data::new(tokens))
+                .configure(routes::config),
+        )
+        .await;
+        let request = |req: test::TestRequest, bearer: &str| {
+            req.insert_header(("Authorization", bearer.to_string()))
+        };
+
+        let body = json!({ "url": receiver.url, "events": ["task.created"] });
+        let resp = test::call_service(
+            &app,
+            request(test::TestRequest::post().uri("/api/webhooks"), &pm_bearer)
+                .set_json(&body)
+                .to_request(),
+        )
+        .await;
+        assert_eq!(resp.status(), 403);
+        for invalid in [
+            json!({ "url": "ftp://example.com", "events": ["task.created"] }),
+            json!({ "url": receiver.url, "events": [] }),
+            json!({ "url": receiver.url, "events": ["task.created"], "secret": "short" }),
+        ] {
+            let resp = test::call_service(
+                &app,
+                request(
+                    test::TestRequest::post().uri("/api/webhooks"),
+                    &admin_bearer,
+                )
+                .set_json(&invalid)
+                .to_request(),
+            )
+            .await;
+            assert_eq!(resp.status(), 422);
+        }
+        let resp = test::call_service(
+            &app,
+            request(
+                test::TestRequest::post().uri("/api/webhooks"),
+                &admin_bearer,
+            )
+            .set_json(&body)
+            .to_request(),
+        )
+        .await;
+        assert_eq!(resp.status(), 201);
+        let created: WebhookCreated = test::read_body_json(resp).await;
+        assert_eq!(created.secret.len(), 43);
+        let listed: Value = test::call_and_read_body_json(
+            &app,
+            request(test::TestRequest::get().uri("/api/webhooks"), &admin_bearer).to_request(),
+        )
+        .await;
+        assert_eq!(listed[0]["events"], json!(["task.created"]));
+        assert!(listed[0].get("secret").is_none());
+
+        let uri = format!("/api/webhooks/{}", created.webhook.id);
+        let resp = test::call_service(
+            &app,
+            request(test::TestRequest::patch().uri(&uri), &admin_bearer)
+                .set_json(json!({ "secret": null }))
+                .to_request(),
+        )
+        .await;
+        assert_eq!(resp.status(), 422);
+
+        let project = create_project("Billing migration", &audit_as(manager.id), &pool).await;
+        create_task(
+            new_task("Map ledgers", project.id),
+            &audit_as(manager.id),
+            &pool,
+        )
+        .await;
+        WebhookService::deliver_due(&WebhookService::http_client(), Utc::now(), &pool)
+            .await
+            .unwrap();
+        let page: Page<WebhookDelivery> = test::call_and_read_body_json(
+            &app,
+            request(
+                test::TestRequest::get().uri(&format!("{}/deliveries?status=succeeded", uri)),
+                &admin_bearer,
+            )
+            .to_request(),
+        )
+        .await;
+        assert_eq!(page.total, 1);
+        let original = &page.items[0];
+
+        let resp = test::call_service(
+            &app,
+            request(
+                test::TestRequest::post()
+                    .uri(&format!("{}/deliveries/{}/redeliver", uri, original.id)),
+                &admin_bearer,
+            )
+            .to_request(),
+        )
+        .await;
+        assert_eq!(resp.status(), 202);
+        let repeat: WebhookDelivery = test::read_body_json(resp).await;
+        assert_eq!(repeat.status, DeliveryStatus::Pending);
+        assert_eq!(repeat.redelivery_of, Some(original.id));
+        assert_eq!(repeat.payload, original.payload);
+        let resp = test::call_service(
+            &app,
+            request(
+                test::TestRequest::post().uri(&format!(
+                    "/api/webhooks/{}/deliveries/{}/redeliver",
+                    Uuid::new_v4(),
+                    original.id
+                )),
+                &admin_bearer,
+            )
+            .to_request(),
+        )
+        .await;
+        assert_eq!(resp.status(), 404);
+
+        let resp = test::call_service(
+            &app,
+            request(test::TestRequest::delete().uri(&uri), &admin_bearer).to_request(),
+        )
+        .await;
+        assert_eq!(resp.status(), 204);
+        let resp = test::call_service(
+            &app,
+            request(test::TestRequest::get().uri(&uri), &admin_bearer).to_request(),
+        )
+        .await;
+        assert_eq!(resp.status(), 404);
+
+        cleanup_test_db(&pool).await;
+    }
+}